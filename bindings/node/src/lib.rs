@@ -0,0 +1,197 @@
+//! Node.js bindings, so Electron-based recovery tools can run the engine
+//! in-process instead of shelling out to the command line.
+//!
+//! ```js
+//! const { validateFile } = require('mnemonic-validator');
+//! const result = await validateFile('dump.txt', { output: 'valid.txt' }, (p) => {
+//!   console.log(`${p.lines}/${p.total} lines, ${p.valid} valid`);
+//! });
+//! ```
+//!
+//! The file is validated on a worker thread (plus the engine's own
+//! validator threads), so the event loop stays free; progress callbacks
+//! are queued onto it. The `engine` option takes the engine's settings
+//! with the keys of the command line's options, as a `--job` file does.
+
+use mnemonic_validator::{
+    batch::{self, Outcome},
+    cancel::CancelToken,
+    config::EngineConfig,
+    engine::FileRun,
+    events::{self, Events},
+    output::HitWriter,
+    pipeline::Hit,
+    prefilter, typography,
+};
+use napi::{
+    bindgen_prelude::*,
+    threadsafe_function::{ErrorStrategy, ThreadSafeCallContext, ThreadsafeFunction, ThreadsafeFunctionCallMode},
+    JsFunction,
+};
+use napi_derive::napi;
+use std::{
+    path::PathBuf,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+#[napi(object)]
+pub struct ValidateOptions {
+    /// Append valid phrases to this file instead of returning them.
+    pub output: Option<String>,
+    /// The engine's settings, e.g. `{ threads: 4, 'max-memory': '2G' }`.
+    pub engine: Option<serde_json::Value>,
+    /// Milliseconds between progress events (default: 1000).
+    pub progress_interval_ms: Option<u32>,
+}
+
+#[napi(object)]
+pub struct Progress {
+    pub lines: i64,
+    pub total: i64,
+    pub valid: i64,
+}
+
+#[napi(object)]
+pub struct ValidHit {
+    /// 1-based line number in the file.
+    pub line: i64,
+    pub phrase: String,
+}
+
+#[napi(object)]
+pub struct ValidateResult {
+    pub lines: i64,
+    pub valid: i64,
+    pub elapsed_ms: f64,
+    /// The valid phrases, when no `output` file was given.
+    pub hits: Option<Vec<ValidHit>>,
+}
+
+pub struct ValidateTask {
+    path: PathBuf,
+    output: Option<PathBuf>,
+    engine: EngineConfig,
+    progress_interval: Duration,
+    on_progress: Option<ThreadsafeFunction<Progress, ErrorStrategy::Fatal>>,
+}
+
+impl Task for ValidateTask {
+    type Output = ValidateResult;
+    type JsValue = ValidateResult;
+
+    fn compute(&mut self) -> Result<ValidateResult> {
+        let io_error = |e: std::io::Error| Error::from_reason(e.to_string());
+        let mut writer = match &self.output {
+            Some(path) => Some(HitWriter::new(path, None, None).map_err(io_error)?),
+            None => None,
+        };
+        let mut hits = Vec::new();
+
+        let start = Instant::now();
+        let mut valid = 0;
+        let validate = |line: &str| {
+            let cleaned = typography::clean(line);
+            let line = cleaned.as_deref().map_or(line, String::as_str);
+            prefilter::parse(line)
+        };
+        let write = |hit: Hit| {
+            valid += 1;
+            // Lines come normalized, as they were validated
+            let cleaned = typography::clean(&hit.text);
+            let phrase = cleaned.as_deref().map_or(hit.text.as_str(), String::as_str);
+            match writer.as_mut() {
+                Some(writer) => writer.write_hit(&hit.mnemonic, phrase).map(|_| ()),
+                None => {
+                    hits.push(ValidHit {
+                        line: hit.line as i64 + 1,
+                        phrase: phrase.to_string(),
+                    });
+                    Ok(())
+                }
+            }
+        };
+
+        let events = self.on_progress.clone().map(|on_progress| {
+            let mut events = Events::new().on_progress(move |progress: &events::Progress| {
+                let progress = Progress {
+                    lines: progress.processed as i64,
+                    total: progress.total.unwrap_or(0) as i64,
+                    valid: progress.valid as i64,
+                };
+                on_progress.call(progress, ThreadsafeFunctionCallMode::NonBlocking);
+            });
+            events.progress_interval = self.progress_interval;
+            Arc::new(events)
+        });
+        let run = FileRun {
+            config: &self.engine,
+            input: &self.path,
+            output: self.output.as_deref(),
+            validation: "english",
+            events,
+            throttle: None,
+        };
+        let total_lines = run.run(&CancelToken::new(), validate, write).map_err(Error::from_reason)?;
+        if let Some(writer) = writer.as_mut() {
+            writer.flush().map_err(io_error)?;
+        }
+
+        Ok(ValidateResult {
+            lines: total_lines as i64,
+            valid: valid as i64,
+            elapsed_ms: start.elapsed().as_secs_f64() * 1000.0,
+            hits: self.output.is_none().then_some(hits),
+        })
+    }
+
+    fn resolve(&mut self, _env: Env, output: ValidateResult) -> Result<ValidateResult> {
+        Ok(output)
+    }
+}
+
+/// Validates every line of the file at `path`; resolves once it's done.
+#[napi(ts_args_type = "path: string, options?: ValidateOptions, onProgress?: (progress: Progress) => void")]
+pub fn validate_file(
+    path: String,
+    options: Option<ValidateOptions>,
+    on_progress: Option<JsFunction>,
+) -> Result<AsyncTask<ValidateTask>> {
+    let options = options.unwrap_or(ValidateOptions {
+        output: None,
+        engine: None,
+        progress_interval_ms: None,
+    });
+    let engine = match options.engine {
+        Some(engine) => serde_json::from_value(engine)
+            .map_err(|e| Error::new(Status::InvalidArg, format!("engine settings aren't valid: {}", e)))?,
+        None => EngineConfig::default(),
+    };
+    let on_progress = on_progress
+        .map(|callback| {
+            callback.create_threadsafe_function(0, |ctx: ThreadSafeCallContext<Progress>| Ok(vec![ctx.value]))
+        })
+        .transpose()?;
+    Ok(AsyncTask::new(ValidateTask {
+        path: path.into(),
+        output: options.output.map(PathBuf::from),
+        engine,
+        progress_interval: options
+            .progress_interval_ms
+            .map_or(events::PROGRESS_INTERVAL, |ms| Duration::from_millis(ms.into())),
+        on_progress,
+    }))
+}
+
+/// Checks a single phrase.
+#[napi]
+pub fn validate_phrase(phrase: String) -> bool {
+    batch::validate(&phrase).is_valid()
+}
+
+/// Checks many phrases in one call, on all CPUs for large batches; far
+/// faster than calling `validatePhrase` for each.
+#[napi]
+pub fn validate_phrases(phrases: Vec<String>) -> Vec<bool> {
+    batch::validate_batch(&phrases).into_iter().map(Outcome::is_valid).collect()
+}
@@ -0,0 +1,39 @@
+// Generates compile-time perfect-hash lookups over the BIP39 wordlists.
+use std::{
+    collections::BTreeMap,
+    env,
+    fs::File,
+    io::{BufWriter, Write},
+    path::Path,
+};
+
+fn main() {
+    let out_path = Path::new(&env::var("OUT_DIR").unwrap()).join("wordlists.rs");
+    let mut out = BufWriter::new(File::create(out_path).unwrap());
+
+    // A word can appear in several lists (e.g. English and French share
+    // some, the two Chinese lists share most), so each maps to all of them.
+    let mut entries: BTreeMap<&str, Vec<String>> = BTreeMap::new();
+    for &language in bip39::Language::ALL {
+        for (index, word) in language.word_list().iter().enumerate() {
+            entries
+                .entry(*word)
+                .or_default()
+                .push(format!("(Language::{:?}, {})", language, index));
+        }
+    }
+
+    let mut words = phf_codegen::Map::new();
+    for (word, hits) in &entries {
+        words.entry(*word, &format!("&[{}]", hits.join(", ")));
+    }
+    writeln!(
+        out,
+        "/// Every word of every BIP39 wordlist with its language(s) and index.\n\
+         static WORDS: phf::Map<&'static str, &'static [(Language, u16)]> = {};",
+        words.build()
+    )
+    .unwrap();
+
+    println!("cargo:rerun-if-changed=build.rs");
+}
@@ -0,0 +1,46 @@
+#ifndef MNEMONIC_VALIDATOR_H
+#define MNEMONIC_VALIDATOR_H
+
+/* Generated by cbindgen from src/ffi.rs; don't edit by hand. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+// Result of checking one phrase.
+typedef enum MvStatus {
+  MV_STATUS_VALID = 0,
+  MV_STATUS_INVALID = 1,
+  MV_STATUS_NULL_POINTER = 2,
+  MV_STATUS_INVALID_UTF8 = 3,
+} MvStatus;
+
+// Checks one phrase.
+//
+// # Safety
+//
+// `phrase` must be null or point to a NUL-terminated string that stays
+// valid for the duration of the call.
+enum MvStatus mv_validate(const char *phrase);
+
+// Checks `count` phrases, storing each one's status in `results`, and
+// returns how many are valid. Returns 0 without touching `results` if
+// either array is null. Large batches are checked on all CPUs, so this is
+// much faster than calling [`mv_validate`] for each phrase.
+//
+// # Safety
+//
+// `phrases` and `results` must point to arrays of at least `count`
+// elements; every element of `phrases` must be null or a NUL-terminated
+// string.
+size_t mv_validate_batch(const char *const *phrases, size_t count, enum MvStatus *results);
+
+// A static, NUL-terminated description of `status`; never free it.
+const char *mv_status_message(enum MvStatus status);
+
+// The library version as a static, NUL-terminated string.
+const char *mv_version(void);
+
+#endif  /* MNEMONIC_VALIDATOR_H */
@@ -0,0 +1,328 @@
+//! Bitcoin addresses to look for, and the keys that pay to them.
+//!
+//! Target addresses are decoded to the 20-byte hash they commit to, so a
+//! derived key is matched by hashing it rather than by encoding an address
+//! for it; [`encode`] is for when the address text itself is wanted. Addresses of single-key wallets are understood: legacy (`1…`,
+//! BIP44), nested SegWit (`3…`, BIP49) and native SegWit (`bc1q…`, BIP84),
+//! and their testnet forms (`m…`/`n…`, `2…`, `tb1q…`/`bcrt1q…`), or those
+//! of another coin of the registry (see [`network`](crate::network)).
+//! Taproot (`bc1p…`) isn't. On coins that hash keys with Keccak-256
+//! (Ethereum's `0x…`, Tron's `T…`), a key has one address, of the legacy
+//! kind: its path is BIP44's.
+
+use crate::{bip32, curve, digest, network::Network};
+use sha3::{Digest, Keccak256};
+use std::collections::HashMap;
+
+/// How a single key's address pays to it, named after the BIP of its
+/// derivation path.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Kind {
+    /// P2PKH, `m/44'/0'/0'`, and the address of a Keccak-hashing coin.
+    Legacy,
+    /// P2SH-P2WPKH, `m/49'/0'/0'`.
+    NestedSegwit,
+    /// P2WPKH, `m/84'/0'/0'`.
+    NativeSegwit,
+}
+
+impl Kind {
+    pub const ALL: [Kind; 3] = [Kind::Legacy, Kind::NestedSegwit, Kind::NativeSegwit];
+
+    /// The BIP44 purpose of its derivation path.
+    pub fn purpose(self) -> u32 {
+        match self {
+            Kind::Legacy => 44,
+            Kind::NestedSegwit => 49,
+            Kind::NativeSegwit => 84,
+        }
+    }
+
+    /// Whether `network` has addresses of this kind.
+    pub fn exists_on(self, network: Network) -> bool {
+        match self {
+            Kind::Legacy => true,
+            Kind::NestedSegwit => network.p2sh_version().is_some(),
+            Kind::NativeSegwit => network.hrp().is_some(),
+        }
+    }
+
+    /// The hash an address of this kind holds for a compressed public key.
+    pub fn hash(self, public_key: &[u8; 33]) -> [u8; 20] {
+        let key_hash = digest::hash160(public_key);
+        match self {
+            Kind::Legacy | Kind::NativeSegwit => key_hash,
+            Kind::NestedSegwit => {
+                // The hash of the witness script `0 <key hash>`
+                let mut script = [0; 22];
+                script[..2].copy_from_slice(&[0x00, 0x14]);
+                script[2..].copy_from_slice(&key_hash);
+                digest::hash160(&script)
+            }
+        }
+    }
+}
+
+/// The last 20 bytes of the Keccak-256 of an uncompressed public key,
+/// which Ethereum and Tron addresses hold.
+fn keccak_hash(public_key: &[u8; 33]) -> Option<[u8; 20]> {
+    let uncompressed = curve::uncompressed(public_key)?;
+    Keccak256::digest(&uncompressed[1..])[12..].try_into().ok()
+}
+
+/// The hash `network`'s address of `kind` holds for `public_key`.
+fn hash_on(kind: Kind, public_key: &[u8; 33], network: Network) -> Option<[u8; 20]> {
+    match network.keccak() {
+        true => keccak_hash(public_key),
+        false => Some(kind.hash(public_key)),
+    }
+}
+
+/// `0x` and `hash` in hex, each letter in upper case where the Keccak-256
+/// of the lower-case hex has a nibble of 8 or more (EIP-55).
+fn encode_eip55(hash: &[u8; 20]) -> String {
+    let hex = crate::digest::to_hex(hash);
+    let checksum = Keccak256::digest(hex.as_bytes());
+    let mut address = String::with_capacity(42);
+    address.push_str("0x");
+    address.extend(hex.chars().enumerate().map(|(i, c)| {
+        let nibble = checksum[i / 2] >> (4 * (1 - i % 2)) & 0xf;
+        if nibble >= 8 {
+            c.to_ascii_uppercase()
+        } else {
+            c
+        }
+    }));
+    address
+}
+
+/// The hash of a hex address; its checksum is checked when it's in mixed
+/// case, as all upper or all lower case carries none.
+fn decode_eip55(text: &str) -> Option<[u8; 20]> {
+    let hex = text.strip_prefix("0x")?;
+    if hex.len() != 40 || !hex.bytes().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    let mut hash = [0; 20];
+    for (i, byte) in hash.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&hex[2 * i..2 * i + 2], 16).ok()?;
+    }
+    let mixed = hex.bytes().any(|c| c.is_ascii_uppercase()) && hex.bytes().any(|c| c.is_ascii_lowercase());
+    if mixed && encode_eip55(&hash) != text {
+        return None;
+    }
+    Some(hash)
+}
+
+const BASE58: &[u8; 58] = b"123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
+const BECH32: &[u8; 32] = b"qpzry9x8gf2tvdw0s3jn54khce6mua7l";
+
+/// The version byte and payload of a base58check string.
+fn decode_base58check(text: &str) -> Option<(u8, Vec<u8>)> {
+    let mut data = bip32::decode_base58check(text)?;
+    let version = *data.first()?;
+    data.remove(0);
+    Some((version, data))
+}
+
+/// The human-readable part as the checksum covers it: the high bits of
+/// each character, a zero, then the low bits.
+fn expand_hrp(hrp: &str) -> impl Iterator<Item = u8> + '_ {
+    hrp.bytes().map(|c| c >> 5).chain([0]).chain(hrp.bytes().map(|c| c & 31))
+}
+
+fn bech32_polymod(values: impl Iterator<Item = u8>) -> u32 {
+    const GENERATOR: [u32; 5] = [0x3b6a57b2, 0x26508e6d, 0x1ea119fa, 0x3d4233dd, 0x2a1462b3];
+    let mut checksum = 1u32;
+    for value in values {
+        let top = checksum >> 25;
+        checksum = (checksum & 0x1ffffff) << 5 ^ value as u32;
+        for (i, generator) in GENERATOR.iter().enumerate() {
+            if top >> i & 1 == 1 {
+                checksum ^= generator;
+            }
+        }
+    }
+    checksum
+}
+
+/// The program of a version 0 SegWit address with human-readable part
+/// `hrp` (BIP173).
+fn decode_segwit_v0(text: &str, hrp: &str) -> Option<Vec<u8>> {
+    let text = text.to_ascii_lowercase();
+    let data = text.strip_prefix(hrp)?.strip_prefix('1')?;
+    let values: Vec<u8> = data
+        .bytes()
+        .map(|c| BECH32.iter().position(|&digit| digit == c).map(|value| value as u8))
+        .collect::<Option<_>>()?;
+    if values.len() < 7 || bech32_polymod(expand_hrp(hrp).chain(values.iter().copied())) != 1 {
+        return None;
+    }
+    let (&version, values) = values[..values.len() - 6].split_first()?;
+    if version != 0 {
+        return None;
+    }
+    // Five bits per value, regrouped into bytes
+    let mut program = Vec::with_capacity(values.len() * 5 / 8);
+    let (mut accumulator, mut bits) = (0u32, 0);
+    for &value in values {
+        accumulator = accumulator << 5 | value as u32;
+        bits += 5;
+        if bits >= 8 {
+            bits -= 8;
+            program.push((accumulator >> bits) as u8);
+        }
+    }
+    Some(program)
+}
+
+/// A version 0 SegWit address for `program` (BIP173).
+fn encode_segwit_v0(program: &[u8], hrp: &str) -> String {
+    encode_bech32(hrp, vec![0], program)
+}
+
+/// The bech32 string of `values`, then `data` with eight bits per byte
+/// regrouped into five, under `hrp`; with no limit on its length, as
+/// Cardano's addresses need.
+pub(crate) fn encode_bech32(hrp: &str, mut values: Vec<u8>, data: &[u8]) -> String {
+    let (mut accumulator, mut bits) = (0u32, 0);
+    for &byte in data {
+        accumulator = accumulator << 8 | byte as u32;
+        bits += 8;
+        while bits >= 5 {
+            bits -= 5;
+            values.push((accumulator >> bits & 31) as u8);
+        }
+    }
+    if bits > 0 {
+        values.push((accumulator << (5 - bits) & 31) as u8);
+    }
+    let checksum = bech32_polymod(expand_hrp(hrp).chain(values.iter().copied()).chain([0; 6])) ^ 1;
+    values.extend((0..6).map(|i| (checksum >> (5 * (5 - i)) & 31) as u8));
+
+    let mut address = String::with_capacity(hrp.len() + 1 + values.len());
+    address.push_str(hrp);
+    address.push('1');
+    address.extend(values.iter().map(|&value| BECH32[value as usize] as char));
+    address
+}
+
+/// The address on `network` paying to `public_key` as `kind`; `None` when
+/// the network has no addresses of that kind.
+pub fn encode(kind: Kind, public_key: &[u8; 33], network: Network) -> Option<String> {
+    let hash = hash_on(kind, public_key, network)?;
+    let version = match kind {
+        Kind::NativeSegwit => return Some(encode_segwit_v0(&hash, network.hrp()?)),
+        Kind::Legacy => match network.p2pkh_version() {
+            Some(version) => version,
+            None => return Some(encode_eip55(&hash)),
+        },
+        Kind::NestedSegwit => network.p2sh_version()?,
+    };
+    let mut data = Vec::with_capacity(1 + 20 + 4);
+    data.push(version);
+    data.extend_from_slice(&hash);
+    Some(bip32::base58check(&mut data))
+}
+
+/// What addresses of `kind` on `network` start with, whatever key they
+/// pay to; nothing when the network has none.
+pub fn starts(kind: Kind, network: Network) -> Vec<String> {
+    let version = match kind {
+        Kind::NativeSegwit => return network.hrp().map(|hrp| format!("{}1q", hrp)).into_iter().collect(),
+        Kind::Legacy => match network.p2pkh_version() {
+            Some(version) => version,
+            None => return vec!["0x".to_string()],
+        },
+        Kind::NestedSegwit => match network.p2sh_version() {
+            Some(version) => version,
+            None => return Vec::new(),
+        },
+    };
+    // The first digit grows with the hash while the length stays, so the
+    // lowest and highest tell the range
+    let first = |byte| {
+        let mut data = vec![version];
+        data.extend_from_slice(&[byte; 20]);
+        let address = bip32::base58check(&mut data);
+        (address.len(), BASE58.iter().position(|&c| c == address.as_bytes()[0]).unwrap())
+    };
+    let ((low_len, low), (high_len, high)) = (first(0x00), first(0xff));
+    let range = if low_len == high_len { low..=high } else { 0..=BASE58.len() - 1 };
+    BASE58[range].iter().map(|&c| (c as char).to_string()).collect()
+}
+
+/// Decodes a single-key address on `network`; `None` for anything else.
+pub fn decode(address: &str, network: Network) -> Option<(Kind, [u8; 20])> {
+    if network.p2pkh_version().is_none() {
+        return Some((Kind::Legacy, decode_eip55(address)?));
+    }
+    if let Some(program) = network.hrp().and_then(|hrp| decode_segwit_v0(address, hrp)) {
+        return Some((Kind::NativeSegwit, program.try_into().ok()?));
+    }
+    let (version, payload) = decode_base58check(address)?;
+    let kind = match version {
+        v if Some(v) == network.p2pkh_version() => Kind::Legacy,
+        v if Some(v) == network.p2sh_version() => Kind::NestedSegwit,
+        _ => return None,
+    };
+    Some((kind, payload.try_into().ok()?))
+}
+
+/// The addresses searched for, by what they pay to.
+#[derive(Default)]
+pub struct Targets {
+    addresses: HashMap<(Kind, [u8; 20]), String>,
+    network: Network,
+}
+
+impl Targets {
+    /// Reads one address on `network` per line, ignoring blank lines and
+    /// `#` comments; fails on the first line that isn't an address this
+    /// module knows.
+    pub fn parse(text: &str, network: Network) -> Result<Self, String> {
+        let mut targets = Self {
+            network,
+            ..Self::default()
+        };
+        for (n, line) in (1..).zip(text.lines()) {
+            let address = line.split('#').next().unwrap_or_default().trim();
+            if address.is_empty() {
+                continue;
+            }
+            let key = decode(address, network).ok_or_else(|| match network.keccak() {
+                true => format!("line {}: '{}' isn't an address on {}", n, address, network),
+                false => format!("line {}: '{}' isn't a {} P2PKH, P2SH or P2WPKH address", n, address, network),
+            })?;
+            targets.addresses.insert(key, address.to_string());
+        }
+        Ok(targets)
+    }
+
+    pub fn len(&self) -> usize {
+        self.addresses.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.addresses.is_empty()
+    }
+
+    /// The addresses, in no particular order.
+    pub fn addresses(&self) -> impl Iterator<Item = &str> {
+        self.addresses.values().map(String::as_str)
+    }
+
+    /// The kinds of address among the targets; only these need deriving.
+    pub fn kinds(&self) -> Vec<Kind> {
+        Kind::ALL
+            .into_iter()
+            .filter(|kind| self.addresses.keys().any(|(k, _)| k == kind))
+            .collect()
+    }
+
+    /// The target address paying to `public_key` as `kind`, if there is one.
+    pub fn find(&self, kind: Kind, public_key: &[u8; 33]) -> Option<&str> {
+        let hash = hash_on(kind, public_key, self.network)?;
+        self.addresses.get(&(kind, hash)).map(String::as_str)
+    }
+}
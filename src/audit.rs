@@ -0,0 +1,139 @@
+//! Append-only audit trail of validation runs, for chain-of-custody records.
+//!
+//! Every run adds timestamped events to one log (by default
+//! `audit.log` in the local data directory): when it started, by whom, on
+//! which host, with which arguments and input hashes; how it ended, with
+//! its counts and output hashes. Phrases never go into it.
+//!
+//! Each event is a line of JSON carrying the SHA-256 of the line before it,
+//! so removing or editing an entry breaks the chain at that point, which
+//! [`verify`] finds. The log is locked while an event is appended, as runs
+//! may share it.
+
+use crate::digest;
+use sha2::{Digest, Sha256};
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{self, Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+};
+
+/// `previous` of the first event.
+const GENESIS: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+/// Read from the end of the log to find its last line; events are far
+/// shorter.
+const TAIL: u64 = 1 << 16;
+
+/// `~/.local/share/mnemonic_validator/audit.log` on Linux.
+pub fn default_path() -> PathBuf {
+    dirs::data_local_dir()
+        .or_else(dirs::home_dir)
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join("mnemonic_validator")
+        .join("audit.log")
+}
+
+fn user() -> String {
+    ["USER", "USERNAME", "LOGNAME"]
+        .iter()
+        .find_map(|name| std::env::var(name).ok())
+        .unwrap_or_default()
+}
+
+/// This machine's name, as far as the environment or `/etc/hostname` tell.
+pub fn host() -> String {
+    ["HOSTNAME", "COMPUTERNAME"]
+        .iter()
+        .find_map(|name| std::env::var(name).ok())
+        .or_else(|| Some(fs::read_to_string("/etc/hostname").ok()?.trim().to_string()))
+        .unwrap_or_default()
+}
+
+fn line_hash(line: &[u8]) -> String {
+    digest::to_hex(&Sha256::digest(line))
+}
+
+/// The hash the next event has to carry: that of the log's last line.
+fn last_hash(file: &mut File) -> io::Result<String> {
+    let len = file.seek(SeekFrom::End(0))?;
+    if len == 0 {
+        return Ok(GENESIS.to_string());
+    }
+    let start = len.saturating_sub(TAIL);
+    file.seek(SeekFrom::Start(start))?;
+    let mut tail = Vec::with_capacity((len - start) as usize);
+    file.read_to_end(&mut tail)?;
+    let tail = tail.strip_suffix(b"\n").unwrap_or(&tail);
+    let last = tail.rsplit(|&byte| byte == b'\n').next().unwrap_or(tail);
+    Ok(line_hash(last))
+}
+
+/// Where a run's events go, and which run they're about.
+#[derive(Clone, Debug)]
+pub struct AuditLog {
+    path: PathBuf,
+    run: Option<String>,
+}
+
+impl AuditLog {
+    /// `run` is the run's ID, when it has one.
+    pub fn new(path: PathBuf, run: Option<String>) -> Self {
+        Self { path, run }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Appends `event` (`started`, `finished`, `interrupted`, `failed`) with
+    /// `details`, an object whose fields are added to the entry.
+    pub fn record(&self, event: &str, details: serde_json::Value) -> io::Result<()> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let mut file = OpenOptions::new()
+            .read(true)
+            .append(true)
+            .create(true)
+            .open(&self.path)?;
+        file.lock()?;
+
+        let mut entry = serde_json::json!({
+            "time": chrono::Local::now().to_rfc3339(),
+            "event": event,
+            "run": self.run,
+            "user": user(),
+            "host": host(),
+            "pid": std::process::id(),
+        });
+        if let (Some(entry), serde_json::Value::Object(details)) = (entry.as_object_mut(), details) {
+            entry.extend(details);
+            entry.insert("previous".to_string(), last_hash(&mut file)?.into());
+        }
+        let mut line = serde_json::to_vec(&entry)?;
+        line.push(b'\n');
+        file.write_all(&line)?;
+        file.sync_data()
+    }
+}
+
+/// Checks the chain of the log at `path`; the number of events if it's
+/// intact, or the line where it breaks.
+pub fn verify(path: &Path) -> Result<usize, String> {
+    let text = fs::read(path).map_err(|e| format!("can't read {}: {}", path.display(), e))?;
+    let mut expected = GENESIS.to_string();
+    let mut events = 0;
+    for (n, line) in (1..).zip(text.split(|&byte| byte == b'\n')) {
+        if line.is_empty() {
+            continue;
+        }
+        let entry: serde_json::Value =
+            serde_json::from_slice(line).map_err(|e| format!("line {} isn't an event: {}", n, e))?;
+        if entry["previous"].as_str() != Some(expected.as_str()) {
+            return Err(format!("the chain breaks at line {}: an event before it was changed or removed", n));
+        }
+        expected = line_hash(line);
+        events += 1;
+    }
+    Ok(events)
+}
@@ -0,0 +1,65 @@
+//! Checking many phrases in one call, for programs embedding the validator.
+//!
+//! A program that checks phrases one call at a time pays the crossing into
+//! the library (and, through the C ABI or Node, into Rust) for every phrase,
+//! and keeps to a single core. [`validate_batch`] takes them all at once and
+//! spreads large batches over the CPUs.
+
+use crate::{lines, prefilter, typography};
+use std::thread;
+
+/// Starting a thread costs about as much as checking this many phrases a
+/// few times over, so smaller batches aren't split further.
+const MIN_PER_THREAD: usize = 1024;
+
+/// How a phrase fared.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    Valid,
+    Invalid,
+}
+
+impl Outcome {
+    pub fn is_valid(self) -> bool {
+        self == Outcome::Valid
+    }
+}
+
+/// Checks one phrase exactly as the command line does a line: English
+/// wordlist, [normalized](lines::normalize), typographic quotes, dashes and
+/// invisible characters cleaned up, pre-filter first.
+pub fn validate(phrase: &str) -> Outcome {
+    let phrase = lines::normalize_str(phrase);
+    let cleaned = typography::clean(phrase);
+    let phrase = cleaned.as_deref().map_or(phrase, String::as_str);
+    if prefilter::parse(phrase).is_some() {
+        Outcome::Valid
+    } else {
+        Outcome::Invalid
+    }
+}
+
+/// Checks every phrase, like [`validate`]; the outcomes are in the order of
+/// the phrases.
+pub fn validate_batch<S: AsRef<str> + Sync>(phrases: &[S]) -> Vec<Outcome> {
+    let mut outcomes = vec![Outcome::Invalid; phrases.len()];
+    let cpus = thread::available_parallelism().map_or(1, |n| n.get());
+    let threads = (phrases.len() / MIN_PER_THREAD).clamp(1, cpus);
+    if threads == 1 {
+        for (phrase, outcome) in phrases.iter().zip(&mut outcomes) {
+            *outcome = validate(phrase.as_ref());
+        }
+        return outcomes;
+    }
+    let per_thread = phrases.len().div_ceil(threads);
+    thread::scope(|scope| {
+        for (phrases, outcomes) in phrases.chunks(per_thread).zip(outcomes.chunks_mut(per_thread)) {
+            scope.spawn(move || {
+                for (phrase, outcome) in phrases.iter().zip(outcomes) {
+                    *outcome = validate(phrase.as_ref());
+                }
+            });
+        }
+    });
+    outcomes
+}
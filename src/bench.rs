@@ -0,0 +1,107 @@
+use crate::corpus;
+use clap::Args;
+use mnemonic_validator::{
+    cancel::CancelToken,
+    pipeline::{self, Pipeline},
+};
+use std::time::Instant;
+
+/// Options of `mnemonic_validator bench`.
+#[derive(Args, Debug)]
+pub struct BenchArgs {
+    /// Number of lines in the synthetic corpus.
+    #[clap(long, value_name = "N", default_value_t = 1_000_000)]
+    lines: usize,
+
+    /// Fraction of valid mnemonics in the corpus, e.g. 0.01 or 1%.
+    #[clap(long, value_name = "RATE", default_value = "1%", value_parser = corpus::parse_ratio)]
+    valid_ratio: f64,
+
+    /// Comma-separated thread counts to measure; defaults to 1, 2, 4, ... up to the number of CPUs.
+    #[clap(
+        long,
+        value_name = "N,N,...",
+        value_delimiter = ',',
+        value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..)
+    )]
+    threads: Vec<usize>,
+
+    /// Seed for the corpus generator.
+    #[clap(long, default_value_t = 0)]
+    seed: u64,
+}
+
+/// Returns the corpus and how many of its lines are valid.
+fn corpus(args: &BenchArgs) -> (Vec<u8>, usize) {
+    let mut text = Vec::new();
+    let valid = corpus::write(&mut text, args.lines, args.valid_ratio, args.seed).expect("writing to memory");
+    (text, valid)
+}
+
+fn default_threads() -> Vec<usize> {
+    let cpus = std::thread::available_parallelism().map_or(1, |n| n.get());
+    let mut threads: Vec<usize> = std::iter::successors(Some(1), |n| Some(n * 2))
+        .take_while(|&n| n < cpus)
+        .collect();
+    threads.push(cpus);
+    threads
+}
+
+/// Times validation of an in-memory corpus at different thread counts.
+pub fn run(args: &BenchArgs) -> Result<(), Box<dyn std::error::Error>> {
+    let threads = if args.threads.is_empty() {
+        default_threads()
+    } else {
+        args.threads.clone()
+    };
+
+    println!(
+        "Generating {} lines ({}% valid)...",
+        args.lines,
+        args.valid_ratio * 100.0
+    );
+    let (corpus, expected) = corpus(args);
+    let megabytes = corpus.len() as f64 / (1024.0 * 1024.0);
+    println!("Corpus: {:.1} MiB, {} valid", megabytes, expected);
+    println!();
+    println!("{:>7}  {:>9}  {:>12}  {:>9}  {:>7}", "threads", "time", "lines/s", "MiB/s", "speedup");
+
+    let never = CancelToken::new();
+    let mut baseline = None;
+    for &n in &threads {
+        let mut found = 0;
+        let start = Instant::now();
+        Pipeline::with_validators(n).run(
+            pipeline::slices(&corpus, 0, pipeline::LINES_PER_CHUNK).map(Ok),
+            0..args.lines,
+            &never,
+            |_, line| crate::parse(line),
+            |_| {
+                found += 1;
+                Ok(())
+            },
+        )?;
+        let secs = start.elapsed().as_secs_f64();
+
+        if found != expected {
+            return Err(format!(
+                "found {} valid mnemonics with {} threads, expected {}",
+                found, n, expected
+            )
+            .into());
+        }
+
+        let speed = args.lines as f64 / secs;
+        let baseline = *baseline.get_or_insert(speed);
+        println!(
+            "{:>7}  {:>8.2}s  {:>12.0}  {:>9.1}  {:>6.2}x",
+            n,
+            secs,
+            speed,
+            megabytes / secs,
+            speed / baseline
+        );
+    }
+
+    Ok(())
+}
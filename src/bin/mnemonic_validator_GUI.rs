@@ -1,20 +1,45 @@
 use std::{
     fs::{self, OpenOptions},
-    io::{BufWriter, Write},
+    io::Write,
     path::{Path, PathBuf},
     sync::{
-        atomic::{AtomicBool, Ordering},
-        Arc,
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
     },
     thread,
     time::{Duration, Instant},
 };
 
 use bip39::{Language, Mnemonic};
-use crossbeam_channel::{unbounded, Receiver};
+use crossbeam_channel::{bounded, Receiver};
 use eframe::{egui, NativeOptions};
+use mnemonic_validator::{
+    address::Targets,
+    cancel::CancelToken,
+    config::{self, EngineConfig},
+    diagnostics, electrum,
+    engine::FileRun,
+    eta::{self, EtaEstimator},
+    events::{Events, Progress},
+    lines,
+    mask,
+    network::Network,
+    output::{ExistingOutput, HitWriter},
+    paths,
+    pipeline::Hit,
+    prefilter,
+    recovery::{self, Confirm, Estimate, Found, Searched, Template},
+    secret::{SecretString, Zeroizing},
+    throttle::Throttle,
+    typography,
+    wallet::{Chain, Scan},
+    wordlist,
+};
 use rfd::FileDialog;
 
+/// Progress updates queued for the UI thread.
+const PROGRESS_QUEUE: usize = 16;
+
 #[derive(Clone, Debug)]
 struct ProgressUpdate {
     processed: usize,
@@ -25,12 +50,486 @@ struct ProgressUpdate {
     status: String,
 }
 
+/// The "Export to Electrum" window: the valid phrases of the output file,
+/// shown masked, and the password to encrypt the wallet with.
+struct ElectrumExport {
+    /// Line in the output file and phrase.
+    phrases: Vec<(usize, SecretString)>,
+    selected: usize,
+    network: Network,
+    password: SecretString,
+    confirm: SecretString,
+    status: String,
+}
+
+impl ElectrumExport {
+    /// Reads the valid phrases back from `output`.
+    fn open(output: &Path) -> Self {
+        let (phrases, status) = match fs::read(output) {
+            Ok(text) => {
+                let text = Zeroizing::new(text);
+                let phrases = lines::lines(&text)
+                    .enumerate()
+                    .filter_map(|(i, line)| {
+                        let line = std::str::from_utf8(line).ok()?.trim();
+                        Mnemonic::parse_normalized(line).ok()?;
+                        Some((i + 1, SecretString::new(line.to_string())))
+                    })
+                    .collect();
+                (phrases, String::new())
+            }
+            Err(e) => (Vec::new(), format!("Error reading output: {}", e)),
+        };
+        Self {
+            phrases,
+            selected: 0,
+            network: Network::default(),
+            // Room for any password typed, so it's never reallocated
+            password: SecretString::new(String::with_capacity(256)),
+            confirm: SecretString::new(String::with_capacity(256)),
+            status,
+        }
+    }
+
+    /// Asks where to save the selected phrase's wallet and writes it.
+    fn save(&mut self) {
+        if *self.password != *self.confirm {
+            self.status = "The passwords don't match.".to_string();
+            return;
+        }
+        let Some((_, phrase)) = self.phrases.get(self.selected) else {
+            return;
+        };
+        let Some(path) = FileDialog::new().set_file_name("electrum_wallet").save_file() else {
+            return;
+        };
+        let path = paths::from_arg(path.into_os_string());
+        let result = Mnemonic::parse_normalized(phrase)
+            .map_err(std::io::Error::other)
+            .and_then(|mnemonic| electrum::wallet(&mnemonic, &self.password, self.network))
+            .and_then(|wallet| fs::write(&path, wallet.as_bytes()));
+        self.status = match result {
+            Ok(()) if self.password.is_empty() => {
+                format!("Saved to {}. It isn't encrypted: keep it safe.", path.display())
+            }
+            Ok(()) => format!("Saved to {}.", path.display()),
+            Err(e) => format!("Couldn't save the wallet: {}", e),
+        };
+    }
+}
+
+/// What the recovery wizard shows.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum WizardStep {
+    Words,
+    Confirm,
+    Search,
+}
+
+/// What's remembered of one word.
+#[derive(Default)]
+struct WordSlot {
+    word: SecretString,
+    unknown: bool,
+    /// Try the words like it too.
+    unsure: bool,
+}
+
+/// List words to offer for what's typed in a slot: those it starts, or
+/// else those like it.
+fn completions(typed: &str) -> Vec<&'static str> {
+    let typed = typed.trim().to_lowercase();
+    if typed.is_empty() || wordlist::index_in(&typed, Language::English).is_some() {
+        return Vec::new();
+    }
+    let starting: Vec<&'static str> = Language::English
+        .word_list()
+        .iter()
+        .copied()
+        .filter(|word| word.starts_with(typed.as_str()))
+        .take(6)
+        .collect();
+    if starting.is_empty() {
+        diagnostics::suggest(&typed)
+    } else {
+        starting
+    }
+}
+
+/// A search running in the background.
+struct RecoverySearch {
+    cancel: CancelToken,
+    count: u128,
+    searched: Arc<Searched>,
+    /// Phrases with a valid checksum.
+    phrases: Arc<AtomicU64>,
+    start: Instant,
+    estimator: EtaEstimator,
+    /// How the search ended.
+    done_rx: Receiver<String>,
+    found: Arc<Mutex<Option<FoundPhrase>>>,
+}
+
+/// The phrase confirmed by an address.
+struct FoundPhrase {
+    phrase: SecretString,
+    passphrase: SecretString,
+    /// The address it gives, and where.
+    target: String,
+}
+
+/// The "Recover a Phrase" window: the words remembered, position by
+/// position, then the addresses and passphrases that tell the right phrase
+/// apart, then the search, as `wizard` does on the command line.
+struct RecoveryWizard {
+    step: WizardStep,
+    slots: Vec<WordSlot>,
+    network: Network,
+    /// One address per line.
+    addresses: String,
+    /// One candidate per line.
+    passphrases: SecretString,
+    output: PathBuf,
+    search: Option<RecoverySearch>,
+    /// Of a search too long to start on the first click.
+    estimate: Option<Estimate>,
+    show_phrase: bool,
+    status: String,
+}
+
+impl RecoveryWizard {
+    fn new() -> Self {
+        Self {
+            step: WizardStep::Words,
+            slots: (0..12).map(|_| WordSlot::default()).collect(),
+            network: Network::default(),
+            addresses: String::new(),
+            passphrases: SecretString::new(String::with_capacity(1024)),
+            output: PathBuf::from("output").join("recovered.txt"),
+            search: None,
+            estimate: None,
+            show_phrase: false,
+            status: String::new(),
+        }
+    }
+
+    fn template(&self) -> Result<Template, String> {
+        let slots = self
+            .slots
+            .iter()
+            .enumerate()
+            .map(|(i, slot)| {
+                let word = slot.word.trim().to_lowercase();
+                if slot.unknown || word == "?" {
+                    return Ok((0..2048).collect());
+                }
+                if word.is_empty() {
+                    return Err(format!("Word {} is empty; tick \"Unknown\" if you don't remember it.", i + 1));
+                }
+                if let Some(index) = wordlist::index_in(&word, Language::English).filter(|_| !slot.unsure) {
+                    return Ok(vec![index]);
+                }
+                let similar = recovery::similar(&word);
+                if similar.is_empty() {
+                    return Err(format!("Word {}, '{}', isn't on the list and none is like it.", i + 1, word));
+                }
+                Ok(similar)
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Template::new(slots)
+    }
+
+    fn confirm(&self) -> Result<Option<Confirm>, String> {
+        let targets = Targets::parse(&self.addresses, self.network)?;
+        if targets.is_empty() {
+            return Ok(None);
+        }
+        let mut passphrases = vec![SecretString::new(String::new())];
+        passphrases.extend(
+            self.passphrases
+                .lines()
+                .filter(|line| !line.is_empty())
+                .map(|line| SecretString::new(line.to_string())),
+        );
+        Ok(Some(Confirm {
+            targets,
+            passphrases,
+            scan: Scan {
+                chains: vec![Chain::External],
+                gap_limit: mnemonic_validator::wallet::DEFAULT_GAP_LIMIT,
+                network: self.network,
+            },
+        }))
+    }
+
+    /// Starts the search; one estimated to take long only once `confirmed`.
+    fn start(&mut self, confirmed: bool) {
+        let (template, confirm) = match (self.template(), self.confirm()) {
+            (Ok(template), Ok(confirm)) => (template, confirm),
+            (Err(e), _) | (_, Err(e)) => {
+                self.status = e;
+                return;
+            }
+        };
+        let Some(count) = template.count().filter(|&count| count <= u64::MAX as u128) else {
+            self.status = "That's more candidates than can be searched; fill in more of the words.".to_string();
+            return;
+        };
+        let threads = thread::available_parallelism().map_or(1, |n| n.get());
+        if !confirmed {
+            let estimate = recovery::estimate(&template, threads, confirm.as_ref());
+            if estimate.is_long() {
+                self.estimate = Some(estimate);
+                return;
+            }
+        }
+        self.estimate = None;
+        if let Some(parent) = self.output.parent() {
+            let _ = fs::create_dir_all(parent);
+        }
+        let output = match OpenOptions::new().create(true).append(true).open(&self.output) {
+            Ok(file) => Mutex::new(file),
+            Err(e) => {
+                self.status = format!("Can't open {}: {}", self.output.display(), e);
+                return;
+            }
+        };
+
+        let (tx, rx) = bounded(1);
+        let search = RecoverySearch {
+            cancel: CancelToken::new(),
+            count,
+            searched: Arc::new(Searched::new(0)),
+            phrases: Arc::new(AtomicU64::new(0)),
+            start: Instant::now(),
+            estimator: EtaEstimator::new(Instant::now(), 0),
+            done_rx: rx,
+            found: Arc::new(Mutex::new(None)),
+        };
+        let (cancel, searched, phrases, found) =
+            (search.cancel.clone(), search.searched.clone(), search.phrases.clone(), search.found.clone());
+        thread::spawn(move || {
+            let on_found = |hit: Found| {
+                let mut line = SecretString::new(hit.mnemonic.to_string());
+                line.push('\n');
+                let _ = output.lock().unwrap().write_all(line.as_bytes());
+                phrases.fetch_add(1, Ordering::Relaxed);
+                if let Some((passphrase, target)) = hit.confirmed {
+                    found.lock().unwrap().get_or_insert(FoundPhrase {
+                        phrase: SecretString::new(hit.mnemonic.to_string()),
+                        passphrase,
+                        target: format!("{} at {}", target.address, target.path),
+                    });
+                    cancel.cancel();
+                }
+                true
+            };
+            let reached = recovery::search(&template, 0..count, threads, confirm.as_ref(), &cancel, &searched, &on_found);
+            let status = if let Some(found) = found.lock().unwrap().as_ref() {
+                format!("Found it: it gives {}.", found.target)
+            } else if reached < count {
+                "Stopped.".to_string()
+            } else if confirm.is_some() {
+                "No candidate gives one of the addresses.".to_string()
+            } else {
+                format!(
+                    "Done: {} phrases have a valid checksum. Give an address the wallet used to tell which is yours.",
+                    phrases.load(Ordering::Relaxed)
+                )
+            };
+            let _ = tx.send(status);
+        });
+        self.search = Some(search);
+        self.status = String::new();
+        self.show_phrase = false;
+        self.step = WizardStep::Search;
+    }
+
+    /// Draws the window; false once it's closed.
+    fn show(&mut self, ctx: &egui::Context) -> bool {
+        let mut open = true;
+        egui::Window::new("Recover a Phrase")
+            .collapsible(false)
+            .resizable(true)
+            .default_width(560.0)
+            .show(ctx, |ui| {
+                ui.vertical(|ui| match self.step {
+                    WizardStep::Words => self.words_step(ui),
+                    WizardStep::Confirm => self.confirm_step(ui),
+                    WizardStep::Search => self.search_step(ui),
+                });
+                if !self.status.is_empty() {
+                    ui.label(&self.status);
+                }
+                let searching = self.step == WizardStep::Search && self.status.is_empty();
+                if !searching && ui.button("Close").clicked() {
+                    open = false;
+                }
+            });
+        open
+    }
+
+    fn words_step(&mut self, ui: &mut egui::Ui) {
+        ui.label("Type the words you remember, each in its place. Tick \"Unknown\" for those you don't, and \"Unsure\" for those that may be misspelled or misremembered.");
+        ui.horizontal(|ui| {
+            ui.label("Words:");
+            let mut words = self.slots.len();
+            egui::ComboBox::from_id_source("recovery_words")
+                .selected_text(words.to_string())
+                .show_ui(ui, |ui| {
+                    for n in [12, 15, 18, 21, 24] {
+                        ui.selectable_value(&mut words, n, n.to_string());
+                    }
+                });
+            self.slots.resize_with(words, WordSlot::default);
+        });
+        egui::ScrollArea::vertical().max_height(360.0).show(ui, |ui| {
+            for (i, slot) in self.slots.iter_mut().enumerate() {
+                ui.horizontal(|ui| {
+                    ui.label(format!("{:>2}.", i + 1));
+                    ui.add_enabled(
+                        !slot.unknown,
+                        egui::TextEdit::singleline(&mut *slot.word).desired_width(120.0),
+                    );
+                    ui.checkbox(&mut slot.unknown, "Unknown");
+                    ui.add_enabled(!slot.unknown, egui::Checkbox::new(&mut slot.unsure, "Unsure"));
+                    if !slot.unknown {
+                        for word in completions(&slot.word) {
+                            if ui.small_button(word).clicked() {
+                                slot.word.clear();
+                                slot.word.push_str(word);
+                            }
+                        }
+                    }
+                });
+            }
+        });
+        match self.template() {
+            Ok(template) => {
+                ui.label(format!(
+                    "{} candidate phrases.",
+                    template.count().map_or_else(|| "More than 10^38".to_string(), |count| count.to_string())
+                ));
+                if ui.button("Next").clicked() {
+                    self.status.clear();
+                    self.step = WizardStep::Confirm;
+                }
+            }
+            Err(e) => {
+                ui.label(e);
+            }
+        }
+    }
+
+    fn confirm_step(&mut self, ui: &mut egui::Ui) {
+        ui.label("Addresses the wallet received coins at, one per line. Without one, every phrase with a valid checksum is kept and there's no telling which is yours.");
+        let mut changed = ui
+            .add(egui::TextEdit::multiline(&mut self.addresses).desired_rows(3).desired_width(f32::INFINITY))
+            .changed();
+        ui.horizontal(|ui| {
+            ui.label("Network:");
+            egui::ComboBox::from_id_source("recovery_network")
+                .selected_text(self.network.name())
+                .show_ui(ui, |ui| {
+                    for network in Network::all() {
+                        ui.selectable_value(&mut self.network, network, network.name());
+                    }
+                });
+        });
+        ui.label("Passphrases the wallet may have had, one per line (no passphrase is tried too):");
+        changed |= ui
+            .add(egui::TextEdit::multiline(&mut *self.passphrases).desired_rows(3).desired_width(f32::INFINITY))
+            .changed();
+        if changed {
+            self.estimate = None;
+        }
+        ui.label(format!("Phrases found are appended to {}.", self.output.display()));
+        if let Some(estimate) = &self.estimate {
+            ui.label(format!(
+                "This search would take {}: {}. Filling in more of the words cuts that down.",
+                eta::format_span(estimate.seconds),
+                estimate
+            ));
+        }
+        ui.horizontal(|ui| {
+            if ui.button("Back").clicked() {
+                self.status.clear();
+                self.estimate = None;
+                self.step = WizardStep::Words;
+            }
+            if self.estimate.is_some() {
+                if ui.button("Start Anyway").clicked() {
+                    self.start(true);
+                }
+            } else if ui.button("Start Search").clicked() {
+                self.start(false);
+            }
+        });
+    }
+
+    fn search_step(&mut self, ui: &mut egui::Ui) {
+        let Some(search) = self.search.as_mut() else {
+            return;
+        };
+        if let Ok(status) = search.done_rx.try_recv() {
+            self.status = status;
+        }
+        let searched = search.searched.count();
+        let now = Instant::now();
+        search.estimator.update(now, searched as usize);
+        let elapsed = now.duration_since(search.start).as_secs_f64();
+        ui.add(egui::ProgressBar::new(searched as f32 / search.count as f32).show_percentage());
+        ui.horizontal(|ui| {
+            ui.label(format!("Searched: {}/{}", searched, search.count));
+            ui.label(format!("Valid checksum: {}", search.phrases.load(Ordering::Relaxed)));
+        });
+        ui.horizontal(|ui| {
+            ui.label(format!("Speed: {:.0}/sec", if elapsed > 0.0 { searched as f64 / elapsed } else { 0.0 }));
+            let eta = search
+                .estimator
+                .estimate((search.count as u64).saturating_sub(searched) as usize)
+                .map_or_else(|| "-".to_string(), |eta| eta.to_string());
+            ui.label(format!("ETA: {}", eta));
+        });
+        if let Some(found) = search.found.lock().unwrap().as_ref() {
+            ui.checkbox(&mut self.show_phrase, "Show the phrase");
+            if self.show_phrase {
+                ui.label(egui::RichText::new(found.phrase.as_str()).monospace());
+                if !found.passphrase.is_empty() {
+                    ui.label(format!("Passphrase: {}", found.passphrase.as_str()));
+                }
+            } else {
+                ui.label(format!("sha256:{} (hidden)", mask::fingerprint(&found.phrase)));
+            }
+        }
+        let running = self.status.is_empty();
+        ui.horizontal(|ui| {
+            if running {
+                if ui.button("Cancel").clicked() {
+                    search.cancel.cancel();
+                }
+            } else if ui.button("Back").clicked() {
+                self.status.clear();
+                self.step = WizardStep::Confirm;
+            }
+        });
+    }
+}
+
 struct AppState {
     input_path: Option<PathBuf>,
     output_path: Option<PathBuf>,
+    /// What to do with an output file left by an earlier run.
+    existing_output: ExistingOutput,
     
     is_running: bool,
-    cancel_flag: Arc<AtomicBool>,
+    cancel: CancelToken,
+    /// Engine settings, kept between sessions in the same form as job files
+    /// and daemon descriptors.
+    engine: EngineConfig,
+    /// CPU limit in percent; applies to a running validation right away.
+    max_cpu: f64,
+    throttle: Arc<Throttle>,
     
     progress: ProgressUpdate,
     progress_rx: Option<Receiver<ProgressUpdate>>,
@@ -38,6 +537,8 @@ struct AppState {
     // UI state
     show_help: bool,
     auto_output: bool,
+    export: Option<ElectrumExport>,
+    recovery: Option<RecoveryWizard>,
 }
 
 impl Default for AppState {
@@ -52,12 +553,22 @@ impl Default for AppState {
                 ]
             })
             .find(|p| p.exists());
-            
+        // Settings that can't be read are left at their defaults
+        let engine = config::default_gui_settings_path()
+            .filter(|path| path.is_file())
+            .and_then(|path| EngineConfig::load(&path).map_err(|e| eprintln!("Warning: {}", e)).ok())
+            .unwrap_or_default();
+        let max_cpu = engine.max_cpu.map_or(100.0, |share| share * 100.0);
+
         Self {
             input_path: default_input,
             output_path: None,
+            existing_output: ExistingOutput::Append,
             is_running: false,
-            cancel_flag: Arc::new(AtomicBool::new(false)),
+            cancel: CancelToken::new(),
+            engine,
+            max_cpu,
+            throttle: Arc::new(Throttle::new(max_cpu / 100.0)),
             progress: ProgressUpdate {
                 processed: 0,
                 valid: 0,
@@ -69,6 +580,8 @@ impl Default for AppState {
             progress_rx: None,
             show_help: false,
             auto_output: true,
+            export: None,
+            recovery: None,
         }
     }
 }
@@ -105,7 +618,11 @@ impl eframe::App for AppState {
                             ui.label("• Works with any file format (txt, csv, dat, etc.)");
                             ui.label("• Validates 12/15/18/21/24-word mnemonics");
                             ui.label("• Fast processing with progress tracking");
-                            ui.label("• Cancellable operation");
+                            ui.label("• Cancellable, pausable operation");
+                            ui.label("• CPU limit, adjustable while running");
+                            ui.label("• Appends to, overwrites or keeps an existing output file");
+                            ui.label("• Exports a valid mnemonic as an Electrum wallet file");
+                            ui.label("• Recovers a phrase with missing or uncertain words");
                         });
                         
                         ui.add_space(15.0);
@@ -117,6 +634,76 @@ impl eframe::App for AppState {
                 });
         }
         
+        // Electrum export dialog
+        let mut close_export = false;
+        if let Some(export) = self.export.as_mut() {
+            egui::Window::new("Export to Electrum")
+                .collapsible(false)
+                .resizable(false)
+                .default_width(500.0)
+                .show(ctx, |ui| {
+                    ui.vertical(|ui| {
+                        if export.phrases.is_empty() {
+                            // Unless reading it failed, which the status tells
+                            if export.status.is_empty() {
+                                ui.label("The output file holds no valid mnemonics.");
+                            }
+                        } else {
+                            ui.label(format!(
+                                "Writes a wallet file for the first native SegWit account ({}) of the mnemonic, to open in Electrum.",
+                                electrum::derivation(export.network)
+                            ));
+                            let label = |(line, phrase): &(usize, SecretString)| mask::masked_line(phrase, None, *line);
+                            egui::ComboBox::from_id_source("electrum_phrase")
+                                .width(450.0)
+                                .selected_text(label(&export.phrases[export.selected]))
+                                .show_ui(ui, |ui| {
+                                    for (i, entry) in export.phrases.iter().enumerate() {
+                                        ui.selectable_value(&mut export.selected, i, label(entry));
+                                    }
+                                });
+                            ui.horizontal(|ui| {
+                                ui.label("Network:");
+                                egui::ComboBox::from_id_source("electrum_network")
+                                    .selected_text(export.network.name())
+                                    .show_ui(ui, |ui| {
+                                        for network in Network::all().filter(|network| network.zpub().is_some()) {
+                                            ui.selectable_value(&mut export.network, network, network.name());
+                                        }
+                                    });
+                            });
+                            ui.horizontal(|ui| {
+                                ui.label("Password (optional):");
+                                ui.add(egui::TextEdit::singleline(&mut *export.password).password(true));
+                            });
+                            ui.horizontal(|ui| {
+                                ui.label("Confirm password:");
+                                ui.add(egui::TextEdit::singleline(&mut *export.confirm).password(true));
+                            });
+                        }
+                        if !export.status.is_empty() {
+                            ui.label(&export.status);
+                        }
+                        ui.horizontal(|ui| {
+                            if ui.add_enabled(!export.phrases.is_empty(), egui::Button::new("Save Wallet...")).clicked() {
+                                export.save();
+                            }
+                            if ui.button("Close").clicked() {
+                                close_export = true;
+                            }
+                        });
+                    });
+                });
+        }
+        if close_export {
+            self.export = None;
+        }
+
+        // Recovery wizard
+        if self.recovery.as_mut().is_some_and(|recovery| !recovery.show(ctx)) {
+            self.recovery = None;
+        }
+
         // Receive progress updates if any
         let mut should_clear_rx = false;
         if let Some(rx) = &self.progress_rx {
@@ -165,7 +752,7 @@ impl eframe::App for AppState {
                             .add_filter("Text Files", &["txt", "csv", "dat", "log"])
                             .pick_file() 
                         {
-                            self.input_path = Some(path);
+                            self.input_path = Some(paths::from_arg(path.into_os_string()));
                             if self.auto_output {
                                 self.update_auto_output();
                             }
@@ -196,12 +783,57 @@ impl eframe::App for AppState {
                                 .add_filter("Text Files", &["txt"])
                                 .save_file()
                             {
-                                self.output_path = Some(path);
+                                self.output_path = Some(paths::from_arg(path.into_os_string()));
                             }
                         }
                     }
                 });
-                
+
+                ui.add_space(10.0);
+
+                // What happens to an earlier run's output, as with --append,
+                // --overwrite and --error-if-exists
+                ui.horizontal(|ui| {
+                    ui.label("If the output exists:");
+                    egui::ComboBox::from_id_source("existing_output")
+                        .selected_text(match self.existing_output {
+                            ExistingOutput::Append => "Append",
+                            ExistingOutput::Overwrite => "Overwrite",
+                            ExistingOutput::Error => "Don't start",
+                        })
+                        .show_ui(ui, |ui| {
+                            ui.selectable_value(&mut self.existing_output, ExistingOutput::Append, "Append");
+                            ui.selectable_value(&mut self.existing_output, ExistingOutput::Overwrite, "Overwrite");
+                            ui.selectable_value(&mut self.existing_output, ExistingOutput::Error, "Don't start");
+                        });
+                });
+
+                ui.add_space(10.0);
+
+                // CPU limit
+                ui.horizontal(|ui| {
+                    ui.label("Max CPU:");
+                    let slider = egui::Slider::new(&mut self.max_cpu, 10.0..=100.0)
+                        .suffix("%")
+                        .integer();
+                    if ui.add(slider).changed() {
+                        self.throttle.set(self.max_cpu / 100.0);
+                        self.engine.max_cpu = (self.max_cpu < 100.0).then_some(self.max_cpu / 100.0);
+                    }
+                });
+
+                // Validator threads, 0 for one per CPU
+                ui.horizontal(|ui| {
+                    ui.label("Threads:");
+                    let mut threads = self.engine.threads.unwrap_or(0);
+                    let drag = egui::DragValue::new(&mut threads)
+                        .clamp_range(0..=1024)
+                        .custom_formatter(|n, _| if n == 0.0 { "Auto".to_string() } else { n.to_string() });
+                    if ui.add_enabled(!self.is_running, drag).changed() {
+                        self.engine.threads = (threads > 0).then_some(threads);
+                    }
+                });
+
                 ui.add_space(20.0);
                 
                 // Action buttons
@@ -217,11 +849,30 @@ impl eframe::App for AppState {
                         }
                     } else {
                         if ui.button("Cancel").clicked() {
-                            self.cancel_flag.store(true, Ordering::SeqCst);
+                            self.cancel.cancel();
                             self.progress.status = "Cancelling...".to_string();
                         }
+                        let paused = self.cancel.is_paused();
+                        if ui.button(if paused { "Resume" } else { "Pause" }).clicked() {
+                            if paused {
+                                self.cancel.resume();
+                                self.progress.status = "Processing...".to_string();
+                            } else {
+                                self.cancel.pause();
+                                self.progress.status = "Paused.".to_string();
+                            }
+                        }
                     }
                     
+                    let has_output = self.output_path.as_ref().is_some_and(|path| path.exists());
+                    if !self.is_running && has_output && ui.button("Export to Electrum...").clicked() {
+                        self.export = Some(ElectrumExport::open(self.output_path.as_ref().unwrap()));
+                    }
+
+                    if !self.is_running && ui.button("Recover a Phrase...").clicked() {
+                        self.recovery.get_or_insert_with(RecoveryWizard::new);
+                    }
+
                     if ui.button("Help").clicked() {
                         self.show_help = true;
                     }
@@ -281,134 +932,110 @@ impl AppState {
             }
             
             let mut output_path = output_dir.to_path_buf();
-            let stem = input_path.file_stem()
-                .and_then(|s| s.to_str())
-                .unwrap_or("output");
-            output_path.push(format!("{}_valid.txt", stem));
+            // Kept as an OS string so non-ASCII file names carry over
+            let mut name = input_path.file_stem().unwrap_or("output".as_ref()).to_os_string();
+            name.push("_valid.txt");
+            output_path.push(name);
             self.output_path = Some(output_path);
         }
     }
     
     fn start_validation(&mut self) {
         self.is_running = true;
-        self.cancel_flag.store(false, Ordering::SeqCst);
+        self.cancel = CancelToken::new();
 
         let input_path = self.input_path.clone().unwrap();
         let output_path = self.output_path.clone().unwrap();
-        let cancel_flag = self.cancel_flag.clone();
+        let existing_output = self.existing_output;
+        let cancel = self.cancel.clone();
+        let throttle = self.throttle.clone();
+        let engine = self.engine.clone();
+        if let Some(path) = config::default_gui_settings_path() {
+            if let Err(e) = engine.save(&path) {
+                eprintln!("Warning: {}", e);
+            }
+        }
 
-        let (tx, rx) = unbounded();
+        // Bounded so a busy UI never lets updates pile up
+        let (tx, rx) = bounded(PROGRESS_QUEUE);
         self.progress_rx = Some(rx);
 
         thread::spawn(move || {
-            // Read the entire file first to handle any encoding
-            let content = match std::fs::read_to_string(&input_path) {
-                Ok(content) => content,
-                Err(e) => {
-                    let _ = tx.send(ProgressUpdate {
-                        processed: 0,
-                        valid: 0,
-                        total: 0,
-                        speed: 0,
-                        eta: "-".to_string(),
-                        status: format!("Error reading file: {}", e),
-                    });
-                    return;
-                }
-            };
-            
-            let total_lines = content.lines().count();
-
-            let output_file = match OpenOptions::new()
-                .create(true)
-                .write(true)
-                .truncate(true)
-                .open(&output_path)
-            {
-                Ok(file) => file,
-                Err(e) => {
-                    let _ = tx.send(ProgressUpdate {
-                        processed: 0,
-                        valid: 0,
-                        total: 0,
-                        speed: 0,
-                        eta: "-".to_string(),
-                        status: format!("Failed to create output file: {}", e),
-                    });
-                    return;
-                }
+            let fail = |status: String| {
+                let _ = tx.send(ProgressUpdate {
+                    processed: 0,
+                    valid: 0,
+                    total: 0,
+                    speed: 0,
+                    eta: "-".to_string(),
+                    status,
+                });
             };
-            
-            let mut writer = BufWriter::new(output_file);
-
-            let start_time = Instant::now();
-            let mut processed = 0usize;
-            let mut valid = 0usize;
-
-            for line in content.lines() {
-                if cancel_flag.load(Ordering::SeqCst) {
-                    let _ = tx.send(ProgressUpdate {
-                        processed,
-                        valid,
-                        total: total_lines,
-                        speed: 0,
-                        eta: "-".to_string(),
-                        status: "Cancelled.".to_string(),
-                    });
-                    return;
-                }
 
-                let line = line.trim();
-                if line.is_empty() {
-                    continue;
-                }
+            let mut writer = match HitWriter::create(&output_path, None, None, existing_output) {
+                Ok(writer) => writer,
+                Err(e) => return fail(format!("Failed to create output file: {}", e)),
+            };
 
-                processed += 1;
-
-                if Mnemonic::parse_in_normalized(Language::English, line).is_ok() {
-                    valid += 1;
-                    if let Err(e) = writeln!(writer, "{}", line) {
-                        let _ = tx.send(ProgressUpdate {
-                            processed,
-                            valid,
-                            total: total_lines,
-                            speed: 0,
-                            eta: "-".to_string(),
-                            status: format!("Error writing output: {}", e),
-                        });
-                        return;
+            let update = move |progress: &Progress, status: &str, eta: &str| ProgressUpdate {
+                processed: progress.processed,
+                valid: progress.valid,
+                total: progress.total.unwrap_or(0),
+                speed: progress.per_second() as usize,
+                eta: eta.to_string(),
+                status: status.to_string(),
+            };
+            let last = Arc::new(Mutex::new(None));
+            let events = Events::new()
+                .on_progress({
+                    let (tx, last) = (tx.clone(), last.clone());
+                    move |progress| {
+                        let eta = progress.eta.map_or_else(|| "-".to_string(), |eta| eta.to_string());
+                        // Drop the update if the UI hasn't caught up yet
+                        let _ = tx.try_send(update(progress, "Processing...", &eta));
+                        *last.lock().unwrap() = Some(progress.clone());
                     }
-                }
+                });
 
-                if processed % 200 == 0 || processed == total_lines {
-                    let elapsed = start_time.elapsed().as_secs_f64();
-                    let speed = if elapsed > 0.0 { (processed as f64 / elapsed) as usize } else { 0 };
-                    let remaining = total_lines.saturating_sub(processed);
-                    let eta_secs = if speed > 0 { remaining / speed } else { 0 };
-                    let eta = format!("{:02}:{:02}", eta_secs / 60, eta_secs % 60);
-
-                    let _ = tx.send(ProgressUpdate {
-                        processed,
-                        valid,
-                        total: total_lines,
-                        speed,
-                        eta,
-                        status: "Processing...".to_string(),
-                    });
+            let validate = |line: &str| {
+                if line.is_empty() {
+                    return None;
                 }
-            }
+                let cleaned = typography::clean(line);
+                let line = cleaned.as_deref().map_or(line, String::as_str);
+                prefilter::parse(line)
+            };
 
-            let elapsed = start_time.elapsed().as_secs_f64();
-            let speed = if elapsed > 0.0 { (processed as f64 / elapsed) as usize } else { 0 };
+            let write = |hit: Hit| {
+                let cleaned = typography::clean(hit.text.trim());
+                writer.write_hit(&hit.mnemonic, cleaned.as_deref().map_or(hit.text.trim(), String::as_str))?;
+                Ok(())
+            };
 
-            let _ = tx.send(ProgressUpdate {
-                processed,
-                valid,
-                total: total_lines,
-                speed,
-                eta: "00:00".to_string(),
-                status: "Done.".to_string(),
+            let run = FileRun {
+                config: &engine,
+                input: &input_path,
+                output: Some(&output_path),
+                validation: "english",
+                events: Some(Arc::new(events)),
+                throttle: Some(throttle),
+            };
+            let result = run.run(&cancel, validate, write);
+            let result = result.and_then(|lines| writer.flush().map(|()| lines).map_err(|e| format!("Error writing output: {}", e)));
+
+            let progress = last.lock().unwrap().take().unwrap_or(Progress {
+                processed: 0,
+                valid: 0,
+                total: result.as_ref().ok().copied(),
+                elapsed: Duration::ZERO,
+                eta: None,
             });
+            let update = match result {
+                Err(e) => update(&progress, &e, "-"),
+                Ok(_) if cancel.is_cancelled() => update(&progress, "Cancelled.", "-"),
+                Ok(_) => update(&progress, "Done.", "00:00"),
+            };
+            let _ = tx.send(update);
         });
     }
 }
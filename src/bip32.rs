@@ -0,0 +1,221 @@
+//! BIP32 key derivation from a mnemonic's seed, for the watch-only exports.
+//!
+//! Only private derivation is needed: every key on a path is derived from
+//! the one before it, and the account's extended public key is written out
+//! for wallets to derive addresses from.
+
+use crate::{
+    curve, digest,
+    secret::{SecretString, Zeroizing},
+};
+use hmac::{Hmac, Mac};
+use sha2::Sha512;
+use std::cell::OnceCell;
+
+/// First hardened child number.
+pub const HARDENED: u32 = 1 << 31;
+
+const BASE58: &[u8; 58] = b"123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
+
+/// An extended private key.
+pub struct ExtendedKey {
+    key: Zeroizing<[u8; 32]>,
+    chain_code: Zeroizing<[u8; 32]>,
+    depth: u8,
+    parent_fingerprint: [u8; 4],
+    child_number: u32,
+    /// Worked out once, as the keys of a chain all need their parent's.
+    public_key: OnceCell<[u8; 33]>,
+}
+
+/// HMAC-SHA512 of the concatenation of `data`.
+pub(crate) fn hmac_sha512(key: &[u8], data: &[&[u8]]) -> Zeroizing<[u8; 64]> {
+    let mut mac = Hmac::<Sha512>::new_from_slice(key).expect("HMAC takes any key length");
+    for data in data {
+        mac.update(data);
+    }
+    let mut output = Zeroizing::new([0; 64]);
+    output.copy_from_slice(&mac.finalize().into_bytes());
+    output
+}
+
+/// Splits the output of HMAC-SHA512 into key and chain code.
+fn split(output: &[u8; 64]) -> (Zeroizing<[u8; 32]>, Zeroizing<[u8; 32]>) {
+    let mut key = Zeroizing::new([0; 32]);
+    let mut chain_code = Zeroizing::new([0; 32]);
+    key.copy_from_slice(&output[..32]);
+    chain_code.copy_from_slice(&output[32..]);
+    (key, chain_code)
+}
+
+impl ExtendedKey {
+    /// The master key of a BIP39 seed; `None` for the one seed in 2^127 that
+    /// has none.
+    pub fn master(seed: &[u8]) -> Option<Self> {
+        let (key, chain_code) = split(&hmac_sha512(b"Bitcoin seed", &[seed]));
+        curve::is_scalar(&key).then_some(Self {
+            key,
+            chain_code,
+            depth: 0,
+            parent_fingerprint: [0; 4],
+            child_number: 0,
+            public_key: OnceCell::new(),
+        })
+    }
+
+    /// The child key `index` (hardened from [`HARDENED`] up); `None` for
+    /// the few that don't exist.
+    pub fn child(&self, index: u32) -> Option<Self> {
+        let output = if index >= HARDENED {
+            hmac_sha512(&*self.chain_code, &[&[0], &*self.key, &index.to_be_bytes()])
+        } else {
+            hmac_sha512(&*self.chain_code, &[&self.public_key(), &index.to_be_bytes()])
+        };
+        let (tweak, chain_code) = split(&output);
+        if !curve::is_scalar(&tweak) {
+            return None;
+        }
+        let key = Zeroizing::new(curve::add_scalars(&tweak, &self.key)?);
+        Some(Self {
+            key,
+            chain_code,
+            depth: self.depth.checked_add(1)?,
+            parent_fingerprint: self.fingerprint(),
+            child_number: index,
+            public_key: OnceCell::new(),
+        })
+    }
+
+    /// The key at `path`, child numbers from this one down; `None` if the
+    /// path is empty or runs into a key that doesn't exist.
+    pub fn derive(&self, path: &[u32]) -> Option<Self> {
+        let mut key = self.child(*path.first()?)?;
+        for &index in &path[1..] {
+            key = key.child(index)?;
+        }
+        Some(key)
+    }
+
+    pub fn public_key(&self) -> [u8; 33] {
+        *self.public_key.get_or_init(|| curve::public_key(&self.key))
+    }
+
+    /// The first four bytes of the public key's HASH160, as found in key
+    /// origins and child keys.
+    pub fn fingerprint(&self) -> [u8; 4] {
+        let hash = digest::hash160(&self.public_key());
+        hash[..4].try_into().unwrap()
+    }
+
+    /// The extended public key, base58check-encoded with `version` (see
+    /// [`Network`](crate::network::Network) for those in use).
+    pub fn encode_public(&self, version: [u8; 4]) -> String {
+        base58check(&mut self.serialize(version, &self.public_key()))
+    }
+
+    /// The extended private key, base58check-encoded with `version`.
+    pub fn encode_private(&self, version: [u8; 4]) -> SecretString {
+        let mut key = Zeroizing::new([0; 33]);
+        key[1..].copy_from_slice(&*self.key);
+        let mut data = self.serialize(version, &key);
+        SecretString::new(base58check(&mut data))
+    }
+
+    /// The 78 bytes of BIP32 serialization, with room for the checksum.
+    fn serialize(&self, version: [u8; 4], key: &[u8; 33]) -> Zeroizing<Vec<u8>> {
+        let mut data = Zeroizing::new(Vec::with_capacity(78 + 4));
+        data.extend_from_slice(&version);
+        data.push(self.depth);
+        data.extend_from_slice(&self.parent_fingerprint);
+        data.extend_from_slice(&self.child_number.to_be_bytes());
+        data.extend_from_slice(&*self.chain_code);
+        data.extend_from_slice(key);
+        data
+    }
+}
+
+/// Checks an extended key in BIP32's encoding, public or private, as
+/// BIP32 says to: `versions` are the public and private version bytes it
+/// can have. The error says what's wrong with it.
+pub fn check_encoded(encoded: &str, versions: ([u8; 4], [u8; 4])) -> Result<(), String> {
+    let data = Zeroizing::new(decode_base58check(encoded).ok_or("not base58check-encoded")?);
+    if data.len() != 78 {
+        return Err(format!("{} bytes rather than 78", data.len()));
+    }
+    let (version, depth, parent_fingerprint, child_number, key) =
+        (&data[..4], data[4], &data[5..9], &data[9..13], &data[45..]);
+    if depth == 0 && parent_fingerprint != [0; 4] {
+        return Err("zero depth with a parent fingerprint".to_string());
+    }
+    if depth == 0 && child_number != [0; 4] {
+        return Err("zero depth with a child number".to_string());
+    }
+    let key: &[u8; 33] = key.try_into().unwrap();
+    if version == versions.0 {
+        curve::is_public_key(key).then_some(()).ok_or_else(|| "not a public key".to_string())
+    } else if version == versions.1 {
+        let scalar: &[u8; 32] = key[1..].try_into().unwrap();
+        (key[0] == 0 && curve::is_scalar(scalar)).then_some(()).ok_or_else(|| "not a private key".to_string())
+    } else {
+        Err("unknown version".to_string())
+    }
+}
+
+/// The data of a base58check string, without its checksum.
+pub(crate) fn decode_base58check(text: &str) -> Option<Vec<u8>> {
+    // Base 256 digits, least significant first
+    let mut bytes: Vec<u8> = Vec::with_capacity(text.len());
+    for c in text.bytes() {
+        let mut carry = BASE58.iter().position(|&digit| digit == c)? as u32;
+        for byte in bytes.iter_mut() {
+            carry += (*byte as u32) * 58;
+            *byte = carry as u8;
+            carry >>= 8;
+        }
+        while carry > 0 {
+            bytes.push(carry as u8);
+            carry >>= 8;
+        }
+    }
+    let zeros = text.bytes().take_while(|&c| c == b'1').count();
+    bytes.extend(std::iter::repeat_n(0, zeros));
+    bytes.reverse();
+
+    let data_len = bytes.len().checked_sub(4)?;
+    if digest::sha256d(&bytes[..data_len])[..4] != bytes[data_len..] {
+        return None;
+    }
+    bytes.truncate(data_len);
+    Some(bytes)
+}
+
+/// `data` with its checksum appended, in base58.
+pub(crate) fn base58check(data: &mut Vec<u8>) -> String {
+    let checksum = digest::sha256d(data);
+    data.extend_from_slice(&checksum[..4]);
+    base58(data)
+}
+
+/// `data` in base58, without a checksum.
+pub(crate) fn base58(data: &[u8]) -> String {
+    // Base 58 digits, least significant first
+    let mut digits = Zeroizing::new(Vec::with_capacity(data.len() * 138 / 100 + 1));
+    for &byte in data.iter() {
+        let mut carry = byte as u32;
+        for digit in digits.iter_mut() {
+            carry += (*digit as u32) << 8;
+            *digit = (carry % 58) as u8;
+            carry /= 58;
+        }
+        while carry > 0 {
+            digits.push((carry % 58) as u8);
+            carry /= 58;
+        }
+    }
+    let zeros = data.iter().take_while(|&&byte| byte == 0).count();
+    // Sized up front: an encoded private key mustn't leave copies behind
+    let mut encoded = String::with_capacity(zeros + digits.len());
+    encoded.extend(std::iter::repeat_n('1', zeros));
+    encoded.extend(digits.iter().rev().map(|&digit| BASE58[digit as usize] as char));
+    encoded
+}
@@ -0,0 +1,103 @@
+//! Blocks of whole lines put together from fixed-size reads, for the
+//! readers that keep several reads in flight (io_uring on Linux, overlapped
+//! I/O on Windows).
+//!
+//! Each read lands at [`CARRY_ROOM`] into its buffer. The tail of a block
+//! that is cut mid-line is carried over into that spare room at the front of
+//! the next buffer, which avoids copying whole blocks around.
+
+use crate::pipeline::Chunk;
+
+/// Room reserved in front of each read for the carried-over partial line.
+/// Longer lines fall back to a copy.
+pub const CARRY_ROOM: usize = 64 << 10;
+
+/// Whole lines of the input.
+pub struct Block {
+    first_line: usize,
+    buf: Vec<u8>,
+    start: usize,
+}
+
+impl Chunk for Block {
+    fn first_line(&self) -> usize {
+        self.first_line
+    }
+
+    fn bytes(&self) -> &[u8] {
+        &self.buf[self.start..]
+    }
+}
+
+/// Joins the reads of a file, taken in file order, into blocks.
+pub struct Joiner {
+    file_len: u64,
+    /// Next byte to join.
+    offset: u64,
+    carry: Vec<u8>,
+    next_line: usize,
+}
+
+impl Joiner {
+    /// Numbers lines from `first_line`.
+    pub fn new(file_len: u64, first_line: usize) -> Self {
+        Self {
+            file_len,
+            offset: 0,
+            carry: Vec::new(),
+            next_line: first_line,
+        }
+    }
+
+    /// Where the read to join next starts.
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    /// Whether the whole file has been joined.
+    pub fn is_done(&self) -> bool {
+        self.offset >= self.file_len
+    }
+
+    /// Turns the read at [`offset`](Self::offset), its bytes from
+    /// [`CARRY_ROOM`] on, into a block of whole lines, or returns `None` if
+    /// it held no line end (a line longer than a block).
+    pub fn join(&mut self, mut buf: Vec<u8>) -> Option<Block> {
+        self.offset += (buf.len() - CARRY_ROOM) as u64;
+        let at_end = self.is_done();
+
+        let mut start = CARRY_ROOM;
+        if self.carry.len() <= CARRY_ROOM {
+            start -= self.carry.len();
+            buf[start..CARRY_ROOM].copy_from_slice(&self.carry);
+        } else {
+            let mut joined = std::mem::take(&mut self.carry);
+            joined.extend_from_slice(&buf[CARRY_ROOM..]);
+            buf = joined;
+            start = 0;
+        }
+        self.carry.clear();
+
+        if !at_end {
+            match crate::lines::last_break(&buf[start..]) {
+                Some(end) => {
+                    let cut = start + end;
+                    self.carry.extend_from_slice(&buf[cut..]);
+                    buf.truncate(cut);
+                }
+                None => {
+                    self.carry = buf.split_off(start);
+                    return None;
+                }
+            }
+        }
+
+        let block = Block {
+            first_line: self.next_line,
+            buf,
+            start,
+        };
+        self.next_line += crate::lines::lines(block.bytes()).count();
+        Some(block)
+    }
+}
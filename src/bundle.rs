@@ -0,0 +1,117 @@
+//! Result bundles: a run's hits, what was derived from them and its
+//! manifest in one file sealed to the client's public key, the deliverable
+//! of a recovery (`export --format json-bundle`).
+//!
+//! The file is [`MAGIC`] followed by the bundle's JSON sealed as `--seal-to`
+//! seals phrases (see [`seal`](crate::seal)), so the key pairs of `reveal
+//! --new-key` serve for both, and `reveal` opens either.
+
+use crate::{
+    seal,
+    secret::{SecretString, Zeroize},
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    fs,
+    io::{Read, Write},
+    path::Path,
+};
+
+/// First bytes of a bundle file.
+pub const MAGIC: &[u8; 10] = b"mvbundle1\n";
+
+/// `format` of the bundles written.
+pub const FORMAT: &str = "mnemonic_validator-bundle/1";
+
+/// An address derived from a hit.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Address {
+    /// e.g. `m/84'/0'/0'/0/3`.
+    pub path: String,
+    pub address: String,
+}
+
+/// A line of the run's output, and what was derived from its phrase.
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct Hit {
+    /// Output file it was in.
+    pub file: String,
+    /// The line as written, which may be masked or annotated.
+    pub line: String,
+    /// `None` for masked lines.
+    pub phrase: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fingerprint: Option<String>,
+    /// Receive and change descriptors of the first BIP84 account.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub descriptors: Option<[String; 2]>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub addresses: Vec<Address>,
+}
+
+impl Drop for Hit {
+    fn drop(&mut self) {
+        self.line.zeroize();
+        self.phrase.zeroize();
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Bundle {
+    pub format: String,
+    pub tool: String,
+    pub created_at: String,
+    /// ID of the recorded run, if it was one.
+    pub run: Option<String>,
+    /// Network the derivations are for.
+    pub network: String,
+    /// The run's manifest, as written.
+    pub manifest: Option<serde_json::Value>,
+    pub hits: Vec<Hit>,
+}
+
+/// Writes `bundle` to `path`, sealed to `recipient`.
+pub fn write(path: &Path, bundle: &Bundle, recipient: &[u8; 33]) -> Result<(), String> {
+    let json = SecretString::new(serde_json::to_string_pretty(bundle).map_err(|e| e.to_string())?);
+    let sealed = seal::seal_bytes(recipient, json.as_bytes())?;
+    let mut temporary = path.as_os_str().to_owned();
+    temporary.push(".tmp");
+    let written = fs::File::create(&temporary).and_then(|mut file| {
+        file.write_all(MAGIC)?;
+        file.write_all(&sealed)?;
+        file.sync_all()
+    });
+    written
+        .and_then(|()| fs::rename(&temporary, path))
+        .map_err(|e| format!("can't write {}: {}", path.display(), e))
+}
+
+/// Whether the file at `path` is a bundle.
+pub fn is_bundle(path: &Path) -> bool {
+    let mut start = [0; MAGIC.len()];
+    fs::File::open(path)
+        .and_then(|mut file| file.read_exact(&mut start))
+        .is_ok_and(|()| start == *MAGIC)
+}
+
+/// Opens the bundle at `path` with the recipient's secret key, as JSON.
+pub fn open_json(path: &Path, secret: &[u8; 32]) -> Result<SecretString, String> {
+    let data = fs::read(path).map_err(|e| format!("can't read {}: {}", path.display(), e))?;
+    let sealed = data
+        .strip_prefix(MAGIC.as_slice())
+        .ok_or_else(|| format!("{} isn't a bundle", path.display()))?;
+    let mut json = seal::open_bytes(secret, sealed).map_err(|e| format!("{}: {}", path.display(), e))?;
+    let json = String::from_utf8(std::mem::take(&mut *json)).map_err(|_| format!("{} is damaged", path.display()))?;
+    Ok(SecretString::new(json))
+}
+
+/// Opens and reads the bundle at `path`.
+pub fn open(path: &Path, secret: &[u8; 32]) -> Result<Bundle, String> {
+    let json = open_json(path, secret)?;
+    let bundle: Bundle =
+        serde_json::from_str(&json).map_err(|e| format!("{} isn't a bundle this version reads: {}", path.display(), e))?;
+    if bundle.format != FORMAT {
+        return Err(format!("{} is a bundle of format '{}', not '{}'", path.display(), bundle.format, FORMAT));
+    }
+    Ok(bundle)
+}
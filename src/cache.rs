@@ -0,0 +1,201 @@
+//! What validating a line came to, remembered by a hash of the line, for
+//! `--cache` and `--cache-file`.
+//!
+//! Dumps made from logs repeat the same lines over and over, and a line
+//! that ruled out a valid phrase by its addresses (`--address-prefix`,
+//! `--utxo-set`) cost a round of key derivations each time. With a cache a
+//! repeated line is looked up instead. Only whether the line was a hit is
+//! kept, never the phrase: a hit is validated again to be written.
+//!
+//! The cache holds up to its capacity of lines in memory, the least
+//! recently used going first. Saved to a file, it carries over to later
+//! runs with the same settings; the hashes are salted with those settings,
+//! so a file made under others is started afresh rather than trusted.
+
+use sha2::{Digest, Sha256};
+use std::{
+    collections::HashMap,
+    fs::{self, File},
+    io::{self, BufReader, BufWriter, Read, Write},
+    path::Path,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+    },
+};
+
+/// Lines held when `--cache-file` is given without `--cache`.
+pub const DEFAULT_CAPACITY: usize = 1_000_000;
+/// Locks taken by validator threads, by hash.
+const SHARDS: usize = 16;
+const MAGIC: &[u8; 8] = b"mvcache1";
+/// A hash and an outcome.
+const RECORD: usize = 17;
+
+/// What a line came to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Outcome {
+    /// No valid phrase on it.
+    Invalid,
+    /// This many valid phrases, none with an address the filter wanted.
+    Unmatched(u8),
+    /// A hit.
+    Valid,
+}
+
+impl Outcome {
+    fn to_byte(self) -> u8 {
+        match self {
+            Outcome::Invalid => 0,
+            Outcome::Valid => 1,
+            Outcome::Unmatched(n) => n.saturating_add(2),
+        }
+    }
+
+    fn from_byte(byte: u8) -> Self {
+        match byte {
+            0 => Outcome::Invalid,
+            1 => Outcome::Valid,
+            n => Outcome::Unmatched(n - 2),
+        }
+    }
+}
+
+/// Two generations per shard: lines go into `recent`, and once it's full
+/// `older` is dropped and `recent` takes its place. A line looked up in
+/// `older` moves back to `recent`, so what's dropped is what went unused
+/// the longest.
+#[derive(Default)]
+struct Shard {
+    recent: HashMap<u128, Outcome>,
+    older: HashMap<u128, Outcome>,
+}
+
+pub struct OutcomeCache {
+    salt: [u8; 32],
+    shards: Vec<Mutex<Shard>>,
+    /// Lines per generation of a shard.
+    generation: usize,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+impl OutcomeCache {
+    /// An empty cache for up to `capacity` lines, for runs with `settings`:
+    /// anything that changes what a line comes to.
+    pub fn new(capacity: usize, settings: &[u8]) -> Self {
+        Self {
+            salt: Sha256::digest(settings).into(),
+            shards: (0..SHARDS).map(|_| Mutex::new(Shard::default())).collect(),
+            generation: capacity.div_ceil(2 * SHARDS).max(1),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        }
+    }
+
+    /// The cache saved at `path`, or an empty one if there is none yet or it
+    /// was saved for other settings; the second value says which.
+    pub fn load(path: &Path, capacity: usize, settings: &[u8]) -> Result<(Self, bool), String> {
+        let cache = Self::new(capacity, settings);
+        let file = match File::open(path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok((cache, false)),
+            Err(e) => return Err(format!("can't read cache file '{}': {}", path.display(), e)),
+        };
+        let fail = |e: io::Error| format!("can't read cache file '{}': {}", path.display(), e);
+        let mut reader = BufReader::new(file);
+        let mut header = [0u8; 40];
+        match reader.read_exact(&mut header) {
+            Ok(()) if &header[..8] == MAGIC => {}
+            Ok(()) | Err(_) => return Err(format!("'{}' isn't a cache file", path.display())),
+        }
+        if header[8..] != cache.salt {
+            return Ok((cache, false));
+        }
+        let mut record = [0u8; RECORD];
+        loop {
+            match reader.read_exact(&mut record) {
+                Ok(()) => {}
+                // A file cut short keeps what was whole
+                Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => break,
+                Err(e) => return Err(fail(e)),
+            }
+            let key = u128::from_le_bytes(record[..16].try_into().unwrap());
+            cache.insert(key, Outcome::from_byte(record[16]));
+        }
+        Ok((cache, true))
+    }
+
+    /// Writes the lines held to `path`, most recently used last, so that
+    /// loading it into a smaller cache keeps those.
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let temp = path.with_extension("tmp");
+        let mut writer = BufWriter::new(File::create(&temp)?);
+        writer.write_all(MAGIC)?;
+        writer.write_all(&self.salt)?;
+        for shard in &self.shards {
+            let shard = shard.lock().unwrap();
+            for (key, outcome) in shard.older.iter().chain(&shard.recent) {
+                writer.write_all(&key.to_le_bytes())?;
+                writer.write_all(&[outcome.to_byte()])?;
+            }
+        }
+        writer.into_inner().map_err(|e| e.into_error())?.sync_all()?;
+        fs::rename(&temp, path)
+    }
+
+    /// The key `line` is kept under.
+    pub fn key(&self, line: &str) -> u128 {
+        let hash = Sha256::new().chain_update(self.salt).chain_update(line.as_bytes()).finalize();
+        u128::from_le_bytes(hash[..16].try_into().unwrap())
+    }
+
+    pub fn get(&self, key: u128) -> Option<Outcome> {
+        let mut shard = self.shard(key).lock().unwrap();
+        let outcome = match shard.recent.get(&key) {
+            Some(&outcome) => Some(outcome),
+            None => {
+                let outcome = shard.older.remove(&key);
+                if let Some(outcome) = outcome {
+                    shard.add(key, outcome, self.generation);
+                }
+                outcome
+            }
+        };
+        let counter = if outcome.is_some() { &self.hits } else { &self.misses };
+        counter.fetch_add(1, Ordering::Relaxed);
+        outcome
+    }
+
+    pub fn insert(&self, key: u128, outcome: Outcome) {
+        self.shard(key).lock().unwrap().add(key, outcome, self.generation);
+    }
+
+    /// Lines held.
+    pub fn len(&self) -> usize {
+        let shards = self.shards.iter().map(|shard| shard.lock().unwrap());
+        shards.map(|shard| shard.recent.len() + shard.older.keys().filter(|key| !shard.recent.contains_key(key)).count()).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Lines found in the cache, and lines that weren't.
+    pub fn hits(&self) -> (u64, u64) {
+        (self.hits.load(Ordering::Relaxed), self.misses.load(Ordering::Relaxed))
+    }
+
+    fn shard(&self, key: u128) -> &Mutex<Shard> {
+        &self.shards[key as usize % SHARDS]
+    }
+}
+
+impl Shard {
+    fn add(&mut self, key: u128, outcome: Outcome, generation: usize) {
+        if self.recent.len() >= generation && !self.recent.contains_key(&key) {
+            self.older = std::mem::take(&mut self.recent);
+        }
+        self.recent.insert(key, outcome);
+    }
+}
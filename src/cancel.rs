@@ -0,0 +1,130 @@
+//! Cooperative cancellation for a run: cancel, finish, pause and deadlines.
+//!
+//! A [`CancelToken`] is shared between whoever controls a run (a signal
+//! handler, a GUI button, a binding) and the engine, which looks at it
+//! between batches. Clones share the same state. Pausing parks the
+//! validator threads; the bounded queues then stop the reader as well, so
+//! a paused run holds its place without using CPU.
+//!
+//! Cancelling drops whatever is queued, so the lines validated by then are
+//! scattered. Finishing (as a deadline does) only stops reading: what was
+//! read is still validated and written, and the run ends at a known line
+//! that it can be resumed from.
+
+use std::{
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc, Condvar, Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+/// `deadline` when there is none.
+const NO_DEADLINE: u64 = u64::MAX;
+
+struct State {
+    cancelled: AtomicBool,
+    finishing: AtomicBool,
+    paused: Mutex<bool>,
+    resumed: Condvar,
+    /// Nanoseconds after `created`.
+    deadline: AtomicU64,
+    created: Instant,
+}
+
+#[derive(Clone)]
+pub struct CancelToken {
+    state: Arc<State>,
+}
+
+impl Default for CancelToken {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CancelToken {
+    pub fn new() -> Self {
+        Self {
+            state: Arc::new(State {
+                cancelled: AtomicBool::new(false),
+                finishing: AtomicBool::new(false),
+                paused: Mutex::new(false),
+                resumed: Condvar::new(),
+                deadline: AtomicU64::new(NO_DEADLINE),
+                created: Instant::now(),
+            }),
+        }
+    }
+
+    /// Stops the run; also ends a pause.
+    pub fn cancel(&self) {
+        self.state.cancelled.store(true, Ordering::SeqCst);
+        let _paused = self.state.paused.lock().unwrap();
+        self.state.resumed.notify_all();
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.state.cancelled.load(Ordering::Relaxed)
+    }
+
+    /// Stops reading input; what was read is still validated. Also ends a
+    /// pause, so that the run can wind down.
+    pub fn finish(&self) {
+        self.state.finishing.store(true, Ordering::SeqCst);
+        let _paused = self.state.paused.lock().unwrap();
+        self.state.resumed.notify_all();
+    }
+
+    /// Whether no more input should be read: the run was cancelled or told
+    /// to finish, or its deadline has passed.
+    pub fn is_finishing(&self) -> bool {
+        if self.is_cancelled() || self.state.finishing.load(Ordering::Relaxed) {
+            return true;
+        }
+        let deadline = self.state.deadline.load(Ordering::Relaxed);
+        deadline != NO_DEADLINE && self.state.created.elapsed().as_nanos() >= deadline as u128
+    }
+
+    /// Finishes the run once `deadline` has passed.
+    pub fn set_deadline(&self, deadline: Instant) {
+        let nanos = deadline.saturating_duration_since(self.state.created).as_nanos();
+        self.state
+            .deadline
+            .store(nanos.min(NO_DEADLINE as u128 - 1) as u64, Ordering::Relaxed);
+    }
+
+    /// Finishes the run `timeout` from now.
+    pub fn set_timeout(&self, timeout: Duration) {
+        self.set_deadline(Instant::now() + timeout);
+    }
+
+    pub fn pause(&self) {
+        *self.state.paused.lock().unwrap() = true;
+    }
+
+    pub fn resume(&self) {
+        *self.state.paused.lock().unwrap() = false;
+        self.state.resumed.notify_all();
+    }
+
+    pub fn is_paused(&self) -> bool {
+        *self.state.paused.lock().unwrap()
+    }
+
+    /// Blocks while the run is paused and not finishing. Returns false if it
+    /// was cancelled.
+    pub fn proceed(&self) -> bool {
+        let mut paused = self.state.paused.lock().unwrap();
+        while *paused && !self.is_finishing() {
+            // Wake up now and then for a deadline passing during the pause
+            paused = self
+                .state
+                .resumed
+                .wait_timeout(paused, Duration::from_millis(200))
+                .unwrap()
+                .0;
+        }
+        !self.is_cancelled()
+    }
+}
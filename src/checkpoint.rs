@@ -0,0 +1,223 @@
+//! Checkpoints, and their verification against the input they were taken
+//! from.
+//!
+//! A checkpoint is a small JSON object with a format version and the line
+//! to resume at, so fields can be added without breaking resumes: a newer
+//! field missing from an older file takes its default, and a file written
+//! by a newer version is refused rather than misread. Checkpoints from
+//! before the format was versioned hold a bare line number; they're read as
+//! version 0 and saved in the current format from then on.
+//!
+//! Checkpoints are replaced in one rename, so a crash mid-save leaves the
+//! previous one rather than a truncated file, and a run saves through one
+//! [`Saver`], so its signal handler and the run itself never write at once.
+//!
+//! Next to the checkpoint we keep a fingerprint of the input (size, SHA-256
+//! and sampled line hashes), so a resume into a different or modified file
+//! is caught instead of silently skipping to a meaningless offset.
+
+use crate::scan::{InputScan, LineSample};
+use serde::{Deserialize, Serialize};
+use std::{
+    fs,
+    io::{self, Write},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+/// Format of the checkpoints written by this version.
+pub const VERSION: u32 = 1;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Checkpoint {
+    pub version: u32,
+    /// Lines before this one are done.
+    pub line: usize,
+}
+
+impl Checkpoint {
+    pub fn new(line: usize) -> Self {
+        Self { version: VERSION, line }
+    }
+}
+
+/// Saves a checkpoint at `line`.
+pub fn save(path: &Path, line: usize) -> io::Result<()> {
+    let mut json = serde_json::to_string(&Checkpoint::new(line))?;
+    json.push('\n');
+    write_atomic(path, json.as_bytes())
+}
+
+/// Replaces `path` with `contents`: written and synced to `path.tmp` first,
+/// then renamed over it, so a crash leaves the old file or the new one.
+pub fn write_atomic(path: &Path, contents: &[u8]) -> io::Result<()> {
+    let mut temporary = path.as_os_str().to_os_string();
+    temporary.push(".tmp");
+    let temporary = PathBuf::from(temporary);
+    let mut file = fs::File::create(&temporary)?;
+    file.write_all(contents)?;
+    file.sync_all()?;
+    fs::rename(&temporary, path)
+}
+
+/// The one writer of a run's checkpoint, shared by the run, its signal
+/// handler and its free-space watch.
+#[derive(Clone)]
+pub struct Saver {
+    path: Arc<Path>,
+    /// Set once a save was the last: the process is about to exit.
+    closed: Arc<Mutex<bool>>,
+}
+
+impl Saver {
+    pub fn new(path: &Path) -> Self {
+        Self {
+            path: path.into(),
+            closed: Arc::new(Mutex::new(false)),
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Saves a checkpoint at `line`, unless one was saved as the last.
+    pub fn save(&self, line: usize) -> io::Result<()> {
+        let closed = self.closed.lock().unwrap_or_else(|e| e.into_inner());
+        match *closed {
+            true => Ok(()),
+            false => save(&self.path, line),
+        }
+    }
+
+    /// Saves a checkpoint at `line` and no more after it, for a process
+    /// about to exit: a save still under way elsewhere can't overwrite it.
+    pub fn save_last(&self, line: usize) -> io::Result<()> {
+        let mut closed = self.closed.lock().unwrap_or_else(|e| e.into_inner());
+        if *closed {
+            return Ok(());
+        }
+        *closed = true;
+        save(&self.path, line)
+    }
+}
+
+/// Reads the checkpoint at `path`, in any format so far.
+pub fn load(path: &Path) -> Result<Checkpoint, String> {
+    let text = fs::read_to_string(path).map_err(|e| format!("can't read the checkpoint {}: {}", path.display(), e))?;
+    migrate(&text).map_err(|e| format!("the checkpoint {} {}", path.display(), e))
+}
+
+fn migrate(text: &str) -> Result<Checkpoint, String> {
+    let text = text.trim();
+    // Version 0: the line number alone
+    if let Ok(line) = text.parse() {
+        return Ok(Checkpoint { version: 0, line });
+    }
+    let value: serde_json::Value = serde_json::from_str(text).map_err(|_| "is damaged".to_string())?;
+    match value["version"].as_u64() {
+        Some(version) if version > u64::from(VERSION) => Err(format!(
+            "was written by a newer version of this tool (format {}, this one reads up to {})",
+            version, VERSION
+        )),
+        Some(_) => serde_json::from_value(value).map_err(|e| format!("is damaged: {}", e)),
+        None => Err("is damaged: no format version".to_string()),
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct InputFingerprint {
+    pub path: String,
+    pub bytes: u64,
+    pub sha256: String,
+    pub samples: Vec<LineSample>,
+}
+
+impl InputFingerprint {
+    /// `name` describes the input(s) in messages.
+    pub fn new(name: String, scan: &InputScan) -> Self {
+        Self {
+            path: name,
+            bytes: scan.bytes,
+            sha256: scan.sha256.clone(),
+            samples: scan.samples.clone(),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum Verdict {
+    /// Same file, byte for byte.
+    Unchanged,
+    /// The file changed, but every sampled line before the checkpoint is
+    /// still the same (typically the file was appended to).
+    PrefixIntact,
+    /// A sampled line before the checkpoint differs, or the file is now
+    /// shorter than the checkpoint.
+    Changed { line: usize },
+}
+
+/// `~/.checkpoint.txt` -> `~/.checkpoint.txt.input.json`
+pub fn fingerprint_path(checkpoint_path: &Path) -> PathBuf {
+    let mut name = checkpoint_path.as_os_str().to_os_string();
+    name.push(".input.json");
+    PathBuf::from(name)
+}
+
+pub fn save_fingerprint(checkpoint_path: &Path, fingerprint: &InputFingerprint) -> io::Result<()> {
+    let json = serde_json::to_string_pretty(fingerprint)?;
+    write_atomic(&fingerprint_path(checkpoint_path), json.as_bytes())
+}
+
+/// Returns `None` when no fingerprint was stored (checkpoints written by
+/// older versions) or it can't be parsed.
+pub fn load_fingerprint(checkpoint_path: &Path) -> Option<InputFingerprint> {
+    let json = fs::read_to_string(fingerprint_path(checkpoint_path)).ok()?;
+    serde_json::from_str(&json).ok()
+}
+
+pub fn remove_fingerprint(checkpoint_path: &Path) -> io::Result<()> {
+    match fs::remove_file(fingerprint_path(checkpoint_path)) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+        _ => Ok(()),
+    }
+}
+
+/// `~/.checkpoint.txt` -> `~/.checkpoint.txt.index`, the journal of the
+/// `--incremental` index.
+pub fn index_path(checkpoint_path: &Path) -> PathBuf {
+    let mut name = checkpoint_path.as_os_str().to_os_string();
+    name.push(".index");
+    PathBuf::from(name)
+}
+
+pub fn remove_index(checkpoint_path: &Path) -> io::Result<()> {
+    match fs::remove_file(index_path(checkpoint_path)) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+        _ => Ok(()),
+    }
+}
+
+/// Compares the stored fingerprint with the current input for a resume at
+/// line `checkpoint`.
+pub fn verify(stored: &InputFingerprint, current: &InputScan, checkpoint: usize) -> Verdict {
+    if stored.bytes == current.bytes && stored.sha256 == current.sha256 {
+        return Verdict::Unchanged;
+    }
+    if current.lines < checkpoint {
+        return Verdict::Changed { line: current.lines };
+    }
+
+    for sample in stored.samples.iter().filter(|s| s.line < checkpoint) {
+        let matches = current
+            .samples
+            .iter()
+            .find(|s| s.line == sample.line)
+            .is_some_and(|s| s.sha256 == sample.sha256);
+        if !matches {
+            return Verdict::Changed { line: sample.line };
+        }
+    }
+
+    Verdict::PrefixIntact
+}
@@ -0,0 +1,52 @@
+//! `mnemonic_validator collect`: the other end of `--stream-to`, keeping
+//! the sealed phrases of a fleet's runs in one sealed file, on the one
+//! machine whose storage is looked after, for `reveal`.
+
+use clap::Args;
+use mnemonic_validator::{collector::{self, TlsFiles}, paths};
+use std::{net::TcpListener, path::PathBuf};
+
+#[derive(Args, Debug)]
+pub struct CollectArgs {
+    /// Address to listen on, e.g. 0.0.0.0:7300.
+    #[clap(long, value_name = "ADDR")]
+    listen: String,
+
+    /// The collector's certificate chain (PEM), naming the host the runs connect to.
+    #[clap(long, value_name = "FILE", value_parser = paths::parser())]
+    tls_cert: PathBuf,
+
+    /// The private key of --tls-cert (PEM).
+    #[clap(long, value_name = "FILE", value_parser = paths::parser())]
+    tls_key: PathBuf,
+
+    /// Authority the runs' client certificates have to be issued by (PEM); others are turned away.
+    #[clap(long, value_name = "FILE", value_parser = paths::parser())]
+    client_ca: PathBuf,
+
+    /// Sealed file to append the hits to.
+    #[clap(short, long, value_name = "FILE", value_parser = paths::parser(), default_value = "collected.sealed")]
+    output: PathBuf,
+}
+
+pub fn run(args: &CollectArgs) -> Result<(), Box<dyn std::error::Error>> {
+    if let Some(parent) = args.output.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+        std::fs::create_dir_all(parent)?;
+    }
+    let tls = TlsFiles {
+        cert: &args.tls_cert,
+        key: &args.tls_key,
+        ca: &args.client_ca,
+    }
+    .server_config()?;
+    let listener = TcpListener::bind(&args.listen).map_err(|e| format!("can't listen on {}: {}", args.listen, e))?;
+    println!(
+        "Collecting sealed hits on {} into {}",
+        listener.local_addr()?,
+        args.output.display()
+    );
+    collector::serve(listener, tls, &args.output, |message| {
+        println!("{} {}", chrono::Local::now().format("%Y-%m-%d %H:%M:%S"), message)
+    })?;
+    Ok(())
+}
@@ -0,0 +1,441 @@
+//! Sending the sealed phrases of a fleet's runs to one collector, so that
+//! they don't pile up on the workers.
+//!
+//! A run with `--stream-to` sends each line it would have written to
+//! `<output>.sealed` to a `collect` server instead, which appends it to a
+//! sealed file of its own and acknowledges it once it's on disk. Lines the
+//! collector hasn't acknowledged when the connection breaks, or written
+//! while it can't be reached, are spilled to `<output>.sealed.spill`, and
+//! sent first once it's back, by this run or the next; the spill file goes
+//! once it's delivered. Sealed lines are safe to send and to spill: only
+//! the holder of the key they're sealed to can open them.
+//!
+//! The protocol is a line per hit, answered by `ok` (or `error <reason>`),
+//! over mutual TLS (rustls): the collector only takes connections with a
+//! certificate from the authority it's given, and runs only send to a
+//! collector whose certificate is from theirs and names the host they
+//! connect to.
+
+use crate::seal;
+use rustls::{
+    pki_types::{pem::PemObject, CertificateDer, PrivateKeyDer, ServerName},
+    server::WebPkiClientVerifier,
+    ClientConfig, ClientConnection, RootCertStore, ServerConfig, ServerConnection, StreamOwned,
+};
+use sha2::{Digest, Sha256};
+use std::{
+    collections::HashSet,
+    fs::{self, File, OpenOptions},
+    io::{self, BufRead, BufReader, Read, Write},
+    net::{TcpListener, TcpStream, ToSocketAddrs},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    thread,
+    time::{Duration, Instant},
+};
+
+/// Lines sent ahead of the collector's acknowledgements.
+const MAX_PENDING: usize = 256;
+/// Time between attempts to reach a collector that went away.
+const RETRY: Duration = Duration::from_secs(5);
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+/// A collector that takes longer than this to acknowledge is given up on.
+const ACK_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// The PEM files of one end of the connection: its certificate chain and
+/// private key, and the authority the other end's certificate has to be
+/// issued by.
+pub struct TlsFiles<'a> {
+    pub cert: &'a Path,
+    pub key: &'a Path,
+    pub ca: &'a Path,
+}
+
+impl TlsFiles<'_> {
+    fn certificates(&self) -> io::Result<Vec<CertificateDer<'static>>> {
+        let certs = CertificateDer::pem_file_iter(self.cert)
+            .and_then(|certs| certs.collect::<Result<Vec<_>, _>>())
+            .map_err(|e| io::Error::other(format!("can't read certificates from {}: {}", self.cert.display(), e)))?;
+        match certs.is_empty() {
+            true => Err(io::Error::other(format!("no certificate in {}", self.cert.display()))),
+            false => Ok(certs),
+        }
+    }
+
+    fn private_key(&self) -> io::Result<PrivateKeyDer<'static>> {
+        PrivateKeyDer::from_pem_file(self.key)
+            .map_err(|e| io::Error::other(format!("can't read a private key from {}: {}", self.key.display(), e)))
+    }
+
+    fn roots(&self) -> io::Result<RootCertStore> {
+        let mut roots = RootCertStore::empty();
+        let certs = CertificateDer::pem_file_iter(self.ca)
+            .and_then(|certs| certs.collect::<Result<Vec<_>, _>>())
+            .map_err(|e| io::Error::other(format!("can't read certificates from {}: {}", self.ca.display(), e)))?;
+        let (added, _) = roots.add_parsable_certificates(certs);
+        match added {
+            0 => Err(io::Error::other(format!("no usable certificate in {}", self.ca.display()))),
+            _ => Ok(roots),
+        }
+    }
+
+    /// The collector's side: its certificate, and client certificates
+    /// required and checked against the authority.
+    pub fn server_config(&self) -> io::Result<Arc<ServerConfig>> {
+        let verifier = WebPkiClientVerifier::builder(Arc::new(self.roots()?))
+            .build()
+            .map_err(io::Error::other)?;
+        let config = ServerConfig::builder()
+            .with_client_cert_verifier(verifier)
+            .with_single_cert(self.certificates()?, self.private_key()?)
+            .map_err(|e| io::Error::other(format!("{} doesn't go with {}: {}", self.key.display(), self.cert.display(), e)))?;
+        Ok(Arc::new(config))
+    }
+
+    /// A run's side: the collector's certificate checked against the
+    /// authority, and the run's own presented.
+    pub fn client_config(&self) -> io::Result<Arc<ClientConfig>> {
+        let config = ClientConfig::builder()
+            .with_root_certificates(self.roots()?)
+            .with_client_auth_cert(self.certificates()?, self.private_key()?)
+            .map_err(|e| io::Error::other(format!("{} doesn't go with {}: {}", self.key.display(), self.cert.display(), e)))?;
+        Ok(Arc::new(config))
+    }
+}
+
+/// `output/valid_mnemonics.txt` -> `output/valid_mnemonics.txt.sealed.spill`
+pub fn spill_path(output: &Path) -> PathBuf {
+    let mut path = seal::sealed_path(output).into_os_string();
+    path.push(".spill");
+    PathBuf::from(path)
+}
+
+/// Lines waiting in the spill file `spill` for the collector.
+pub fn spilled(spill: &Path) -> io::Result<usize> {
+    match File::open(spill) {
+        Ok(file) => Ok(BufReader::new(file).split(b'\n').count()),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(0),
+        Err(e) => Err(e),
+    }
+}
+
+/// The sealed file of a run that streams to a collector. Written a line at
+/// a time; a flush returns once every line is with the collector, or
+/// spilled.
+pub struct Uplink {
+    addr: String,
+    tls: Arc<ClientConfig>,
+    spill: PathBuf,
+    conn: Option<BufReader<StreamOwned<ClientConnection, TcpStream>>>,
+    last_attempt: Option<Instant>,
+    last_error: Option<io::Error>,
+    /// What was written after the last complete line.
+    partial: Vec<u8>,
+    /// Sent, and not acknowledged yet.
+    pending: Vec<Vec<u8>>,
+}
+
+impl Uplink {
+    /// Connects to the collector at `addr` (host:port) with `tls` and sends
+    /// it what an earlier run left in `spill`. One that can't be reached yet
+    /// isn't an error: lines are spilled until it can.
+    pub fn open(addr: &str, tls: Arc<ClientConfig>, spill: PathBuf) -> Self {
+        let mut uplink = Self {
+            addr: addr.to_string(),
+            tls,
+            spill,
+            conn: None,
+            last_attempt: None,
+            last_error: None,
+            partial: Vec::new(),
+            pending: Vec::new(),
+        };
+        uplink.reconnect();
+        uplink
+    }
+
+    pub fn is_connected(&self) -> bool {
+        self.conn.is_some()
+    }
+
+    /// Why the collector couldn't be reached last time.
+    pub fn last_error(&self) -> Option<&io::Error> {
+        self.last_error.as_ref()
+    }
+
+    pub fn spill_path(&self) -> &Path {
+        &self.spill
+    }
+
+    /// Connected, or connects if it's been a while since the last attempt,
+    /// sending the spill file first.
+    fn reconnect(&mut self) -> bool {
+        if self.conn.is_some() {
+            return true;
+        }
+        if self.last_attempt.is_some_and(|last| last.elapsed() < RETRY) {
+            return false;
+        }
+        self.last_attempt = Some(Instant::now());
+        match self.connect().and_then(|()| self.replay()) {
+            Ok(()) => {
+                self.last_error = None;
+                true
+            }
+            Err(e) => {
+                self.conn = None;
+                self.last_error = Some(e);
+                false
+            }
+        }
+    }
+
+    fn connect(&mut self) -> io::Result<()> {
+        // The collector's certificate has to name the host as given
+        let host = self.addr.rsplit_once(':').map_or(self.addr.as_str(), |(host, _)| host);
+        let name = ServerName::try_from(host.trim_start_matches('[').trim_end_matches(']').to_string())
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, format!("{} isn't a host name", host)))?;
+        let mut last = io::Error::new(io::ErrorKind::NotFound, format!("{} resolves to no address", self.addr));
+        for addr in self.addr.to_socket_addrs()? {
+            match TcpStream::connect_timeout(&addr, CONNECT_TIMEOUT) {
+                Ok(mut stream) => {
+                    stream.set_read_timeout(Some(ACK_TIMEOUT))?;
+                    stream.set_nodelay(true)?;
+                    // Handshake now, so that a certificate refused either
+                    // way is an error here
+                    let mut tls = ClientConnection::new(self.tls.clone(), name.clone()).map_err(io::Error::other)?;
+                    while tls.is_handshaking() {
+                        tls.complete_io(&mut stream)?;
+                    }
+                    self.conn = Some(BufReader::new(StreamOwned::new(tls, stream)));
+                    return Ok(());
+                }
+                Err(e) => last = e,
+            }
+        }
+        Err(last)
+    }
+
+    /// Sends what's been spilled, then drops the spill file. Lines of it
+    /// sent again after a lost acknowledgement are taken only once.
+    fn replay(&mut self) -> io::Result<()> {
+        let file = match File::open(&self.spill) {
+            Ok(file) => file,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(()),
+            Err(e) => return Err(e),
+        };
+        let conn = self.conn.as_mut().unwrap();
+        let mut count = 0;
+        for line in BufReader::new(file).split(b'\n') {
+            let mut line = line?;
+            line.push(b'\n');
+            conn.get_mut().write_all(&line)?;
+            count += 1;
+            if count == MAX_PENDING {
+                read_acks(conn, count)?;
+                count = 0;
+            }
+        }
+        read_acks(conn, count)?;
+        fs::remove_file(&self.spill)
+    }
+
+    fn send(&mut self, line: Vec<u8>) -> io::Result<()> {
+        if !self.reconnect() {
+            return self.append_to_spill(&[line]);
+        }
+        let conn = self.conn.as_mut().unwrap().get_mut();
+        let sent = conn.write_all(&line).and_then(|()| conn.flush());
+        self.pending.push(line);
+        match sent {
+            Ok(()) if self.pending.len() < MAX_PENDING => Ok(()),
+            Ok(()) => self.await_acks(),
+            Err(e) => self.disconnect(e),
+        }
+    }
+
+    /// Waits for the collector to acknowledge what's pending, and spills it
+    /// if it doesn't.
+    fn await_acks(&mut self) -> io::Result<()> {
+        let Some(conn) = self.conn.as_mut() else {
+            return Ok(());
+        };
+        match read_acks(conn, self.pending.len()) {
+            Ok(()) => {
+                self.pending.clear();
+                Ok(())
+            }
+            Err(e) => self.disconnect(e),
+        }
+    }
+
+    fn disconnect(&mut self, e: io::Error) -> io::Result<()> {
+        self.conn = None;
+        self.last_error = Some(e);
+        let pending = std::mem::take(&mut self.pending);
+        self.append_to_spill(&pending)
+    }
+
+    fn append_to_spill(&mut self, lines: &[Vec<u8>]) -> io::Result<()> {
+        if lines.is_empty() {
+            return Ok(());
+        }
+        let mut file = OpenOptions::new().create(true).append(true).open(&self.spill)?;
+        file.write_all(&lines.concat())?;
+        file.sync_data()
+    }
+}
+
+fn read_acks(reader: &mut impl BufRead, count: usize) -> io::Result<()> {
+    let mut reply = String::new();
+    for _ in 0..count {
+        reply.clear();
+        if reader.read_line(&mut reply)? == 0 {
+            return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "the collector closed the connection"));
+        }
+        if reply.trim_end() != "ok" {
+            return Err(io::Error::other(format!("the collector says: {}", reply.trim_end())));
+        }
+    }
+    Ok(())
+}
+
+impl Write for Uplink {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.partial.extend_from_slice(buf);
+        while let Some(end) = self.partial.iter().position(|&b| b == b'\n') {
+            let line = self.partial.drain(..=end).collect();
+            self.send(line)?;
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        if self.conn.is_some() {
+            self.await_acks()
+        } else {
+            // Sends the spill file if the collector is back
+            self.reconnect();
+            Ok(())
+        }
+    }
+}
+
+impl Drop for Uplink {
+    /// Tells the collector the run is done, so it doesn't take the
+    /// connection for a lost one.
+    fn drop(&mut self) {
+        if let Some(conn) = self.conn.as_mut() {
+            let tls = conn.get_mut();
+            tls.conn.send_close_notify();
+            let _ = tls.flush();
+        }
+    }
+}
+
+/// What the collector holds: its sealed file, and a digest of each of its
+/// lines to know the ones sent again.
+struct Store {
+    file: File,
+    seen: HashSet<[u8; 32]>,
+}
+
+/// Takes the sealed lines of the runs connecting to `listener` with `tls`
+/// and appends them to `output`, acknowledging each once it's on disk.
+/// `log` is told of connections coming and going.
+pub fn serve(listener: TcpListener, tls: Arc<ServerConfig>, output: &Path, log: fn(&str)) -> io::Result<()> {
+    let mut seen = HashSet::new();
+    match File::open(output) {
+        Ok(file) => {
+            for line in BufReader::new(file).split(b'\n') {
+                seen.insert(Sha256::digest(line?).into());
+            }
+        }
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+        Err(e) => return Err(e),
+    }
+    let file = OpenOptions::new().create(true).append(true).open(output)?;
+    let store = Arc::new(Mutex::new(Store { file, seen }));
+    for stream in listener.incoming() {
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(e) => {
+                log(&format!("Failed to accept a connection: {}", e));
+                continue;
+            }
+        };
+        let store = store.clone();
+        let tls = tls.clone();
+        thread::spawn(move || {
+            let peer = stream.peer_addr().map_or_else(|_| "?".to_string(), |peer| peer.to_string());
+            log(&format!("{} connected", peer));
+            let received = ServerConnection::new(tls)
+                .map_err(io::Error::other)
+                .and_then(|tls| receive(BufReader::new(StreamOwned::new(tls, stream)), &store));
+            match received {
+                Ok((taken, repeated)) => log(&format!(
+                    "{} disconnected after sending {} hit(s){}",
+                    peer,
+                    taken,
+                    match repeated {
+                        0 => String::new(),
+                        n => format!(" and {} already held", n),
+                    }
+                )),
+                Err(e) => log(&format!("{} lost: {}", peer, e)),
+            }
+        });
+    }
+    Ok(())
+}
+
+/// Takes the lines of one connection until it closes; the lines that came
+/// in together are written and synced together. The TLS handshake, and
+/// with it the check of the run's certificate, happens on the first read.
+fn receive<S: Read + Write>(mut reader: BufReader<S>, store: &Mutex<Store>) -> io::Result<(usize, usize)> {
+    let (mut taken, mut repeated) = (0, 0);
+    let mut line = String::new();
+    loop {
+        let mut lines = Vec::new();
+        let mut replies = String::new();
+        loop {
+            line.clear();
+            if reader.read_line(&mut line)? == 0 {
+                break;
+            }
+            match seal::split_line(&line) {
+                Some(_) if line.ends_with('\n') => {
+                    lines.push(line.trim_end().to_string());
+                    replies.push_str("ok\n");
+                }
+                _ => replies.push_str("error not a sealed line\n"),
+            }
+            if !reader.buffer().contains(&b'\n') {
+                break;
+            }
+        }
+        if replies.is_empty() {
+            return Ok((taken, repeated));
+        }
+        {
+            let mut store = store.lock().unwrap();
+            let mut new = String::new();
+            for line in &lines {
+                if store.seen.insert(Sha256::digest(line).into()) {
+                    new.push_str(line);
+                    new.push('\n');
+                    taken += 1;
+                } else {
+                    repeated += 1;
+                }
+            }
+            if !new.is_empty() {
+                store.file.write_all(new.as_bytes())?;
+                store.file.sync_data()?;
+            }
+        }
+        let writer = reader.get_mut();
+        writer.write_all(replies.as_bytes())?;
+        writer.flush()?;
+    }
+}
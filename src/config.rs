@@ -0,0 +1,239 @@
+//! The engine's settings in one place: threads, reads and queues, memory
+//! and CPU limits, long lines, the outcome cache.
+//!
+//! [`EngineConfig`] is flattened into the command line's options (with the
+//! `cli` feature, on by default), so a `--job` file sets it with the same
+//! keys, and it (de)serializes with those keys too: a daemon descriptor's
+//! `"engine"`, the GUI's saved settings and a run's manifest all hold it as
+//! it is, and the GUI and the Node addon run with it through
+//! [`engine`](crate::engine). An option added here is there in every front
+//! end at once.
+//!
+//! Sizes are byte counts, or text as on the command line (`"2G"`); the CPU
+//! share is a fraction, or a percentage (`"50%"`).
+
+use crate::{
+    memory,
+    pipeline::{LongLines, Oversize},
+    throttle::{self, Throttle},
+};
+#[cfg(feature = "cli")]
+use clap::Args;
+use serde::{de, Deserialize, Deserializer, Serialize};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "cli", derive(Args))]
+#[serde(rename_all = "kebab-case", default, deny_unknown_fields)]
+pub struct EngineConfig {
+    /// Number of validator threads (default: one per CPU, or as many as the storage keeps busy).
+    #[cfg_attr(feature = "cli", clap(long, value_name = "N", value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..)))]
+    pub threads: Option<usize>,
+
+    /// Bytes per read when the input isn't memory-mapped (--max-memory, --io-uring), e.g. 4M; tuned to the storage by default.
+    #[cfg_attr(feature = "cli", clap(long, value_name = "SIZE", value_parser = parse_block_size))]
+    #[serde(deserialize_with = "block_size")]
+    pub block_size: Option<usize>,
+
+    /// Chunks of lines queued between the reader and the validators; twice the threads by default.
+    #[cfg_attr(feature = "cli", clap(long, value_name = "N", value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..)))]
+    pub queue_depth: Option<usize>,
+
+    /// Lines per chunk handed to a validator from a memory-mapped input; tuned to the validation speed by default.
+    #[cfg_attr(feature = "cli", clap(long, value_name = "N", value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..)))]
+    pub chunk_lines: Option<usize>,
+
+    /// Don't time the storage and validation before the run; use fixed defaults for what isn't set.
+    #[cfg_attr(feature = "cli", clap(long))]
+    pub no_auto_tune: bool,
+
+    /// Let the validator threads use at most this share of their CPU time, e.g. 50% or 0.5.
+    #[cfg_attr(feature = "cli", clap(long, value_name = "SHARE", value_parser = throttle::parse_share))]
+    #[serde(deserialize_with = "share")]
+    pub max_cpu: Option<f64>,
+
+    /// Keep memory use under SIZE (e.g. 2G or 512M) by shrinking internal queues and batches, and bounding the cache, --incremental index, --duplicates and --group-by.
+    #[cfg_attr(feature = "cli", clap(long, value_name = "SIZE", value_parser = memory::parse_size))]
+    #[serde(deserialize_with = "optional_size")]
+    pub max_memory: Option<u64>,
+
+    /// Lines longer than SIZE (e.g. 64K) are handled as --long-lines says instead of being validated whole; 0 turns the limit off.
+    #[cfg_attr(feature = "cli", clap(long, value_name = "SIZE", value_parser = memory::parse_size, default_value = MAX_LINE_LENGTH))]
+    #[serde(deserialize_with = "size")]
+    pub max_line_length: u64,
+
+    /// What happens to a line longer than --max-line-length: skip it, or truncate it and validate what's left.
+    #[cfg_attr(feature = "cli", clap(long, value_name = "skip|truncate", default_value = LONG_LINES))]
+    pub long_lines: Oversize,
+
+    /// Refuse to start, and pause once running, while the output or checkpoint volume has less than SIZE free; 0 turns the check off.
+    #[cfg_attr(feature = "cli", clap(long, value_name = "SIZE", value_parser = memory::parse_size, default_value = MIN_FREE_SPACE))]
+    #[serde(deserialize_with = "size")]
+    pub min_free_space: u64,
+
+    /// Read the input with several reads in flight instead of memory-mapping it: io_uring on Linux builds with the `io-uring` feature, overlapped I/O on Windows.
+    #[cfg_attr(feature = "cli", clap(long, alias = "overlapped"))]
+    pub io_uring: bool,
+
+    /// Remember what up to N lines came to, so a line repeated in the input is looked up rather than validated again; pays off most with address filters.
+    #[cfg_attr(feature = "cli", clap(long, value_name = "N", value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..)))]
+    pub cache: Option<usize>,
+
+    /// Keep the --cache in FILE between runs with the same settings (1000000 lines unless --cache says otherwise).
+    #[cfg_attr(feature = "cli", clap(long, value_name = "FILE", value_parser = crate::paths::parser()))]
+    pub cache_file: Option<PathBuf>,
+}
+
+// Defaults, as the command line shows them.
+const MAX_LINE_LENGTH: &str = "64K";
+const LONG_LINES: &str = "skip";
+const MIN_FREE_SPACE: &str = "64M";
+
+/// The command line's defaults, from the same constants.
+impl Default for EngineConfig {
+    fn default() -> Self {
+        Self {
+            threads: None,
+            block_size: None,
+            queue_depth: None,
+            chunk_lines: None,
+            no_auto_tune: false,
+            max_cpu: None,
+            max_memory: None,
+            max_line_length: memory::parse_size(MAX_LINE_LENGTH).expect("valid default"),
+            long_lines: LONG_LINES.parse().expect("valid default"),
+            min_free_space: memory::parse_size(MIN_FREE_SPACE).expect("valid default"),
+            io_uring: false,
+            cache: None,
+            cache_file: None,
+        }
+    }
+}
+
+impl EngineConfig {
+    /// The options that set this, left out where they'd give the default:
+    /// for starting a run of its own, or writing a command line down.
+    pub fn to_args(&self) -> Vec<String> {
+        let default = Self::default();
+        let values = [
+            ("threads", self.threads.map(|n| n.to_string())),
+            ("block-size", self.block_size.map(|n| n.to_string())),
+            ("queue-depth", self.queue_depth.map(|n| n.to_string())),
+            ("chunk-lines", self.chunk_lines.map(|n| n.to_string())),
+            ("max-cpu", self.max_cpu.map(|share| share.to_string())),
+            ("max-memory", self.max_memory.map(|n| n.to_string())),
+            ("cache", self.cache.map(|n| n.to_string())),
+            ("cache-file", self.cache_file.as_ref().map(|path| path.display().to_string())),
+            (
+                "max-line-length",
+                (self.max_line_length != default.max_line_length).then(|| self.max_line_length.to_string()),
+            ),
+            ("long-lines", (self.long_lines != default.long_lines).then(|| self.long_lines.name().to_string())),
+            (
+                "min-free-space",
+                (self.min_free_space != default.min_free_space).then(|| self.min_free_space.to_string()),
+            ),
+        ];
+        let flags = [("no-auto-tune", self.no_auto_tune), ("io-uring", self.io_uring)];
+
+        let mut args = Vec::new();
+        for (name, value) in values {
+            if let Some(value) = value {
+                args.push(format!("--{}", name));
+                args.push(value);
+            }
+        }
+        for (name, set) in flags {
+            if set {
+                args.push(format!("--{}", name));
+            }
+        }
+        args
+    }
+
+    /// The throttle for `max_cpu`, if it's set.
+    pub fn throttle(&self) -> Option<Arc<Throttle>> {
+        self.max_cpu.map(|share| Arc::new(Throttle::new(share)))
+    }
+
+    /// The line length limit, unless it's off.
+    pub fn long_lines(&self) -> Option<Arc<LongLines>> {
+        (self.max_line_length > 0).then(|| Arc::new(LongLines::new(self.max_line_length as usize, self.long_lines)))
+    }
+
+    /// Reads settings saved by [`save`](EngineConfig::save).
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let text = fs::read_to_string(path)
+            .map_err(|e| format!("can't read settings '{}': {}", path.display(), e))?;
+        serde_json::from_str(&text).map_err(|e| format!("settings '{}' aren't valid: {}", path.display(), e))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            fs::create_dir_all(dir).map_err(|e| format!("can't create '{}': {}", dir.display(), e))?;
+        }
+        let json = serde_json::to_string_pretty(self).expect("settings serialize");
+        fs::write(path, json).map_err(|e| format!("can't write settings '{}': {}", path.display(), e))
+    }
+}
+
+/// `~/.config/mnemonic_validator/gui.json` on Linux.
+pub fn default_gui_settings_path() -> Option<PathBuf> {
+    Some(dirs::config_dir()?.join("mnemonic_validator").join("gui.json"))
+}
+
+/// A `--block-size`, which reads can't go below.
+pub fn parse_block_size(s: &str) -> Result<usize, String> {
+    let size = memory::parse_size(s)?;
+    if size < memory::MIN_BLOCK_SIZE as u64 {
+        return Err(format!("blocks must be at least {}", memory::format_size(memory::MIN_BLOCK_SIZE as u64)));
+    }
+    usize::try_from(size).map_err(|_| format!("'{}' is too large", s))
+}
+
+/// A setting given as a number, or as text the way the command line takes it.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Given<T> {
+    Value(T),
+    Text(String),
+}
+
+fn size<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u64, D::Error> {
+    match Given::<u64>::deserialize(deserializer)? {
+        Given::Value(bytes) => Ok(bytes),
+        Given::Text(text) => memory::parse_size(&text).map_err(de::Error::custom),
+    }
+}
+
+fn optional_size<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<u64>, D::Error> {
+    #[derive(Deserialize)]
+    struct Size(#[serde(deserialize_with = "size")] u64);
+    Ok(Option::<Size>::deserialize(deserializer)?.map(|Size(bytes)| bytes))
+}
+
+fn block_size<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<usize>, D::Error> {
+    let Some(given) = Option::<Given<u64>>::deserialize(deserializer)? else {
+        return Ok(None);
+    };
+    let text = match given {
+        Given::Value(bytes) => bytes.to_string(),
+        Given::Text(text) => text,
+    };
+    parse_block_size(&text).map(Some).map_err(de::Error::custom)
+}
+
+fn share<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<f64>, D::Error> {
+    let Some(given) = Option::<Given<f64>>::deserialize(deserializer)? else {
+        return Ok(None);
+    };
+    let text = match given {
+        Given::Value(share) => share.to_string(),
+        Given::Text(text) => text,
+    };
+    throttle::parse_share(&text).map(Some).map_err(de::Error::custom)
+}
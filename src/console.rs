@@ -0,0 +1,172 @@
+//! Console output and shutdown signals, the same on every platform.
+//!
+//! The progress line is redrawn in place with ANSI escapes where the console
+//! understands them. On Windows that needs virtual terminal processing,
+//! which older consoles (and cmd.exe before Windows 10) don't have; there
+//! the line is overwritten with a carriage return and padding instead. When
+//! stdout isn't a terminal at all (systemd, CI, a redirect to a file) the
+//! progress becomes a timestamped log line every half minute, with no
+//! carriage returns or escape codes.
+//!
+//! The exit handler runs on Ctrl+C everywhere, on Ctrl+Break and when the
+//! console window is closed (or the user logs off) on Windows, and on
+//! SIGTERM and SIGHUP elsewhere. Only the Windows events leave no time to
+//! wind down.
+
+use std::{
+    io::{self, IsTerminal, Write},
+    sync::Arc,
+    time::Duration,
+};
+
+/// How often a progress line redrawn in place is updated.
+const REDRAW_INTERVAL: Duration = Duration::from_secs(3);
+/// How often progress is logged when stdout isn't a terminal.
+const LOG_INTERVAL: Duration = Duration::from_secs(30);
+
+enum Mode {
+    /// `\r` and erase-line.
+    Ansi,
+    /// `\r` and spaces over whatever is left of the previous line.
+    Pad,
+    /// One timestamped line per update.
+    Lines,
+}
+
+/// A status line that is redrawn in place, or logged.
+pub struct Progress {
+    mode: Mode,
+    /// Length of the line shown last.
+    shown: usize,
+}
+
+impl Progress {
+    pub fn new() -> Self {
+        let mode = if !io::stdout().is_terminal() {
+            Mode::Lines
+        } else if enable_ansi() {
+            Mode::Ansi
+        } else {
+            Mode::Pad
+        };
+        Self { mode, shown: 0 }
+    }
+
+    pub fn show(&mut self, line: &str) {
+        let mut out = io::stdout().lock();
+        let _ = match self.mode {
+            Mode::Ansi => write!(out, "\r\x1B[K{}", line),
+            Mode::Pad => write!(out, "\r{:<width$}", line, width = self.shown),
+            Mode::Lines => {
+                let now = chrono::Local::now().format("%Y-%m-%d %H:%M:%S");
+                writeln!(out, "{} {}", now, line)
+            }
+        };
+        let _ = out.flush();
+        self.shown = line.chars().count();
+    }
+
+    /// Time between updates worth showing.
+    pub fn interval(&self) -> Duration {
+        match self.mode {
+            Mode::Lines => LOG_INTERVAL,
+            Mode::Ansi | Mode::Pad => REDRAW_INTERVAL,
+        }
+    }
+
+    /// Moves past a line that was drawn in place, so regular output can
+    /// follow.
+    pub fn finish(&mut self) {
+        if !matches!(self.mode, Mode::Lines) && self.shown > 0 {
+            println!();
+        }
+        self.shown = 0;
+    }
+}
+
+#[cfg(windows)]
+fn enable_ansi() -> bool {
+    use windows_sys::Win32::System::Console::{
+        GetConsoleMode, GetStdHandle, SetConsoleMode, ENABLE_VIRTUAL_TERMINAL_PROCESSING,
+        STD_OUTPUT_HANDLE,
+    };
+
+    // Safety: plain calls on the process's own stdout handle
+    unsafe {
+        let handle = GetStdHandle(STD_OUTPUT_HANDLE);
+        let mut mode = 0;
+        if GetConsoleMode(handle, &mut mode) == 0 {
+            return false;
+        }
+        mode & ENABLE_VIRTUAL_TERMINAL_PROCESSING != 0
+            || SetConsoleMode(handle, mode | ENABLE_VIRTUAL_TERMINAL_PROCESSING) != 0
+    }
+}
+
+#[cfg(not(windows))]
+fn enable_ansi() -> bool {
+    std::env::var_os("TERM").is_none_or(|term| term != "dumb")
+}
+
+/// How long the process has once asked to stop.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stop {
+    /// Ctrl+C, Ctrl+Break, SIGTERM, SIGHUP: as long as it takes.
+    Requested,
+    /// Windows ends the process as soon as the handler returns.
+    Imminent,
+}
+
+/// Runs `handler` when the user or the system asks the process to stop,
+/// every time they do. Unless it exits the process, Windows ends it anyway
+/// after a console close, logoff or shutdown event.
+pub fn on_exit(handler: impl Fn(Stop) + Send + Sync + 'static) -> Result<(), ctrlc::Error> {
+    let handler: Arc<dyn Fn(Stop) + Send + Sync> = Arc::new(handler);
+    ctrlc::set_handler({
+        let handler = handler.clone();
+        move || handler(Stop::Requested)
+    })?;
+    #[cfg(windows)]
+    windows::on_close(handler);
+    Ok(())
+}
+
+/// Windows terminates a process as soon as its handler for a close, logoff
+/// or shutdown event returns, so those can't be left to ctrlc's handler
+/// thread: the checkpoint is written from within the event handler itself.
+#[cfg(windows)]
+mod windows {
+    use super::Stop;
+    use std::sync::{Arc, OnceLock};
+    use windows_sys::Win32::{
+        Foundation::{BOOL, FALSE, TRUE},
+        System::Console::{
+            SetConsoleCtrlHandler, CTRL_CLOSE_EVENT, CTRL_LOGOFF_EVENT, CTRL_SHUTDOWN_EVENT,
+        },
+    };
+
+    static HANDLER: OnceLock<Arc<dyn Fn(Stop) + Send + Sync>> = OnceLock::new();
+
+    unsafe extern "system" fn on_event(event: u32) -> BOOL {
+        match event {
+            CTRL_CLOSE_EVENT | CTRL_LOGOFF_EVENT | CTRL_SHUTDOWN_EVENT => {
+                if let Some(handler) = HANDLER.get() {
+                    handler(Stop::Imminent);
+                }
+                TRUE
+            }
+            // Ctrl+C and Ctrl+Break go on to ctrlc's handler
+            _ => FALSE,
+        }
+    }
+
+    pub fn on_close(handler: Arc<dyn Fn(Stop) + Send + Sync>) {
+        if HANDLER.set(handler).is_ok() {
+            // Safety: `on_event` only reads the static set above. Handlers
+            // added later run first, so this one sees events before ctrlc's.
+            unsafe {
+                SetConsoleCtrlHandler(Some(on_event), TRUE);
+            }
+        }
+    }
+}
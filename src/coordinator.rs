@@ -0,0 +1,332 @@
+//! `mnemonic_validator coordinator`: splitting inputs into shards for a
+//! fleet of `worker --coordinator` processes, and following them on a web
+//! dashboard.
+//!
+//! Workers ask for a shard, report their progress every few seconds, and
+//! say how their run of it ended; everything goes over a small JSON API,
+//! and the book of shards is saved after every change, so a coordinator
+//! started again carries on. The dashboard and `/api/status` show progress
+//! and counts, never phrases: those stay in the workers' outputs.
+//!
+//! Everything is served by `axum` on a runtime of [`THREADS`] threads, so
+//! connections don't cost a thread each, and with a token every route asks
+//! for it.
+
+use crate::report::escape;
+use axum::{
+    body::Bytes,
+    extract::{DefaultBodyLimit, Path, Request, State},
+    http::{
+        header::{AUTHORIZATION, CONTENT_TYPE},
+        StatusCode,
+    },
+    middleware::{self, Next},
+    response::{Html, IntoResponse, Response},
+    routing::{get, post},
+    Router,
+};
+use clap::Args;
+use mnemonic_validator::{
+    eta::format_duration,
+    http, paths,
+    shards::{Book, Next as Shard, Status},
+};
+use serde::Deserialize;
+use serde_json::json;
+use std::{
+    fmt::Write as _,
+    path::PathBuf,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+/// Seconds a worker with nothing to take waits before asking again.
+const WAIT: u64 = 10;
+/// Threads answering requests, and threads saving the book of shards.
+const THREADS: usize = 4;
+
+#[derive(Args, Debug)]
+pub struct CoordinatorArgs {
+    /// Input files to split into shards; their paths must be the same on the workers (shared storage).
+    #[clap(short, long = "input", value_name = "FILE", value_parser = paths::parser(), num_args = 1..)]
+    inputs: Vec<PathBuf>,
+
+    /// Address to serve the workers' API and the dashboard on.
+    #[clap(long, value_name = "ADDR", default_value = "127.0.0.1:7400")]
+    listen: String,
+
+    /// Lines per shard.
+    #[clap(long, value_name = "N", default_value_t = 5_000_000, value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..))]
+    shard_lines: usize,
+
+    /// Seconds without a report after which a worker's shard is given to another.
+    #[clap(long, value_name = "SECS", default_value_t = 120)]
+    lease: u64,
+
+    /// File keeping the shards and their progress; a coordinator started with an existing one carries on from it.
+    #[clap(long, value_name = "FILE", value_parser = paths::parser(), default_value = "coordinator.json")]
+    state: PathBuf,
+
+    /// Token the workers must send, and the dashboard be opened with as `/?token=` (default: MV_COORDINATOR_TOKEN, or none).
+    #[clap(long, value_name = "TOKEN")]
+    token: Option<String>,
+}
+
+/// What a worker sends; `shard` and the rest only where they apply.
+#[derive(Deserialize)]
+struct Report {
+    worker: String,
+    shard: Option<usize>,
+    #[serde(default)]
+    line: usize,
+    #[serde(default)]
+    valid: usize,
+    status: Option<String>,
+    error: Option<String>,
+}
+
+struct Coordinator {
+    book: Mutex<Book>,
+    state: PathBuf,
+    lease: Duration,
+    token: Option<String>,
+}
+
+pub fn run(args: &CoordinatorArgs) -> Result<(), Box<dyn std::error::Error>> {
+    let book = if args.state.exists() {
+        let book = Book::load(&args.state).map_err(|e| format!("can't read {}: {}", args.state.display(), e))?;
+        if !args.inputs.is_empty() {
+            println!("Warning: carrying on from {}; the inputs given are ignored", args.state.display());
+        }
+        book
+    } else if args.inputs.is_empty() {
+        return Err(format!("no inputs to split, and no {} to carry on from", args.state.display()).into());
+    } else {
+        let book = Book::plan(&args.inputs, args.shard_lines)?;
+        book.save(&args.state)?;
+        book
+    };
+    let totals = book.totals();
+    println!(
+        "{} shard(s) of {} line(s), {} done",
+        totals.shards, totals.lines, totals.done
+    );
+    let token = args.token.clone().or_else(|| std::env::var("MV_COORDINATOR_TOKEN").ok());
+    let coordinator = Arc::new(Coordinator {
+        book: Mutex::new(book),
+        state: args.state.clone(),
+        lease: Duration::from_secs(args.lease),
+        token,
+    });
+    let app = Router::new()
+        .route("/", get(dashboard))
+        .route("/api/status", get(status))
+        .route("/api/{call}", post(api))
+        .fallback(|| async { (StatusCode::NOT_FOUND, "not found\n") })
+        .layer(middleware::from_fn_with_state(coordinator.clone(), authorize))
+        .layer(DefaultBodyLimit::max(http::MAX_BODY))
+        .with_state(coordinator.clone());
+    let runtime = tokio::runtime::Builder::new_multi_thread()
+        .worker_threads(THREADS)
+        .max_blocking_threads(THREADS)
+        .enable_all()
+        .build()?;
+    runtime.block_on(async {
+        let listener = tokio::net::TcpListener::bind(&args.listen)
+            .await
+            .map_err(|e| format!("can't listen on {}: {}", args.listen, e))?;
+        println!("Coordinating on http://{} (dashboard at /)", listener.local_addr()?);
+        if coordinator.token.is_none() && !listener.local_addr()?.ip().is_loopback() {
+            println!(
+                "Warning: no --token; anyone who can reach {} can take and report shards and see the dashboard.",
+                args.listen
+            );
+        }
+        axum::serve(listener, app).await?;
+        Ok(())
+    })
+}
+
+/// Turns away requests without the token, on every route: API calls send
+/// it as a bearer token, and the dashboard is opened with `?token=`.
+async fn authorize(State(coordinator): State<Arc<Coordinator>>, request: Request, next: Next) -> Response {
+    let Some(token) = &coordinator.token else {
+        return next.run(request).await;
+    };
+    let bearer = request
+        .headers()
+        .get(AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+    let query = request
+        .uri()
+        .query()
+        .and_then(|query| query.split('&').find_map(|pair| pair.strip_prefix("token=")));
+    if http::token_matches(bearer.or(query), token) {
+        next.run(request).await
+    } else {
+        json_response(StatusCode::UNAUTHORIZED, json!({ "error": "wrong or missing token" }))
+    }
+}
+
+fn json_response(status: StatusCode, body: serde_json::Value) -> Response {
+    (status, [(CONTENT_TYPE, "application/json")], body.to_string()).into_response()
+}
+
+async fn dashboard(State(coordinator): State<Arc<Coordinator>>) -> Html<String> {
+    Html(coordinator.dashboard())
+}
+
+async fn status(State(coordinator): State<Arc<Coordinator>>) -> Response {
+    let book = coordinator.book.lock().unwrap();
+    json_response(
+        StatusCode::OK,
+        json!({ "totals": book.totals(), "workers": book.workers, "shards": book.shards }),
+    )
+}
+
+/// A worker's call; answered on a blocking thread, as it may save the book.
+async fn api(State(coordinator): State<Arc<Coordinator>>, Path(call): Path<String>, body: Bytes) -> Response {
+    match tokio::task::spawn_blocking(move || coordinator.api(&call, &body)).await {
+        Ok(Ok((status, reply))) => json_response(status, reply),
+        Ok(Err(e)) => json_response(StatusCode::INTERNAL_SERVER_ERROR, json!({ "error": e.to_string() })),
+        Err(e) => json_response(StatusCode::INTERNAL_SERVER_ERROR, json!({ "error": e.to_string() })),
+    }
+}
+
+impl Coordinator {
+    fn api(&self, call: &str, body: &[u8]) -> std::io::Result<(StatusCode, serde_json::Value)> {
+        let report: Report = match serde_json::from_slice(body) {
+            Ok(report) => report,
+            Err(e) => return Ok((StatusCode::BAD_REQUEST, json!({ "error": e.to_string() }))),
+        };
+        let mut book = self.book.lock().unwrap();
+        let (reply, changed) = match (call, report.shard) {
+            ("register", _) => {
+                book.register(&report.worker);
+                println!("Worker {} registered", report.worker);
+                (json!({ "ok": true }), true)
+            }
+            ("next", _) => match book.next(&report.worker, self.lease) {
+                Shard::Shard(shard) => {
+                    println!("Shard {} ({}, from line {}) to {}", shard.id, shard.path.display(), shard.skip + 1, report.worker);
+                    (json!({ "shard": shard }), true)
+                }
+                Shard::Wait => (json!({ "wait": WAIT }), false),
+                Shard::Finished => (json!({ "finished": true }), false),
+            },
+            ("progress", Some(shard)) => {
+                let ok = book.progress(&report.worker, shard, report.line, report.valid);
+                (json!({ "ok": ok }), false)
+            }
+            ("finish", Some(shard)) => {
+                let status = report.status.as_deref().unwrap_or("failed");
+                let ok = book.finish(&report.worker, shard, status, report.line, report.valid, report.error);
+                if ok {
+                    println!("Shard {} {} by {}", shard, status, report.worker);
+                    let totals = book.totals();
+                    if totals.done + totals.failed == totals.shards {
+                        println!("All shards finished: {} valid, {} failed shard(s)", totals.valid, totals.failed);
+                    }
+                }
+                (json!({ "ok": ok }), ok)
+            }
+            ("progress" | "finish", None) => return Ok((StatusCode::BAD_REQUEST, json!({ "error": "no shard" }))),
+            _ => return Ok((StatusCode::NOT_FOUND, json!({ "error": "no such call" }))),
+        };
+        if changed {
+            book.save(&self.state)?;
+        }
+        Ok((StatusCode::OK, reply))
+    }
+
+    fn dashboard(&self) -> String {
+        let book = self.book.lock().unwrap();
+        let totals = book.totals();
+        let share = |part: usize| if totals.lines == 0 { 0.0 } else { part as f64 / totals.lines as f64 * 100.0 };
+        let eta = match totals.rate {
+            rate if rate > 0.0 => format_duration(Duration::from_secs_f64(
+                totals.lines.saturating_sub(totals.lines_done) as f64 / rate,
+            )),
+            _ => "-".to_string(),
+        };
+        let mut page = String::new();
+        let _ = write!(
+            page,
+            "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n<meta http-equiv=\"refresh\" content=\"5\">\n\
+             <title>Coordinator</title>\n<style>\n\
+             body {{ font-family: system-ui, sans-serif; max-width: 60rem; margin: 2rem auto; padding: 0 1rem; color: #222; }}\n\
+             table {{ border-collapse: collapse; margin: 1rem 0; }}\n\
+             th, td {{ border: 1px solid #ccc; padding: 0.3rem 0.6rem; text-align: left; }}\n\
+             td.n {{ text-align: right; font-variant-numeric: tabular-nums; }}\n\
+             .track {{ background: #e3e3e3; width: 30rem; }}\n\
+             .bar {{ background: #4a7bd0; height: 0.8rem; }}\n\
+             .none {{ color: #777; }}\n\
+             </style>\n</head>\n<body>\n<h1>Coordinator</h1>\n\
+             <div class=\"track\"><div class=\"bar\" style=\"width: {:.1}%\"></div></div>\n<table>\n\
+             <tr><th>Lines</th><td class=\"n\">{} of {} ({:.1}%)</td></tr>\n\
+             <tr><th>Valid</th><td class=\"n\">{}</td></tr>\n\
+             <tr><th>Lines/s</th><td class=\"n\">{:.0}</td></tr>\n\
+             <tr><th>Time left</th><td class=\"n\">{}</td></tr>\n\
+             <tr><th>Shards</th><td class=\"n\">{} done, {} running, {} pending, {} failed</td></tr>\n</table>\n",
+            share(totals.lines_done),
+            totals.lines_done,
+            totals.lines,
+            share(totals.lines_done),
+            totals.valid,
+            totals.rate,
+            eta,
+            totals.done,
+            totals.running,
+            totals.pending,
+            totals.failed
+        );
+        page.push_str("<h2>Workers</h2>\n");
+        if book.workers.is_empty() {
+            page.push_str("<p class=\"none\">No worker has registered yet.</p>\n");
+        } else {
+            page.push_str("<table>\n<tr><th>Worker</th><th>Shard</th><th>Lines/s</th><th>Lines</th><th>Valid</th><th>Last seen</th></tr>\n");
+            let now = chrono::Utc::now().timestamp();
+            for (name, worker) in &book.workers {
+                let _ = writeln!(
+                    page,
+                    "<tr><td>{}</td><td class=\"n\">{}</td><td class=\"n\">{:.0}</td><td class=\"n\">{}</td><td class=\"n\">{}</td><td class=\"n\">{}s ago</td></tr>",
+                    escape(name),
+                    worker.shard.map_or("-".to_string(), |shard| shard.to_string()),
+                    worker.rate,
+                    worker.lines,
+                    worker.valid,
+                    now - worker.last_seen
+                );
+            }
+            page.push_str("</table>\n");
+        }
+        let active: Vec<_> = book
+            .shards
+            .iter()
+            .filter(|state| matches!(state.status, Status::Running | Status::Failed))
+            .collect();
+        if !active.is_empty() {
+            page.push_str("<h2>Running and failed shards</h2>\n<table>\n<tr><th>Shard</th><th>File</th><th>From line</th><th>Status</th><th>Worker</th><th>Done</th><th>Valid</th><th>Attempts</th><th>Error</th></tr>\n");
+            for state in active {
+                let _ = writeln!(
+                    page,
+                    "<tr><td class=\"n\">{}</td><td>{}</td><td class=\"n\">{}</td><td>{:?}</td><td>{}</td><td class=\"n\">{} of {}</td><td class=\"n\">{}</td><td class=\"n\">{}</td><td>{}</td></tr>",
+                    state.shard.id,
+                    escape(&state.shard.path.display().to_string()),
+                    state.shard.skip + 1,
+                    state.status,
+                    escape(state.worker.as_deref().unwrap_or("-")),
+                    state.done,
+                    state.lines,
+                    state.valid,
+                    state.attempts,
+                    escape(state.error.as_deref().unwrap_or(""))
+                );
+            }
+            page.push_str("</table>\n");
+        }
+        page.push_str("</body>\n</html>\n");
+        page
+    }
+}
@@ -0,0 +1,148 @@
+//! `mnemonic_validator gen-corpus`: a synthetic input file, the same for
+//! the same seed on every machine.
+//!
+//! Valid phrases of every length are mixed with near misses (wordlist
+//! phrases whose checksum fails, the kind only a full parse rejects) and
+//! ordinary junk, in the proportions `bench` measures. Runs over the same
+//! file can be compared for results or speed across builds and machines.
+
+use bip39::{Language, Mnemonic};
+use clap::Args;
+use mnemonic_validator::paths;
+use std::{
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::PathBuf,
+};
+
+/// Options of `mnemonic_validator gen-corpus`.
+#[derive(Args, Debug)]
+pub struct GenCorpusArgs {
+    /// Number of lines to write.
+    #[clap(long, value_name = "N", default_value_t = 1_000_000)]
+    lines: usize,
+
+    /// Fraction of valid mnemonics among them, e.g. 0.001 or 0.1%.
+    #[clap(long, value_name = "RATE", default_value = "0.1%", value_parser = parse_ratio)]
+    valid_ratio: f64,
+
+    /// Seed for the generator; the same seed, line count and ratio give the same file.
+    #[clap(long, default_value_t = 0)]
+    seed: u64,
+
+    /// File to write; standard output if not given.
+    #[clap(short, long, value_name = "FILE", value_parser = paths::parser())]
+    output: Option<PathBuf>,
+}
+
+/// Like `--sample`, but 0 (no valid lines at all) is allowed too.
+pub fn parse_ratio(s: &str) -> Result<f64, String> {
+    if s.trim_end_matches('%').trim().parse::<f64>() == Ok(0.0) {
+        return Ok(0.0);
+    }
+    mnemonic_validator::sample::parse_rate(s)
+}
+
+/// xorshift64*; plenty for synthetic data.
+pub struct Rng(u64);
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Self(seed.wrapping_mul(0x9e3779b97f4a7c15) | 1)
+    }
+
+    pub fn next(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545f4914f6cdd1d)
+    }
+
+    pub fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+
+    fn chance(&mut self, rate: f64) -> bool {
+        (self.next() as f64 / u64::MAX as f64) < rate
+    }
+}
+
+pub fn random_mnemonic(rng: &mut Rng) -> Mnemonic {
+    let entropy_len = [16, 20, 24, 28, 32][rng.below(5)];
+    let entropy: Vec<u8> = (0..entropy_len).map(|_| rng.next() as u8).collect();
+    Mnemonic::from_entropy(&entropy).expect("valid entropy length")
+}
+
+/// Lines that look like what real dumps are full of. Half are wordlist
+/// phrases with a broken checksum, which the pre-filter can't reject and
+/// so cost a full parse; the rest is ordinary junk.
+fn invalid_line(rng: &mut Rng, out: &mut String) {
+    const JUNK: [&str; 4] = [
+        "2024-03-01 12:00:{} INFO request handled in {}ms",
+        "user{}@example.com:hunter{}",
+        "the quick brown fox jumps over the lazy dog {} {}",
+        "{},{},0xdeadbeef,,",
+    ];
+
+    if rng.chance(0.5) {
+        let words = Language::English.word_list();
+        loop {
+            let mnemonic = random_mnemonic(rng);
+            let count = mnemonic.word_count();
+            let mut phrase: Vec<&str> = mnemonic.words().take(count - 1).collect();
+            phrase.push(words[rng.below(words.len())]);
+            let phrase = phrase.join(" ");
+            if Mnemonic::parse_in_normalized(Language::English, &phrase).is_err() {
+                out.push_str(&phrase);
+                return;
+            }
+        }
+    }
+
+    let mut parts = JUNK[rng.below(JUNK.len())].split("{}");
+    out.push_str(parts.next().unwrap_or_default());
+    for part in parts {
+        out.push_str(&rng.below(100_000).to_string());
+        out.push_str(part);
+    }
+}
+
+/// Writes `lines` lines, each valid with the chance `valid_ratio`, and
+/// returns how many are.
+pub fn write(out: &mut impl Write, lines: usize, valid_ratio: f64, seed: u64) -> io::Result<usize> {
+    let mut rng = Rng::new(seed);
+    let mut line = String::new();
+    let mut valid = 0;
+    for _ in 0..lines {
+        line.clear();
+        if rng.chance(valid_ratio) {
+            line.push_str(&random_mnemonic(&mut rng).to_string());
+            valid += 1;
+        } else {
+            invalid_line(&mut rng, &mut line);
+        }
+        line.push('\n');
+        out.write_all(line.as_bytes())?;
+    }
+    Ok(valid)
+}
+
+pub fn run(args: &GenCorpusArgs) -> Result<(), Box<dyn std::error::Error>> {
+    let valid = match &args.output {
+        Some(path) => {
+            let file = File::create(path).map_err(|e| format!("can't create {}: {}", path.display(), e))?;
+            let mut out = BufWriter::new(file);
+            let valid = write(&mut out, args.lines, args.valid_ratio, args.seed)?;
+            out.flush()?;
+            valid
+        }
+        None => {
+            let mut out = BufWriter::new(io::stdout().lock());
+            let valid = write(&mut out, args.lines, args.valid_ratio, args.seed)?;
+            out.flush()?;
+            valid
+        }
+    };
+    eprintln!("{} lines, {} valid (seed {})", args.lines, valid, args.seed);
+    Ok(())
+}
@@ -0,0 +1,37 @@
+//! The secp256k1 operations BIP32 and sealing hits need, over libsecp256k1
+//! (the `secp256k1` crate): private keys into public keys, and scalar
+//! addition for child keys.
+//!
+//! Keys go in and out as bytes so that callers keep them in wiped buffers;
+//! the library's own operations on them are constant-time.
+
+use secp256k1::{PublicKey, Scalar, SecretKey, SECP256K1};
+
+/// `a + b mod n`, for `a` and `b` below `n`; `None` if it's zero.
+pub fn add_scalars(a: &[u8; 32], b: &[u8; 32]) -> Option<[u8; 32]> {
+    let tweak = Scalar::from_be_bytes(*a).ok()?;
+    let sum = SecretKey::from_slice(b).ok()?.add_tweak(&tweak).ok()?;
+    Some(sum.secret_bytes())
+}
+
+/// Whether `k` is a valid private key: not zero and below the group order.
+pub fn is_scalar(k: &[u8; 32]) -> bool {
+    SecretKey::from_slice(k).is_ok()
+}
+
+/// The compressed public key `k·G` of a valid private key `k`.
+pub fn public_key(k: &[u8; 32]) -> [u8; 33] {
+    let k = SecretKey::from_slice(k).expect("a valid private key");
+    PublicKey::from_secret_key(SECP256K1, &k).serialize()
+}
+
+/// The uncompressed form (`04`, x, y) of a compressed public key, as
+/// Ethereum and Tron hash it; `None` if it isn't on the curve.
+pub fn uncompressed(public_key: &[u8; 33]) -> Option<[u8; 65]> {
+    Some(PublicKey::from_slice(public_key).ok()?.serialize_uncompressed())
+}
+
+/// Whether `bytes` is a compressed public key on the curve.
+pub fn is_public_key(bytes: &[u8; 33]) -> bool {
+    matches!(bytes[0], 2 | 3) && PublicKey::from_slice(bytes).is_ok()
+}
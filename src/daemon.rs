@@ -0,0 +1,272 @@
+use crate::{console, job, Cli};
+use clap::{Args, Parser};
+use mnemonic_validator::config::EngineConfig;
+use serde::Serialize;
+use std::{
+    fs::{self, File},
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+/// Options of `mnemonic_validator daemon`.
+#[derive(Args, Debug)]
+pub struct DaemonArgs {
+    /// Spool directory to take job descriptors (`*.json`) and job files (`*.toml`) from.
+    #[clap(long, value_parser = mnemonic_validator::paths::parser())]
+    spool: PathBuf,
+
+    /// Seconds between looks at an empty spool directory.
+    #[clap(long, value_name = "SECS", default_value_t = 2)]
+    poll_interval: u64,
+
+    /// Run the queued jobs and exit instead of waiting for more.
+    #[clap(long)]
+    once: bool,
+}
+
+/// A job descriptor: the arguments of a normal run, e.g.
+/// `{"args": ["-i", "/cases/7/dump.txt", "-o", "/cases/7/valid.txt"]}`,
+/// and optionally its engine settings as an [`EngineConfig`], which the
+/// arguments override. A `--job` file works as well.
+#[derive(serde::Deserialize)]
+struct Job {
+    #[serde(default)]
+    args: Vec<String>,
+    #[serde(default)]
+    engine: Option<EngineConfig>,
+}
+
+impl Job {
+    /// The arguments of the run, the engine settings first.
+    fn command_line(&self) -> Vec<String> {
+        let mut args = self.engine.as_ref().map(EngineConfig::to_args).unwrap_or_default();
+        args.extend(self.args.iter().cloned());
+        args
+    }
+}
+
+/// Written next to the finished descriptor as `<job>.result.json`.
+#[derive(Serialize)]
+struct JobResult {
+    job: String,
+    status: &'static str,
+    exit_code: Option<i32>,
+    started_at: String,
+    finished_at: String,
+    elapsed_secs: f64,
+    log: Option<String>,
+    manifest: Option<String>,
+    error: Option<String>,
+}
+
+/// The spool directory and the subdirectories jobs move through:
+/// `<spool>/*.json` (or `*.toml`) -> `running/` -> `done/` or `failed/`.
+struct Spool {
+    root: PathBuf,
+}
+
+impl Spool {
+    fn dir(&self, name: &str) -> PathBuf {
+        self.root.join(name)
+    }
+
+    fn create(&self) -> std::io::Result<()> {
+        for dir in ["running", "done", "failed", "logs", "checkpoints"] {
+            fs::create_dir_all(self.dir(dir))?;
+        }
+        Ok(())
+    }
+
+    /// Jobs interrupted by a previous daemon come first, then new ones by
+    /// name. Writers should create `name.json.tmp` and rename it, so a
+    /// half-written descriptor is never picked up.
+    fn next_job(&self) -> std::io::Result<Option<PathBuf>> {
+        for dir in [self.dir("running"), self.root.clone()] {
+            let mut jobs: Vec<PathBuf> = fs::read_dir(&dir)?
+                .filter_map(|entry| entry.ok().map(|e| e.path()))
+                .filter(|path| path.is_file() && path.extension().is_some_and(|ext| ext == "json" || ext == "toml"))
+                .collect();
+            jobs.sort();
+            if let Some(job) = jobs.into_iter().next() {
+                return Ok(Some(job));
+            }
+        }
+        Ok(None)
+    }
+}
+
+fn job_name(path: &Path) -> String {
+    path.file_stem().unwrap_or_default().to_string_lossy().into_owned()
+}
+
+pub fn run(args: &DaemonArgs) -> Result<(), Box<dyn std::error::Error>> {
+    let spool = Spool {
+        root: args.spool.clone(),
+    };
+    spool.create()?;
+    let exe = std::env::current_exe()?;
+
+    // A job's own process gets the same signal and saves its checkpoint;
+    // the daemon only stops taking new jobs
+    let stop = Arc::new(AtomicBool::new(false));
+    let stop_flag = stop.clone();
+    console::on_exit(move |_| stop_flag.store(true, Ordering::SeqCst))?;
+
+    println!("Watching {} for jobs", spool.root.display());
+    while !stop.load(Ordering::SeqCst) {
+        let Some(descriptor) = spool.next_job()? else {
+            if args.once {
+                break;
+            }
+            let idle = Instant::now();
+            while idle.elapsed() < Duration::from_secs(args.poll_interval) && !stop.load(Ordering::SeqCst) {
+                thread::sleep(Duration::from_millis(100));
+            }
+            continue;
+        };
+
+        let name = job_name(&descriptor);
+        let running = spool.dir("running").join(descriptor.file_name().unwrap());
+        if descriptor != running {
+            fs::rename(&descriptor, &running)?;
+        }
+        println!("Job {}: started", name);
+        let result = run_job(&spool, &exe, &name, &running, &stop);
+
+        if result.status == "interrupted" {
+            // Stays in running/ and resumes from its checkpoint next time;
+            // one stopped by its own time limit ends the daemon's window too
+            println!("Job {}: interrupted, will resume", name);
+            break;
+        }
+        let dir = spool.dir(if result.status == "done" { "done" } else { "failed" });
+        fs::rename(&running, dir.join(running.file_name().unwrap()))?;
+        fs::write(
+            dir.join(format!("{}.result.json", name)),
+            serde_json::to_string_pretty(&result)?,
+        )?;
+        match &result.error {
+            Some(error) => println!("Job {}: {} ({})", name, result.status, error),
+            None => println!("Job {}: {} in {:.1}s", name, result.status, result.elapsed_secs),
+        }
+    }
+    Ok(())
+}
+
+/// Runs one job as a child process of this executable with its own
+/// checkpoint and log.
+fn run_job(spool: &Spool, exe: &Path, name: &str, descriptor: &Path, stop: &AtomicBool) -> JobResult {
+    let started_at = chrono::Utc::now();
+    let start = Instant::now();
+    let mut result = JobResult {
+        job: name.to_string(),
+        status: "failed",
+        exit_code: None,
+        started_at: started_at.to_rfc3339(),
+        finished_at: String::new(),
+        elapsed_secs: 0.0,
+        log: None,
+        manifest: None,
+        error: None,
+    };
+    let finish = |mut result: JobResult| {
+        result.finished_at = chrono::Utc::now().to_rfc3339();
+        result.elapsed_secs = start.elapsed().as_secs_f64();
+        result
+    };
+
+    let job = if descriptor.extension().is_some_and(|ext| ext == "toml") {
+        job::args(descriptor).map(|args| Job { args, engine: None }).map_err(|e| e.to_string())
+    } else {
+        fs::read_to_string(descriptor)
+            .map_err(|e| e.to_string())
+            .and_then(|json| serde_json::from_str(&json).map_err(|e| e.to_string()))
+    };
+    let job = match job {
+        Ok(job) => job,
+        Err(e) => {
+            result.status = "invalid";
+            result.error = Some(format!("unreadable job descriptor: {}", e));
+            return finish(result);
+        }
+    };
+
+    // Check the arguments here, so a typo fails the job rather than the run
+    let args = job.command_line();
+    let cli = match Cli::try_parse_from(std::iter::once("mnemonic_validator".to_string()).chain(args.clone())) {
+        Ok(cli) if cli.command.is_some() => {
+            result.status = "invalid";
+            result.error = Some("jobs can't run subcommands".to_string());
+            return finish(result);
+        }
+        Ok(cli) if cli.job.is_some() => {
+            result.status = "invalid";
+            result.error = Some("put the job file itself into the spool instead of --job".to_string());
+            return finish(result);
+        }
+        Ok(cli) if cli.listen_tcp.is_some() || cli.listen_unix.is_some() => {
+            result.status = "invalid";
+            result.error = Some("jobs must finish; --listen-tcp and --listen-unix never do".to_string());
+            return finish(result);
+        }
+        Ok(cli) if cli.checkpoint.is_some() => {
+            result.status = "invalid";
+            result.error = Some("jobs get their checkpoint from the daemon; drop --checkpoint".to_string());
+            return finish(result);
+        }
+        Ok(cli) => cli,
+        Err(e) => {
+            result.status = "invalid";
+            result.error = Some(e.to_string().lines().next().unwrap_or_default().to_string());
+            return finish(result);
+        }
+    };
+
+    let checkpoint = spool.dir("checkpoints").join(format!("{}.txt", name));
+    let log_path = spool.dir("logs").join(format!("{}.log", name));
+    result.log = Some(log_path.display().to_string());
+    let log = match File::options().create(true).append(true).open(&log_path) {
+        Ok(log) => log,
+        Err(e) => {
+            result.error = Some(format!("can't open log: {}", e));
+            return finish(result);
+        }
+    };
+
+    let status = log.try_clone().and_then(|stderr| {
+        Command::new(exe)
+            .args(&args)
+            .arg("--checkpoint")
+            .arg(&checkpoint)
+            .stdin(Stdio::null())
+            .stdout(log)
+            .stderr(stderr)
+            .status()
+    });
+    match status {
+        Ok(status) => {
+            result.exit_code = status.code();
+            let resumable = status.code() == Some(crate::EXIT_RESUMABLE);
+            result.status = if resumable || stop.load(Ordering::SeqCst) && checkpoint.exists() {
+                "interrupted"
+            } else if status.success() {
+                "done"
+            } else {
+                "failed"
+            };
+        }
+        Err(e) => result.error = Some(format!("failed to start: {}", e)),
+    }
+
+    let manifest = mnemonic_validator::manifest::manifest_path(&cli.output);
+    if result.status == "done" && !cli.no_manifest && !cli.count_only {
+        result.manifest = Some(manifest.display().to_string());
+    }
+    finish(result)
+}
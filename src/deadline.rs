@@ -0,0 +1,68 @@
+//! Time limits for a run, for maintenance windows and spot instances.
+//!
+//! `--max-duration 6h` and `--stop-at 02:00` set a deadline on the run's
+//! [`CancelToken`](crate::cancel::CancelToken), which then finishes: it
+//! stops reading, validates and writes what it has read, and saves the
+//! checkpoint at the line it got to, so that the next run picks up from
+//! there exactly.
+
+use chrono::{DateTime, Local, NaiveTime, TimeZone};
+use std::time::Duration;
+
+/// Parses a duration such as `6h`, `90m`, `1h30m`, `2d` or `45s`; a plain
+/// number is seconds.
+pub fn parse_duration(s: &str) -> Result<Duration, String> {
+    let t = s.trim();
+    if let Ok(secs) = t.parse::<u64>() {
+        return Ok(Duration::from_secs(secs));
+    }
+    let mut total = 0u64;
+    let mut rest = t;
+    while !rest.is_empty() {
+        let digits = rest.find(|c: char| !c.is_ascii_digit()).unwrap_or(rest.len());
+        let number: u64 = rest[..digits]
+            .parse()
+            .map_err(|_| format!("invalid duration '{}', expected e.g. 6h, 90m or 1h30m", s))?;
+        let unit = match rest[digits..].chars().next() {
+            Some('d') => 86400,
+            Some('h') => 3600,
+            Some('m') => 60,
+            Some('s') => 1,
+            _ => return Err(format!("unknown unit in '{}', expected d, h, m or s", s)),
+        };
+        total = number
+            .checked_mul(unit)
+            .and_then(|secs| total.checked_add(secs))
+            .ok_or_else(|| format!("duration '{}' is too long", s))?;
+        rest = &rest[digits + 1..];
+    }
+    if total == 0 {
+        return Err(format!("duration '{}' is zero", s));
+    }
+    Ok(Duration::from_secs(total))
+}
+
+/// Parses a local time of day, `HH:MM` or `HH:MM:SS`.
+pub fn parse_clock(s: &str) -> Result<NaiveTime, String> {
+    let t = s.trim();
+    NaiveTime::parse_from_str(t, "%H:%M:%S")
+        .or_else(|_| NaiveTime::parse_from_str(t, "%H:%M"))
+        .map_err(|_| format!("invalid time '{}', expected HH:MM (24-hour, local time)", s))
+}
+
+/// The next time it's `time` o'clock after `now`: today, or tomorrow if
+/// that has passed. A time skipped by a clock change is taken an hour later.
+pub fn next_at(time: NaiveTime, now: DateTime<Local>) -> DateTime<Local> {
+    let mut date = now.date_naive();
+    loop {
+        let day = date.and_time(time);
+        let at = Local
+            .from_local_datetime(&day)
+            .earliest()
+            .or_else(|| Local.from_local_datetime(&(day + chrono::Duration::hours(1))).earliest());
+        if let Some(at) = at.filter(|at| *at > now) {
+            return at;
+        }
+        date = date.succ_opt().expect("date out of range");
+    }
+}
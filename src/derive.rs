@@ -0,0 +1,201 @@
+//! Addresses of the chains whose wallets derive Ed25519 keys from a BIP39
+//! phrase, for `--blockchain`: Solana and Cardano.
+//!
+//! Solana wallets (Phantom, Solflare) derive with SLIP-10 from the BIP39
+//! seed, one account per address at `m/44'/501'/i'/0'`, the address being
+//! the public key in base58.
+//!
+//! Cardano Shelley wallets (Daedalus, Yoroi, Eternl) derive with
+//! BIP32-Ed25519 from an Icarus master key, which comes from the phrase's
+//! entropy rather than its seed. The addresses are CIP-1852 base addresses
+//! on mainnet: a payment key at `m/1852'/1815'/0'/0/i` and the account's
+//! stake key at `m/1852'/1815'/0'/2/0`.
+
+use crate::{
+    bip32::{self, hmac_sha512, HARDENED},
+    secret::Zeroizing,
+};
+use bip39::Mnemonic;
+use blake2::{digest::consts::U28, Blake2b, Digest};
+use curve25519_dalek::{EdwardsPoint, Scalar};
+use ed25519_dalek::SigningKey;
+
+/// The chain whose addresses are looked at.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Blockchain {
+    /// On `--network`, see [`address`](crate::address).
+    #[default]
+    Bitcoin,
+    Solana,
+    Cardano,
+}
+
+impl Blockchain {
+    pub fn name(self) -> &'static str {
+        match self {
+            Blockchain::Bitcoin => "bitcoin",
+            Blockchain::Solana => "solana",
+            Blockchain::Cardano => "cardano",
+        }
+    }
+}
+
+/// `bitcoin`, `solana` or `cardano`, for clap.
+pub fn parse_blockchain(s: &str) -> Result<Blockchain, String> {
+    match s.trim().to_ascii_lowercase().as_str() {
+        "bitcoin" | "btc" => Ok(Blockchain::Bitcoin),
+        "solana" | "sol" => Ok(Blockchain::Solana),
+        "cardano" | "ada" => Ok(Blockchain::Cardano),
+        _ => Err(format!("'{}' isn't a blockchain; expected bitcoin, solana or cardano", s)),
+    }
+}
+
+/// A SLIP-10 Ed25519 private key; only hardened children exist.
+pub struct Slip10Key {
+    key: Zeroizing<[u8; 32]>,
+    chain_code: Zeroizing<[u8; 32]>,
+}
+
+impl Slip10Key {
+    fn from_output(output: &[u8; 64]) -> Self {
+        let mut key = Zeroizing::new([0; 32]);
+        let mut chain_code = Zeroizing::new([0; 32]);
+        key.copy_from_slice(&output[..32]);
+        chain_code.copy_from_slice(&output[32..]);
+        Self { key, chain_code }
+    }
+
+    pub fn master(seed: &[u8]) -> Self {
+        Self::from_output(&hmac_sha512(b"ed25519 seed", &[seed]))
+    }
+
+    /// The hardened child `index`, whether or not it's given as one.
+    pub fn child(&self, index: u32) -> Self {
+        let index = index | HARDENED;
+        Self::from_output(&hmac_sha512(&*self.chain_code, &[&[0], &*self.key, &index.to_be_bytes()]))
+    }
+
+    pub fn private_key(&self) -> &[u8; 32] {
+        &self.key
+    }
+
+    pub fn chain_code(&self) -> &[u8; 32] {
+        &self.chain_code
+    }
+
+    /// The public key, as Ed25519 signing makes it.
+    pub fn public_key(&self) -> [u8; 32] {
+        SigningKey::from_bytes(&self.key).verifying_key().to_bytes()
+    }
+}
+
+/// The Solana addresses of the first `count` accounts of a BIP39 seed.
+pub fn solana_addresses(seed: &[u8], count: u32) -> impl Iterator<Item = String> {
+    let coin = Slip10Key::master(seed).child(44).child(501);
+    (0..count).map(move |account| bip32::base58(&coin.child(account).child(0).public_key()))
+}
+
+/// A BIP32-Ed25519 extended private key: the scalar `kl`, `kr` for
+/// signing, and the chain code.
+struct IcarusKey {
+    key: Zeroizing<[u8; 64]>,
+    chain_code: Zeroizing<[u8; 32]>,
+}
+
+impl IcarusKey {
+    /// The Icarus master key of a phrase: PBKDF2-HMAC-SHA512 of its entropy
+    /// under an empty passphrase, 4096 rounds, clamped.
+    fn master(mnemonic: &Mnemonic) -> Self {
+        let (entropy, length) = mnemonic.to_entropy_array();
+        let entropy = Zeroizing::new(entropy);
+        let mut output = Zeroizing::new([0u8; 96]);
+        for (block, chunk) in (1u32..).zip(output.chunks_mut(64)) {
+            let mut u = hmac_sha512(b"", &[&entropy[..length], &block.to_be_bytes()]);
+            let mut t = Zeroizing::new(*u);
+            for _ in 1..4096 {
+                u = hmac_sha512(b"", &[&*u]);
+                for (t, u) in t.iter_mut().zip(u.iter()) {
+                    *t ^= u;
+                }
+            }
+            chunk.copy_from_slice(&t[..chunk.len()]);
+        }
+        let mut key = Zeroizing::new([0; 64]);
+        let mut chain_code = Zeroizing::new([0; 32]);
+        key.copy_from_slice(&output[..64]);
+        chain_code.copy_from_slice(&output[64..]);
+        key[0] &= 0xF8;
+        key[31] &= 0x1F;
+        key[31] |= 0x40;
+        Self { key, chain_code }
+    }
+
+    /// `kl` times the base point. `kl` can be past the group order, which
+    /// the base point's multiples repeat at.
+    fn public_key(&self) -> [u8; 32] {
+        let scalar = Zeroizing::new(Scalar::from_bytes_mod_order(self.key[..32].try_into().unwrap()));
+        EdwardsPoint::mul_base(&scalar).compress().to_bytes()
+    }
+
+    /// The child `index` (hardened from [`HARDENED`] up): `kl` grows by 8
+    /// times the first 28 bytes of Z, `kr` by its last 32, mod 2^256.
+    fn child(&self, index: u32) -> Self {
+        let suffix = index.to_le_bytes();
+        let (z, chain) = if index >= HARDENED {
+            (
+                hmac_sha512(&*self.chain_code, &[&[0x00], &*self.key, &suffix]),
+                hmac_sha512(&*self.chain_code, &[&[0x01], &*self.key, &suffix]),
+            )
+        } else {
+            let public_key = self.public_key();
+            (
+                hmac_sha512(&*self.chain_code, &[&[0x02], &public_key, &suffix]),
+                hmac_sha512(&*self.chain_code, &[&[0x03], &public_key, &suffix]),
+            )
+        };
+        let mut key = Zeroizing::new([0; 64]);
+        let mut carry = 0u16;
+        for i in 0..32 {
+            let tweak = if i < 28 { (z[i] as u16) << 3 } else { 0 };
+            let sum = self.key[i] as u16 + tweak + carry;
+            key[i] = sum as u8;
+            carry = sum >> 8;
+        }
+        let mut carry = 0u16;
+        for i in 32..64 {
+            let sum = self.key[i] as u16 + z[i] as u16 + carry;
+            key[i] = sum as u8;
+            carry = sum >> 8;
+        }
+        let mut chain_code = Zeroizing::new([0; 32]);
+        chain_code.copy_from_slice(&chain[32..]);
+        Self { key, chain_code }
+    }
+
+    fn derive(&self, path: &[u32]) -> Self {
+        let mut key = self.child(path[0]);
+        for &index in &path[1..] {
+            key = key.child(index);
+        }
+        key
+    }
+}
+
+/// The Cardano Shelley base addresses of the first `count` receive keys
+/// of a phrase's first account, on mainnet.
+pub fn cardano_addresses(mnemonic: &Mnemonic, count: u32) -> impl Iterator<Item = String> {
+    let account = IcarusKey::master(mnemonic).derive(&[1852 | HARDENED, 1815 | HARDENED, HARDENED]);
+    let stake = blake2b_224(&account.derive(&[2, 0]).public_key());
+    let receive = account.child(0);
+    (0..count).map(move |index| {
+        let mut address = vec![0x01];
+        address.extend_from_slice(&blake2b_224(&receive.child(index).public_key()));
+        address.extend_from_slice(&stake);
+        crate::address::encode_bech32("addr", Vec::new(), &address)
+    })
+}
+
+/// BLAKE2b with a 28-byte digest, Cardano's hash of keys in addresses.
+fn blake2b_224(data: &[u8]) -> [u8; 28] {
+    Blake2b::<U28>::digest(data).into()
+}
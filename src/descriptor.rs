@@ -0,0 +1,105 @@
+//! Output descriptors for watching a valid phrase's wallet from Bitcoin
+//! Core (`--descriptors`).
+//!
+//! Each phrase gets the receive and change descriptors of its first BIP84
+//! account, `wpkh([fingerprint/84h/0h/0h]xpub…/0/*)`, as one
+//! `importdescriptors` request. They hold the account's public key only,
+//! which is enough to see the wallet's history and balance but not to
+//! spend from it.
+
+use crate::{
+    bip32::{ExtendedKey, HARDENED},
+    network::Network,
+    secret::Zeroizing,
+};
+use bip39::Mnemonic;
+
+/// Addresses imported on each chain; wallets that used more can extend the
+/// range later.
+pub const RANGE: u32 = 1000;
+
+const INPUT_CHARSET: &str =
+    "0123456789()[],'/*abcdefgh@:$%{}IJKLMNOPQRSTUVWXYZ&+-.;<=>?!^_|~ijklmnopqrstuvwxyzABCDEFGH`#\"\\ ";
+const CHECKSUM_CHARSET: &[u8; 32] = b"qpzry9x8gf2tvdw0s3jn54khce6mua7l";
+
+fn polymod(checksum: u64, value: u64) -> u64 {
+    const GENERATOR: [u64; 5] = [0xf5dee51989, 0xa9fdca3312, 0x1bab10e32d, 0x3706b1677a, 0x644d626ffd];
+    let top = checksum >> 35;
+    let mut checksum = (checksum & 0x7ffffffff) << 5 ^ value;
+    for (i, generator) in GENERATOR.iter().enumerate() {
+        if top >> i & 1 == 1 {
+            checksum ^= generator;
+        }
+    }
+    checksum
+}
+
+/// `descriptor` with its BIP380 checksum appended; `None` if it has a
+/// character descriptors can't.
+pub fn with_checksum(descriptor: &str) -> Option<String> {
+    let mut checksum = 1;
+    let mut classes = Vec::with_capacity(3);
+    for c in descriptor.chars() {
+        let position = INPUT_CHARSET.find(c)? as u64;
+        checksum = polymod(checksum, position & 31);
+        classes.push(position >> 5);
+        if classes.len() == 3 {
+            checksum = polymod(checksum, classes[0] * 9 + classes[1] * 3 + classes[2]);
+            classes.clear();
+        }
+    }
+    match classes[..] {
+        [a] => checksum = polymod(checksum, a),
+        [a, b] => checksum = polymod(checksum, a * 3 + b),
+        _ => {}
+    }
+    for _ in 0..8 {
+        checksum = polymod(checksum, 0);
+    }
+    checksum ^= 1;
+
+    let mut result = String::with_capacity(descriptor.len() + 9);
+    result.push_str(descriptor);
+    result.push('#');
+    for i in 0..8 {
+        result.push(CHECKSUM_CHARSET[(checksum >> (5 * (7 - i)) & 31) as usize] as char);
+    }
+    Some(result)
+}
+
+/// The receive and change descriptors of the phrase's first BIP84 account
+/// on `network` (no passphrase), checksums included.
+pub fn bip84(mnemonic: &Mnemonic, network: Network) -> Option<[String; 2]> {
+    let seed = Zeroizing::new(mnemonic.to_seed_normalized(""));
+    let master = ExtendedKey::master(&*seed)?;
+    let coin = network.coin_type();
+    let account = master.derive(&[84 | HARDENED, coin | HARDENED, HARDENED])?;
+    let origin = master
+        .fingerprint()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect::<String>();
+    let xpub = account.encode_public(network.xpub());
+    let receive = with_checksum(&format!("wpkh([{}/84h/{}h/0h]{}/0/*)", origin, coin, xpub))?;
+    let change = with_checksum(&format!("wpkh([{}/84h/{}h/0h]{}/1/*)", origin, coin, xpub))?;
+    Some([receive, change])
+}
+
+/// A `bitcoin-cli importdescriptors` request for the receive and change
+/// descriptors, on one line. They're imported inactive and scanned from
+/// the genesis block, as nothing tells when the wallet was first used.
+pub fn import_request(descriptors: &[String; 2]) -> String {
+    let requests: Vec<_> = descriptors
+        .iter()
+        .enumerate()
+        .map(|(i, descriptor)| {
+            serde_json::json!({
+                "desc": descriptor,
+                "timestamp": 0,
+                "range": [0, RANGE - 1],
+                "internal": i == 1,
+            })
+        })
+        .collect();
+    serde_json::Value::Array(requests).to_string()
+}
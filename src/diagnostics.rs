@@ -0,0 +1,332 @@
+//! A word-by-word look at a phrase, for telling what's wrong with one that
+//! doesn't validate.
+//!
+//! [`analyze`] splits a phrase the way the validator does, on whitespace
+//! and the typographic separators [`typography`](crate::typography) turns
+//! into spaces, with invisible characters dropped from the words. Each word
+//! comes with its index in the English wordlist or, if it isn't on the
+//! list, the list words nearest to it.
+//!
+//! Nearness is an edit distance that knows how backups get mistyped and
+//! misread: hitting a key next to the right one on a QWERTY keyboard, or a
+//! scan reading `rn` for `m` or `0` for `o`, costs less than any other
+//! substitution, and swapping two letters counts as a single edit. Words
+//! transcribed from a spoken phrase go wrong differently, `nite` for
+//! `night` or `fone` for `phone`, so list words that sound the same, by a
+//! simplified Metaphone key, are as near as a neighbouring key.
+//!
+//! [`repair`] puts the suggestions together into whole phrases and keeps
+//! those whose checksum holds.
+
+use crate::{secret::SecretString, typography, wordlist};
+use bip39::Language;
+use std::{ops::Range, sync::OnceLock};
+
+/// At most this many suggestions per word.
+const MAX_SUGGESTIONS: usize = 3;
+/// Cost of an edit with no excuse: inserting, deleting or swapping letters,
+/// or substituting unrelated ones.
+const EDIT: usize = 4;
+/// Substituting a letter for one on a neighbouring key.
+const KEY: usize = 2;
+/// Substituting letters that look alike in print.
+const OCR: usize = 1;
+/// A list word that sounds like the word.
+const SOUND: usize = KEY;
+/// Words further away than two plain edits aren't suggested.
+const MAX_DISTANCE: usize = 2 * EDIT;
+/// Letter rows of a QWERTY keyboard, each one set half a key further right
+/// than the one above.
+const ROWS: [&str; 3] = ["qwertyuiop", "asdfghjkl", "zxcvbnm"];
+/// Characters OCR (and tired eyes) mistake for one another.
+const LOOKALIKES: [(char, char); 10] = [
+    ('0', 'o'),
+    ('1', 'l'),
+    ('1', 'i'),
+    ('l', 'i'),
+    ('5', 's'),
+    ('2', 'z'),
+    ('8', 'b'),
+    ('9', 'g'),
+    ('c', 'e'),
+    ('u', 'v'),
+];
+/// Pairs of letters OCR reads as one, and the other way round.
+const MERGES: [(&str, char); 3] = [("rn", 'm'), ("vv", 'w'), ("cl", 'd')];
+/// Every list word is told apart by its first four letters, so a backup
+/// may well hold no more than those.
+const PREFIX: usize = 4;
+/// [`repair`] gives up on phrases with more combinations of suggestions
+/// than this.
+const MAX_REPAIRS: usize = 1 << 16;
+
+#[derive(Clone, Debug)]
+pub struct WordDiagnostic {
+    /// The word, as it appears in the phrase but for invisible characters.
+    pub word: SecretString,
+    /// 0-based position among the words of the phrase.
+    pub position: usize,
+    /// Bytes of the phrase the word takes up.
+    pub span: Range<usize>,
+    /// Index in the English wordlist.
+    pub index: Option<u16>,
+    /// List words the word may have been meant to be, nearest first; empty
+    /// for a list word.
+    pub suggestions: Vec<&'static str>,
+}
+
+/// The words of `phrase` and what's known about each.
+pub fn analyze(phrase: &str) -> Vec<WordDiagnostic> {
+    let mut diagnostics: Vec<WordDiagnostic> = Vec::new();
+    let mut word = SecretString::new(String::new());
+    let mut start = None;
+    let mut finish = |word: &mut SecretString, span: Range<usize>| {
+        let index = wordlist::index_in(word, Language::English);
+        diagnostics.push(WordDiagnostic {
+            suggestions: if index.is_some() { Vec::new() } else { suggest(word) },
+            word: SecretString::new(std::mem::take(&mut **word)),
+            position: diagnostics.len(),
+            span,
+            index,
+        });
+    };
+    for (at, c) in phrase.char_indices() {
+        if c.is_whitespace() || typography::is_separator(c) {
+            if let Some(start) = start.take() {
+                finish(&mut word, start..at);
+            }
+        } else {
+            start.get_or_insert(at);
+            if !typography::is_invisible(c) {
+                word.push(c);
+            }
+        }
+    }
+    if let Some(start) = start {
+        finish(&mut word, start..phrase.len());
+    }
+    diagnostics
+}
+
+/// English list words near `word`: first those it's a prefix of, if it's
+/// as long as one, then the nearest others.
+pub fn suggest(word: &str) -> Vec<&'static str> {
+    static SOUNDS: OnceLock<Vec<String>> = OnceLock::new();
+    let sounds = SOUNDS.get_or_init(|| Language::English.word_list().iter().map(|word| sound(word)).collect());
+    let word = SecretString::new(word.to_lowercase());
+    let word_sound = SecretString::new(sound(&word));
+    let mut ranked: Vec<(usize, &'static str)> = Language::English
+        .word_list()
+        .iter()
+        .zip(sounds)
+        .filter_map(|(&candidate, candidate_sound)| {
+            if word.chars().count() >= PREFIX && candidate.starts_with(word.as_str()) {
+                return Some((0, candidate));
+            }
+            let mut distance = distance(&word, candidate);
+            if !word_sound.is_empty() && *word_sound == *candidate_sound {
+                distance = distance.min(SOUND);
+            }
+            (distance <= MAX_DISTANCE).then_some((distance, candidate))
+        })
+        .collect();
+    // Stable, so ties stay in list order
+    ranked.sort_by_key(|&(distance, _)| distance);
+    ranked.into_iter().take(MAX_SUGGESTIONS).map(|(_, candidate)| candidate).collect()
+}
+
+/// Every phrase that `phrase` may have been meant to be, going by the
+/// suggestions for the words that aren't on the list, and that has a valid
+/// checksum. Empty if a word has no suggestions or there would be more than
+/// [`MAX_REPAIRS`] phrases to try.
+pub fn repair(phrase: &str) -> Vec<SecretString> {
+    let diagnostics = analyze(phrase);
+    let choices: Vec<Vec<&str>> = diagnostics
+        .iter()
+        .map(|diagnostic| match diagnostic.index {
+            Some(_) => vec![diagnostic.word.as_str()],
+            None => diagnostic.suggestions.clone(),
+        })
+        .collect();
+    let tries = choices
+        .iter()
+        .try_fold(1usize, |tries, words| tries.checked_mul(words.len()));
+    if !matches!(tries, Some(1..=MAX_REPAIRS)) {
+        return Vec::new();
+    }
+
+    let mut repaired = Vec::new();
+    // Counts through every combination, the last word turning fastest
+    let mut picks = vec![0; choices.len()];
+    loop {
+        let mut candidate = SecretString::new(String::with_capacity(phrase.len() + choices.len() * 8));
+        for (words, &pick) in choices.iter().zip(&picks) {
+            if !candidate.is_empty() {
+                candidate.push(' ');
+            }
+            candidate.push_str(words[pick]);
+        }
+        if bip39::Mnemonic::parse_in_normalized(Language::English, &candidate).is_ok() {
+            repaired.push(candidate);
+        }
+        let Some(turn) = (0..picks.len()).rev().find(|&k| picks[k] + 1 < choices[k].len()) else {
+            break;
+        };
+        picks[turn] += 1;
+        picks[turn + 1..].fill(0);
+    }
+    repaired
+}
+
+/// A simplified Metaphone key of `word`: what it sounds like, with spelling
+/// that isn't heard (silent letters, doubled letters) left out and
+/// spellings of the same sound made one. Unlike Metaphone it keeps the
+/// vowels; with words as short as list words, too many would sound alike
+/// without them.
+fn sound(word: &str) -> String {
+    let letters: Vec<u8> = word.bytes().filter(u8::is_ascii_lowercase).collect();
+    let is_vowel = |at: usize| matches!(letters.get(at), Some(b'a' | b'e' | b'i' | b'o' | b'u' | b'y'));
+    let soft = |at: usize| matches!(letters.get(at), Some(b'e' | b'i' | b'y'));
+    let next = |at: usize| letters.get(at + 1).copied();
+
+    let mut key = String::new();
+    let mut at = match letters.as_slice() {
+        [b'k' | b'g' | b'p', b'n', ..] | [b'w', b'r', ..] | [b'p', b's', ..] => 1,
+        _ => 0,
+    };
+    let start = at;
+    while at < letters.len() {
+        let mut skip = 1;
+        let code = match letters[at] {
+            // A final `e` after a consonant is silent
+            b'e' if at + 1 == letters.len() && at > start && !is_vowel(at - 1) => None,
+            b'y' => Some('i'),
+            _ if is_vowel(at) => Some(letters[at] as char),
+            b'b' if at + 1 == letters.len() && at > 0 && letters[at - 1] == b'm' => None,
+            b'c' if next(at) == Some(b'h') => {
+                skip = 2;
+                Some('x')
+            }
+            b'c' if soft(at + 1) => Some('s'),
+            b'c' if next(at) == Some(b'k') => None,
+            b'd' if next(at) == Some(b'g') && soft(at + 2) => {
+                skip = 2;
+                Some('j')
+            }
+            b'd' => Some('t'),
+            b'g' if next(at) == Some(b'h') => {
+                skip = 2;
+                (is_vowel(at + 2) && at == start).then_some('k')
+            }
+            b'g' if next(at) == Some(b'n') && at + 2 == letters.len() => None,
+            b'g' if soft(at + 1) => Some('j'),
+            b'q' if next(at) == Some(b'u') => {
+                skip = 2;
+                key.push('k');
+                Some('w')
+            }
+            b'g' | b'k' | b'q' => Some('k'),
+            b'h' => is_vowel(at + 1).then_some('h'),
+            b'p' if next(at) == Some(b'h') => {
+                skip = 2;
+                Some('f')
+            }
+            b's' if next(at) == Some(b'h') => {
+                skip = 2;
+                Some('x')
+            }
+            b't' if next(at) == Some(b'h') => {
+                skip = 2;
+                Some('0')
+            }
+            b't' if next(at) == Some(b'i') && matches!(letters.get(at + 2), Some(b'a' | b'o')) => Some('x'),
+            b'v' => Some('f'),
+            b'w' => is_vowel(at + 1).then_some('w'),
+            b'x' => {
+                key.push('k');
+                Some('s')
+            }
+            b'z' => Some('s'),
+            other => Some(other as char),
+        };
+        if let Some(code) = code {
+            if !key.ends_with(code) {
+                key.push(code);
+            }
+        }
+        at += skip;
+    }
+    key
+}
+
+fn key(c: char) -> Option<(usize, usize)> {
+    ROWS.iter()
+        .enumerate()
+        .find_map(|(row, keys)| Some((row, keys.find(c)?)))
+}
+
+/// Whether `a` and `b` are keys next to each other, in the same row or in
+/// the rows above and below.
+fn neighbours(a: char, b: char) -> bool {
+    let (Some((row_a, col_a)), Some((row_b, col_b))) = (key(a), key(b)) else {
+        return false;
+    };
+    match (row_a as isize - row_b as isize, col_a as isize - col_b as isize) {
+        (0, -1 | 1) => true,
+        // The row below is shifted right: its key at the same column and
+        // the one left of it touch
+        (-1, 0 | 1) | (1, 0 | -1) => true,
+        _ => false,
+    }
+}
+
+fn substitution(a: char, b: char) -> usize {
+    if a == b {
+        0
+    } else if LOOKALIKES.contains(&(a, b)) || LOOKALIKES.contains(&(b, a)) {
+        OCR
+    } else if neighbours(a, b) {
+        KEY
+    } else {
+        EDIT
+    }
+}
+
+/// Whether the two characters `pair` are one that OCR misreads as `single`.
+fn merge(pair: &[char], single: char) -> bool {
+    MERGES
+        .iter()
+        .any(|&(merged, c)| c == single && merged.chars().eq(pair.iter().copied()))
+}
+
+/// Weighted edit distance from `a` to `b`, in characters: plain edits cost
+/// [`EDIT`], a neighbouring key [`KEY`] and a lookalike [`OCR`].
+fn distance(a: &str, b: &str) -> usize {
+    let (a, b): (Vec<char>, Vec<char>) = (a.chars().collect(), b.chars().collect());
+    let width = b.len() + 1;
+    let mut d = vec![0; (a.len() + 1) * width];
+    for i in 0..=a.len() {
+        for j in 0..=b.len() {
+            d[i * width + j] = match (i, j) {
+                (0, _) => j * EDIT,
+                (_, 0) => i * EDIT,
+                _ => {
+                    let mut best = (d[(i - 1) * width + j] + EDIT)
+                        .min(d[i * width + j - 1] + EDIT)
+                        .min(d[(i - 1) * width + j - 1] + substitution(a[i - 1], b[j - 1]));
+                    if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                        best = best.min(d[(i - 2) * width + j - 2] + EDIT);
+                    }
+                    if i > 1 && merge(&a[i - 2..i], b[j - 1]) {
+                        best = best.min(d[(i - 2) * width + j - 1] + OCR);
+                    }
+                    if j > 1 && merge(&b[j - 2..j], a[i - 1]) {
+                        best = best.min(d[(i - 1) * width + j - 2] + OCR);
+                    }
+                    best
+                }
+            };
+        }
+    }
+    d[a.len() * width + b.len()]
+}
@@ -0,0 +1,64 @@
+//! SHA-256 helpers shared by fingerprints and manifests, and the hashes
+//! Bitcoin builds from it.
+
+use ripemd::Ripemd160;
+use sha2::{Digest, Sha256};
+use std::{
+    fs::File,
+    io::{self, Read, Seek, SeekFrom},
+    path::Path,
+};
+
+/// Lower-case hex encoding of `bytes`.
+pub fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// SHA-256 of SHA-256, as in base58 checksums.
+pub fn sha256d(data: &[u8]) -> [u8; 32] {
+    Sha256::digest(Sha256::digest(data)).into()
+}
+
+/// RIPEMD-160 of SHA-256, which addresses hash public keys and scripts with.
+pub fn hash160(data: &[u8]) -> [u8; 20] {
+    Ripemd160::digest(Sha256::digest(data)).into()
+}
+
+/// SHA-256 of a phrase with its words joined by single spaces, so the same
+/// mnemonic always hashes the same regardless of the original spacing.
+pub fn phrase_sha256<'a>(words: impl Iterator<Item = &'a str>) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    for (n, word) in words.enumerate() {
+        if n > 0 {
+            hasher.update(b" ");
+        }
+        hasher.update(word.as_bytes());
+    }
+    hasher.finalize().into()
+}
+
+/// Hex SHA-256 of a file's contents together with its size in bytes.
+pub fn sha256_file(path: &Path) -> io::Result<(String, u64)> {
+    sha256_file_from(path, 0)
+}
+
+/// Hex SHA-256 of a file's contents from byte `offset` on, together with
+/// their size in bytes.
+pub fn sha256_file_from(path: &Path, offset: u64) -> io::Result<(String, u64)> {
+    let mut file = File::open(path)?;
+    file.seek(SeekFrom::Start(offset))?;
+    let mut hasher = Sha256::new();
+    let mut buf = vec![0u8; 1 << 16];
+    let mut size = 0u64;
+
+    loop {
+        let n = file.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[..n]);
+        size += n as u64;
+    }
+
+    Ok((to_hex(&hasher.finalize()), size))
+}
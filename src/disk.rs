@@ -0,0 +1,99 @@
+//! Free disk space, for checking the output and checkpoint volumes before
+//! and during a run (`--min-free-space`).
+//!
+//! A run that fills its disk would otherwise only find out when a write
+//! fails; watching the free space lets it pause with a checkpoint instead.
+
+use std::{
+    io,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+/// How often a running validation looks at the free space.
+pub const POLL: Duration = Duration::from_secs(2);
+
+/// Longest masked line aside from the file name: two words of up to eight
+/// letters, the word count, the fingerprint and a line number.
+pub const MASKED_LINE_BYTES: u64 = 80;
+
+/// A volume found to be short of space.
+#[derive(Debug, Clone)]
+pub struct LowSpace {
+    pub path: PathBuf,
+    pub free: u64,
+}
+
+/// Bytes available to this process on the volume holding `path`. The path
+/// doesn't need to exist yet; its nearest existing ancestor is asked.
+pub fn free_space(path: &Path) -> io::Result<u64> {
+    let mut existing = path;
+    while !existing.exists() {
+        match existing.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => existing = parent,
+            _ => {
+                existing = Path::new(".");
+                break;
+            }
+        }
+    }
+    available(existing)
+}
+
+/// The first of `paths` whose volume has less than `min_free` bytes
+/// available.
+pub fn low_space(paths: &[PathBuf], min_free: u64) -> io::Result<Option<LowSpace>> {
+    for path in paths {
+        let free = free_space(path)?;
+        if free < min_free {
+            return Ok(Some(LowSpace {
+                path: path.clone(),
+                free,
+            }));
+        }
+    }
+    Ok(None)
+}
+
+#[cfg(unix)]
+fn available(path: &Path) -> io::Result<u64> {
+    use std::{ffi::CString, os::unix::ffi::OsStrExt};
+
+    let path = CString::new(path.as_os_str().as_bytes())
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+    let mut stat = std::mem::MaybeUninit::<libc::statvfs>::uninit();
+    // Safety: a NUL-terminated path and room for the result
+    if unsafe { libc::statvfs(path.as_ptr(), stat.as_mut_ptr()) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    // Safety: filled in by the successful call
+    let stat = unsafe { stat.assume_init() };
+    // The field widths differ between systems
+    #[allow(clippy::unnecessary_cast)]
+    Ok((stat.f_bavail as u64).saturating_mul(stat.f_frsize as u64))
+}
+
+#[cfg(windows)]
+fn available(path: &Path) -> io::Result<u64> {
+    use std::os::windows::ffi::OsStrExt;
+    use windows_sys::Win32::Storage::FileSystem::GetDiskFreeSpaceExW;
+
+    let path: Vec<u16> = path.as_os_str().encode_wide().chain([0]).collect();
+    let mut free = 0;
+    // Safety: a NUL-terminated path; the totals that aren't needed are null
+    let ok = unsafe {
+        GetDiskFreeSpaceExW(path.as_ptr(), &mut free, std::ptr::null_mut(), std::ptr::null_mut())
+    };
+    if ok == 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(free)
+}
+
+#[cfg(not(any(unix, windows)))]
+fn available(_path: &Path) -> io::Result<u64> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "free disk space can't be checked on this system",
+    ))
+}
@@ -0,0 +1,127 @@
+//! How many times each valid phrase turned up and where, for
+//! `--duplicates`.
+//!
+//! A phrase that a user backed up is usually found again and again: in
+//! several copies of a file, in a log that replayed it, on consecutive
+//! lines of a sheet. So the count is often the best hint of which hits are
+//! real. Phrases are told apart by the SHA-256 of their words and shown
+//! masked, as with `--mask`; consecutive lines are given as ranges.
+//!
+//! Under `--max-memory` only so many phrases are kept: past that, hits of
+//! phrases not seen yet are counted but not told apart.
+
+use crate::{digest::phrase_sha256, languages, mask};
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs::File,
+    io::{self, BufWriter, Write},
+    ops::RangeInclusive,
+    path::Path,
+};
+
+struct Cluster {
+    masked: String,
+    count: usize,
+    /// Lines by file, counting from 1, in the order found.
+    places: BTreeMap<String, Vec<RangeInclusive<usize>>>,
+    /// Order of first appearance, to break ties.
+    first: usize,
+}
+
+#[derive(Default)]
+pub struct Duplicates {
+    phrases: HashMap<[u8; 32], Cluster>,
+    /// Phrases kept at most.
+    limit: Option<usize>,
+    /// Hits of phrases past the limit.
+    untracked: usize,
+}
+
+impl Duplicates {
+    /// Keeps no more than `limit` phrases.
+    pub fn with_limit(limit: usize) -> Self {
+        Self {
+            limit: Some(limit),
+            ..Self::default()
+        }
+    }
+
+    /// Records `phrase` at `line` of `file`.
+    pub fn add(&mut self, phrase: &str, file: &str, line: usize) {
+        let order = self.phrases.len();
+        let key = phrase_sha256(languages::words(phrase).into_iter());
+        if self.limit.is_some_and(|limit| order >= limit) && !self.phrases.contains_key(&key) {
+            self.untracked += 1;
+            return;
+        }
+        let cluster = self.phrases.entry(key).or_insert_with(|| Cluster {
+            masked: mask::masked(phrase),
+            count: 0,
+            places: BTreeMap::new(),
+            first: order,
+        });
+        cluster.count += 1;
+        let lines = cluster.places.entry(file.to_string()).or_default();
+        match lines.last_mut() {
+            Some(last) if line == last.end() + 1 => *last = *last.start()..=line,
+            // Found twice on one line, it's counted twice at that line
+            Some(last) if last.contains(&line) => {}
+            _ => lines.push(line..=line),
+        }
+    }
+
+    /// Number of different phrases.
+    pub fn phrases(&self) -> usize {
+        self.phrases.len()
+    }
+
+    /// Hits left out once the limit of phrases was reached.
+    pub fn untracked(&self) -> usize {
+        self.untracked
+    }
+
+    /// The phrases found more than once, most often first, each masked with
+    /// its count.
+    pub fn repeated(&self) -> Vec<(&str, usize)> {
+        self.sorted()
+            .into_iter()
+            .filter(|cluster| cluster.count > 1)
+            .map(|cluster| (cluster.masked.as_str(), cluster.count))
+            .collect()
+    }
+
+    /// Writes every phrase, most often first, as tab-separated lines of the
+    /// count, the masked phrase and where it was, e.g. `3`,
+    /// `boy...banana 24w sha256:1f3c2a9b7d4e0c55` and `dump.txt:17-18,40`.
+    pub fn write(&self, path: &Path) -> io::Result<()> {
+        let mut out = BufWriter::new(File::create(path)?);
+        writeln!(out, "count\tphrase\tfound at")?;
+        for cluster in self.sorted() {
+            let places: Vec<String> = cluster
+                .places
+                .iter()
+                .map(|(file, lines)| {
+                    let lines: Vec<String> = lines
+                        .iter()
+                        .map(|lines| {
+                            if lines.start() == lines.end() {
+                                lines.start().to_string()
+                            } else {
+                                format!("{}-{}", lines.start(), lines.end())
+                            }
+                        })
+                        .collect();
+                    format!("{}:{}", file, lines.join(","))
+                })
+                .collect();
+            writeln!(out, "{}\t{}\t{}", cluster.count, cluster.masked, places.join(" "))?;
+        }
+        out.into_inner().map_err(|e| e.into_error())?.sync_all()
+    }
+
+    fn sorted(&self) -> Vec<&Cluster> {
+        let mut clusters: Vec<&Cluster> = self.phrases.values().collect();
+        clusters.sort_by_key(|cluster| (std::cmp::Reverse(cluster.count), cluster.first));
+        clusters
+    }
+}
@@ -0,0 +1,119 @@
+//! Electrum wallet files for a valid phrase, so that checking its funds is a
+//! matter of opening one file in Electrum.
+//!
+//! The wallet is a standard one holding the phrase's first BIP84 account
+//! (no passphrase), which is what Electrum makes of a BIP39 seed restored
+//! as native SegWit. Only the account's extended keys are stored, not the
+//! phrase. Given a password, the private key is encrypted as Electrum does
+//! it (AES-256-CBC under the double SHA-256 of the password) and Electrum
+//! asks for the password before signing; the rest of the file, the public
+//! key included, stays readable.
+
+use crate::{
+    bip32::{ExtendedKey, HARDENED},
+    digest,
+    network::Network,
+    secret::{SecretString, Zeroizing},
+};
+use aes::{
+    cipher::{block_padding::Pkcs7, BlockEncryptMut, KeyIvInit},
+    Aes256,
+};
+use bip39::Mnemonic;
+use std::{fmt::Write, io};
+
+/// Where the wallet's keys come from on `network`, e.g. `m/84'/0'/0'`.
+pub fn derivation(network: Network) -> String {
+    format!("m/84'/{}'/0'", network.coin_type())
+}
+
+/// The wallet file format written; Electrum upgrades it when opening it.
+const SEED_VERSION: u32 = 17;
+
+const BASE64: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+fn base64(data: &[u8]) -> String {
+    let mut encoded = String::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let bytes = [chunk[0], *chunk.get(1).unwrap_or(&0), *chunk.get(2).unwrap_or(&0)];
+        let n = u32::from_be_bytes([0, bytes[0], bytes[1], bytes[2]]);
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(BASE64[(n >> (18 - 6 * i) & 63) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
+
+/// `secret` encrypted with `password` the way Electrum encrypts keystore
+/// fields: base64 of a random IV followed by the ciphertext.
+pub fn encrypt(secret: &str, password: &str) -> io::Result<String> {
+    let mut iv = [0; 16];
+    getrandom::getrandom(&mut iv).map_err(io::Error::other)?;
+    Ok(encrypt_with_iv(secret, password, &iv))
+}
+
+/// [`encrypt`] with a given IV: AES-256-CBC with PKCS#7 padding, under the
+/// double SHA-256 of the password (Electrum's `pw_encode`).
+pub fn encrypt_with_iv(secret: &str, password: &str, iv: &[u8; 16]) -> String {
+    let key = Zeroizing::new(digest::sha256d(password.as_bytes()));
+    let ciphertext = cbc::Encryptor::<Aes256>::new(key.as_ref().into(), iv.into())
+        .encrypt_padded_vec_mut::<Pkcs7>(secret.as_bytes());
+    let mut data = iv.to_vec();
+    data.extend(ciphertext);
+    base64(&data)
+}
+
+/// The wallet file for `mnemonic` on `network`, with its private key
+/// encrypted under `password` unless that's empty. Electrum has to be
+/// started with `--testnet`, `--signet` or `--regtest` to open one for a
+/// test network.
+pub fn wallet(mnemonic: &Mnemonic, password: &str, network: Network) -> io::Result<SecretString> {
+    let derivation = derivation(network);
+    let no_key = || io::Error::other(format!("the phrase has no key at {}", derivation));
+    let seed = Zeroizing::new(mnemonic.to_seed_normalized(""));
+    let master = ExtendedKey::master(&*seed).ok_or_else(no_key)?;
+    let account = master
+        .derive(&[84 | HARDENED, network.coin_type() | HARDENED, HARDENED])
+        .ok_or_else(no_key)?;
+    let (Some(zpub), Some(zprv)) = (network.zpub(), network.zprv()) else {
+        return Err(io::Error::other(format!("Electrum has no native SegWit keys for {}", network)));
+    };
+    let fingerprint: String = master.fingerprint().iter().map(|byte| format!("{:02x}", byte)).collect();
+    let xprv = account.encode_private(zprv);
+    let xprv = match password {
+        "" => xprv,
+        password => SecretString::new(encrypt(&xprv, password)?),
+    };
+
+    // Every value is hex, base58 or base64, so nothing needs escaping
+    let mut wallet = SecretString::new(String::with_capacity(1024));
+    let _ = write!(
+        wallet,
+        concat!(
+            "{{\n",
+            "    \"keystore\": {{\n",
+            "        \"derivation\": \"{}\",\n",
+            "        \"pw_hash_version\": 1,\n",
+            "        \"root_fingerprint\": \"{}\",\n",
+            "        \"type\": \"bip32\",\n",
+            "        \"xprv\": \"{}\",\n",
+            "        \"xpub\": \"{}\"\n",
+            "    }},\n",
+            "    \"seed_version\": {},\n",
+            "    \"use_encryption\": {},\n",
+            "    \"wallet_type\": \"standard\"\n",
+            "}}\n",
+        ),
+        derivation,
+        fingerprint,
+        *xprv,
+        account.encode_public(zpub),
+        SEED_VERSION,
+        !password.is_empty(),
+    );
+    Ok(wallet)
+}
@@ -0,0 +1,366 @@
+//! Runs set up as an [`EngineConfig`] says, the same way in every front
+//! end: the command line, the GUI and the Node addon all tune, read, cache
+//! and check free space with these, so a setting means the same in each.
+//!
+//! The command line puts the pieces together itself, around its own
+//! options; [`FileRun`] puts them together for a front end that validates
+//! one file with nothing more than an `EngineConfig`.
+
+use crate::{
+    cache::{self, Outcome, OutcomeCache},
+    cancel::CancelToken,
+    config::EngineConfig,
+    disk,
+    events::Events,
+    lines::{self, Input, ReadBlocks},
+    memory::{self, Budget, Growing, Limits},
+    pipeline::{self, Chunk, Hit, Pipeline, Source},
+    throttle::Throttle,
+    tune,
+};
+use bip39::Mnemonic;
+use std::{
+    io,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+/// The sizes a run goes with: tuned for the machine and the input, or as
+/// given, and shrunk to fit `max_memory`.
+pub struct Tuned {
+    pub validators: usize,
+    pub limits: Limits,
+    pub chunk_lines: usize,
+    /// What grows with the input may take under `max_memory`.
+    pub budget: Budget,
+}
+
+impl Tuned {
+    /// Sizes for `validators` threads as `profile` found them, or the
+    /// defaults, unless `config` sets them. Under `max_memory` they're
+    /// shrunk to leave each of the `growing` structures
+    /// [`MIN_SHARE`](memory::MIN_SHARE) at least; `avg_line` is the input's
+    /// mean line length.
+    pub fn new(
+        config: &EngineConfig,
+        validators: usize,
+        profile: Option<tune::Profile>,
+        growing: Growing,
+        avg_line: usize,
+    ) -> Result<Self, String> {
+        let mut limits = Limits::new(validators);
+        if let Some(profile) = profile {
+            limits.block_size = profile.block_size;
+            limits.queue_depth = profile.queue_depth;
+        }
+        limits.block_size = config.block_size.unwrap_or(limits.block_size);
+        limits.queue_depth = config.queue_depth.unwrap_or(limits.queue_depth);
+        let chunk_lines = config
+            .chunk_lines
+            .or(profile.map(|profile| profile.chunk_lines))
+            .unwrap_or(pipeline::LINES_PER_CHUNK);
+        let mut budget = Budget::default();
+        if let Some(cap) = config.max_memory {
+            // Each gets some room whatever the pipeline would take
+            let reserved = growing.count() as u64 * memory::MIN_SHARE;
+            limits = match limits.shrink_to(cap.saturating_sub(reserved), validators, avg_line, config.io_uring) {
+                Ok(limits) => limits,
+                Err(_) if reserved > 0 => {
+                    let least = limits.smallest(validators, config.io_uring).estimate(validators, avg_line, config.io_uring);
+                    return Err(format!(
+                        "--max-memory {} is too small for {} validator threads and the cache, --incremental, --duplicates or --group-by; needs at least {}",
+                        memory::format_size(cap),
+                        validators,
+                        memory::format_size(least + reserved)
+                    ));
+                }
+                Err(e) => return Err(e),
+            };
+            let in_use = limits.estimate(validators, avg_line, config.io_uring);
+            budget = Budget::split(cap.saturating_sub(in_use), growing);
+        }
+        Ok(Self {
+            validators,
+            limits,
+            chunk_lines,
+            budget,
+        })
+    }
+
+    /// A pipeline with these sizes, and the throttle and line length limit
+    /// of `config`.
+    pub fn pipeline(&self, config: &EngineConfig) -> Pipeline {
+        Pipeline {
+            validators: self.validators,
+            queue_depth: self.limits.queue_depth,
+            hit_queue: self.limits.hit_queue,
+            throttle: config.throttle(),
+            long_lines: config.long_lines(),
+            events: None,
+        }
+    }
+}
+
+/// Times the storage and validation on the file at `path`, unless
+/// `no_auto_tune` is set.
+pub fn probe(config: &EngineConfig, path: &Path, validate: impl Fn(&str) -> bool) -> io::Result<Option<tune::Probe>> {
+    if config.no_auto_tune {
+        return Ok(None);
+    }
+    tune::probe(path, validate).map(Some)
+}
+
+/// Validator threads: as set, or as many as `profile` found the storage
+/// keeps busy, or one per CPU.
+pub fn validators(config: &EngineConfig, profile: Option<tune::Profile>) -> usize {
+    config
+        .threads
+        .or(profile.map(|profile| profile.threads))
+        .unwrap_or_else(|| Pipeline::default().validators)
+}
+
+/// Refuses to start with less than `min_free_space` free on the volume of
+/// any of `paths`.
+pub fn check_free_space(config: &EngineConfig, paths: &[PathBuf]) -> Result<(), String> {
+    let low = disk::low_space(paths, config.min_free_space).map_err(|e| format!("can't tell the free space: {}", e))?;
+    match low {
+        Some(low) => Err(format!(
+            "only {} free for '{}', under --min-free-space {}; free some space or lower it (0 turns the check off)",
+            memory::format_size(low.free),
+            low.path.display(),
+            memory::format_size(config.min_free_space)
+        )),
+        None => Ok(()),
+    }
+}
+
+/// How the input is read.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Reader {
+    /// Memory-mapped and cut into chunks of lines.
+    Mapped,
+    /// In blocks of whole lines, to keep it out of resident memory under
+    /// `max_memory`.
+    Blocks,
+    /// With several reads in flight: io_uring on Linux, overlapped I/O on
+    /// Windows.
+    InFlight,
+}
+
+impl Reader {
+    /// How `config` has the input read. Where io_uring is asked for but
+    /// the kernel doesn't offer it, the input is read as if it hadn't been,
+    /// and the reason comes along.
+    pub fn choose(config: &EngineConfig) -> Result<(Self, Option<String>), String> {
+        let otherwise = match config.max_memory {
+            Some(_) => Reader::Blocks,
+            None => Reader::Mapped,
+        };
+        if !config.io_uring {
+            return Ok((otherwise, None));
+        }
+        Ok(match in_flight_unavailable()? {
+            None => (Reader::InFlight, None),
+            Some(why) => (otherwise, Some(why)),
+        })
+    }
+}
+
+/// Why reads can't be kept in flight on this system right now, if they
+/// can't; an error where they never can.
+#[cfg(all(target_os = "linux", feature = "io-uring"))]
+fn in_flight_unavailable() -> Result<Option<String>, String> {
+    Ok(crate::uring::available().err().map(|e| e.to_string()))
+}
+
+#[cfg(windows)]
+fn in_flight_unavailable() -> Result<Option<String>, String> {
+    Ok(None)
+}
+
+#[cfg(not(any(all(target_os = "linux", feature = "io-uring"), windows)))]
+fn in_flight_unavailable() -> Result<Option<String>, String> {
+    Err("--io-uring needs Windows, or a Linux build with the `io-uring` feature (cargo build --features io-uring)".to_string())
+}
+
+/// The lines of `files`, each a path and the index of its first line, read
+/// by `reader`. Memory maps are kept in `mapped` for the chunks borrowed
+/// from them.
+pub fn source<'a>(
+    config: &EngineConfig,
+    reader: Reader,
+    files: Vec<(PathBuf, usize)>,
+    tuned: &Tuned,
+    mapped: &'a mut Vec<(usize, Input)>,
+) -> io::Result<Source<'a>> {
+    let block_size = tuned.limits.block_size;
+    Ok(match reader {
+        #[cfg(all(target_os = "linux", feature = "io-uring"))]
+        Reader::InFlight => block_source(files, &tuned.limits, crate::uring::BlockReader::with_sizes),
+        #[cfg(windows)]
+        Reader::InFlight => block_source(files, &tuned.limits, crate::overlapped::BlockReader::with_sizes),
+        #[cfg(not(any(all(target_os = "linux", feature = "io-uring"), windows)))]
+        Reader::InFlight => return Err(io::Error::new(io::ErrorKind::Unsupported, in_flight_unavailable().unwrap_err())),
+        Reader::Blocks => {
+            let max_line = config.long_lines().map(|long_lines| long_lines.max);
+            Box::new(files.into_iter().flat_map(move |(path, first_line)| {
+                let blocks: Box<dyn Iterator<Item = _> + Send> = match ReadBlocks::open(&path, first_line, block_size) {
+                    Ok(blocks) => Box::new(match max_line {
+                        Some(max) => blocks.max_line(max),
+                        None => blocks,
+                    }),
+                    Err(e) => Box::new(std::iter::once(Err(e))),
+                };
+                blocks.map(|block| block.map(|b| Box::new(b) as Box<dyn Chunk>))
+            })) as Source
+        }
+        Reader::Mapped => {
+            *mapped = files
+                .iter()
+                .map(|(path, first_line)| Ok((*first_line, Input::open(path)?)))
+                .collect::<io::Result<Vec<_>>>()?;
+            let mapped: &'a Vec<_> = mapped;
+            let chunk_lines = tuned.chunk_lines;
+            Box::new(
+                mapped
+                    .iter()
+                    .flat_map(move |(first_line, input)| pipeline::slices(input.bytes(), *first_line, chunk_lines))
+                    .map(|slice| Ok(Box::new(slice) as Box<dyn Chunk>)),
+            ) as Source
+        }
+    })
+}
+
+/// The blocks of every file, each read by its own reader from `open`,
+/// opened when the previous one is done.
+#[cfg(any(all(target_os = "linux", feature = "io-uring"), windows))]
+fn block_source<'a, R>(
+    files: Vec<(PathBuf, usize)>,
+    limits: &Limits,
+    open: fn(&Path, usize, usize, usize) -> io::Result<R>,
+) -> Source<'a>
+where
+    R: Iterator<Item = io::Result<crate::blocks::Block>> + Send + 'static,
+{
+    let (block_size, ring_depth) = (limits.block_size, limits.ring_depth);
+    let blocks = files.into_iter().flat_map(move |(path, first_line)| {
+        let reader: Box<dyn Iterator<Item = _> + Send> = match open(&path, first_line, block_size, ring_depth) {
+            Ok(reader) => Box::new(reader),
+            Err(e) => Box::new(std::iter::once(Err(e))),
+        };
+        reader
+    });
+    Box::new(blocks.map(|block| block.map(|b| Box::new(b) as Box<dyn Chunk>)))
+}
+
+/// Lines the outcome cache is asked to hold, and the fewer `budget` leaves
+/// room for under `max_memory`; `None` without a cache.
+pub fn cache_lines(config: &EngineConfig, budget: &Budget) -> Option<(usize, usize)> {
+    let asked = match (config.cache, &config.cache_file) {
+        (None, None) => return None,
+        (capacity, _) => capacity.unwrap_or(cache::DEFAULT_CAPACITY),
+    };
+    Some((asked, budget.cache_lines.map_or(asked, |lines| lines.min(asked))))
+}
+
+/// The outcome cache `config` asks for, and whether it was loaded from its
+/// file. `settings` says what the outcomes are of; a cache file made with
+/// other settings is started afresh.
+pub fn open_cache(config: &EngineConfig, settings: &[u8], budget: &Budget) -> Result<Option<(OutcomeCache, bool)>, String> {
+    let Some((_, capacity)) = cache_lines(config, budget) else {
+        return Ok(None);
+    };
+    match &config.cache_file {
+        Some(path) => OutcomeCache::load(path, capacity, settings).map(Some),
+        None => Ok(Some((OutcomeCache::new(capacity, settings), false))),
+    }
+}
+
+/// What `validate` makes of `line`, unless `cache` knows there's nothing
+/// valid on it; the outcome is remembered there. A line known to be valid
+/// is validated again, to have its phrase.
+pub fn validate_cached(
+    cache: Option<&OutcomeCache>,
+    line: &str,
+    validate: impl FnOnce(&str) -> Option<Mnemonic>,
+) -> Option<Mnemonic> {
+    let Some(cache) = cache else {
+        return validate(line);
+    };
+    let key = cache.key(line);
+    if let Some(Outcome::Invalid | Outcome::Unmatched(_)) = cache.get(key) {
+        return None;
+    }
+    let mnemonic = validate(line);
+    cache.insert(key, if mnemonic.is_some() { Outcome::Valid } else { Outcome::Invalid });
+    mnemonic
+}
+
+/// One file validated as an [`EngineConfig`] says, for front ends that
+/// have no options of their own beyond it.
+pub struct FileRun<'a> {
+    pub config: &'a EngineConfig,
+    pub input: &'a Path,
+    /// Where the hits go, if to a file: `min_free_space` is checked there.
+    pub output: Option<&'a Path>,
+    /// What `validate` does, e.g. `english`; a cache file made for
+    /// something else is started afresh.
+    pub validation: &'a str,
+    pub events: Option<Arc<Events>>,
+    /// Used instead of a throttle for `max_cpu`, e.g. one a slider changes
+    /// during the run.
+    pub throttle: Option<Arc<Throttle>>,
+}
+
+impl FileRun<'_> {
+    /// Validates every line of the input until done or `cancel` stops it,
+    /// and returns how many lines there are.
+    pub fn run(
+        &self,
+        cancel: &CancelToken,
+        validate: impl Fn(&str) -> Option<Mnemonic> + Sync,
+        write: impl FnMut(Hit) -> io::Result<()>,
+    ) -> Result<usize, String> {
+        let config = self.config;
+        let read_error = |e: io::Error| format!("Error reading '{}': {}", self.input.display(), e);
+        if let Some(output) = self.output {
+            check_free_space(config, &[output.to_path_buf()])?;
+        }
+        let (reader, _) = Reader::choose(config)?;
+        // Counted in blocks, so that the file isn't kept resident under a cap
+        let mut total_lines = 0;
+        for block in ReadBlocks::open(self.input, 0, 1 << 20).map_err(read_error)? {
+            total_lines += lines::lines(block.map_err(read_error)?.bytes()).count();
+        }
+        let total_bytes = std::fs::metadata(self.input).map_err(read_error)?.len();
+
+        let probe = probe(config, self.input, |line| validate(line).is_some()).map_err(read_error)?;
+        let profile = probe.map(|probe| probe.profile());
+        let avg_line = (total_bytes as usize).checked_div(total_lines).unwrap_or(128).max(1);
+        let growing = Growing {
+            cache: cache_lines(config, &Budget::default()).is_some(),
+            ..Growing::default()
+        };
+        let tuned = Tuned::new(config, validators(config, profile), profile, growing, avg_line)?;
+
+        let settings = format!("{} {} {:?}", env!("CARGO_PKG_VERSION"), self.validation, config.long_lines);
+        let cache = open_cache(config, settings.as_bytes(), &tuned.budget)?.map(|(cache, _)| cache);
+        let mut mapped = Vec::new();
+        let source = source(config, reader, vec![(self.input.to_path_buf(), 0)], &tuned, &mut mapped).map_err(read_error)?;
+        let mut pipeline = Pipeline {
+            events: self.events.clone(),
+            ..tuned.pipeline(config)
+        };
+        if let Some(throttle) = &self.throttle {
+            pipeline.throttle = Some(throttle.clone());
+        }
+        let validate = |_: usize, line: &str| validate_cached(cache.as_ref(), line, &validate);
+        pipeline
+            .run(source, 0..total_lines, cancel, validate, write)
+            .map_err(|e| e.to_string())?;
+        if let (Some(cache), Some(path)) = (&cache, &config.cache_file) {
+            cache.save(path).map_err(|e| format!("can't save the cache to '{}': {}", path.display(), e))?;
+        }
+        Ok(total_lines)
+    }
+}
@@ -0,0 +1,141 @@
+//! Time-remaining estimates for progress output.
+//!
+//! Throughput differs a lot between dense and sparse regions of a dump, so
+//! instead of the run-wide average the estimate follows an exponential
+//! moving average of recent throughput. The slowest and fastest of the last
+//! few measurements give a best/worst-case range around it.
+
+use std::{
+    collections::VecDeque,
+    fmt,
+    time::{Duration, Instant},
+};
+
+/// Time constant of the moving average: older throughput fades out over
+/// roughly this long.
+const SMOOTHING: Duration = Duration::from_secs(30);
+/// Measurements closer together than this are merged.
+const MIN_INTERVAL: Duration = Duration::from_millis(500);
+/// Recent measurements kept for the best/worst-case range.
+const WINDOW: usize = 20;
+
+pub struct EtaEstimator {
+    last: (Instant, usize),
+    average: Option<f64>,
+    recent: VecDeque<f64>,
+}
+
+/// Remaining time at the smoothed rate, and at the fastest and slowest
+/// recent rates.
+#[derive(Debug, Clone, Copy)]
+pub struct Eta {
+    pub expected: Duration,
+    pub best: Duration,
+    pub worst: Duration,
+}
+
+impl EtaEstimator {
+    /// Starts measuring from `done` units (lines) at `start`.
+    pub fn new(start: Instant, done: usize) -> Self {
+        Self {
+            last: (start, done),
+            average: None,
+            recent: VecDeque::with_capacity(WINDOW),
+        }
+    }
+
+    /// Records that `done` units are finished by `now`.
+    pub fn update(&mut self, now: Instant, done: usize) {
+        let (then, done_then) = self.last;
+        let elapsed = now.saturating_duration_since(then);
+        if elapsed < MIN_INTERVAL || done < done_then {
+            return;
+        }
+        let rate = (done - done_then) as f64 / elapsed.as_secs_f64();
+        self.last = (now, done);
+
+        // Weight by the time covered, so irregular updates average evenly
+        let weight = 1.0 - (-elapsed.as_secs_f64() / SMOOTHING.as_secs_f64()).exp();
+        self.average = Some(match self.average {
+            Some(average) => average + weight * (rate - average),
+            None => rate,
+        });
+
+        if self.recent.len() == WINDOW {
+            self.recent.pop_front();
+        }
+        self.recent.push_back(rate);
+    }
+
+    /// `None` until there is a usable measurement.
+    pub fn estimate(&self, remaining: usize) -> Option<Eta> {
+        let average = self.average.filter(|&rate| rate > 0.01)?;
+        let fastest = self.recent.iter().copied().fold(average, f64::max);
+        let slowest = self.recent.iter().copied().fold(average, f64::min);
+
+        let at = |rate: f64| {
+            if rate > 0.01 {
+                Duration::from_secs_f64(remaining as f64 / rate)
+            } else {
+                Duration::MAX
+            }
+        };
+        Some(Eta {
+            expected: at(average),
+            best: at(fastest),
+            worst: at(slowest),
+        })
+    }
+}
+
+/// `MM:SS`, or `HH:MM:SS` from an hour up.
+pub fn format_duration(duration: Duration) -> String {
+    let total_secs = duration.as_secs();
+    let hours = total_secs / 3600;
+    let minutes = (total_secs % 3600) / 60;
+    let secs = total_secs % 60;
+
+    if hours > 0 {
+        format!("{:02}:{:02}:{:02}", hours, minutes, secs)
+    } else {
+        format!("{:02}:{:02}", minutes, secs)
+    }
+}
+
+/// A duration that may be far too long for `HH:MM:SS`: that up to 100
+/// hours, then days, then years (`3.2e9 years` past a million).
+pub fn format_span(seconds: f64) -> String {
+    const DAY: f64 = 86_400.0;
+    const YEAR: f64 = 365.25 * DAY;
+    if !seconds.is_finite() {
+        "forever".to_string()
+    } else if seconds < 100.0 * 3600.0 {
+        format_duration(Duration::from_secs_f64(seconds.max(0.0)))
+    } else if seconds < 2.0 * YEAR {
+        format!("{:.0} days", seconds / DAY)
+    } else if seconds < 1e6 * YEAR {
+        format!("{:.0} years", seconds / YEAR)
+    } else {
+        format!("{:.1e} years", seconds / YEAR)
+    }
+}
+
+impl fmt::Display for Eta {
+    /// `05:12 (03:40-09:55)`; the range is left out while it's a single
+    /// value.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let show = |d: Duration| {
+            if d == Duration::MAX {
+                "--:--".to_string()
+            } else {
+                format_duration(d)
+            }
+        };
+        let (expected, best, worst) = (show(self.expected), show(self.best), show(self.worst));
+        if best == worst {
+            write!(f, "{}", expected)
+        } else {
+            write!(f, "{} ({}-{})", expected, best, worst)
+        }
+    }
+}
@@ -0,0 +1,133 @@
+//! Callbacks for what happens during a validation, for embedders.
+//!
+//! A binding or a GUI registers closures on an [`Events`] and hands it to
+//! the [`Pipeline`](crate::pipeline::Pipeline), which calls them as it goes:
+//! each hit once it's written, progress at a steady interval with the
+//! counting and the time estimate done, the line a run could resume from,
+//! and the error that ends a run. Nothing has to poll a channel or count
+//! lines in its `validate`.
+//!
+//! The callbacks run on the thread that called
+//! [`Pipeline::run`](crate::pipeline::Pipeline::run), between hits, so a
+//! slow one holds up writing like a slow sink does.
+
+use crate::{eta::Eta, pipeline::Hit};
+use std::{io, time::Duration};
+
+/// How often [`Events::on_progress`] is called unless set otherwise.
+pub const PROGRESS_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Where a run has got to.
+#[derive(Debug, Clone)]
+pub struct Progress {
+    /// Lines validated.
+    pub processed: usize,
+    /// Hits written.
+    pub valid: usize,
+    /// Lines in the run, when its end is known.
+    pub total: Option<usize>,
+    pub elapsed: Duration,
+    /// Time left, once there's a rate to go by and the end is known.
+    pub eta: Option<Eta>,
+}
+
+impl Progress {
+    /// Lines a second, over the whole run.
+    pub fn per_second(&self) -> f64 {
+        match self.elapsed.as_secs_f64() {
+            secs if secs > 0.0 => self.processed as f64 / secs,
+            _ => 0.0,
+        }
+    }
+}
+
+type OnValid = Box<dyn Fn(&Hit) + Send + Sync>;
+type OnProgress = Box<dyn Fn(&Progress) + Send + Sync>;
+type OnCheckpoint = Box<dyn Fn(usize) + Send + Sync>;
+type OnError = Box<dyn Fn(&io::Error) + Send + Sync>;
+
+/// The callbacks registered for a run; any number for each event, called
+/// in the order they were added.
+pub struct Events {
+    valid: Vec<OnValid>,
+    progress: Vec<OnProgress>,
+    checkpoint: Vec<OnCheckpoint>,
+    error: Vec<OnError>,
+    pub progress_interval: Duration,
+}
+
+impl Default for Events {
+    fn default() -> Self {
+        Self {
+            valid: Vec::new(),
+            progress: Vec::new(),
+            checkpoint: Vec::new(),
+            error: Vec::new(),
+            progress_interval: PROGRESS_INTERVAL,
+        }
+    }
+}
+
+impl Events {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Calls `f` with each hit once the pipeline's `write` has taken it
+    /// without an error.
+    pub fn on_valid(mut self, f: impl Fn(&Hit) + Send + Sync + 'static) -> Self {
+        self.valid.push(Box::new(f));
+        self
+    }
+
+    /// Calls `f` every [`progress_interval`](Events::progress_interval),
+    /// and once more at the end.
+    pub fn on_progress(mut self, f: impl Fn(&Progress) + Send + Sync + 'static) -> Self {
+        self.progress.push(Box::new(f));
+        self
+    }
+
+    /// Calls `f` with a line every line before which is validated and its
+    /// hits written: where a checkpoint can resume from. It's called as
+    /// that line moves on, at most once per progress interval, and at the
+    /// end.
+    pub fn on_checkpoint(mut self, f: impl Fn(usize) + Send + Sync + 'static) -> Self {
+        self.checkpoint.push(Box::new(f));
+        self
+    }
+
+    /// Calls `f` with the error that stops the run, from reading the input
+    /// or from `write`.
+    pub fn on_error(mut self, f: impl Fn(&io::Error) + Send + Sync + 'static) -> Self {
+        self.error.push(Box::new(f));
+        self
+    }
+
+    pub(crate) fn wants_valid(&self) -> bool {
+        !self.valid.is_empty()
+    }
+
+    pub(crate) fn valid(&self, hit: &Hit) {
+        for f in &self.valid {
+            f(hit);
+        }
+    }
+
+    pub(crate) fn progress(&self, progress: &Progress) {
+        for f in &self.progress {
+            f(progress);
+        }
+    }
+
+    pub(crate) fn checkpoint(&self, line: usize) {
+        for f in &self.checkpoint {
+            f(line);
+        }
+    }
+
+    pub(crate) fn error(&self, error: &io::Error) {
+        for f in &self.error {
+            f(error);
+        }
+    }
+}
@@ -0,0 +1,219 @@
+//! `mnemonic_validator export`: a run's results as one sealed bundle, to
+//! hand to the client of a recovery.
+//!
+//! The bundle holds every line of the run's output files (all of them, as
+//! listed in its manifest, when the output was split), the derivations
+//! asked for of each phrase found in them, and the manifest itself. It's
+//! sealed to the client's public key, and `reveal` opens it with the
+//! secret key. The export is entered in the audit log, without the phrases.
+
+use bip39::Mnemonic;
+use clap::Args;
+use mnemonic_validator::{
+    address::{self, Kind},
+    audit::{self, AuditLog},
+    bip32::{ExtendedKey, HARDENED},
+    bundle::{self, Bundle, Hit},
+    descriptor, digest, fingerprint, manifest,
+    network::{self, Network},
+    paths, seal,
+    secret::{SecretString, Zeroizing},
+};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Derivation {
+    Fingerprint,
+    Descriptors,
+    Addresses,
+}
+
+fn parse_derivation(s: &str) -> Result<Derivation, String> {
+    match s.trim().to_ascii_lowercase().as_str() {
+        "fingerprint" => Ok(Derivation::Fingerprint),
+        "descriptors" => Ok(Derivation::Descriptors),
+        "addresses" => Ok(Derivation::Addresses),
+        _ => Err(format!("'{}' isn't a derivation; expected fingerprint, descriptors or addresses", s)),
+    }
+}
+
+#[derive(Args, Debug)]
+pub struct ExportArgs {
+    /// Output file of the run, with its manifest next to it.
+    #[clap(value_parser = paths::parser(), required_unless_present = "run")]
+    output: Option<PathBuf>,
+
+    /// Export the recorded run with this ID (see `runs list`) instead.
+    #[clap(long, value_name = "ID", conflicts_with = "output")]
+    run: Option<String>,
+
+    /// Format of the export; json-bundle is the one there is.
+    #[clap(long, value_name = "FORMAT", default_value = "json-bundle", value_parser = ["json-bundle"])]
+    format: String,
+
+    /// Bundle file to write.
+    #[clap(short = 'o', long = "bundle", value_name = "FILE", value_parser = paths::parser())]
+    bundle: PathBuf,
+
+    /// Public key of the client to seal the bundle to (hex, or a file holding it), as made by `reveal --new-key`.
+    #[clap(long, value_name = "KEY", value_parser = seal::parse_public_key)]
+    to: [u8; 33],
+
+    /// What to derive from each phrase, no passphrase: fingerprint, descriptors (of the first BIP84 account), addresses (the first --address-count receive addresses of its BIP44, BIP49 and BIP84 accounts).
+    #[clap(long, value_name = "WHAT,...", value_delimiter = ',', value_parser = parse_derivation)]
+    derive: Vec<Derivation>,
+
+    /// Receive addresses per account for --derive addresses.
+    #[clap(long, value_name = "N", default_value_t = 5)]
+    address_count: u32,
+
+    /// Network of the derivations: mainnet, testnet, signet, regtest, ethereum, or one of the coin registry.
+    #[clap(long, value_name = "NETWORK", default_value = "mainnet", value_parser = network::parse_network)]
+    network: Network,
+
+    /// Append the export to this audit log instead of the one in the data directory.
+    #[clap(long, value_name = "FILE", value_parser = paths::parser())]
+    audit_log: Option<PathBuf>,
+}
+
+/// The phrase of an output line: the whole line, or what follows the
+/// fingerprint and location `--fingerprint` and `--annotate` put before it.
+pub(crate) fn phrase_of(line: &str) -> Option<Mnemonic> {
+    std::iter::once(0)
+        .chain(line.match_indices(':').map(|(i, _)| i + 1))
+        .find_map(|start| Mnemonic::parse_normalized(line[start..].trim()).ok())
+}
+
+fn addresses(mnemonic: &Mnemonic, count: u32, network: Network) -> Vec<bundle::Address> {
+    let seed = Zeroizing::new(mnemonic.to_seed_normalized(""));
+    let Some(master) = ExtendedKey::master(&*seed) else {
+        return Vec::new();
+    };
+    let coin = network.coin_type();
+    let mut addresses = Vec::new();
+    for kind in Kind::ALL.into_iter().filter(|kind| kind.exists_on(network)) {
+        let Some(receive) = master.derive(&[kind.purpose() | HARDENED, coin | HARDENED, HARDENED, 0]) else {
+            continue;
+        };
+        for index in 0..count {
+            let Some(address) = receive
+                .child(index)
+                .and_then(|key| address::encode(kind, &key.public_key(), network))
+            else {
+                continue;
+            };
+            addresses.push(bundle::Address {
+                path: format!("m/{}'/{}'/0'/0/{}", kind.purpose(), coin, index),
+                address,
+            });
+        }
+    }
+    addresses
+}
+
+fn hit(file: &Path, line: &str, args: &ExportArgs) -> Hit {
+    let mnemonic = phrase_of(line);
+    let derive = |what| mnemonic.as_ref().filter(|_| args.derive.contains(&what));
+    Hit {
+        file: file.display().to_string(),
+        line: line.to_string(),
+        phrase: mnemonic.as_ref().map(|mnemonic| mnemonic.to_string()),
+        fingerprint: derive(Derivation::Fingerprint).and_then(fingerprint::master_fingerprint),
+        descriptors: derive(Derivation::Descriptors).and_then(|mnemonic| descriptor::bip84(mnemonic, args.network)),
+        addresses: derive(Derivation::Addresses)
+            .map(|mnemonic| addresses(mnemonic, args.address_count, args.network))
+            .unwrap_or_default(),
+    }
+}
+
+/// The directory the run's paths are relative to, its output file and its
+/// ID: the output named, or that of the recorded run `run`, whose paths are
+/// relative to where it ran.
+pub(crate) fn locate(
+    output: Option<&Path>,
+    run: Option<&str>,
+) -> Result<(PathBuf, PathBuf, Option<String>), Box<dyn std::error::Error>> {
+    match (output, run) {
+        (_, Some(id)) => {
+            let run = crate::runs::Run::load(id)?;
+            let cwd = PathBuf::from(&run.record.cwd);
+            let output = cwd.join(&run.record.output);
+            Ok((cwd, output, Some(run.record.id)))
+        }
+        (Some(output), None) => Ok((PathBuf::new(), output.to_path_buf(), None)),
+        (None, None) => Err("name the run's output file or --run".into()),
+    }
+}
+
+/// The manifest next to `output`, if there's one, and the output files it
+/// lists, relative to `cwd`: all of them when the output was split, or just
+/// `output`.
+pub(crate) fn output_files(cwd: &Path, output: &Path) -> Result<(Option<serde_json::Value>, Vec<PathBuf>), String> {
+    let manifest_path = manifest::manifest_path(output);
+    let manifest: Option<serde_json::Value> = match fs::read_to_string(&manifest_path) {
+        Ok(text) => Some(serde_json::from_str(&text).map_err(|e| format!("{} isn't a manifest: {}", manifest_path.display(), e))?),
+        Err(_) => {
+            eprintln!("Warning: no manifest at {}", manifest_path.display());
+            None
+        }
+    };
+    let mut files: Vec<PathBuf> = manifest
+        .as_ref()
+        .and_then(|manifest| manifest["outputs"].as_array())
+        .map(|outputs| outputs.iter().filter_map(|entry| entry["path"].as_str()).map(|path| cwd.join(path)).collect())
+        .unwrap_or_default();
+    if files.is_empty() {
+        files.push(output.to_path_buf());
+    }
+    Ok((manifest, files))
+}
+
+pub fn run(args: &ExportArgs) -> Result<(), Box<dyn std::error::Error>> {
+    if args.derive.contains(&Derivation::Descriptors) && args.network.hrp().is_none() {
+        return Err(format!("descriptors are of BIP84 accounts, and {} has no native SegWit", args.network).into());
+    }
+    let (cwd, output, run) = locate(args.output.as_deref(), args.run.as_deref())?;
+    let (manifest, files) = output_files(&cwd, &output)?;
+
+    let mut hits = Vec::new();
+    for file in &files {
+        let text = SecretString::new(fs::read_to_string(file).map_err(|e| format!("can't read {}: {}", file.display(), e))?);
+        hits.extend(text.lines().filter(|line| !line.trim().is_empty()).map(|line| hit(file, line, args)));
+    }
+    let phrases = hits.iter().filter(|hit| hit.phrase.is_some()).count();
+
+    let bundle = Bundle {
+        format: bundle::FORMAT.to_string(),
+        tool: manifest::Manifest::tool_name(),
+        created_at: chrono::Local::now().to_rfc3339(),
+        run: run.clone(),
+        network: args.network.name().to_string(),
+        manifest,
+        hits,
+    };
+    bundle::write(&args.bundle, &bundle, &args.to)?;
+
+    AuditLog::new(args.audit_log.clone().unwrap_or_else(audit::default_path), run)
+        .record(
+            "exported",
+            serde_json::json!({
+                "bundle": args.bundle,
+                "outputs": files,
+                "hits": bundle.hits.len(),
+                "sealed_to": digest::to_hex(&args.to),
+            }),
+        )
+        .map_err(|e| format!("can't write the audit log: {}", e))?;
+    println!(
+        "Bundle of {} line(s), {} with a phrase, from {} file(s) written to {}, sealed to {}",
+        bundle.hits.len(),
+        phrases,
+        files.len(),
+        args.bundle.display(),
+        digest::to_hex(&args.to)
+    );
+    Ok(())
+}
@@ -0,0 +1,144 @@
+//! Input files that couldn't be read, set aside so a run over several
+//! files or directories carries on without them.
+//!
+//! Such a file is moved into `failed/` next to the output, with the error
+//! in `<name>.error.json` beside it, and the run goes on with the others.
+//! `mnemonic_validator retry-failed` moves the files back to where they
+//! were and validates them again, once whatever was wrong (permissions, a
+//! flaky drive) is fixed; when there are several, one that fails again is
+//! set aside again.
+
+use clap::Args;
+use mnemonic_validator::paths;
+use serde::{Deserialize, Serialize};
+use std::{
+    ffi::OsString,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+const SIDECAR: &str = ".error.json";
+
+/// `output/valid_mnemonics.txt` -> `output/failed`
+pub fn failed_dir(output: &Path) -> PathBuf {
+    output.parent().unwrap_or(Path::new("")).join("failed")
+}
+
+/// Written next to a set-aside file as `<name>.error.json`.
+#[derive(Serialize, Deserialize, Debug)]
+struct Failure {
+    /// Where the file was read from, and goes back to.
+    path: PathBuf,
+    /// Where it is now; `None` if it couldn't be moved and is still there.
+    moved_to: Option<PathBuf>,
+    error: String,
+    failed_at: String,
+    run: Option<String>,
+}
+
+/// A name in `dir` that no other set-aside file has taken.
+fn free_name(dir: &Path, name: &std::ffi::OsStr) -> PathBuf {
+    let taken = |path: &Path| {
+        let mut sidecar = path.as_os_str().to_owned();
+        sidecar.push(SIDECAR);
+        path.exists() || Path::new(&sidecar).exists()
+    };
+    let mut candidate = dir.join(name);
+    let mut n = 1;
+    while taken(&candidate) {
+        n += 1;
+        let mut numbered = name.to_owned();
+        numbered.push(format!(".{}", n));
+        candidate = dir.join(numbered);
+    }
+    candidate
+}
+
+/// Moves `path` into `dir` with a record of `error`; returns where it went,
+/// or `None` if it couldn't be moved (onto another drive, say) and the
+/// record says so instead.
+pub fn set_aside(dir: &Path, path: &Path, error: &io::Error, run: Option<&str>) -> io::Result<Option<PathBuf>> {
+    fs::create_dir_all(dir)?;
+    let destination = free_name(dir, path.file_name().unwrap_or(path.as_os_str()));
+    let moved_to = fs::rename(path, &destination).ok().map(|()| destination.clone());
+    let failure = Failure {
+        path: std::path::absolute(path)?,
+        moved_to: moved_to.as_ref().map(std::path::absolute).transpose()?,
+        error: error.to_string(),
+        failed_at: chrono::Local::now().to_rfc3339(),
+        run: run.map(String::from),
+    };
+    let mut sidecar = destination.into_os_string();
+    sidecar.push(SIDECAR);
+    fs::write(sidecar, serde_json::to_string_pretty(&failure)?)?;
+    Ok(moved_to)
+}
+
+/// Options of `mnemonic_validator retry-failed`.
+#[derive(Args, Debug)]
+pub struct RetryFailedArgs {
+    /// Output file to add the hits to; its failed/ directory holds the files to retry.
+    #[clap(short, long, value_parser = paths::parser(), default_value = "output/valid_mnemonics.txt")]
+    output: PathBuf,
+
+    /// Take the files from this directory instead of the output's failed/.
+    #[clap(long, value_name = "DIR", value_parser = paths::parser())]
+    failed_dir: Option<PathBuf>,
+}
+
+/// Puts the set-aside file of `sidecar` back; returns where it is.
+fn restore(sidecar: &Path) -> Result<PathBuf, String> {
+    let text = fs::read_to_string(sidecar).map_err(|e| format!("can't read {}: {}", sidecar.display(), e))?;
+    let failure: Failure =
+        serde_json::from_str(&text).map_err(|e| format!("{} isn't a failure record: {}", sidecar.display(), e))?;
+    if let Some(moved_to) = &failure.moved_to {
+        if failure.path.exists() {
+            return Err(format!(
+                "can't put {} back: {} exists again",
+                moved_to.display(),
+                failure.path.display()
+            ));
+        }
+        if let Some(parent) = failure.path.parent() {
+            fs::create_dir_all(parent).map_err(|e| format!("can't recreate {}: {}", parent.display(), e))?;
+        }
+        fs::rename(moved_to, &failure.path)
+            .map_err(|e| format!("can't move {} back to {}: {}", moved_to.display(), failure.path.display(), e))?;
+    }
+    fs::remove_file(sidecar).map_err(|e| format!("can't remove {}: {}", sidecar.display(), e))?;
+    Ok(failure.path)
+}
+
+pub fn run(args: &RetryFailedArgs) -> Result<(), Box<dyn std::error::Error>> {
+    let dir = args.failed_dir.clone().unwrap_or_else(|| failed_dir(&args.output));
+    let mut sidecars: Vec<PathBuf> = match fs::read_dir(&dir) {
+        Ok(entries) => entries
+            .filter_map(|entry| Some(entry.ok()?.path()))
+            .filter(|path| path.to_string_lossy().ends_with(SIDECAR))
+            .collect(),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Vec::new(),
+        Err(e) => return Err(format!("can't read {}: {}", dir.display(), e).into()),
+    };
+    if sidecars.is_empty() {
+        println!("No failed files in {}.", dir.display());
+        return Ok(());
+    }
+    sidecars.sort();
+
+    let mut files = Vec::new();
+    for sidecar in &sidecars {
+        files.push(restore(sidecar)?);
+    }
+    println!("Retrying {} file(s) from {}", files.len(), dir.display());
+
+    let mut run_args: Vec<OsString> = vec![env!("CARGO_PKG_NAME").into()];
+    for file in &files {
+        run_args.push("-i".into());
+        run_args.push(file.into());
+    }
+    run_args.push("-o".into());
+    run_args.push(args.output.clone().into());
+    let cli = crate::parse_args(&run_args)?;
+    crate::run(&cli, &run_args[1..], None);
+    Ok(())
+}
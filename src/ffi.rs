@@ -0,0 +1,129 @@
+//! C ABI for embedding the validator in other languages (feature `ffi`).
+//!
+//! Build it as a shared library with
+//! `cargo rustc --release --lib --features ffi --crate-type cdylib` (or
+//! `staticlib`); `include/mnemonic_validator.h` declares what is exported
+//! here and is regenerated with cbindgen (see `cbindgen.toml`).
+//!
+//! Phrases are checked exactly as the command line checks them: English
+//! wordlist, typographic quotes and dashes cleaned up, pre-filter first. Nothing is copied or kept beyond a call.
+
+use crate::batch::{self, Outcome};
+use std::ffi::{c_char, CStr};
+
+/// Result of checking one phrase.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MvStatus {
+    Valid = 0,
+    Invalid = 1,
+    NullPointer = 2,
+    InvalidUtf8 = 3,
+}
+
+impl MvStatus {
+    fn message(self) -> &'static CStr {
+        match self {
+            MvStatus::Valid => c"valid BIP39 mnemonic",
+            MvStatus::Invalid => c"not a valid BIP39 mnemonic",
+            MvStatus::NullPointer => c"null pointer argument",
+            MvStatus::InvalidUtf8 => c"phrase is not valid UTF-8",
+        }
+    }
+}
+
+impl From<Outcome> for MvStatus {
+    fn from(outcome: Outcome) -> Self {
+        match outcome {
+            Outcome::Valid => MvStatus::Valid,
+            Outcome::Invalid => MvStatus::Invalid,
+        }
+    }
+}
+
+/// The phrase as a string, or why it isn't one.
+///
+/// # Safety
+///
+/// As for [`mv_validate`]; the string lives as long as the caller says.
+unsafe fn to_str<'a>(phrase: *const c_char) -> Result<&'a str, MvStatus> {
+    if phrase.is_null() {
+        return Err(MvStatus::NullPointer);
+    }
+    // Safety: a NUL-terminated string, as the caller guarantees
+    let phrase = unsafe { CStr::from_ptr(phrase) };
+    phrase.to_str().map_err(|_| MvStatus::InvalidUtf8)
+}
+
+/// Checks one phrase.
+///
+/// # Safety
+///
+/// `phrase` must be null or point to a NUL-terminated string that stays
+/// valid for the duration of the call.
+#[no_mangle]
+pub unsafe extern "C" fn mv_validate(phrase: *const c_char) -> MvStatus {
+    // Safety: passed on from the caller
+    match unsafe { to_str(phrase) } {
+        Ok(phrase) => batch::validate(phrase).into(),
+        Err(status) => status,
+    }
+}
+
+/// Checks `count` phrases, storing each one's status in `results`, and
+/// returns how many are valid. Returns 0 without touching `results` if
+/// either array is null. Large batches are checked on all CPUs, so this is
+/// much faster than calling [`mv_validate`] for each phrase.
+///
+/// # Safety
+///
+/// `phrases` and `results` must point to arrays of at least `count`
+/// elements; every element of `phrases` must be null or a NUL-terminated
+/// string.
+#[no_mangle]
+pub unsafe extern "C" fn mv_validate_batch(
+    phrases: *const *const c_char,
+    count: usize,
+    results: *mut MvStatus,
+) -> usize {
+    if phrases.is_null() || results.is_null() {
+        return 0;
+    }
+    // Safety: `phrases` holds `count` elements, as documented
+    let phrases = unsafe { std::slice::from_raw_parts(phrases, count) };
+    // Each phrase's status, or `None` until its outcome is in
+    let mut statuses = Vec::with_capacity(count);
+    let mut texts = Vec::with_capacity(count);
+    for &phrase in phrases {
+        // Safety: each element is null or a string, as documented
+        match unsafe { to_str(phrase) } {
+            Ok(phrase) => {
+                texts.push(phrase);
+                statuses.push(None);
+            }
+            Err(status) => statuses.push(Some(status)),
+        }
+    }
+    let outcomes = batch::validate_batch(&texts);
+    let mut outcome = outcomes.iter();
+    for (i, status) in statuses.into_iter().enumerate() {
+        let status = status.unwrap_or_else(|| (*outcome.next().expect("one outcome per phrase")).into());
+        // Safety: `results` has room for `count` elements, as documented.
+        // They're written without being read, as they may not hold an
+        // `MvStatus` yet.
+        unsafe { results.add(i).write(status) };
+    }
+    outcomes.iter().filter(|outcome| outcome.is_valid()).count()
+}
+
+/// A static, NUL-terminated description of `status`; never free it.
+#[no_mangle]
+pub extern "C" fn mv_status_message(status: MvStatus) -> *const c_char {
+    status.message().as_ptr()
+}
+
+/// The library version as a static, NUL-terminated string.
+#[no_mangle]
+pub extern "C" fn mv_version() -> *const c_char {
+    concat!(env!("CARGO_PKG_VERSION"), "\0").as_ptr().cast()
+}
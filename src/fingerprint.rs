@@ -0,0 +1,87 @@
+//! Master key fingerprints of valid phrases (`--fingerprint`), and hits
+//! grouped by them (`--group-by fingerprint`).
+//!
+//! The fingerprint is what wallets show to tell seeds apart: the first four
+//! bytes of the HASH160 of the master public key, without a passphrase.
+//! Hits with the same one are the same wallet, whichever way the phrase was
+//! written down (words, SeedQR digits, a numbered sheet). A phrase
+//! translated into another wordlist is not: the seed is made from the words
+//! themselves, so it gets a fingerprint of its own.
+
+use crate::{bip32::ExtendedKey, secret::Zeroizing};
+use bip39::Mnemonic;
+use std::{collections::BTreeMap, str::FromStr};
+
+/// The master fingerprint of `mnemonic` in hex, e.g. `73c5da0a`.
+pub fn master_fingerprint(mnemonic: &Mnemonic) -> Option<String> {
+    let seed = Zeroizing::new(mnemonic.to_seed_normalized(""));
+    let master = ExtendedKey::master(&*seed)?;
+    Some(master.fingerprint().iter().map(|byte| format!("{:02x}", byte)).collect())
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GroupBy {
+    Fingerprint,
+}
+
+impl FromStr for GroupBy {
+    type Err = String;
+
+    /// Accepts both `by=fingerprint` and plain `fingerprint`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.strip_prefix("by=").unwrap_or(s) {
+            "fingerprint" => Ok(GroupBy::Fingerprint),
+            other => Err(format!("unknown grouping '{}', expected by=fingerprint", other)),
+        }
+    }
+}
+
+/// Where the hits of each wallet were found, by fingerprint. Under
+/// `--max-memory` only so many hits are kept; those past it are counted.
+#[derive(Default)]
+pub struct Groups {
+    hits: BTreeMap<String, Vec<String>>,
+    /// Hits kept at most.
+    limit: Option<usize>,
+    kept: usize,
+    untracked: usize,
+}
+
+impl Groups {
+    /// Keeps no more than `limit` hits.
+    pub fn with_limit(limit: usize) -> Self {
+        Self {
+            limit: Some(limit),
+            ..Self::default()
+        }
+    }
+
+    /// Records a hit at `location`, e.g. `dump.txt:17`.
+    pub fn add(&mut self, fingerprint: String, location: String) {
+        if self.limit.is_some_and(|limit| self.kept >= limit) {
+            self.untracked += 1;
+            return;
+        }
+        self.kept += 1;
+        self.hits.entry(fingerprint).or_default().push(location);
+    }
+
+    /// Hits left out once the limit was reached.
+    pub fn untracked(&self) -> usize {
+        self.untracked
+    }
+
+    /// Number of different wallets among the hits.
+    pub fn wallets(&self) -> usize {
+        self.hits.len()
+    }
+
+    /// The wallets found more than once, with their locations in the order
+    /// they were found.
+    pub fn repeated(&self) -> impl Iterator<Item = (&str, &[String])> {
+        self.hits
+            .iter()
+            .filter(|(_, locations)| locations.len() > 1)
+            .map(|(fingerprint, locations)| (fingerprint.as_str(), locations.as_slice()))
+    }
+}
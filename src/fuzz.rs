@@ -0,0 +1,101 @@
+//! One entry point through every layer that takes apart untrusted input,
+//! for fuzzers.
+//!
+//! Inputs are dumps of unknown origin, so splitting them into lines,
+//! normalizing and cleaning each line, splitting it into words, finding the
+//! candidate phrases of every layout and checking them must never panic,
+//! whatever the bytes. [`fuzz_one`] runs a buffer through all of it and
+//! also checks that the fast paths agree with the plain ones, so that a
+//! fuzzer finds both crashes and wrong answers. `fuzz/` holds the cargo-fuzz
+//! target: `cargo +nightly fuzz run fuzz_one`.
+
+use crate::{diagnostics, layout, lines, prefilter, seedqr, tokens, typography};
+use bip39::{Language, Mnemonic};
+use std::ops::ControlFlow;
+
+/// Lines a window is made of, as many as any layout reads ahead.
+const WINDOW: usize = 24;
+
+/// Runs `data` through line splitting, normalization, tokenization,
+/// candidate extraction in every layout and validation; panics if one of
+/// them does, or if two ways of computing the same thing disagree.
+pub fn fuzz_one(data: &[u8]) {
+    // The first byte picks the layout, as a fuzzer mutates it like any other
+    let (layout, data) = match data.split_first() {
+        Some((&first, rest)) => (layout_of(first), rest),
+        None => (layout::Layout::default(), data),
+    };
+
+    let mut count = 0;
+    let mut texts = Vec::new();
+    for line in lines::lines(data) {
+        count += 1;
+        assert!(
+            !line.iter().any(|&byte| byte == b'\n' || byte == b'\r'),
+            "a line holds a break"
+        );
+        let line = lines::normalize(line);
+        if let Ok(text) = std::str::from_utf8(line) {
+            check_line(text);
+            // Suggestions are looked for across the whole wordlist, which on
+            // every line would slow a fuzzer to a crawl
+            if count == 1 {
+                check_diagnostics(text);
+            }
+            texts.push(text);
+        }
+    }
+    let breaks = lines::breaks(data).count();
+    assert!(count == breaks || count == breaks + 1, "{} lines but {} breaks", count, breaks);
+    if let Some(last) = lines::last_break(data) {
+        assert!(last <= data.len(), "break past the end");
+    }
+
+    for start in 0..texts.len() {
+        let end = texts.len().min(start + layout.window());
+        let window = texts[start..end].join(&layout::LINE_BREAK.to_string());
+        let _ = layout.each_candidate(&window, |_, candidate| {
+            check_phrase(candidate);
+            ControlFlow::<()>::Continue(())
+        });
+    }
+}
+
+fn layout_of(byte: u8) -> layout::Layout {
+    layout::Layout {
+        delimiter: (byte & 1 != 0).then_some(';'),
+        numbered: byte & 2 != 0,
+        join_lines: (byte & 4 != 0).then_some(WINDOW / 4),
+        seedqr: byte & 8 != 0,
+        compact_seedqr: byte & 16 != 0,
+    }
+}
+
+fn check_line(line: &str) {
+    let cleaned = typography::clean(line);
+    let text = cleaned.as_deref().map_or(line, String::as_str);
+    assert!(text.len() <= line.len(), "cleaning made the line longer");
+    let _ = seedqr::decode_standard(text);
+    let _ = seedqr::decode_compact(text);
+    check_phrase(text);
+}
+
+fn check_diagnostics(line: &str) {
+    let words = diagnostics::analyze(line);
+    for (position, word) in words.iter().enumerate() {
+        assert_eq!(word.position, position);
+        assert!(line.get(word.span.clone()).is_some(), "span off a character boundary");
+    }
+}
+
+fn check_phrase(phrase: &str) {
+    assert!(
+        tokens::words(phrase).eq(phrase.split_whitespace()),
+        "byte tokenizer disagrees with split_whitespace"
+    );
+    let full = Mnemonic::parse_in_normalized(Language::English, phrase).ok();
+    if !prefilter::may_be_valid(phrase) {
+        assert!(full.is_none(), "the pre-filter rejects a valid phrase");
+    }
+    assert_eq!(prefilter::parse(phrase), full, "fast parse disagrees with bip39");
+}
@@ -0,0 +1,93 @@
+//! Shell-style patterns for picking files out of input directories.
+//!
+//! `*` matches within a path component, `**` across components, `?` one
+//! character and `[abc]`/`[!abc]` one of a set. A pattern without a `/`
+//! matches file names, wherever they are; one with a `/` matches the path
+//! below the input directory, from its start.
+
+use regex::Regex;
+use std::{path::Path, str::FromStr};
+
+#[derive(Clone, Debug)]
+pub struct Glob {
+    pattern: String,
+    regex: Regex,
+    /// Matched against the whole relative path rather than the file name.
+    path: bool,
+}
+
+impl Glob {
+    pub fn as_str(&self) -> &str {
+        &self.pattern
+    }
+
+    /// Whether `relative`, a path below an input directory, matches.
+    pub fn matches(&self, relative: &Path) -> bool {
+        if self.path {
+            let components: Vec<_> = relative.iter().map(|c| c.to_string_lossy()).collect();
+            self.regex.is_match(&components.join("/"))
+        } else {
+            relative
+                .file_name()
+                .is_some_and(|name| self.regex.is_match(&name.to_string_lossy()))
+        }
+    }
+}
+
+impl FromStr for Glob {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut regex = String::from("^");
+        let mut chars = s.trim_start_matches("./").chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '*' if chars.peek() == Some(&'*') => {
+                    chars.next();
+                    // `**/` may match no directories at all
+                    if chars.peek() == Some(&'/') {
+                        chars.next();
+                        regex.push_str("(?:.*/)?");
+                    } else {
+                        regex.push_str(".*");
+                    }
+                }
+                '*' => regex.push_str("[^/]*"),
+                '?' => regex.push_str("[^/]"),
+                '[' => {
+                    let mut class = String::new();
+                    let mut closed = false;
+                    for c in chars.by_ref() {
+                        if c == ']' && !class.is_empty() {
+                            closed = true;
+                            break;
+                        }
+                        class.push(c);
+                    }
+                    if !closed {
+                        return Err(format!("'{}' has a '[' without its ']'", s));
+                    }
+                    let negated = class.strip_prefix('!');
+                    regex.push('[');
+                    if negated.is_some() {
+                        regex.push('^');
+                    }
+                    for c in negated.unwrap_or(&class).chars() {
+                        if matches!(c, '\\' | '[' | ']' | '^' | '&' | '~') {
+                            regex.push('\\');
+                        }
+                        regex.push(c);
+                    }
+                    regex.push(']');
+                }
+                c => regex.push_str(&regex::escape(&c.to_string())),
+            }
+        }
+        regex.push('$');
+        Ok(Self {
+            pattern: s.to_string(),
+            regex: Regex::new(&regex).map_err(|e| format!("'{}' isn't a valid pattern: {}", s, e))?,
+            path: s.contains('/'),
+        })
+    }
+}
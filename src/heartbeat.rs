@@ -0,0 +1,89 @@
+//! A status file for watchdogs, rewritten every few seconds while a run
+//! makes progress.
+//!
+//! A supervisor that can't tell a slow run from a hung one can read the
+//! file's `updated_at` (or its modification time): the file is refreshed as
+//! lines are validated, so a run that stops getting anywhere stops
+//! refreshing it, and can be killed and restarted from its checkpoint. The
+//! file is replaced in one rename, so it's never seen half written.
+
+use serde::Serialize;
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+/// What the file holds.
+#[derive(Serialize, Debug)]
+pub struct Status<'a> {
+    pub pid: u32,
+    pub run: Option<&'a str>,
+    /// `running`, then `done` once the run has finished, or `stopped` at a
+    /// time limit.
+    pub status: &'a str,
+    /// The input file being validated.
+    pub file: &'a Path,
+    /// Position in the combined input, as in the checkpoint.
+    pub line: usize,
+    pub valid: usize,
+    pub updated_at: String,
+}
+
+impl<'a> Status<'a> {
+    pub fn new(run: Option<&'a str>, status: &'a str, file: &'a Path, line: usize, valid: usize) -> Self {
+        Self {
+            pid: std::process::id(),
+            run,
+            status,
+            file,
+            line,
+            valid,
+            updated_at: chrono::Local::now().to_rfc3339(),
+        }
+    }
+}
+
+pub struct Heartbeat {
+    path: PathBuf,
+    interval: Duration,
+    last: Mutex<Option<Instant>>,
+}
+
+impl Heartbeat {
+    pub fn new(path: PathBuf, interval: Duration) -> Self {
+        Self {
+            path,
+            interval,
+            last: Mutex::new(None),
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Writes the status `status` gives if the last write was at least an
+    /// interval ago. Another thread already writing one skips it.
+    pub fn beat<'a>(&self, status: impl FnOnce() -> Status<'a>) -> io::Result<()> {
+        let Ok(mut last) = self.last.try_lock() else {
+            return Ok(());
+        };
+        if last.is_some_and(|last| last.elapsed() < self.interval) {
+            return Ok(());
+        }
+        *last = Some(Instant::now());
+        self.write(&status())
+    }
+
+    /// Writes `status` now, as at the start and the end of a run.
+    pub fn write(&self, status: &Status) -> io::Result<()> {
+        let mut temporary = self.path.as_os_str().to_owned();
+        temporary.push(".tmp");
+        let mut json = serde_json::to_vec_pretty(status)?;
+        json.push(b'\n');
+        fs::write(&temporary, json)?;
+        fs::rename(&temporary, &self.path)
+    }
+}
@@ -0,0 +1,261 @@
+//! The local hit index: every hit imported on this machine, from result
+//! bundles, runs' output files and other machines' indexes (`import`), in
+//! one place.
+//!
+//! Hits are keyed by the digest their masked lines show (see
+//! [`mask`](crate::mask)), so a masked hit and the same hit with its phrase
+//! are one entry. An entry keeps what each import told of it: the phrase
+//! if it was there, the fingerprint, descriptors and addresses, and where
+//! it came from. A hit imported again whose phrase, fingerprint or an
+//! address disagrees with the entry is a conflict, and [`OnConflict`]
+//! decides what becomes of it.
+//!
+//! The index holds phrases, like the output files it's filled from. It's
+//! one JSON file, readable by its owner only where the platform allows,
+//! and replaced whole when it's saved.
+
+use crate::{
+    bundle::Address,
+    lock::RunLock,
+    secret::Zeroize,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    fs::{self, OpenOptions},
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+/// `format` of the index files written.
+pub const FORMAT: &str = "mnemonic_validator-hits/1";
+
+/// `~/.local/share/mnemonic_validator/hits.json` on Linux.
+pub fn default_path() -> PathBuf {
+    dirs::data_local_dir()
+        .or_else(dirs::home_dir)
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join("mnemonic_validator")
+        .join("hits.json")
+}
+
+/// Where an import of an entry came from.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct Source {
+    /// The bundle, output file or index imported.
+    pub origin: String,
+    /// ID of the run it was found by, if known.
+    pub run: Option<String>,
+    /// Machine it was imported on.
+    pub host: String,
+    pub imported_at: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct Entry {
+    /// `None` while only masked lines of it were imported.
+    pub phrase: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fingerprint: Option<String>,
+    /// Network of the descriptors and addresses.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub network: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub descriptors: Option<[String; 2]>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub addresses: Vec<Address>,
+    pub sources: Vec<Source>,
+}
+
+impl Drop for Entry {
+    fn drop(&mut self) {
+        self.phrase.zeroize();
+    }
+}
+
+impl Entry {
+    /// What in `other` contradicts this entry, if anything. Derivations for
+    /// another network don't.
+    fn conflict(&self, other: &Entry) -> Option<String> {
+        if let (Some(a), Some(b)) = (&self.phrase, &other.phrase) {
+            if a != b {
+                return Some("another phrase has the same digest".to_string());
+            }
+        }
+        if let (Some(a), Some(b)) = (&self.fingerprint, &other.fingerprint) {
+            if a != b {
+                return Some(format!("fingerprint {} where the index has {}", b, a));
+            }
+        }
+        if self.network.is_some() && self.network != other.network {
+            return None;
+        }
+        if let (Some(a), Some(b)) = (&self.descriptors, &other.descriptors) {
+            if a != b {
+                return Some("other descriptors".to_string());
+            }
+        }
+        other.addresses.iter().find_map(|theirs| {
+            let ours = self.addresses.iter().find(|ours| ours.path == theirs.path)?;
+            (ours.address != theirs.address)
+                .then(|| format!("{} at {} where the index has {}", theirs.address, theirs.path, ours.address))
+        })
+    }
+
+    /// Adds what `other` knows and this entry doesn't.
+    fn fill(&mut self, other: &Entry) -> bool {
+        let mut changed = false;
+        if self.phrase.is_none() && other.phrase.is_some() {
+            self.phrase = other.phrase.clone();
+            changed = true;
+        }
+        if self.fingerprint.is_none() && other.fingerprint.is_some() {
+            self.fingerprint = other.fingerprint.clone();
+            changed = true;
+        }
+        if self.network.is_none() || self.network == other.network {
+            if self.network.is_none() && (other.descriptors.is_some() || !other.addresses.is_empty()) {
+                self.network = other.network.clone();
+            }
+            if self.descriptors.is_none() && other.descriptors.is_some() {
+                self.descriptors = other.descriptors.clone();
+                changed = true;
+            }
+            for address in &other.addresses {
+                if !self.addresses.iter().any(|ours| ours.path == address.path) {
+                    self.addresses.push(address.clone());
+                    changed = true;
+                }
+            }
+        }
+        for source in &other.sources {
+            if !self.sources.contains(source) {
+                self.sources.push(source.clone());
+            }
+        }
+        changed
+    }
+}
+
+/// What to do with a hit that conflicts with its entry.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OnConflict {
+    /// Import nothing.
+    Fail,
+    /// Keep the entry as it is.
+    Keep,
+    /// Replace the entry with the hit.
+    Replace,
+}
+
+pub fn parse_on_conflict(s: &str) -> Result<OnConflict, String> {
+    match s.trim().to_ascii_lowercase().as_str() {
+        "fail" => Ok(OnConflict::Fail),
+        "keep" => Ok(OnConflict::Keep),
+        "replace" => Ok(OnConflict::Replace),
+        _ => Err(format!("'{}' isn't a conflict policy; expected fail, keep or replace", s)),
+    }
+}
+
+/// What an import did with a hit.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Merged {
+    Added,
+    /// Known, and the hit told more of it.
+    Updated,
+    Unchanged,
+    Conflict(String),
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct IndexFile {
+    format: String,
+    /// By digest.
+    hits: BTreeMap<String, Entry>,
+}
+
+/// An index file, locked for as long as it's open.
+pub struct Index {
+    path: PathBuf,
+    pub hits: BTreeMap<String, Entry>,
+    _lock: RunLock,
+}
+
+/// Reads an index file, as `import` does another machine's.
+pub fn read(path: &Path) -> Result<BTreeMap<String, Entry>, String> {
+    let text = fs::read_to_string(path).map_err(|e| format!("can't read {}: {}", path.display(), e))?;
+    let file: IndexFile =
+        serde_json::from_str(&text).map_err(|e| format!("{} isn't a hit index this version reads: {}", path.display(), e))?;
+    if file.format != FORMAT {
+        return Err(format!("{} is a hit index of format '{}', not '{}'", path.display(), file.format, FORMAT));
+    }
+    Ok(file.hits)
+}
+
+/// Whether the file at `path` looks like a hit index.
+pub fn is_index(path: &Path) -> bool {
+    let mut start = [0; 64];
+    let read = fs::File::open(path).and_then(|mut file| std::io::Read::read(&mut file, &mut start));
+    read.is_ok_and(|n| String::from_utf8_lossy(&start[..n]).contains(FORMAT))
+}
+
+impl Index {
+    /// Opens the index at `path`, empty if there's none yet.
+    pub fn open(path: &Path) -> Result<Index, String> {
+        let mut lock = RunLock::default();
+        lock.add(path, &format!("process {} (import)", std::process::id()))?;
+        let hits = if path.exists() { read(path)? } else { BTreeMap::new() };
+        Ok(Index {
+            path: path.to_path_buf(),
+            hits,
+            _lock: lock,
+        })
+    }
+
+    /// Merges `hit` into the entry of `digest`. With [`OnConflict::Fail`] a
+    /// conflicting hit is left out, for the caller to give up.
+    pub fn merge(&mut self, digest: &str, hit: Entry, on_conflict: OnConflict) -> Merged {
+        let Some(entry) = self.hits.get_mut(digest) else {
+            self.hits.insert(digest.to_string(), hit);
+            return Merged::Added;
+        };
+        if let Some(conflict) = entry.conflict(&hit) {
+            if on_conflict == OnConflict::Replace {
+                *entry = hit;
+            }
+            return Merged::Conflict(conflict);
+        }
+        if entry.fill(&hit) {
+            Merged::Updated
+        } else {
+            Merged::Unchanged
+        }
+    }
+
+    /// Writes the index back, in place of the old file.
+    pub fn save(&self) -> Result<(), String> {
+        let file = IndexFile {
+            format: FORMAT.to_string(),
+            hits: self.hits.clone(),
+        };
+        let mut json = serde_json::to_vec_pretty(&file).map_err(|e| e.to_string())?;
+        drop(file);
+        if let Some(parent) = self.path.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+            fs::create_dir_all(parent).map_err(|e| format!("can't create {}: {}", parent.display(), e))?;
+        }
+        let mut temporary = self.path.as_os_str().to_owned();
+        temporary.push(".tmp");
+        let mut options = OpenOptions::new();
+        options.write(true).create(true).truncate(true);
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+        let written = options.open(&temporary).and_then(|mut file| {
+            file.write_all(&json)?;
+            file.sync_all()
+        });
+        json.zeroize();
+        written
+            .and_then(|()| fs::rename(&temporary, &self.path))
+            .map_err(|e| format!("can't write {}: {}", self.path.display(), e))
+    }
+}
@@ -0,0 +1,149 @@
+use crossbeam_channel::{bounded, Sender};
+use mnemonic_validator::secret::{SecretString, Zeroizing};
+use std::{
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    thread::{self, JoinHandle},
+};
+
+/// A valid mnemonic waiting to be handed to the user command.
+struct Hit {
+    phrase: SecretString,
+    file: PathBuf,
+    line: usize,
+}
+
+/// Runs a user-supplied command for every valid mnemonic, with at most
+/// `concurrency` commands alive at the same time.
+///
+/// The template is split into arguments up front (shell-style quoting is
+/// honoured) and the command is started directly rather than through a
+/// shell, so the contents of a phrase are never interpreted as shell syntax.
+/// Placeholders are substituted per argument:
+///
+/// * `{}`     - the mnemonic phrase
+/// * `{line}` - the 1-based line number in its input file
+/// * `{file}` - the path of that input file
+///
+/// The same values are exported as `MV_PHRASE`, `MV_LINE` and `MV_FILE` so
+/// scripts can avoid having the phrase show up in the process list.
+///
+/// Queued phrases and the expanded arguments are wiped once the command has
+/// been started; only the copies held by the child process remain.
+pub struct HookRunner {
+    sender: Option<Sender<Hit>>,
+    workers: Vec<JoinHandle<()>>,
+    failures: Arc<AtomicUsize>,
+}
+
+impl HookRunner {
+    pub fn new(template: &str, concurrency: usize) -> Result<Self, Box<dyn std::error::Error>> {
+        let args = match shlex::split(template) {
+            Some(args) if !args.is_empty() => args,
+            _ => return Err(format!("Invalid --exec-per-valid command: '{}'", template).into()),
+        };
+
+        let concurrency = concurrency.max(1);
+        let (sender, receiver) = bounded::<Hit>(concurrency);
+        let failures = Arc::new(AtomicUsize::new(0));
+
+        let workers = (0..concurrency)
+            .map(|_| {
+                let receiver = receiver.clone();
+                let failures = failures.clone();
+                let args = args.clone();
+
+                thread::spawn(move || {
+                    for hit in receiver {
+                        let line = hit.line.to_string();
+                        // Exact in MV_FILE; arguments are strings
+                        let file = hit.file.to_string_lossy();
+                        let expanded: Vec<Zeroizing<String>> =
+                            args.iter().map(|arg| expand(arg, &hit.phrase, &line, &file)).collect();
+
+                        let status = Command::new(expanded[0].as_str())
+                            .args(expanded[1..].iter().map(|arg| arg.as_str()))
+                            .env("MV_PHRASE", hit.phrase.as_str())
+                            .env("MV_LINE", &line)
+                            .env("MV_FILE", &hit.file)
+                            .stdin(Stdio::null())
+                            .status();
+
+                        // Never echo the phrase itself, only where it came from.
+                        match status {
+                            Ok(status) if status.success() => {}
+                            Ok(status) => {
+                                failures.fetch_add(1, Ordering::Relaxed);
+                                eprintln!("\nHook for {}:{} exited with {}", file, line, status);
+                            }
+                            Err(e) => {
+                                failures.fetch_add(1, Ordering::Relaxed);
+                                eprintln!("\nHook for {}:{} failed to start: {}", file, line, e);
+                            }
+                        }
+                    }
+                })
+            })
+            .collect();
+
+        Ok(Self {
+            sender: Some(sender),
+            workers,
+            failures,
+        })
+    }
+
+    /// Queues a hit. Blocks while all hook slots are busy, which keeps the
+    /// number of pending phrases bounded.
+    pub fn submit(&self, phrase: &str, file: &Path, line: usize) {
+        if let Some(sender) = &self.sender {
+            let _ = sender.send(Hit {
+                phrase: SecretString::new(phrase.to_string()),
+                file: file.to_path_buf(),
+                line,
+            });
+        }
+    }
+
+    /// Waits for every queued command to finish and returns how many failed.
+    pub fn finish(mut self) -> usize {
+        self.sender = None;
+        for worker in self.workers.drain(..) {
+            let _ = worker.join();
+        }
+        self.failures.load(Ordering::Relaxed)
+    }
+}
+
+/// Substitutes the placeholders of `arg` in one pass from left to right, so
+/// that a value put in is never itself searched for placeholders: a phrase
+/// or path containing `{line}` stays as it is.
+fn expand(arg: &str, phrase: &str, line: &str, file: &str) -> Zeroizing<String> {
+    // Room for every value up front: growing the string would leave copies
+    // of the phrase behind in the buffers it outgrew
+    let room = |placeholder: &str, value: &str| arg.matches(placeholder).count() * value.len();
+    let capacity = arg.len() + room("{}", phrase) + room("{line}", line) + room("{file}", file);
+    let mut expanded = Zeroizing::new(String::with_capacity(capacity));
+    let mut rest = arg;
+    while let Some(start) = rest.find('{') {
+        expanded.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let (value, placeholder) = if rest.starts_with("{}") {
+            (phrase, "{}")
+        } else if rest.starts_with("{line}") {
+            (line, "{line}")
+        } else if rest.starts_with("{file}") {
+            (file, "{file}")
+        } else {
+            ("{", "{")
+        };
+        expanded.push_str(value);
+        rest = &rest[placeholder.len()..];
+    }
+    expanded.push_str(rest);
+    expanded
+}
@@ -0,0 +1,81 @@
+//! The coordinator's HTTP, as its workers and its dashboard see it: the
+//! address of a coordinator, the token that lets a caller in, and a worker's
+//! JSON calls (over `ureq`). The coordinator serves it with `axum`.
+
+use sha2::{Digest, Sha256};
+use std::{
+    io::{self, Read},
+    time::Duration,
+};
+use subtle::ConstantTimeEq;
+
+/// Requests and responses larger than this are refused.
+pub const MAX_BODY: usize = 1 << 20;
+const TIMEOUT: Duration = Duration::from_secs(30);
+
+/// `http://host[:port]`, as given to `worker --coordinator`.
+#[derive(Clone, Debug)]
+pub struct Endpoint {
+    pub host: String,
+    pub port: u16,
+}
+
+impl std::fmt::Display for Endpoint {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.host.contains(':') {
+            true => write!(f, "http://[{}]:{}", self.host, self.port),
+            false => write!(f, "http://{}:{}", self.host, self.port),
+        }
+    }
+}
+
+pub fn parse_endpoint(s: &str) -> Result<Endpoint, String> {
+    let rest = match s.split_once("://") {
+        Some(("http", rest)) => rest,
+        Some(("https", _)) => return Err("https isn't supported by the coordinator; tunnel it".to_string()),
+        Some(_) => return Err(format!("invalid address '{}', expected http://host[:port]", s)),
+        None => s,
+    };
+    let address = rest.trim_end_matches('/');
+    let (host, port) = match address.rsplit_once(':') {
+        Some((host, port)) => (host, port.parse().map_err(|_| format!("invalid port in '{}'", s))?),
+        None => (address, 80),
+    };
+    if host.is_empty() || host.contains('/') {
+        return Err(format!("invalid address '{}', expected http://host[:port]", s));
+    }
+    Ok(Endpoint {
+        host: host.trim_start_matches('[').trim_end_matches(']').to_string(),
+        port,
+    })
+}
+
+/// Whether `given` is `token`, in time that doesn't depend on where they
+/// differ, or on the length of either.
+pub fn token_matches(given: Option<&str>, token: &str) -> bool {
+    let given = Sha256::digest(given.unwrap_or_default());
+    let token = Sha256::digest(token);
+    given.ct_eq(&token).into()
+}
+
+/// Posts a JSON `body` to `path` and returns the status and body of the
+/// response.
+pub fn post(endpoint: &Endpoint, path: &str, token: Option<&str>, body: &[u8]) -> io::Result<(u16, Vec<u8>)> {
+    let mut request = ureq::post(&format!("{}{}", endpoint, path))
+        .timeout(TIMEOUT)
+        .set("Content-Type", "application/json");
+    if let Some(token) = token {
+        request = request.set("Authorization", &format!("Bearer {}", token));
+    }
+    let response = match request.send_bytes(body) {
+        Ok(response) | Err(ureq::Error::Status(_, response)) => response,
+        Err(ureq::Error::Transport(e)) => return Err(io::Error::other(e)),
+    };
+    let status = response.status();
+    let mut reply = Vec::new();
+    response.into_reader().take(MAX_BODY as u64 + 1).read_to_end(&mut reply)?;
+    if reply.len() > MAX_BODY {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "response too large"));
+    }
+    Ok((status, reply))
+}
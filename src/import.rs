@@ -0,0 +1,187 @@
+//! `mnemonic_validator import`: results from other machines and
+//! engagements merged into the local hit index (see
+//! [`hits`](mnemonic_validator::hits)).
+//!
+//! It takes result bundles (opened with `--key`), the output files of runs
+//! (all of a split output, as its manifest lists them; masked lines too)
+//! and other hit indexes. Nothing is written if a hit conflicts with what
+//! the index has, unless `--on-conflict` says to keep or replace. Each
+//! import is entered in the audit log, without the phrases.
+
+use clap::Args;
+use mnemonic_validator::{
+    audit::{self, AuditLog},
+    bundle,
+    hits::{self, Entry, Index, Merged, OnConflict, Source},
+    mask, paths, seal,
+    secret::SecretString,
+};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+#[derive(Args, Debug)]
+pub struct ImportArgs {
+    /// Bundles written by `export`, runs' output files with their manifests next to them, or hit indexes.
+    #[clap(value_parser = paths::parser(), required_unless_present = "run")]
+    files: Vec<PathBuf>,
+
+    /// Import the output of the recorded run with this ID (see `runs list`) too.
+    #[clap(long, value_name = "ID")]
+    run: Option<String>,
+
+    /// Secret key file the bundles were sealed to.
+    #[clap(long, value_name = "FILE", value_parser = paths::parser())]
+    key: Option<PathBuf>,
+
+    /// What to do with a hit that contradicts the index: fail (import nothing), keep (the index's entry) or replace (it with the hit).
+    #[clap(long, value_name = "POLICY", default_value = "fail", value_parser = hits::parse_on_conflict)]
+    on_conflict: OnConflict,
+
+    /// Hit index to merge into instead of the one in the data directory.
+    #[clap(long, value_name = "FILE", value_parser = paths::parser())]
+    index: Option<PathBuf>,
+
+    /// Append the import to this audit log instead of the one in the data directory.
+    #[clap(long, value_name = "FILE", value_parser = paths::parser())]
+    audit_log: Option<PathBuf>,
+}
+
+/// The digest on a masked line, which has no phrase.
+fn masked_digest(line: &str) -> Option<String> {
+    let digest = line.split_whitespace().find_map(|word| word.strip_prefix("sha256:"))?;
+    (digest.len() == mask::FINGERPRINT_LEN && digest.bytes().all(|b| b.is_ascii_hexdigit())).then(|| digest.to_ascii_lowercase())
+}
+
+/// The hits in the file at `path`, by digest.
+fn read_hits(
+    path: &Path,
+    run: Option<&str>,
+    source: &dyn Fn(String, Option<String>) -> Source,
+    key: Option<&Path>,
+) -> Result<Vec<(String, Entry)>, Box<dyn std::error::Error>> {
+    let origin = path.display().to_string();
+    if bundle::is_bundle(path) {
+        let Some(key) = key else {
+            return Err(format!("{} is a bundle; name the secret key it was sealed to with --key", origin).into());
+        };
+        let bundle = bundle::open(path, &*seal::read_secret_key(key)?)?;
+        let hits = bundle.hits.iter().filter_map(|hit| {
+            let phrase = hit.phrase.as_ref()?;
+            let entry = Entry {
+                phrase: Some(phrase.clone()),
+                fingerprint: hit.fingerprint.clone(),
+                network: (hit.descriptors.is_some() || !hit.addresses.is_empty()).then(|| bundle.network.clone()),
+                descriptors: hit.descriptors.clone(),
+                addresses: hit.addresses.clone(),
+                sources: vec![source(origin.clone(), bundle.run.clone())],
+            };
+            Some((mask::fingerprint(phrase), entry))
+        });
+        return Ok(hits.collect());
+    }
+    if hits::is_index(path) {
+        let hits = hits::read(path)?;
+        return Ok(hits.into_iter().collect());
+    }
+
+    let (cwd, output, run) = crate::export::locate(Some(path), run)?;
+    let (_, files) = crate::export::output_files(&cwd, &output)?;
+    let mut hits = Vec::new();
+    for file in &files {
+        let text = SecretString::new(fs::read_to_string(file).map_err(|e| format!("can't read {}: {}", file.display(), e))?);
+        let source = source(file.display().to_string(), run.clone());
+        for line in text.lines().filter(|line| !line.trim().is_empty()) {
+            let hit = match crate::export::phrase_of(line) {
+                Some(mnemonic) => {
+                    let phrase = mnemonic.to_string();
+                    let digest = mask::fingerprint(&phrase);
+                    Some((digest, Some(phrase)))
+                }
+                None => masked_digest(line).map(|digest| (digest, None)),
+            };
+            if let Some((digest, phrase)) = hit {
+                hits.push((
+                    digest,
+                    Entry {
+                        phrase,
+                        fingerprint: None,
+                        network: None,
+                        descriptors: None,
+                        addresses: Vec::new(),
+                        sources: vec![source.clone()],
+                    },
+                ));
+            }
+        }
+    }
+    Ok(hits)
+}
+
+pub fn run(args: &ImportArgs) -> Result<(), Box<dyn std::error::Error>> {
+    let index_path = args.index.clone().unwrap_or_else(hits::default_path);
+    let mut index = Index::open(&index_path)?;
+
+    let host = audit::host();
+    let imported_at = chrono::Local::now().to_rfc3339();
+    let source = |origin: String, run: Option<String>| Source {
+        origin,
+        run,
+        host: host.clone(),
+        imported_at: imported_at.clone(),
+    };
+    let mut inputs: Vec<(PathBuf, Option<&str>)> = args.files.iter().map(|file| (file.clone(), None)).collect();
+    if let Some(id) = &args.run {
+        let (_, output, _) = crate::export::locate(None, Some(id))?;
+        inputs.push((output, Some(id)));
+    }
+
+    let (mut added, mut updated, mut unchanged) = (0, 0, 0);
+    let mut conflicts = Vec::new();
+    for (path, run) in &inputs {
+        for (digest, hit) in read_hits(path, *run, &source, args.key.as_deref())? {
+            match index.merge(&digest, hit, args.on_conflict) {
+                Merged::Added => added += 1,
+                Merged::Updated => updated += 1,
+                Merged::Unchanged => unchanged += 1,
+                Merged::Conflict(conflict) => conflicts.push(format!("sha256:{} from {}: {}", digest, path.display(), conflict)),
+            }
+        }
+    }
+
+    for conflict in &conflicts {
+        eprintln!("Conflict: {}", conflict);
+    }
+    if !conflicts.is_empty() && args.on_conflict == OnConflict::Fail {
+        return Err(format!(
+            "{} conflicting hit(s), nothing imported; use --on-conflict keep or replace to import anyway",
+            conflicts.len()
+        )
+        .into());
+    }
+    index.save()?;
+
+    AuditLog::new(args.audit_log.clone().unwrap_or_else(audit::default_path), args.run.clone())
+        .record(
+            "imported",
+            serde_json::json!({
+                "inputs": inputs.iter().map(|(path, _)| path).collect::<Vec<_>>(),
+                "index": index_path,
+                "added": added,
+                "updated": updated,
+                "conflicts": conflicts.len(),
+            }),
+        )
+        .map_err(|e| format!("can't write the audit log: {}", e))?;
+    println!(
+        "{} hit(s) added, {} updated, {} already known, {} conflicting; {} in {}",
+        added,
+        updated,
+        unchanged,
+        conflicts.len(),
+        index.hits.len(),
+        index_path.display()
+    );
+    Ok(())
+}
@@ -0,0 +1,308 @@
+//! The input files of a run.
+//!
+//! Several inputs are validated as one sequence of lines, in the order they
+//! were given, with directories expanded to the files below them sorted by
+//! path, unless an [`Order`] puts the likeliest sources first. A
+//! [`Filter`] can leave files in the directories out, and leaves out those
+//! in binary formats unless told otherwise. Line numbers, the checkpoint and `--skip`/`--limit` all refer to
+//! that combined sequence; [`Inputs::locate`] maps a line back to its file.
+//!
+//! A run over a single file scans, fingerprints and numbers lines exactly
+//! as before, so existing checkpoints stay valid.
+//!
+//! With many files, opening and reading each one for its scan costs more
+//! than validating it, so files are scanned several at a time.
+
+use crate::{
+    digest::to_hex,
+    glob::Glob,
+    scan::{self, InputScan, LineSample},
+    sniff,
+};
+use sha2::{Digest, Sha256};
+use std::{
+    cmp::Reverse,
+    fs, io,
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex,
+    },
+    thread,
+};
+
+/// Words in the names of files likelier than others to hold a phrase.
+const PROMISING: [&str; 9] = [
+    "wallet", "seed", "mnemonic", "phrase", "bip39", "recovery", "backup", "secret", "key",
+];
+
+/// The order several input files are validated in.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Order {
+    /// As given, directories sorted by path.
+    #[default]
+    Path,
+    /// Files named like a wallet backup first (`wallet`, `seed`, ...), and
+    /// in each group smaller before larger and newer before older.
+    Priority,
+    /// Smallest first.
+    Size,
+    /// Most recently modified first.
+    Newest,
+}
+
+impl FromStr for Order {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "path" => Ok(Order::Path),
+            "priority" => Ok(Order::Priority),
+            "size" => Ok(Order::Size),
+            "newest" => Ok(Order::Newest),
+            other => Err(format!("unknown order '{}', expected path, priority, size or newest", other)),
+        }
+    }
+}
+
+/// Whether the file name of `path` has one of the [`PROMISING`] words.
+fn promising(path: &Path) -> bool {
+    let name = path.file_name().unwrap_or_default().to_string_lossy().to_lowercase();
+    PROMISING.iter().any(|word| name.contains(word))
+}
+
+/// Puts `paths` in `order`; ties keep the order they're in.
+pub fn sort(paths: &mut [PathBuf], order: Order) -> io::Result<()> {
+    if order == Order::Path {
+        return Ok(());
+    }
+    let mut keyed = Vec::with_capacity(paths.len());
+    for path in paths.iter() {
+        let meta = fs::metadata(path)?;
+        let modified = meta.modified().ok();
+        keyed.push((promising(path), meta.len(), modified, path.clone()));
+    }
+    match order {
+        Order::Path => {}
+        Order::Priority => keyed.sort_by_key(|&(promising, len, modified, _)| (!promising, len, Reverse(modified))),
+        Order::Size => keyed.sort_by_key(|&(_, len, _, _)| len),
+        Order::Newest => keyed.sort_by_key(|&(_, _, modified, _)| Reverse(modified)),
+    }
+    for (path, (_, _, _, sorted)) in paths.iter_mut().zip(keyed) {
+        *path = sorted;
+    }
+    Ok(())
+}
+
+pub struct InputFile {
+    pub path: PathBuf,
+    /// Index of the file's first line in the combined sequence.
+    pub first_line: usize,
+    pub scan: InputScan,
+}
+
+pub struct Inputs {
+    pub files: Vec<InputFile>,
+}
+
+/// Which of the files found in input directories are read. Files named as
+/// inputs themselves are always read.
+#[derive(Clone, Debug, Default)]
+pub struct Filter {
+    /// Only files matching one of these, if there are any.
+    pub include: Vec<Glob>,
+    /// No files matching one of these.
+    pub exclude: Vec<Glob>,
+    /// Read files in binary formats too (see [`sniff`]).
+    pub carve: bool,
+}
+
+impl Filter {
+    /// Why the file at `path`, `relative` below its input directory, is
+    /// left out, if it is.
+    fn skip(&self, path: &Path, relative: &Path) -> io::Result<Option<Skip>> {
+        let included = self.include.is_empty() || self.include.iter().any(|glob| glob.matches(relative));
+        if !included || self.exclude.iter().any(|glob| glob.matches(relative)) {
+            return Ok(Some(Skip::Excluded));
+        }
+        if self.carve {
+            return Ok(None);
+        }
+        // One that can't be read is left to the scan, which reports it
+        Ok(sniff::sniff(path).ok().flatten().map(Skip::Binary))
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Skip {
+    /// By `--include` or `--exclude`.
+    Excluded,
+    /// In this binary format.
+    Binary(&'static str),
+}
+
+/// The files to read, and those in input directories that weren't.
+#[derive(Debug, Default)]
+pub struct Expanded {
+    pub files: Vec<PathBuf>,
+    pub skipped: Vec<(PathBuf, Skip)>,
+}
+
+/// Expands directories (recursively, sorted by path) and keeps files as
+/// given, leaving out the files in the directories that `filter` does.
+pub fn expand(paths: &[PathBuf], filter: &Filter) -> io::Result<Expanded> {
+    struct Walk<'a> {
+        root: &'a Path,
+        filter: &'a Filter,
+        expanded: Expanded,
+    }
+
+    fn walk(dir: &Path, out: &mut Walk) -> io::Result<()> {
+        let mut entries: Vec<PathBuf> = fs::read_dir(dir)?
+            .map(|entry| entry.map(|e| e.path()))
+            .collect::<io::Result<_>>()?;
+        entries.sort();
+        for path in entries {
+            if path.is_dir() {
+                walk(&path, out)?;
+            } else if path.is_file() {
+                let relative = path.strip_prefix(out.root).unwrap_or(&path);
+                match out.filter.skip(&path, relative)? {
+                    Some(skip) => out.expanded.skipped.push((path, skip)),
+                    None => out.expanded.files.push(path),
+                }
+            }
+        }
+        Ok(())
+    }
+
+    let mut out = Walk {
+        root: Path::new(""),
+        filter,
+        expanded: Expanded::default(),
+    };
+    for path in paths {
+        if path.is_dir() {
+            out.root = path;
+            walk(path, &mut out)?;
+        } else {
+            out.expanded.files.push(path.clone());
+        }
+    }
+    Ok(out.expanded)
+}
+
+/// Scans of `paths`, up to `threads` at a time, in their order; after a
+/// failure, with `stop`, the files yet to be scanned are left out.
+fn scan_files(paths: &[PathBuf], threads: usize, stop: bool) -> Vec<Option<io::Result<InputScan>>> {
+    let scans: Vec<Mutex<Option<io::Result<InputScan>>>> = paths.iter().map(|_| Mutex::new(None)).collect();
+    let next = AtomicUsize::new(0);
+    thread::scope(|s| {
+        for _ in 0..threads.clamp(1, paths.len().max(1)) {
+            s.spawn(|| loop {
+                let i = next.fetch_add(1, Ordering::Relaxed);
+                let Some(path) = paths.get(i) else { break };
+                let scan = scan::scan_input(path);
+                let failed = scan.is_err();
+                *scans[i].lock().unwrap() = Some(scan);
+                if failed && stop {
+                    // Files after it won't be needed
+                    next.fetch_add(paths.len(), Ordering::Relaxed);
+                }
+            });
+        }
+    });
+    scans.into_iter().map(|scan| scan.into_inner().unwrap()).collect()
+}
+
+impl Inputs {
+    /// Scans every file once (see [`scan::scan_input`]), up to `threads`
+    /// at a time.
+    pub fn scan(paths: Vec<PathBuf>, threads: usize) -> io::Result<Self> {
+        let scans = scan_files(&paths, threads, true);
+        let mut inputs = Self { files: Vec::with_capacity(paths.len()) };
+        for (path, scan) in paths.into_iter().zip(scans) {
+            // The first failure in path order, as when they were scanned in turn
+            inputs.push(path, scan.expect("every file before a failure is scanned")?);
+        }
+        Ok(inputs)
+    }
+
+    /// Like [`Inputs::scan`], but leaves out the files that can't be read
+    /// instead of failing; those come back with why.
+    pub fn scan_all(paths: Vec<PathBuf>, threads: usize) -> (Self, Vec<(PathBuf, io::Error)>) {
+        let scans = scan_files(&paths, threads, false);
+        let mut inputs = Self { files: Vec::with_capacity(paths.len()) };
+        let mut failed = Vec::new();
+        for (path, scan) in paths.into_iter().zip(scans) {
+            match scan.expect("every file is scanned") {
+                Ok(scan) => inputs.push(path, scan),
+                Err(e) => failed.push((path, e)),
+            }
+        }
+        (inputs, failed)
+    }
+
+    fn push(&mut self, path: PathBuf, scan: InputScan) {
+        let first_line = self.total_lines();
+        self.files.push(InputFile {
+            path,
+            first_line,
+            scan,
+        });
+    }
+
+    pub fn total_lines(&self) -> usize {
+        self.files.last().map_or(0, |f| f.first_line + f.scan.lines)
+    }
+
+    pub fn total_bytes(&self) -> u64 {
+        self.files.iter().map(|f| f.scan.bytes).sum()
+    }
+
+    /// Index of the file holding `line`, and the line's index within it.
+    pub fn locate(&self, line: usize) -> (usize, usize) {
+        let file = self
+            .files
+            .partition_point(|f| f.first_line <= line)
+            .saturating_sub(1);
+        (file, line - self.files[file].first_line)
+    }
+
+    /// The file path, or the first one and how many follow.
+    pub fn name(&self) -> String {
+        match self.files.as_slice() {
+            [] => String::new(),
+            [only] => only.path.display().to_string(),
+            [first, rest @ ..] => format!("{} (+{} more)", first.path.display(), rest.len()),
+        }
+    }
+
+    /// One scan covering all files, for the checkpoint fingerprint. For a
+    /// single file it's that file's scan; otherwise the hash covers each
+    /// file's hash in order and the samples are renumbered into the
+    /// combined sequence.
+    pub fn combined_scan(&self) -> InputScan {
+        if let [only] = self.files.as_slice() {
+            return only.scan.clone();
+        }
+
+        let mut hasher = Sha256::new();
+        let mut samples = Vec::new();
+        for file in &self.files {
+            hasher.update(file.scan.sha256.as_bytes());
+            hasher.update(b"\n");
+            samples.extend(file.scan.samples.iter().map(|s| LineSample {
+                line: file.first_line + s.line,
+                sha256: s.sha256.clone(),
+            }));
+        }
+        InputScan {
+            lines: self.total_lines(),
+            bytes: self.total_bytes(),
+            sha256: to_hex(&hasher.finalize()),
+            samples,
+        }
+    }
+}
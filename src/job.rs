@@ -0,0 +1,103 @@
+//! Job files for `--job`: a whole run described in TOML, so it can be
+//! repeated exactly or queued into the daemon.
+//!
+//! Each key is a long option of the command line, with the same value:
+//!
+//! ```toml
+//! input = ["dumps/2024", "extra.txt"]
+//! output = "cases/7/valid.txt"
+//! split-output = "by=language"
+//! mask = true
+//! max-memory = "2G"
+//! exec-per-valid = "notify.sh {file} {line}"
+//! ```
+//!
+//! The file is turned into arguments and parsed like any others, so it
+//! accepts exactly what the command line does and fails the same way.
+
+use crate::Cli;
+use clap::Parser;
+use std::{ffi::OsString, fs, path::Path};
+use toml_edit::{DocumentMut, Item, Value};
+
+/// Reads `path` into the arguments it stands for, without the program name.
+pub fn args(path: &Path) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    let text = fs::read_to_string(path)
+        .map_err(|e| format!("can't read job file '{}': {}", path.display(), e))?;
+    let doc: DocumentMut = text
+        .parse()
+        .map_err(|e| format!("job file '{}' isn't valid TOML: {}", path.display(), e))?;
+
+    let mut args = Vec::new();
+    for (key, item) in doc.as_table() {
+        let option = format!("--{}", key.replace('_', "-"));
+        let value = match item {
+            Item::Value(value) => value,
+            _ => return Err(format!("job file '{}': '{}' must be a plain value or a list", path.display(), key).into()),
+        };
+        match value {
+            Value::Boolean(flag) => {
+                if *flag.value() {
+                    args.push(option);
+                }
+            }
+            Value::Array(values) => {
+                for value in values {
+                    args.push(option.clone());
+                    args.push(scalar(value).ok_or_else(|| {
+                        format!("job file '{}': '{}' may only list plain values", path.display(), key)
+                    })?);
+                }
+            }
+            value => {
+                args.push(option);
+                args.push(scalar(value).ok_or_else(|| {
+                    format!("job file '{}': '{}' must be a plain value or a list", path.display(), key)
+                })?);
+            }
+        }
+    }
+    Ok(args)
+}
+
+fn scalar(value: &Value) -> Option<String> {
+    match value {
+        Value::String(s) => Some(s.value().clone()),
+        Value::Integer(n) => Some(n.value().to_string()),
+        Value::Float(f) => Some(f.value().to_string()),
+        Value::Boolean(b) => Some(b.value().to_string()),
+        Value::Datetime(_) | Value::Array(_) | Value::InlineTable(_) => None,
+    }
+}
+
+/// Parses the job file's options followed by the rest of the command line
+/// (`args_os` minus `--job` itself), so options given there override the
+/// file's single values and add to its lists.
+pub fn load(path: &Path, command_line: impl Iterator<Item = OsString>) -> Result<Cli, Box<dyn std::error::Error>> {
+    let mut command_line = command_line;
+    let program = command_line.next().unwrap_or_else(|| "mnemonic_validator".into());
+    let mut rest = Vec::new();
+    while let Some(arg) = command_line.next() {
+        if arg == "--job" {
+            command_line.next();
+        } else if !arg.to_string_lossy().starts_with("--job=") {
+            rest.push(arg);
+        }
+    }
+
+    let file_args = args(path)?.into_iter().map(OsString::from);
+    let cli = Cli::try_parse_from(std::iter::once(program).chain(file_args).chain(rest)).map_err(|e| {
+        format!(
+            "job file '{}': {}",
+            path.display(),
+            e.to_string().lines().next().unwrap_or_default().trim_start_matches("error: ")
+        )
+    })?;
+    if cli.command.is_some() {
+        return Err(format!("job file '{}' can't run a subcommand", path.display()).into());
+    }
+    if cli.job.is_some() {
+        return Err(format!("job file '{}' can't name another job file", path.display()).into());
+    }
+    Ok(cli)
+}
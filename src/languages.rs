@@ -0,0 +1,213 @@
+//! Phrases in the other BIP39 languages, for `--language`.
+//!
+//! English is validated by [`prefilter::parse`](crate::prefilter::parse)
+//! unless another language is asked for. With `--language auto` each word
+//! is looked up in every wordlist and the phrase is tried in each language
+//! that has all of its words. Wordlists share words (English and French
+//! about a hundred, the two Chinese lists most of their characters, at the
+//! same places), so a phrase can be valid in more than one language. Each
+//! is a different wallet, so such a phrase is flagged rather than taken for
+//! one of them without a word.
+//!
+//! Words are compared NFKD-normalized, as BIP39 has them. That also turns
+//! the ideographic spaces Japanese phrases are written with into plain ones,
+//! and half-width katakana into full-width; katakana are then read as the
+//! hiragana the Japanese list is in, since backups are written in either.
+//!
+//! Every word of the two Chinese lists is a single character, and Chinese
+//! backups are usually written without spaces, or with full-width ones or
+//! ideographic commas between the words; so a Chinese phrase is taken a
+//! character at a time, whatever is between them. The traditional list is
+//! the simplified one in traditional characters, word for word, and a
+//! phrase that went through an input method or a converter can mix the two:
+//! a character from either list stands for its word in the list asked for.
+//! With `auto`, a phrase all in one list's characters is read in that list
+//! (in both, if they're characters the lists share), and one that mixes
+//! them in both.
+
+use crate::{prefilter, secret::SecretString, wordlist};
+use bip39::{Language, Mnemonic};
+use std::borrow::Cow;
+
+/// Which languages a line is validated in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Choice {
+    Only(Language),
+    /// Every language, each phrase in those it's valid in.
+    Auto,
+}
+
+impl Default for Choice {
+    fn default() -> Self {
+        Choice::Only(Language::English)
+    }
+}
+
+/// The name of `language` as `--language` and `--split-output by=language`
+/// have it, e.g. `simplifiedchinese`.
+pub fn name(language: Language) -> String {
+    format!("{:?}", language).to_lowercase()
+}
+
+/// A `--language`: `auto`, or one of the names of [`name`].
+pub fn parse_choice(s: &str) -> Result<Choice, String> {
+    let s = s.trim().to_lowercase().replace(['-', '_', ' '], "");
+    if s == "auto" {
+        return Ok(Choice::Auto);
+    }
+    Language::ALL
+        .iter()
+        .find(|&&language| name(language) == s)
+        .map(|&language| Choice::Only(language))
+        .ok_or_else(|| {
+            let names: Vec<String> = Language::ALL.iter().map(|&language| name(language)).collect();
+            format!("unknown language '{}', expected auto or one of {}", s, names.join(", "))
+        })
+}
+
+/// Parses `line` as a phrase in the language(s) of `choice`; with
+/// [`Choice::Auto`], in the first language it's valid in (see [`parse_all`]
+/// for the others).
+pub fn parse(line: &str, choice: Choice) -> Option<Mnemonic> {
+    match choice {
+        Choice::Only(Language::English) => prefilter::parse(line),
+        Choice::Only(language @ (Language::SimplifiedChinese | Language::TraditionalChinese)) => {
+            normalized(line, |line| parse_chinese(line, language))
+        }
+        Choice::Only(Language::Japanese) => {
+            normalized(line, |line| hiragana(line, |line| Mnemonic::parse_in_normalized(Language::Japanese, line).ok()))
+        }
+        Choice::Only(language) => normalized(line, |line| Mnemonic::parse_in_normalized(language, line).ok()),
+        Choice::Auto => parse_all(line).into_iter().next(),
+    }
+}
+
+/// The phrase `line` is in each language it's valid in, in the order of
+/// [`Language::ALL`].
+pub fn parse_all(line: &str) -> Vec<Mnemonic> {
+    normalized(line, |line| hiragana(line, parse_all_normalized))
+}
+
+fn parse_all_normalized(line: &str) -> Vec<Mnemonic> {
+    if line.trim_start().chars().next().is_some_and(is_chinese) {
+        return parse_all_chinese(line);
+    }
+    let mut words = line.split_whitespace();
+    let Some(first) = words.next() else {
+        return Vec::new();
+    };
+    let mut languages: Vec<Language> = wordlist::lookup(first).iter().map(|&(language, _)| language).collect();
+    let mut count = 1;
+    for word in words {
+        count += 1;
+        languages.retain(|&language| wordlist::index_in(word, language).is_some());
+        if languages.is_empty() || count > 24 {
+            return Vec::new();
+        }
+    }
+    if !matches!(count, 12 | 15 | 18 | 21 | 24) {
+        return Vec::new();
+    }
+    let mut valid: Vec<Mnemonic> = languages
+        .into_iter()
+        .filter_map(|language| Mnemonic::parse_in_normalized(language, line).ok())
+        .collect();
+    valid.sort_by_key(|mnemonic| Language::ALL.iter().position(|&language| language == mnemonic.language()));
+    valid
+}
+
+const CHINESE: [Language; 2] = [Language::SimplifiedChinese, Language::TraditionalChinese];
+
+/// Characters between the words of a Chinese phrase besides spaces: the
+/// ideographic and full-width commas.
+fn is_chinese_separator(c: char) -> bool {
+    c.is_whitespace() || matches!(c, '\u{3001}' | '\u{FF0C}')
+}
+
+fn in_list(c: char, language: Language) -> bool {
+    wordlist::index_in(c.encode_utf8(&mut [0; 4]), language).is_some()
+}
+
+fn is_chinese(c: char) -> bool {
+    CHINESE.iter().any(|&language| in_list(c, language))
+}
+
+/// `line` in the words of the Chinese `language`, taking each character
+/// as a word and either list's character for the word.
+fn chinese_phrase(line: &str, language: Language) -> Option<SecretString> {
+    let other = CHINESE.into_iter().find(|&other| other != language)?;
+    let list = language.word_list();
+    let mut phrase = SecretString::new(String::with_capacity(4 * 24));
+    let mut count = 0;
+    for c in line.chars().filter(|&c| !is_chinese_separator(c)) {
+        count += 1;
+        let mut word = [0; 4];
+        let word = c.encode_utf8(&mut word);
+        let index = wordlist::index_in(word, language).or_else(|| wordlist::index_in(word, other))?;
+        if count > 24 {
+            return None;
+        }
+        if count > 1 {
+            phrase.push(' ');
+        }
+        phrase.push_str(list[index as usize]);
+    }
+    Some(phrase)
+}
+
+fn parse_chinese(line: &str, language: Language) -> Option<Mnemonic> {
+    Mnemonic::parse_in_normalized(language, &chinese_phrase(line, language)?).ok()
+}
+
+/// A Chinese phrase in the list(s) all its characters are on, or in both
+/// if it mixes them.
+fn parse_all_chinese(line: &str) -> Vec<Mnemonic> {
+    let characters = || line.chars().filter(|&c| !is_chinese_separator(c));
+    let mut languages: Vec<Language> =
+        CHINESE.into_iter().filter(|&language| characters().all(|c| in_list(c, language))).collect();
+    if languages.is_empty() {
+        languages = CHINESE.to_vec();
+    }
+    languages.into_iter().filter_map(|language| parse_chinese(line, language)).collect()
+}
+
+/// The words of `phrase`: split at whitespace, or a character at a time
+/// if it's Chinese.
+pub fn words(phrase: &str) -> Vec<&str> {
+    if !phrase.trim_start().chars().next().is_some_and(is_chinese) {
+        return phrase.split_whitespace().collect();
+    }
+    phrase
+        .char_indices()
+        .filter(|&(_, c)| !is_chinese_separator(c))
+        .map(|(i, c)| &phrase[i..i + c.len_utf8()])
+        .collect()
+}
+
+fn is_katakana(c: char) -> bool {
+    matches!(c, '\u{30A1}'..='\u{30F6}')
+}
+
+/// Calls `f` with the katakana of the NFKD-normalized `line` turned into
+/// hiragana; their voicing marks are apart after NFKD, and the same for both.
+fn hiragana<T>(line: &str, f: impl FnOnce(&str) -> T) -> T {
+    if !line.chars().any(is_katakana) {
+        return f(line);
+    }
+    let mut folded = SecretString::new(String::with_capacity(line.len()));
+    for c in line.chars() {
+        // The two blocks are in the same order, 0x60 apart
+        folded.push(if is_katakana(c) { char::from_u32(c as u32 - 0x60).unwrap() } else { c });
+    }
+    f(&folded)
+}
+
+/// Calls `f` with `line` NFKD-normalized; a copy that takes is wiped.
+fn normalized<T>(line: &str, f: impl FnOnce(&str) -> T) -> T {
+    let mut cow = Cow::Borrowed(line);
+    Mnemonic::normalize_utf8_cow(&mut cow);
+    match cow {
+        Cow::Borrowed(line) => f(line),
+        Cow::Owned(copy) => f(&SecretString::new(copy)),
+    }
+}
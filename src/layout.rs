@@ -0,0 +1,390 @@
+//! Where the candidate phrases are in a line, for inputs that don't hold
+//! exactly one phrase per line.
+//!
+//! With `--delimiter` a line holds several phrases, e.g. `a b c…; d e f…`.
+//! With `--numbered` the words of a phrase are numbered as on a backup
+//! sheet, `1) apple 2) banana …` or `Word 1: apple`, on one line or on a few
+//! consecutive ones, and may be in any order (two-column sheets list 1 and
+//! 13 side by side); they are put back in numeric order. With
+//! `--join-lines` a phrase is written a few words per line, as hardware
+//! wallet cards are often transcribed, and consecutive short lines are
+//! joined until they add up to a phrase. With `--seedqr` and
+//! `--compact-seedqr` a line or part holds a QR payload instead of words;
+//! see [`seedqr`].
+//!
+//! With `--join-lines` only a line that starts a run of
+//! lines with as many words can start one: a window from the middle of a
+//! card passes the checksum by chance often enough (one time in 16 for 12
+//! words) to bury the real phrase.
+//!
+//! Layouts that span lines need to see the lines after the one being
+//! validated. [`Windows`] wraps the input so that every line comes with the
+//! lines following it, keeping the line numbers as they are: a candidate is
+//! always reported at its first line.
+
+use crate::{
+    pipeline::{Chunk, Source, LINES_PER_CHUNK},
+    secret::{SecretString, Zeroizing},
+    seedqr, typography,
+};
+use std::{collections::VecDeque, io, ops::ControlFlow};
+
+/// Separates the lines of a window. Not a whitespace character, so it never
+/// joins the words of two lines by accident.
+pub const LINE_BREAK: char = '\u{1e}';
+
+const WORD_COUNTS: [usize; 5] = [12, 15, 18, 21, 24];
+const MAX_WORDS: usize = 24;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Layout {
+    /// Several phrases per line, separated by this character.
+    pub delimiter: Option<char>,
+    /// Numbered words, on one line or several.
+    pub numbered: bool,
+    /// Phrases split over up to this many short lines.
+    pub join_lines: Option<usize>,
+    /// SeedQR digit strings.
+    pub seedqr: bool,
+    /// CompactSeedQR entropy in hex.
+    pub compact_seedqr: bool,
+}
+
+/// `;`, `|`, or an escape for a tab (`\t` or `tab`). Spaces separate words,
+/// so they can't separate phrases.
+pub fn parse_delimiter(s: &str) -> Result<char, String> {
+    let delimiter = match s {
+        "\\t" | "tab" => '\t',
+        _ => {
+            let mut chars = s.chars();
+            match (chars.next(), chars.next()) {
+                (Some(c), None) => c,
+                _ => return Err(format!("expected a single character, got '{}'", s)),
+            }
+        }
+    };
+    if delimiter == ' ' || delimiter == '\n' || delimiter == LINE_BREAK {
+        return Err("spaces and newlines can't separate phrases".to_string());
+    }
+    Ok(delimiter)
+}
+
+impl Layout {
+    /// Lines a candidate can span. Above 1 the input has to be read through
+    /// [`Windows`].
+    pub fn window(&self) -> usize {
+        let numbered = if self.numbered { MAX_WORDS } else { 1 };
+        numbered.max(self.join_lines.unwrap_or(1))
+    }
+
+    /// Calls `f` with every candidate phrase starting on the first line of
+    /// `text`, cleaned up by [`typography::clean`], until `f` breaks. With a
+    /// delimiter each comes with its position on the line, counting from 1.
+    pub fn each_candidate<B>(
+        &self,
+        text: &str,
+        mut f: impl FnMut(Option<usize>, &str) -> ControlFlow<B>,
+    ) -> ControlFlow<B> {
+        let mut lines = text.split(LINE_BREAK);
+        let line = lines.next().unwrap_or_default();
+        let cleaned = typography::clean(line);
+        let line = cleaned.as_deref().map_or(line, String::as_str);
+
+        // Only a line some candidate may start on gets any following lines
+        let raw: Vec<&str> = lines.collect();
+        let cleaned: Vec<_> = raw.iter().map(|line| typography::clean(line)).collect();
+        let following = || {
+            raw.iter()
+                .zip(&cleaned)
+                .map(|(&line, cleaned)| cleaned.as_deref().map_or(line, String::as_str))
+        };
+
+        let mut single = |part: Option<usize>, candidate: &str| match self.decode(candidate) {
+            Some(phrase) => f(part, &phrase),
+            None => f(part, candidate),
+        };
+        match self.delimiter {
+            Some(delimiter) => {
+                for (n, part) in (1..).zip(line.split(delimiter)) {
+                    single(Some(n), part.trim())?;
+                }
+            }
+            None => single(None, line)?,
+        }
+
+        if self.numbered {
+            if let Some(first) = sheet_start(line) {
+                let mut words: [Option<&str>; MAX_WORDS] = [None; MAX_WORDS];
+                'sheet: for numbered in std::iter::once(Some(first)).chain(following().map(numbered_words)) {
+                    let Some(numbered) = numbered else { break };
+                    for (n, word) in numbered {
+                        if words[n - 1].replace(word).is_some() {
+                            // A number seen twice: another sheet begins
+                            break 'sheet;
+                        }
+                    }
+                    if let Some(phrase) = complete_phrase(&words) {
+                        f(None, &phrase)?;
+                    }
+                }
+            }
+        }
+
+        if let Some(join_lines) = self.join_lines.filter(|_| is_short(line)) {
+            // Never outgrows the window, so it's never reallocated
+            let mut phrase = SecretString::new(String::with_capacity(text.len()));
+            let mut words = 0;
+            for line in std::iter::once(line).chain(following()).take(join_lines) {
+                if !is_short(line) {
+                    break;
+                }
+                for word in line.split_whitespace() {
+                    if words > 0 {
+                        phrase.push(' ');
+                    }
+                    phrase.push_str(word);
+                    words += 1;
+                }
+                if words > MAX_WORDS {
+                    break;
+                }
+                // A line alone is never short enough to be a phrase
+                if WORD_COUNTS.contains(&words) {
+                    f(None, &phrase)?;
+                }
+            }
+        }
+        ControlFlow::Continue(())
+    }
+
+    /// The phrase of a QR payload, if `candidate` is one of those enabled.
+    fn decode(&self, candidate: &str) -> Option<SecretString> {
+        let standard = || self.seedqr.then(|| seedqr::decode_standard(candidate)).flatten();
+        let compact = || self.compact_seedqr.then(|| seedqr::decode_compact(candidate)).flatten();
+        standard().or_else(compact)
+    }
+
+    /// Whether a candidate may start on `line`, following `previous` (`None`
+    /// at the start of a file), and go on to the lines after it.
+    fn spans_lines(&self, previous: Option<&[u8]>, line: &[u8]) -> bool {
+        let count = |line: &[u8]| {
+            std::str::from_utf8(line).map_or(0, |line| {
+                let cleaned = typography::clean(line);
+                cleaned.as_deref().map_or(line, String::as_str).split_whitespace().count()
+            })
+        };
+        if self.join_lines.is_some() {
+            let words = count(line);
+            return (1..WORD_COUNTS[0]).contains(&words) && previous.map(count) != Some(words);
+        }
+        let Ok(text) = std::str::from_utf8(line) else {
+            return false;
+        };
+        let cleaned = typography::clean(text);
+        self.numbered && sheet_start(cleaned.as_deref().map_or(text, String::as_str)).is_some()
+    }
+
+    /// The first candidate in `text` for which `f` returns something.
+    pub fn find_map<T>(&self, text: &str, mut f: impl FnMut(&str) -> Option<T>) -> Option<T> {
+        match self.each_candidate(text, |_, candidate| match f(candidate) {
+            Some(found) => ControlFlow::Break(found),
+            None => ControlFlow::Continue(()),
+        }) {
+            ControlFlow::Break(found) => Some(found),
+            ControlFlow::Continue(()) => None,
+        }
+    }
+}
+
+/// The numbered words of `line`, if it can be the first line of a sheet.
+fn sheet_start(line: &str) -> Option<Vec<(usize, &str)>> {
+    numbered_words(line).filter(|words| words.iter().any(|&(n, _)| n == 1))
+}
+
+/// Whether `line` holds some words, but fewer than the shortest phrase: a
+/// piece of one written over several lines.
+fn is_short(line: &str) -> bool {
+    (1..WORD_COUNTS[0]).contains(&line.split_whitespace().count())
+}
+
+/// `words[0..n]`, joined, once exactly the first `n` numbers of a phrase
+/// length have been seen.
+fn complete_phrase(words: &[Option<&str>; MAX_WORDS]) -> Option<SecretString> {
+    let seen = words.iter().take_while(|word| word.is_some()).count();
+    if !WORD_COUNTS.contains(&seen) || words[seen..].iter().any(Option::is_some) {
+        return None;
+    }
+    let len = words[..seen].iter().map(|word| word.unwrap().len() + 1).sum();
+    let mut phrase = SecretString::new(String::with_capacity(len));
+    for (i, word) in words[..seen].iter().enumerate() {
+        if i > 0 {
+            phrase.push(' ');
+        }
+        phrase.push_str(word.unwrap());
+    }
+    Some(phrase)
+}
+
+/// The numbered words of a backup-sheet line (`1) apple 2) banana`,
+/// `1. apple`, `Word 1: apple`, `#1 apple`). `None` unless the line holds
+/// numbered words and nothing else.
+fn numbered_words(line: &str) -> Option<Vec<(usize, &str)>> {
+    let mut words = Vec::new();
+    let mut number = None;
+    for token in line.split_whitespace() {
+        // "Word 1:" labels the number that follows
+        if number.is_none() && token.eq_ignore_ascii_case("word") {
+            continue;
+        }
+        // Punctuation standing on its own between number and word
+        if number.is_some() && matches!(token, ")" | "." | ":" | "-" | "=") {
+            continue;
+        }
+        let digits = token.strip_prefix('#').unwrap_or(token);
+        let end = digits.find(|c: char| !c.is_ascii_digit()).unwrap_or(digits.len());
+        if end > 0 {
+            if number.is_some() {
+                return None;
+            }
+            let n: usize = digits[..end].parse().ok().filter(|n| (1..=MAX_WORDS).contains(n))?;
+            let rest = digits[end..].strip_prefix([')', '.', ':', '-', '=']).unwrap_or(&digits[end..]);
+            let rest = rest.trim_end_matches([',', ';']);
+            match rest {
+                "" => number = Some(n),
+                // Glued to its word, as in `1.apple`
+                word if word.chars().all(char::is_alphabetic) => words.push((n, word)),
+                _ => return None,
+            }
+        } else {
+            words.push((number.take()?, token.trim_end_matches([',', ';'])));
+        }
+    }
+    (number.is_none() && !words.is_empty()).then_some(words)
+}
+
+/// Gives every line on which a candidate spanning lines may start the lines
+/// after it, up to the layout's window and within the same input file,
+/// joined to it with [`LINE_BREAK`]. Line numbers stay as they were, so
+/// checkpoints and reported lines mean the same with and without it.
+///
+/// The lines are copied, into buffers wiped when dropped.
+pub struct Windows<'a> {
+    source: Source<'a>,
+    layout: Layout,
+    window: usize,
+    /// First line of each input file.
+    boundaries: Vec<usize>,
+    /// Lines read but not handed out yet, starting at line `first`.
+    pending: VecDeque<Zeroizing<Vec<u8>>>,
+    first: usize,
+    /// The line before `first`, once handed out.
+    previous: Option<Zeroizing<Vec<u8>>>,
+    done: bool,
+}
+
+/// Lines with their windows, numbered from `first_line`.
+pub struct Window {
+    first_line: usize,
+    bytes: Zeroizing<Vec<u8>>,
+}
+
+impl Chunk for Window {
+    fn first_line(&self) -> usize {
+        self.first_line
+    }
+
+    fn bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+}
+
+impl<'a> Windows<'a> {
+    pub fn new(source: Source<'a>, layout: Layout, boundaries: Vec<usize>) -> Self {
+        Self {
+            source,
+            layout,
+            window: layout.window(),
+            boundaries,
+            pending: VecDeque::new(),
+            first: 0,
+            previous: None,
+            done: false,
+        }
+    }
+
+    /// Hands out the first `count` pending lines with their windows.
+    fn emit(&mut self, count: usize) -> Window {
+        let mut bytes = Zeroizing::new(Vec::new());
+        let end = self.first + self.pending.len();
+        for k in 0..count {
+            let line = self.first + k;
+            let file_end = self
+                .boundaries
+                .iter()
+                .copied()
+                .find(|&start| start > line)
+                .unwrap_or(usize::MAX);
+            let previous = match k {
+                _ if self.boundaries.contains(&line) => None,
+                0 => self.previous.as_deref().map(Vec::as_slice),
+                _ => Some(self.pending[k - 1].as_slice()),
+            };
+            let last = if self.layout.spans_lines(previous, &self.pending[k]) {
+                (line + self.window).min(file_end).min(end)
+            } else {
+                line + 1
+            };
+            bytes.extend_from_slice(&self.pending[k]);
+            for following in k + 1..last - self.first {
+                bytes.push(LINE_BREAK as u8);
+                // Leave a line that isn't text out rather than spoil the
+                // whole window for the validator
+                if std::str::from_utf8(&self.pending[following]).is_ok() {
+                    bytes.extend_from_slice(&self.pending[following]);
+                }
+            }
+            bytes.push(b'\n');
+        }
+        self.previous = self.pending.drain(..count).next_back();
+        let window = Window {
+            first_line: self.first,
+            bytes,
+        };
+        self.first += count;
+        window
+    }
+}
+
+impl<'a> Iterator for Windows<'a> {
+    type Item = io::Result<Box<dyn Chunk + 'a>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            // A line can go once the lines of its window have been read
+            let ready = if self.done {
+                self.pending.len()
+            } else {
+                self.pending.len().saturating_sub(self.window - 1)
+            };
+            if ready >= LINES_PER_CHUNK || (self.done && ready > 0) {
+                let window = self.emit(ready.min(LINES_PER_CHUNK));
+                return Some(Ok(Box::new(window)));
+            }
+            if self.done {
+                return None;
+            }
+            match self.source.next() {
+                None => self.done = true,
+                Some(Err(e)) => return Some(Err(e)),
+                Some(Ok(chunk)) => {
+                    if self.pending.is_empty() {
+                        self.first = chunk.first_line();
+                    }
+                    for line in crate::lines::lines(chunk.bytes()).map(crate::lines::normalize) {
+                        self.pending.push_back(Zeroizing::new(line.to_vec()));
+                    }
+                }
+            }
+        }
+    }
+}
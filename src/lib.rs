@@ -0,0 +1,72 @@
+//! Shared building blocks used by both the command-line validator and the GUI.
+
+pub mod address;
+pub mod audit;
+pub mod batch;
+pub mod bip32;
+pub mod blocks;
+pub mod bundle;
+pub mod cache;
+pub mod cancel;
+pub mod checkpoint;
+pub mod collector;
+pub mod config;
+pub mod curve;
+pub mod deadline;
+pub mod derive;
+pub mod descriptor;
+pub mod diagnostics;
+pub mod digest;
+pub mod duplicates;
+pub mod disk;
+pub mod electrum;
+pub mod engine;
+pub mod eta;
+pub mod events;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+pub mod fingerprint;
+pub mod fuzz;
+pub mod glob;
+pub mod heartbeat;
+pub mod hits;
+pub mod http;
+pub mod inputs;
+pub mod languages;
+pub mod layout;
+pub mod lines;
+pub mod lock;
+pub mod manifest;
+pub mod mask;
+pub mod memory;
+pub mod network;
+#[cfg(windows)]
+pub mod overlapped;
+pub mod output;
+pub mod passphrase;
+pub mod paths;
+pub mod pipeline;
+pub mod prefilter;
+pub mod queue;
+pub mod recovery;
+pub mod sample;
+pub mod scan;
+pub mod secret;
+pub mod seal;
+pub mod seedqr;
+pub mod seedxor;
+pub mod shards;
+pub mod slip39;
+pub mod sniff;
+pub mod spill;
+pub mod stats;
+pub mod throttle;
+pub mod tokens;
+pub mod tune;
+pub mod typography;
+#[cfg(all(target_os = "linux", feature = "io-uring"))]
+pub mod uring;
+pub mod utxo;
+pub mod vanity;
+pub mod wallet;
+pub mod wordlist;
@@ -0,0 +1,356 @@
+//! Zero-copy iteration over input lines.
+//!
+//! The input is memory-mapped and split on line breaks with memchr, so
+//! every line is a `&[u8]` slice borrowed from the mapping. Nothing is
+//! allocated per line; only hits get copied, when they're written out.
+//!
+//! A line break is `\n`, `\r\n` or a lone `\r`, so files from Windows and
+//! classic Mac OS tools split the same way as Unix ones. That makes a `\r`
+//! at the very end of a read ambiguous until the next byte is known, which
+//! is why readers stop at [`last_break`] rather than at the last `\r`.
+//! bstr's `lines` only ends a line at `\n`, so the splitting is done here
+//! with memchr, which bstr itself searches with.
+//!
+//! Mapped pages count towards the process's resident memory until the
+//! kernel drops them, so under `--max-memory` the input is read in blocks
+//! of whole lines instead ([`ReadBlocks`]).
+//!
+//! With `--follow`, lines appended after the scan are read by [`Follow`].
+
+use crate::{cancel::CancelToken, pipeline::Chunk};
+use memmap2::Mmap;
+use std::{
+    fs::File,
+    io::{self, Read, Seek, SeekFrom},
+    path::Path,
+    thread,
+    time::Duration,
+};
+
+/// How often [`Follow`] looks for new data at the end of the file.
+const FOLLOW_POLL: Duration = Duration::from_millis(250);
+
+pub struct Input {
+    /// `None` for an empty file, which can't be mapped.
+    map: Option<Mmap>,
+}
+
+impl Input {
+    pub fn open(path: &Path) -> io::Result<Self> {
+        let file = File::open(path)?;
+        if file.metadata()?.len() == 0 {
+            return Ok(Self { map: None });
+        }
+        // Safety: the mapping is read-only; like with a buffered reader, a
+        // file truncated by someone else mid-run is not something we guard
+        // against.
+        let map = unsafe { Mmap::map(&file)? };
+        #[cfg(unix)]
+        map.advise(memmap2::Advice::Sequential)?;
+        Ok(Self { map: Some(map) })
+    }
+
+    pub fn bytes(&self) -> &[u8] {
+        self.map.as_deref().unwrap_or_default()
+    }
+
+    pub fn lines(&self) -> Lines<'_> {
+        lines(self.bytes())
+    }
+}
+
+/// Whole lines read from the input into an owned buffer.
+pub struct Block {
+    first_line: usize,
+    buf: Vec<u8>,
+}
+
+impl Chunk for Block {
+    fn first_line(&self) -> usize {
+        self.first_line
+    }
+
+    fn bytes(&self) -> &[u8] {
+        &self.buf
+    }
+}
+
+/// Reads the input `block_size` bytes at a time and hands out blocks that
+/// end on a line boundary. The partial line at the end of a read is carried
+/// into the next block; a line longer than a block makes that block grow,
+/// unless it's over [`max_line`](Self::max_line).
+pub struct ReadBlocks {
+    file: File,
+    block_size: usize,
+    max_line: Option<usize>,
+    carry: Vec<u8>,
+    next_line: usize,
+    eof: bool,
+}
+
+impl ReadBlocks {
+    /// Numbers lines from `first_line`.
+    pub fn open(path: &Path, first_line: usize, block_size: usize) -> io::Result<Self> {
+        Ok(Self {
+            file: File::open(path)?,
+            block_size,
+            max_line: None,
+            carry: Vec::new(),
+            next_line: first_line,
+            eof: false,
+        })
+    }
+
+    /// Keeps no more of a line than its first `max + 1` bytes, enough to
+    /// tell it's too long, so that one with no end in sight can't take up
+    /// all memory.
+    pub fn max_line(mut self, max: usize) -> Self {
+        self.max_line = Some(max);
+        self
+    }
+
+    /// Appends up to `block_size` bytes to `buf`; fewer only at the end.
+    fn fill(&mut self, buf: &mut Vec<u8>) -> io::Result<()> {
+        let start = buf.len();
+        buf.resize(start + self.block_size, 0);
+        let mut filled = start;
+        while filled < buf.len() {
+            match self.file.read(&mut buf[filled..]) {
+                Ok(0) => {
+                    self.eof = true;
+                    break;
+                }
+                Ok(n) => filled += n,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        }
+        buf.truncate(filled);
+        Ok(())
+    }
+}
+
+impl Iterator for ReadBlocks {
+    type Item = io::Result<Block>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut buf = std::mem::take(&mut self.carry);
+        loop {
+            if self.eof {
+                break;
+            }
+            let searched = buf.len();
+            if let Err(e) = self.fill(&mut buf) {
+                return Some(Err(e));
+            }
+            // A `\r` just before the new bytes may have been half a break
+            let from = searched.saturating_sub(1);
+            if let Some(end) = last_break(&buf[from..]) {
+                self.carry = buf.split_off(from + end);
+                break;
+            }
+            // All of `buf` is one line so far
+            if let Some(max) = self.max_line {
+                if buf.len() > max + 1 {
+                    // Keeping a final `\r`, which may be its line break
+                    let cr = buf.last() == Some(&b'\r');
+                    buf.truncate(max + 1);
+                    if cr {
+                        buf.push(b'\r');
+                    }
+                }
+            }
+        }
+        if buf.is_empty() {
+            return None;
+        }
+
+        let block = Block {
+            first_line: self.next_line,
+            buf,
+        };
+        self.next_line += lines(&block.buf).count();
+        Some(Ok(block))
+    }
+}
+
+/// Length of the first `len` bytes of `path` up to and including their last
+/// line break, i.e. without a final line that may still be being written.
+pub fn complete_len(path: &Path, len: u64) -> io::Result<u64> {
+    let mut file = File::open(path)?;
+    let mut buf = vec![0u8; 64 << 10];
+    let mut end = len;
+    while end > 0 {
+        let start = end.saturating_sub(buf.len() as u64);
+        let part = &mut buf[..(end - start) as usize];
+        file.seek(SeekFrom::Start(start))?;
+        file.read_exact(part)?;
+        // Only a `\r` at the very end could still become a `\r\n`
+        if end < len && part.last() == Some(&b'\r') {
+            return Ok(end);
+        }
+        if let Some(end) = last_break(part) {
+            return Ok(start + end as u64);
+        }
+        end = start;
+    }
+    Ok(0)
+}
+
+/// Lines appended to a file, like `tail -f`: at the end of the file it
+/// waits for more instead of ending. Only complete lines are handed out; a
+/// line still being written is held back until its line break arrives.
+pub struct Follow {
+    file: File,
+    offset: u64,
+    carry: Vec<u8>,
+    next_line: usize,
+    stop: Option<CancelToken>,
+}
+
+impl Follow {
+    /// Starts reading at byte `offset`, which should be the start of a
+    /// line, numbering lines from `first_line`.
+    pub fn open(path: &Path, offset: u64, first_line: usize) -> io::Result<Self> {
+        let mut file = File::open(path)?;
+        file.seek(SeekFrom::Start(offset))?;
+        Ok(Self {
+            file,
+            offset,
+            carry: Vec::new(),
+            next_line: first_line,
+            stop: None,
+        })
+    }
+
+    /// Stops waiting for more once `stop` is told to finish: an empty block
+    /// at the next line then tells the pipeline where following stopped.
+    pub fn until(mut self, stop: CancelToken) -> Self {
+        self.stop = Some(stop);
+        self
+    }
+}
+
+impl Iterator for Follow {
+    type Item = io::Result<Block>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut read = vec![0u8; 64 << 10];
+        loop {
+            let n = match self.file.read(&mut read) {
+                Ok(n) => n,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Some(Err(e)),
+            };
+            if n == 0 {
+                match self.file.metadata() {
+                    Ok(meta) if meta.len() < self.offset => {
+                        return Some(Err(io::Error::other("the input was truncated while following it")))
+                    }
+                    Ok(_) if self.stop.as_ref().is_some_and(CancelToken::is_finishing) => {
+                        return Some(Ok(Block {
+                            first_line: self.next_line,
+                            buf: Vec::new(),
+                        }))
+                    }
+                    Ok(_) => thread::sleep(FOLLOW_POLL),
+                    Err(e) => return Some(Err(e)),
+                }
+                continue;
+            }
+            self.offset += n as u64;
+            let from = self.carry.len().saturating_sub(1);
+            self.carry.extend_from_slice(&read[..n]);
+            if let Some(end) = last_break(&self.carry[from..]) {
+                let rest = self.carry.split_off(from + end);
+                let block = Block {
+                    first_line: self.next_line,
+                    buf: std::mem::replace(&mut self.carry, rest),
+                };
+                self.next_line += lines(&block.buf).count();
+                return Some(Ok(block));
+            }
+        }
+    }
+}
+
+/// The end of the first line of `bytes` and the start of the one after it,
+/// past its line break.
+#[inline]
+fn line_end(bytes: &[u8]) -> Option<(usize, usize)> {
+    let end = memchr::memchr2(b'\n', b'\r', bytes)?;
+    let next = match &bytes[end..] {
+        [b'\r', b'\n', ..] => end + 2,
+        _ => end + 1,
+    };
+    Some((end, next))
+}
+
+/// Where the lines of `bytes` start after the first one: one past each line
+/// break.
+pub fn breaks(bytes: &[u8]) -> impl Iterator<Item = usize> + '_ {
+    let mut at = 0;
+    std::iter::from_fn(move || {
+        let (_, next) = line_end(&bytes[at..])?;
+        at += next;
+        Some(at)
+    })
+}
+
+/// One past the last line break of `bytes` that's certain to be one. A
+/// final `\r` isn't, as the `\n` of a `\r\n` may be yet to come.
+pub fn last_break(bytes: &[u8]) -> Option<usize> {
+    let bytes = bytes.strip_suffix(b"\r").unwrap_or(bytes);
+    memchr::memrchr2(b'\n', b'\r', bytes).map(|end| end + 1)
+}
+
+/// A line as the engine validates it: without a byte order mark at its
+/// start, which only the first line of a file should have, or whitespace
+/// at its end.
+#[inline]
+pub fn normalize(line: &[u8]) -> &[u8] {
+    let mut line = line.strip_prefix("\u{feff}".as_bytes()).unwrap_or(line);
+    // Vertical tab and form feed too, unlike `trim_ascii_end`
+    while let [rest @ .., b' ' | b'\t' | b'\n' | b'\x0b' | b'\x0c' | b'\r'] = line {
+        line = rest;
+    }
+    line
+}
+
+/// [`normalize`] for a line that's text already.
+pub fn normalize_str(line: &str) -> &str {
+    let kept = normalize(line.as_bytes());
+    // Only a whole character, or ASCII, is ever taken off
+    let start = kept.as_ptr() as usize - line.as_ptr() as usize;
+    &line[start..start + kept.len()]
+}
+
+pub fn lines(bytes: &[u8]) -> Lines<'_> {
+    Lines { rest: bytes }
+}
+
+/// Lines split on `\n`, `\r\n` or a lone `\r`, with no empty line after a
+/// final line break.
+pub struct Lines<'a> {
+    rest: &'a [u8],
+}
+
+impl<'a> Iterator for Lines<'a> {
+    type Item = &'a [u8];
+
+    #[inline]
+    fn next(&mut self) -> Option<&'a [u8]> {
+        if self.rest.is_empty() {
+            return None;
+        }
+        let line = match line_end(self.rest) {
+            Some((end, next)) => {
+                let line = &self.rest[..end];
+                self.rest = &self.rest[next..];
+                line
+            }
+            None => std::mem::take(&mut self.rest),
+        };
+        Some(line)
+    }
+}
@@ -0,0 +1,83 @@
+//! Keeping two runs off the same output and checkpoint.
+//!
+//! Two invocations with the default paths would append to the same output
+//! and overwrite each other's checkpoint. A run holds an advisory lock on
+//! `<path>.lock` next to each file it writes, for as long as it runs, and
+//! writes into it which process it is, so a second run can say which one
+//! is in its way. The lock goes with the process, however it ends; the lock
+//! files stay behind, and are taken over by the next run.
+
+use std::{
+    fs::{self, File, OpenOptions, TryLockError},
+    io::{Read, Seek, Write},
+    path::{Path, PathBuf},
+};
+
+/// `output/valid_mnemonics.txt` -> `output/valid_mnemonics.txt.lock`
+pub fn lock_path(path: &Path) -> PathBuf {
+    let mut name = path.as_os_str().to_os_string();
+    name.push(".lock");
+    PathBuf::from(name)
+}
+
+/// Locks held until dropped.
+#[derive(Debug, Default)]
+pub struct RunLock {
+    files: Vec<File>,
+}
+
+impl RunLock {
+    /// Locks `path` too, or tells which process holds it. `holder`
+    /// describes this process to the runs that find it locked.
+    pub fn add(&mut self, path: &Path, holder: &str) -> Result<(), String> {
+        let lock_path = lock_path(path);
+        // Before the run has made the output's directory
+        if let Some(parent) = lock_path.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+            fs::create_dir_all(parent).map_err(|e| format!("can't create {}: {}", parent.display(), e))?;
+        }
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(&lock_path)
+            .map_err(|e| format!("can't create the lock file {}: {}", lock_path.display(), e))?;
+        match file.try_lock() {
+            Ok(()) => {}
+            Err(TryLockError::WouldBlock) => {
+                let mut other = String::new();
+                let _ = file.read_to_string(&mut other);
+                let other = match other.trim() {
+                    "" => "another process".to_string(),
+                    other => other.to_string(),
+                };
+                return Err(format!(
+                    "{} is in use by another run: {}. Wait for it to finish, or stop it first",
+                    path.display(),
+                    other
+                ));
+            }
+            Err(TryLockError::Error(e)) => return Err(format!("can't lock {}: {}", lock_path.display(), e)),
+        }
+        let written = file
+            .set_len(0)
+            .and_then(|()| file.rewind())
+            .and_then(|()| writeln!(file, "{}", holder));
+        written.map_err(|e| format!("can't write the lock file {}: {}", lock_path.display(), e))?;
+        self.files.push(file);
+        Ok(())
+    }
+}
+
+/// This process, as a lock file names it: its ID, command line (`args`
+/// without the program name) and start time.
+pub fn holder(args: &[std::ffi::OsString]) -> String {
+    let mut command = vec![env!("CARGO_PKG_NAME").to_string()];
+    command.extend(args.iter().map(|arg| arg.to_string_lossy().into_owned()));
+    format!(
+        "process {} ({}), started {}",
+        std::process::id(),
+        command.join(" "),
+        chrono::Local::now().format("%Y-%m-%d %H:%M:%S")
+    )
+}
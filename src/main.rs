@@ -1,232 +1,2105 @@
-use bip39::{Mnemonic, Language};
-use rayon::prelude::*;
+use bip39::{Language, Mnemonic};
+use chrono::NaiveTime;
 use std::{
-    fs::{self, File, OpenOptions},
-    io::{BufWriter, BufRead, Write, BufReader},
+    cell::RefCell,
+    collections::BTreeMap,
+    ffi::OsString,
+    ops::ControlFlow,
+    fs::{self, OpenOptions},
+    io::{BufWriter, Write},
     path::{Path, PathBuf},
-    sync::{Arc, Mutex, atomic::{AtomicUsize, Ordering}},
-    time::{Instant, Duration},
+    sync::{Arc, Mutex, atomic::{AtomicBool, AtomicUsize, Ordering}, mpsc},
+    time::{Duration, Instant},
 };
-use std::sync::atomic::AtomicBool;
-use ctrlc;
-use clap::Parser;
+use clap::{Parser, Subcommand};
+use regex::Regex;
+use mnemonic_validator::{
+    audit::{self, AuditLog},
+    cache::{OutcomeCache, Outcome},
+    cancel::CancelToken,
+    checkpoint::{self as resume, InputFingerprint, Verdict},
+    collector::{self, TlsFiles, Uplink},
+    config::EngineConfig,
+    deadline,
+    derive::{self, Blockchain},
+    descriptor, digest, disk,
+    engine::{self, Reader, Tuned},
+    duplicates::Duplicates,
+    eta::format_duration,
+    events::{self, Events},
+    fingerprint::{self, GroupBy, Groups},
+    glob::Glob,
+    heartbeat::{self, Heartbeat},
+    inputs::{self, Inputs, Skip},
+    languages::{self, Choice},
+    layout::{self, Layout, Windows},
+    lines::{self, Follow, Input},
+    lock::{self, RunLock},
+    manifest, mask,
+    memory::{self, Budget, Growing},
+    network::{self, Network},
+    output::{ExistingOutput, HitWriter, SplitBy},
+    pipeline::{Hit, LongLines, Oversize, Pipeline, Source},
+    paths, prefilter, sample,
+    scan::InputScan,
+    seal,
+    secret::SecretString,
+    stats::{RunStats, Summary},
+    tune,
+    typography,
+    vanity::{self, AddressFilter},
+};
+
+mod bench;
+mod collect;
+mod console;
+mod coordinator;
+mod corpus;
+mod daemon;
+mod export;
+mod failed;
+mod hooks;
+mod import;
+mod job;
+mod passphrases;
+mod recover;
+mod report;
+mod reveal;
+mod runs;
+mod selftest;
+mod server;
+mod shamir;
+mod utxo_index;
+mod wizard;
+mod worker;
+mod xor;
+
+use console::Progress;
+use hooks::HookRunner;
+use runs::Run;
+
+/// Hits written between flushes of the output, bounding how many can be
+/// lost in a buffer when writing fails.
+const FLUSH_HITS: usize = 1024;
+
+/// Exit status of a run stopped before the end that can be resumed from its
+/// checkpoint, interrupted or at its time limit: sysexits' EX_TEMPFAIL,
+/// which batch schedulers take as "run it again".
+const EXIT_RESUMABLE: i32 = 75;
+
+/// How a run that didn't fail ended.
+enum Ended {
+    Finished,
+    /// Before the end, with a checkpoint to resume from.
+    Stopped,
+}
 
 #[derive(Parser, Debug)]
 #[clap(
     name = "mnemonic_validator",
     about = "Validates BIP39 mnemonic phrases from a file.",
-    long_about = "Reads mnemonic phrases from an input file, validates them, and writes the valid ones to an output file.  Supports automatic checkpoints and Ctrl+C handling."
+    long_about = "Reads mnemonic phrases from one or more input files, validates them, and writes the valid ones to an output file.  Supports automatic checkpoints and Ctrl+C handling."
+,
+    // An option repeated after a --job file's replaces the file's value
+    args_override_self = true
 )]
 struct Cli {
-    /// The path to the input file containing mnemonic phrases (one per line).
-    #[clap(short, long, value_parser, default_value = "input/mnemonics.txt")]
-    input: String,
+    #[clap(subcommand)]
+    command: Option<Command>,
+
+    /// Take the options of this run from a TOML job file; options given here as well override its values and add to its lists.
+    #[clap(long, value_name = "FILE", value_parser = paths::parser())]
+    job: Option<PathBuf>,
+
+    /// The input file(s) containing mnemonic phrases (one per line). Directories are read recursively; several inputs are validated in order as one.
+    #[clap(short, long, value_parser = paths::parser(), num_args = 1.., default_value = "input/mnemonics.txt")]
+    input: Vec<PathBuf>,
+
+    /// Only read the files in input directories matching GLOB, e.g. '*.txt' or 'exports/**/*.csv' (a pattern with a / matches the path below the directory, one without the file name); may be given more than once.
+    #[clap(long, value_name = "GLOB")]
+    include: Vec<Glob>,
+
+    /// Leave out the files in input directories matching GLOB, even if --include matches them; may be given more than once.
+    #[clap(long, value_name = "GLOB")]
+    exclude: Vec<Glob>,
+
+    /// Read files in input directories that are in binary formats too (images, audio, video, executables), which are skipped otherwise.
+    #[clap(long)]
+    carve: bool,
+
+    /// Order to validate several input files in: path (as given, directories sorted by path), priority (names like wallet or seed first, then smaller and newer files), size (smallest first) or newest (most recently modified first).
+    #[clap(long, value_name = "ORDER", default_value = "path")]
+    order: inputs::Order,
 
     /// The path to the output file for valid mnemonic phrases.
-    #[clap(short, long, value_parser, default_value = "output/valid_mnemonics.txt")]
-    output: String,
+    #[clap(short, long, value_parser = paths::parser(), default_value = "output/valid_mnemonics.txt")]
+    output: PathBuf,
+
+    /// Add to an output file left by an earlier run (the default).
+    #[clap(long, conflicts_with_all = ["overwrite", "error_if_exists"])]
+    append: bool,
+
+    /// Empty an output file left by an earlier run before writing; a resumed run still appends to its own.
+    #[clap(long, conflicts_with_all = ["error_if_exists", "incremental"])]
+    overwrite: bool,
+
+    /// Refuse to start if the output file already exists; a resumed run may still append to its own.
+    #[clap(long, conflicts_with = "incremental")]
+    error_if_exists: bool,
+
+    /// Write hits into separate files per category: by=word-count, by=language or by=scheme.
+    #[clap(long, value_name = "by=CATEGORY")]
+    split_output: Option<SplitBy>,
+
+    /// Spread hits over N output files by a hash of the phrase.
+    #[clap(long, value_name = "N", value_parser = clap::value_parser!(u32).range(1..))]
+    shards: Option<u32>,
+
+    /// Don't write hits whose line is already in the output file, so a re-run over an updated input only appends new ones (and runs --exec-per-valid only for those).
+    #[clap(long, conflicts_with = "count_only")]
+    incremental: bool,
+
+    /// Write only a fingerprint of each valid mnemonic (first/last word, SHA-256 prefix, line number) instead of the phrase.
+    #[clap(long)]
+    mask: bool,
+
+    /// With --mask, also seal each valid mnemonic to this public key (hex, or a file holding it; see `reveal --new-key`) into `<output>.sealed`, so that only the key's holder can `reveal` the confirmed ones.
+    #[clap(long, value_name = "KEY", value_parser = seal::parse_public_key, requires = "mask", conflicts_with = "count_only")]
+    seal_to: Option<[u8; 33]>,
+
+    /// With --seal-to, send the sealed phrases to a `collect` server at ADDR (host:port) over mutual TLS instead of writing `<output>.sealed`; while it can't be reached they wait in `<output>.sealed.spill`.
+    #[clap(long, value_name = "ADDR", requires_all = ["seal_to", "stream_cert", "stream_key", "stream_ca"])]
+    stream_to: Option<String>,
+
+    /// This worker's client certificate chain for --stream-to (PEM).
+    #[clap(long, value_name = "FILE", value_parser = paths::parser(), requires = "stream_to")]
+    stream_cert: Option<PathBuf>,
+
+    /// The private key of --stream-cert (PEM).
+    #[clap(long, value_name = "FILE", value_parser = paths::parser(), requires = "stream_to")]
+    stream_key: Option<PathBuf>,
+
+    /// Authority the collector's certificate has to be issued by (PEM).
+    #[clap(long, value_name = "FILE", value_parser = paths::parser(), requires = "stream_to")]
+    stream_ca: Option<PathBuf>,
+
+    /// Split each line into several candidate phrases at this character, e.g. ';', '|' or '\t'.
+    #[clap(long, value_name = "CHAR", value_parser = layout::parse_delimiter)]
+    delimiter: Option<char>,
+
+    /// Recognize numbered words as on a backup sheet (`1) apple 2) banana`, `Word 1: apple`), on one line or over consecutive lines, and validate them in numeric order.
+    #[clap(long, conflicts_with = "follow")]
+    numbered: bool,
+
+    /// Join up to N consecutive short lines (fewer than 12 words each, e.g. 3 words per line as on a hardware wallet card) and validate them as one phrase, starting only where a run of lines with the same number of words starts; 2 to 24.
+    #[clap(long, value_name = "N", value_parser = clap::value_parser!(u32).range(2..=24), conflicts_with_all = ["follow", "delimiter", "numbered", "listen_tcp", "listen_unix"])]
+    join_lines: Option<u32>,
+
+    /// Language of the phrases: english, or another BIP39 wordlist (simplifiedchinese, traditionalchinese, czech, french, italian, japanese, korean, portuguese, spanish), or auto to try every one; phrases valid in more than one language are reported.
+    #[clap(long, value_name = "LANG", default_value = "english", value_parser = languages::parse_choice)]
+    language: Choice,
+
+    /// Decode SeedSigner SeedQR digit strings (four digits per word, 48 or 96 in all) and validate the phrases they stand for.
+    #[clap(long)]
+    seedqr: bool,
+
+    /// Decode CompactSeedQR payloads written in hex (32 or 64 digits of entropy) into phrases. Entropy has no checksum, so any hex string of that length, such as a hash, decodes to a valid phrase.
+    #[clap(long)]
+    compact_seedqr: bool,
+
+    /// Prefix each valid mnemonic with the input file and line it came from, as `file:line:phrase` (`file:line:part:phrase` with --delimiter, counting parts from 1).
+    #[clap(long, conflicts_with = "mask")]
+    annotate: bool,
+
+    /// Prefix each valid mnemonic with its BIP32 master fingerprint (no passphrase), as `73c5da0a:phrase` (before the file and line with --annotate); with --mask it is appended as `fp:73c5da0a`.
+    #[clap(long)]
+    fingerprint: bool,
+
+    /// After the run, list the hits that are the same wallet, by=fingerprint: found more than once, whether as the same phrase or in another layout such as SeedQR.
+    #[clap(long, value_name = "by=KEY")]
+    group_by: Option<GroupBy>,
+
+    /// After the run, write how many times each valid mnemonic was found, and at which files and lines, to FILE: most often first, with the phrases masked.
+    #[clap(long, value_name = "FILE", value_parser = paths::parser())]
+    duplicates: Option<PathBuf>,
+
+    /// Also write, for each valid mnemonic, the watch-only descriptors of its first BIP84 account (wpkh([fingerprint/84h/0h/0h]xpub.../0/* and /1/*)) to this file, one `bitcoin-cli importdescriptors` request per line.
+    #[clap(long, value_name = "FILE", value_parser = paths::parser(), conflicts_with = "count_only")]
+    descriptors: Option<PathBuf>,
+
+    /// Network the --descriptors and the addresses filtered on are for: mainnet, testnet, signet, regtest (test networks use coin type 1 and tpub keys), ethereum, or one of the coin registry (see MV_COINS).
+    #[clap(long, value_name = "NETWORK", default_value = "mainnet", value_parser = network::parse_network)]
+    network: Network,
+
+    /// Only keep valid mnemonics with an address starting with PREFIX, e.g. bc1qxy2 (case-sensitive); may be given more than once. The addresses are the first --address-count receive addresses of the BIP44, BIP49 and BIP84 accounts (no passphrase).
+    #[clap(long, value_name = "PREFIX")]
+    address_prefix: Vec<String>,
+
+    /// Only keep valid mnemonics with an address matching REGEX, e.g. 'x[a-z0-9]*7k$'; with --address-prefix, the same address must match both.
+    #[clap(long, value_name = "REGEX", value_parser = vanity::parse_regex)]
+    address_regex: Option<Regex>,
+
+    /// Only keep valid mnemonics with an address holding coins in FILE, checked offline: a Bitcoin Core `dumptxoutset` snapshot, an index made from one with `utxo-index`, or a list of addresses (one per line, optionally followed by an amount).
+    #[clap(long, value_name = "FILE", value_parser = paths::parser())]
+    utxo_set: Option<PathBuf>,
+
+    /// Chain whose addresses --address-prefix and --address-regex look at: bitcoin (on --network), solana (accounts m/44'/501'/i'/0') or cardano (Shelley base addresses of m/1852'/1815'/0'/0/i).
+    #[clap(long, value_name = "CHAIN", default_value = "bitcoin", value_parser = derive::parse_blockchain)]
+    blockchain: Blockchain,
+
+    /// Receive addresses per account checked by --address-prefix, --address-regex and --utxo-set.
+    #[clap(long, value_name = "N", default_value_t = vanity::DEFAULT_ADDRESSES, value_parser = clap::value_parser!(u32).range(1..))]
+    address_count: u32,
+
+    /// Skip the first N lines of the input.
+    #[clap(long, value_name = "N", default_value_t = 0)]
+    skip: usize,
+
+    /// Only look at N lines (after --skip).
+    #[clap(long, value_name = "N")]
+    limit: Option<usize>,
+
+    /// After the end of the input, keep validating lines as they are appended to it (like `tail -f`) until stopped.
+    #[clap(long, conflicts_with = "limit")]
+    follow: bool,
+
+    /// Stop cleanly after this long (e.g. 6h, 90m or 1h30m): what was read is validated and written, and the checkpoint is saved at the line the run got to, to resume from.
+    #[clap(long, value_name = "DURATION", value_parser = deadline::parse_duration)]
+    max_duration: Option<Duration>,
+
+    /// Stop cleanly, as with --max-duration, the next time it's this local time of day (HH:MM, e.g. 02:00).
+    #[clap(long, value_name = "HH:MM", value_parser = deadline::parse_clock)]
+    stop_at: Option<NaiveTime>,
+
+    /// Stop cleanly, as with --max-duration, once this many valid mnemonics have been written in this run; those found past them are left to the resumed run.
+    #[clap(long, value_name = "N", value_parser = clap::value_parser!(u64).range(1..))]
+    stop_after: Option<u64>,
+
+    /// On Ctrl+C or SIGTERM, take up to this many seconds to validate and write the lines already read and save the checkpoint at the line the run got to; past it, or at a second signal, the checkpoint is saved as it stands and the run exits at once.
+    #[clap(long, value_name = "SECS", default_value_t = 20)]
+    grace_period: u64,
+
+    /// Validate only a pseudo-random fraction of the lines, e.g. 0.01 or 1%.
+    #[clap(long, value_name = "RATE", value_parser = sample::parse_rate)]
+    sample: Option<f64>,
+
+    /// Seed choosing which lines --sample picks; the same seed always picks the same lines.
+    #[clap(long, value_name = "SEED", default_value_t = 0)]
+    sample_seed: u64,
+
+    /// Checkpoint file to use instead of the run's own; such runs aren't recorded in `runs list`.
+    #[clap(long, value_name = "PATH", value_parser = paths::parser())]
+    checkpoint: Option<PathBuf>,
+
+    /// Ignore any saved checkpoint and start from the first line.
+    #[clap(long)]
+    restart: bool,
+
+    /// Resume from the saved checkpoint even if the input file no longer matches it.
+    #[clap(long, conflicts_with = "restart")]
+    force_resume: bool,
+
+    /// Don't write the `<output>.manifest.json` integrity manifest after the run.
+    #[clap(long)]
+    no_manifest: bool,
+
+    /// Sign the manifest with this minisign secret key (password from MINISIGN_PASSWORD or prompted).
+    #[clap(long, value_parser = paths::parser())]
+    sign_manifest: Option<PathBuf>,
+
+    /// Append the run's events to this audit log instead of the one in the data directory (see `runs audit`).
+    #[clap(long, value_name = "FILE", value_parser = paths::parser())]
+    audit_log: Option<PathBuf>,
+
+    /// Keep a JSON status file (pid, file, line, valid count, time) here, refreshed while the run makes progress, for watchdogs.
+    #[clap(long, value_name = "FILE", value_parser = paths::parser())]
+    heartbeat: Option<PathBuf>,
+
+    /// Seconds between refreshes of the --heartbeat file.
+    #[clap(long, value_name = "SECS", default_value_t = 10, requires = "heartbeat")]
+    heartbeat_interval: u64,
+
+    /// Validate everything but write nothing (no output, checkpoint or manifest); only print statistics.
+    #[clap(long, conflicts_with_all = ["exec_per_valid", "sign_manifest"])]
+    count_only: bool,
+
+    /// Command to run for each valid mnemonic, e.g. 'notify.sh {}'. `{}` is replaced by the phrase, `{line}` by its line number and `{file}` by the path of the input file it came from.
+    #[clap(long, value_parser)]
+    exec_per_valid: Option<String>,
+
+    #[clap(flatten)]
+    engine: EngineConfig,
+
+    /// Don't read files; answer each line received on this TCP address (e.g. 127.0.0.1:7000) with `valid` or `invalid`.
+    #[clap(long, value_name = "ADDR", conflicts_with = "listen_unix")]
+    listen_tcp: Option<std::net::SocketAddr>,
+
+    /// Like --listen-tcp, on a Unix socket at PATH.
+    #[clap(long, value_name = "PATH", value_parser = paths::parser())]
+    listen_unix: Option<PathBuf>,
+
+    /// Maximum number of --exec-per-valid commands running at the same time.
+    #[clap(long, value_parser, default_value_t = 4)]
+    exec_concurrency: usize,
 }
 
-fn is_valid(mnemonic: &str) -> bool {
-    Mnemonic::parse_in_normalized(Language::English, mnemonic).is_ok()
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Measure validation throughput per thread count on a synthetic in-memory corpus.
+    Bench(bench::BenchArgs),
+    /// Write a reproducible synthetic input of valid phrases, near misses and junk.
+    GenCorpus(corpus::GenCorpusArgs),
+    /// Put the input files a run couldn't read back where they were and validate them again.
+    RetryFailed(failed::RetryFailedArgs),
+    /// Check this build against the BIP39 test vectors and adversarial inputs.
+    Selftest(selftest::SelftestArgs),
+    /// Run job descriptors dropped into a spool directory, one after another.
+    Daemon(daemon::DaemonArgs),
+    /// Turn a run's manifest into an HTML or Markdown report with charts and per-file figures.
+    Report(report::ReportArgs),
+    /// Take the sealed phrases that runs with --stream-to send, into one sealed file.
+    Collect(collect::CollectArgs),
+    /// Open the sealed phrases of the hits confirmed from a --seal-to run's masked output, or make a key pair for it.
+    Reveal(reveal::RevealArgs),
+    /// List, resume or clean up recorded runs.
+    Runs(runs::RunsArgs),
+    /// Put a run's results, derivations and manifest into one bundle sealed to the client's public key.
+    Export(export::ExportArgs),
+    /// Merge result bundles, runs' output files or other hit indexes into the local hit index.
+    Import(import::ImportArgs),
+    /// Try a list of BIP39 passphrases on a valid phrase until one gives a wallet holding a known address.
+    Passphrase(passphrases::PassphraseArgs),
+    /// Search for a phrase with missing or uncertain words, given what's known of each word.
+    Recover(recover::RecoverArgs),
+    /// Answer a few questions about what you remember of a phrase, and search for it.
+    Wizard(wizard::WizardArgs),
+    /// Read a Bitcoin Core UTXO snapshot (`dumptxoutset`) into an index file for --utxo-set.
+    UtxoIndex(utxo_index::UtxoIndexArgs),
+    /// Combine Seed XOR shares into the phrase they were split from, or split a phrase into shares.
+    Xor(xor::XorArgs),
+    /// Combine SLIP-39 shares into the master secret they were split from.
+    Slip39(shamir::Slip39Args),
+    /// Split inputs into shards for a fleet of workers, and follow their progress on a web dashboard.
+    Coordinator(coordinator::CoordinatorArgs),
+    /// Take files or batches of lines to validate from a queue shared with other workers, and push the results back.
+    Worker(worker::WorkerArgs),
 }
 
-fn format_duration(duration: Duration) -> String {
-    let total_secs = duration.as_secs();
-    let hours = total_secs / 3600;
-    let minutes = (total_secs % 3600) / 60;
-    let secs = total_secs % 60;
+fn parse(mnemonic: &str) -> Option<Mnemonic> {
+    let cleaned = typography::clean(mnemonic);
+    let mnemonic = cleaned.as_deref().map_or(mnemonic, String::as_str);
+    prefilter::parse(mnemonic)
+}
 
-    if hours > 0 {
-        format!("{:02}:{:02}:{:02}", hours, minutes, secs)
-    } else {
-        format!("{:02}:{:02}", minutes, secs)
+/// Where the candidate phrases are in a line, going by the options.
+fn layout(cli: &Cli) -> Layout {
+    Layout {
+        delimiter: cli.delimiter,
+        numbered: cli.numbered,
+        join_lines: cli.join_lines.map(|n| n as usize),
+        seedqr: cli.seedqr,
+        compact_seedqr: cli.compact_seedqr,
     }
 }
 
-fn estimate_remaining(processed: usize, total: usize, elapsed: Duration) -> String {
-    if processed == 0 {
-        return "Calculating...".to_string();
+/// The phrases `mnemonic` is in the languages of `choice`: with `auto`, one
+/// for each language it's valid in.
+fn parse_in(mnemonic: &str, choice: Choice) -> Vec<Mnemonic> {
+    let cleaned = typography::clean(mnemonic);
+    let mnemonic = cleaned.as_deref().map_or(mnemonic, String::as_str);
+    match choice {
+        Choice::Auto => languages::parse_all(mnemonic),
+        choice => languages::parse(mnemonic, choice).into_iter().collect(),
     }
+}
 
-    let lines_per_sec = processed as f64 / elapsed.as_secs_f64();
-    if lines_per_sec < 0.01 {
-        return "Calculating...".to_string();
+/// The hits of a --language auto run per language, and a warning for the
+/// phrases valid in several.
+fn print_languages(by_language: &BTreeMap<String, usize>, ambiguous: &[String]) {
+    let mut counts: Vec<String> = by_language.iter().map(|(name, count)| format!("{} {}", name, count)).collect();
+    if !ambiguous.is_empty() {
+        counts.push(format!("several {}", ambiguous.len()));
+    }
+    if !counts.is_empty() {
+        println!("By language: {}", counts.join(", "));
+    }
+    if !ambiguous.is_empty() {
+        println!(
+            "Warning: {} valid in more than one language, each a different wallet (written once, filed under the first):",
+            if ambiguous.len() == 1 { "1 phrase is".to_string() } else { format!("{} phrases are", ambiguous.len()) }
+        );
+        for phrase in ambiguous {
+            println!("  {}", phrase);
+        }
     }
+}
 
-    let remaining_lines = total.saturating_sub(processed);
-    let remaining_secs = remaining_lines as f64 / lines_per_sec;
+/// Warns of the sealed hits of a --stream-to run still waiting to be sent.
+fn report_spill(cli: &Cli, output_path: &Path) -> std::io::Result<()> {
+    let Some(addr) = &cli.stream_to else {
+        return Ok(());
+    };
+    let spill = collector::spill_path(output_path);
+    let count = collector::spilled(&spill)?;
+    if count > 0 {
+        println!(
+            "Warning: {} sealed hit(s) haven't reached the collector at {} yet; they wait in {} and are sent first by the next run streaming to it",
+            count,
+            addr,
+            spill.display()
+        );
+    }
+    Ok(())
+}
 
-    format_duration(Duration::from_secs_f64(remaining_secs))
+/// Says how many files in the input directories were left out, and why.
+fn report_skipped(skipped: &[(PathBuf, Skip)]) {
+    if skipped.is_empty() {
+        return;
+    }
+    let mut excluded = 0;
+    let mut formats: BTreeMap<&str, usize> = BTreeMap::new();
+    for (_, skip) in skipped {
+        match skip {
+            Skip::Excluded => excluded += 1,
+            Skip::Binary(format) => *formats.entry(format).or_default() += 1,
+        }
+    }
+    let mut reasons = Vec::new();
+    if excluded > 0 {
+        reasons.push(format!("{} by --include/--exclude", excluded));
+    }
+    if !formats.is_empty() {
+        let formats: Vec<String> = formats.iter().map(|(format, n)| format!("{} {}", n, format)).collect();
+        reasons.push(format!("{} in binary formats, read with --carve ({})", skipped.len() - excluded, formats.join(", ")));
+    }
+    println!("Skipped {} file(s) in the input directories: {}", skipped.len(), reasons.join("; "));
 }
 
-fn process_file(
-    input_path: &Path,
+/// Moves the input files that couldn't be read into the output's failed/
+/// directory, or just reports them for a run that writes nothing.
+fn set_aside_failed(
+    cli: &Cli,
+    failures: &[(PathBuf, std::io::Error)],
+    run: Option<&Run>,
+    writes: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if failures.is_empty() {
+        return Ok(());
+    }
+    let dir = failed::failed_dir(&cli.output);
+    for (path, error) in failures {
+        if !writes {
+            println!("Warning: skipping '{}': {}", path.display(), error);
+            continue;
+        }
+        let moved = failed::set_aside(&dir, path, error, run.map(|run| run.record.id.as_str()))
+            .map_err(|e| format!("can't set aside '{}' in {}: {}", path.display(), dir.display(), e))?;
+        match moved {
+            Some(moved) => println!("Warning: skipping '{}': {}; moved to {}", path.display(), error, moved.display()),
+            None => println!("Warning: skipping '{}': {}; recorded in {}", path.display(), error, dir.display()),
+        }
+    }
+    if writes {
+        println!(
+            "{} file(s) couldn't be read. Once fixed, `mnemonic_validator retry-failed -o {}` validates them again.",
+            failures.len(),
+            cli.output.display()
+        );
+    }
+    Ok(())
+}
+
+/// Per-file results table for runs over several inputs.
+fn print_file_summaries(inputs: &Inputs, per_file: &[Summary], total: &Summary) {
+    let width = inputs
+        .files
+        .iter()
+        .map(|f| f.path.display().to_string().chars().count())
+        .max()
+        .unwrap_or(0)
+        .max("total".len());
+    println!(
+        "{:<width$} {:>12} {:>8} {:>9} {:>12} {:>9}",
+        "file", "lines", "valid", "time", "lines/s", "hit rate"
+    );
+    let rows = inputs
+        .files
+        .iter()
+        .map(|f| f.path.display().to_string())
+        .zip(per_file)
+        .chain(std::iter::once(("total".to_string(), total)));
+    for (name, summary) in rows {
+        println!(
+            "{:<width$} {:>12} {:>8} {:>9} {:>12.0} {:>8.4}%",
+            name,
+            summary.lines,
+            summary.valid,
+            format_duration(summary.elapsed),
+            summary.lines_per_sec(),
+            summary.hit_rate() * 100.0
+        );
+    }
+}
+
+/// Refuses to start on a volume already under `--min-free-space`, and
+/// warns when the output could outgrow the space there is.
+fn check_free_space(
+    cli: &Cli,
+    inputs: &Inputs,
     output_path: &Path,
-    checkpoint_path: &Path, // Now always a hidden path
+    checkpoint_path: &Path,
+    remaining: usize,
+    total_lines: usize,
 ) -> Result<(), Box<dyn std::error::Error>> {
+    engine::check_free_space(&cli.engine, &[output_path.to_path_buf(), checkpoint_path.to_path_buf()])?;
+
+    // Worst case: every remaining line is valid
+    let name = inputs.files.iter().map(|file| file.path.as_os_str().len()).max().unwrap_or(0) as u64;
+    let worst_case = if cli.mask {
+        remaining as u64 * (disk::MASKED_LINE_BYTES + name)
+    } else {
+        let bytes = inputs.total_bytes() as u128 * remaining as u128 / total_lines.max(1) as u128;
+        // `file:line:` in front of each, with up to 20 digits
+        let annotations = if cli.annotate { remaining as u64 * (name + 22) } else { 0 };
+        bytes as u64 + annotations
+    };
+    let free = disk::free_space(output_path)?;
+    if worst_case > free {
+        println!(
+            "Warning: the output could grow to {} if every line were valid, but only {} is free for '{}'.",
+            memory::format_size(worst_case),
+            memory::format_size(free),
+            output_path.display()
+        );
+    }
+    Ok(())
+}
+
+/// Appends the `importdescriptors` request for a hit's first BIP84 account.
+fn write_descriptors(out: &mut impl Write, mnemonic: &Mnemonic, network: Network) -> std::io::Result<()> {
+    match descriptor::bip84(mnemonic, network) {
+        Some(descriptors) => writeln!(out, "{}", descriptor::import_request(&descriptors)),
+        // A seed without a key at the path, one in about 2^127
+        None => Ok(()),
+    }
+}
+
+/// What a run writes its hits to, with the lines of those that may not be
+/// on disk yet.
+struct Sinks {
+    writer: HitWriter,
+    descriptors: Option<BufWriter<fs::File>>,
+    sealed: Option<Box<dyn Write + Send>>,
+    /// The sealed hits file, when they're written to one, and its length
+    /// before the run.
+    sealed_file: Option<(PathBuf, u64)>,
+    /// Lines of the hits written since the last flush: until the next one
+    /// succeeds they may not be on disk yet.
+    unflushed: Vec<usize>,
+    /// Hits flushed.
+    persisted: usize,
+    /// Why writing failed, and the first line whose hit may be lost.
+    failed: Option<(std::io::Error, usize)>,
+}
+
+impl Sinks {
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.writer.flush()?;
+        if let Some(descriptors) = self.descriptors.as_mut() {
+            descriptors.flush()?;
+        }
+        if let Some(sealed) = self.sealed.as_mut() {
+            sealed.flush()?;
+        }
+        self.persisted += self.unflushed.len();
+        self.unflushed.clear();
+        Ok(())
+    }
+    /// What the run appended to each output file, hashed from where it
+    /// started appending.
+    fn appended(&self) -> std::io::Result<Vec<manifest::FileEntry>> {
+        let written = self.writer.stats().into_iter().map(|target| (target.path, target.offset));
+        written
+            .chain(self.sealed_file.clone())
+            .map(|(path, offset)| {
+                let (sha256, bytes) = digest::sha256_file_from(&path, offset)?;
+                Ok(manifest::FileEntry {
+                    path: path.display().to_string(),
+                    offset: Some(offset),
+                    sha256,
+                    bytes,
+                })
+            })
+            .collect()
+    }
+}
+
+/// Pauses the run while the output or checkpoint volume is under
+/// `--min-free-space`, saving the checkpoint as it does, and resumes it once
+/// there is room again. Returns when `done` is dropped.
+fn watch_free_space(
+    volumes: &[PathBuf],
+    min_free: u64,
+    stop: &CancelToken,
+    position: &AtomicUsize,
+    saver: &resume::Saver,
+    done: mpsc::Receiver<()>,
+) {
+    let mut paused = false;
+    while let Err(mpsc::RecvTimeoutError::Timeout) = done.recv_timeout(disk::POLL) {
+        // A volume that can't be asked doesn't hold the run up
+        let low = disk::low_space(volumes, min_free).unwrap_or(None);
+        match (low, paused) {
+            (Some(low), false) => {
+                stop.pause();
+                paused = true;
+                let pos = position.load(Ordering::SeqCst);
+                let saved = match saver.save(pos) {
+                    Ok(()) => format!("checkpoint saved at position {}", pos),
+                    Err(e) => format!("checkpoint not saved: {}", e),
+                };
+                println!(
+                    "\nWarning: only {} free for '{}' (--min-free-space {}). Paused, {}; free some space to continue, or press Ctrl+C to stop.",
+                    memory::format_size(low.free),
+                    low.path.display(),
+                    memory::format_size(min_free),
+                    saved
+                );
+            }
+            (None, true) => {
+                stop.resume();
+                paused = false;
+                println!("Disk space available again; continuing.");
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Creates the output and checkpoint directories, and returns the line to
+/// resume from: the checkpoint's, unless there is none or --restart.
+fn load_checkpoint(cli: &Cli, checkpoint_path: &Path, writes: bool) -> Result<usize, Box<dyn std::error::Error>> {
+    // A count-only run never touches the disk
+    if !writes {
+        return Ok(0);
+    }
     // Create output directory only in the current working directory
-    if let Some(parent) = output_path.parent() {
+    if let Some(parent) = cli.output.parent() {
         fs::create_dir_all(parent)?;
     }
-
     // Create checkpoint directory if it doesn't exist
     if let Some(cp_parent) = checkpoint_path.parent() {
         fs::create_dir_all(cp_parent)?;
     }
+    if !checkpoint_path.exists() || cli.restart {
+        return Ok(0);
+    }
+    let checkpoint = resume::load(checkpoint_path).map_err(|e| format!("{}; --restart starts from the first line", e))?;
+    Ok(checkpoint.line)
+}
 
-    // Load checkpoint
-    let checkpoint = if checkpoint_path.exists() {
-        fs::read_to_string(checkpoint_path)?.parse().unwrap_or(0)
+/// Tunes the run on the first input unless --no-auto-tune, then scans the
+/// inputs with as many threads as it validates with. Over several files or
+/// directories, one that can't be read is set aside rather than ending the
+/// run.
+fn scan_inputs(
+    cli: &Cli,
+    input_paths: Vec<PathBuf>,
+    run: Option<&Run>,
+    writes: bool,
+) -> Result<(Inputs, Option<tune::Profile>, usize), Box<dyn std::error::Error>> {
+    let profile = engine::probe(&cli.engine, &input_paths[0], |line| parse(line).is_some())?.map(|probe| {
+        let profile = probe.profile();
+        println!("Tuned for {}: {}", probe, profile);
+        profile
+    });
+    let validators = engine::validators(&cli.engine, profile);
+    let several_inputs = cli.input.len() > 1 || cli.input.iter().any(|path| path.is_dir());
+    let inputs = if several_inputs {
+        let (inputs, failures) = Inputs::scan_all(input_paths, validators);
+        set_aside_failed(cli, &failures, run, writes)?;
+        if inputs.files.is_empty() {
+            return Err("none of the input files could be read".into());
+        }
+        inputs
     } else {
-        0
+        Inputs::scan(input_paths, validators)?
+    };
+    Ok((inputs, profile, validators))
+}
+
+/// Makes sure the checkpoint to resume from was taken from these inputs,
+/// then records their fingerprint for the next one.
+fn verify_checkpoint(
+    cli: &Cli,
+    checkpoint_path: &Path,
+    inputs: &Inputs,
+    input_scan: &InputScan,
+    checkpoint: usize,
+    writes: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let input_name = inputs.name();
+    if checkpoint > 0 {
+        match resume::load_fingerprint(checkpoint_path) {
+            None => println!("Warning: checkpoint has no input fingerprint, resuming without verification."),
+            Some(stored) => match resume::verify(&stored, input_scan, checkpoint) {
+                Verdict::Unchanged => {}
+                Verdict::PrefixIntact => println!(
+                    "Warning: '{}' changed since the checkpoint, but the first {} lines still match. Resuming.",
+                    input_name,
+                    checkpoint
+                ),
+                Verdict::Changed { line } if cli.force_resume => println!(
+                    "Warning: checkpoint was taken from '{}' and no longer matches at line {}. Resuming anyway (--force-resume).",
+                    stored.path,
+                    line + 1
+                ),
+                Verdict::Changed { line } => {
+                    return Err(format!(
+                        "checkpoint at line {} was taken from '{}', which doesn't match '{}' (first difference at line {}). \
+                         Use --restart to start over or --force-resume to resume anyway.",
+                        checkpoint,
+                        stored.path,
+                        input_name,
+                        line + 1
+                    )
+                    .into());
+                }
+            },
+        }
+    }
+    if writes {
+        resume::save_fingerprint(checkpoint_path, &InputFingerprint::new(input_name, input_scan))?;
+    }
+    Ok(())
+}
+
+/// Logs the start of a run. No run goes unrecorded: it doesn't start if the
+/// log can't be written.
+fn record_start(
+    audit: &AuditLog,
+    args: &[OsString],
+    inputs: &Inputs,
+    checkpoint: usize,
+    writes: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    audit
+        .record(
+            "started",
+            serde_json::json!({
+                "args": args.iter().map(|arg| arg.to_string_lossy()).collect::<Vec<_>>(),
+                "cwd": std::env::current_dir().ok(),
+                "inputs": inputs
+                    .files
+                    .iter()
+                    .map(|file| serde_json::json!({
+                        "path": file.path,
+                        "sha256": file.scan.sha256,
+                        "bytes": file.scan.bytes,
+                        "lines": file.scan.lines,
+                    }))
+                    .collect::<Vec<_>>(),
+                "resumed_from_line": checkpoint,
+                "count_only": !writes,
+            }),
+        )
+        .map_err(|e| format!("can't write the audit log {}: {}", audit.path().display(), e))?;
+    Ok(())
+}
+
+/// Queue and batch sizes as tuned or given, shrunk to fit --max-memory.
+fn tune_limits(
+    cli: &Cli,
+    profile: Option<tune::Profile>,
+    validators: usize,
+    inputs: &Inputs,
+    total_lines: usize,
+) -> Result<Tuned, Box<dyn std::error::Error>> {
+    // An empty input has nothing to read anyway
+    let avg_line = (inputs.total_bytes() as usize).checked_div(total_lines).unwrap_or(128).max(1);
+    let growing = Growing {
+        cache: cli.engine.cache.is_some() || cli.engine.cache_file.is_some(),
+        index: cli.incremental,
+        duplicates: cli.duplicates.is_some(),
+        groups: cli.group_by.is_some(),
     };
+    let tuned = Tuned::new(&cli.engine, validators, profile, growing, avg_line)?;
+    if let Some(cap) = cli.engine.max_memory {
+        let limits = &tuned.limits;
+        println!(
+            "Memory cap {}: about {} in use (blocks of {}, queue depth {}, up to {} hits queued)",
+            memory::format_size(cap),
+            memory::format_size(limits.estimate(validators, avg_line, cli.engine.io_uring)),
+            memory::format_size(limits.block_size as u64),
+            limits.queue_depth,
+            limits.hit_queue
+        );
+        let budget = &tuned.budget;
+        let shares: Vec<String> = [
+            (budget.cache_lines, "cached lines"),
+            (budget.index_keys, "--incremental keys in memory"),
+            (budget.duplicates, "--duplicates phrases"),
+            (budget.group_hits, "--group-by hits"),
+        ]
+        .into_iter()
+        .filter_map(|(count, what)| count.map(|count| format!("{} {}", count, what)))
+        .collect();
+        if !shares.is_empty() {
+            println!("The rest holds up to {}", shares.join(", "));
+        }
+    }
+    Ok(tuned)
+}
 
-    let file = File::open(input_path)?;
-    let reader = BufReader::new(file);
-    let mut total_lines = 0;
-    for _ in reader.lines() {
-        total_lines += 1;
+/// What becomes of an existing output: a resumed run appends to what its
+/// earlier part wrote, whatever the flags.
+fn existing_output(cli: &Cli, checkpoint: usize) -> ExistingOutput {
+    match (cli.overwrite, cli.error_if_exists) {
+        _ if checkpoint > 0 => ExistingOutput::Append,
+        (true, _) => ExistingOutput::Overwrite,
+        (_, true) => ExistingOutput::Error,
+        _ => ExistingOutput::Append,
     }
+}
 
-    let file = File::open(input_path)?; // reopen
-    let reader = BufReader::new(file);
+/// Opens what the run writes its hits to: the output files (only counted
+/// for a count-only run), the --descriptors file, and the sealed hits file
+/// or the collector.
+fn open_sinks(
+    cli: &Cli,
+    checkpoint_path: &Path,
+    existing: ExistingOutput,
+    checkpoint: usize,
+    writes: bool,
+    index_keys: Option<usize>,
+) -> Result<Sinks, Box<dyn std::error::Error>> {
+    let output_path = cli.output.as_path();
+    let writer = if writes {
+        let mut writer = HitWriter::create(output_path, cli.split_output, cli.shards, existing).map_err(|e| {
+            match e.kind() {
+                std::io::ErrorKind::AlreadyExists => format!("{} (--error-if-exists); use --append or --overwrite to reuse it", e),
+                _ => e.to_string(),
+            }
+        })?;
+        if cli.incremental {
+            if let Some(keys) = index_keys {
+                writer.keys_in_memory(keys);
+            }
+            // The journal only speeds up resuming; a new run starts it over
+            if checkpoint == 0 {
+                resume::remove_index(checkpoint_path)?;
+            }
+            writer.skip_existing(Some(&resume::index_path(checkpoint_path)))?;
+        }
+        writer
+    } else {
+        HitWriter::dry_run(output_path, cli.split_output, cli.shards)
+    };
 
-    println!("Total lines: {}, Starting from checkpoint: {}", total_lines, checkpoint);
+    // Descriptors aren't secret, but they're written in step with the hits
+    let descriptors = match cli.descriptors.as_ref().filter(|_| writes) {
+        Some(_) if cli.network.hrp().is_none() => {
+            return Err(format!("--descriptors are of BIP84 accounts, and {} has no native SegWit", cli.network).into())
+        }
+        Some(path) => {
+            let file = OpenOptions::new().create(true).append(true).open(path)?;
+            Some(BufWriter::new(file))
+        }
+        None => None,
+    };
 
-    let output = OpenOptions::new()
-        .create(true)
-        .append(true)
-        .open(output_path)?;
-    let writer = Arc::new(Mutex::new(BufWriter::new(output)));
+    // Sealed phrases are, like the masked lines, written in step with them
+    // or sent to the collector, with the ones it doesn't take spilled
+    let mut sealed_file = None;
+    let sealed: Option<Box<dyn Write + Send>> = match cli.seal_to.filter(|_| writes) {
+        Some(_) => match &cli.stream_to {
+            Some(addr) => {
+                let tls = TlsFiles {
+                    cert: cli.stream_cert.as_deref().unwrap(),
+                    key: cli.stream_key.as_deref().unwrap(),
+                    ca: cli.stream_ca.as_deref().unwrap(),
+                }
+                .client_config()?;
+                let uplink = Uplink::open(addr, tls, collector::spill_path(output_path));
+                match uplink.last_error() {
+                    Some(e) => println!(
+                        "Warning: can't reach the collector at {} ({}); sealed hits wait in {} until it can be reached",
+                        addr,
+                        e,
+                        uplink.spill_path().display()
+                    ),
+                    None => println!("Sending sealed hits to the collector at {}", addr),
+                }
+                Some(Box::new(uplink))
+            }
+            None => {
+                let path = seal::sealed_path(output_path);
+                let file = match existing {
+                    ExistingOutput::Overwrite => fs::File::create(&path)?,
+                    _ => OpenOptions::new().create(true).append(true).open(&path)?,
+                };
+                sealed_file = Some((path, file.metadata()?.len()));
+                Some(Box::new(BufWriter::new(file)))
+            }
+        },
+        None => None,
+    };
 
-    // Set up Ctrl+C handler
-    let running = Arc::new(AtomicBool::new(true));
-    let r = running.clone();
-    let cp_path = checkpoint_path.to_path_buf();
+    Ok(Sinks {
+        writer,
+        descriptors,
+        sealed,
+        sealed_file,
+        unflushed: Vec::new(),
+        persisted: 0,
+        failed: None,
+    })
+}
 
-    // Setup current progress tracking for checkpoint on Ctrl+C
-    let current_position = Arc::new(AtomicUsize::new(checkpoint));
-    let pos_for_handler = current_position.clone();
+/// Handles Ctrl+C, Ctrl+Break, closing the console window, SIGTERM and
+/// SIGHUP: the run finishes the lines it has read, within the grace
+/// period. A second signal, or the end of the grace period, saves the
+/// checkpoint at `saved_position` and exits without waiting for the lines
+/// in flight; those are validated again on resuming. Returns whether a
+/// signal came, and the flag to set once the run has drained and saves its
+/// own state, which the handler then leaves to it.
+fn handle_signals(
+    cli: &Cli,
+    stop: &CancelToken,
+    saver: &resume::Saver,
+    saved_position: &Arc<AtomicUsize>,
+    run: Option<&Run>,
+    audit: &AuditLog,
+    writes: bool,
+) -> (Arc<AtomicBool>, Arc<Mutex<bool>>) {
+    let r = stop.clone();
+    let saver = saver.clone();
+    let pos_for_handler = saved_position.clone();
+    let run_for_handler = run.cloned();
+    let audit_for_handler = audit.clone();
+    let interrupted = Arc::new(AtomicBool::new(false));
+    let interrupted_for_handler = interrupted.clone();
+    let settled = Arc::new(Mutex::new(false));
+    let settled_for_handler = settled.clone();
 
-    ctrlc::set_handler(move || {
-        r.store(false, Ordering::SeqCst);
+    let stop_now = Arc::new(move || {
+        r.cancel();
+        if *settled_for_handler.lock().unwrap() {
+            return;
+        }
         let pos = pos_for_handler.load(Ordering::SeqCst);
-        println!("\nReceived Ctrl+C! Saving checkpoint at position: {}", pos);
-        fs::write(&cp_path, pos.to_string()).expect("Failed to write checkpoint on exit");
-        println!("Checkpoint saved. Exiting safely.");
-        std::process::exit(0);
+        let _ = audit_for_handler.record("interrupted", serde_json::json!({ "position": pos }));
+        if !writes {
+            println!("\nInterrupted! Count-only run stopped.");
+            std::process::exit(0);
+        }
+        println!("\nInterrupted! Saving checkpoint at position: {}", pos);
+        saver.save_last(pos).expect("Failed to write checkpoint on exit");
+        match run_for_handler.clone() {
+            Some(mut run) => {
+                let _ = run.update("interrupted");
+                println!("Checkpoint saved. Resume with: mnemonic_validator runs resume {}", run.record.id);
+            }
+            None => println!("Checkpoint saved. Exiting safely."),
+        }
+        std::process::exit(EXIT_RESUMABLE);
+    });
+
+    let grace = Duration::from_secs(cli.grace_period);
+    let r = stop.clone();
+    console::on_exit(move |signal| {
+        if signal == console::Stop::Imminent || interrupted_for_handler.swap(true, Ordering::SeqCst) {
+            stop_now();
+            return;
+        }
+        r.finish();
+        println!(
+            "\nInterrupted! Finishing the lines already read (up to {}s; interrupt again to stop at once)...",
+            grace.as_secs()
+        );
+        let stop_now = stop_now.clone();
+        std::thread::spawn(move || {
+            std::thread::sleep(grace);
+            stop_now();
+        });
     }).expect("Error setting Ctrl+C handler");
+    (interrupted, settled)
+}
+
+/// Valid phrases, unless --address-prefix, --address-regex or --utxo-set
+/// rules them out; `None` when there's nothing to filter on.
+fn address_filter(cli: &Cli) -> Result<Option<AddressFilter>, Box<dyn std::error::Error>> {
+    let funded = match &cli.utxo_set {
+        Some(_) if cli.blockchain != Blockchain::Bitcoin => {
+            return Err(format!("--utxo-set holds Bitcoin outputs, not {} ones", cli.blockchain.name()).into())
+        }
+        Some(path) => Some(utxo_index::open(path, cli.network)?.0),
+        None => None,
+    };
+    Ok(AddressFilter::new(
+        cli.address_prefix.clone(),
+        cli.address_regex.clone(),
+        funded,
+        cli.address_count,
+        cli.blockchain,
+        cli.network,
+    ))
+}
+
+/// The --cache, loaded from --cache-file if it was saved with the same
+/// settings; `None` without either.
+fn open_cache(cli: &Cli, budget: &Budget) -> Result<Option<OutcomeCache>, Box<dyn std::error::Error>> {
+    if let Some((asked, lines)) = engine::cache_lines(&cli.engine, budget).filter(|(asked, lines)| lines < asked) {
+        println!("Cache: holding {} lines rather than {}, to stay under --max-memory", lines, asked);
+    }
+    let settings = cache_settings(cli)?;
+    let Some((cache, loaded)) = engine::open_cache(&cli.engine, settings.as_bytes(), budget)? else {
+        return Ok(None);
+    };
+    if let Some(path) = &cli.engine.cache_file {
+        if loaded {
+            println!("Cache: {} lines from {}", cache.len(), path.display());
+        } else if path.exists() {
+            println!("Cache: {} was made with other settings; starting it afresh", path.display());
+        }
+    }
+    Ok(Some(cache))
+}
+
+/// The lines of the inputs: read through io_uring (or overlapped I/O) if
+/// asked to, in blocks under a memory cap, or else from memory maps kept in
+/// `mapped` for the slices borrowed from them. Layouts spanning lines get
+/// the lines after each one.
+fn input_source<'a>(
+    cli: &Cli,
+    inputs: &'a Inputs,
+    tuned: &Tuned,
+    layout: Layout,
+    mapped: &'a mut Vec<(usize, Input)>,
+) -> Result<Source<'a>, Box<dyn std::error::Error>> {
+    let (reader, unavailable) = Reader::choose(&cli.engine)?;
+    if let Some(why) = unavailable {
+        println!("Warning: io_uring unavailable ({}), memory-mapping the input instead.", why);
+    }
+    let files = inputs.files.iter().map(|file| (file.path.clone(), file.first_line)).collect();
+    let source = engine::source(&cli.engine, reader, files, tuned, mapped)?;
+    Ok(match layout.window() {
+        1 => source,
+        _ => {
+            let boundaries = inputs.files.iter().map(|file| file.first_line).collect();
+            Box::new(Windows::new(source, layout, boundaries)) as Source
+        }
+    })
+}
+
+/// What the hits written tell beyond their number: wallets by fingerprint,
+/// repeated phrases, and languages with --language auto.
+struct Findings {
+    groups: Option<Groups>,
+    duplicates: Option<Duplicates>,
+    /// Hits per language with --language auto.
+    by_language: Option<BTreeMap<String, usize>>,
+    /// Phrases valid in several languages.
+    ambiguous: Vec<String>,
+}
+
+impl Findings {
+    fn new(cli: &Cli, budget: &Budget) -> Self {
+        Self {
+            groups: cli.group_by.map(|_| budget.group_hits.map_or_else(Groups::default, Groups::with_limit)),
+            duplicates: cli.duplicates.as_ref().map(|_| budget.duplicates.map_or_else(Duplicates::default, Duplicates::with_limit)),
+            by_language: (cli.language == Choice::Auto).then(BTreeMap::new),
+            ambiguous: Vec::new(),
+        }
+    }
+
+    fn print_languages(&self) {
+        if let Some(by_language) = &self.by_language {
+            print_languages(by_language, &self.ambiguous);
+        }
+    }
+
+    /// Prints the wallets found more than once, and the repeated phrases,
+    /// which go to the --duplicates report.
+    fn report(&self, cli: &Cli) -> Result<(), Box<dyn std::error::Error>> {
+        if let Some(groups) = &self.groups {
+            println!("Different wallets (by master fingerprint): {}", groups.wallets());
+            for (fingerprint, locations) in groups.repeated() {
+                println!("  {} found {} times: {}", fingerprint, locations.len(), locations.join(", "));
+            }
+            if groups.untracked() > 0 {
+                println!("Warning: {} hits past what --max-memory left room for weren't grouped.", groups.untracked());
+            }
+        }
+        if let (Some(duplicates), Some(path)) = (&self.duplicates, &cli.duplicates) {
+            let repeated = duplicates.repeated();
+            println!("Different phrases: {}, found more than once: {}", duplicates.phrases(), repeated.len());
+            for (phrase, count) in repeated.iter().take(5) {
+                println!("  {} found {} times", phrase, count);
+            }
+            if duplicates.untracked() > 0 {
+                println!(
+                    "Warning: {} hits of phrases past what --max-memory left room for aren't in the report.",
+                    duplicates.untracked()
+                );
+            }
+            duplicates
+                .write(path)
+                .map_err(|e| format!("can't write the duplicates report {}: {}", path.display(), e))?;
+            println!("Duplicates report written to {}", path.display());
+        }
+        Ok(())
+    }
+}
+
+/// The error a run ends with when writing its results or saving the
+/// checkpoint failed, after setting the checkpoint before any hit that may
+/// be lost.
+fn failed_run(
+    sinks: &mut Sinks,
+    checkpoint_error: Option<std::io::Error>,
+    saved_position: usize,
+    saver: &resume::Saver,
+    output_path: &Path,
+) -> Box<dyn std::error::Error> {
+    let (reason, mut lost) = match sinks.failed.take() {
+        Some((e, lost)) => (format!("writing results failed: {}", e), lost),
+        None => (format!("saving the checkpoint failed: {}", checkpoint_error.unwrap()), usize::MAX),
+    };
+    // The output may still take what was buffered; if not, it's lost too
+    if let Some(&first) = sinks.unflushed.iter().min() {
+        if sinks.flush().is_err() {
+            lost = lost.min(first);
+        }
+    }
+    let safe = saved_position.min(lost);
+    let saved = match saver.save(safe) {
+        Ok(()) => format!("Checkpoint set to line {}, before any result that may be lost", safe),
+        Err(e) => format!("The checkpoint couldn't be saved either ({})", e),
+    };
+    format!(
+        "{}\nStopped early: {} valid mnemonics were saved to '{}'. {}; resume once the problem is fixed \
+         (hits saved past the checkpoint may then be written twice).",
+        reason,
+        sinks.persisted,
+        output_path.display(),
+        saved
+    )
+    .into()
+}
+
+/// The options of a run as the manifest records them. The engine settings
+/// are as given; the sizes are the ones the run went with after tuning and
+/// any memory cap.
+fn manifest_parameters(
+    cli: &Cli,
+    tuned: &Tuned,
+    existing: ExistingOutput,
+    long_lines: Option<&LongLines>,
+) -> serde_json::Value {
+    serde_json::json!({
+        "split_output": cli.split_output.map(|split| format!("{:?}", split)),
+        "shards": cli.shards,
+        "order": format!("{:?}", cli.order).to_lowercase(),
+        "include": cli.include.iter().map(Glob::as_str).collect::<Vec<_>>(),
+        "exclude": cli.exclude.iter().map(Glob::as_str).collect::<Vec<_>>(),
+        "carve": cli.carve,
+        "heartbeat": cli.heartbeat,
+        "threads": tuned.validators,
+        "block_size": tuned.limits.block_size,
+        "queue_depth": tuned.limits.queue_depth,
+        "chunk_lines": tuned.chunk_lines,
+        "skip": cli.skip,
+        "limit": cli.limit,
+        "sample": cli.sample,
+        "sample_seed": cli.sample_seed,
+        "mask": cli.mask,
+        "seal_to": cli.seal_to.map(|key| digest::to_hex(&key)),
+        "annotate": cli.annotate,
+        "fingerprint": cli.fingerprint,
+        "group_by": cli.group_by.map(|group| format!("{:?}", group)),
+        "descriptors": cli.descriptors,
+        "network": cli.network.name(),
+        "address_prefix": cli.address_prefix,
+        "address_regex": cli.address_regex.as_ref().map(Regex::as_str),
+        "utxo_set": cli.utxo_set,
+        "blockchain": cli.blockchain.name(),
+        "address_count": cli.address_count,
+        "delimiter": cli.delimiter,
+        "numbered": cli.numbered,
+        "join_lines": cli.join_lines,
+        "seedqr": cli.seedqr,
+        "compact_seedqr": cli.compact_seedqr,
+        "incremental": cli.incremental,
+        "existing_output": format!("{:?}", existing),
+        "engine": cli.engine,
+        "long_lines_seen": long_lines.map(LongLines::seen),
+        "exec_per_valid": cli.exec_per_valid.is_some(),
+    })
+}
+
+/// The inputs as the manifest records them, with what the run found in
+/// each.
+fn manifest_inputs(inputs: &Inputs, per_file: &[Summary]) -> Vec<manifest::InputEntry> {
+    inputs
+        .files
+        .iter()
+        .zip(per_file)
+        .map(|(file, summary)| manifest::InputEntry {
+            file: manifest::FileEntry {
+                path: file.path.display().to_string(),
+                offset: None,
+                sha256: file.scan.sha256.clone(),
+                bytes: file.scan.bytes,
+            },
+            lines: file.scan.lines,
+            processed: summary.processed,
+            valid: summary.valid,
+            elapsed_secs: summary.elapsed.as_secs_f64(),
+            lines_per_sec: summary.lines_per_sec(),
+            hit_rate: summary.hit_rate(),
+        })
+        .collect()
+}
+
+/// A hit's line in the output when it isn't the phrase alone: masked, or
+/// with its fingerprint and where it was found in front.
+fn hit_line(
+    cli: &Cli,
+    text: &str,
+    fingerprint: Option<&str>,
+    file: &Path,
+    local: usize,
+    part: Option<usize>,
+    several: bool,
+) -> Option<SecretString> {
+    if cli.mask {
+        let name = several.then(|| file.display().to_string());
+        let mut line = mask::masked_line(text, name.as_deref(), local + 1);
+        if let Some(fingerprint) = fingerprint {
+            line.push_str(&format!(" fp:{}", fingerprint));
+        }
+        return Some(SecretString::new(line));
+    }
+    if !cli.annotate && fingerprint.is_none() {
+        return None;
+    }
+    let fingerprint = fingerprint.map(|f| format!("{}:", f)).unwrap_or_default();
+    let location = if cli.annotate {
+        let part = part.map(|n| format!("{}:", n)).unwrap_or_default();
+        format!("{}:{}:{}", file.display(), local + 1, part)
+    } else {
+        String::new()
+    };
+    Some(SecretString::new(format!("{}{}{}", fingerprint, location, text)))
+}
+
+/// The progress line of a run, with the time left.
+struct StatusLine {
+    progress: Progress,
+    /// Line the pipeline's current pass started at.
+    base: usize,
+    end_line: usize,
+}
+
+impl StatusLine {
+    fn new(first_line: usize, end_line: usize) -> Self {
+        Self {
+            progress: Progress::new(),
+            base: first_line,
+            end_line,
+        }
+    }
+
+    /// Redraws the line from the pipeline's progress event, which comes
+    /// every few seconds (every half minute when logging to a file).
+    fn show(&mut self, progress: &events::Progress) {
+        let i = self.base + progress.processed;
+        let speed = progress.per_second() as usize;
+        if progress.total.is_none() {
+            // Past the end with --follow: no total to count towards
+            self.progress.show(&format!("[follow] {} lines, {} valid, {} lines/s", i, progress.valid, speed));
+            return;
+        }
+        let percent_done = (i * 100) / self.end_line.max(1);
+        let eta = progress.eta.map_or_else(|| "Calculating...".to_string(), |eta| eta.to_string());
+        self.progress.show(&format!(
+            "[{:3}%] {}/{} lines, {} valid, {} lines/s, ETA: {}",
+            percent_done, i, self.end_line, progress.valid, speed, eta
+        ));
+    }
+}
+
+/// The pipeline's events for a run: its progress drives `status`.
+fn status_events(status: &Arc<Mutex<StatusLine>>) -> Events {
+    let interval = status.lock().unwrap().progress.interval();
+    let status = status.clone();
+    let mut events = Events::new().on_progress(move |progress| status.lock().unwrap().show(progress));
+    events.progress_interval = interval;
+    events
+}
+
+/// Says how many lines went over --max-line-length, if any did.
+fn report_long_lines(long_lines: &LongLines) {
+    if long_lines.seen() == 0 {
+        return;
+    }
+    let handled = match long_lines.oversize {
+        Oversize::Skip => "skipped",
+        Oversize::Truncate => "truncated",
+    };
+    println!(
+        "Lines longer than {} ({}): {}",
+        memory::format_size(long_lines.max as u64),
+        handled,
+        long_lines.seen()
+    );
+}
+
+/// Lets go of the checkpoint of a finished run, with its input fingerprint
+/// and --incremental journal.
+fn remove_checkpoint(saver: &resume::Saver, total_lines: usize) -> std::io::Result<()> {
+    // Final checkpoint update
+    saver.save(total_lines)?;
+    fs::remove_file(saver.path())?;
+    resume::remove_fingerprint(saver.path())?;
+    resume::remove_index(saver.path())
+}
+
+/// The lines processed and hits found over time, as the manifest records
+/// them.
+fn manifest_timeline(stats: &RunStats) -> Vec<manifest::TimelineEntry> {
+    stats
+        .timeline()
+        .into_iter()
+        .map(|sample| manifest::TimelineEntry {
+            elapsed_secs: sample.elapsed.as_secs_f64(),
+            processed: sample.processed,
+            valid: sample.valid,
+        })
+        .collect()
+}
+
+/// Validates the inputs into the outputs, from the checkpoint on if there
+/// is one, and reports how it went.
+fn process_file(
+    cli: &Cli,
+    input_paths: Vec<PathBuf>,
+    checkpoint_path: &Path,
+    hooks: Option<HookRunner>,
+    mut run: Option<Run>,
+    audit: &AuditLog,
+    args: &[OsString],
+) -> Result<Ended, Box<dyn std::error::Error>> {
+    let output_path = cli.output.as_path();
+
+    // A count-only run never touches the disk
+    let writes = !cli.count_only;
+    let checkpoint = load_checkpoint(cli, checkpoint_path, writes)?;
+
+    let started_at = chrono::Utc::now();
+    // Scanning and tuning count towards the time limit too
+    let time_limit = time_limit(cli);
+    let (inputs, profile, validators) = scan_inputs(cli, input_paths, run.as_ref(), writes)?;
+    let input_scan = inputs.combined_scan();
+    let total_lines = input_scan.lines;
+
+    // --follow picks up a final line still being written once it's complete
+    let complete = if !cli.follow {
+        None
+    } else if inputs.files.len() != 1 {
+        return Err("--follow needs a single input file".into());
+    } else {
+        let file = &inputs.files[0];
+        let len = lines::complete_len(&file.path, file.scan.bytes)?;
+        Some((len, total_lines - usize::from(len < file.scan.bytes)))
+    };
+
+    verify_checkpoint(cli, checkpoint_path, &inputs, &input_scan, checkpoint, writes)?;
+    record_start(audit, args, &inputs, checkpoint, writes)?;
+
+    if inputs.files.len() > 1 {
+        println!("Inputs: {} files, {} bytes", inputs.files.len(), inputs.total_bytes());
+    }
+    println!("Total lines: {}, Starting from checkpoint: {}", total_lines, checkpoint);
+
+    // Window of lines selected by --skip / --limit, resumed from the checkpoint
+    let first_line = checkpoint.max(cli.skip);
+    let end_line = match (complete, cli.limit) {
+        (Some((_, complete_lines)), _) => complete_lines,
+        (None, Some(limit)) => total_lines.min(cli.skip.saturating_add(limit)),
+        (None, None) => total_lines,
+    };
+    if cli.skip > 0 || cli.limit.is_some() || cli.sample.is_some() {
+        println!(
+            "Selected lines {}..{}{}",
+            cli.skip + 1,
+            end_line,
+            cli.sample
+                .map(|rate| format!(", sampling {}%", rate * 100.0))
+                .unwrap_or_default()
+        );
+    }
+
+    if writes {
+        let remaining = end_line.saturating_sub(first_line);
+        check_free_space(cli, &inputs, output_path, checkpoint_path, remaining, total_lines)?;
+    }
+
+    let tuned = tune_limits(cli, profile, validators, &inputs, total_lines)?;
+    let existing = existing_output(cli, checkpoint);
+    let sinks = RefCell::new(open_sinks(cli, checkpoint_path, existing, checkpoint, writes, tuned.budget.index_keys)?);
+
+    // The last checkpoint saved, once every hit before it was flushed; a
+    // signal or a full disk saves it again, as it can't flush the output
+    let saved_position = Arc::new(AtomicUsize::new(checkpoint));
+    let saver = resume::Saver::new(checkpoint_path);
+    let stop = CancelToken::new();
+    let (interrupted, settled) = handle_signals(cli, &stop, &saver, &saved_position, run.as_ref(), audit, writes);
+
+    if let Some((deadline, at)) = time_limit {
+        stop.set_deadline(deadline);
+        println!("Time limit: stopping at {}", at.format("%Y-%m-%d %H:%M:%S"));
+    }
+
+    // Pause before a full disk can cut the run short; dropping `_watching`
+    // ends the watch
+    let (_watching, done) = mpsc::channel::<()>();
+    if writes && cli.engine.min_free_space > 0 {
+        let volumes = [output_path.to_path_buf(), checkpoint_path.to_path_buf()];
+        let min_free = cli.engine.min_free_space;
+        let stop = stop.clone();
+        let position = saved_position.clone();
+        let saver = saver.clone();
+        std::thread::spawn(move || watch_free_space(&volumes, min_free, &stop, &position, &saver, done));
+    }
+
+    let heartbeat = cli
+        .heartbeat
+        .clone()
+        .map(|path| Heartbeat::new(path, Duration::from_secs(cli.heartbeat_interval)));
+    let run_id = run.as_ref().map(|run| run.record.id.clone());
+    let file_at = |line: usize| inputs.files[inputs.locate(line).0].path.as_path();
+    if let Some(heartbeat) = &heartbeat {
+        heartbeat
+            .write(&heartbeat::Status::new(run_id.as_deref(), "running", file_at(first_line), first_line, 0))
+            .map_err(|e| format!("can't write the heartbeat file {}: {}", heartbeat.path().display(), e))?;
+    }
 
     println!("Starting validation process...");
     let start_time = Instant::now();
-    let last_status_update = Arc::new(Mutex::new(Instant::now()));
-
-    // Counters for statistics
-    let processed = AtomicUsize::new(0);
-    let valid_count = AtomicUsize::new(0);
-
-    // Process lines in parallel
-    reader
-        .lines()
-        .enumerate()
-        .filter(|(i, _)| *i >= checkpoint && running.load(Ordering::Relaxed))
-        .par_bridge()
-        .for_each(|(i, result_line)| {
-            if !running.load(Ordering::Relaxed) {
-                return;
-            }
-
-            processed.fetch_add(1, Ordering::Relaxed);
-            current_position.store(i, Ordering::SeqCst);
-            
-             match result_line { // handle the Result from lines()
-                Ok(line) => {
-                    if is_valid(&line) {
-                        let mut w = writer.lock().unwrap();
-                        writeln!(w, "{}", line).expect("Failed to write");
-                        valid_count.fetch_add(1, Ordering::Relaxed);
+    let status = Arc::new(Mutex::new(StatusLine::new(first_line, end_line)));
+
+    // Counters for statistics, per input file
+    let stats = RunStats::new(&inputs, first_line..end_line, start_time);
+
+    // Phrases the address filter rules out are counted but not written
+    let filter = address_filter(cli)?;
+    let unmatched = AtomicUsize::new(0);
+    let passes = |mnemonic: &Mnemonic| filter.as_ref().is_none_or(|filter| filter.matches(mnemonic));
+    let several = inputs.files.len() > 1;
+
+    let layout = layout(cli);
+    let cache = open_cache(cli, &tuned.budget)?;
+    let validate = |i: usize, line: &str| {
+        let selected = cli.sample.is_none_or(|rate| sample::keep(i, rate, cli.sample_seed));
+        // A hit is validated again, to have its phrase
+        let key = cache.as_ref().filter(|_| selected).map(|cache| cache.key(line));
+        let cached = key.and_then(|key| cache.as_ref()?.get(key));
+        let mnemonic = match cached {
+            _ if !selected => None,
+            Some(Outcome::Invalid) => None,
+            Some(Outcome::Unmatched(n)) => {
+                unmatched.fetch_add(n as usize, Ordering::Relaxed);
+                None
+            }
+            Some(Outcome::Valid) | None => {
+                let mut filtered = 0;
+                let mnemonic = layout.find_map(line, |candidate| {
+                    let valid = parse_in(candidate, cli.language);
+                    if valid.is_empty() {
+                        return None;
+                    }
+                    // In any of the languages it's valid in
+                    let mnemonic = valid.into_iter().find(passes);
+                    if mnemonic.is_none() {
+                        filtered += 1;
                     }
+                    mnemonic
+                });
+                unmatched.fetch_add(filtered, Ordering::Relaxed);
+                if let (Some(cache), Some(key)) = (&cache, key) {
+                    cache.insert(
+                        key,
+                        match (&mnemonic, filtered) {
+                            (Some(_), _) => Outcome::Valid,
+                            (None, 0) => Outcome::Invalid,
+                            (None, n) => Outcome::Unmatched(n.min(u8::MAX as usize) as u8),
+                        },
+                    );
+                }
+                mnemonic
+            }
+        };
+        stats.line_done(inputs.locate(i).0, selected);
+
+        // Update the statistics and heartbeat every 10000 lines
+        if i.is_multiple_of(10000) && i > checkpoint {
+            stats.sample();
+            if let Some(heartbeat) = &heartbeat {
+                // A watchdog reading a stale file is the worst that can happen
+                let _ = heartbeat.beat(|| heartbeat::Status::new(run_id.as_deref(), "running", file_at(i), i, stats.valid()));
+            }
+        }
+
+        mnemonic
+    };
+
+    let mut findings = Findings::new(cli, &tuned.budget);
+
+    // Once --stop-after is reached, the first line whose hit was left out
+    let mut over_limit: Option<usize> = None;
+
+    let mut write = |hit: Hit| -> std::io::Result<()> {
+        let mut sinks = sinks.borrow_mut();
+        let sinks = &mut *sinks;
+        if let Some((_, lost)) = sinks.failed.as_mut() {
+            // Stopping, but let the pipeline drain so every lost hit is seen
+            *lost = (*lost).min(hit.line);
+            return Ok(());
+        }
+        if let Some(first) = over_limit.as_mut() {
+            *first = (*first).min(hit.line);
+            return Ok(());
+        }
+        // Line numbers in the output count from the start of each file
+        let (f, local) = inputs.locate(hit.line);
+        let followed = hit.line >= end_line;
+        let file = &inputs.files[f].path;
+        // The line is gone through again for every valid candidate on it,
+        // not just the one that made it a hit. Candidates come cleaned up,
+        // so they're written as they validated.
+        let mut parts = Vec::new();
+        let _ = layout.each_candidate(&hit.text, |part, candidate| {
+            let valid = parse_in(candidate, cli.language);
+            let languages: Vec<Language> = valid.iter().map(Mnemonic::language).collect();
+            if let Some(mnemonic) = valid.into_iter().find(passes) {
+                parts.push((part, mnemonic, SecretString::new(candidate.to_string()), languages));
+            }
+            ControlFlow::<()>::Continue(())
+        });
+        for (part, mnemonic, text, languages) in &parts {
+            let (part, text) = (*part, text.as_str());
+            let fingerprint = (cli.fingerprint || findings.groups.is_some())
+                .then(|| fingerprint::master_fingerprint(mnemonic))
+                .flatten();
+            let shown = fingerprint.as_deref().filter(|_| cli.fingerprint);
+            let written = match hit_line(cli, text, shown, file, local, part, several) {
+                Some(line) => sinks.writer.write_hit(mnemonic, &line),
+                None => sinks.writer.write_hit(mnemonic, text),
+            };
+            // Counted even when --incremental leaves it out as written before
+            if let (Some(duplicates), Ok(_)) = (findings.duplicates.as_mut(), &written) {
+                duplicates.add(text, &file.display().to_string(), local + 1);
+            }
+            if let (Some(by_language), Ok(true)) = (findings.by_language.as_mut(), &written) {
+                if languages.len() > 1 {
+                    let names: Vec<String> = languages.iter().map(|&language| languages::name(language)).collect();
+                    findings.ambiguous.push(format!(
+                        "{} ({}) at {}:{}",
+                        mask::masked(text),
+                        names.join(", "),
+                        file.display(),
+                        local + 1
+                    ));
+                } else {
+                    *by_language.entry(languages::name(mnemonic.language())).or_insert(0) += 1;
                 }
+            }
+            if let Ok(true) = written {
+                sinks.unflushed.push(hit.line);
+                if let (Some(groups), Some(fingerprint)) = (findings.groups.as_mut(), fingerprint) {
+                    groups.add(fingerprint, format!("{}:{}", file.display(), local + 1));
+                }
+            }
+            // Appended lines may come slowly, and a paused run may be left
+            // so; don't keep their hits buffered
+            let flushed = written.and_then(|written| {
+                if let Some(descriptors) = sinks.descriptors.as_mut().filter(|_| written) {
+                    write_descriptors(descriptors, mnemonic, cli.network)?;
+                }
+                if let (Some(sealed), Some(key)) = (sinks.sealed.as_mut().filter(|_| written), &cli.seal_to) {
+                    writeln!(sealed, "{}", seal::seal(key, text).map_err(std::io::Error::other)?)?;
+                }
+                if followed || stop.is_paused() || sinks.unflushed.len() >= FLUSH_HITS {
+                    sinks.flush()?;
+                }
+                Ok(written)
+            });
+            let written = match flushed {
+                Ok(written) => written,
                 Err(e) => {
-                    eprintln!("Error reading line {}: {}", i, e);
+                    let lost = sinks.unflushed.drain(..).chain([hit.line]).min().unwrap();
+                    sinks.failed = Some((e, lost));
+                    stop.cancel();
+                    return Ok(());
                 }
+            };
+            stats.hit(f);
+            if cli.stop_after.is_some_and(|limit| stats.valid() as u64 >= limit) {
+                over_limit = Some(usize::MAX);
+                stop.finish();
             }
 
-            // Update checkpoint every 10000 lines
-            if i % 10000 == 0 && i > checkpoint {
-                // Write checkpoint
-                fs::write(checkpoint_path, i.to_string()).expect("Checkpoint update failed");
-
-                // Only update status every 3 seconds to reduce terminal spam
-                let mut last_update = last_status_update.lock().unwrap();
-                if last_update.elapsed() >= Duration::from_secs(3) {
-                    // Get statistics
-                    let elapsed = start_time.elapsed();
-                    let valid = valid_count.load(Ordering::Relaxed);
-                    let proc = processed.load(Ordering::Relaxed);
-                    let percent_done = (i * 100) / total_lines.max(1);
-                    let speed = if elapsed.as_secs() > 0 { proc / elapsed.as_secs() as usize } else { proc };
-                    let eta = estimate_remaining(proc, total_lines, elapsed);
-
-                    // Clear previous line and print progress
-                    print!("\r\x1B[K");
-                    print!(
-                        "[{:3}%] {}/{} lines, {} valid, {} lines/s, ETA: {}",
-                        percent_done,
-                        i,
-                        total_lines,
-                        valid,
-                        speed,
-                        eta
-                    );
-                    std::io::stdout().flush().expect("Failed to flush stdout");
+            if let Some(hooks) = hooks.as_ref().filter(|_| written) {
+                hooks.submit(text, file, local + 1);
+            }
+        }
+        Ok(())
+    };
 
-                    *last_update = Instant::now();
-                }
+    // Every 10000 lines of the pipeline's watermark, once every hit before
+    // it is flushed. A run that's finishing saves where it stops instead.
+    let mut last_saved = checkpoint;
+    let mut checkpoint_error = None;
+    let mut save_checkpoint = |line: usize| -> std::io::Result<()> {
+        if !writes || line < last_saved + 10000 || stop.is_finishing() {
+            return Ok(());
+        }
+        let mut sinks = sinks.borrow_mut();
+        if sinks.failed.is_some() {
+            return Ok(());
+        }
+        if let Err(e) = sinks.flush() {
+            let lost = sinks.unflushed.drain(..).min().unwrap_or(line);
+            sinks.failed = Some((e, lost));
+            stop.cancel();
+            return Ok(());
+        }
+        match saver.save(line) {
+            Ok(()) => {
+                last_saved = line;
+                saved_position.fetch_max(line, Ordering::SeqCst);
             }
-        });
+            Err(e) => {
+                checkpoint_error.get_or_insert(e);
+                stop.cancel();
+            }
+        }
+        Ok(())
+    };
+
+    // Process lines in parallel. `mapped` keeps the memory maps alive for
+    // the slices borrowed from them.
+    let mut mapped = Vec::new();
+    let source = input_source(cli, &inputs, &tuned, layout, &mut mapped)?;
+    if let Some(share) = cli.engine.max_cpu {
+        println!("Throttling validator threads to {}% CPU", share * 100.0);
+    }
+    let pipeline = Pipeline {
+        events: Some(Arc::new(status_events(&status))),
+        ..tuned.pipeline(&cli.engine)
+    };
+    let long_lines = pipeline.long_lines.clone();
+    // The line a time limit or a signal stopped the run before
+    let mut stopped =
+        pipeline.run_checkpointed(source, first_line..end_line, &stop, validate, &mut write, &mut save_checkpoint)?;
+
+    if let Some((offset, complete_lines)) = complete.filter(|_| stopped.is_none() && !stop.is_cancelled()) {
+        if stop.is_finishing() {
+            // Resumed, it follows from here
+            stopped = Some(first_line.max(complete_lines));
+        } else {
+            let file = &inputs.files[0];
+            status.lock().unwrap().progress.finish();
+            println!(
+                "Reached the end of '{}' at line {}; following it for new lines (Ctrl+C to stop).",
+                file.path.display(),
+                complete_lines
+            );
+            // Ends only when stopped or on a read error
+            let appended = Follow::open(&file.path, offset, complete_lines)?.until(stop.clone());
+            let range = first_line.max(complete_lines)..usize::MAX;
+            status.lock().unwrap().base = range.start;
+            stopped = pipeline.run_checkpointed(appended, range, &stop, validate, &mut write, &mut save_checkpoint)?;
+        }
+    }
+
+    // The hits left out past the limit are found again when it's resumed
+    if let Some(first) = over_limit.filter(|&first| first < usize::MAX) {
+        stopped = Some(stopped.map_or(first, |line| line.min(first)));
+    }
+
+    // From here on the run saves its own state; a signal handler past its
+    // grace period leaves it to that
+    *settled.lock().unwrap() = true;
+
+    if let (Some(cache), Some(path)) = (&cache, &cli.engine.cache_file) {
+        if let Err(e) = cache.save(path) {
+            eprintln!("Warning: can't save the cache to {}: {}", path.display(), e);
+        }
+    }
+
+    // Let any outstanding hook commands finish before reporting
+    let hook_failures = hooks.map(HookRunner::finish);
+
+    let mut sinks = sinks.into_inner();
+    if sinks.failed.is_some() || checkpoint_error.is_some() {
+        status.lock().unwrap().progress.finish();
+        let saved = saved_position.load(Ordering::SeqCst);
+        return Err(failed_run(&mut sinks, checkpoint_error, saved, &saver, output_path));
+    }
+
+    // Every line before the one it stopped at was validated, so the
+    // checkpoint goes exactly there
+    if let Some(line) = stopped {
+        let valid = stats.total().valid;
+        status.lock().unwrap().progress.finish();
+        if writes {
+            sinks.flush()?;
+            report_spill(cli, output_path)?;
+            saver.save(line)?;
+        }
+        let (status, reason) = if interrupted.load(Ordering::SeqCst) {
+            println!("Interrupted: stopped at line {} of {}.", line, total_lines);
+            ("interrupted", "signal")
+        } else if cli.stop_after.is_some_and(|limit| valid as u64 >= limit) {
+            println!("Hit limit reached (--stop-after): stopped at line {} of {}.", line, total_lines);
+            ("stopped", "hit limit")
+        } else {
+            println!("Time limit reached: stopped at line {} of {}.", line, total_lines);
+            ("stopped", "time limit")
+        };
+        audit.record("interrupted", serde_json::json!({ "position": line, "reason": reason }))?;
+        println!("Valid mnemonics found: {}", valid);
+        findings.print_languages();
+        if let Some(failures) = hook_failures {
+            println!("Hook commands failed: {}", failures);
+        }
+        match &mut run {
+            _ if !writes => println!("Count only: nothing was saved to resume from."),
+            Some(run) => {
+                run.update(status)?;
+                println!("Checkpoint saved. Resume with: mnemonic_validator runs resume {}", run.record.id);
+            }
+            None => println!("Checkpoint saved. Run the same command again to resume."),
+        }
+        finish_heartbeat(heartbeat.as_ref(), status, run_id.as_deref(), file_at(line), line, valid)?;
+        return Ok(if writes { Ended::Stopped } else { Ended::Finished });
+    }
 
     // Final statistics
-    let elapsed = start_time.elapsed();
-    let valid = valid_count.load(Ordering::Relaxed);
-    let processed_total = processed.load(Ordering::Relaxed);
+    let per_file = stats.per_file();
+    let total = stats.total();
+    let elapsed = total.elapsed;
+    let valid = total.valid;
+    let processed_total = total.processed;
 
-    println!("\nValidation complete!");
+    status.lock().unwrap().progress.finish();
+    println!("Validation complete!");
+    if several {
+        print_file_summaries(&inputs, &per_file, &total);
+    }
     println!("Valid mnemonics found: {}", valid);
+    findings.print_languages();
+    if filter.is_some() {
+        println!("Valid, but no address matched the filter: {}", unmatched.into_inner());
+    }
+    if let Some(cache) = &cache {
+        let (hits, misses) = cache.hits();
+        println!("Lines looked up in the cache: {} of {}", hits, hits + misses);
+    }
+    findings.report(cli)?;
+    if let Some(long_lines) = long_lines.as_deref() {
+        report_long_lines(long_lines);
+    }
+    if cli.incremental {
+        let skipped: usize = sinks.writer.stats().iter().map(|target| target.skipped).sum();
+        println!("Already in the output (skipped): {}", skipped);
+    }
+    if let Some(failures) = hook_failures {
+        println!("Hook commands failed: {}", failures);
+    }
     println!("Time taken: {}", format_duration(elapsed));
     println!("Processing speed: {} lines/s", if elapsed.as_secs() > 0 { processed_total / elapsed.as_secs() as usize } else { processed_total });
+    println!("Hit rate: {:.4}%", total.hit_rate() * 100.0);
     println!("Made by z1ph1us.");
-    // Final checkpoint update
-    fs::write(checkpoint_path, total_lines.to_string())?;
-    
-    //remove checkpoint file.
-    fs::remove_file(checkpoint_path)?;
 
-    // Make sure we've written everything
-    writer.lock().unwrap().flush()?;
+    let counts = serde_json::json!({
+        "total_lines": total_lines,
+        "processed": processed_total,
+        "valid": valid,
+        "elapsed_secs": elapsed.as_secs_f64(),
+    });
+    if !writes {
+        println!("Count only: nothing was written. Would have written:");
+        for target in sinks.writer.stats() {
+            println!("  {}: {} hits, {} bytes", target.path.display(), target.hits, target.bytes);
+        }
+        audit.record("finished", serde_json::json!({ "results": counts }))?;
+        finish_heartbeat(heartbeat.as_ref(), "done", run_id.as_deref(), file_at(end_line), end_line, valid)?;
+        return Ok(Ended::Finished);
+    }
 
-    Ok(())
+    // Make sure we've written everything before letting go of the checkpoint
+    sinks.flush()?;
+    report_spill(cli, output_path)?;
+
+    remove_checkpoint(&saver, total_lines)?;
+
+    // Only what this run appended: the files may hold earlier runs' hits
+    let outputs = sinks.appended()?;
+    audit.record("finished", serde_json::json!({ "results": counts, "outputs": outputs }))?;
+
+    if !cli.no_manifest {
+        let run_manifest = manifest::Manifest {
+            tool: manifest::Manifest::tool_name(),
+            started_at: started_at.to_rfc3339(),
+            finished_at: chrono::Utc::now().to_rfc3339(),
+            inputs: manifest_inputs(&inputs, &per_file),
+            outputs,
+            parameters: manifest_parameters(cli, &tuned, existing, long_lines.as_deref()),
+            results: manifest::Results {
+                total_lines,
+                resumed_from_line: checkpoint,
+                processed: processed_total,
+                valid,
+                elapsed_secs: elapsed.as_secs_f64(),
+                lines_per_sec: total.lines_per_sec(),
+                hit_rate: total.hit_rate(),
+            },
+            timeline: manifest_timeline(&stats),
+        };
+        let path = manifest::write(&run_manifest, output_path, cli.sign_manifest.as_deref())?;
+        println!("Manifest written to {}", path.display());
+        if let Some(run) = &mut run {
+            run.record.manifest = Some(path.display().to_string());
+        }
+    }
+
+    if let Some(run) = &mut run {
+        run.record.valid = Some(valid);
+        run.update("done")?;
+    }
+    finish_heartbeat(heartbeat.as_ref(), "done", run_id.as_deref(), file_at(end_line), end_line, valid)?;
+    Ok(Ended::Finished)
 }
 
-fn main() {
-    let cli = Cli::parse();
+/// Everything besides the line that decides what it comes to, salting the
+/// --cache so that lines aren't looked up under other settings.
+fn cache_settings(cli: &Cli) -> std::io::Result<String> {
+    // An updated UTXO set funds other addresses
+    let utxo_set = match &cli.utxo_set {
+        Some(path) => {
+            let meta = fs::metadata(path)?;
+            Some((path, meta.len(), meta.modified().ok()))
+        }
+        None => None,
+    };
+    Ok(format!(
+        "{} {:?} {:?} {:?} {:?} {:?} {} {} {} {:?}",
+        env!("CARGO_PKG_VERSION"),
+        cli.language,
+        layout(cli),
+        cli.address_prefix,
+        cli.address_regex.as_ref().map(Regex::as_str),
+        utxo_set,
+        cli.blockchain.name(),
+        cli.network.name(),
+        cli.address_count,
+        cli.engine.long_lines,
+    ))
+}
 
-    let input_path = Path::new(&cli.input);
-    let output_path = Path::new(&cli.output);
+/// When --max-duration or --stop-at, whichever comes first, stops the run,
+/// and what time that is.
+fn time_limit(cli: &Cli) -> Option<(Instant, chrono::DateTime<chrono::Local>)> {
+    let (now, local) = (Instant::now(), chrono::Local::now());
+    let after = cli.max_duration.map(|duration| (now + duration, local + duration));
+    let at = cli.stop_at.map(|time| {
+        let at = deadline::next_at(time, local);
+        (now + (at - local).to_std().unwrap_or_default(), at)
+    });
+    after.into_iter().chain(at).min_by_key(|(deadline, _)| *deadline)
+}
 
-    // Construct the checkpoint path in the user's home directory as a hidden file.
-    let home_dir = dirs::home_dir().unwrap_or_else(|| PathBuf::from(".")); // Use current dir if home dir is not found.
-    let checkpoint_path = home_dir.join(".mnemonic_validator_checkpoint.txt");
-    
+/// Marks the --heartbeat file done (or stopped, or interrupted), so a
+/// watchdog doesn't take the ended run for a hung one.
+fn finish_heartbeat(
+    heartbeat: Option<&Heartbeat>,
+    status: &str,
+    run: Option<&str>,
+    file: &Path,
+    line: usize,
+    valid: usize,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if let Some(heartbeat) = heartbeat {
+        heartbeat
+            .write(&heartbeat::Status::new(run, status, file, line, valid))
+            .map_err(|e| format!("can't write the heartbeat file {}: {}", heartbeat.path().display(), e))?;
+    }
+    Ok(())
+}
 
-    if !input_path.exists() {
-        eprintln!("Error: Input file not found at '{}'", input_path.display());
-        std::process::exit(1);
+/// Parses a command line, including the options of a `--job` file it
+/// names. Exits on a usage error, like `Cli::parse`.
+fn parse_args(args: &[OsString]) -> Result<Cli, Box<dyn std::error::Error>> {
+    let cli = Cli::try_parse_from(args).unwrap_or_else(|e| e.exit());
+    match &cli.job {
+        Some(job) => job::load(job, args.iter().cloned()),
+        None => Ok(cli),
     }
+}
 
-    if let Err(e) = process_file(input_path, output_path, &checkpoint_path) {
-        eprintln!("\nError: {}", e);
+/// Runs the validation `cli` asks for; `args` (without the program name)
+/// are recorded with the run. `resumed` is the run `runs resume` picked.
+fn run(cli: &Cli, args: &[OsString], resumed: Option<Run>) {
+    if let Some(missing) = cli.input.iter().find(|path| !path.exists()) {
+        eprintln!("Error: Input file not found at '{}'", missing.display());
         std::process::exit(1);
     }
+    let filter = inputs::Filter {
+        include: cli.include.clone(),
+        exclude: cli.exclude.clone(),
+        carve: cli.carve,
+    };
+    let input_paths = match inputs::expand(&cli.input, &filter).and_then(|mut expanded| {
+        report_skipped(&expanded.skipped);
+        inputs::sort(&mut expanded.files, cli.order)?;
+        Ok(expanded.files)
+    }) {
+        Ok(paths) if paths.is_empty() => {
+            eprintln!("Error: No input files found");
+            std::process::exit(1);
+        }
+        Ok(paths) => paths,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    let hooks = match &cli.exec_per_valid {
+        Some(template) => match HookRunner::new(template, cli.exec_concurrency) {
+            Ok(runner) => Some(runner),
+            Err(e) => {
+                eprintln!("Error: {}", e);
+                std::process::exit(1);
+            }
+        },
+        None => None,
+    };
+
+    // Taken before the run is recorded, so a run turned away leaves the
+    // record of the one in its way alone
+    let mut lock = RunLock::default();
+    let holder = lock::holder(args);
+    if !cli.count_only {
+        if let Err(e) = lock.add(&cli.output, &holder) {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
+    }
+
+    // Runs with their own --checkpoint, and count-only runs that save
+    // nothing to come back to, aren't recorded
+    let run = match resumed {
+        Some(run) => Some(run),
+        None if cli.checkpoint.is_some() || cli.count_only => None,
+        None => match Run::start(args, &cli.output, cli.restart) {
+            Ok(run) => Some(run),
+            Err(e) => {
+                eprintln!("Error: can't record the run: {}", e);
+                std::process::exit(1);
+            }
+        },
+    };
+    let checkpoint_path = match (&cli.checkpoint, &run) {
+        (Some(path), _) => path.clone(),
+        (None, Some(run)) => run.checkpoint(),
+        // Never read or written by a count-only run
+        (None, None) => PathBuf::new(),
+    };
+    if !cli.count_only {
+        if let Err(e) = lock.add(&checkpoint_path, &holder) {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
+    }
+    if let Some(run) = &run {
+        println!("Run {}", run.record.id);
+    }
+    let audit = AuditLog::new(
+        cli.audit_log.clone().unwrap_or_else(audit::default_path),
+        run.as_ref().map(|run| run.record.id.clone()),
+    );
+
+    match process_file(cli, input_paths, &checkpoint_path, hooks, run.clone(), &audit, args) {
+        Ok(Ended::Finished) => {}
+        Ok(Ended::Stopped) => std::process::exit(EXIT_RESUMABLE),
+        Err(e) => {
+            let _ = audit.record("failed", serde_json::json!({ "error": e.to_string() }));
+            if let Some(mut run) = run {
+                run.record.error = Some(e.to_string());
+                let _ = run.update("failed");
+            }
+            eprintln!("\nError: {}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+fn main() {
+    let args: Vec<OsString> = std::env::args_os().collect();
+    let cli = match parse_args(&args) {
+        Ok(cli) => cli,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    if let Some(command) = &cli.command {
+        let result = match command {
+            Command::Bench(args) => bench::run(args),
+            Command::Collect(args) => collect::run(args),
+            Command::GenCorpus(args) => corpus::run(args),
+            Command::RetryFailed(args) => failed::run(args),
+            Command::Selftest(args) => selftest::run(args),
+            Command::Daemon(args) => daemon::run(args),
+            Command::Passphrase(args) => passphrases::run(args),
+            Command::Recover(args) => recover::run(args),
+            Command::Wizard(args) => wizard::run(args),
+            Command::Report(args) => report::run(args),
+            Command::Reveal(args) => reveal::run(args),
+            Command::Runs(args) => runs::run(args),
+            Command::Export(args) => export::run(args),
+            Command::Import(args) => import::run(args),
+            Command::UtxoIndex(args) => utxo_index::run(args),
+            Command::Xor(args) => xor::run(args),
+            Command::Slip39(args) => shamir::run(args),
+            Command::Worker(args) => worker::run(args),
+            Command::Coordinator(args) => coordinator::run(args),
+        };
+        if let Err(e) = result {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    let served = match (cli.listen_tcp, &cli.listen_unix) {
+        (Some(addr), _) => Some(server::listen_tcp(addr, server::Settings::new(&cli.engine, cli.language, layout(&cli)))),
+        (None, Some(path)) => Some(server::listen_unix(path, server::Settings::new(&cli.engine, cli.language, layout(&cli)))),
+        (None, None) => None,
+    };
+    match served {
+        Some(Err(e)) => {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
+        Some(Ok(())) => {}
+        None => run(&cli, &args[1..], None),
+    }
 }
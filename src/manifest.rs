@@ -0,0 +1,127 @@
+//! Sidecar manifest written next to the output file after a completed run.
+//!
+//! The manifest records hashes of the input files and of what the run
+//! appended to its output files, the run parameters, timestamps, per-input
+//! timing and progress over time, so a result file copied to another machine can
+//! be checked for completeness and tampering, and `report` can chart the run. It can optionally be signed
+//! with a minisign secret key, producing a detached `.minisig` next to it.
+
+use serde::{Deserialize, Serialize};
+use std::{
+    fs,
+    io::Cursor,
+    path::{Path, PathBuf},
+};
+
+/// Environment variable holding the password of an encrypted minisign key.
+pub const PASSWORD_ENV: &str = "MINISIGN_PASSWORD";
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct FileEntry {
+    pub path: String,
+    /// Of an output, where the run started appending to it: the hash and
+    /// size are of the bytes from there on. Missing for inputs, and from
+    /// manifests of older versions, which hashed the whole file.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub offset: Option<u64>,
+    pub sha256: String,
+    pub bytes: u64,
+}
+
+/// An input file with what this run found in it.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct InputEntry {
+    #[serde(flatten)]
+    pub file: FileEntry,
+    pub lines: usize,
+    pub processed: usize,
+    pub valid: usize,
+    pub elapsed_secs: f64,
+    pub lines_per_sec: f64,
+    pub hit_rate: f64,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Results {
+    pub total_lines: usize,
+    pub resumed_from_line: usize,
+    pub processed: usize,
+    pub valid: usize,
+    pub elapsed_secs: f64,
+    pub lines_per_sec: f64,
+    pub hit_rate: f64,
+}
+
+/// Progress some time into the run.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct TimelineEntry {
+    pub elapsed_secs: f64,
+    pub processed: usize,
+    pub valid: usize,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Manifest {
+    pub tool: String,
+    pub started_at: String,
+    pub finished_at: String,
+    pub inputs: Vec<InputEntry>,
+    pub outputs: Vec<FileEntry>,
+    pub parameters: serde_json::Value,
+    pub results: Results,
+    /// Missing from manifests of older versions.
+    #[serde(default)]
+    pub timeline: Vec<TimelineEntry>,
+}
+
+impl Manifest {
+    pub fn tool_name() -> String {
+        format!("{} {}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION"))
+    }
+}
+
+/// `output/valid.txt` -> `output/valid.txt.manifest.json`
+pub fn manifest_path(output_path: &Path) -> PathBuf {
+    let mut name = output_path.as_os_str().to_os_string();
+    name.push(".manifest.json");
+    PathBuf::from(name)
+}
+
+/// Writes the manifest next to `output_path` and, when `sign_key` is given,
+/// a minisign signature of it. Returns the manifest path.
+pub fn write(
+    manifest: &Manifest,
+    output_path: &Path,
+    sign_key: Option<&Path>,
+) -> Result<PathBuf, Box<dyn std::error::Error>> {
+    let path = manifest_path(output_path);
+    let json = serde_json::to_string_pretty(manifest)?;
+    fs::write(&path, &json)?;
+
+    if let Some(key_path) = sign_key {
+        let key_box = fs::read_to_string(key_path)?;
+        let secret_key = match minisign::SecretKeyBox::from_string(&key_box)?
+            .into_unencrypted_secret_key()
+        {
+            Ok(key) => key,
+            // Without the environment variable minisign prompts for the
+            // password of encrypted keys on the terminal.
+            Err(_) => minisign::SecretKeyBox::from_string(&key_box)?
+                .into_secret_key(std::env::var(PASSWORD_ENV).ok())?,
+        };
+        let trusted_comment = format!("file:{}", path.display());
+        let signature = minisign::sign(
+            None,
+            &secret_key,
+            Cursor::new(json.as_bytes()),
+            Some(&trusted_comment),
+            None,
+        )?;
+
+        let mut sig_path = path.as_os_str().to_os_string();
+        sig_path.push(".minisig");
+        fs::write(PathBuf::from(sig_path), signature.into_string())?;
+    }
+
+    Ok(path)
+}
@@ -0,0 +1,39 @@
+//! Masked ("fingerprint only") output for valid mnemonics.
+//!
+//! A masked hit keeps just enough information to triage and later locate a
+//! result - the first and last word, a SHA-256 prefix of the normalized
+//! phrase, and the source line - without revealing the phrase itself.
+
+use crate::{
+    digest::{phrase_sha256, to_hex},
+    languages,
+};
+
+/// Number of hex characters of the SHA-256 digest kept in a fingerprint.
+pub const FINGERPRINT_LEN: usize = 16;
+
+/// Hex prefix of the phrase's SHA-256, independent of the original spacing
+/// (a Chinese phrase's words can have none between them).
+pub fn fingerprint(phrase: &str) -> String {
+    let mut hex = to_hex(&phrase_sha256(languages::words(phrase).into_iter()));
+    hex.truncate(FINGERPRINT_LEN);
+    hex
+}
+
+/// The phrase without its middle words, e.g.
+/// `boy...banana 24w sha256:1f3c2a9b7d4e0c55`.
+pub fn masked(phrase: &str) -> String {
+    let words = languages::words(phrase);
+    let first = words.first().copied().unwrap_or_default();
+    let last = words.last().copied().unwrap_or_default();
+    let count = words.len().max(1);
+    format!("{}...{} {}w sha256:{}", first, last, count, fingerprint(phrase))
+}
+
+/// Formats a masked output line, e.g.
+/// `boy...banana 24w sha256:1f3c2a9b7d4e0c55 line:17`. With several inputs
+/// the file is named too: `... file:dump2.txt line:17`.
+pub fn masked_line(phrase: &str, file: Option<&str>, line: usize) -> String {
+    let file = file.map(|f| format!(" file:{}", f)).unwrap_or_default();
+    format!("{}{} line:{}", masked(phrase), file, line)
+}
@@ -0,0 +1,205 @@
+//! Sizing a run for `--max-memory`.
+//!
+//! Lines are only queued in bounded channels and hits are written out as
+//! they arrive, so the pipeline stays under a cap by shrinking its queues
+//! and the blocks the input is read in ([`Limits`]). Some options keep
+//! something per line or per hit, which grows with the input: the cache,
+//! the `--incremental` index, `--duplicates` and `--group-by`. The room
+//! the pipeline leaves is split between those in use ([`Budget`]): the
+//! cache holds fewer lines, the index spills to disk past its share, and
+//! the duplicates and groups stop taking in new phrases and wallets.
+//!
+//! Under a cap the input is read in blocks (with plain reads or io_uring)
+//! rather than memory-mapped, so that its pages don't pile up in the
+//! process's resident memory.
+
+use crate::pipeline::HIT_QUEUE;
+
+/// Allowance for everything that doesn't scale with the queues: the
+/// wordlists, output and hook buffers, thread stacks in use.
+pub const BASE_BYTES: u64 = 16 << 20;
+/// Per selected line in a batch: its index and byte range.
+const LINE_BYTES: u64 = 24;
+/// Per queued hit: the copied phrase and the parsed mnemonic.
+const HIT_BYTES: u64 = 512;
+const MIN_HIT_QUEUE: usize = 16;
+/// Reads never shrink below this.
+pub const MIN_BLOCK_SIZE: usize = 64 << 10;
+/// Per cached line, two generations of hash maps with room to grow.
+const CACHE_LINE_BYTES: u64 = 64;
+/// Per key of the `--incremental` index in memory.
+const INDEX_KEY_BYTES: u64 = 48;
+/// Per phrase of `--duplicates`: its masked form and where it was found.
+const DUPLICATE_BYTES: u64 = 256;
+/// Per hit of `--group-by`: its fingerprint and location.
+const GROUP_HIT_BYTES: u64 = 128;
+/// Each structure that grows with the input gets at least this much.
+pub const MIN_SHARE: u64 = 1 << 20;
+
+/// Parses a size such as `2G`, `512M`, `1.5GiB` or a plain byte count.
+/// Units are binary (1K = 1024 bytes).
+pub fn parse_size(s: &str) -> Result<u64, String> {
+    let t = s.trim();
+    let digits = t
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(t.len());
+    let (number, unit) = t.split_at(digits);
+    let number: f64 = number
+        .parse()
+        .map_err(|_| format!("invalid size '{}'", s))?;
+
+    let unit = unit.trim().to_ascii_uppercase();
+    let shift = match unit.trim_end_matches("IB").trim_end_matches('B') {
+        "" => 0,
+        "K" => 10,
+        "M" => 20,
+        "G" => 30,
+        "T" => 40,
+        _ => return Err(format!("unknown size unit in '{}', expected K, M, G or T", s)),
+    };
+    Ok((number * (1u64 << shift) as f64) as u64)
+}
+
+/// Queue and chunk sizes that fit a memory cap.
+#[derive(Debug, Clone, Copy)]
+pub struct Limits {
+    pub queue_depth: usize,
+    pub hit_queue: usize,
+    /// Bytes per read.
+    pub block_size: usize,
+    /// io_uring reads in flight.
+    pub ring_depth: usize,
+}
+
+impl Limits {
+    /// The sizes used without a cap.
+    pub fn new(validators: usize) -> Self {
+        Limits {
+            queue_depth: 2 * validators,
+            hit_queue: HIT_QUEUE,
+            // uring::BLOCK_SIZE and uring::QUEUE_DEPTH
+            block_size: 4 << 20,
+            ring_depth: 8,
+        }
+    }
+
+    /// Starts from the default sizes for `validators` threads and shrinks
+    /// them until the estimate fits `cap`. `avg_line` is the input's mean
+    /// line length, which says how many lines a block holds.
+    pub fn fit(cap: u64, validators: usize, avg_line: usize, io_uring: bool) -> Result<Self, String> {
+        Limits::new(validators).shrink_to(cap, validators, avg_line, io_uring)
+    }
+
+    /// Like [`fit`](Self::fit), but starting from these sizes.
+    pub fn shrink_to(self, cap: u64, validators: usize, avg_line: usize, io_uring: bool) -> Result<Self, String> {
+        let mut limits = self;
+        loop {
+            let usage = limits.estimate(validators, avg_line, io_uring);
+            if usage <= cap {
+                return Ok(limits);
+            }
+            if !limits.shrink(validators, io_uring) {
+                return Err(format!(
+                    "--max-memory {} is too small for {} validator threads; needs at least {}",
+                    format_size(cap),
+                    validators,
+                    format_size(usage)
+                ));
+            }
+        }
+    }
+
+    /// These sizes shrunk as far as they go.
+    pub fn smallest(mut self, validators: usize, io_uring: bool) -> Self {
+        while self.shrink(validators, io_uring) {}
+        self
+    }
+
+    /// Estimated peak memory use in bytes.
+    pub fn estimate(&self, validators: usize, avg_line: usize, io_uring: bool) -> u64 {
+        // Queued in both channels, plus one being split and one per validator
+        let chunks = (2 * self.queue_depth + validators + 1) as u64;
+        let block_lines = (self.block_size / avg_line.max(1)).max(1) as u64;
+        // Plus the block being read: with io_uring, the reads in flight and
+        // reaped ones waiting to be emitted in order
+        let reading = if io_uring { 2 * self.ring_depth as u64 } else { 1 };
+        BASE_BYTES
+            + chunks * block_lines * LINE_BYTES
+            + (chunks + reading) * self.block_size as u64
+            + self.hit_queue as u64 * HIT_BYTES
+    }
+
+    /// Halves the first size, in order of how much they usually take, that
+    /// is still above its minimum. Returns false when nothing is left to
+    /// shrink.
+    fn shrink(&mut self, validators: usize, io_uring: bool) -> bool {
+        if self.block_size > MIN_BLOCK_SIZE {
+            self.block_size /= 2;
+        } else if io_uring && self.ring_depth > 2 {
+            self.ring_depth /= 2;
+        } else if self.queue_depth > validators.max(1) {
+            self.queue_depth = (self.queue_depth / 2).max(validators.max(1));
+        } else if self.hit_queue > MIN_HIT_QUEUE {
+            self.hit_queue /= 2;
+        } else {
+            return false;
+        }
+        true
+    }
+}
+
+/// What grows with the input, among a run's options.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Growing {
+    pub cache: bool,
+    pub index: bool,
+    pub duplicates: bool,
+    pub groups: bool,
+}
+
+impl Growing {
+    /// How many are in use.
+    pub fn count(self) -> usize {
+        [self.cache, self.index, self.duplicates, self.groups].iter().filter(|&&used| used).count()
+    }
+}
+
+/// How much of each growing structure fits in its share of the room left
+/// under a cap; `None` for those not in use.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Budget {
+    pub cache_lines: Option<usize>,
+    pub index_keys: Option<usize>,
+    pub duplicates: Option<usize>,
+    pub group_hits: Option<usize>,
+}
+
+impl Budget {
+    /// Splits `spare` bytes evenly between the structures of `growing`.
+    pub fn split(spare: u64, growing: Growing) -> Self {
+        let share = spare / growing.count().max(1) as u64;
+        let fits = |used: bool, bytes: u64| used.then_some((share / bytes) as usize);
+        Budget {
+            cache_lines: fits(growing.cache, CACHE_LINE_BYTES),
+            index_keys: fits(growing.index, INDEX_KEY_BYTES),
+            duplicates: fits(growing.duplicates, DUPLICATE_BYTES),
+            group_hits: fits(growing.groups, GROUP_HIT_BYTES),
+        }
+    }
+}
+
+/// `1.5 GiB`, `512.0 MiB`, ...
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KiB", "MiB", "GiB", "TiB"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} B", bytes)
+    } else {
+        format!("{:.1} {}", value, UNITS[unit])
+    }
+}
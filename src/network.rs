@@ -0,0 +1,322 @@
+//! The network derived keys and addresses are for (`--network`).
+//!
+//! Networks are coin definitions in TOML: Bitcoin's four are built in
+//! (`coins.toml`), and a registry file adds more, such as Litecoin or
+//! Dogecoin, without recompiling. It's the file `MV_COINS` names, or else
+//! `coins.toml` in the `mnemonic_validator` configuration directory if
+//! there is one:
+//!
+//! ```toml
+//! [litecoin]
+//! aliases = ["ltc"]
+//! coin-type = 2
+//! curve = "secp256k1"
+//! p2pkh = 0x30
+//! p2sh = 0x32
+//! hrp = "ltc"
+//! xpub = "019da462"
+//! ```
+//!
+//! A definition gives what the addresses this tool knows need: version
+//! bytes for base58 addresses, the prefix of native SegWit ones and
+//! version bytes for extended keys. With `hash = "keccak256"`, addresses
+//! hold the Keccak-256 of the key instead of its HASH160, in hex with
+//! Ethereum's mixed-case checksum, or in base58 under the `p2pkh` version
+//! as Tron's do (`0x41`). Coins with keys on other curves (Cardano's and
+//! Solana's Ed25519 keys) can't be described, and are refused; those two
+//! have [`derive`](crate::derive) instead.
+
+use std::{fmt, fs, path::PathBuf, sync::OnceLock};
+use toml_edit::{DocumentMut, Item, Value};
+
+/// The registry's file, when it isn't the default one.
+pub const COINS_ENV: &str = "MV_COINS";
+
+const BUILT_IN: &str = include_str!("coins.toml");
+
+/// A network as defined in a registry.
+#[derive(Debug)]
+struct Coin {
+    name: String,
+    aliases: Vec<String>,
+    coin_type: u32,
+    /// Addresses hold the Keccak-256 of the key rather than its HASH160.
+    keccak: bool,
+    p2pkh: Option<u8>,
+    p2sh: Option<u8>,
+    hrp: Option<String>,
+    xpub: [u8; 4],
+    zpub: Option<[u8; 4]>,
+    zprv: Option<[u8; 4]>,
+    magic: Vec<[u8; 4]>,
+}
+
+struct Registry {
+    coins: Vec<Coin>,
+    /// Why the registry file couldn't be read; the built-in networks are
+    /// there regardless.
+    error: Option<String>,
+}
+
+/// `~/.config/mnemonic_validator/coins.toml` on Linux.
+pub fn default_registry_path() -> Option<PathBuf> {
+    Some(dirs::config_dir()?.join("mnemonic_validator").join("coins.toml"))
+}
+
+fn registry() -> &'static Registry {
+    static REGISTRY: OnceLock<Registry> = OnceLock::new();
+    REGISTRY.get_or_init(|| {
+        let mut coins = parse(BUILT_IN, &[]).expect("the built-in networks are valid");
+        let path = match std::env::var_os(COINS_ENV) {
+            Some(path) => Some(PathBuf::from(path)),
+            None => default_registry_path().filter(|path| path.is_file()),
+        };
+        let error = path.and_then(|path| {
+            let added = fs::read_to_string(&path)
+                .map_err(|e| e.to_string())
+                .and_then(|text| parse(&text, &coins));
+            match added {
+                Ok(added) => {
+                    coins.extend(added);
+                    None
+                }
+                Err(e) => Some(format!("can't read the coin registry {}: {}", path.display(), e)),
+            }
+        });
+        Registry { coins, error }
+    })
+}
+
+fn hex_bytes<const N: usize>(text: &str) -> Option<[u8; N]> {
+    if text.len() != 2 * N {
+        return None;
+    }
+    let mut bytes = [0; N];
+    for (i, byte) in bytes.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(text.get(2 * i..2 * i + 2)?, 16).ok()?;
+    }
+    Some(bytes)
+}
+
+/// Reads a registry's definitions, refusing names `known` already has.
+fn parse(text: &str, known: &[Coin]) -> Result<Vec<Coin>, String> {
+    let doc: DocumentMut = text.parse().map_err(|e| format!("it isn't valid TOML: {}", e))?;
+    let mut coins: Vec<Coin> = Vec::new();
+    for (name, item) in doc.as_table() {
+        let fail = |message: String| format!("[{}]: {}", name, message);
+        let Item::Table(table) = item else {
+            return Err(fail("must be a table".to_string()));
+        };
+        for key in table.iter().map(|(key, _)| key) {
+            if !matches!(
+                key,
+                "aliases"
+                    | "coin-type"
+                    | "curve"
+                    | "hash"
+                    | "p2pkh"
+                    | "p2sh"
+                    | "hrp"
+                    | "xpub"
+                    | "zpub"
+                    | "zprv"
+                    | "magic"
+            ) {
+                return Err(fail(format!("unknown key '{}'", key)));
+            }
+        }
+        let value = |key: &str| table.get(key).and_then(Item::as_value);
+        let integer = |key: &str, max: u32| -> Result<Option<u32>, String> {
+            match value(key) {
+                None => Ok(None),
+                Some(Value::Integer(n)) if (0..=max as i64).contains(n.value()) => Ok(Some(*n.value() as u32)),
+                Some(_) => Err(fail(format!("'{}' must be a number from 0 to {}", key, max))),
+            }
+        };
+        let string = |key: &str| -> Result<Option<String>, String> {
+            match value(key) {
+                None => Ok(None),
+                Some(Value::String(s)) => Ok(Some(s.value().clone())),
+                Some(_) => Err(fail(format!("'{}' must be a string", key))),
+            }
+        };
+        let strings = |key: &str| -> Result<Vec<String>, String> {
+            match value(key) {
+                None => Ok(Vec::new()),
+                Some(Value::Array(values)) => values
+                    .iter()
+                    .map(|value| value.as_str().map(str::to_string))
+                    .collect::<Option<_>>()
+                    .ok_or_else(|| fail(format!("'{}' must be a list of strings", key))),
+                Some(_) => Err(fail(format!("'{}' must be a list of strings", key))),
+            }
+        };
+        let version = |key: &str| -> Result<Option<[u8; 4]>, String> {
+            string(key)?
+                .map(|hex| hex_bytes(&hex).ok_or_else(|| fail(format!("'{}' must be 4 bytes in hex", key))))
+                .transpose()
+        };
+
+        match string("curve")?.as_deref() {
+            Some("secp256k1") => {}
+            Some(curve) => return Err(fail(format!("curve '{}' isn't supported, only secp256k1", curve))),
+            None => return Err(fail("'curve' is missing".to_string())),
+        }
+        let keccak = match string("hash")?.as_deref() {
+            None | Some("hash160") => false,
+            Some("keccak256") => true,
+            Some(hash) => return Err(fail(format!("hash '{}' isn't supported, only hash160 or keccak256", hash))),
+        };
+        let coin = Coin {
+            name: name.to_ascii_lowercase(),
+            aliases: strings("aliases")?.iter().map(|alias| alias.to_ascii_lowercase()).collect(),
+            coin_type: integer("coin-type", (1 << 31) - 1)?.ok_or_else(|| fail("'coin-type' is missing".to_string()))?,
+            keccak,
+            p2pkh: integer("p2pkh", 0xff)?.map(|version| version as u8),
+            p2sh: integer("p2sh", 0xff)?.map(|version| version as u8),
+            hrp: string("hrp")?.map(|hrp| hrp.to_ascii_lowercase()),
+            xpub: version("xpub")?.ok_or_else(|| fail("'xpub' is missing".to_string()))?,
+            zpub: version("zpub")?,
+            zprv: version("zprv")?,
+            magic: strings("magic")?
+                .iter()
+                .map(|hex| hex_bytes(hex).ok_or_else(|| fail("'magic' must list 4 bytes in hex".to_string())))
+                .collect::<Result<_, _>>()?,
+        };
+        if coin.p2pkh.is_none() && !keccak {
+            return Err(fail("'p2pkh' is missing".to_string()));
+        }
+        // Keccak-hashing coins have a single kind of address, and no UTXOs
+        if let Some(key) = ["p2sh", "hrp", "zpub", "zprv", "magic"].into_iter().find(|key| keccak && table.contains_key(key)) {
+            return Err(fail(format!("'{}' doesn't go with hash = \"keccak256\"", key)));
+        }
+        if coin.zpub.is_some() != coin.zprv.is_some() {
+            return Err(fail("'zpub' and 'zprv' go together".to_string()));
+        }
+        let names = || std::iter::once(&coin.name).chain(&coin.aliases);
+        if let Some(taken) = names().find(|name| known.iter().chain(&coins).any(|coin| coin.is_named(name))) {
+            return Err(fail(format!("'{}' is already the name of a network", taken)));
+        }
+        coins.push(coin);
+    }
+    Ok(coins)
+}
+
+impl Coin {
+    fn is_named(&self, name: &str) -> bool {
+        self.name == name || self.aliases.iter().any(|alias| alias == name)
+    }
+}
+
+#[derive(Clone, Copy)]
+pub struct Network(&'static Coin);
+
+impl Network {
+    /// The networks of the registry, the built-in ones first.
+    pub fn all() -> impl Iterator<Item = Network> {
+        registry().coins.iter().map(Network)
+    }
+
+    /// The network known by `name` or one of its aliases.
+    pub fn find(name: &str) -> Option<Network> {
+        let name = name.trim().to_ascii_lowercase();
+        Self::all().find(|network| network.0.is_named(&name))
+    }
+
+    /// Its place in [`Network::all`].
+    pub fn index(self) -> usize {
+        Self::all().position(|network| network == self).unwrap()
+    }
+
+    pub fn name(self) -> &'static str {
+        &self.0.name
+    }
+
+    /// The BIP44 coin type of its derivation paths.
+    pub fn coin_type(self) -> u32 {
+        self.0.coin_type
+    }
+
+    /// The human-readable part of its SegWit addresses (BIP173), if it has
+    /// them.
+    pub fn hrp(self) -> Option<&'static str> {
+        self.0.hrp.as_deref()
+    }
+
+    /// Whether its addresses hold the Keccak-256 of the key, as Ethereum's
+    /// and Tron's do, rather than its HASH160.
+    pub fn keccak(self) -> bool {
+        self.0.keccak
+    }
+
+    /// Version byte of its P2PKH addresses; `None` for addresses in hex, as
+    /// Ethereum's are.
+    pub fn p2pkh_version(self) -> Option<u8> {
+        self.0.p2pkh
+    }
+
+    /// Version byte of its P2SH addresses, if it has nested SegWit ones.
+    pub fn p2sh_version(self) -> Option<u8> {
+        self.0.p2sh
+    }
+
+    /// Version bytes of an `xpub` (`tpub` on test networks), as Bitcoin
+    /// Core writes keys of every script type in descriptors.
+    pub fn xpub(self) -> [u8; 4] {
+        self.0.xpub
+    }
+
+    /// Version bytes of a `zpub` (`vpub`), the public key of a native
+    /// SegWit (BIP84) account in Electrum.
+    pub fn zpub(self) -> Option<[u8; 4]> {
+        self.0.zpub
+    }
+
+    /// Version bytes of a `zprv` (`vprv`), the matching private key.
+    pub fn zprv(self) -> Option<[u8; 4]> {
+        self.0.zprv
+    }
+
+    /// Whether a Bitcoin Core snapshot starting with `magic` is of it.
+    pub fn has_magic(self, magic: [u8; 4]) -> bool {
+        self.0.magic.contains(&magic)
+    }
+}
+
+impl Default for Network {
+    fn default() -> Self {
+        Self::find("mainnet").unwrap()
+    }
+}
+
+impl PartialEq for Network {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self.0, other.0)
+    }
+}
+
+impl Eq for Network {}
+
+impl fmt::Debug for Network {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl fmt::Display for Network {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// A network of the registry, by name or alias (`mainnet` or `bitcoin`,
+/// `testnet`, `signet`, `regtest`, ...), for clap.
+pub fn parse_network(s: &str) -> Result<Network, String> {
+    if let Some(error) = &registry().error {
+        return Err(error.clone());
+    }
+    Network::find(s).ok_or_else(|| {
+        let names: Vec<_> = Network::all().map(Network::name).collect();
+        format!("'{}' isn't a network; expected {}", s, names.join(", "))
+    })
+}
@@ -0,0 +1,566 @@
+//! Output files for valid mnemonics.
+//!
+//! By default every hit goes to the single `--output` file. With
+//! `--split-output` hits are routed into one file per category next to it,
+//! e.g. `valid_mnemonics_12w.txt` and `valid_mnemonics_24w.txt`. With
+//! `--shards N` each of those is further spread over N files by a hash of
+//! the phrase (`valid_mnemonics_shard07.txt`), so the same mnemonic always
+//! lands in the same shard across runs.
+//!
+//! Output files left by an earlier run are appended to by default; they can
+//! instead be truncated, or make the run refuse to start
+//! ([`ExistingOutput`]). With a split every file the run could write to is
+//! considered, so no stale category file from an earlier run is left
+//! looking like part of this one.
+//!
+//! A dry-run writer (`--count-only`) goes through the same routing but only
+//! counts what would have been written.
+//!
+//! With `--incremental` the lines already in each output file are hashed
+//! when it is opened, and a hit whose line is among them (or was written
+//! earlier in the run) is skipped, so re-running over an updated input only
+//! appends new hits. Only hashes are kept, never the lines themselves. A
+//! journal of them can be kept with the checkpoint, so that a resumed run
+//! doesn't have to hash its output again. Under `--max-memory` the hashes
+//! of a file past its share are spilled next to it (see [`crate::spill`]).
+
+use crate::{
+    digest::phrase_sha256,
+    secret::ZeroizingWriter,
+    spill::{Key as LineKey, KeySet},
+};
+use bip39::{Language, Mnemonic};
+use sha2::{Digest, Sha256};
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs::{File, OpenOptions},
+    io::{self, BufRead, BufReader, Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+use zeroize::Zeroizing;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SplitBy {
+    WordCount,
+    Language,
+    Scheme,
+}
+
+impl FromStr for SplitBy {
+    type Err = String;
+
+    /// Accepts both `by=word-count` and plain `word-count`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.strip_prefix("by=").unwrap_or(s) {
+            "word-count" => Ok(SplitBy::WordCount),
+            "language" => Ok(SplitBy::Language),
+            "scheme" => Ok(SplitBy::Scheme),
+            other => Err(format!(
+                "unknown split '{}', expected by=word-count, by=language or by=scheme",
+                other
+            )),
+        }
+    }
+}
+
+impl SplitBy {
+    /// File name suffix for a hit.
+    fn category(self, mnemonic: &Mnemonic) -> String {
+        match self {
+            SplitBy::WordCount => format!("{}w", mnemonic.word_count()),
+            SplitBy::Language => format!("{:?}", mnemonic.language()).to_lowercase(),
+            // BIP39 is the only scheme validated so far
+            SplitBy::Scheme => "bip39".to_string(),
+        }
+    }
+
+    /// Every suffix [`category`](Self::category) can return.
+    fn categories(self) -> Vec<String> {
+        match self {
+            SplitBy::WordCount => [12, 15, 18, 21, 24].iter().map(|n| format!("{}w", n)).collect(),
+            SplitBy::Language => Language::ALL
+                .iter()
+                .map(|language| format!("{:?}", language).to_lowercase())
+                .collect(),
+            SplitBy::Scheme => vec!["bip39".to_string()],
+        }
+    }
+}
+
+/// What to do with an output file that already exists when a run starts.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ExistingOutput {
+    #[default]
+    Append,
+    /// Truncate it.
+    Overwrite,
+    /// Refuse to start.
+    Error,
+}
+
+/// `output/valid.txt` + `12w` -> `output/valid_12w.txt`
+///
+/// Several categories are joined with `_`: `output/valid_12w_shard07.txt`.
+pub fn category_path(base: &Path, category: &str) -> PathBuf {
+    // Built as an OS string so non-UTF-8 names survive
+    let mut name = base.file_stem().unwrap_or_default().to_os_string();
+    name.push("_");
+    name.push(category);
+    if let Some(ext) = base.extension() {
+        name.push(".");
+        name.push(ext);
+    }
+    base.with_file_name(name)
+}
+
+fn open_append(path: &Path) -> io::Result<ZeroizingWriter<File>> {
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    // A run stopped by a full disk can leave half a line behind; end it
+    // rather than run the next hit into it
+    if ends_mid_line(path, file.metadata()?.len()).unwrap_or(false) {
+        file.write_all(b"\n")?;
+    }
+    Ok(ZeroizingWriter::new(file))
+}
+
+fn ends_mid_line(path: &Path, len: u64) -> io::Result<bool> {
+    if len == 0 {
+        return Ok(false);
+    }
+    let mut file = File::open(path)?;
+    let mut last = [0];
+    file.seek(SeekFrom::Start(len - 1))?;
+    file.read_exact(&mut last)?;
+    Ok(last != *b"\n")
+}
+
+/// Stable shard index of a phrase; depends only on its words.
+pub fn shard_of(mnemonic: &Mnemonic, shards: u32) -> u32 {
+    let digest = phrase_sha256(mnemonic.words());
+    let prefix = u64::from_be_bytes(digest[..8].try_into().unwrap());
+    (prefix % shards as u64) as u32
+}
+
+/// Identifies an output line for `--incremental` without keeping it.
+fn line_key(line: &[u8]) -> LineKey {
+    Sha256::digest(line)[..16].try_into().unwrap()
+}
+
+/// Calls `each` with the key and length (with its line break, and any blank
+/// lines before it) of every line of `path` from byte `from` on, if the file
+/// exists.
+fn read_keys(path: &Path, from: u64, mut each: impl FnMut(LineKey, u32) -> io::Result<()>) -> io::Result<()> {
+    let mut file = match File::open(path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(e),
+    };
+    file.seek(SeekFrom::Start(from))?;
+    let mut reader = BufReader::new(file);
+    let mut line = Zeroizing::new(Vec::new());
+    let mut blank = 0;
+    while reader.read_until(b'\n', &mut line)? > 0 {
+        let text = line.strip_suffix(b"\n").unwrap_or(&line);
+        let text = text.strip_suffix(b"\r").unwrap_or(text);
+        if text.is_empty() {
+            blank += line.len() as u32;
+        } else {
+            each(line_key(text), blank + line.len() as u32)?;
+            blank = 0;
+        }
+        line.clear();
+    }
+    Ok(())
+}
+
+/// Journal of the `--incremental` index, kept with the checkpoint so that a
+/// resumed run picks up the keys of its output files instead of reading
+/// and hashing them all over again.
+///
+/// It's append-only: a `T` record names an output file and gives it the
+/// next number, a `K` record adds the key and length of a line written to
+/// file number so-and-so. A file whose keys can't be trusted any more gets a
+/// new number and starts over. Like the index, it holds no phrases.
+struct Journal {
+    file: File,
+    /// Records not written yet: they go out after the lines they're of.
+    pending: Vec<u8>,
+    /// Current number of each file.
+    ids: HashMap<PathBuf, u32>,
+    next_id: u32,
+    /// What the journal held for each file when it was opened: the keys,
+    /// and where in the file the lines they're of end.
+    loaded: HashMap<PathBuf, (KeySet, u64)>,
+    /// Keys each file's set holds in memory.
+    keys_in_memory: Option<usize>,
+}
+
+const TARGET: u8 = b'T';
+const KEY: u8 = b'K';
+
+impl Journal {
+    fn open(path: &Path, keys_in_memory: Option<usize>) -> io::Result<Self> {
+        let mut file = OpenOptions::new().read(true).write(true).create(true).truncate(false).open(path)?;
+        let mut bytes = Vec::new();
+        file.read_to_end(&mut bytes)?;
+
+        let mut by_id: Vec<PathBuf> = Vec::new();
+        let mut ids = HashMap::new();
+        let mut loaded: HashMap<PathBuf, (KeySet, u64)> = HashMap::new();
+        let mut rest = &bytes[..];
+        // Where the last whole record ends; a run stopped mid-write leaves
+        // part of one after it
+        let mut whole = 0;
+        loop {
+            match rest {
+                [TARGET, tail @ ..] if tail.len() >= 2 => {
+                    let len = u16::from_le_bytes([tail[0], tail[1]]) as usize;
+                    let Some(name) = tail.get(2..2 + len) else { break };
+                    let path = PathBuf::from(String::from_utf8_lossy(name).into_owned());
+                    ids.insert(path.clone(), by_id.len() as u32);
+                    loaded.insert(path.clone(), (KeySet::new(&keys_path(&path), keys_in_memory), 0));
+                    by_id.push(path);
+                    rest = &tail[2 + len..];
+                }
+                [KEY, tail @ ..] if tail.len() >= 24 => {
+                    let id = u32::from_le_bytes(tail[..4].try_into().unwrap());
+                    let key: LineKey = tail[4..20].try_into().unwrap();
+                    let len = u32::from_le_bytes(tail[20..24].try_into().unwrap());
+                    // Keys of a file from before it started over don't count
+                    if let Some(path) = by_id.get(id as usize).filter(|path| ids.get(*path) == Some(&id)) {
+                        let (keys, end) = loaded.get_mut(path).unwrap();
+                        keys.add(key)?;
+                        *end += len as u64;
+                    }
+                    rest = &tail[24..];
+                }
+                _ => break,
+            }
+            whole = bytes.len() - rest.len();
+        }
+        file.set_len(whole as u64)?;
+        file.seek(SeekFrom::End(0))?;
+        Ok(Self {
+            file,
+            pending: Vec::new(),
+            ids,
+            next_id: by_id.len() as u32,
+            loaded,
+            keys_in_memory,
+        })
+    }
+
+    /// The keys of the lines in `path`: those journaled, if the file still
+    /// goes on from where they end, and those of the lines after them.
+    fn target(&mut self, path: &Path) -> io::Result<KeySet> {
+        // Those lines are all on disk already, unlike some of the pending
+        // ones, so their records go out straight away
+        let mut records = Vec::new();
+        let (mut keys, end) = match self.loaded.remove(path) {
+            Some((keys, end)) if continues(path, end)? => (keys, end),
+            _ => {
+                let name = path.to_string_lossy();
+                records.push(TARGET);
+                records.extend_from_slice(&(name.len() as u16).to_le_bytes());
+                records.extend_from_slice(name.as_bytes());
+                self.ids.insert(path.to_path_buf(), self.next_id);
+                self.next_id += 1;
+                (KeySet::new(&keys_path(path), self.keys_in_memory), 0)
+            }
+        };
+        let id = self.ids[path];
+        read_keys(path, end, |key, len| {
+            keys.add(key)?;
+            key_record(&mut records, id, key, len);
+            Ok(())
+        })?;
+        self.file.write_all(&records)?;
+        Ok(keys)
+    }
+
+    fn append(&mut self, path: &Path, key: LineKey, len: u32) {
+        key_record(&mut self.pending, self.ids[path], key, len);
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.write_all(&self.pending)?;
+        self.pending.clear();
+        Ok(())
+    }
+}
+
+fn key_record(records: &mut Vec<u8>, id: u32, key: LineKey, len: u32) {
+    records.push(KEY);
+    records.extend_from_slice(&id.to_le_bytes());
+    records.extend_from_slice(&key);
+    records.extend_from_slice(&len.to_le_bytes());
+}
+
+/// `out.txt` -> `out.txt.keys`, where the `--incremental` index of
+/// `out.txt` spills to.
+fn keys_path(path: &Path) -> PathBuf {
+    let mut name = path.as_os_str().to_os_string();
+    name.push(".keys");
+    PathBuf::from(name)
+}
+
+/// Whether `path` still holds whole lines up to `end`, as when its keys were
+/// journaled.
+fn continues(path: &Path, end: u64) -> io::Result<bool> {
+    let len = match path.metadata() {
+        Ok(metadata) => metadata.len(),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(end == 0),
+        Err(e) => return Err(e),
+    };
+    Ok(end == 0 || (len >= end && !ends_mid_line(path, end)?))
+}
+
+/// One output file and what has been written to it during this run.
+struct Target {
+    /// `None` in dry-run mode.
+    writer: Option<ZeroizingWriter<File>>,
+    /// Length of the file when this run opened it.
+    offset: u64,
+    hits: usize,
+    bytes: u64,
+    /// Lines the file holds, with `--incremental`.
+    existing: Option<KeySet>,
+    /// Hits not written because the file already had them.
+    skipped: usize,
+}
+
+/// Per-file totals for the end-of-run summary.
+#[derive(Debug, Clone)]
+pub struct TargetStats {
+    pub path: PathBuf,
+    /// Where this run started appending.
+    pub offset: u64,
+    pub hits: usize,
+    pub bytes: u64,
+    pub skipped: usize,
+}
+
+pub struct HitWriter {
+    base: PathBuf,
+    split: Option<SplitBy>,
+    shards: Option<u32>,
+    dry_run: bool,
+    incremental: bool,
+    /// Keys of the `--incremental` index held in memory, over all files.
+    keys_in_memory: Option<usize>,
+    journal: Option<Journal>,
+    files: BTreeMap<PathBuf, Target>,
+}
+
+impl HitWriter {
+    /// Without a split the output file is created right away, so an empty
+    /// result still leaves an (empty) output behind. Split files are only
+    /// created once they receive a hit.
+    pub fn new(base: &Path, split: Option<SplitBy>, shards: Option<u32>) -> io::Result<Self> {
+        Self::create(base, split, shards, ExistingOutput::Append)
+    }
+
+    /// Like [`new`](Self::new), deciding first what happens to output files
+    /// that already exist.
+    pub fn create(
+        base: &Path,
+        split: Option<SplitBy>,
+        shards: Option<u32>,
+        existing: ExistingOutput,
+    ) -> io::Result<Self> {
+        let mut writer = Self::dry_run(base, split, shards);
+        writer.dry_run = false;
+        if existing != ExistingOutput::Append {
+            for path in writer.possible_paths().iter().filter(|path| path.exists()) {
+                if existing == ExistingOutput::Error {
+                    return Err(io::Error::new(
+                        io::ErrorKind::AlreadyExists,
+                        format!("output file '{}' already exists", path.display()),
+                    ));
+                }
+                File::create(path)?;
+            }
+        }
+        if writer.split.is_none() && writer.shards.is_none() {
+            writer.open(base.to_path_buf())?;
+        }
+        Ok(writer)
+    }
+
+    /// A writer that routes and counts hits but never touches the disk.
+    pub fn dry_run(base: &Path, split: Option<SplitBy>, shards: Option<u32>) -> Self {
+        Self {
+            base: base.to_path_buf(),
+            split,
+            shards: shards.filter(|&n| n > 1),
+            dry_run: true,
+            incremental: false,
+            keys_in_memory: None,
+            journal: None,
+            files: BTreeMap::new(),
+        }
+    }
+
+    /// Skips hits whose line is already in their output file, for
+    /// `--incremental`. With an `index` journal, the keys of the lines are
+    /// taken from it and kept in it as hits are written (see [`Journal`]);
+    /// it's left to a resumed run, so a new one should remove it first.
+    pub fn skip_existing(&mut self, index: Option<&Path>) -> io::Result<()> {
+        self.incremental = true;
+        if let Some(index) = index.filter(|_| !self.dry_run) {
+            self.journal = Some(Journal::open(index, self.file_keys())?);
+        }
+        let paths: Vec<PathBuf> = self.files.keys().cloned().collect();
+        for path in paths {
+            let existing = self.existing_lines(&path)?;
+            self.files.get_mut(&path).unwrap().existing = Some(existing);
+        }
+        Ok(())
+    }
+
+    /// Holds no more than `keys` keys of the `--incremental` index in
+    /// memory, spilling the rest to disk; to be set before
+    /// [`skip_existing`](Self::skip_existing). A dry run never spills.
+    pub fn keys_in_memory(&mut self, keys: usize) {
+        self.keys_in_memory = Some(keys).filter(|_| !self.dry_run);
+    }
+
+    /// The share of each file a hit may go to.
+    fn file_keys(&self) -> Option<usize> {
+        self.keys_in_memory.map(|keys| keys / self.possible_paths().len())
+    }
+
+    /// Whether the `--incremental` index of some file was spilled to disk.
+    pub fn spilled(&self) -> bool {
+        self.files.values().any(|target| target.existing.as_ref().is_some_and(KeySet::spilled))
+    }
+
+    /// Keys of the lines already in `path`.
+    fn existing_lines(&mut self, path: &Path) -> io::Result<KeySet> {
+        if let Some(journal) = self.journal.as_mut() {
+            return journal.target(path);
+        }
+        let mut keys = KeySet::new(&keys_path(path), self.file_keys());
+        read_keys(path, 0, |key, _| keys.add(key))?;
+        Ok(keys)
+    }
+
+    fn open(&mut self, path: PathBuf) -> io::Result<()> {
+        let existing = if self.incremental {
+            Some(self.existing_lines(&path)?)
+        } else {
+            None
+        };
+        let (writer, offset) = if self.dry_run {
+            (None, path.metadata().map(|metadata| metadata.len()).unwrap_or(0))
+        } else {
+            let writer = open_append(&path)?;
+            (Some(writer), path.metadata()?.len())
+        };
+        self.files.insert(
+            path,
+            Target {
+                writer,
+                offset,
+                hits: 0,
+                bytes: 0,
+                existing,
+                skipped: 0,
+            },
+        );
+        Ok(())
+    }
+
+    fn path_for(&self, mnemonic: &Mnemonic) -> PathBuf {
+        let category = self.split.map(|split| split.category(mnemonic));
+        let shard = self.shards.map(|shards| shard_of(mnemonic, shards));
+        self.path_with(category, shard)
+    }
+
+    fn path_with(&self, category: Option<String>, shard: Option<u32>) -> PathBuf {
+        let mut categories = Vec::new();
+        categories.extend(category);
+        if let (Some(shards), Some(shard)) = (self.shards, shard) {
+            let width = (shards - 1).to_string().len();
+            categories.push(format!("shard{:0width$}", shard, width = width));
+        }
+
+        if categories.is_empty() {
+            self.base.clone()
+        } else {
+            category_path(&self.base, &categories.join("_"))
+        }
+    }
+
+    /// Every file this writer could route a hit to.
+    fn possible_paths(&self) -> Vec<PathBuf> {
+        let categories = match self.split {
+            Some(split) => split.categories().into_iter().map(Some).collect(),
+            None => vec![None],
+        };
+        let shards = match self.shards {
+            Some(shards) => (0..shards).map(Some).collect(),
+            None => vec![None],
+        };
+        let mut paths = Vec::new();
+        for category in &categories {
+            for &shard in &shards {
+                paths.push(self.path_with(category.clone(), shard));
+            }
+        }
+        paths
+    }
+
+    /// Writes `text` (the phrase, or its masked form) as one line. Returns
+    /// false if it was skipped as already written.
+    pub fn write_hit(&mut self, mnemonic: &Mnemonic, text: &str) -> io::Result<bool> {
+        let path = self.path_for(mnemonic);
+        if !self.files.contains_key(&path) {
+            self.open(path.clone())?;
+        }
+        let target = self.files.get_mut(&path).unwrap();
+        let key = line_key(text.as_bytes());
+        if let Some(existing) = target.existing.as_mut() {
+            if !existing.insert(key)? {
+                target.skipped += 1;
+                return Ok(false);
+            }
+        }
+        if let Some(writer) = target.writer.as_mut() {
+            writeln!(writer, "{}", text)?;
+        }
+        if let Some(journal) = self.journal.as_mut() {
+            journal.append(&path, key, text.len() as u32 + 1);
+        }
+        target.hits += 1;
+        target.bytes += text.len() as u64 + 1;
+        Ok(true)
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        for writer in self.files.values_mut().filter_map(|t| t.writer.as_mut()) {
+            writer.flush()?;
+        }
+        // After the lines, so the journal never gets ahead of them
+        match self.journal.as_mut() {
+            Some(journal) => journal.flush(),
+            None => Ok(()),
+        }
+    }
+
+    /// Hits and bytes written (or, in dry-run mode, that would have been
+    /// written) per file during this run.
+    pub fn stats(&self) -> Vec<TargetStats> {
+        self.files
+            .iter()
+            .map(|(path, target)| TargetStats {
+                path: path.clone(),
+                offset: target.offset,
+                hits: target.hits,
+                bytes: target.bytes,
+                skipped: target.skipped,
+            })
+            .collect()
+    }
+}
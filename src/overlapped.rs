@@ -0,0 +1,209 @@
+//! Overlapped I/O block reader (Windows).
+//!
+//! The counterpart of the io_uring reader: keeps several fixed-size reads
+//! of a file opened for overlapped I/O in flight at once, and hands out
+//! blocks that always end on a line boundary (see [`crate::blocks`]). Each
+//! read has an event of its own to wait on, and reads are waited for in the
+//! order they were issued, which is the order they're needed in.
+
+use crate::blocks::{Block, Joiner, CARRY_ROOM};
+use std::{
+    collections::{BTreeMap, VecDeque},
+    fs::{File, OpenOptions},
+    io,
+    os::windows::{fs::OpenOptionsExt, io::AsRawHandle},
+    path::Path,
+    ptr,
+};
+use windows_sys::Win32::{
+    Foundation::{CloseHandle, ERROR_HANDLE_EOF, ERROR_IO_PENDING, HANDLE},
+    Storage::FileSystem::{ReadFile, FILE_FLAG_OVERLAPPED},
+    System::{
+        IO::{CancelIoEx, GetOverlappedResult, OVERLAPPED},
+        Threading::CreateEventW,
+    },
+};
+
+/// A read in flight: where it reads to and into which buffer. Neither the
+/// buffer's heap storage nor the boxed `OVERLAPPED` move until it's done.
+struct Pending {
+    offset: u64,
+    len: usize,
+    buf: Vec<u8>,
+    overlapped: Box<OVERLAPPED>,
+}
+
+impl Drop for Pending {
+    fn drop(&mut self) {
+        unsafe { CloseHandle(self.overlapped.hEvent) };
+    }
+}
+
+pub struct BlockReader {
+    file: File,
+    file_len: u64,
+    block_size: usize,
+    queue_depth: usize,
+    /// Next byte not yet requested.
+    next_offset: u64,
+    /// Ranges that came back short and still need reading.
+    retry: Vec<(u64, usize)>,
+    /// Reads in flight, in the order they were issued.
+    pending: VecDeque<Pending>,
+    /// Finished reads by offset, waiting to be joined in order.
+    done: BTreeMap<u64, Vec<u8>>,
+    joiner: Joiner,
+}
+
+// Safety: the handles and `OVERLAPPED`s are only used by whichever thread
+// owns the reader, and Windows doesn't tie them to the thread that made them.
+unsafe impl Send for BlockReader {}
+
+impl BlockReader {
+    /// Numbers lines from `first_line`, with `block_size` bytes per read and
+    /// `queue_depth` reads in flight.
+    pub fn with_sizes(path: &Path, first_line: usize, block_size: usize, queue_depth: usize) -> io::Result<Self> {
+        let file = OpenOptions::new().read(true).custom_flags(FILE_FLAG_OVERLAPPED).open(path)?;
+        let file_len = file.metadata()?.len();
+        Ok(Self {
+            file,
+            file_len,
+            block_size,
+            queue_depth: queue_depth.max(1),
+            next_offset: 0,
+            retry: Vec::new(),
+            pending: VecDeque::new(),
+            done: BTreeMap::new(),
+            joiner: Joiner::new(file_len, first_line),
+        })
+    }
+
+    fn handle(&self) -> HANDLE {
+        self.file.as_raw_handle() as HANDLE
+    }
+
+    /// Issues reads until `queue_depth` are in flight or the whole file is
+    /// requested.
+    fn fill(&mut self) -> io::Result<()> {
+        while self.pending.len() < self.queue_depth {
+            let (offset, len) = match self.retry.pop() {
+                Some(range) => range,
+                None if self.next_offset < self.file_len => {
+                    let len = self.block_size.min((self.file_len - self.next_offset) as usize);
+                    let offset = self.next_offset;
+                    self.next_offset += len as u64;
+                    (offset, len)
+                }
+                None => break,
+            };
+
+            let event = unsafe { CreateEventW(ptr::null(), 1, 0, ptr::null()) };
+            if event.is_null() {
+                return Err(io::Error::last_os_error());
+            }
+            // Safety: all zeroes is an idle OVERLAPPED
+            let mut overlapped: Box<OVERLAPPED> = Box::new(unsafe { std::mem::zeroed() });
+            overlapped.Anonymous.Anonymous.Offset = offset as u32;
+            overlapped.Anonymous.Anonymous.OffsetHigh = (offset >> 32) as u32;
+            overlapped.hEvent = event;
+            let mut read = Pending {
+                offset,
+                len,
+                buf: vec![0u8; CARRY_ROOM + len],
+                overlapped,
+            };
+
+            // Safety: the buffer and the OVERLAPPED are owned by the entry
+            // in `pending` until the read is waited for, or cancelled and
+            // waited for when the reader is dropped.
+            let started = unsafe {
+                ReadFile(
+                    self.handle(),
+                    read.buf[CARRY_ROOM..].as_mut_ptr(),
+                    len as u32,
+                    ptr::null_mut(),
+                    &mut *read.overlapped,
+                )
+            };
+            if started == 0 {
+                match io::Error::last_os_error() {
+                    e if is(&e, ERROR_IO_PENDING) => {}
+                    e if is(&e, ERROR_HANDLE_EOF) => return Err(shrank()),
+                    e => return Err(e),
+                }
+            }
+            self.pending.push_back(read);
+        }
+        Ok(())
+    }
+
+    /// Waits for the oldest read in flight and files it away.
+    fn reap(&mut self) -> io::Result<()> {
+        let Some(mut read) = self.pending.pop_front() else {
+            return Ok(());
+        };
+        let mut n = 0u32;
+        let finished = unsafe { GetOverlappedResult(self.handle(), &*read.overlapped, &mut n, 1) };
+        if finished == 0 {
+            return match io::Error::last_os_error() {
+                e if is(&e, ERROR_HANDLE_EOF) => Err(shrank()),
+                e => Err(e),
+            };
+        }
+        let n = n as usize;
+        if n == 0 {
+            return Err(shrank());
+        }
+        if n < read.len {
+            self.retry.push((read.offset + n as u64, read.len - n));
+        }
+        let mut buf = std::mem::take(&mut read.buf);
+        buf.truncate(CARRY_ROOM + n);
+        self.done.insert(read.offset, buf);
+        Ok(())
+    }
+}
+
+fn is(e: &io::Error, code: u32) -> bool {
+    e.raw_os_error() == Some(code as i32)
+}
+
+fn shrank() -> io::Error {
+    io::Error::new(io::ErrorKind::UnexpectedEof, "input file shrank while reading")
+}
+
+impl Iterator for BlockReader {
+    type Item = io::Result<Block>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(buf) = self.done.remove(&self.joiner.offset()) {
+                match self.joiner.join(buf) {
+                    Some(block) => return Some(Ok(block)),
+                    None => continue,
+                }
+            }
+            if self.joiner.is_done() {
+                return None;
+            }
+            if let Err(e) = self.fill().and_then(|_| self.reap()) {
+                return Some(Err(e));
+            }
+        }
+    }
+}
+
+impl Drop for BlockReader {
+    /// Reads still in flight write into their buffers until they're
+    /// cancelled and done, so the buffers must outlive them.
+    fn drop(&mut self) {
+        let handle = self.handle();
+        for read in &self.pending {
+            let mut n = 0u32;
+            unsafe {
+                CancelIoEx(handle, &*read.overlapped);
+                GetOverlappedResult(handle, &*read.overlapped, &mut n, 1);
+            }
+        }
+    }
+}
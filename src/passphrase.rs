@@ -0,0 +1,25 @@
+//! Trying BIP39 passphrases on a valid phrase whose wallet looks empty.
+//!
+//! Each passphrase gives a different seed and so a different wallet, which
+//! is scanned for the targets up to the gap limit; a match means the
+//! passphrase was found. Stretching the seed (2048 rounds of PBKDF2) costs
+//! more than the handful of addresses after it.
+
+use crate::{
+    address::Targets,
+    bip32::ExtendedKey,
+    secret::Zeroizing,
+    wallet::{Hit, Scan},
+};
+use bip39::Mnemonic;
+
+/// The wallet of `mnemonic` under `passphrase`.
+pub fn master(mnemonic: &Mnemonic, passphrase: &str) -> Option<ExtendedKey> {
+    let seed = Zeroizing::new(mnemonic.to_seed_normalized(passphrase));
+    ExtendedKey::master(&*seed)
+}
+
+/// The first target found in the wallet of `mnemonic` under `passphrase`.
+pub fn search(mnemonic: &Mnemonic, passphrase: &str, targets: &Targets, scan: &Scan) -> Option<Hit> {
+    scan.run(&master(mnemonic, passphrase)?, targets, true).pop()
+}
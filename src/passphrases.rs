@@ -0,0 +1,318 @@
+//! `mnemonic_validator passphrase`: trying a list of BIP39 passphrases on a
+//! valid phrase until one gives a wallet holding a known address.
+//!
+//! The passphrases are tried in batches spread over the threads. After
+//! each batch the number tried so far is saved to a checkpoint with a
+//! digest of the search, so an interrupted search resumes where it stopped
+//! and a checkpoint of another phrase, other targets or another scan is
+//! refused; the checkpoint is removed once the list is exhausted or a
+//! passphrase is found.
+
+use crate::console::{self, Progress};
+use bip39::Mnemonic;
+use clap::Args;
+use mnemonic_validator::{
+    address::Targets,
+    cancel::CancelToken,
+    checkpoint::write_atomic,
+    digest,
+    eta::{self, EtaEstimator},
+    lines, mask,
+    network::{self, Network},
+    passphrase, paths,
+    secret::Zeroizing,
+    typography,
+    wallet::{self, Chain, Hit, Scan},
+};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::{
+    fs,
+    io::{self, Read},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex,
+    },
+    thread,
+    time::Instant,
+};
+
+/// Passphrases per thread in a batch.
+const BATCH_PER_THREAD: usize = 16;
+/// Format of the checkpoints written by this version.
+const CHECKPOINT_VERSION: u32 = 1;
+
+/// Options of `mnemonic_validator passphrase`.
+#[derive(Args, Debug)]
+pub struct PassphraseArgs {
+    /// File with the phrase, e.g. a one-line excerpt of the output; standard input if left out.
+    #[clap(value_parser = paths::parser())]
+    file: Option<PathBuf>,
+
+    /// Candidate passphrases, one per line, used exactly as written (an empty line is the empty passphrase).
+    #[clap(long, value_name = "FILE", value_parser = paths::parser())]
+    passphrase_file: PathBuf,
+
+    /// Addresses the wallet is known to have used, one per line: legacy (1...), nested SegWit (3...) or native SegWit (bc1q...), or Ethereum (0x...) on --network ethereum.
+    #[clap(long, value_name = "FILE", value_parser = paths::parser())]
+    targets: PathBuf,
+
+    /// Network of the target addresses and derivation paths: mainnet, testnet, signet, regtest, ethereum, or one of the coin registry.
+    #[clap(long, value_name = "NETWORK", default_value = "mainnet", value_parser = network::parse_network)]
+    network: Network,
+
+    /// Stop walking a chain after this many addresses in a row that aren't targets.
+    #[clap(long, value_name = "N", default_value_t = wallet::DEFAULT_GAP_LIMIT, value_parser = clap::value_parser!(u32).range(1..))]
+    gap_limit: u32,
+
+    /// Chains of each account to walk: external (receive addresses) and/or internal (change addresses).
+    #[clap(long, value_name = "CHAIN,...", value_delimiter = ',', default_value = "external", value_parser = wallet::parse_chain)]
+    chains: Vec<Chain>,
+
+    /// Number of threads (default: one per CPU).
+    #[clap(long, value_name = "N", value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..))]
+    threads: Option<usize>,
+
+    /// Where to keep the number of passphrases tried (default: the passphrase file's path with .checkpoint appended).
+    #[clap(long, value_name = "FILE", value_parser = paths::parser())]
+    checkpoint: Option<PathBuf>,
+
+    /// Ignore any saved checkpoint and start from the first passphrase.
+    #[clap(long)]
+    restart: bool,
+}
+
+/// The one phrase in `file`, or on standard input.
+fn read_phrase(file: Option<&PathBuf>) -> Result<Mnemonic, Box<dyn std::error::Error>> {
+    let mut text = Zeroizing::new(Vec::new());
+    match file {
+        Some(path) => fs::File::open(path)?.read_to_end(&mut text)?,
+        None => io::stdin().read_to_end(&mut text)?,
+    };
+    let mut phrases = lines::lines(&text)
+        .map(|line| std::str::from_utf8(line).unwrap_or_default().trim())
+        .filter(|line| !line.is_empty());
+    let (Some(line), None) = (phrases.next(), phrases.next()) else {
+        return Err("expected exactly one phrase".into());
+    };
+    let cleaned = typography::clean(line);
+    let line = cleaned.as_deref().map_or(line, String::as_str);
+    Ok(Mnemonic::parse_normalized(line).map_err(|e| format!("the phrase isn't a valid mnemonic: {}", e))?)
+}
+
+fn default_checkpoint(passphrase_file: &Path) -> PathBuf {
+    let mut name = passphrase_file.as_os_str().to_os_string();
+    name.push(".checkpoint");
+    PathBuf::from(name)
+}
+
+/// Where a search got to.
+#[derive(Serialize, Deserialize)]
+struct Checkpoint {
+    version: u32,
+    /// [`search_digest`] of the search.
+    search: String,
+    /// Passphrases before this one are done.
+    tried: usize,
+}
+
+/// What a checkpoint must have been saved by to be resumed: the phrase's
+/// fingerprint, the targets and how wallets are scanned for them, hashed.
+fn search_digest(mnemonic: &Mnemonic, targets: &Targets, scan: &Scan) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(mask::fingerprint(&Zeroizing::new(mnemonic.to_string())));
+    let mut addresses: Vec<&str> = targets.addresses().collect();
+    addresses.sort_unstable();
+    for address in addresses {
+        hasher.update(b"\0");
+        hasher.update(address);
+    }
+    hasher.update(format!("\0{}\0{}", scan.network.name(), scan.gap_limit));
+    for chain in &scan.chains {
+        hasher.update(format!("\0{}", chain.index()));
+    }
+    digest::to_hex(&hasher.finalize())
+}
+
+fn save_checkpoint(path: &Path, search: &str, tried: usize) -> Result<(), String> {
+    let checkpoint = Checkpoint {
+        version: CHECKPOINT_VERSION,
+        search: search.to_string(),
+        tried,
+    };
+    let mut json = serde_json::to_string(&checkpoint).map_err(|e| e.to_string())?;
+    json.push('\n');
+    write_atomic(path, json.as_bytes()).map_err(|e| format!("can't save the checkpoint {}: {}", path.display(), e))
+}
+
+/// The number of passphrases the checkpoint at `path` says were tried in
+/// the search `search`; 0 if there's none.
+fn load_checkpoint(path: &Path, search: &str) -> Result<usize, String> {
+    if !path.exists() {
+        return Ok(0);
+    }
+    let shown = path.display();
+    let text = fs::read_to_string(path).map_err(|e| format!("can't read the checkpoint {}: {}", shown, e))?;
+    let checkpoint: Checkpoint = serde_json::from_str(&text)
+        .map_err(|_| format!("the checkpoint {} is damaged; give --restart to start over", shown))?;
+    if checkpoint.version > CHECKPOINT_VERSION {
+        return Err(format!("the checkpoint {} was written by a newer version of this tool", shown));
+    }
+    if checkpoint.search != search {
+        return Err(format!(
+            "the checkpoint {} is of another search; remove it or give --restart to start this one over, or --checkpoint to keep both",
+            shown
+        ));
+    }
+    Ok(checkpoint.tried)
+}
+
+/// The first passphrase of `batch` with a hit, by position in the batch.
+fn search_batch<'a>(
+    mnemonic: &Mnemonic,
+    batch: &[&'a str],
+    targets: &Targets,
+    scan: &Scan,
+    threads: usize,
+) -> Option<(&'a str, Hit)> {
+    let next = AtomicUsize::new(0);
+    let found: Mutex<Option<(usize, Hit)>> = Mutex::new(None);
+    thread::scope(|scope| {
+        for _ in 0..threads.min(batch.len()) {
+            scope.spawn(|| loop {
+                let i = next.fetch_add(1, Ordering::Relaxed);
+                let Some(candidate) = batch.get(i) else {
+                    break;
+                };
+                if let Some(hit) = passphrase::search(mnemonic, candidate, targets, scan) {
+                    let mut found = found.lock().unwrap();
+                    if found.as_ref().is_none_or(|(first, _)| i < *first) {
+                        *found = Some((i, hit));
+                    }
+                }
+            });
+        }
+    });
+    let (i, hit) = found.into_inner().unwrap()?;
+    Some((batch[i], hit))
+}
+
+pub fn run(args: &PassphraseArgs) -> Result<(), Box<dyn std::error::Error>> {
+    let mnemonic = read_phrase(args.file.as_ref())?;
+    let targets = Targets::parse(&fs::read_to_string(&args.targets)?, args.network)
+        .map_err(|e| format!("{}: {}", args.targets.display(), e))?;
+    if targets.is_empty() {
+        return Err(format!("no addresses in {}", args.targets.display()).into());
+    }
+
+    let mut text = Zeroizing::new(Vec::new());
+    fs::File::open(&args.passphrase_file)?.read_to_end(&mut text)?;
+    let candidates: Vec<&str> = lines::lines(&text)
+        .filter_map(|line| std::str::from_utf8(line).ok())
+        .collect();
+    let skipped = lines::lines(&text).count() - candidates.len();
+    if skipped > 0 {
+        println!("Warning: skipping {} passphrases that aren't valid UTF-8.", skipped);
+    }
+
+    let scan = Scan {
+        chains: args.chains.clone(),
+        gap_limit: args.gap_limit,
+        network: args.network,
+    };
+    let checkpoint_path = args
+        .checkpoint
+        .clone()
+        .unwrap_or_else(|| default_checkpoint(&args.passphrase_file));
+    let search = search_digest(&mnemonic, &targets, &scan);
+    let mut tried = match args.restart {
+        true => 0,
+        false => load_checkpoint(&checkpoint_path, &search)?,
+    };
+    if tried > candidates.len() {
+        println!(
+            "Warning: the checkpoint is past the end of '{}', which has {} passphrases; starting over.",
+            args.passphrase_file.display(),
+            candidates.len()
+        );
+        tried = 0;
+    }
+
+    let threads = args
+        .threads
+        .unwrap_or_else(|| thread::available_parallelism().map_or(1, |n| n.get()));
+    println!(
+        "Trying {} passphrases against {} addresses, starting from {}, with {} threads",
+        candidates.len(),
+        targets.len(),
+        tried,
+        threads
+    );
+
+    let cancel = CancelToken::new();
+    console::on_exit({
+        let cancel = cancel.clone();
+        move |_| cancel.cancel()
+    })?;
+
+    let (start, resumed) = (Instant::now(), tried);
+    let mut estimator = EtaEstimator::new(start, tried);
+    let mut progress = Progress::new();
+    let mut shown = start;
+    let batch_size = threads * BATCH_PER_THREAD;
+    let mut found = None;
+    while tried < candidates.len() && !cancel.is_cancelled() {
+        let batch = &candidates[tried..(tried + batch_size).min(candidates.len())];
+        found = search_batch(&mnemonic, batch, &targets, &scan, threads);
+        if found.is_some() {
+            break;
+        }
+        tried += batch.len();
+
+        let now = Instant::now();
+        estimator.update(now, tried);
+        if now.duration_since(shown) >= progress.interval() {
+            shown = now;
+            save_checkpoint(&checkpoint_path, &search, tried)?;
+            let rate = (tried - resumed) as f64 / now.duration_since(start).as_secs_f64();
+            let eta = estimator
+                .estimate(candidates.len() - tried)
+                .map_or_else(|| "--:--".to_string(), |eta| eta.to_string());
+            progress.show(&format!(
+                "Tried {}/{} passphrases ({:.0}/s), ETA {}",
+                tried,
+                candidates.len(),
+                rate,
+                eta
+            ));
+        }
+    }
+    progress.finish();
+
+    if let Some((candidate, _)) = found {
+        let _ = fs::remove_file(&checkpoint_path);
+        println!("Found the passphrase: '{}'", candidate);
+        // The rest of the wallet's targets, now that the search is over
+        let master = passphrase::master(&mnemonic, candidate);
+        for hit in master.iter().flat_map(|master| scan.run(master, &targets, false)) {
+            println!("It gives {} at {}", hit.address, hit.path);
+        }
+    } else if tried < candidates.len() {
+        save_checkpoint(&checkpoint_path, &search, tried)?;
+        println!(
+            "Stopped after {} passphrases in {}; run the same command again to continue (checkpoint '{}').",
+            tried,
+            eta::format_duration(start.elapsed()),
+            checkpoint_path.display()
+        );
+    } else {
+        let _ = fs::remove_file(&checkpoint_path);
+        println!(
+            "None of the {} passphrases gives one of the addresses (tried in {}).",
+            candidates.len(),
+            eta::format_duration(start.elapsed())
+        );
+    }
+    Ok(())
+}
@@ -0,0 +1,52 @@
+//! Paths given on the command line.
+//!
+//! Paths stay OS strings from the arguments to the files opened, so
+//! non-ASCII (and on Unix, non-UTF-8) names work; only messages and the
+//! manifest use the lossy display form. On Windows the standard library
+//! already switches to the `\\?\` long-path form when a path is longer than
+//! MAX_PATH, so deep directory trees open like any other path.
+//!
+//! A path that already carries the `\\?\` prefix is passed to Windows
+//! verbatim, though: `/` is not a separator there and `.` and `..` are not
+//! resolved. [`from_arg`] normalizes such paths first.
+
+#[cfg(feature = "cli")]
+use clap::builder::{OsStringValueParser, TypedValueParser};
+use std::{ffi::OsString, path::PathBuf};
+
+/// Clap value parser for path arguments.
+#[cfg(feature = "cli")]
+pub fn parser() -> impl TypedValueParser<Value = PathBuf> {
+    OsStringValueParser::new().map(from_arg)
+}
+
+pub fn from_arg(arg: OsString) -> PathBuf {
+    #[cfg(windows)]
+    if let Some(verbatim) = arg.to_str().and_then(|s| s.strip_prefix(r"\\?\")) {
+        return PathBuf::from(format!(r"\\?\{}", normalize_verbatim(verbatim)));
+    }
+    PathBuf::from(arg)
+}
+
+/// `C:\cases/2024\.\dump\..\img` -> `C:\cases\2024\img`. The first
+/// component (`C:` or `UNC`) is never removed by `..`.
+#[cfg(windows)]
+fn normalize_verbatim(path: &str) -> String {
+    let mut parts: Vec<&str> = Vec::new();
+    for part in path.split(['\\', '/']) {
+        match part {
+            "" | "." => {}
+            ".." if parts.len() > 1 => {
+                parts.pop();
+            }
+            ".." => {}
+            part => parts.push(part),
+        }
+    }
+    let mut normalized = parts.join(r"\");
+    if parts.len() == 1 && parts[0].ends_with(':') {
+        // A bare drive needs its root: `\\?\C:\`
+        normalized.push('\\');
+    }
+    normalized
+}
@@ -0,0 +1,556 @@
+//! Bounded validation pipeline.
+//!
+//! reader → splitter → validators → writer, connected by bounded channels.
+//! When a stage falls behind (usually the writer, or a slow sink behind it)
+//! the stages before it block instead of queueing more work, so memory use
+//! stays flat however large the input is and however slow the output.
+//!
+//! A small file makes a chunk of its own, and a batch per chunk would leave
+//! validators handing batches back and forth more than validating. The
+//! splitter therefore packs chunks that are already waiting into one batch,
+//! up to [`LINES_PER_CHUNK`] lines and [`BATCH_BYTES`] bytes.
+
+use crate::{
+    cancel::CancelToken,
+    eta::EtaEstimator,
+    events::{Events, Progress},
+    lines,
+    secret::SecretString,
+    throttle::Throttle,
+};
+use bip39::Mnemonic;
+use crossbeam_channel::{bounded, RecvTimeoutError};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    io,
+    ops::Range,
+    str::FromStr,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    thread,
+    time::Instant,
+};
+
+/// Lines per chunk cut from a memory-mapped input.
+pub const LINES_PER_CHUNK: usize = 4096;
+/// Most bytes of input a batch packs small chunks into: no more than the
+/// smallest block under a memory cap, so packing never adds to the memory
+/// the cap plans for.
+pub const BATCH_BYTES: usize = 64 << 10;
+/// Hits waiting for the writer before validators block.
+pub const HIT_QUEUE: usize = 1024;
+/// Longest line validated as it is unless set otherwise, in bytes: far
+/// beyond any phrase, even several to a line, and short of the "lines" of
+/// megabytes that carved binary data yields.
+pub const MAX_LINE: usize = 64 << 10;
+
+/// A run of whole input lines, as produced by the reader stage.
+pub trait Chunk: Send {
+    /// 0-based index of the first line.
+    fn first_line(&self) -> usize;
+    /// The lines; only the last one of the input may lack its newline.
+    fn bytes(&self) -> &[u8];
+}
+
+impl<C: Chunk + ?Sized> Chunk for Box<C> {
+    fn first_line(&self) -> usize {
+        (**self).first_line()
+    }
+
+    fn bytes(&self) -> &[u8] {
+        (**self).bytes()
+    }
+}
+
+/// Whatever the reader stage reads from: memory-mapped slices, or blocks
+/// read by io_uring or overlapped I/O.
+pub type Source<'a> = Box<dyn Iterator<Item = io::Result<Box<dyn Chunk + 'a>>> + Send + 'a>;
+
+/// Lines borrowed from a memory-mapped input.
+pub struct Slice<'a> {
+    first_line: usize,
+    bytes: &'a [u8],
+}
+
+impl Chunk for Slice<'_> {
+    fn first_line(&self) -> usize {
+        self.first_line
+    }
+
+    fn bytes(&self) -> &[u8] {
+        self.bytes
+    }
+}
+
+/// Cuts `bytes` into chunks of `lines_per_chunk` lines, numbering them
+/// from `first_line`.
+pub fn slices(bytes: &[u8], first_line: usize, lines_per_chunk: usize) -> Slices<'_> {
+    Slices {
+        rest: bytes,
+        next_line: first_line,
+        lines_per_chunk,
+    }
+}
+
+pub struct Slices<'a> {
+    rest: &'a [u8],
+    next_line: usize,
+    lines_per_chunk: usize,
+}
+
+impl<'a> Iterator for Slices<'a> {
+    type Item = Slice<'a>;
+
+    fn next(&mut self) -> Option<Slice<'a>> {
+        if self.rest.is_empty() {
+            return None;
+        }
+        let end = lines::breaks(self.rest)
+            .nth(self.lines_per_chunk - 1)
+            .unwrap_or(self.rest.len());
+        let (bytes, rest) = self.rest.split_at(end);
+        self.rest = rest;
+
+        let slice = Slice {
+            first_line: self.next_line,
+            bytes,
+        };
+        self.next_line += self.lines_per_chunk;
+        Some(slice)
+    }
+}
+
+/// Line numbers and byte ranges of the lines selected from a chunk.
+type Selected = Vec<(usize, Range<usize>)>;
+
+/// Chunks together with the lines of each that were selected, as byte
+/// ranges into it. Mostly just one chunk; small ones are packed together.
+struct Batch<C> {
+    parts: Vec<(C, Selected)>,
+    /// The line numbers the parts take up.
+    covered: Vec<Range<usize>>,
+    lines: usize,
+    bytes: usize,
+}
+
+impl<C: Chunk> Batch<C> {
+    fn new() -> Self {
+        Self {
+            parts: Vec::new(),
+            covered: Vec::new(),
+            lines: 0,
+            bytes: 0,
+        }
+    }
+
+    fn push(&mut self, chunk: C, lines: Selected, covered: Range<usize>) {
+        self.lines += lines.len();
+        self.bytes += chunk.bytes().len();
+        self.parts.push((chunk, lines));
+        self.covered.push(covered);
+    }
+
+    /// Whether another chunk of `bytes` bytes would still fit.
+    fn has_room(&self, bytes: usize) -> bool {
+        self.lines < LINES_PER_CHUNK && self.bytes + bytes <= BATCH_BYTES
+    }
+}
+
+/// What happens to a line longer than the limit.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Oversize {
+    /// Validate it as an empty line.
+    #[default]
+    Skip,
+    /// Validate its first bytes, up to the limit.
+    Truncate,
+}
+
+impl Oversize {
+    /// As `--long-lines` takes it.
+    pub fn name(self) -> &'static str {
+        match self {
+            Oversize::Skip => "skip",
+            Oversize::Truncate => "truncate",
+        }
+    }
+}
+
+impl FromStr for Oversize {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "skip" => Ok(Oversize::Skip),
+            "truncate" => Ok(Oversize::Truncate),
+            other => Err(format!("unknown handling '{}', expected skip or truncate", other)),
+        }
+    }
+}
+
+/// The line length limit, and how many lines went over it.
+#[derive(Debug)]
+pub struct LongLines {
+    pub max: usize,
+    pub oversize: Oversize,
+    seen: AtomicUsize,
+}
+
+impl Default for LongLines {
+    fn default() -> Self {
+        Self::new(MAX_LINE, Oversize::Skip)
+    }
+}
+
+impl LongLines {
+    pub fn new(max: usize, oversize: Oversize) -> Self {
+        Self {
+            max,
+            oversize,
+            seen: AtomicUsize::new(0),
+        }
+    }
+
+    /// Lines over the limit so far.
+    pub fn seen(&self) -> usize {
+        self.seen.load(Ordering::Relaxed)
+    }
+
+    /// What of `line` is validated.
+    fn apply<'a>(&self, line: &'a [u8]) -> &'a [u8] {
+        if line.len() <= self.max {
+            return line;
+        }
+        self.seen.fetch_add(1, Ordering::Relaxed);
+        match self.oversize {
+            Oversize::Skip => &[],
+            // A character cut in two is left out
+            Oversize::Truncate => match std::str::from_utf8(&line[..self.max]) {
+                Err(e) if e.error_len().is_none() => &line[..e.valid_up_to()],
+                _ => &line[..self.max],
+            },
+        }
+    }
+}
+
+/// A valid mnemonic on its way to the writer. Only hits are copied out of
+/// the input.
+#[derive(Clone)]
+pub struct Hit {
+    /// 0-based line index.
+    pub line: usize,
+    pub text: SecretString,
+    pub mnemonic: Mnemonic,
+}
+
+pub struct Pipeline {
+    /// Validator threads.
+    pub validators: usize,
+    /// Chunks queued between two stages.
+    pub queue_depth: usize,
+    /// Hits queued for the writer.
+    pub hit_queue: usize,
+    /// Paces the validator threads, if set.
+    pub throttle: Option<Arc<Throttle>>,
+    /// Keeps a pathological line from stalling a validator, if set.
+    pub long_lines: Option<Arc<LongLines>>,
+    /// Called as the run goes, if set.
+    pub events: Option<Arc<Events>>,
+}
+
+/// What the validators send the writer.
+enum Message {
+    Hit(Hit),
+    /// Lines whose hits have all been sent.
+    Done(Range<usize>),
+}
+
+/// The line before which every line is done, from ranges done in any
+/// order.
+struct Watermark {
+    line: usize,
+    /// Ranges done past `line`, by start.
+    ahead: BTreeMap<usize, usize>,
+}
+
+impl Watermark {
+    fn add(&mut self, range: Range<usize>) {
+        self.ahead.insert(range.start, range.end);
+        while let Some(entry) = self.ahead.first_entry().filter(|entry| *entry.key() <= self.line) {
+            self.line = self.line.max(entry.remove());
+        }
+    }
+}
+
+impl Default for Pipeline {
+    fn default() -> Self {
+        let validators = thread::available_parallelism().map_or(1, |n| n.get());
+        Self::with_validators(validators)
+    }
+}
+
+impl Pipeline {
+    pub fn with_validators(validators: usize) -> Self {
+        let validators = validators.max(1);
+        Self {
+            validators,
+            queue_depth: 2 * validators,
+            hit_queue: HIT_QUEUE,
+            throttle: None,
+            long_lines: Some(Arc::new(LongLines::default())),
+            events: None,
+        }
+    }
+
+    /// Runs lines `range` of `source` through `validate` on the validator
+    /// threads and hands every line it accepts to `write` on the calling
+    /// thread, in no particular order. Every line in `range` is passed to
+    /// `validate` exactly once.
+    ///
+    /// Stops early once `cancel` is cancelled, or when `write` (or
+    /// reading) fails; that error is returned. While it is paused the
+    /// validators wait, and the other stages with them.
+    ///
+    /// Once `cancel` is told to finish, or its deadline passes, no more
+    /// input is read but what was read is validated and written. The line
+    /// reading stopped before is returned then: every line of `range`
+    /// before it went through `validate`, and none after. It's `None` when
+    /// the source ran out or the run was cancelled.
+    pub fn run<C, V, W>(
+        &self,
+        source: impl Iterator<Item = io::Result<C>> + Send,
+        range: Range<usize>,
+        cancel: &CancelToken,
+        validate: V,
+        write: W,
+    ) -> io::Result<Option<usize>>
+    where
+        C: Chunk,
+        V: Fn(usize, &str) -> Option<Mnemonic> + Sync,
+        W: FnMut(Hit) -> io::Result<()>,
+    {
+        self.run_checkpointed(source, range, cancel, validate, write, |_| Ok(()))
+    }
+
+    /// [`run`](Pipeline::run), also calling `checkpoint` on the calling
+    /// thread, between calls to `write`, each time the line before which
+    /// every line is validated and its hits handed to `write` moves on. An
+    /// error from it stops the run like one from `write`.
+    pub fn run_checkpointed<C, V, W, K>(
+        &self,
+        source: impl Iterator<Item = io::Result<C>> + Send,
+        range: Range<usize>,
+        cancel: &CancelToken,
+        validate: V,
+        mut write: W,
+        mut checkpoint: K,
+    ) -> io::Result<Option<usize>>
+    where
+        C: Chunk,
+        V: Fn(usize, &str) -> Option<Mnemonic> + Sync,
+        W: FnMut(Hit) -> io::Result<()>,
+        K: FnMut(usize) -> io::Result<()>,
+    {
+        let (chunk_tx, chunk_rx) = bounded::<C>(self.queue_depth);
+        let (batch_tx, batch_rx) = bounded::<Batch<C>>(self.queue_depth);
+        let (hit_tx, hit_rx) = bounded::<Message>(self.hit_queue);
+        let validate = &validate;
+        let throttle = self.throttle.as_deref();
+        let long_lines = self.long_lines.as_deref();
+        let events = self.events.as_deref();
+        let processed = &AtomicUsize::new(0);
+
+        thread::scope(|s| {
+            let (start, end) = (range.start, range.end);
+            let reader = s.spawn(move || -> io::Result<Option<usize>> {
+                for chunk in source {
+                    if cancel.is_cancelled() {
+                        break;
+                    }
+                    let chunk = chunk?;
+                    if chunk.first_line() >= end {
+                        break;
+                    }
+                    // Where to go on from is only known from the next chunk
+                    if cancel.is_finishing() {
+                        return Ok(Some(chunk.first_line().max(start)));
+                    }
+                    if chunk_tx.send(chunk).is_err() {
+                        break;
+                    }
+                }
+                Ok(None)
+            });
+
+            s.spawn(move || {
+                let mut batch = Batch::new();
+                let mut received = chunk_rx.recv().ok();
+                while let Some(chunk) = received.take() {
+                    let base = chunk.bytes().as_ptr() as usize;
+                    let mut lines = Vec::new();
+                    let mut past_end = false;
+                    for (n, line) in lines::lines(chunk.bytes()).enumerate() {
+                        let i = chunk.first_line() + n;
+                        if i >= range.end {
+                            past_end = true;
+                            break;
+                        }
+                        if i >= range.start {
+                            let start = line.as_ptr() as usize - base;
+                            lines.push((i, start..start + line.len()));
+                        }
+                    }
+                    if let (Some(&(first, _)), Some(&(last, _))) = (lines.first(), lines.last()) {
+                        batch.push(chunk, lines, first..last + 1);
+                    }
+                    // Only chunks already waiting are packed, so that a
+                    // batch never waits for the reader
+                    if !past_end {
+                        received = chunk_rx.try_recv().ok();
+                    }
+                    let packs = received
+                        .as_ref()
+                        .is_some_and(|next| batch.has_room(next.bytes().len()));
+                    if !packs
+                        && !batch.parts.is_empty()
+                        && batch_tx.send(std::mem::replace(&mut batch, Batch::new())).is_err()
+                    {
+                        break;
+                    }
+                    if received.is_none() && !past_end {
+                        received = chunk_rx.recv().ok();
+                    }
+                }
+            });
+
+            for _ in 0..self.validators {
+                let batch_rx = batch_rx.clone();
+                let hit_tx = hit_tx.clone();
+                s.spawn(move || {
+                    let mut pacer = throttle.map(Throttle::pacer);
+                    for batch in batch_rx {
+                        if !cancel.proceed() {
+                            return;
+                        }
+                        let started = Instant::now();
+                        let selected = batch.parts.iter().flat_map(|(chunk, lines)| {
+                            lines.iter().map(move |(i, range)| (*i, &chunk.bytes()[range.clone()]))
+                        });
+                        for (i, line) in selected {
+                            let line = lines::normalize(line);
+                            let line = long_lines.map_or(line, |long_lines| long_lines.apply(line));
+                            // Invalid UTF-8 can't be a mnemonic; `validate`
+                            // still sees the line, as an empty one
+                            let Ok(line) = std::str::from_utf8(line) else {
+                                validate(i, "");
+                                continue;
+                            };
+                            if let Some(mnemonic) = validate(i, line) {
+                                let hit = Hit {
+                                    line: i,
+                                    text: SecretString::new(line.to_string()),
+                                    mnemonic,
+                                };
+                                if hit_tx.send(Message::Hit(hit)).is_err() {
+                                    return;
+                                }
+                            }
+                        }
+                        processed.fetch_add(batch.lines, Ordering::Relaxed);
+                        for covered in batch.covered {
+                            if hit_tx.send(Message::Done(covered)).is_err() {
+                                return;
+                            }
+                        }
+                        if let Some(pacer) = &mut pacer {
+                            pacer.worked(started.elapsed());
+                        }
+                    }
+                });
+            }
+            drop(batch_rx);
+            drop(hit_tx);
+
+            // Dropping the receiver on error unblocks and ends every stage
+            let written = (move || -> io::Result<()> {
+                let started = Instant::now();
+                let mut estimator = EtaEstimator::new(started, 0);
+                let mut done = Watermark {
+                    line: start,
+                    ahead: BTreeMap::new(),
+                };
+                let (mut valid, mut reported, mut reported_line) = (0, started, start);
+                let mut report = |done: &Watermark, valid: usize| {
+                    let Some(events) = events else {
+                        return;
+                    };
+                    let now = Instant::now();
+                    let processed = processed.load(Ordering::Relaxed);
+                    estimator.update(now, processed);
+                    let total = (end < usize::MAX).then(|| end - start);
+                    events.progress(&Progress {
+                        processed,
+                        valid,
+                        total,
+                        elapsed: now.duration_since(started),
+                        eta: total.and_then(|total| estimator.estimate(total.saturating_sub(processed))),
+                    });
+                    if done.line > reported_line {
+                        reported_line = done.line;
+                        events.checkpoint(reported_line);
+                    }
+                };
+                loop {
+                    let message = match events {
+                        Some(events) => {
+                            let wait = events.progress_interval.saturating_sub(reported.elapsed());
+                            match hit_rx.recv_timeout(wait) {
+                                Ok(message) => Some(message),
+                                Err(RecvTimeoutError::Timeout) => None,
+                                Err(RecvTimeoutError::Disconnected) => break,
+                            }
+                        }
+                        None => match hit_rx.recv() {
+                            Ok(message) => Some(message),
+                            Err(_) => break,
+                        },
+                    };
+                    match message {
+                        Some(Message::Hit(hit)) => {
+                            let copy = events.filter(|events| events.wants_valid()).map(|_| hit.clone());
+                            write(hit)?;
+                            valid += 1;
+                            if let (Some(events), Some(hit)) = (events, copy) {
+                                events.valid(&hit);
+                            }
+                        }
+                        Some(Message::Done(range)) => {
+                            let before = done.line;
+                            done.add(range);
+                            if done.line > before {
+                                checkpoint(done.line)?;
+                            }
+                        }
+                        None => {}
+                    }
+                    if events.is_some_and(|events| reported.elapsed() >= events.progress_interval) {
+                        report(&done, valid);
+                        reported = Instant::now();
+                    }
+                }
+                report(&done, valid);
+                Ok(())
+            })();
+            let read = reader.join().expect("reader thread panicked");
+            if let (Some(events), Err(e)) = (events, written.as_ref().and(read.as_ref())) {
+                events.error(e);
+            }
+            // Cancelled while finishing, the lines before may not all be done
+            written.and(read).map(|stopped| stopped.filter(|_| !cancel.is_cancelled()))
+        })
+    }
+}
@@ -0,0 +1,81 @@
+//! Cheap rejection of lines that can't possibly be a valid mnemonic.
+//!
+//! Full parsing splits and counts every token of a line, looks up each word
+//! and hashes the entropy. In noisy dumps almost every line fails on the
+//! first token or the word count, so those are checked first: the token
+//! count is bounded (it stops counting past 24), and the first two tokens
+//! are looked up in the perfect-hash wordlist map.
+//!
+//! Lines that pass are split as bytes by [`tokens::words`], and [`parse`]
+//! checks the checksum of an ASCII line straight from the word indices
+//! ([`entropy`]), so only the phrases that hold up are handed to `bip39`.
+
+use crate::{tokens, wordlist};
+use bip39::{Language, Mnemonic};
+use sha2::{Digest, Sha256};
+
+const MAX_WORDS: usize = 24;
+
+/// Returns false for lines that [`bip39::Mnemonic::parse_in_normalized`]
+/// would certainly reject; true means the line still needs a full parse.
+#[inline]
+pub fn may_be_valid(line: &str) -> bool {
+    let mut tokens = tokens::words(line);
+
+    for _ in 0..2 {
+        match tokens.next() {
+            Some(word) if wordlist::is_english_word(word) => {}
+            _ => return false,
+        }
+    }
+
+    let mut count = 2;
+    for _ in tokens {
+        count += 1;
+        if count > MAX_WORDS {
+            return false;
+        }
+    }
+    matches!(count, 12 | 15 | 18 | 21 | 24)
+}
+
+/// Parses `line` as an English mnemonic, as
+/// [`bip39::Mnemonic::parse_in_normalized`] would.
+#[inline]
+pub fn parse(line: &str) -> Option<Mnemonic> {
+    if !may_be_valid(line) {
+        return None;
+    }
+    if !line.is_ascii() {
+        return Mnemonic::parse_in_normalized(Language::English, line).ok();
+    }
+    // At most MAX_WORDS of them, as checked above
+    let mut indices = [0u16; MAX_WORDS];
+    let mut count = 0;
+    for word in tokens::words(line) {
+        indices[count] = wordlist::index_in(word, Language::English)?;
+        count += 1;
+    }
+    let (bits, len) = entropy(&indices[..count])?;
+    Mnemonic::from_entropy_in(Language::English, &bits[..len]).ok()
+}
+
+/// The entropy of the words with these indices (12 to 24 of them, a
+/// multiple of 3), if their checksum is valid: its bytes and how many
+/// there are.
+pub fn entropy(indices: &[u16]) -> Option<([u8; 33], usize)> {
+    let mut bits = [0u8; 33];
+    for (n, &index) in indices.iter().enumerate() {
+        for bit in 0..11 {
+            if index & (1 << (10 - bit)) != 0 {
+                let at = n * 11 + bit;
+                bits[at / 8] |= 1 << (7 - at % 8);
+            }
+        }
+    }
+    // 32 bits of entropy per 3 words, then a checksum bit per 3 words
+    let len = indices.len() / 3 * 4;
+    let checksum = indices.len() / 3;
+    let expected = Sha256::digest(&bits[..len])[0] >> (8 - checksum);
+    (expected == bits[len] >> (8 - checksum)).then_some((bits, len))
+}
@@ -0,0 +1,498 @@
+//! A work queue shared by a fleet of workers: a Redis list, or an Amazon
+//! SQS queue.
+//!
+//! On Redis, a producer pushes messages onto the list (`LPUSH`), and each
+//! worker moves one at a time onto a processing list of its own (`BLMOVE`),
+//! so a message is never handed to two workers, and one taken by a worker
+//! that dies is still there: the worker puts its leftovers back when it
+//! starts again. A finished message is acknowledged by pushing its result
+//! onto `<list>:results` and removing it from the processing list. The
+//! commands need Redis 6.2 or later (or a server that speaks the same, such
+//! as Valkey or KeyDB), spoken by the `redis` crate.
+//!
+//! On SQS, a message taken is hidden from the other workers for
+//! [`VISIBILITY`], which the worker keeps extending while it works on it;
+//! one taken by a worker that dies comes back once that runs out. A finished
+//! message is acknowledged by sending its result to the queue named like it
+//! with `-results` added, and deleting it. Requests go over HTTPS in SQS's
+//! JSON protocol, signed with AWS Signature Version 4.
+
+use crate::digest::to_hex;
+use hmac::{Hmac, Mac};
+use redis::{ConnectionAddr, ConnectionInfo, RedisConnectionInfo};
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use std::{
+    fmt,
+    io::{self, Read},
+    time::Duration,
+};
+
+/// A reply can take this much longer than the timeout of the command
+/// waiting for it before the connection is given up for lost.
+const REPLY_SLACK: Duration = Duration::from_secs(30);
+/// How long an SQS message taken stays hidden from other workers unless
+/// it's kept for longer.
+pub const VISIBILITY: Duration = Duration::from_secs(300);
+/// How often a worker keeps the message it's working on: well within
+/// [`VISIBILITY`].
+pub const KEEP_INTERVAL: Duration = Duration::from_secs(60);
+/// Longest SQS reply read.
+const MAX_REPLY: u64 = 1 << 20;
+
+/// Where the queue is.
+#[derive(Clone)]
+pub enum QueueUrl {
+    /// `redis://[user:password@]host[:port]/LIST`
+    Redis {
+        host: String,
+        port: u16,
+        user: Option<String>,
+        password: Option<String>,
+        list: String,
+    },
+    /// `https://sqs.REGION.amazonaws.com/ACCOUNT/QUEUE`, as SQS gives it.
+    Sqs {
+        /// `https://host[:port]`, where requests are sent.
+        endpoint: String,
+        /// `host[:port]`, as signed.
+        host: String,
+        region: String,
+        url: String,
+    },
+}
+
+// The password stays out of messages and logs
+impl fmt::Display for QueueUrl {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Redis { host, port, list, .. } => write!(f, "redis://{}:{}/{}", host, port, list),
+            Self::Sqs { url, .. } => f.write_str(url),
+        }
+    }
+}
+
+impl fmt::Debug for QueueUrl {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+/// Parses a queue URL. A Redis password may be left out of it and given in
+/// `REDIS_PASSWORD` instead, where other users' `ps` don't see it. An SQS
+/// queue's region is taken from its host, or from `AWS_REGION` for other
+/// hosts (a VPC endpoint, or a local stand-in).
+pub fn parse_url(s: &str) -> Result<QueueUrl, String> {
+    match s.split_once("://") {
+        Some(("redis", rest)) => parse_redis(s, rest),
+        Some(("https" | "http", rest)) => parse_sqs(s, rest),
+        _ => Err(format!(
+            "invalid queue '{}', expected redis://host[:port]/LIST or https://sqs.REGION.amazonaws.com/ACCOUNT/QUEUE",
+            s
+        )),
+    }
+}
+
+fn parse_redis(s: &str, rest: &str) -> Result<QueueUrl, String> {
+    let (authority, list) = rest
+        .split_once('/')
+        .filter(|(_, list)| !list.is_empty())
+        .ok_or_else(|| format!("queue '{}' names no list, expected redis://host[:port]/LIST", s))?;
+    let (userinfo, address) = match authority.rsplit_once('@') {
+        Some((userinfo, address)) => (Some(userinfo), address),
+        None => (None, authority),
+    };
+    let (user, password) = match userinfo.map(|userinfo| userinfo.split_once(':')) {
+        Some(Some((user, password))) => ((!user.is_empty()).then(|| user.to_string()), Some(password.to_string())),
+        Some(None) => (None, userinfo.map(String::from)),
+        None => (None, None),
+    };
+    let (host, port) = match address.rsplit_once(':') {
+        Some((host, port)) => (host, port.parse().map_err(|_| format!("invalid port in queue '{}'", s))?),
+        None => (address, 6379),
+    };
+    if host.is_empty() {
+        return Err(format!("queue '{}' names no host", s));
+    }
+    Ok(QueueUrl::Redis {
+        host: host.trim_start_matches('[').trim_end_matches(']').to_string(),
+        port,
+        user,
+        password: password.or_else(|| std::env::var("REDIS_PASSWORD").ok()),
+        list: list.to_string(),
+    })
+}
+
+fn parse_sqs(s: &str, rest: &str) -> Result<QueueUrl, String> {
+    let (host, path) = rest
+        .split_once('/')
+        .ok_or_else(|| format!("queue '{}' names no queue, expected https://sqs.REGION.amazonaws.com/ACCOUNT/QUEUE", s))?;
+    let path = path.trim_end_matches('/');
+    match path.split('/').collect::<Vec<_>>()[..] {
+        [account, name] if !account.is_empty() && !name.is_empty() => {
+            if name.ends_with(".fifo") {
+                return Err(format!("queue '{}' is a FIFO queue; use a standard one", s));
+            }
+        }
+        _ => return Err(format!("queue '{}' isn't an SQS queue URL, expected https://sqs.REGION.amazonaws.com/ACCOUNT/QUEUE", s)),
+    }
+    let name = host.split(':').next().unwrap_or_default();
+    let region = match name.strip_prefix("sqs.").and_then(|rest| rest.split_once('.')) {
+        Some((region, "amazonaws.com" | "amazonaws.com.cn")) => region.to_string(),
+        _ => std::env::var("AWS_REGION")
+            .or_else(|_| std::env::var("AWS_DEFAULT_REGION"))
+            .map_err(|_| format!("no region in queue '{}'; set AWS_REGION", s))?,
+    };
+    let scheme = &s[..s.len() - rest.len()];
+    Ok(QueueUrl::Sqs {
+        endpoint: format!("{}{}", scheme, host),
+        host: host.to_string(),
+        region,
+        url: s.trim_end_matches('/').to_string(),
+    })
+}
+
+/// A message taken from the queue, to be acknowledged or released.
+pub struct Taken {
+    pub body: Vec<u8>,
+    /// SQS's handle of it, to delete it or keep it hidden.
+    receipt: Option<String>,
+}
+
+/// The queue as one worker sees it.
+pub enum WorkQueue {
+    Redis {
+        conn: redis::Connection,
+        list: String,
+        processing: String,
+        results: String,
+    },
+    Sqs(Sqs),
+}
+
+impl WorkQueue {
+    /// Connects as `worker`, whose processing list on Redis is
+    /// `<list>:processing:<worker>`.
+    pub fn open(url: &QueueUrl, worker: &str) -> io::Result<Self> {
+        match url {
+            QueueUrl::Redis {
+                host,
+                port,
+                user,
+                password,
+                list,
+            } => {
+                let info = ConnectionInfo {
+                    addr: ConnectionAddr::Tcp(host.clone(), *port),
+                    redis: RedisConnectionInfo {
+                        username: user.clone(),
+                        password: password.clone(),
+                        ..RedisConnectionInfo::default()
+                    },
+                };
+                let client = redis::Client::open(info).map_err(redis_error)?;
+                let conn = client.get_connection_with_timeout(REPLY_SLACK).map_err(redis_error)?;
+                Ok(Self::Redis {
+                    conn,
+                    list: list.clone(),
+                    processing: format!("{}:processing:{}", list, worker),
+                    results: format!("{}:results", list),
+                })
+            }
+            QueueUrl::Sqs {
+                endpoint,
+                host,
+                region,
+                url,
+            } => Ok(Self::Sqs(Sqs {
+                endpoint: endpoint.clone(),
+                host: host.clone(),
+                region: region.clone(),
+                url: url.clone(),
+                results: format!("{}-results", url),
+                credentials: Credentials::from_env()?,
+            })),
+        }
+    }
+
+    /// Puts back the messages a previous run of this worker took and didn't
+    /// finish, to be taken next; returns how many there were. On SQS they
+    /// come back by themselves once they're no longer hidden.
+    pub fn recover(&mut self) -> io::Result<usize> {
+        let Self::Redis {
+            conn, list, processing, ..
+        } = self
+        else {
+            return Ok(0);
+        };
+        let mut recovered = 0;
+        while redis::cmd("LMOVE")
+            .arg(&*processing)
+            .arg(&*list)
+            .arg("LEFT")
+            .arg("RIGHT")
+            .query::<Option<Vec<u8>>>(conn)
+            .map_err(redis_error)?
+            .is_some()
+        {
+            recovered += 1;
+        }
+        Ok(recovered)
+    }
+
+    /// Waits up to `timeout` for a message and takes it: onto this worker's
+    /// processing list, or hidden from the others.
+    pub fn take(&mut self, timeout: Duration) -> io::Result<Option<Taken>> {
+        match self {
+            Self::Redis {
+                conn, list, processing, ..
+            } => {
+                conn.set_read_timeout(Some(timeout + REPLY_SLACK)).map_err(redis_error)?;
+                let body = redis::cmd("BLMOVE")
+                    .arg(&*list)
+                    .arg(&*processing)
+                    .arg("RIGHT")
+                    .arg("LEFT")
+                    .arg(timeout.as_secs_f64())
+                    .query::<Option<Vec<u8>>>(conn)
+                    .map_err(redis_error)?;
+                Ok(body.map(|body| Taken { body, receipt: None }))
+            }
+            Self::Sqs(sqs) => sqs.receive(timeout),
+        }
+    }
+
+    /// Keeps `taken` from other workers for a while longer, while it's
+    /// being worked on.
+    pub fn keep(&mut self, taken: &Taken) -> io::Result<()> {
+        match (self, &taken.receipt) {
+            (Self::Sqs(sqs), Some(receipt)) => sqs.hide(receipt, VISIBILITY),
+            _ => Ok(()),
+        }
+    }
+
+    /// Sends the `result` of `taken` to the results and drops it from the
+    /// queue.
+    pub fn ack(&mut self, taken: &Taken, result: &[u8]) -> io::Result<()> {
+        match self {
+            Self::Redis {
+                conn,
+                processing,
+                results,
+                ..
+            } => {
+                redis::cmd("LPUSH").arg(&*results).arg(result).query::<i64>(conn).map_err(redis_error)?;
+                redis::cmd("LREM")
+                    .arg(&*processing)
+                    .arg(1)
+                    .arg(&taken.body)
+                    .query::<i64>(conn)
+                    .map_err(redis_error)?;
+                Ok(())
+            }
+            Self::Sqs(sqs) => {
+                let result = String::from_utf8_lossy(result);
+                sqs.call("SendMessage", json!({ "QueueUrl": sqs.results, "MessageBody": result }))?;
+                let receipt = taken.receipt.as_deref().unwrap_or_default();
+                sqs.call("DeleteMessage", json!({ "QueueUrl": sqs.url, "ReceiptHandle": receipt }))?;
+                Ok(())
+            }
+        }
+    }
+
+    /// Puts `taken` back to be taken next, by this worker or another.
+    pub fn release(&mut self, taken: &Taken) -> io::Result<()> {
+        match self {
+            Self::Redis {
+                conn, list, processing, ..
+            } => {
+                redis::cmd("RPUSH").arg(&*list).arg(&taken.body).query::<i64>(conn).map_err(redis_error)?;
+                redis::cmd("LREM")
+                    .arg(&*processing)
+                    .arg(1)
+                    .arg(&taken.body)
+                    .query::<i64>(conn)
+                    .map_err(redis_error)?;
+                Ok(())
+            }
+            Self::Sqs(sqs) => sqs.hide(taken.receipt.as_deref().unwrap_or_default(), Duration::ZERO),
+        }
+    }
+}
+
+fn redis_error(e: redis::RedisError) -> io::Error {
+    io::Error::other(format!("the queue server says: {}", e))
+}
+
+/// AWS credentials, from `AWS_ACCESS_KEY_ID`, `AWS_SECRET_ACCESS_KEY` and,
+/// for temporary ones, `AWS_SESSION_TOKEN`.
+pub struct Credentials {
+    pub access_key: String,
+    pub secret_key: String,
+    pub session_token: Option<String>,
+}
+
+impl Credentials {
+    fn from_env() -> io::Result<Self> {
+        match (std::env::var("AWS_ACCESS_KEY_ID"), std::env::var("AWS_SECRET_ACCESS_KEY")) {
+            (Ok(access_key), Ok(secret_key)) => Ok(Self {
+                access_key,
+                secret_key,
+                session_token: std::env::var("AWS_SESSION_TOKEN").ok(),
+            }),
+            _ => Err(io::Error::other("no AWS credentials: set AWS_ACCESS_KEY_ID and AWS_SECRET_ACCESS_KEY")),
+        }
+    }
+}
+
+/// An SQS queue and the one its results go to.
+pub struct Sqs {
+    endpoint: String,
+    host: String,
+    region: String,
+    url: String,
+    results: String,
+    credentials: Credentials,
+}
+
+impl Sqs {
+    fn receive(&self, timeout: Duration) -> io::Result<Option<Taken>> {
+        let reply = self.call(
+            "ReceiveMessage",
+            json!({
+                "QueueUrl": self.url,
+                "MaxNumberOfMessages": 1,
+                "WaitTimeSeconds": timeout.as_secs().clamp(1, 20),
+                "VisibilityTimeout": VISIBILITY.as_secs(),
+            }),
+        )?;
+        let Some(message) = reply["Messages"].get(0) else {
+            return Ok(None);
+        };
+        match (message["Body"].as_str(), message["ReceiptHandle"].as_str()) {
+            (Some(body), Some(receipt)) => Ok(Some(Taken {
+                body: body.as_bytes().to_vec(),
+                receipt: Some(receipt.to_string()),
+            })),
+            _ => Err(io::Error::new(io::ErrorKind::InvalidData, "SQS sent a message without a body or handle")),
+        }
+    }
+
+    fn hide(&self, receipt: &str, time: Duration) -> io::Result<()> {
+        self.call(
+            "ChangeMessageVisibility",
+            json!({ "QueueUrl": self.url, "ReceiptHandle": receipt, "VisibilityTimeout": time.as_secs() }),
+        )
+        .map(drop)
+    }
+
+    /// Makes the call `action` with `body`, and returns the reply.
+    fn call(&self, action: &str, body: serde_json::Value) -> io::Result<serde_json::Value> {
+        let body = body.to_string();
+        let time = chrono::Utc::now().format("%Y%m%dT%H%M%SZ").to_string();
+        let target = format!("AmazonSQS.{}", action);
+        let mut headers = vec![
+            ("content-type", "application/x-amz-json-1.0"),
+            ("host", self.host.as_str()),
+            ("x-amz-date", time.as_str()),
+            ("x-amz-target", target.as_str()),
+        ];
+        if let Some(token) = &self.credentials.session_token {
+            headers.push(("x-amz-security-token", token));
+        }
+        let signing = Signing {
+            region: &self.region,
+            service: "sqs",
+            time: &time,
+            method: "POST",
+            headers: &headers,
+            body: body.as_bytes(),
+        };
+        let mut request = ureq::post(&format!("{}/", self.endpoint)).timeout(REPLY_SLACK + Duration::from_secs(20));
+        for &(name, value) in headers.iter().filter(|(name, _)| *name != "host") {
+            request = request.set(name, value);
+        }
+        request = request.set("authorization", &signing.authorization(&self.credentials));
+        let response = match request.send_bytes(body.as_bytes()) {
+            Ok(response) | Err(ureq::Error::Status(_, response)) => response,
+            Err(ureq::Error::Transport(e)) => return Err(io::Error::other(e)),
+        };
+        let status = response.status();
+        let mut reply = Vec::new();
+        response.into_reader().take(MAX_REPLY).read_to_end(&mut reply)?;
+        let reply: serde_json::Value = serde_json::from_slice(&reply).unwrap_or_default();
+        match status {
+            200 => Ok(reply),
+            _ => Err(io::Error::other(format!(
+                "SQS says {}: {}",
+                reply["__type"].as_str().unwrap_or(&status.to_string()),
+                reply["message"].as_str().unwrap_or("no reason given")
+            ))),
+        }
+    }
+}
+
+/// What AWS Signature Version 4 signs of a request to `/` with no query:
+/// `headers` are all the headers signed, with lowercase names, `host` and
+/// `x-amz-date` (`time`, as `YYYYMMDDTHHMMSSZ`) among them.
+pub struct Signing<'a> {
+    pub region: &'a str,
+    pub service: &'a str,
+    pub time: &'a str,
+    pub method: &'a str,
+    pub headers: &'a [(&'a str, &'a str)],
+    pub body: &'a [u8],
+}
+
+impl Signing<'_> {
+    fn scope(&self) -> String {
+        format!("{}/{}/{}/aws4_request", &self.time[..8], self.region, self.service)
+    }
+
+    fn signed_headers(&self) -> String {
+        let mut names: Vec<&str> = self.headers.iter().map(|(name, _)| *name).collect();
+        names.sort_unstable();
+        names.join(";")
+    }
+
+    /// The signature, in hex.
+    pub fn signature(&self, secret_key: &str) -> String {
+        let mut headers = self.headers.to_vec();
+        headers.sort_unstable();
+        let mut canonical = format!("{}\n/\n\n", self.method);
+        for (name, value) in headers {
+            // Runs of spaces count as one
+            let value = value.split(' ').filter(|part| !part.is_empty()).collect::<Vec<_>>().join(" ");
+            canonical.push_str(&format!("{}:{}\n", name, value));
+        }
+        canonical.push_str(&format!("\n{}\n{}", self.signed_headers(), to_hex(&Sha256::digest(self.body))));
+        let to_sign = format!(
+            "AWS4-HMAC-SHA256\n{}\n{}\n{}",
+            self.time,
+            self.scope(),
+            to_hex(&Sha256::digest(canonical))
+        );
+        let mut key = hmac_sha256(format!("AWS4{}", secret_key).as_bytes(), &self.time.as_bytes()[..8]);
+        for part in [self.region, self.service, "aws4_request"] {
+            key = hmac_sha256(&key, part.as_bytes());
+        }
+        to_hex(&hmac_sha256(&key, to_sign.as_bytes()))
+    }
+
+    /// The `Authorization` header.
+    pub fn authorization(&self, credentials: &Credentials) -> String {
+        format!(
+            "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}",
+            credentials.access_key,
+            self.scope(),
+            self.signed_headers(),
+            self.signature(&credentials.secret_key)
+        )
+    }
+}
+
+fn hmac_sha256(key: &[u8], data: &[u8]) -> [u8; 32] {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC takes any key length");
+    mac.update(data);
+    mac.finalize().into_bytes().into()
+}
@@ -0,0 +1,434 @@
+//! `mnemonic_validator recover`: searching for a phrase of which some words
+//! are missing or uncertain (see [`recovery`]).
+//!
+//! Every candidate with a valid checksum is written to the output, unless
+//! addresses the wallet used are given: then only the phrase (and the
+//! passphrase) that gives one of them is, and the search stops there.
+//!
+//! As it goes, the candidate to resume from is saved to a checkpoint with a
+//! digest of the search, so the same command run again after a stop or a
+//! reboot picks up there; a checkpoint of another search is refused. After
+//! a crash, phrases found just before it may be written again.
+
+use crate::console::{self, Progress};
+use clap::Args;
+use mnemonic_validator::{
+    digest,
+    address::Targets,
+    cancel::CancelToken,
+    checkpoint::write_atomic,
+    eta::{self, EtaEstimator},
+    lines,
+    network::{self, Network},
+    paths,
+    recovery::{self, Confirm, Constraint, Found, Priorities, Searched, Template},
+    secret::{SecretString, Zeroizing},
+    wallet::{self, Chain, Scan},
+};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::{
+    fs::{self, OpenOptions},
+    io::{Read, Write},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+/// Format of the checkpoints written by this version.
+const CHECKPOINT_VERSION: u32 = 1;
+
+#[derive(Args, Debug)]
+pub struct RecoverArgs {
+    /// What's known of the phrase, a token per word: the word, ? for a missing one, a pattern such as app* or ca?e, word~ for that word or one like it, {a,b,c} for one of those. Quote it.
+    #[clap(value_name = "TEMPLATE", value_parser = Template::parse, required_unless_present = "words")]
+    template: Option<Template>,
+
+    /// Number of words of the phrase, none of them known but for the --constraints, instead of a template.
+    #[clap(long, value_name = "N", conflicts_with = "template", value_parser = Template::unknown_parser)]
+    words: Option<Template>,
+
+    /// What's remembered of a word: pos3=app*, pos7∈{river,rival}, first!=abandon, last=? (see the README); may be given more than once.
+    #[clap(short, long = "constraint", value_name = "CONSTRAINT", value_parser = recovery::parse_constraint)]
+    constraints: Vec<Constraint>,
+
+    /// Addresses the wallet is known to have used, one per line; only the phrase giving one of them is kept.
+    #[clap(long, value_name = "FILE", value_parser = paths::parser())]
+    targets: Option<PathBuf>,
+
+    /// Candidate passphrases to try with --targets, one per line (an empty line is the empty passphrase); only the empty one if left out.
+    #[clap(long, value_name = "FILE", value_parser = paths::parser(), requires = "targets")]
+    passphrase_file: Option<PathBuf>,
+
+    /// Network of the target addresses and derivation paths: mainnet, testnet, signet, regtest, ethereum, or one of the coin registry.
+    #[clap(long, value_name = "NETWORK", default_value = "mainnet", value_parser = network::parse_network)]
+    network: Network,
+
+    /// Stop walking a chain after this many addresses in a row that aren't targets.
+    #[clap(long, value_name = "N", default_value_t = wallet::DEFAULT_GAP_LIMIT, value_parser = clap::value_parser!(u32).range(1..))]
+    gap_limit: u32,
+
+    /// File to append the phrases found to.
+    #[clap(short, long, value_name = "FILE", default_value = "output/recovered.txt", value_parser = paths::parser())]
+    output: PathBuf,
+
+    /// Number of threads (default: one per CPU).
+    #[clap(long, value_name = "N", value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..))]
+    threads: Option<usize>,
+
+    /// Start the search even if it's estimated to take more than an hour.
+    #[clap(short, long)]
+    yes: bool,
+
+    /// A word, pattern or {set} thought likely: the words it names are tried before the others wherever they may be; may be given more than once, likeliest first.
+    #[clap(long = "hint", value_name = "WORDS")]
+    hints: Vec<String>,
+
+    /// Text to count the list words in, such as notes or phrases used before; the more often a word occurs, the sooner it's tried.
+    #[clap(long, value_name = "FILE", value_parser = paths::parser())]
+    frequencies: Option<PathBuf>,
+
+    /// End the search at the first phrase with a valid checksum, the likeliest going by the order words are tried in; the same as --stop-after 1.
+    #[clap(long, conflicts_with = "stop_after")]
+    stop_at_first: bool,
+
+    /// End the search once it has found this many phrases with a valid checksum, saving the checkpoint so the same command goes on to the next ones (with --targets, it ends at the one confirmed anyway).
+    #[clap(long, value_name = "N", value_parser = clap::value_parser!(u64).range(1..))]
+    stop_after: Option<u64>,
+
+    /// Where to keep the candidate to resume from (default: the output's path with .checkpoint appended).
+    #[clap(long, value_name = "FILE", value_parser = paths::parser())]
+    checkpoint: Option<PathBuf>,
+
+    /// Ignore any saved checkpoint and start from the first candidate.
+    #[clap(long)]
+    restart: bool,
+}
+
+/// A search, as `recover` and the wizard set it up.
+pub struct Plan {
+    pub template: Template,
+    pub confirm: Option<Confirm>,
+    pub output: PathBuf,
+    pub threads: usize,
+    pub checkpoint: PathBuf,
+    /// Start from the first candidate whatever the checkpoint says.
+    pub restart: bool,
+    /// End the search after this many phrases with a valid checksum, even
+    /// with nothing to confirm them.
+    pub stop_after: Option<u64>,
+}
+
+impl Plan {
+    /// What a checkpoint must have been saved by to be resumed: the
+    /// template and what confirms a phrase, hashed.
+    fn digest(&self) -> String {
+        let mut hasher = Sha256::new();
+        for slot in self.template.slots() {
+            hasher.update(b"\0");
+            for index in slot {
+                hasher.update(index.to_be_bytes());
+            }
+        }
+        if let Some(confirm) = &self.confirm {
+            hasher.update(format!("\0{}\0{}\0{}", confirm.targets.len(), confirm.scan.network.name(), confirm.scan.gap_limit));
+            for passphrase in &confirm.passphrases {
+                hasher.update(b"\0");
+                hasher.update(passphrase.as_bytes());
+            }
+        }
+        digest::to_hex(&hasher.finalize())
+    }
+}
+
+/// Where a search got to.
+#[derive(Serialize, Deserialize)]
+struct Checkpoint {
+    version: u32,
+    /// [`Plan::digest`] of the search.
+    search: String,
+    /// Candidates before this one are done.
+    next: u64,
+    /// Candidates past `next` whose phrases were written already.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    written: Vec<u64>,
+}
+
+pub fn default_checkpoint(output: &Path) -> PathBuf {
+    let mut name = output.as_os_str().to_os_string();
+    name.push(".checkpoint");
+    PathBuf::from(name)
+}
+
+/// Saves that the search of `plan` is done up to `next`, and the
+/// candidates among `written` past it.
+fn save_checkpoint(plan: &Plan, next: u128, written: &[u128]) -> Result<(), String> {
+    let checkpoint = Checkpoint {
+        version: CHECKPOINT_VERSION,
+        search: plan.digest(),
+        next: next as u64,
+        written: written.iter().filter(|&&n| n >= next).map(|&n| n as u64).collect(),
+    };
+    let mut json = serde_json::to_string(&checkpoint).map_err(|e| e.to_string())?;
+    json.push('\n');
+    write_atomic(&plan.checkpoint, json.as_bytes())
+        .map_err(|e| format!("can't save the checkpoint {}: {}", plan.checkpoint.display(), e))
+}
+
+/// The candidate to resume `plan` from, and those past it written already.
+fn resume_at(plan: &Plan, count: u128) -> Result<(u128, Vec<u128>), String> {
+    if plan.restart || !plan.checkpoint.exists() {
+        return Ok((0, Vec::new()));
+    }
+    let path = plan.checkpoint.display();
+    let text = fs::read_to_string(&plan.checkpoint).map_err(|e| format!("can't read the checkpoint {}: {}", path, e))?;
+    let checkpoint: Checkpoint = serde_json::from_str(&text).map_err(|_| format!("the checkpoint {} is damaged", path))?;
+    if checkpoint.version > CHECKPOINT_VERSION {
+        return Err(format!("the checkpoint {} was written by a newer version of this tool", path));
+    }
+    if checkpoint.search != plan.digest() {
+        return Err(format!(
+            "the checkpoint {} is of another search; remove it or give --restart to start this one over, or --checkpoint to keep both",
+            path
+        ));
+    }
+    let written = checkpoint.written.into_iter().map(u128::from).collect();
+    Ok((u128::from(checkpoint.next).min(count), written))
+}
+
+fn read_lines(path: &Path) -> Result<Vec<SecretString>, Box<dyn std::error::Error>> {
+    let mut text = Zeroizing::new(Vec::new());
+    fs::File::open(path)
+        .and_then(|mut file| file.read_to_end(&mut text))
+        .map_err(|e| format!("can't read {}: {}", path.display(), e))?;
+    Ok(lines::lines(&text)
+        .filter_map(|line| std::str::from_utf8(line).ok())
+        .map(|line| SecretString::new(line.to_string()))
+        .collect())
+}
+
+pub fn default_threads() -> usize {
+    thread::available_parallelism().map_or(1, |n| n.get())
+}
+
+pub fn run(args: &RecoverArgs) -> Result<(), Box<dyn std::error::Error>> {
+    let confirm = match &args.targets {
+        Some(path) => {
+            let targets = Targets::parse(&fs::read_to_string(path)?, args.network)
+                .map_err(|e| format!("{}: {}", path.display(), e))?;
+            if targets.is_empty() {
+                return Err(format!("no addresses in {}", path.display()).into());
+            }
+            let passphrases = match &args.passphrase_file {
+                Some(path) => read_lines(path)?,
+                None => vec![SecretString::new(String::new())],
+            };
+            Some(Confirm {
+                targets,
+                passphrases,
+                scan: Scan {
+                    chains: vec![Chain::External],
+                    gap_limit: args.gap_limit,
+                    network: args.network,
+                },
+            })
+        }
+        None => None,
+    };
+    let mut template = args.template.clone().or_else(|| args.words.clone()).ok_or("give a template or --words")?;
+    for constraint in &args.constraints {
+        template.constrain(constraint)?;
+    }
+    let mut priorities = Priorities::default();
+    for hint in &args.hints {
+        priorities.hint(hint).map_err(|e| format!("--hint {}: {}", hint, e))?;
+    }
+    if let Some(path) = &args.frequencies {
+        priorities.count_frequencies(&fs::read_to_string(path).map_err(|e| format!("can't read {}: {}", path.display(), e))?);
+    }
+    template.prioritize(&priorities);
+    let plan = Plan {
+        template,
+        confirm,
+        output: args.output.clone(),
+        threads: args.threads.unwrap_or_else(default_threads),
+        checkpoint: args.checkpoint.clone().unwrap_or_else(|| default_checkpoint(&args.output)),
+        restart: args.restart,
+        stop_after: if args.stop_at_first { Some(1) } else { args.stop_after },
+    };
+    let mut estimate = recovery::estimate(&plan.template, plan.threads, plan.confirm.as_ref());
+    if let Some(limit) = plan.stop_after.filter(|_| plan.confirm.is_none()) {
+        estimate = estimate.until(limit);
+    }
+    println!("Estimate: {}", estimate);
+    if estimate.is_long() && estimate.seconds.is_finite() && !args.yes {
+        return Err(format!(
+            "that search would take {}; pin down more of the words, or give --yes to start it anyway",
+            eta::format_span(estimate.seconds)
+        )
+        .into());
+    }
+    execute(&plan)
+}
+
+/// Runs the search of `plan`, with progress, until it's done, a phrase is
+/// confirmed, `stop_after` phrases are found or it's stopped.
+pub fn execute(plan: &Plan) -> Result<(), Box<dyn std::error::Error>> {
+    let Some(count) = plan.template.count().filter(|&count| count <= u64::MAX as u128) else {
+        return Err("that's more candidates than can be searched; pin down more of the words".into());
+    };
+    if let Some(parent) = plan.output.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+        fs::create_dir_all(parent)?;
+    }
+    let output = Mutex::new(
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&plan.output)
+            .map_err(|e| format!("can't open {}: {}", plan.output.display(), e))?,
+    );
+    let (resumed, written_before) = resume_at(plan, count)?;
+    if resumed > 0 {
+        println!("Resuming from candidate {} (checkpoint {}).", resumed, plan.checkpoint.display());
+    }
+    println!(
+        "Searching {} candidates for a {}-word phrase with {} threads{}",
+        count - resumed,
+        plan.template.words(),
+        plan.threads,
+        match &plan.confirm {
+            Some(confirm) => format!(", against {} addresses", confirm.targets.len()),
+            None => String::new(),
+        }
+    );
+
+    let cancel = CancelToken::new();
+    console::on_exit({
+        let cancel = cancel.clone();
+        move |_| cancel.cancel()
+    })?;
+
+    let searched = Searched::new(resumed);
+    let phrases = AtomicU64::new(0);
+    // Candidates written, and the phrase the search ended at
+    let kept: Mutex<(Vec<u128>, Option<Found>)> = Mutex::new((written_before.clone(), None));
+    let written: Mutex<Option<std::io::Error>> = Mutex::new(None);
+    let start = Instant::now();
+    let found = |found: Found| {
+        if written_before.contains(&found.index) {
+            return true;
+        }
+        let (indices, last) = &mut *kept.lock().unwrap();
+        if last.is_some() {
+            // The search has ended; this one is left for the next run
+            return false;
+        }
+        let mut line = SecretString::new(found.mnemonic.to_string());
+        line.push('\n');
+        if let Err(e) = output.lock().unwrap().write_all(line.as_bytes()) {
+            written.lock().unwrap().get_or_insert(e);
+            cancel.cancel();
+            return false;
+        }
+        indices.push(found.index);
+        let phrases = phrases.fetch_add(1, Ordering::Relaxed) + 1;
+        if found.confirmed.is_some() || plan.stop_after.is_some_and(|limit| phrases >= limit) {
+            *last = Some(found);
+            cancel.cancel();
+        }
+        true
+    };
+
+    let reached = thread::scope(|scope| {
+        let search = scope.spawn(|| {
+            recovery::search(&plan.template, resumed..count, plan.threads, plan.confirm.as_ref(), &cancel, &searched, &found)
+        });
+        let mut estimator = EtaEstimator::new(start, 0);
+        let mut saved = Ok(());
+        let mut progress = Progress::new();
+        let mut shown = start;
+        while !search.is_finished() {
+            thread::sleep(Duration::from_millis(100));
+            let now = Instant::now();
+            if now.duration_since(shown) < progress.interval() {
+                continue;
+            }
+            shown = now;
+            if saved.is_ok() {
+                saved = save_checkpoint(plan, searched.resume_at(), &kept.lock().unwrap().0);
+                if saved.is_err() {
+                    cancel.cancel();
+                }
+            }
+            let done = searched.count();
+            estimator.update(now, done as usize);
+            let eta = estimator
+                .estimate((count - resumed) as usize - done as usize)
+                .map_or_else(|| "--:--".to_string(), |eta| eta.to_string());
+            progress.show(&format!(
+                "Searched {}/{} ({:.0}/s), {} with a valid checksum, ETA {}",
+                resumed as u64 + done,
+                count,
+                done as f64 / now.duration_since(start).as_secs_f64(),
+                phrases.load(Ordering::Relaxed),
+                eta
+            ));
+        }
+        progress.finish();
+        saved.map(|_| search.join().unwrap())
+    })?;
+    if let Some(e) = written.into_inner().unwrap() {
+        return Err(format!("can't write {}: {}", plan.output.display(), e).into());
+    }
+    let (indices, last) = kept.into_inner().unwrap();
+    let confirmed = last.as_ref().is_some_and(|found| found.confirmed.is_some());
+    if reached < count && !confirmed {
+        save_checkpoint(plan, reached, &indices)?;
+    } else {
+        let _ = fs::remove_file(&plan.checkpoint);
+    }
+
+    let elapsed = eta::format_duration(start.elapsed());
+    let phrases = phrases.into_inner();
+    let more = format!("run the same command again to continue (checkpoint {})", plan.checkpoint.display());
+    if let Some((passphrase, hit)) = last.as_ref().and_then(|found| found.confirmed.as_ref()) {
+        println!("Found the phrase (in {}), written to {}:", elapsed, plan.output.display());
+        println!("{}", last.as_ref().unwrap().mnemonic);
+        if !passphrase.is_empty() {
+            println!("with the passphrase '{}'", passphrase.as_str());
+        }
+        println!("It gives {} at {}", hit.address, hit.path);
+    } else if let Some(found) = last.as_ref().filter(|_| phrases == 1) {
+        println!("The first phrase with a valid checksum (found in {}), written to {}:", elapsed, plan.output.display());
+        println!("{}", found.mnemonic);
+        if reached < count {
+            println!("For the next one, {}.", more);
+        }
+    } else if last.is_some() {
+        println!(
+            "Found the {} phrases with a valid checksum asked for (in {}), written to {}{}.",
+            phrases,
+            elapsed,
+            plan.output.display(),
+            if reached < count { format!("; for more, {}", more) } else { String::new() }
+        );
+    } else if reached < count {
+        println!("Stopped after {} of {} candidates in {}; {}.", reached, count, elapsed, more);
+    } else if plan.confirm.is_some() {
+        println!("No candidate gives one of the addresses (searched {} in {}).", count, elapsed);
+    } else {
+        println!(
+            "{} of the {} candidates{} have a valid checksum (searched in {}), written to {}. Give an address the wallet used to tell which is yours.",
+            phrases,
+            count - resumed,
+            if resumed > 0 { " left" } else { "" },
+            elapsed,
+            plan.output.display()
+        );
+    }
+    Ok(())
+}
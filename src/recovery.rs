@@ -0,0 +1,560 @@
+//! Searching for a phrase of which only part is remembered: some words
+//! missing, some uncertain (`recover`, and the wizard that sets it up).
+//!
+//! What's known is a [`Template`], one slot per word holding the list words
+//! it may be, which [`Constraint`]s narrow down further and [`Priorities`]
+//! put in order, likeliest first. Every combination is a candidate, numbered
+//! with the last word turning fastest, so a range of numbers is a share of
+//! the search and the first candidates are the likeliest. Most
+//! candidates fail the checksum, which is checked straight from the word
+//! indices as [`prefilter`](crate::prefilter) does; the few that pass can be
+//! confirmed against addresses the wallet is known to have used, under each
+//! candidate passphrase.
+//!
+//! Like [`diagnostics`](crate::diagnostics), it works on the English list.
+
+use crate::{
+    address::Targets,
+    cancel::CancelToken,
+    diagnostics, eta, memory, passphrase, prefilter,
+    secret::SecretString,
+    wallet::{Hit, Scan},
+    wordlist,
+};
+use bip39::{Language, Mnemonic};
+use std::{
+    fmt,
+    ops::Range,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+/// Candidates a thread takes at a time.
+const CHUNK: u128 = 1 << 12;
+
+/// Searches expected to take longer than this many seconds are only
+/// started when asked for twice (`--yes`, or a second click).
+pub const LONG_SEARCH_SECS: f64 = 3600.0;
+
+/// How long [`estimate`] checks candidates for.
+const SAMPLE: Duration = Duration::from_millis(200);
+
+/// Stack of a search thread, the bulk of the memory a search needs.
+const THREAD_STACK: u64 = 2 << 20;
+
+/// What is known of each word of the phrase.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Template {
+    /// Indices in the English list of the words each slot may be.
+    slots: Vec<Vec<u16>>,
+}
+
+/// The list words `word` may have been meant to be: itself if it's one, and
+/// those [`diagnostics::suggest`] finds near it.
+pub fn similar(word: &str) -> Vec<u16> {
+    let mut indices: Vec<u16> = wordlist::index_in(word, Language::English).into_iter().collect();
+    for suggestion in diagnostics::suggest(word) {
+        let index = wordlist::index_in(suggestion, Language::English).unwrap();
+        if !indices.contains(&index) {
+            indices.push(index);
+        }
+    }
+    indices
+}
+
+/// Indices of the list words matching `pattern`: a list word, or one with
+/// `*` for any letters and `?` for one letter. `?` alone, or `*`, is any
+/// word.
+pub fn matching(pattern: &str) -> Result<Vec<u16>, String> {
+    let pattern = pattern.to_lowercase();
+    if pattern == "?" || pattern == "*" {
+        return Ok((0..2048).collect());
+    }
+    if !pattern.contains(['*', '?']) {
+        return wordlist::index_in(&pattern, Language::English).map(|index| vec![index]).ok_or_else(|| {
+            let suggestions = diagnostics::suggest(&pattern);
+            if suggestions.is_empty() {
+                format!("'{}' isn't a list word", pattern)
+            } else {
+                format!("'{}' isn't a list word; did you mean {}?", pattern, suggestions.join(", "))
+            }
+        });
+    }
+    let pattern: Vec<char> = pattern.chars().collect();
+    let indices: Vec<u16> = (0..2048)
+        .filter(|&i| {
+            let word: Vec<char> = Language::English.word_list()[i as usize].chars().collect();
+            glob_matches(&pattern, &word)
+        })
+        .collect();
+    if indices.is_empty() {
+        return Err(format!("'{}' matches no list word", pattern.iter().collect::<String>()));
+    }
+    Ok(indices)
+}
+
+fn glob_matches(pattern: &[char], word: &[char]) -> bool {
+    match pattern.split_first() {
+        None => word.is_empty(),
+        Some(('*', rest)) => (0..=word.len()).any(|skip| glob_matches(rest, &word[skip..])),
+        Some(('?', rest)) => !word.is_empty() && glob_matches(rest, &word[1..]),
+        Some((c, rest)) => word.first() == Some(c) && glob_matches(rest, &word[1..]),
+    }
+}
+
+/// The words one template token or constraint value stands for: a pattern
+/// as [`matching`] reads it, `word~` for that word or one like it, or
+/// `{a,b*,c}` for any of several.
+pub fn words(token: &str) -> Result<Vec<u16>, String> {
+    let token = token.trim().to_lowercase();
+    if let Some(word) = token.strip_suffix('~') {
+        let indices = similar(word);
+        if indices.is_empty() {
+            return Err(format!("no list word is like '{}'", word));
+        }
+        return Ok(indices);
+    }
+    let Some(set) = token.strip_prefix('{').and_then(|token| token.strip_suffix('}')) else {
+        return matching(&token);
+    };
+    let mut indices = Vec::new();
+    for pattern in set.split(',').map(str::trim).filter(|pattern| !pattern.is_empty()) {
+        for index in words(pattern)? {
+            if !indices.contains(&index) {
+                indices.push(index);
+            }
+        }
+    }
+    if indices.is_empty() {
+        return Err("{} names no word".to_string());
+    }
+    Ok(indices)
+}
+
+/// The order the words of each slot are tried in, likeliest first: words
+/// hinted at before all others, then those more frequent in a sample of
+/// text. Words it says nothing about keep the order they had, which for
+/// `word~` is nearest first, keyboard slips counting as near.
+#[derive(Clone, Debug, Default)]
+pub struct Priorities {
+    /// In the order given.
+    hints: Vec<u16>,
+    /// Occurrences of each list word; empty without a sample.
+    frequencies: Vec<u32>,
+}
+
+impl Priorities {
+    /// Puts the words of `token` (read as [`words`] does) after the hints
+    /// so far.
+    pub fn hint(&mut self, token: &str) -> Result<(), String> {
+        for index in words(token)? {
+            if !self.hints.contains(&index) {
+                self.hints.push(index);
+            }
+        }
+        Ok(())
+    }
+
+    /// Counts the list words in `text`.
+    pub fn count_frequencies(&mut self, text: &str) {
+        self.frequencies.resize(2048, 0);
+        for word in text.split(|c: char| !c.is_alphabetic()).filter(|word| !word.is_empty()) {
+            if let Some(index) = wordlist::index_in(&word.to_lowercase(), Language::English) {
+                self.frequencies[index as usize] += 1;
+            }
+        }
+    }
+
+    fn key(&self, index: u16) -> (usize, std::cmp::Reverse<u32>) {
+        let hinted = self.hints.iter().position(|&hint| hint == index).unwrap_or(usize::MAX);
+        let frequency = self.frequencies.get(index as usize).copied().unwrap_or(0);
+        (hinted, std::cmp::Reverse(frequency))
+    }
+}
+
+/// Which word a [`Constraint`] is about.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Position {
+    /// 1-based.
+    Nth(usize),
+    Last,
+}
+
+/// What's remembered of one word, in a line of its own: `pos3=app*`,
+/// `pos7∈{river,rival}`, `last=?`, `first!=abandon`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Constraint {
+    position: Position,
+    /// The word isn't one of `words`.
+    negated: bool,
+    words: Vec<u16>,
+}
+
+/// A constraint: `posN`, `first` or `last`, then `=` or `∈` (is one of) or
+/// `!=` or `∉` (is none of), then what [`words`] reads, for clap.
+pub fn parse_constraint(s: &str) -> Result<Constraint, String> {
+    let (at, operator) = ["!=", "∉", "∈", "="]
+        .iter()
+        .filter_map(|operator| Some((s.find(operator)?, *operator)))
+        .min()
+        .ok_or_else(|| format!("'{}' isn't a constraint; expected e.g. pos3=app*, pos7∈{{river,rival}} or last=?", s))?;
+    let (target, value) = (s[..at].trim().to_lowercase(), &s[at + operator.len()..]);
+    let position = match target.as_str() {
+        "first" => Position::Nth(1),
+        "last" => Position::Last,
+        _ => match target.strip_prefix("pos").and_then(|n| n.parse::<usize>().ok()) {
+            Some(n @ 1..=24) => Position::Nth(n),
+            _ => return Err(format!("'{}' isn't a word position; expected pos1 to pos24, first or last", target)),
+        },
+    };
+    Ok(Constraint {
+        position,
+        negated: matches!(operator, "!=" | "∉"),
+        words: words(value).map_err(|e| format!("{}: {}", s, e))?,
+    })
+}
+
+impl Template {
+    /// A template from one token per word, as [`words`] reads it: `?` for
+    /// any word, a list word, a pattern, `word~` or `{a,b,c}`.
+    pub fn parse(text: &str) -> Result<Template, String> {
+        let slots = text
+            .split_whitespace()
+            .enumerate()
+            .map(|(i, token)| words(token).map_err(|e| format!("word {}: {}", i + 1, e)))
+            .collect::<Result<Vec<_>, _>>()?;
+        Template::new(slots)
+    }
+
+    /// A template of `words` words none of which is known.
+    pub fn unknown(words: usize) -> Result<Template, String> {
+        Template::new(vec![(0..2048).collect(); words])
+    }
+
+    /// [`Template::unknown`] of a number of words, for clap.
+    pub fn unknown_parser(s: &str) -> Result<Template, String> {
+        Template::unknown(s.trim().parse().map_err(|_| format!("'{}' isn't a number of words", s))?)
+    }
+
+    /// Narrows the template down to what `constraint` allows.
+    pub fn constrain(&mut self, constraint: &Constraint) -> Result<(), String> {
+        let n = match constraint.position {
+            Position::Nth(n) if n <= self.slots.len() => n,
+            Position::Nth(n) => return Err(format!("pos{} is past the end of a {}-word phrase", n, self.slots.len())),
+            Position::Last => self.slots.len(),
+        };
+        let slot = &mut self.slots[n - 1];
+        slot.retain(|index| constraint.words.contains(index) != constraint.negated);
+        if slot.is_empty() {
+            return Err(format!("no word is left for word {} after the constraints", n));
+        }
+        Ok(())
+    }
+
+    /// Reorders each slot as `priorities` has it, so the likelier
+    /// candidates come first.
+    pub fn prioritize(&mut self, priorities: &Priorities) {
+        for slot in &mut self.slots {
+            // Stable, so words it doesn't tell apart keep their order
+            slot.sort_by_key(|&index| priorities.key(index));
+        }
+    }
+
+    /// Indices in the English list of the words each slot may be, in the
+    /// order they're tried.
+    pub fn slots(&self) -> &[Vec<u16>] {
+        &self.slots
+    }
+
+    /// A template from the indices each slot may be.
+    pub fn new(slots: Vec<Vec<u16>>) -> Result<Template, String> {
+        if !matches!(slots.len(), 12 | 15 | 18 | 21 | 24) {
+            return Err(format!("a phrase has 12, 15, 18, 21 or 24 words, not {}", slots.len()));
+        }
+        Ok(Template { slots })
+    }
+
+    pub fn words(&self) -> usize {
+        self.slots.len()
+    }
+
+    /// How many phrases it stands for; `None` past `u128`, which no search
+    /// would get through anyway.
+    pub fn count(&self) -> Option<u128> {
+        self.slots.iter().try_fold(1u128, |count, slot| count.checked_mul(slot.len() as u128))
+    }
+
+    /// Word indices of candidate `n`.
+    fn candidate(&self, mut n: u128, indices: &mut [u16]) {
+        for (slot, index) in self.slots.iter().zip(indices.iter_mut()).rev() {
+            let len = slot.len() as u128;
+            *index = slot[(n % len) as usize];
+            n /= len;
+        }
+    }
+
+    /// The template written the way [`Template::parse`] reads it, with
+    /// every slot of several words spelled out.
+    pub fn to_text(&self) -> String {
+        let list = Language::English.word_list();
+        let slots: Vec<String> = self
+            .slots
+            .iter()
+            .map(|slot| match slot.len() {
+                1 => list[slot[0] as usize].to_string(),
+                2048 => "?".to_string(),
+                _ => format!("{{{}}}", slot.iter().map(|&i| list[i as usize]).collect::<Vec<_>>().join(",")),
+            })
+            .collect();
+        slots.join(" ")
+    }
+}
+
+/// Whether the words with these indices have a valid checksum.
+pub fn checksum_ok(indices: &[u16]) -> bool {
+    prefilter::entropy(indices).is_some()
+}
+
+fn mnemonic(indices: &[u16]) -> Mnemonic {
+    let list = Language::English.word_list();
+    let phrase = SecretString::new(indices.iter().map(|&i| list[i as usize]).collect::<Vec<_>>().join(" "));
+    Mnemonic::parse_in_normalized(Language::English, &phrase).expect("the checksum was checked")
+}
+
+/// How a phrase with a valid checksum is confirmed to be the one.
+pub struct Confirm {
+    pub targets: Targets,
+    /// Tried in order; the empty passphrase only if it's among them.
+    pub passphrases: Vec<SecretString>,
+    pub scan: Scan,
+}
+
+/// A phrase found, with what confirmed it.
+pub struct Found {
+    /// Which candidate it is.
+    pub index: u128,
+    pub mnemonic: Mnemonic,
+    /// The passphrase and the target it gave, when there was a [`Confirm`].
+    pub confirmed: Option<(SecretString, Hit)>,
+}
+
+/// The first passphrase under which `mnemonic` gives a target.
+fn confirm(mnemonic: &Mnemonic, confirm: &Confirm) -> Option<(SecretString, Hit)> {
+    confirm.passphrases.iter().find_map(|candidate| {
+        let hit = passphrase::search(mnemonic, candidate, &confirm.targets, &confirm.scan)?;
+        Some((candidate.clone(), hit))
+    })
+}
+
+/// How far a [`search`] has got, read while it runs.
+pub struct Searched {
+    count: AtomicU64,
+    /// Every candidate before this one is done.
+    done_before: Mutex<u128>,
+}
+
+impl Searched {
+    /// For a search starting at candidate `start`.
+    pub fn new(start: u128) -> Self {
+        Self {
+            count: AtomicU64::new(0),
+            done_before: Mutex::new(start),
+        }
+    }
+
+    /// Candidates done so far.
+    pub fn count(&self) -> u64 {
+        self.count.load(Ordering::Relaxed)
+    }
+
+    /// The first candidate not known to be done, to resume from: the
+    /// threads go through their chunks side by side, so some after it may
+    /// be done too.
+    pub fn resume_at(&self) -> u128 {
+        *self.done_before.lock().unwrap()
+    }
+}
+
+/// Goes through the candidates `range` of `template` on `threads` threads,
+/// calling `found` with each phrase that has a valid checksum and, with
+/// `confirming`, is confirmed; it returns false to turn a phrase down, which
+/// stops that thread before it. `searched` follows the search as it goes.
+///
+/// Returns where it got to: the end of `range`, or, once cancelled, the
+/// first candidate not searched. The threads stop where they are, so some
+/// after it may have been searched too.
+pub fn search(
+    template: &Template,
+    range: Range<u128>,
+    threads: usize,
+    confirming: Option<&Confirm>,
+    cancel: &CancelToken,
+    searched: &Searched,
+    found: &(dyn Fn(Found) -> bool + Sync),
+) -> u128 {
+    // Chunks are handed out in order, each listed with where it's done up
+    // to until it's finished, so everything before the first one listed (or
+    // `next`) is done
+    let next = Mutex::new((range.start, Vec::<(u128, u128)>::new()));
+    thread::scope(|scope| {
+        for _ in 0..threads.max(1) {
+            scope.spawn(|| {
+                let mut indices = vec![0u16; template.words()];
+                loop {
+                    if cancel.is_cancelled() {
+                        break;
+                    }
+                    let chunk = {
+                        let (next, running) = &mut *next.lock().unwrap();
+                        if *next >= range.end {
+                            break;
+                        }
+                        let chunk = *next..(*next + CHUNK).min(range.end);
+                        *next = chunk.end;
+                        running.push((chunk.start, chunk.start));
+                        chunk
+                    };
+                    let mut stopped = None;
+                    for n in chunk.clone() {
+                        if cancel.is_cancelled() {
+                            stopped = Some(n);
+                            break;
+                        }
+                        template.candidate(n, &mut indices);
+                        if !checksum_ok(&indices) {
+                            continue;
+                        }
+                        let mnemonic = mnemonic(&indices);
+                        let confirmed = match confirming {
+                            None => None,
+                            Some(with) => match confirm(&mnemonic, with) {
+                                Some(confirmed) => Some(confirmed),
+                                None => continue,
+                            },
+                        };
+                        if !found(Found { index: n, mnemonic, confirmed }) {
+                            stopped = Some(n);
+                            break;
+                        }
+                    }
+                    let end = stopped.unwrap_or(chunk.end);
+                    searched.count.fetch_add((end - chunk.start) as u64, Ordering::Relaxed);
+                    let (next, running) = &mut *next.lock().unwrap();
+                    match stopped {
+                        None => running.retain(|&(start, _)| start != chunk.start),
+                        Some(at) => running.iter_mut().filter(|(start, _)| *start == chunk.start).for_each(|(_, done)| *done = at),
+                    }
+                    *searched.done_before.lock().unwrap() = running.iter().map(|&(_, done)| done).min().unwrap_or(*next);
+                    if stopped.is_some() {
+                        break;
+                    }
+                }
+            });
+        }
+    });
+    let (next, running) = next.into_inner().unwrap();
+    running.iter().map(|&(_, done)| done).min().unwrap_or(next)
+}
+
+/// What a search would take on this machine, from a short measurement.
+#[derive(Clone, Debug)]
+pub struct Estimate {
+    /// `None` past `u128`.
+    pub candidates: Option<u128>,
+    /// Candidates expected to pass the checksum, one in 2^(words / 3).
+    pub valid: f64,
+    pub threads: usize,
+    /// Candidates checked a second, on all the threads.
+    pub per_second: f64,
+    /// For the whole search; with addresses to confirm against, the
+    /// phrase may turn up well before the end.
+    pub seconds: f64,
+    pub confirming: bool,
+    /// Bytes of memory the search needs.
+    pub memory: u64,
+}
+
+impl Estimate {
+    /// Whether it's long enough to ask before starting.
+    pub fn is_long(&self) -> bool {
+        self.seconds > LONG_SEARCH_SECS
+    }
+
+    /// The estimate for a search that stops after `found` phrases with a
+    /// valid checksum, taken to be spread evenly over the candidates.
+    pub fn until(&self, found: u64) -> Estimate {
+        let share = (found as f64 / self.valid).min(1.0);
+        Estimate {
+            seconds: self.seconds * share,
+            ..self.clone()
+        }
+    }
+}
+
+impl fmt::Display for Estimate {
+    /// `2048 candidates, about 128 with a valid checksum; 00:02 on 8
+    /// threads (1200000 a second), 16.0 MiB of memory`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Some(candidates) = self.candidates else {
+            return write!(f, "more than 10^38 candidates, more than any search gets through");
+        };
+        write!(
+            f,
+            "{} candidates, about {:.0} with a valid checksum; {}{} on {} thread{} ({:.0} a second), {} of memory",
+            candidates,
+            self.valid,
+            if self.confirming { "up to " } else { "" },
+            eta::format_span(self.seconds),
+            self.threads,
+            if self.threads == 1 { "" } else { "s" },
+            self.per_second,
+            memory::format_size(self.memory)
+        )
+    }
+}
+
+/// Works out what searching all of `template` on `threads` threads would
+/// take, by timing the checksum on its first candidates for a moment and,
+/// with `confirming`, the confirmation of one phrase under one passphrase.
+pub fn estimate(template: &Template, threads: usize, confirming: Option<&Confirm>) -> Estimate {
+    let threads = threads.max(1);
+    let candidates = template.count();
+    let mut indices = vec![0u16; template.words()];
+    let start = Instant::now();
+    let mut checked = 0u128;
+    while candidates.is_none_or(|candidates| checked < candidates) && (!checked.is_multiple_of(1024) || start.elapsed() < SAMPLE) {
+        template.candidate(checked, &mut indices);
+        std::hint::black_box(checksum_ok(&indices));
+        checked += 1;
+    }
+    let per_second = checked as f64 / start.elapsed().as_secs_f64().max(1e-9) * threads as f64;
+
+    let total = candidates.map_or(f64::INFINITY, |candidates| candidates as f64);
+    let valid = total / (1u32 << (template.words() / 3)) as f64;
+    let confirm_secs = match confirming.and_then(|with| Some((with, with.passphrases.first()?))) {
+        Some((with, first)) => {
+            let sample = Mnemonic::from_entropy(&vec![0; template.words() / 3 * 4]).expect("the entropy length is valid");
+            let start = Instant::now();
+            std::hint::black_box(passphrase::search(&sample, first, &with.targets, &with.scan));
+            start.elapsed().as_secs_f64() * with.passphrases.len() as f64
+        }
+        None => 0.0,
+    };
+    let slots: usize = template.slots.iter().map(Vec::len).sum();
+    Estimate {
+        candidates,
+        valid,
+        threads,
+        per_second,
+        seconds: match confirm_secs {
+            0.0 => total / per_second,
+            _ => total / per_second + valid * confirm_secs / threads as f64,
+        },
+        confirming: confirming.is_some(),
+        memory: slots as u64 * 2 + threads as u64 * THREAD_STACK,
+    }
+}
@@ -0,0 +1,469 @@
+//! `mnemonic_validator report`: a run's manifest turned into a report to
+//! hand over, as one self-contained HTML file (charts drawn in inline SVG)
+//! or as Markdown.
+//!
+//! Hits can be listed too, but only from `--mask` output: the report shows
+//! where each was found, its length and fingerprints, and never any of its
+//! words.
+
+use clap::Args;
+use mnemonic_validator::{
+    eta::format_duration,
+    manifest::{Manifest, TimelineEntry},
+    paths,
+};
+use std::{fmt::Write, fs, path::PathBuf, str::FromStr, time::Duration};
+
+/// Size of the HTML charts, in pixels.
+const CHART_WIDTH: f64 = 720.0;
+const CHART_HEIGHT: f64 = 220.0;
+/// Room for the axis labels.
+const MARGIN_LEFT: f64 = 70.0;
+const MARGIN_BOTTOM: f64 = 30.0;
+/// Rows of the text charts in Markdown.
+const TEXT_ROWS: usize = 12;
+/// Width of the longest bar in the text charts.
+const TEXT_BAR: usize = 40;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Format {
+    Html,
+    Markdown,
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "html" => Ok(Format::Html),
+            "markdown" | "md" => Ok(Format::Markdown),
+            other => Err(format!("unknown format '{}', expected html or markdown", other)),
+        }
+    }
+}
+
+/// Options of `mnemonic_validator report`.
+#[derive(Args, Debug)]
+pub struct ReportArgs {
+    /// The run's manifest, e.g. output/valid_mnemonics.txt.manifest.json.
+    #[clap(value_parser = paths::parser())]
+    manifest: PathBuf,
+
+    /// Also list the hits of this --mask output file: file, line, word count and fingerprints, never any words.
+    #[clap(long, value_name = "FILE", value_parser = paths::parser())]
+    hits: Option<PathBuf>,
+
+    /// html or markdown (default: markdown for an --output ending in .md, html otherwise).
+    #[clap(long, value_name = "FORMAT")]
+    format: Option<Format>,
+
+    /// Where to write the report (default: next to the manifest, with .report.html or .report.md appended).
+    #[clap(short, long, value_name = "FILE", value_parser = paths::parser())]
+    output: Option<PathBuf>,
+}
+
+/// What a masked output line tells about a hit.
+struct HitEntry {
+    file: Option<String>,
+    line: usize,
+    words: String,
+    sha256: String,
+    fingerprint: Option<String>,
+}
+
+/// Parses `boy...banana 24w sha256:1f3c2a9b7d4e0c55 [file:F] line:17
+/// [fp:73c5da0a]`, dropping the words at the front.
+fn parse_masked(line: &str) -> Option<HitEntry> {
+    let (_, rest) = line.trim().split_once(' ')?;
+    let (head, tail) = rest.rsplit_once(" line:")?;
+    let mut tail = tail.split(' ');
+    let number = tail.next()?.parse().ok()?;
+    let fingerprint = tail.next().and_then(|token| token.strip_prefix("fp:")).map(str::to_string);
+    let (head, file) = match head.split_once(" file:") {
+        Some((head, file)) => (head, Some(file.to_string())),
+        None => (head, None),
+    };
+    let (words, sha256) = head.split_once(' ')?;
+    Some(HitEntry {
+        file,
+        line: number,
+        words: words.strip_suffix('w')?.to_string(),
+        sha256: sha256.strip_prefix("sha256:")?.to_string(),
+        fingerprint,
+    })
+}
+
+fn read_hits(path: &PathBuf) -> Result<Vec<HitEntry>, Box<dyn std::error::Error>> {
+    let text = fs::read_to_string(path)?;
+    text.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(i, line)| {
+            parse_masked(line).ok_or_else(|| {
+                format!(
+                    "line {} of {} isn't a --mask line; only masked output can go into a report",
+                    i + 1,
+                    path.display()
+                )
+                .into()
+            })
+        })
+        .collect()
+}
+
+/// `1.2M`, `350k`, `42`.
+fn compact(n: f64) -> String {
+    match n {
+        n if n >= 1e9 => format!("{:.1}G", n / 1e9),
+        n if n >= 1e6 => format!("{:.1}M", n / 1e6),
+        n if n >= 1e3 => format!("{:.1}k", n / 1e3),
+        n => format!("{:.0}", n),
+    }
+}
+
+fn duration(secs: f64) -> String {
+    format_duration(Duration::from_secs_f64(secs.max(0.0)))
+}
+
+/// Lines per second between consecutive samples, at the end of each.
+fn throughput(timeline: &[TimelineEntry]) -> Vec<(f64, f64)> {
+    timeline
+        .windows(2)
+        .filter(|pair| pair[1].elapsed_secs > pair[0].elapsed_secs)
+        .map(|pair| {
+            let lines = pair[1].processed.saturating_sub(pair[0].processed) as f64;
+            (pair[1].elapsed_secs, lines / (pair[1].elapsed_secs - pair[0].elapsed_secs))
+        })
+        .collect()
+}
+
+fn hits_over_time(timeline: &[TimelineEntry]) -> Vec<(f64, f64)> {
+    timeline.iter().map(|sample| (sample.elapsed_secs, sample.valid as f64)).collect()
+}
+
+/// Run parameters that were set, as `name`, `value`.
+fn parameters(manifest: &Manifest) -> Vec<(String, String)> {
+    let Some(parameters) = manifest.parameters.as_object() else {
+        return Vec::new();
+    };
+    parameters
+        .iter()
+        .filter(|(_, value)| !matches!(value, serde_json::Value::Null | serde_json::Value::Bool(false)))
+        .filter(|(_, value)| value.as_array().is_none_or(|values| !values.is_empty()))
+        .map(|(name, value)| {
+            let value = match value {
+                serde_json::Value::String(s) => s.clone(),
+                other => other.to_string(),
+            };
+            (name.clone(), value)
+        })
+        .collect()
+}
+
+pub(crate) fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// A line chart of `points` (seconds into the run, value) in SVG.
+fn svg_chart(points: &[(f64, f64)], unit: &str) -> String {
+    if points.is_empty() {
+        return "<p class=\"none\">Not enough samples for a chart.</p>".to_string();
+    }
+    let max_x = points.iter().map(|p| p.0).fold(0.0, f64::max).max(1.0);
+    let max_y = points.iter().map(|p| p.1).fold(0.0, f64::max).max(1.0);
+    let (plot_w, plot_h) = (CHART_WIDTH - MARGIN_LEFT - 10.0, CHART_HEIGHT - MARGIN_BOTTOM - 10.0);
+    let x = |t: f64| MARGIN_LEFT + t / max_x * plot_w;
+    let y = |v: f64| 10.0 + plot_h - v / max_y * plot_h;
+
+    let mut svg = String::new();
+    let _ = write!(
+        svg,
+        "<svg viewBox=\"0 0 {w} {h}\" width=\"{w}\" height=\"{h}\" role=\"img\">",
+        w = CHART_WIDTH,
+        h = CHART_HEIGHT
+    );
+    for i in 0..=4 {
+        let value = max_y * i as f64 / 4.0;
+        let _ = write!(
+            svg,
+            "<line class=\"grid\" x1=\"{x1}\" x2=\"{x2}\" y1=\"{y:.1}\" y2=\"{y:.1}\"/>\
+             <text x=\"{tx}\" y=\"{ty:.1}\" text-anchor=\"end\">{label}{unit}</text>",
+            x1 = MARGIN_LEFT,
+            x2 = CHART_WIDTH - 10.0,
+            y = y(value),
+            tx = MARGIN_LEFT - 6.0,
+            ty = y(value) + 4.0,
+            label = compact(value),
+            unit = escape(unit),
+        );
+        let t = max_x * i as f64 / 4.0;
+        let _ = write!(
+            svg,
+            "<text x=\"{:.1}\" y=\"{}\" text-anchor=\"middle\">{}</text>",
+            x(t),
+            CHART_HEIGHT - 8.0,
+            duration(t)
+        );
+    }
+    let line: Vec<String> = points.iter().map(|&(t, v)| format!("{:.1},{:.1}", x(t), y(v))).collect();
+    let _ = write!(svg, "<polyline class=\"line\" points=\"{}\"/></svg>", line.join(" "));
+    svg
+}
+
+/// A chart of `points` as rows of text bars, for Markdown.
+fn text_chart(points: &[(f64, f64)], unit: &str) -> String {
+    if points.is_empty() {
+        return "Not enough samples for a chart.\n".to_string();
+    }
+    let step = points.len().div_ceil(TEXT_ROWS);
+    let rows: Vec<(f64, f64)> = points
+        .chunks(step)
+        .map(|chunk| {
+            let average = chunk.iter().map(|p| p.1).sum::<f64>() / chunk.len() as f64;
+            (chunk[chunk.len() - 1].0, average)
+        })
+        .collect();
+    let max = rows.iter().map(|row| row.1).fold(0.0, f64::max).max(1.0);
+    let mut chart = String::from("```\n");
+    for (t, value) in rows {
+        let bar = (value / max * TEXT_BAR as f64).round() as usize;
+        let _ = writeln!(
+            chart,
+            "{:>8} {:<width$} {}{}",
+            duration(t),
+            "█".repeat(bar),
+            compact(value),
+            unit,
+            width = TEXT_BAR
+        );
+    }
+    chart.push_str("```\n");
+    chart
+}
+
+fn html(manifest: &Manifest, hits: Option<&[HitEntry]>) -> String {
+    let results = &manifest.results;
+    let mut page = String::new();
+    let _ = write!(
+        page,
+        "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n<title>Validation report</title>\n<style>\n\
+         body {{ font-family: system-ui, sans-serif; max-width: 60rem; margin: 2rem auto; padding: 0 1rem; color: #222; }}\n\
+         table {{ border-collapse: collapse; margin: 1rem 0; }}\n\
+         th, td {{ border: 1px solid #ccc; padding: 0.3rem 0.6rem; text-align: left; }}\n\
+         td.n {{ text-align: right; font-variant-numeric: tabular-nums; }}\n\
+         .bar {{ background: #4a7bd0; height: 0.8rem; }}\n\
+         svg text {{ font-size: 11px; fill: #555; }}\n\
+         svg .grid {{ stroke: #e3e3e3; }}\n\
+         svg .line {{ fill: none; stroke: #4a7bd0; stroke-width: 2; }}\n\
+         .none {{ color: #777; }}\n\
+         </style>\n</head>\n<body>\n<h1>Validation report</h1>\n"
+    );
+    let _ = write!(
+        page,
+        "<p>{} run from {} to {}.</p>\n<table>\n\
+         <tr><th>Lines</th><td class=\"n\">{}</td></tr>\n\
+         <tr><th>Resumed from line</th><td class=\"n\">{}</td></tr>\n\
+         <tr><th>Processed</th><td class=\"n\">{}</td></tr>\n\
+         <tr><th>Valid</th><td class=\"n\">{}</td></tr>\n\
+         <tr><th>Time</th><td class=\"n\">{}</td></tr>\n\
+         <tr><th>Lines/s</th><td class=\"n\">{:.0}</td></tr>\n\
+         <tr><th>Hit rate</th><td class=\"n\">{:.4}%</td></tr>\n</table>\n",
+        escape(&manifest.tool),
+        escape(&manifest.started_at),
+        escape(&manifest.finished_at),
+        results.total_lines,
+        results.resumed_from_line,
+        results.processed,
+        results.valid,
+        duration(results.elapsed_secs),
+        results.lines_per_sec,
+        results.hit_rate * 100.0
+    );
+
+    let _ = write!(page, "<h2>Throughput</h2>\n{}\n", svg_chart(&throughput(&manifest.timeline), "/s"));
+    let _ = write!(page, "<h2>Hits over time</h2>\n{}\n", svg_chart(&hits_over_time(&manifest.timeline), ""));
+
+    page.push_str("<h2>Input files</h2>\n<table>\n<tr><th>File</th><th>SHA-256</th><th>Lines</th><th>Valid</th><th>Time</th><th>Lines/s</th><th>Hit rate</th><th></th></tr>\n");
+    let fastest = manifest.inputs.iter().map(|input| input.lines_per_sec).fold(0.0, f64::max).max(1.0);
+    for input in &manifest.inputs {
+        let _ = writeln!(
+            page,
+            "<tr><td>{}</td><td><code>{}</code></td><td class=\"n\">{}</td><td class=\"n\">{}</td><td class=\"n\">{}</td>\
+             <td class=\"n\">{:.0}</td><td class=\"n\">{:.4}%</td><td><div class=\"bar\" style=\"width: {:.0}px\"></div></td></tr>",
+            escape(&input.file.path),
+            escape(&input.file.sha256[..16.min(input.file.sha256.len())]),
+            input.lines,
+            input.valid,
+            duration(input.elapsed_secs),
+            input.lines_per_sec,
+            input.hit_rate * 100.0,
+            input.lines_per_sec / fastest * 120.0
+        );
+    }
+    page.push_str("</table>\n");
+
+    page.push_str("<h2>Output files</h2>\n<table>\n<tr><th>File</th><th>From byte</th><th>SHA-256</th><th>Bytes</th></tr>\n");
+    for output in &manifest.outputs {
+        let _ = writeln!(
+            page,
+            "<tr><td>{}</td><td class=\"n\">{}</td><td><code>{}</code></td><td class=\"n\">{}</td></tr>",
+            escape(&output.path),
+            output.offset.unwrap_or(0),
+            escape(&output.sha256),
+            output.bytes
+        );
+    }
+    page.push_str("</table>\n");
+
+    let parameters = parameters(manifest);
+    if !parameters.is_empty() {
+        page.push_str("<h2>Parameters</h2>\n<table>\n");
+        for (name, value) in parameters {
+            let _ = writeln!(page, "<tr><th>{}</th><td><code>{}</code></td></tr>", escape(&name), escape(&value));
+        }
+        page.push_str("</table>\n");
+    }
+
+    if let Some(hits) = hits {
+        let _ = write!(
+            page,
+            "<h2>Hits</h2>\n<table>\n<tr><th>#</th><th>File</th><th>Line</th><th>Words</th><th>SHA-256</th><th>Fingerprint</th></tr>\n"
+        );
+        for (i, hit) in hits.iter().enumerate() {
+            let _ = writeln!(
+                page,
+                "<tr><td class=\"n\">{}</td><td>{}</td><td class=\"n\">{}</td><td class=\"n\">{}</td><td><code>{}</code></td><td><code>{}</code></td></tr>",
+                i + 1,
+                escape(hit.file.as_deref().unwrap_or("")),
+                hit.line,
+                escape(&hit.words),
+                escape(&hit.sha256),
+                escape(hit.fingerprint.as_deref().unwrap_or(""))
+            );
+        }
+        page.push_str("</table>\n");
+    }
+    page.push_str("</body>\n</html>\n");
+    page
+}
+
+/// A table cell; `|` would end it early.
+fn cell(text: &str) -> String {
+    text.replace('|', "\\|")
+}
+
+fn markdown(manifest: &Manifest, hits: Option<&[HitEntry]>) -> String {
+    let results = &manifest.results;
+    let mut page = String::from("# Validation report\n\n");
+    let _ = write!(
+        page,
+        "{} run from {} to {}.\n\n\
+         | | |\n|---|---:|\n\
+         | Lines | {} |\n| Resumed from line | {} |\n| Processed | {} |\n| Valid | {} |\n\
+         | Time | {} |\n| Lines/s | {:.0} |\n| Hit rate | {:.4}% |\n\n",
+        manifest.tool,
+        manifest.started_at,
+        manifest.finished_at,
+        results.total_lines,
+        results.resumed_from_line,
+        results.processed,
+        results.valid,
+        duration(results.elapsed_secs),
+        results.lines_per_sec,
+        results.hit_rate * 100.0
+    );
+
+    let _ = write!(page, "## Throughput\n\n{}\n", text_chart(&throughput(&manifest.timeline), "/s"));
+    let _ = write!(page, "## Hits over time\n\n{}\n", text_chart(&hits_over_time(&manifest.timeline), ""));
+
+    page.push_str("## Input files\n\n| File | SHA-256 | Lines | Valid | Time | Lines/s | Hit rate |\n|---|---|---:|---:|---:|---:|---:|\n");
+    for input in &manifest.inputs {
+        let _ = writeln!(
+            page,
+            "| {} | `{}` | {} | {} | {} | {:.0} | {:.4}% |",
+            cell(&input.file.path),
+            &input.file.sha256[..16.min(input.file.sha256.len())],
+            input.lines,
+            input.valid,
+            duration(input.elapsed_secs),
+            input.lines_per_sec,
+            input.hit_rate * 100.0
+        );
+    }
+
+    page.push_str("\n## Output files\n\n| File | From byte | SHA-256 | Bytes |\n|---|---:|---|---:|\n");
+    for output in &manifest.outputs {
+        let _ = writeln!(
+            page,
+            "| {} | {} | `{}` | {} |",
+            cell(&output.path),
+            output.offset.unwrap_or(0),
+            output.sha256,
+            output.bytes
+        );
+    }
+
+    let parameters = parameters(manifest);
+    if !parameters.is_empty() {
+        page.push_str("\n## Parameters\n\n| Parameter | Value |\n|---|---|\n");
+        for (name, value) in parameters {
+            let _ = writeln!(page, "| {} | `{}` |", name, cell(&value));
+        }
+    }
+
+    if let Some(hits) = hits {
+        page.push_str("\n## Hits\n\n| # | File | Line | Words | SHA-256 | Fingerprint |\n|---:|---|---:|---:|---|---|\n");
+        for (i, hit) in hits.iter().enumerate() {
+            let _ = writeln!(
+                page,
+                "| {} | {} | {} | {} | `{}` | {} |",
+                i + 1,
+                cell(hit.file.as_deref().unwrap_or("")),
+                hit.line,
+                hit.words,
+                hit.sha256,
+                hit.fingerprint.as_deref().map(|f| format!("`{}`", f)).unwrap_or_default()
+            );
+        }
+    }
+    page
+}
+
+pub fn run(args: &ReportArgs) -> Result<(), Box<dyn std::error::Error>> {
+    let manifest: Manifest = serde_json::from_str(&fs::read_to_string(&args.manifest)?)
+        .map_err(|e| format!("{} isn't a run manifest: {}", args.manifest.display(), e))?;
+    let hits = args.hits.as_ref().map(read_hits).transpose()?;
+
+    let format = args.format.unwrap_or_else(|| {
+        match args.output.as_ref().and_then(|path| path.extension()) {
+            Some(extension) if extension == "md" => Format::Markdown,
+            _ => Format::Html,
+        }
+    });
+    let output = args.output.clone().unwrap_or_else(|| {
+        let mut name = args.manifest.as_os_str().to_os_string();
+        name.push(match format {
+            Format::Html => ".report.html",
+            Format::Markdown => ".report.md",
+        });
+        PathBuf::from(name)
+    });
+    let report = match format {
+        Format::Html => html(&manifest, hits.as_deref()),
+        Format::Markdown => markdown(&manifest, hits.as_deref()),
+    };
+    fs::write(&output, report)?;
+    println!("Report written to {}", output.display());
+    Ok(())
+}
@@ -0,0 +1,184 @@
+//! `mnemonic_validator reveal`: the second step of a run with `--seal-to`,
+//! opening the sealed phrases of the hits confirmed from its masked output.
+//!
+//! Whoever runs the search and reviews its results needs only the public
+//! key; the secret key stays with whoever is allowed to see the phrases,
+//! and opens nothing but the digests handed to it. Every reveal is entered
+//! in the audit log, with the digests but not the phrases.
+//!
+//! Given a bundle written by `export` instead, it opens the whole bundle,
+//! which was sealed to the client's key for them to read.
+
+use clap::Args;
+use mnemonic_validator::{
+    audit::{self, AuditLog},
+    bundle, paths, seal,
+};
+use std::{
+    collections::BTreeSet,
+    fs::{self, OpenOptions},
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+#[derive(Args, Debug)]
+pub struct RevealArgs {
+    /// Sealed file written by a run with --seal-to, e.g. output/valid_mnemonics.txt.sealed, or a bundle written by `export`.
+    #[clap(value_parser = paths::parser(), required_unless_present = "new_key")]
+    sealed: Option<PathBuf>,
+
+    /// Secret key file the hits were sealed to.
+    #[clap(long, value_name = "FILE", value_parser = paths::parser(), required_unless_present = "new_key")]
+    key: Option<PathBuf>,
+
+    /// Digest of a confirmed hit, as on its masked line (sha256:1f3c2a9b7d4e0c55, or just the hex); may be given more than once.
+    #[clap(long = "digest", value_name = "DIGEST")]
+    digests: Vec<String>,
+
+    /// File of confirmed hits, one per line: lines cut from the masked output, or just their digests.
+    #[clap(long, value_name = "FILE", value_parser = paths::parser())]
+    confirmed: Option<PathBuf>,
+
+    /// Write the phrases to this file instead of printing them.
+    #[clap(short, long, value_name = "FILE", value_parser = paths::parser())]
+    output: Option<PathBuf>,
+
+    /// Make a new key pair instead: the secret key goes into FILE, the public key for --seal-to into FILE.pub.
+    #[clap(long, value_name = "FILE", value_parser = paths::parser(), conflicts_with_all = ["sealed", "key", "digests", "confirmed", "output"])]
+    new_key: Option<PathBuf>,
+
+    /// Append the reveal to this audit log instead of the one in the data directory.
+    #[clap(long, value_name = "FILE", value_parser = paths::parser())]
+    audit_log: Option<PathBuf>,
+}
+
+/// The digest on a masked line, or the line itself if it's just one.
+fn digest_of(line: &str) -> Option<String> {
+    let digest = line
+        .split_whitespace()
+        .find_map(|token| token.strip_prefix("sha256:"))
+        .or_else(|| line.split_whitespace().next())?;
+    (!digest.is_empty() && digest.bytes().all(|b| b.is_ascii_hexdigit())).then(|| digest.to_ascii_lowercase())
+}
+
+/// Writes a new key pair; the secret key readable by its owner only, where
+/// the platform allows.
+fn new_key(path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    let (secret, public) = seal::generate()?;
+    let mut options = OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    let mut file = options
+        .open(path)
+        .map_err(|e| format!("can't create the key file {}: {}", path.display(), e))?;
+    writeln!(file, "{}", secret.as_str())?;
+    let mut public_path = path.as_os_str().to_owned();
+    public_path.push(".pub");
+    let public_path = PathBuf::from(public_path);
+    fs::write(&public_path, format!("{}\n", public))?;
+    println!("Secret key written to {}; keep it away from the machines doing the search.", path.display());
+    println!("Public key, for --seal-to: {} (also in {})", public, public_path.display());
+    Ok(())
+}
+
+/// Opens a whole bundle, writing its JSON.
+fn reveal_bundle(args: &RevealArgs, path: &Path, key_path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    let secret = seal::read_secret_key(key_path)?;
+    let json = bundle::open_json(path, &secret)?;
+    AuditLog::new(args.audit_log.clone().unwrap_or_else(audit::default_path), None)
+        .record(
+            "revealed",
+            serde_json::json!({
+                "bundle": path,
+                "output": args.output,
+            }),
+        )
+        .map_err(|e| format!("can't write the audit log, so nothing was revealed: {}", e))?;
+    match &args.output {
+        Some(output) => fs::write(output, json.as_bytes())?,
+        None => println!("{}", json.as_str()),
+    }
+    eprintln!("Revealed the bundle {}", path.display());
+    Ok(())
+}
+
+pub fn run(args: &RevealArgs) -> Result<(), Box<dyn std::error::Error>> {
+    if let Some(path) = &args.new_key {
+        return new_key(path);
+    }
+    let (Some(sealed_path), Some(key_path)) = (&args.sealed, &args.key) else {
+        return Err("name the sealed file and --key".into());
+    };
+
+    if bundle::is_bundle(sealed_path) {
+        return reveal_bundle(args, sealed_path, key_path);
+    }
+
+    let mut wanted: BTreeSet<String> = BTreeSet::new();
+    for digest in &args.digests {
+        wanted.insert(digest_of(digest).ok_or_else(|| format!("'{}' isn't a digest", digest))?);
+    }
+    if let Some(path) = &args.confirmed {
+        let text = fs::read_to_string(path).map_err(|e| format!("can't read {}: {}", path.display(), e))?;
+        for (n, line) in (1..).zip(text.lines()) {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let digest = digest_of(line).ok_or_else(|| format!("line {} of {} has no digest", n, path.display()))?;
+            wanted.insert(digest);
+        }
+    }
+    if wanted.is_empty() {
+        return Err("name the confirmed hits with --digest or --confirmed".into());
+    }
+
+    let secret = seal::read_secret_key(key_path)?;
+    let text = fs::read_to_string(sealed_path).map_err(|e| format!("can't read {}: {}", sealed_path.display(), e))?;
+    let mut revealed = BTreeSet::new();
+    let mut phrases = Vec::new();
+    for (n, line) in (1..).zip(text.lines()) {
+        let Some((digest, sealed)) = seal::split_line(line) else {
+            continue;
+        };
+        // A shorter digest picks out every hit it starts
+        if !wanted.iter().any(|wanted| digest.starts_with(wanted.as_str())) {
+            continue;
+        }
+        let phrase = seal::open(&secret, sealed).map_err(|e| format!("line {} of {}: {}", n, sealed_path.display(), e))?;
+        phrases.push(phrase);
+        revealed.insert(digest.to_string());
+    }
+
+    let missing: Vec<&String> = wanted
+        .iter()
+        .filter(|wanted| !revealed.iter().any(|digest| digest.starts_with(wanted.as_str())))
+        .collect();
+    // Nothing is shown before the reveal is on record
+    AuditLog::new(args.audit_log.clone().unwrap_or_else(audit::default_path), None)
+        .record(
+            "revealed",
+            serde_json::json!({
+                "sealed": sealed_path,
+                "digests": revealed,
+                "not_found": missing,
+                "output": args.output,
+            }),
+        )
+        .map_err(|e| format!("can't write the audit log, so nothing was revealed: {}", e))?;
+
+    let mut out: Box<dyn Write> = match &args.output {
+        Some(path) => Box::new(OpenOptions::new().create(true).append(true).open(path)?),
+        None => Box::new(io::stdout().lock()),
+    };
+    for phrase in &phrases {
+        writeln!(out, "{}", phrase.as_str())?;
+    }
+    out.flush()?;
+    eprintln!("Revealed {} phrase(s)", revealed.len());
+    if !missing.is_empty() {
+        let missing: Vec<&str> = missing.iter().map(|digest| digest.as_str()).collect();
+        return Err(format!("not in {}: {}", sealed_path.display(), missing.join(", ")).into());
+    }
+    Ok(())
+}
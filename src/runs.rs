@@ -0,0 +1,294 @@
+//! Run history for `mnemonic_validator runs`.
+//!
+//! Every run that writes output gets an ID and a directory under the local
+//! data directory (`~/.local/share/mnemonic_validator/runs/<id>/` on Linux)
+//! holding its checkpoint and a `run.json` record: the arguments, working
+//! directory, status and where the results went. Starting the same command
+//! again from the same directory picks its unfinished run back up, as the
+//! single checkpoint file used to.
+//!
+//! `runs audit` shows the audit log, which every run appends to, recorded
+//! here or not, and checks its chain.
+
+use clap::{Args, Subcommand};
+use mnemonic_validator::{audit, checkpoint, paths};
+use serde::{Deserialize, Serialize};
+use std::{
+    ffi::OsString,
+    fs,
+    io,
+    path::{Path, PathBuf},
+};
+
+#[derive(Args, Debug)]
+pub struct RunsArgs {
+    #[clap(subcommand)]
+    command: RunsCommand,
+}
+
+#[derive(Subcommand, Debug)]
+enum RunsCommand {
+    /// Show recorded runs, newest last.
+    List,
+    /// Continue an unfinished run from its checkpoint, with its original arguments and working directory.
+    Resume {
+        /// ID shown by `runs list`.
+        id: String,
+    },
+    /// Delete the records of finished runs.
+    Clean {
+        /// Delete unfinished runs and their checkpoints too.
+        #[clap(long)]
+        all: bool,
+    },
+    /// Show the audit log of all runs and check that no entry was changed or removed.
+    Audit {
+        /// Audit log to read instead of the one in the data directory.
+        #[clap(long, value_name = "FILE", value_parser = paths::parser())]
+        file: Option<PathBuf>,
+    },
+}
+
+/// `run.json`.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Record {
+    pub id: String,
+    /// `running`, `interrupted`, `failed` or `done`.
+    pub status: String,
+    pub args: Vec<String>,
+    pub cwd: String,
+    pub started_at: String,
+    pub updated_at: String,
+    pub output: String,
+    pub valid: Option<usize>,
+    pub manifest: Option<String>,
+    pub error: Option<String>,
+}
+
+/// A recorded run and its directory.
+#[derive(Clone, Debug)]
+pub struct Run {
+    dir: PathBuf,
+    pub record: Record,
+}
+
+fn runs_dir() -> PathBuf {
+    dirs::data_local_dir()
+        .or_else(dirs::home_dir)
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join("mnemonic_validator")
+        .join("runs")
+}
+
+/// All recorded runs, oldest first. Unreadable records are skipped.
+fn all_runs() -> io::Result<Vec<Run>> {
+    let dir = runs_dir();
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+    let mut runs: Vec<Run> = fs::read_dir(dir)?
+        .filter_map(|entry| Run::open(&entry.ok()?.path()).ok())
+        .collect();
+    // IDs start with the start time
+    runs.sort_by(|a, b| a.record.id.cmp(&b.record.id));
+    Ok(runs)
+}
+
+impl Run {
+    fn open(dir: &Path) -> Result<Run, Box<dyn std::error::Error>> {
+        let record = serde_json::from_str(&fs::read_to_string(dir.join("run.json"))?)?;
+        Ok(Run {
+            dir: dir.to_path_buf(),
+            record,
+        })
+    }
+
+    pub fn load(id: &str) -> Result<Run, Box<dyn std::error::Error>> {
+        let dir = runs_dir().join(id);
+        if id.contains(['/', '\\']) || !dir.join("run.json").exists() {
+            return Err(format!("no run with ID '{}' (see `runs list`)", id).into());
+        }
+        Run::open(&dir)
+    }
+
+    /// The unfinished run of this command in this directory, or a new one.
+    /// `restart` always starts a new one.
+    pub fn start(args: &[OsString], output: &Path, restart: bool) -> Result<Run, Box<dyn std::error::Error>> {
+        let args: Vec<String> = args.iter().map(|arg| arg.to_string_lossy().into_owned()).collect();
+        let cwd = std::env::current_dir()?.display().to_string();
+        if !restart {
+            let unfinished = all_runs()?
+                .into_iter()
+                .rev()
+                .find(|run| run.record.status != "done" && run.record.args == args && run.record.cwd == cwd);
+            if let Some(mut run) = unfinished {
+                run.update("running")?;
+                return Ok(run);
+            }
+        }
+
+        let started_at = chrono::Local::now();
+        let base = started_at.format("%Y%m%d-%H%M%S").to_string();
+        let mut id = base.clone();
+        let mut n = 1;
+        while runs_dir().join(&id).exists() {
+            n += 1;
+            id = format!("{}-{}", base, n);
+        }
+        let dir = runs_dir().join(&id);
+        fs::create_dir_all(&dir)?;
+        let mut run = Run {
+            dir,
+            record: Record {
+                id,
+                status: String::new(),
+                args,
+                cwd,
+                started_at: started_at.to_rfc3339(),
+                updated_at: String::new(),
+                output: output.display().to_string(),
+                valid: None,
+                manifest: None,
+                error: None,
+            },
+        };
+        run.update("running")?;
+        Ok(run)
+    }
+
+    pub fn checkpoint(&self) -> PathBuf {
+        self.dir.join("checkpoint.txt")
+    }
+
+    /// Sets the status and saves the record.
+    pub fn update(&mut self, status: &str) -> io::Result<()> {
+        self.record.status = status.to_string();
+        self.record.updated_at = chrono::Local::now().to_rfc3339();
+        let json = serde_json::to_string_pretty(&self.record).map_err(io::Error::other)?;
+        fs::write(self.dir.join("run.json"), json)
+    }
+
+    /// Line the checkpoint is at, for unfinished runs.
+    fn position(&self) -> Option<usize> {
+        checkpoint::load(&self.checkpoint()).ok().map(|checkpoint| checkpoint.line)
+    }
+}
+
+pub fn run(args: &RunsArgs) -> Result<(), Box<dyn std::error::Error>> {
+    match &args.command {
+        RunsCommand::List => {
+            let runs = all_runs()?;
+            if runs.is_empty() {
+                println!("No runs recorded in {}", runs_dir().display());
+                return Ok(());
+            }
+            println!("{:<18} {:<12} {:<19} {:>12}  command", "id", "status", "started", "progress");
+            for run in runs {
+                let record = &run.record;
+                let started = chrono::DateTime::parse_from_rfc3339(&record.started_at)
+                    .map(|t| t.format("%Y-%m-%d %H:%M:%S").to_string())
+                    .unwrap_or_default();
+                let progress = match (record.status.as_str(), record.valid, run.position()) {
+                    ("done", Some(valid), _) => format!("{} valid", valid),
+                    (_, _, Some(line)) => format!("line {}", line),
+                    _ => String::new(),
+                };
+                println!(
+                    "{:<18} {:<12} {:<19} {:>12}  {}",
+                    record.id,
+                    record.status,
+                    started,
+                    progress,
+                    shlex::try_join(record.args.iter().map(String::as_str)).unwrap_or_else(|_| record.args.join(" "))
+                );
+            }
+            Ok(())
+        }
+        RunsCommand::Resume { id } => {
+            let run = Run::load(id)?;
+            if run.record.status == "done" {
+                return Err(format!("run {} already finished", id).into());
+            }
+            std::env::set_current_dir(&run.record.cwd)
+                .map_err(|e| format!("can't enter the run's directory '{}': {}", run.record.cwd, e))?;
+            let args: Vec<OsString> = std::iter::once("mnemonic_validator".into())
+                .chain(run.record.args.iter().map(OsString::from))
+                .collect();
+            let cli = crate::parse_args(&args)?;
+            crate::run(&cli, &args[1..], Some(run));
+            Ok(())
+        }
+        RunsCommand::Clean { all } => {
+            let mut removed = 0;
+            for run in all_runs()? {
+                if *all || run.record.status == "done" {
+                    fs::remove_dir_all(&run.dir)?;
+                    removed += 1;
+                }
+            }
+            println!("Removed {} run(s)", removed);
+            Ok(())
+        }
+        RunsCommand::Audit { file } => {
+            let path = file.clone().unwrap_or_else(audit::default_path);
+            if !path.exists() {
+                println!("No audit log at {}", path.display());
+                return Ok(());
+            }
+            for line in fs::read_to_string(&path)?.lines() {
+                let Ok(entry) = serde_json::from_str::<serde_json::Value>(line) else {
+                    continue;
+                };
+                let time = chrono::DateTime::parse_from_rfc3339(entry["time"].as_str().unwrap_or_default())
+                    .map(|t| t.format("%Y-%m-%d %H:%M:%S").to_string())
+                    .unwrap_or_default();
+                let detail = match entry["event"].as_str() {
+                    Some("started") => entry["inputs"]
+                        .as_array()
+                        .map(|inputs| {
+                            inputs.iter().filter_map(|input| input["path"].as_str()).collect::<Vec<_>>().join(", ")
+                        })
+                        .unwrap_or_default(),
+                    Some("finished") => format!(
+                        "{} processed, {} valid",
+                        entry["results"]["processed"], entry["results"]["valid"]
+                    ),
+                    Some("interrupted") => format!("at line {}", entry["position"]),
+                    Some("failed") => entry["error"].as_str().unwrap_or_default().to_string(),
+                    Some("revealed") if entry["bundle"].is_string() => {
+                        format!("bundle {}", entry["bundle"].as_str().unwrap_or_default())
+                    }
+                    Some("revealed") => format!(
+                        "{} phrase(s) from {}",
+                        entry["digests"].as_array().map_or(0, Vec::len),
+                        entry["sealed"].as_str().unwrap_or_default()
+                    ),
+                    Some("exported") => format!(
+                        "{} line(s) to {}",
+                        entry["hits"],
+                        entry["bundle"].as_str().unwrap_or_default()
+                    ),
+                    Some("imported") => format!(
+                        "{} added, {} updated, {} conflicting, into {}",
+                        entry["added"],
+                        entry["updated"],
+                        entry["conflicts"],
+                        entry["index"].as_str().unwrap_or_default()
+                    ),
+                    _ => String::new(),
+                };
+                println!(
+                    "{:<19} {:<12} {:<18} {:<16} {}",
+                    time,
+                    entry["event"].as_str().unwrap_or_default(),
+                    entry["run"].as_str().unwrap_or("-"),
+                    format!("{}@{}", entry["user"].as_str().unwrap_or_default(), entry["host"].as_str().unwrap_or_default()),
+                    detail
+                );
+            }
+            let events = audit::verify(&path)?;
+            println!("{} events in {}, chain intact", events, path.display());
+            Ok(())
+        }
+    }
+}
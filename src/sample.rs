@@ -0,0 +1,35 @@
+//! Deterministic line sampling for `--sample`.
+//!
+//! Whether a line is sampled depends only on its index and the seed, so a
+//! resumed run keeps validating exactly the same subset.
+
+/// Parses a sampling rate given as a fraction (`0.01`) or a percentage (`1%`).
+pub fn parse_rate(s: &str) -> Result<f64, String> {
+    let rate = match s.strip_suffix('%') {
+        Some(percent) => percent.trim().parse::<f64>().map(|p| p / 100.0),
+        None => s.trim().parse::<f64>(),
+    }
+    .map_err(|_| format!("invalid sampling rate '{}'", s))?;
+
+    if rate > 0.0 && rate <= 1.0 {
+        Ok(rate)
+    } else {
+        Err(format!("sampling rate must be in (0, 1], got '{}'", s))
+    }
+}
+
+/// SplitMix64 finalizer; spreads consecutive line indices evenly.
+fn mix(mut x: u64) -> u64 {
+    x = (x ^ (x >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94d049bb133111eb);
+    x ^ (x >> 31)
+}
+
+/// Returns true if line `index` belongs to the sample.
+pub fn keep(index: usize, rate: f64, seed: u64) -> bool {
+    if rate >= 1.0 {
+        return true;
+    }
+    let x = mix((index as u64).wrapping_add(mix(seed)));
+    (x as f64 / u64::MAX as f64) < rate
+}
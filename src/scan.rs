@@ -0,0 +1,141 @@
+//! Single pass over an input file before validation starts.
+//!
+//! Besides counting lines for progress reporting, the pass hashes the whole
+//! file and a set of sample lines at fixed offsets. The hashes end up in the
+//! run manifest and in the checkpoint fingerprint used to verify resumes.
+
+use crate::digest::to_hex;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::{
+    fs::File,
+    io::{self, Read},
+    path::Path,
+};
+
+/// Hash of a single input line.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct LineSample {
+    /// 0-based line index.
+    pub line: usize,
+    pub sha256: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct InputScan {
+    /// Counted the same way [`crate::lines::lines`] splits them.
+    pub lines: usize,
+    pub bytes: u64,
+    pub sha256: String,
+    pub samples: Vec<LineSample>,
+}
+
+/// Sampled line indices: 0, 1, 2, 3, 4, 6, 8, 12, 16, 24, ... They don't
+/// depend on the file length, so the samples of a file that has since been
+/// appended to still line up with the ones recorded earlier.
+pub fn is_sample_line(line: usize) -> bool {
+    if line < 4 {
+        return true;
+    }
+    let shift = usize::BITS - 1 - line.leading_zeros();
+    let power = 1usize << shift;
+    line == power || line == power + power / 2
+}
+
+/// Counts lines and hashes the sampled ones, given the file a chunk at a
+/// time.
+struct Counter {
+    line: usize,
+    line_hasher: Option<Sha256>,
+    samples: Vec<LineSample>,
+}
+
+impl Counter {
+    /// Ends the current line with `tail`, its last bytes before the break.
+    fn end_line(&mut self, tail: &[u8]) {
+        if let Some(mut h) = self.line_hasher.take() {
+            h.update(tail);
+            self.samples.push(LineSample {
+                line: self.line,
+                sha256: to_hex(&h.finalize()),
+            });
+        }
+        self.line += 1;
+        if is_sample_line(self.line) {
+            self.line_hasher = Some(Sha256::new());
+        }
+    }
+
+    fn add(&mut self, bytes: &[u8]) {
+        if let Some(h) = self.line_hasher.as_mut() {
+            h.update(bytes);
+        }
+    }
+}
+
+pub fn scan_input(path: &Path) -> io::Result<InputScan> {
+    let mut file = File::open(path)?;
+    let mut hasher = Sha256::new();
+    let mut buf = vec![0u8; 1 << 16];
+    let mut bytes = 0u64;
+    let mut counter = Counter {
+        line: 0,
+        line_hasher: Some(Sha256::new()),
+        samples: Vec::new(),
+    };
+    // Whether the last chunk ended in a `\r`, which is a line break of its
+    // own unless a `\n` follows
+    let mut cr = false;
+    // Whether there are bytes after the last line break
+    let mut open = false;
+
+    loop {
+        let n = file.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        let chunk = &buf[..n];
+        hasher.update(chunk);
+        bytes += n as u64;
+
+        let mut start = 0;
+        if std::mem::take(&mut cr) {
+            if chunk[0] == b'\n' {
+                // A `\r\n` line's sample takes the `\r` in, as it always has
+                counter.add(b"\r");
+            } else {
+                counter.end_line(b"");
+            }
+        }
+        for end in memchr::memchr2_iter(b'\n', b'\r', chunk) {
+            if chunk[end] == b'\r' {
+                match chunk.get(end + 1) {
+                    Some(b'\n') => continue,
+                    Some(_) => {}
+                    None => {
+                        counter.add(&chunk[start..end]);
+                        start = n;
+                        cr = true;
+                        break;
+                    }
+                }
+            }
+            counter.end_line(&chunk[start..end]);
+            start = end + 1;
+        }
+        counter.add(&chunk[start..]);
+        open = start < n;
+    }
+
+    if cr || open {
+        // A final lone `\r`, or an unterminated last line
+        counter.end_line(b"");
+    }
+
+    Ok(InputScan {
+        lines: counter.line,
+        bytes,
+        sha256: to_hex(&hasher.finalize()),
+        samples: counter.samples,
+    })
+}
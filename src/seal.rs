@@ -0,0 +1,200 @@
+//! Sealed hits, for splitting a recovery between people who may see only
+//! masked results and the one who may see the phrases.
+//!
+//! A run with `--seal-to` writes masked output, and each hit's phrase
+//! encrypted to a public key into `<output>.sealed`, one per line after the
+//! digest its masked line shows. Only the holder of the matching secret key
+//! can open them, and `reveal` opens just the ones whose digests were
+//! confirmed.
+//!
+//! Each phrase gets a fresh key pair, whose public half is sent along:
+//! Diffie-Hellman with the recipient's key on secp256k1 (libsecp256k1), then
+//! SHA-512 of the shared point gives an AES-256-CTR key and an HMAC-SHA256
+//! key. The ciphertext starts from a random IV, and the tag over all of it
+//! is checked in constant time before anything is decrypted.
+//!
+//! Sealed data starts with a version byte.
+
+use crate::{
+    curve, digest, mask,
+    secret::{SecretString, Zeroizing},
+};
+use aes::{
+    cipher::{KeyIvInit, StreamCipher},
+    Aes256,
+};
+use hmac::{Hmac, Mac};
+use secp256k1::{ecdh, PublicKey, SecretKey};
+use sha2::{Digest, Sha256, Sha512};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+type Aes256Ctr = ctr::Ctr128BE<Aes256>;
+
+const VERSION: u8 = 1;
+const KEY: usize = 33;
+const IV: usize = 16;
+const TAG: usize = 32;
+
+/// Where the sealed hits of `output` go.
+pub fn sealed_path(output: &Path) -> PathBuf {
+    let mut path = output.as_os_str().to_owned();
+    path.push(".sealed");
+    PathBuf::from(path)
+}
+
+fn decode_hex(s: &str) -> Option<Vec<u8>> {
+    if !s.len().is_multiple_of(2) || !s.is_ascii() {
+        return None;
+    }
+    s.as_bytes()
+        .chunks(2)
+        .map(|pair| u8::from_str_radix(std::str::from_utf8(pair).ok()?, 16).ok())
+        .collect()
+}
+
+fn parse_hex<const N: usize>(s: &str) -> Option<[u8; N]> {
+    Zeroizing::new(decode_hex(s.trim())?).as_slice().try_into().ok()
+}
+
+/// A public key to seal to, given in hex or as a file holding it, for clap.
+pub fn parse_public_key(s: &str) -> Result<[u8; 33], String> {
+    let text = match parse_hex::<33>(s) {
+        Some(_) => s.to_string(),
+        None => fs::read_to_string(s).map_err(|e| format!("'{}' is neither a public key nor a file holding one: {}", s, e))?,
+    };
+    parse_hex(&text)
+        .filter(curve::is_public_key)
+        .ok_or_else(|| format!("'{}' doesn't hold a public key (66 hex digits)", s))
+}
+
+/// Reads a secret key file written by [`generate`].
+pub fn read_secret_key(path: &Path) -> Result<Zeroizing<[u8; 32]>, String> {
+    let text = SecretString::new(fs::read_to_string(path).map_err(|e| format!("can't read {}: {}", path.display(), e))?);
+    parse_hex(&text)
+        .filter(curve::is_scalar)
+        .map(Zeroizing::new)
+        .ok_or_else(|| format!("{} doesn't hold a secret key (64 hex digits)", path.display()))
+}
+
+/// A new key pair: the secret key and its public key, both in hex.
+pub fn generate() -> Result<(SecretString, String), String> {
+    let secret = random_scalar()?;
+    let public = curve::public_key(&secret);
+    Ok((SecretString::new(digest::to_hex(&*secret)), digest::to_hex(&public)))
+}
+
+fn random_scalar() -> Result<Zeroizing<[u8; 32]>, String> {
+    let mut k = Zeroizing::new([0; 32]);
+    // All but a vanishing share of 256-bit numbers will do
+    while !curve::is_scalar(&k) {
+        getrandom::getrandom(&mut *k).map_err(|e| format!("no random numbers for a key: {}", e))?;
+    }
+    Ok(k)
+}
+
+/// The compressed point `k·P` of a valid private key `k` and a public key
+/// `P`; `None` if `public` isn't a point on the curve.
+fn shared_point(k: &[u8; 32], public: &[u8; 33]) -> Option<Zeroizing<[u8; 33]>> {
+    if !curve::is_public_key(public) {
+        return None;
+    }
+    let mut k = SecretKey::from_slice(k).ok()?;
+    let point = Zeroizing::new(ecdh::shared_secret_point(&PublicKey::from_slice(public).ok()?, &k));
+    k.non_secure_erase();
+    let mut shared = Zeroizing::new([0; 33]);
+    shared[0] = 2 | (point[63] & 1);
+    shared[1..].copy_from_slice(&point[..32]);
+    Some(shared)
+}
+
+/// The cipher and MAC keys shared by an ephemeral and a recipient key.
+fn keys(shared: &[u8; 33], ephemeral: &[u8; 33]) -> (Zeroizing<[u8; 32]>, Zeroizing<[u8; 32]>) {
+    let output = Zeroizing::new(<[u8; 64]>::from(Sha512::new().chain_update(shared).chain_update(ephemeral).finalize()));
+    let (mut cipher, mut mac) = (Zeroizing::new([0; 32]), Zeroizing::new([0; 32]));
+    cipher.copy_from_slice(&output[..32]);
+    mac.copy_from_slice(&output[32..]);
+    (cipher, mac)
+}
+
+fn mac(key: &[u8; 32], data: &[u8]) -> Hmac<Sha256> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC takes any key length");
+    mac.update(data);
+    mac
+}
+
+/// `data` encrypted to `recipient`: the version, a fresh public key, the IV,
+/// the ciphertext and its tag.
+pub fn seal_bytes(recipient: &[u8; 33], data: &[u8]) -> Result<Vec<u8>, String> {
+    let k = random_scalar()?;
+    let mut iv = [0; IV];
+    getrandom::getrandom(&mut iv).map_err(|e| format!("no random numbers for an IV: {}", e))?;
+    seal_bytes_with(recipient, data, &k, &iv)
+}
+
+/// [`seal_bytes`] with the ephemeral key `k` and the IV given, for known
+/// answer tests; each has to be used only once.
+pub fn seal_bytes_with(recipient: &[u8; 33], data: &[u8], k: &[u8; 32], iv: &[u8; IV]) -> Result<Vec<u8>, String> {
+    if !curve::is_scalar(k) {
+        return Err("not a private key".to_string());
+    }
+    let ephemeral = curve::public_key(k);
+    let shared = shared_point(k, recipient).ok_or("not a public key")?;
+    let (cipher, mac_key) = keys(&shared, &ephemeral);
+    // Sized up front so that no copy of the data is left behind
+    let header = 1 + KEY + IV;
+    let mut sealed = Vec::with_capacity(header + data.len() + TAG);
+    sealed.push(VERSION);
+    sealed.extend_from_slice(&ephemeral);
+    sealed.extend_from_slice(iv);
+    sealed.extend_from_slice(data);
+    Aes256Ctr::new(cipher.as_ref().into(), iv.into()).apply_keystream(&mut sealed[header..]);
+    let tag = mac(&mac_key, &sealed).finalize().into_bytes();
+    sealed.extend_from_slice(&tag);
+    Ok(sealed)
+}
+
+/// Opens what [`seal_bytes`] sealed, with the recipient's secret key.
+pub fn open_bytes(secret: &[u8; 32], sealed: &[u8]) -> Result<Zeroizing<Vec<u8>>, String> {
+    let header = 1 + KEY + IV;
+    if sealed.first() != Some(&VERSION) || sealed.len() <= header + TAG {
+        return Err("not sealed data".to_string());
+    }
+    let (message, tag) = sealed.split_at(sealed.len() - TAG);
+    let ephemeral: [u8; KEY] = message[1..1 + KEY].try_into().unwrap();
+    let iv: [u8; IV] = message[1 + KEY..header].try_into().unwrap();
+    let shared = shared_point(secret, &ephemeral).ok_or("not sealed data")?;
+    let (cipher, mac_key) = keys(&shared, &ephemeral);
+    mac(&mac_key, message)
+        .verify_slice(tag)
+        .map_err(|_| "sealed to another key, or damaged".to_string())?;
+    let mut data = Zeroizing::new(message[header..].to_vec());
+    Aes256Ctr::new(cipher.as_ref().into(), (&iv).into()).apply_keystream(&mut data);
+    Ok(data)
+}
+
+/// The line of the sealed file for `phrase`: its masked digest and the
+/// phrase encrypted to `recipient`.
+pub fn seal(recipient: &[u8; 33], phrase: &str) -> Result<String, String> {
+    let sealed = seal_bytes(recipient, phrase.as_bytes())?;
+    Ok(format!("sha256:{} {}", mask::fingerprint(phrase), digest::to_hex(&sealed)))
+}
+
+/// The digest and the sealed phrase of a line of the sealed file.
+pub fn split_line(line: &str) -> Option<(&str, &str)> {
+    let (digest, sealed) = line.trim().split_once(' ')?;
+    Some((digest.strip_prefix("sha256:")?, sealed))
+}
+
+/// Opens the sealed phrase of a line with the recipient's secret key.
+pub fn open(secret: &[u8; 32], sealed: &str) -> Result<SecretString, String> {
+    let bytes = decode_hex(sealed).ok_or("not a sealed phrase")?;
+    let mut phrase = open_bytes(secret, &bytes)?;
+    // Checked before the bytes are moved, so that an error leaves them to
+    // be wiped
+    std::str::from_utf8(&phrase).map_err(|_| "not a sealed phrase")?;
+    let phrase = String::from_utf8(std::mem::take(&mut *phrase)).expect("checked above");
+    Ok(SecretString::new(phrase))
+}
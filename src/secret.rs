@@ -0,0 +1,73 @@
+//! Helpers for keeping mnemonic phrases (and anything derived from them) out
+//! of freed memory.
+//!
+//! Phrases are held in [`SecretString`]s, which wipe their heap buffer when
+//! dropped, and hits are written through [`ZeroizingWriter`] instead of a
+//! plain `BufWriter` so the output buffer is wiped after every flush. Seeds
+//! and keys derived from a phrase should likewise be wrapped in
+//! [`Zeroizing`] as soon as they are created.
+
+use std::io::{self, Write};
+
+pub use zeroize::{Zeroize, Zeroizing};
+
+/// A heap string that is wiped when dropped.
+pub type SecretString = Zeroizing<String>;
+
+const DEFAULT_CAPACITY: usize = 8 * 1024;
+
+/// A buffered writer whose buffer is zeroed every time it is flushed to the
+/// underlying writer, and when the writer is dropped.
+///
+/// The buffer never grows past its initial capacity, so no stale copies are
+/// left behind by reallocation.
+pub struct ZeroizingWriter<W: Write> {
+    inner: W,
+    buf: Zeroizing<Vec<u8>>,
+}
+
+impl<W: Write> ZeroizingWriter<W> {
+    pub fn new(inner: W) -> Self {
+        Self::with_capacity(DEFAULT_CAPACITY, inner)
+    }
+
+    pub fn with_capacity(capacity: usize, inner: W) -> Self {
+        Self {
+            inner,
+            buf: Zeroizing::new(Vec::with_capacity(capacity)),
+        }
+    }
+
+    fn flush_buf(&mut self) -> io::Result<()> {
+        if !self.buf.is_empty() {
+            let result = self.inner.write_all(&self.buf);
+            self.buf.zeroize();
+            result?;
+        }
+        Ok(())
+    }
+}
+
+impl<W: Write> Write for ZeroizingWriter<W> {
+    fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+        if self.buf.len() + data.len() > self.buf.capacity() {
+            self.flush_buf()?;
+        }
+        if data.len() >= self.buf.capacity() {
+            return self.inner.write(data);
+        }
+        self.buf.extend_from_slice(data);
+        Ok(data.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.flush_buf()?;
+        self.inner.flush()
+    }
+}
+
+impl<W: Write> Drop for ZeroizingWriter<W> {
+    fn drop(&mut self) {
+        let _ = self.flush_buf();
+    }
+}
@@ -0,0 +1,70 @@
+//! SeedSigner's QR formats, for dumps of scanned or exported QR payloads.
+//!
+//! A SeedQR holds the English wordlist index of every word as four decimal
+//! digits, 48 digits for 12 words and 96 for 24. A CompactSeedQR holds the
+//! entropy itself, 16 or 32 bytes; as text it shows up in hex. Both decode
+//! to the phrase they stand for, which is then validated like any other.
+//!
+//! Indices carry the checksum word, so a SeedQR still has to pass it. The
+//! entropy of a CompactSeedQR has no checksum: any 16 or 32 bytes make a
+//! valid phrase, and so will any hash of that length in the input.
+
+use crate::secret::{SecretString, Zeroizing};
+use bip39::{Language, Mnemonic};
+
+/// Word counts a SeedQR can hold.
+const WORD_COUNTS: [usize; 2] = [12, 24];
+
+/// The phrase spelled out by the wordlist `indices`.
+fn phrase(indices: impl Iterator<Item = usize>) -> SecretString {
+    let words = Language::English.word_list();
+    // Room for 24 words of up to eight letters, so it's never reallocated
+    let mut phrase = SecretString::new(String::with_capacity(24 * 9));
+    for (i, index) in indices.enumerate() {
+        if i > 0 {
+            phrase.push(' ');
+        }
+        phrase.push_str(words[index]);
+    }
+    phrase
+}
+
+/// The phrase of a SeedQR's digits, or `None` if `text` isn't one.
+pub fn decode_standard(text: &str) -> Option<SecretString> {
+    let digits = text.as_bytes();
+    if !WORD_COUNTS.contains(&(digits.len() / 4))
+        || !digits.len().is_multiple_of(4)
+        || !digits.iter().all(u8::is_ascii_digit)
+    {
+        return None;
+    }
+    let indices = digits.chunks(4).map(|digits| {
+        digits
+            .iter()
+            .fold(0, |index, digit| index * 10 + usize::from(digit - b'0'))
+    });
+    if indices.clone().any(|index| index >= 2048) {
+        return None;
+    }
+    Some(phrase(indices))
+}
+
+/// The phrase of a CompactSeedQR's entropy written in hex, or `None` if
+/// `text` isn't one.
+pub fn decode_compact(text: &str) -> Option<SecretString> {
+    let hex = text.as_bytes();
+    // 128 or 256 bits of entropy, four to a digit
+    if !WORD_COUNTS.iter().any(|&words| hex.len() * 4 == words / 3 * 32)
+        || !hex.iter().all(u8::is_ascii_hexdigit)
+    {
+        return None;
+    }
+    let nibble = |digit: u8| (digit as char).to_digit(16).unwrap() as u8;
+    let entropy: Zeroizing<Vec<u8>> = Zeroizing::new(
+        hex.chunks(2)
+            .map(|pair| nibble(pair[0]) << 4 | nibble(pair[1]))
+            .collect(),
+    );
+    let mnemonic = Mnemonic::from_entropy_in(Language::English, &entropy).ok()?;
+    Some(phrase(mnemonic.word_indices()))
+}
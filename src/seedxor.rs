@@ -0,0 +1,65 @@
+//! Coldcard-style Seed XOR: a phrase split into shares that are phrases of
+//! their own, whose entropies XOR to the original's.
+//!
+//! Every share is a valid BIP39 phrase of the same length as the secret
+//! (its checksum recomputed), and so is any XOR of shares. That also means
+//! nothing tells whether a set of shares belongs together: combining the
+//! wrong ones gives a perfectly valid phrase of an empty wallet.
+
+use crate::secret::Zeroizing;
+use bip39::{Language, Mnemonic};
+
+/// Shares a phrase can be split into, or combined from.
+pub const SHARES: std::ops::RangeInclusive<usize> = 2..=4;
+
+fn check_count(shares: usize) -> Result<(), String> {
+    if SHARES.contains(&shares) {
+        Ok(())
+    } else {
+        Err(format!("Seed XOR takes 2 to 4 shares, not {}", shares))
+    }
+}
+
+/// The entropy of `mnemonic`, checksum left out.
+fn entropy(mnemonic: &Mnemonic) -> Zeroizing<Vec<u8>> {
+    let (bytes, len) = mnemonic.to_entropy_array();
+    let bytes = Zeroizing::new(bytes);
+    Zeroizing::new(bytes[..len].to_vec())
+}
+
+fn from_entropy(entropy: &[u8], language: Language) -> Mnemonic {
+    // 16 to 32 bytes in steps of 4, taken from a valid phrase
+    Mnemonic::from_entropy_in(language, entropy).unwrap()
+}
+
+/// The phrase the shares were split from, in the language of the first.
+pub fn combine(shares: &[Mnemonic]) -> Result<Mnemonic, String> {
+    check_count(shares.len())?;
+    if shares.iter().any(|share| share.word_count() != shares[0].word_count()) {
+        return Err("all shares must have the same number of words".to_string());
+    }
+    let mut secret = entropy(&shares[0]);
+    for share in &shares[1..] {
+        for (byte, share) in secret.iter_mut().zip(entropy(share).iter()) {
+            *byte ^= share;
+        }
+    }
+    Ok(from_entropy(&secret, shares[0].language()))
+}
+
+/// Splits `mnemonic` into `parts` random shares in its language.
+pub fn split(mnemonic: &Mnemonic, parts: usize) -> Result<Vec<Mnemonic>, String> {
+    check_count(parts)?;
+    let mut last = entropy(mnemonic);
+    let mut shares = Vec::with_capacity(parts);
+    for _ in 1..parts {
+        let mut share = Zeroizing::new(vec![0; last.len()]);
+        getrandom::getrandom(&mut share).map_err(|e| format!("no random numbers for the shares: {}", e))?;
+        for (byte, share) in last.iter_mut().zip(share.iter()) {
+            *byte ^= share;
+        }
+        shares.push(from_entropy(&share, mnemonic.language()));
+    }
+    shares.push(from_entropy(&last, mnemonic.language()));
+    Ok(shares)
+}
@@ -0,0 +1,1119 @@
+use crate::corpus::{self, Rng};
+use bip39::{Language, Mnemonic};
+use clap::Args;
+use mnemonic_validator::{address, bip32, derive, diagnostics, electrum, digest::to_hex, lines, prefilter, queue, seal, seedqr, seedxor, slip39, wordlist, network::Network};
+
+/// Options of `mnemonic_validator selftest`.
+#[derive(Args, Debug)]
+pub struct SelftestArgs {
+    /// Also check round-trip invariants on random phrases: entropy, translation between wordlists, repair, SeedQR and Seed XOR.
+    #[clap(long)]
+    extended: bool,
+
+    /// Random phrases per invariant with --extended.
+    #[clap(long, value_name = "N", default_value_t = 2000, requires = "extended")]
+    cases: usize,
+
+    /// Seed for the random phrases, to rerun the cases of a failure; a new one each run by default.
+    #[clap(long, requires = "extended")]
+    seed: Option<u64>,
+}
+
+/// Results of one group of checks.
+struct Group {
+    name: &'static str,
+    passed: usize,
+    failures: Vec<String>,
+}
+
+impl Group {
+    fn new(name: &'static str) -> Self {
+        Self {
+            name,
+            passed: 0,
+            failures: Vec::new(),
+        }
+    }
+
+    fn check(&mut self, ok: bool, what: impl FnOnce() -> String) {
+        if ok {
+            self.passed += 1;
+        } else {
+            self.failures.push(what());
+        }
+    }
+
+    fn report(&self) {
+        let total = self.passed + self.failures.len();
+        if self.failures.is_empty() {
+            println!("PASS  {} ({} checks)", self.name, total);
+        } else {
+            println!("FAIL  {} ({} of {} checks failed)", self.name, self.failures.len(), total);
+            for failure in &self.failures {
+                println!("        {}", failure);
+            }
+        }
+    }
+}
+
+fn from_hex(hex: &str) -> Vec<u8> {
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).expect("test vectors are valid hex"))
+        .collect()
+}
+
+fn english_vectors() -> Group {
+    let mut group = Group::new("BIP39 English test vectors");
+    for (entropy, phrase, seed) in ENGLISH_VECTORS {
+        let short = phrase.split(' ').take(3).collect::<Vec<_>>().join(" ");
+
+        let generated = Mnemonic::from_entropy(&from_hex(entropy)).map(|m| m.to_string());
+        group.check(generated.as_deref() == Ok(phrase), || {
+            format!("entropy {} doesn't encode to '{} ...'", entropy, short)
+        });
+
+        group.check(prefilter::may_be_valid(phrase), || {
+            format!("pre-filter rejects '{} ...'", short)
+        });
+
+        match crate::parse(phrase) {
+            Some(mnemonic) => {
+                group.check(to_hex(&mnemonic.to_entropy()) == entropy, || {
+                    format!("'{} ...' doesn't decode to entropy {}", short, entropy)
+                });
+                group.check(to_hex(&mnemonic.to_seed_normalized("TREZOR")) == seed, || {
+                    format!("wrong seed for '{} ...'", short)
+                });
+            }
+            None => group.check(false, || format!("'{} ...' is rejected", short)),
+        }
+    }
+    group
+}
+
+fn japanese_vectors() -> Group {
+    let mut group = Group::new("BIP39 Japanese test vectors");
+    for (entropy, phrase, passphrase, seed) in JAPANESE_VECTORS {
+        // Normalizes the ideographic spaces and kana as required
+        match Mnemonic::parse_in(Language::Japanese, phrase) {
+            Ok(mnemonic) => {
+                group.check(to_hex(&mnemonic.to_entropy()) == entropy, || {
+                    format!("phrase for entropy {} decodes to {}", entropy, to_hex(&mnemonic.to_entropy()))
+                });
+                group.check(to_hex(&mnemonic.to_seed(passphrase)) == seed, || {
+                    format!("wrong seed for entropy {}", entropy)
+                });
+            }
+            Err(e) => group.check(false, || format!("phrase for entropy {} is rejected: {}", entropy, e)),
+        }
+
+        let generated = Mnemonic::from_entropy_in(Language::Japanese, &from_hex(entropy));
+        group.check(
+            generated.is_ok_and(|m| to_hex(&m.to_seed(passphrase)) == seed),
+            || format!("entropy {} doesn't round-trip to the expected seed", entropy),
+        );
+    }
+    group
+}
+
+/// Inputs a dump can contain that must not trip up the validator.
+fn adversarial_inputs() -> Group {
+    let zeros_24 = ENGLISH_VECTORS
+        .iter()
+        .map(|v| v.1)
+        .find(|phrase| phrase.split(' ').count() == 24)
+        .expect("there is a 24-word vector");
+    let abandon_12 = ENGLISH_VECTORS[0].1;
+
+    let cases: Vec<(&str, String, bool)> = vec![
+        ("bad checksum", "primary advice cage absurd amount doctor acoustic avoid letter advice cage above".into(), false),
+        ("unknown word", "getter advice cage absurd amount doctor acoustic avoid letter advice cage above".into(), false),
+        ("11 words", "advice cage absurd amount doctor acoustic avoid letter advice cage above".into(), false),
+        ("13 words", format!("{} abandon", abandon_12), false),
+        ("25 words", format!("{} abandon", zeros_24), false),
+        ("upper case", abandon_12.to_uppercase(), false),
+        ("empty line", String::new(), false),
+        ("only whitespace", " \t  ".into(), false),
+        ("tabs and extra spaces", format!("  {}\t ", abandon_12.replace(' ', " \t ")), true),
+        ("trailing carriage return", format!("{}\r", abandon_12), true),
+        ("NUL byte inside a word", abandon_12.replacen("abandon", "aban\0don", 1), false),
+        ("zero-width space inside a word", abandon_12.replacen("abandon", "aban\u{200b}don", 1), true),
+        ("very long line", "abandon ".repeat(100_000), false),
+        ("Japanese phrase", JAPANESE_VECTORS[0].1.into(), false),
+    ];
+
+    let mut group = Group::new("Adversarial inputs");
+    for (name, input, expect_valid) in cases {
+        let valid = crate::parse(&input).is_some();
+        group.check(valid == expect_valid, || {
+            format!("{}: expected {}", name, if expect_valid { "valid" } else { "invalid" })
+        });
+    }
+
+    let split: Vec<&[u8]> = lines::lines(b"a\r\nb\n\nc\rd\r").collect();
+    group.check(split == [&b"a"[..], b"b", b"", b"c", b"d"], || "line splitting".to_string());
+    let normalized = lines::normalize("\u{feff}abandon about \t\x0b".as_bytes());
+    group.check(normalized == b"abandon about", || "byte order mark and trailing whitespace".to_string());
+    group
+}
+
+/// The generated perfect-hash map agrees with every wordlist.
+fn wordlists() -> Group {
+    let mut group = Group::new("Wordlist lookup");
+    for &language in Language::ALL {
+        let wrong = language
+            .word_list()
+            .iter()
+            .enumerate()
+            .filter(|&(index, word)| wordlist::index_in(word, language) != Some(index as u16))
+            .count();
+        group.check(wrong == 0, || format!("{:?}: {} words map to the wrong index", language, wrong));
+    }
+    group
+}
+
+/// BIP32 extended keys, as the descriptor, wallet and Electrum exports
+/// write them and the Bitcoin address filters derive them.
+fn bip32_vectors() -> Group {
+    const XPUB: [u8; 4] = [0x04, 0x88, 0xB2, 0x1E];
+    const XPRV: [u8; 4] = [0x04, 0x88, 0xAD, 0xE4];
+    let mut group = Group::new("BIP32 test vectors");
+    for (seed, keys) in BIP32_VECTORS {
+        let Some(master) = bip32::ExtendedKey::master(&from_hex(seed)) else {
+            group.check(false, || format!("seed {} has no master key", seed));
+            continue;
+        };
+        for &(path, xpub, xprv) in keys {
+            let indices: Vec<u32> = path
+                .split('/')
+                .skip(1)
+                .map(|index| match index.strip_suffix('h') {
+                    Some(index) => index.parse::<u32>().expect("test vectors have valid paths") | bip32::HARDENED,
+                    None => index.parse().expect("test vectors have valid paths"),
+                })
+                .collect();
+            let derived;
+            let key = if indices.is_empty() {
+                &master
+            } else if let Some(key) = master.derive(&indices) {
+                derived = key;
+                &derived
+            } else {
+                group.check(false, || format!("no key at {} of seed {}", path, seed));
+                continue;
+            };
+            group.check(key.encode_public(XPUB) == xpub, || format!("wrong xpub at {} of seed {}", path, seed));
+            group.check(key.encode_private(XPRV).as_str() == xprv, || format!("wrong xprv at {} of seed {}", path, seed));
+        }
+    }
+    for (encoded, reason) in BIP32_INVALID_KEYS {
+        group.check(bip32::check_encoded(encoded, (XPUB, XPRV)).is_err(), || {
+            format!("{}... is taken although it's invalid ({})", &encoded[..16], reason)
+        });
+    }
+    group
+}
+
+/// Keys encrypted for Electrum wallet files as Electrum's own `pw_encode`
+/// encrypts them.
+fn electrum_vectors() -> Group {
+    let mut group = Group::new("Electrum wallet encryption vectors");
+    for (secret, password, iv, encrypted) in ELECTRUM_VECTORS {
+        let iv: [u8; 16] = from_hex(iv).try_into().expect("test vectors have 16-byte IVs");
+        group.check(electrum::encrypt_with_iv(secret, password, &iv) == encrypted, || {
+            format!("'{}...' encrypts to something Electrum won't decrypt", &secret[..8.min(secret.len())])
+        });
+    }
+    group
+}
+
+/// SLIP-10 Ed25519 keys, as Solana wallets derive them, and Cardano base
+/// addresses.
+fn ed25519_vectors() -> Group {
+    let mut group = Group::new("SLIP-10 Ed25519 and Cardano test vectors");
+    for (seed, keys) in SLIP10_VECTORS {
+        for &(path, chain_code, private, public) in keys {
+            let mut key = derive::Slip10Key::master(&from_hex(seed));
+            for index in path.split('/').skip(1) {
+                key = key.child(index.trim_end_matches('H').parse().expect("test vectors have valid paths"));
+            }
+            group.check(to_hex(key.chain_code()) == chain_code, || format!("wrong chain code at {} of seed {}", path, seed));
+            group.check(to_hex(key.private_key()) == private, || format!("wrong private key at {} of seed {}", path, seed));
+            group.check(format!("00{}", to_hex(&key.public_key())) == public, || {
+                format!("wrong public key at {} of seed {}", path, seed)
+            });
+        }
+    }
+    for (entropy, address) in CARDANO_VECTORS {
+        let mnemonic = Mnemonic::from_entropy(&from_hex(entropy)).expect("test vectors have valid entropy");
+        let derived = derive::cardano_addresses(&mnemonic, 1).next();
+        group.check(derived.as_deref() == Some(address), || format!("wrong Cardano address for entropy {}", entropy));
+    }
+    group
+}
+
+/// Ethereum addresses: MetaMask's first for a known phrase, and the
+/// checksummed examples of EIP-55, which no longer decode once the case of
+/// a letter is flipped.
+fn ethereum_vectors() -> Group {
+    let mut group = Group::new("Ethereum address vectors");
+    let ethereum = Network::find("ethereum").expect("Ethereum is built in");
+    for (phrase, expected) in ETHEREUM_VECTORS {
+        let seed = Mnemonic::parse_normalized(phrase).expect("test vectors are valid").to_seed_normalized("");
+        let path = [44 | bip32::HARDENED, 60 | bip32::HARDENED, bip32::HARDENED, 0, 0];
+        let derived = bip32::ExtendedKey::master(&seed)
+            .and_then(|master| master.derive(&path))
+            .and_then(|key| address::encode(address::Kind::Legacy, &key.public_key(), ethereum));
+        group.check(derived.as_deref() == Some(expected), || format!("wrong address for '{}'", phrase));
+    }
+    for checksummed in EIP55_VECTORS {
+        group.check(address::decode(checksummed, ethereum).is_some(), || format!("{} doesn't decode", checksummed));
+        let Some(last) = checksummed[2..].rfind(|c: char| c.is_ascii_alphabetic()) else {
+            continue;
+        };
+        let mut flipped = checksummed.as_bytes().to_vec();
+        flipped[2 + last] ^= 0x20;
+        let flipped = String::from_utf8(flipped).expect("hex stays ASCII");
+        group.check(address::decode(&flipped, ethereum).is_none(), || format!("{} decodes", flipped));
+    }
+    group
+}
+
+/// Sealed hits open to what was sealed: known answers from an independent
+/// implementation, then a fresh round trip and a damaged copy that has to be refused.
+fn seal_vectors() -> Group {
+    let mut group = Group::new("Sealed hit vectors");
+    let secret: [u8; 32] = from_hex(SEAL_SECRET).try_into().expect("test vectors are 32 bytes");
+    let recipient: [u8; 33] = from_hex(SEAL_RECIPIENT).try_into().expect("test vectors are 33 bytes");
+    for (k, iv, phrase, sealed) in SEAL_VECTORS {
+        let k: [u8; 32] = from_hex(k).try_into().expect("test vectors are 32 bytes");
+        let iv: [u8; 16] = from_hex(iv).try_into().expect("test vectors are 16 bytes");
+        let made = seal::seal_bytes_with(&recipient, phrase.as_bytes(), &k, &iv).map(|bytes| to_hex(&bytes));
+        group.check(made.as_deref() == Ok(sealed), || format!("'{}' doesn't seal to the known answer", phrase));
+        let opened = seal::open_bytes(&secret, &from_hex(sealed));
+        group.check(opened.is_ok_and(|data| *data == phrase.as_bytes()), || format!("the known answer doesn't open to '{}'", phrase));
+    }
+    let phrase = SEAL_VECTORS[0].2;
+    match seal::seal_bytes(&recipient, phrase.as_bytes()) {
+        Ok(mut sealed) => {
+            let opened = seal::open_bytes(&secret, &sealed);
+            group.check(opened.is_ok_and(|data| *data == phrase.as_bytes()), || "a fresh seal doesn't open".to_string());
+            let last = sealed.len() - 40;
+            sealed[last] ^= 1;
+            group.check(seal::open_bytes(&secret, &sealed).is_err(), || "a damaged seal opens".to_string());
+        }
+        Err(e) => group.check(false, || format!("can't seal: {}", e)),
+    }
+    group
+}
+
+/// Requests to SQS are signed as AWS's own SDK signs them.
+fn sigv4_vectors() -> Group {
+    let mut group = Group::new("AWS Signature Version 4 vectors");
+    let sign = |header: Option<(&str, &str)>| {
+        let mut headers = vec![("host", "some-endpoint.some-region.amazonaws.com"), ("x-amz-date", "20150830T123600Z")];
+        headers.extend(header);
+        let signing = queue::Signing {
+            region: "us-east-1",
+            service: "service",
+            time: "20150830T123600Z",
+            method: "GET",
+            headers: &headers,
+            body: b"",
+        };
+        signing.signature("notrealrnrELgWzOk3IfjzDKtFBhDby")
+    };
+    for (header, signature) in SIGV4_VECTORS {
+        group.check(sign(header) == signature, || format!("wrong signature with header {:?}", header));
+    }
+    let spaced = sign(Some(("some-header", "  test  test   ")));
+    group.check(spaced == sign(Some(("some-header", "test test"))), || "spaces in a header value are signed".to_string());
+    group
+}
+
+/// Official SLIP-39 test vectors (passphrase "TREZOR"): each set of shares
+/// combines into its master secret, or is refused.
+fn slip39_vectors() -> Group {
+    let mut group = Group::new("SLIP-39 test vectors");
+    let vectors: Vec<(String, Vec<String>, String)> =
+        serde_json::from_str(SLIP39_VECTORS).expect("the SLIP-39 vectors are valid JSON");
+    for (description, phrases, secret) in vectors {
+        let combined = phrases
+            .iter()
+            .map(|phrase| slip39::parse(phrase))
+            .collect::<Result<Vec<_>, _>>()
+            .and_then(|shares| slip39::combine(&shares, b"TREZOR"));
+        match combined {
+            Ok(combined) => group.check(to_hex(&combined) == secret, || {
+                format!("{}: combined into the wrong master secret", description)
+            }),
+            Err(e) => group.check(secret.is_empty(), || format!("{}: refused ({})", description, e)),
+        }
+    }
+    group
+}
+
+/// Entropy gives a phrase that validates and decodes to the same entropy.
+fn entropy_round_trips(rng: &mut Rng, cases: usize) -> Group {
+    let mut group = Group::new("Entropy round trips");
+    for _ in 0..cases {
+        let mnemonic = corpus::random_mnemonic(rng);
+        let phrase = mnemonic.to_string();
+        let entropy = to_hex(&mnemonic.to_entropy());
+        let parsed = crate::parse(&phrase);
+        group.check(parsed.as_ref() == Some(&mnemonic), || format!("entropy {} gives a phrase that's rejected", entropy));
+        group.check(parsed.is_some_and(|parsed| to_hex(&parsed.to_entropy()) == entropy), || {
+            format!("entropy {} doesn't decode back to itself", entropy)
+        });
+    }
+    group
+}
+
+/// A phrase moved to another wordlist word by word keeps its entropy, and
+/// comes back unchanged.
+fn translations(rng: &mut Rng, cases: usize) -> Group {
+    let mut group = Group::new("Translation between wordlists");
+    for _ in 0..cases {
+        let mnemonic = corpus::random_mnemonic(rng);
+        let language = Language::ALL[rng.below(Language::ALL.len())];
+        let words = language.word_list();
+        let translated: Vec<&str> = mnemonic.word_indices().map(|index| words[index]).collect();
+        let entropy = to_hex(&mnemonic.to_entropy());
+        match Mnemonic::parse_in(language, translated.join(" ")) {
+            Ok(translated) => {
+                group.check(to_hex(&translated.to_entropy()) == entropy, || {
+                    format!("entropy {} changes in {:?}", entropy, language)
+                });
+                let back: Vec<&str> = translated
+                    .word_indices()
+                    .map(|index| Language::English.word_list()[index])
+                    .collect();
+                group.check(back.join(" ") == mnemonic.to_string(), || {
+                    format!("entropy {} doesn't come back from {:?}", entropy, language)
+                });
+            }
+            Err(e) => group.check(false, || format!("entropy {} in {:?} is rejected: {}", entropy, language, e)),
+        }
+    }
+    group
+}
+
+/// Repair leaves a valid phrase as it is, and finds it among its own
+/// one-word typos' repairs.
+fn repairs(rng: &mut Rng, cases: usize) -> Group {
+    let mut group = Group::new("Repair of valid phrases");
+    for _ in 0..cases {
+        let phrase = corpus::random_mnemonic(rng).to_string();
+        let short = phrase.split(' ').take(3).collect::<Vec<_>>().join(" ");
+        let repaired = diagnostics::repair(&phrase);
+        group.check(repaired.len() == 1 && repaired[0].as_str() == phrase, || {
+            format!("repair changes the valid phrase '{} ...'", short)
+        });
+
+        // A doubled letter is one edit away, so the phrase is always among
+        // the suggestions
+        let mut words: Vec<String> = phrase.split(' ').map(String::from).collect();
+        let typo = rng.below(words.len());
+        let last = words[typo].chars().last().unwrap_or_default();
+        words[typo].push(last);
+        let repaired = diagnostics::repair(&words.join(" "));
+        group.check(repaired.iter().any(|candidate| candidate.as_str() == phrase), || {
+            format!("repair doesn't undo '{}' in '{} ...'", words[typo], short)
+        });
+    }
+    group
+}
+
+/// Encoding a phrase as a SeedQR or splitting it into Seed XOR shares, and
+/// back, gives the same phrase.
+fn conversions(rng: &mut Rng, cases: usize) -> Group {
+    let mut group = Group::new("SeedQR and Seed XOR round trips");
+    for _ in 0..cases {
+        let mnemonic = corpus::random_mnemonic(rng);
+        let phrase = mnemonic.to_string();
+        let entropy = mnemonic.to_entropy();
+
+        // SeedQR only holds 12 and 24 words
+        if matches!(entropy.len(), 16 | 32) {
+            let digits: String = mnemonic.word_indices().map(|index| format!("{:04}", index)).collect();
+            let decoded = seedqr::decode_standard(&digits);
+            group.check(decoded.as_deref().map(String::as_str) == Some(phrase.as_str()), || {
+                format!("SeedQR {} doesn't decode to its phrase", digits)
+            });
+            let hex = to_hex(&entropy);
+            let decoded = seedqr::decode_compact(&hex);
+            group.check(decoded.as_deref().map(String::as_str) == Some(phrase.as_str()), || {
+                format!("CompactSeedQR {} doesn't decode to its phrase", hex)
+            });
+        }
+
+        let parts = 2 + rng.below(3);
+        let combined = seedxor::split(&mnemonic, parts).and_then(|shares| seedxor::combine(&shares));
+        group.check(combined.as_ref() == Ok(&mnemonic), || {
+            format!("entropy {} doesn't come back from {} Seed XOR shares", to_hex(&entropy), parts)
+        });
+    }
+    group
+}
+
+/// Runs every check and fails if any of them does.
+pub fn run(args: &SelftestArgs) -> Result<(), Box<dyn std::error::Error>> {
+    let mut groups = vec![
+        english_vectors(),
+        japanese_vectors(),
+        adversarial_inputs(),
+        wordlists(),
+        bip32_vectors(),
+        electrum_vectors(),
+        ed25519_vectors(),
+        ethereum_vectors(),
+        seal_vectors(),
+        sigv4_vectors(),
+        slip39_vectors(),
+    ];
+    if args.extended {
+        let seed = args.seed.unwrap_or_else(|| {
+            std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map_or(0, |elapsed| elapsed.as_nanos() as u64)
+        });
+        println!("Invariants on {} random phrases each, --seed {}", args.cases, seed);
+        let mut rng = Rng::new(seed);
+        groups.push(entropy_round_trips(&mut rng, args.cases));
+        groups.push(translations(&mut rng, args.cases));
+        groups.push(repairs(&mut rng, args.cases));
+        groups.push(conversions(&mut rng, args.cases));
+    }
+    for group in &groups {
+        group.report();
+    }
+
+    let failed: usize = groups.iter().map(|g| g.failures.len()).sum();
+    if failed > 0 {
+        return Err(format!("{} self-test checks failed", failed).into());
+    }
+    println!("All self-tests passed.");
+    Ok(())
+}
+
+/// The SLIP-39 test vectors of the reference implementation: (description,
+/// shares, master secret hex, or "" where the shares must be refused).
+const SLIP39_VECTORS: &str = include_str!("slip39_vectors.json");
+
+/// Official BIP39 English test vectors (passphrase "TREZOR"):
+/// (entropy, mnemonic, seed).
+const ENGLISH_VECTORS: [(&str, &str, &str); 24] = [
+    (
+        "00000000000000000000000000000000",
+        "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about",
+        "c55257c360c07c72029aebc1b53c05ed0362ada38ead3e3e9efa3708e53495531f09a6987599d18264c1e1c92f2cf141630c7a3c4ab7c81b2f001698e7463b04",
+    ),
+    (
+        "7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f",
+        "legal winner thank year wave sausage worth useful legal winner thank yellow",
+        "2e8905819b8723fe2c1d161860e5ee1830318dbf49a83bd451cfb8440c28bd6fa457fe1296106559a3c80937a1c1069be3a3a5bd381ee6260e8d9739fce1f607",
+    ),
+    (
+        "80808080808080808080808080808080",
+        "letter advice cage absurd amount doctor acoustic avoid letter advice cage above",
+        "d71de856f81a8acc65e6fc851a38d4d7ec216fd0796d0a6827a3ad6ed5511a30fa280f12eb2e47ed2ac03b5c462a0358d18d69fe4f985ec81778c1b370b652a8",
+    ),
+    (
+        "ffffffffffffffffffffffffffffffff",
+        "zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo wrong",
+        "ac27495480225222079d7be181583751e86f571027b0497b5b5d11218e0a8a13332572917f0f8e5a589620c6f15b11c61dee327651a14c34e18231052e48c069",
+    ),
+    (
+        "000000000000000000000000000000000000000000000000",
+        "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon agent",
+        "035895f2f481b1b0f01fcf8c289c794660b289981a78f8106447707fdd9666ca06da5a9a565181599b79f53b844d8a71dd9f439c52a3d7b3e8a79c906ac845fa",
+    ),
+    (
+        "7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f",
+        "legal winner thank year wave sausage worth useful legal winner thank year wave sausage worth useful legal will",
+        "f2b94508732bcbacbcc020faefecfc89feafa6649a5491b8c952cede496c214a0c7b3c392d168748f2d4a612bada0753b52a1c7ac53c1e93abd5c6320b9e95dd",
+    ),
+    (
+        "808080808080808080808080808080808080808080808080",
+        "letter advice cage absurd amount doctor acoustic avoid letter advice cage absurd amount doctor acoustic avoid letter always",
+        "107d7c02a5aa6f38c58083ff74f04c607c2d2c0ecc55501dadd72d025b751bc27fe913ffb796f841c49b1d33b610cf0e91d3aa239027f5e99fe4ce9e5088cd65",
+    ),
+    (
+        "ffffffffffffffffffffffffffffffffffffffffffffffff",
+        "zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo when",
+        "0cd6e5d827bb62eb8fc1e262254223817fd068a74b5b449cc2f667c3f1f985a76379b43348d952e2265b4cd129090758b3e3c2c49103b5051aac2eaeb890a528",
+    ),
+    (
+        "0000000000000000000000000000000000000000000000000000000000000000",
+        "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon art",
+        "bda85446c68413707090a52022edd26a1c9462295029f2e60cd7c4f2bbd3097170af7a4d73245cafa9c3cca8d561a7c3de6f5d4a10be8ed2a5e608d68f92fcc8",
+    ),
+    (
+        "7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f",
+        "legal winner thank year wave sausage worth useful legal winner thank year wave sausage worth useful legal winner thank year wave sausage worth title",
+        "bc09fca1804f7e69da93c2f2028eb238c227f2e9dda30cd63699232578480a4021b146ad717fbb7e451ce9eb835f43620bf5c514db0f8add49f5d121449d3e87",
+    ),
+    (
+        "8080808080808080808080808080808080808080808080808080808080808080",
+        "letter advice cage absurd amount doctor acoustic avoid letter advice cage absurd amount doctor acoustic avoid letter advice cage absurd amount doctor acoustic bless",
+        "c0c519bd0e91a2ed54357d9d1ebef6f5af218a153624cf4f2da911a0ed8f7a09e2ef61af0aca007096df430022f7a2b6fb91661a9589097069720d015e4e982f",
+    ),
+    (
+        "ffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff",
+        "zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo vote",
+        "dd48c104698c30cfe2b6142103248622fb7bb0ff692eebb00089b32d22484e1613912f0a5b694407be899ffd31ed3992c456cdf60f5d4564b8ba3f05a69890ad",
+    ),
+    (
+        "9e885d952ad362caeb4efe34a8e91bd2",
+        "ozone drill grab fiber curtain grace pudding thank cruise elder eight picnic",
+        "274ddc525802f7c828d8ef7ddbcdc5304e87ac3535913611fbbfa986d0c9e5476c91689f9c8a54fd55bd38606aa6a8595ad213d4c9c9f9aca3fb217069a41028",
+    ),
+    (
+        "6610b25967cdcca9d59875f5cb50b0ea75433311869e930b",
+        "gravity machine north sort system female filter attitude volume fold club stay feature office ecology stable narrow fog",
+        "628c3827a8823298ee685db84f55caa34b5cc195a778e52d45f59bcf75aba68e4d7590e101dc414bc1bbd5737666fbbef35d1f1903953b66624f910feef245ac",
+    ),
+    (
+        "68a79eaca2324873eacc50cb9c6eca8cc68ea5d936f98787c60c7ebc74e6ce7c",
+        "hamster diagram private dutch cause delay private meat slide toddler razor book happy fancy gospel tennis maple dilemma loan word shrug inflict delay length",
+        "64c87cde7e12ecf6704ab95bb1408bef047c22db4cc7491c4271d170a1b213d20b385bc1588d9c7b38f1b39d415665b8a9030c9ec653d75e65f847d8fc1fc440",
+    ),
+    (
+        "c0ba5a8e914111210f2bd131f3d5e08d",
+        "scheme spot photo card baby mountain device kick cradle pact join borrow",
+        "ea725895aaae8d4c1cf682c1bfd2d358d52ed9f0f0591131b559e2724bb234fca05aa9c02c57407e04ee9dc3b454aa63fbff483a8b11de949624b9f1831a9612",
+    ),
+    (
+        "6d9be1ee6ebd27a258115aad99b7317b9c8d28b6d76431c3",
+        "horn tenant knee talent sponsor spell gate clip pulse soap slush warm silver nephew swap uncle crack brave",
+        "fd579828af3da1d32544ce4db5c73d53fc8acc4ddb1e3b251a31179cdb71e853c56d2fcb11aed39898ce6c34b10b5382772db8796e52837b54468aeb312cfc3d",
+    ),
+    (
+        "9f6a2878b2520799a44ef18bc7df394e7061a224d2c33cd015b157d746869863",
+        "panda eyebrow bullet gorilla call smoke muffin taste mesh discover soft ostrich alcohol speed nation flash devote level hobby quick inner drive ghost inside",
+        "72be8e052fc4919d2adf28d5306b5474b0069df35b02303de8c1729c9538dbb6fc2d731d5f832193cd9fb6aeecbc469594a70e3dd50811b5067f3b88b28c3e8d",
+    ),
+    (
+        "23db8160a31d3e0dca3688ed941adbf3",
+        "cat swing flag economy stadium alone churn speed unique patch report train",
+        "deb5f45449e615feff5640f2e49f933ff51895de3b4381832b3139941c57b59205a42480c52175b6efcffaa58a2503887c1e8b363a707256bdd2b587b46541f5",
+    ),
+    (
+        "8197a4a47f0425faeaa69deebc05ca29c0a5b5cc76ceacc0",
+        "light rule cinnamon wrap drastic word pride squirrel upgrade then income fatal apart sustain crack supply proud access",
+        "4cbdff1ca2db800fd61cae72a57475fdc6bab03e441fd63f96dabd1f183ef5b782925f00105f318309a7e9c3ea6967c7801e46c8a58082674c860a37b93eda02",
+    ),
+    (
+        "066dca1a2bb7e8a1db2832148ce9933eea0f3ac9548d793112d9a95c9407efad",
+        "all hour make first leader extend hole alien behind guard gospel lava path output census museum junior mass reopen famous sing advance salt reform",
+        "26e975ec644423f4a4c4f4215ef09b4bd7ef924e85d1d17c4cf3f136c2863cf6df0a475045652c57eb5fb41513ca2a2d67722b77e954b4b3fc11f7590449191d",
+    ),
+    (
+        "f30f8c1da665478f49b001d94c5fc452",
+        "vessel ladder alter error federal sibling chat ability sun glass valve picture",
+        "2aaa9242daafcee6aa9d7269f17d4efe271e1b9a529178d7dc139cd18747090bf9d60295d0ce74309a78852a9caadf0af48aae1c6253839624076224374bc63f",
+    ),
+    (
+        "c10ec20dc3cd9f652c7fac2f1230f7a3c828389a14392f05",
+        "scissors invite lock maple supreme raw rapid void congress muscle digital elegant little brisk hair mango congress clump",
+        "7b4a10be9d98e6cba265566db7f136718e1398c71cb581e1b2f464cac1ceedf4f3e274dc270003c670ad8d02c4558b2f8e39edea2775c9e232c7cb798b069e88",
+    ),
+    (
+        "f585c11aec520db57dd353c69554b21a89b20fb0650966fa0a9d6f74fd989d8f",
+        "void come effort suffer camp survey warrior heavy shoot primary clutch crush open amazing screen patrol group space point ten exist slush involve unfold",
+        "01f5bced59dec48e362f2c45b5de68b9fd6c92c6634f44d6d40aab69056506f0e35524a518034ddc1192e1dacd32c1ed3eaa3c3b131c88ed8e7e54c49a5d0998",
+    ),
+];
+
+/// Official BIP39 Japanese test vectors: (entropy, mnemonic, passphrase,
+/// seed). The mnemonics are separated by ideographic spaces and are not
+/// NFKD-normalized.
+const JAPANESE_VECTORS: [(&str, &str, &str, &str); 24] = [
+    (
+        "00000000000000000000000000000000",
+        "あいこくしん　あいこくしん　あいこくしん　あいこくしん　あいこくしん　あいこくしん　あいこくしん　あいこくしん　あいこくしん　あいこくしん　あいこくしん　あおぞら",
+        "㍍ガバヴァぱばぐゞちぢ十人十色",
+        "a262d6fb6122ecf45be09c50492b31f92e9beb7d9a845987a02cefda57a15f9c467a17872029a9e92299b5cbdf306e3a0ee620245cbd508959b6cb7ca637bd55",
+    ),
+    (
+        "7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f",
+        "そつう　れきだい　ほんやく　わかす　りくつ　ばいか　ろせん　やちん　そつう　れきだい　ほんやく　わかめ",
+        "㍍ガバヴァぱばぐゞちぢ十人十色",
+        "aee025cbe6ca256862f889e48110a6a382365142f7d16f2b9545285b3af64e542143a577e9c144e101a6bdca18f8d97ec3366ebf5b088b1c1af9bc31346e60d9",
+    ),
+    (
+        "80808080808080808080808080808080",
+        "そとづら　あまど　おおう　あこがれる　いくぶん　けいけん　あたえる　いよく　そとづら　あまど　おおう　あかちゃん",
+        "㍍ガバヴァぱばぐゞちぢ十人十色",
+        "e51736736ebdf77eda23fa17e31475fa1d9509c78f1deb6b4aacfbd760a7e2ad769c714352c95143b5c1241985bcb407df36d64e75dd5a2b78ca5d2ba82a3544",
+    ),
+    (
+        "ffffffffffffffffffffffffffffffff",
+        "われる　われる　われる　われる　われる　われる　われる　われる　われる　われる　われる　ろんぶん",
+        "㍍ガバヴァぱばぐゞちぢ十人十色",
+        "4cd2ef49b479af5e1efbbd1e0bdc117f6a29b1010211df4f78e2ed40082865793e57949236c43b9fe591ec70e5bb4298b8b71dc4b267bb96ed4ed282c8f7761c",
+    ),
+    (
+        "000000000000000000000000000000000000000000000000",
+        "あいこくしん　あいこくしん　あいこくしん　あいこくしん　あいこくしん　あいこくしん　あいこくしん　あいこくしん　あいこくしん　あいこくしん　あいこくしん　あいこくしん　あいこくしん　あいこくしん　あいこくしん　あいこくしん　あいこくしん　あらいぐま",
+        "㍍ガバヴァぱばぐゞちぢ十人十色",
+        "d99e8f1ce2d4288d30b9c815ae981edd923c01aa4ffdc5dee1ab5fe0d4a3e13966023324d119105aff266dac32e5cd11431eeca23bbd7202ff423f30d6776d69",
+    ),
+    (
+        "7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f",
+        "そつう　れきだい　ほんやく　わかす　りくつ　ばいか　ろせん　やちん　そつう　れきだい　ほんやく　わかす　りくつ　ばいか　ろせん　やちん　そつう　れいぎ",
+        "㍍ガバヴァぱばぐゞちぢ十人十色",
+        "eaaf171efa5de4838c758a93d6c86d2677d4ccda4a064a7136344e975f91fe61340ec8a615464b461d67baaf12b62ab5e742f944c7bd4ab6c341fbafba435716",
+    ),
+    (
+        "808080808080808080808080808080808080808080808080",
+        "そとづら　あまど　おおう　あこがれる　いくぶん　けいけん　あたえる　いよく　そとづら　あまど　おおう　あこがれる　いくぶん　けいけん　あたえる　いよく　そとづら　いきなり",
+        "㍍ガバヴァぱばぐゞちぢ十人十色",
+        "aec0f8d3167a10683374c222e6e632f2940c0826587ea0a73ac5d0493b6a632590179a6538287641a9fc9df8e6f24e01bf1be548e1f74fd7407ccd72ecebe425",
+    ),
+    (
+        "ffffffffffffffffffffffffffffffffffffffffffffffff",
+        "われる　われる　われる　われる　われる　われる　われる　われる　われる　われる　われる　われる　われる　われる　われる　われる　われる　りんご",
+        "㍍ガバヴァぱばぐゞちぢ十人十色",
+        "f0f738128a65b8d1854d68de50ed97ac1831fc3a978c569e415bbcb431a6a671d4377e3b56abd518daa861676c4da75a19ccb41e00c37d086941e471a4374b95",
+    ),
+    (
+        "0000000000000000000000000000000000000000000000000000000000000000",
+        "あいこくしん　あいこくしん　あいこくしん　あいこくしん　あいこくしん　あいこくしん　あいこくしん　あいこくしん　あいこくしん　あいこくしん　あいこくしん　あいこくしん　あいこくしん　あいこくしん　あいこくしん　あいこくしん　あいこくしん　あいこくしん　あいこくしん　あいこくしん　あいこくしん　あいこくしん　あいこくしん　いってい",
+        "㍍ガバヴァぱばぐゞちぢ十人十色",
+        "23f500eec4a563bf90cfda87b3e590b211b959985c555d17e88f46f7183590cd5793458b094a4dccc8f05807ec7bd2d19ce269e20568936a751f6f1ec7c14ddd",
+    ),
+    (
+        "7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f",
+        "そつう　れきだい　ほんやく　わかす　りくつ　ばいか　ろせん　やちん　そつう　れきだい　ほんやく　わかす　りくつ　ばいか　ろせん　やちん　そつう　れきだい　ほんやく　わかす　りくつ　ばいか　ろせん　まんきつ",
+        "㍍ガバヴァぱばぐゞちぢ十人十色",
+        "cd354a40aa2e241e8f306b3b752781b70dfd1c69190e510bc1297a9c5738e833bcdc179e81707d57263fb7564466f73d30bf979725ff783fb3eb4baa86560b05",
+    ),
+    (
+        "8080808080808080808080808080808080808080808080808080808080808080",
+        "そとづら　あまど　おおう　あこがれる　いくぶん　けいけん　あたえる　いよく　そとづら　あまど　おおう　あこがれる　いくぶん　けいけん　あたえる　いよく　そとづら　あまど　おおう　あこがれる　いくぶん　けいけん　あたえる　うめる",
+        "㍍ガバヴァぱばぐゞちぢ十人十色",
+        "6b7cd1b2cdfeeef8615077cadd6a0625f417f287652991c80206dbd82db17bf317d5c50a80bd9edd836b39daa1b6973359944c46d3fcc0129198dc7dc5cd0e68",
+    ),
+    (
+        "ffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff",
+        "われる　われる　われる　われる　われる　われる　われる　われる　われる　われる　われる　われる　われる　われる　われる　われる　われる　われる　われる　われる　われる　われる　われる　らいう",
+        "㍍ガバヴァぱばぐゞちぢ十人十色",
+        "a44ba7054ac2f9226929d56505a51e13acdaa8a9097923ca07ea465c4c7e294c038f3f4e7e4b373726ba0057191aced6e48ac8d183f3a11569c426f0de414623",
+    ),
+    (
+        "77c2b00716cec7213839159e404db50d",
+        "せまい　うちがわ　あずき　かろう　めずらしい　だんち　ますく　おさめる　ていぼう　あたる　すあな　えしゃく",
+        "㍍ガバヴァぱばぐゞちぢ十人十色",
+        "344cef9efc37d0cb36d89def03d09144dd51167923487eec42c487f7428908546fa31a3c26b7391a2b3afe7db81b9f8c5007336b58e269ea0bd10749a87e0193",
+    ),
+    (
+        "b63a9c59a6e641f288ebc103017f1da9f8290b3da6bdef7b",
+        "ぬすむ　ふっかつ　うどん　こうりつ　しつじ　りょうり　おたがい　せもたれ　あつめる　いちりゅう　はんしゃ　ごますり　そんけい　たいちょう　らしんばん　ぶんせき　やすみ　ほいく",
+        "㍍ガバヴァぱばぐゞちぢ十人十色",
+        "b14e7d35904cb8569af0d6a016cee7066335a21c1c67891b01b83033cadb3e8a034a726e3909139ecd8b2eb9e9b05245684558f329b38480e262c1d6bc20ecc4",
+    ),
+    (
+        "3e141609b97933b66a060dcddc71fad1d91677db872031e85f4c015c5e7e8982",
+        "くのう　てぬぐい　そんかい　すろっと　ちきゅう　ほあん　とさか　はくしゅ　ひびく　みえる　そざい　てんすう　たんぴん　くしょう　すいようび　みけん　きさらぎ　げざん　ふくざつ　あつかう　はやい　くろう　おやゆび　こすう",
+        "㍍ガバヴァぱばぐゞちぢ十人十色",
+        "32e78dce2aff5db25aa7a4a32b493b5d10b4089923f3320c8b287a77e512455443298351beb3f7eb2390c4662a2e566eec5217e1a37467af43b46668d515e41b",
+    ),
+    (
+        "0460ef47585604c5660618db2e6a7e7f",
+        "あみもの　いきおい　ふいうち　にげる　ざんしょ　じかん　ついか　はたん　ほあん　すんぽう　てちがい　わかめ",
+        "㍍ガバヴァぱばぐゞちぢ十人十色",
+        "0acf902cd391e30f3f5cb0605d72a4c849342f62bd6a360298c7013d714d7e58ddf9c7fdf141d0949f17a2c9c37ced1d8cb2edabab97c4199b142c829850154b",
+    ),
+    (
+        "72f60ebac5dd8add8d2a25a797102c3ce21bc029c200076f",
+        "すろっと　にくしみ　なやむ　たとえる　へいこう　すくう　きない　けってい　とくべつ　ねっしん　いたみ　せんせい　おくりがな　まかい　とくい　けあな　いきおい　そそぐ",
+        "㍍ガバヴァぱばぐゞちぢ十人十色",
+        "9869e220bec09b6f0c0011f46e1f9032b269f096344028f5006a6e69ea5b0b8afabbb6944a23e11ebd021f182dd056d96e4e3657df241ca40babda532d364f73",
+    ),
+    (
+        "2c85efc7f24ee4573d2b81a6ec66cee209b2dcbd09d8eddc51e0215b0b68e416",
+        "かほご　きうい　ゆたか　みすえる　もらう　がっこう　よそう　ずっと　ときどき　したうけ　にんか　はっこう　つみき　すうじつ　よけい　くげん　もくてき　まわり　せめる　げざい　にげる　にんたい　たんそく　ほそく",
+        "㍍ガバヴァぱばぐゞちぢ十人十色",
+        "713b7e70c9fbc18c831bfd1f03302422822c3727a93a5efb9659bec6ad8d6f2c1b5c8ed8b0b77775feaf606e9d1cc0a84ac416a85514ad59f5541ff5e0382481",
+    ),
+    (
+        "eaebabb2383351fd31d703840b32e9e2",
+        "めいえん　さのう　めだつ　すてる　きぬごし　ろんぱ　はんこ　まける　たいおう　さかいし　ねんいり　はぶらし",
+        "㍍ガバヴァぱばぐゞちぢ十人十色",
+        "06e1d5289a97bcc95cb4a6360719131a786aba057d8efd603a547bd254261c2a97fcd3e8a4e766d5416437e956b388336d36c7ad2dba4ee6796f0249b10ee961",
+    ),
+    (
+        "7ac45cfe7722ee6c7ba84fbc2d5bd61b45cb2fe5eb65aa78",
+        "せんぱい　おしえる　ぐんかん　もらう　きあい　きぼう　やおや　いせえび　のいず　じゅしん　よゆう　きみつ　さといも　ちんもく　ちわわ　しんせいじ　とめる　はちみつ",
+        "㍍ガバヴァぱばぐゞちぢ十人十色",
+        "1fef28785d08cbf41d7a20a3a6891043395779ed74503a5652760ee8c24dfe60972105ee71d5168071a35ab7b5bd2f8831f75488078a90f0926c8e9171b2bc4a",
+    ),
+    (
+        "4fa1a8bc3e6d80ee1316050e862c1812031493212b7ec3f3bb1b08f168cabeef",
+        "こころ　いどう　きあつ　そうがんきょう　へいあん　せつりつ　ごうせい　はいち　いびき　きこく　あんい　おちつく　きこえる　けんとう　たいこ　すすめる　はっけん　ていど　はんおん　いんさつ　うなぎ　しねま　れいぼう　みつかる",
+        "㍍ガバヴァぱばぐゞちぢ十人十色",
+        "43de99b502e152d4c198542624511db3007c8f8f126a30818e856b2d8a20400d29e7a7e3fdd21f909e23be5e3c8d9aee3a739b0b65041ff0b8637276703f65c2",
+    ),
+    (
+        "18ab19a9f54a9274f03e5209a2ac8a91",
+        "うりきれ　さいせい　じゆう　むろん　とどける　ぐうたら　はいれつ　ひけつ　いずれ　うちあわせ　おさめる　おたく",
+        "㍍ガバヴァぱばぐゞちぢ十人十色",
+        "3d711f075ee44d8b535bb4561ad76d7d5350ea0b1f5d2eac054e869ff7963cdce9581097a477d697a2a9433a0c6884bea10a2193647677977c9820dd0921cbde",
+    ),
+    (
+        "18a2e1d81b8ecfb2a333adcb0c17a5b9eb76cc5d05db91a4",
+        "うりきれ　うねる　せっさたくま　きもち　めんきょ　へいたく　たまご　ぜっく　びじゅつかん　さんそ　むせる　せいじ　ねくたい　しはらい　せおう　ねんど　たんまつ　がいけん",
+        "㍍ガバヴァぱばぐゞちぢ十人十色",
+        "753ec9e333e616e9471482b4b70a18d413241f1e335c65cd7996f32b66cf95546612c51dcf12ead6f805f9ee3d965846b894ae99b24204954be80810d292fcdd",
+    ),
+    (
+        "15da872c95a13dd738fbf50e427583ad61f18fd99f628c417a61cf8343c90419",
+        "うちゅう　ふそく　ひしょ　がちょう　うけもつ　めいそう　みかん　そざい　いばる　うけとる　さんま　さこつ　おうさま　ぱんつ　しひょう　めした　たはつ　いちぶ　つうじょう　てさぎょう　きつね　みすえる　いりぐち　かめれおん",
+        "㍍ガバヴァぱばぐゞちぢ十人十色",
+        "346b7321d8c04f6f37b49fdf062a2fddc8e1bf8f1d33171b65074531ec546d1d3469974beccb1a09263440fc92e1042580a557fdce314e27ee4eabb25fa5e5fe",
+    ),
+];
+
+/// Electrum's `pw_encode` of a secret, with the IV given: (secret,
+/// password, IV, base64 of the IV and ciphertext).
+const ELECTRUM_VECTORS: [(&str, &str, &str, &str); 2] = [
+    (
+        "xprv9s21ZrQH143K3QTDL4LXw2F7HEK3wJUD2nW2nRk4stbPy6cq3jPPqjiChkVvvNKmPGJxWUtg6LnF5kejMRNNU3TGtRBeJgk33yuGBxrMPHi",
+        "hunter2",
+        "000102030405060708090a0b0c0d0e0f",
+        "AAECAwQFBgcICQoLDA0OD13SzE1bYAKConWpVWpuyTZKAapQUJDcTDkVcn0uHTRNZJBrGGzRsV6QTdjfDpguhWCarqwHOgW9pmhBzHAhDJWkSJ0OwYO1qLFM26CsnPCqaiqCsNUB03g/eGmI02q1EvFpkhTGEr5r3OF65cx2380=",
+    ),
+    ("abandon", "", "00000000000000000000000000000000", "AAAAAAAAAAAAAAAAAAAAAMEMMzaT437w/EH3PjLLdhg="),
+];
+
+/// A key of a SLIP-10 test vector: (path, chain code, private key, public
+/// key).
+type Slip10Key = (&'static str, &'static str, &'static str, &'static str);
+
+/// Official SLIP-10 Ed25519 test vectors: (seed, keys).
+const SLIP10_VECTORS: [(&str, &[Slip10Key]); 2] = [
+    (
+        "000102030405060708090a0b0c0d0e0f",
+        &[
+            (
+                "m",
+                "90046a93de5380a72b5e45010748567d5ea02bbf6522f979e05c0d8d8ca9fffb",
+                "2b4be7f19ee27bbf30c667b642d5f4aa69fd169872f8fc3059c08ebae2eb19e7",
+                "00a4b2856bfec510abab89753fac1ac0e1112364e7d250545963f135f2a33188ed",
+            ),
+            (
+                "m/0H",
+                "8b59aa11380b624e81507a27fedda59fea6d0b779a778918a2fd3590e16e9c69",
+                "68e0fe46dfb67e368c75379acec591dad19df3cde26e63b93a8e704f1dade7a3",
+                "008c8a13df77a28f3445213a0f432fde644acaa215fc72dcdf300d5efaa85d350c",
+            ),
+            (
+                "m/0H/1H",
+                "a320425f77d1b5c2505a6b1b27382b37368ee640e3557c315416801243552f14",
+                "b1d0bad404bf35da785a64ca1ac54b2617211d2777696fbffaf208f746ae84f2",
+                "001932a5270f335bed617d5b935c80aedb1a35bd9fc1e31acafd5372c30f5c1187",
+            ),
+            (
+                "m/0H/1H/2H",
+                "2e69929e00b5ab250f49c3fb1c12f252de4fed2c1db88387094a0f8c4c9ccd6c",
+                "92a5b23c0b8a99e37d07df3fb9966917f5d06e02ddbd909c7e184371463e9fc9",
+                "00ae98736566d30ed0e9d2f4486a64bc95740d89c7db33f52121f8ea8f76ff0fc1",
+            ),
+            (
+                "m/0H/1H/2H/2H",
+                "8f6d87f93d750e0efccda017d662a1b31a266e4a6f5993b15f5c1f07f74dd5cc",
+                "30d1dc7e5fc04c31219ab25a27ae00b50f6fd66622f6e9c913253d6511d1e662",
+                "008abae2d66361c879b900d204ad2cc4984fa2aa344dd7ddc46007329ac76c429c",
+            ),
+            (
+                "m/0H/1H/2H/2H/1000000000H",
+                "68789923a0cac2cd5a29172a475fe9e0fb14cd6adb5ad98a3fa70333e7afa230",
+                "8f94d394a8e8fd6b1bc2f3f49f5c47e385281d5c17e65324b0f62483e37e8793",
+                "003c24da049451555d51a7014a37337aa4e12d41e485abccfa46b47dfb2af54b7a",
+            ),
+        ],
+    ),
+    (
+        "fffcf9f6f3f0edeae7e4e1dedbd8d5d2cfccc9c6c3c0bdbab7b4b1aeaba8a5a29f9c999693908d8a8784817e7b7875726f6c696663605d5a5754514e4b484542",
+        &[
+            (
+                "m",
+                "ef70a74db9c3a5af931b5fe73ed8e1a53464133654fd55e7a66f8570b8e33c3b",
+                "171cb88b1b3c1db25add599712e36245d75bc65a1a5c9e18d76f9f2b1eab4012",
+                "008fe9693f8fa62a4305a140b9764c5ee01e455963744fe18204b4fb948249308a",
+            ),
+            (
+                "m/0H",
+                "0b78a3226f915c082bf118f83618a618ab6dec793752624cbeb622acb562862d",
+                "1559eb2bbec5790b0c65d8693e4d0875b1747f4970ae8b650486ed7470845635",
+                "0086fab68dcb57aa196c77c5f264f215a112c22a912c10d123b0d03c3c28ef1037",
+            ),
+            (
+                "m/0H/2147483647H",
+                "138f0b2551bcafeca6ff2aa88ba8ed0ed8de070841f0c4ef0165df8181eaad7f",
+                "ea4f5bfe8694d8bb74b7b59404632fd5968b774ed545e810de9c32a4fb4192f4",
+                "005ba3b9ac6e90e83effcd25ac4e58a1365a9e35a3d3ae5eb07b9e4d90bcf7506d",
+            ),
+            (
+                "m/0H/2147483647H/1H",
+                "73bd9fff1cfbde33a1b846c27085f711c0fe2d66fd32e139d3ebc28e5a4a6b90",
+                "3757c7577170179c7868353ada796c839135b3d30554bbb74a4b1e4a5a58505c",
+                "002e66aa57069c86cc18249aecf5cb5a9cebbfd6fadeab056254763874a9352b45",
+            ),
+            (
+                "m/0H/2147483647H/1H/2147483646H",
+                "0902fe8a29f9140480a00ef244bd183e8a13288e4412d8389d140aac1794825a",
+                "5837736c89570de861ebc173b1086da4f505d4adb387c6a1b1342d5e4ac9ec72",
+                "00e33c0f7d81d843c572275f287498e8d408654fdf0d1e065b84e2e6f157aab09b",
+            ),
+            (
+                "m/0H/2147483647H/1H/2147483646H/2H",
+                "5d70af781f3a37b829f0d060924d5e960bdc02e85423494afc0b1a41bbe196d4",
+                "551d333177df541ad876a60ea71f00447931c0a9da16f227c11ea080d7391b8d",
+                "0047150c75db263559a70d5778bf36abbab30fb061ad69f69ece61a72b0cfa4fc0",
+            ),
+        ],
+    ),
+];
+
+/// The first address of MetaMask's wallet (`m/44'/60'/0'/0/0`): (phrase,
+/// address).
+const ETHEREUM_VECTORS: [(&str, &str); 1] = [(
+    "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about",
+    "0x9858EfFD232B4033E47d90003D41EC34EcaEda94",
+)];
+
+/// The mixed-case examples of EIP-55.
+const EIP55_VECTORS: [&str; 4] = [
+    "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed",
+    "0xfB6916095ca1df60bB79Ce92cE3Ea74c37c5d359",
+    "0xdbF03B407c01E7cD3CBea99509d93f8DDDC8C6FB",
+    "0xD1220A0cf47c7B9Be7A2E6BA89F429762e7b9aDb",
+];
+
+/// Cardano mainnet base addresses of the first payment and stake keys
+/// (`m/1852'/1815'/0'/0/0` and `.../2/0`), as cardano-serialization-lib
+/// has them: (entropy, address).
+const CARDANO_VECTORS: [(&str, &str); 3] = [
+    (
+        "df9ed25ed146bf43336a5d7cf7395994",
+        "addr1qx2fxv2umyhttkxyxp8x0dlpdt3k6cwng5pxj3jhsydzer3jcu5d8ps7zex2k2xt3uqxgjqnnj83ws8lhrn648jjxtwqfjkjv7",
+    ),
+    (
+        "0ccb74f36b7da1649a8144675522d4d8097c6412",
+        "addr1q9u5vlrf4xkxv2qpwngf6cjhtw542ayty80v8dyr49rf5ewvxwdrt70qlcpeeagscasafhffqsxy36t90ldv06wqrk2qld6xc3",
+    ),
+    (
+        "4e828f9a67ddcff0e6391ad4f26ddb7579f59ba14b6dd4baf63dcfdb9d2420da",
+        "addr1qyy6nhfyks7wdu3dudslys37v252w2nwhv0fw2nfawemmn8k8ttq8f3gag0h89aepvx3xf69g0l9pf80tqv7cve0l33sdn8p3d",
+    ),
+];
+
+/// The recipient of [`SEAL_VECTORS`]: its secret key and public key.
+const SEAL_SECRET: &str = "1111111111111111111111111111111111111111111111111111111111111111";
+const SEAL_RECIPIENT: &str = "034f355bdcb7cc0af728ef3cceb9615d90684bb5b2ca5f859ab0f0b704075871aa";
+
+/// Phrases sealed by a separate Python implementation (pure-Python
+/// secp256k1, AES-CTR and HMAC from `cryptography`): (ephemeral key, IV,
+/// phrase, sealed).
+const SEAL_VECTORS: [(&str, &str, &str, &str); 1] = [
+    (
+        "2222222222222222222222222222222222222222222222222222222222222222",
+        "000102030405060708090a0b0c0d0e0f",
+        "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about",
+        "0102466d7fcae563e5cb09a0d1870bb580344804617879a14949cf22285f1bae3f27000102030405060708090a0b0c0d0e0f965e3b6829ba1e1c4fe6f9d93c4f3bb94f1a7853a68e35c36ae50521d188bc00e35b96a2aaa8e48dc1f45b6544f8c7bb4db46fc7b6439d27aa51b5c07507381403a357375e9c8cdb598cc5637d6d98aaa6e01967832d05ac689a04d5b2490c60e9c0161f588a4b493767f3aca7a677b222e730def36c95bf91c9791100",
+    ),
+];
+
+/// Signatures of a GET of `/` at `some-endpoint.some-region.amazonaws.com`
+/// by the AWS SDK for Rust's tests (`aws-sigv4`): (header besides `host`
+/// and `x-amz-date`, signature).
+const SIGV4_VECTORS: [(Option<(&str, &str)>, &str); 2] = [
+    (None, "ab32de057edf094958d178b3c91f3c8d5c296d526b11da991cd5773d09cea560"),
+    (Some(("some-header", "テスト")), "55e16b31f9bde5fd04f9d3b780dd2b5e5f11a5219001f91a8ca9ec83eaf1618f"),
+];
+
+/// A key of a BIP32 test vector: (path, xpub, xprv).
+type Bip32Key = (&'static str, &'static str, &'static str);
+
+/// Official BIP32 test vectors 1 to 4: (seed, keys).
+const BIP32_VECTORS: [(&str, &[Bip32Key]); 4] = [
+    (
+        "000102030405060708090a0b0c0d0e0f",
+        &[
+            (
+                "m",
+                "xpub661MyMwAqRbcFtXgS5sYJABqqG9YLmC4Q1Rdap9gSE8NqtwybGhePY2gZ29ESFjqJoCu1Rupje8YtGqsefD265TMg7usUDFdp6W1EGMcet8",
+                "xprv9s21ZrQH143K3QTDL4LXw2F7HEK3wJUD2nW2nRk4stbPy6cq3jPPqjiChkVvvNKmPGJxWUtg6LnF5kejMRNNU3TGtRBeJgk33yuGBxrMPHi",
+            ),
+            (
+                "m/0h",
+                "xpub68Gmy5EdvgibQVfPdqkBBCHxA5htiqg55crXYuXoQRKfDBFA1WEjWgP6LHhwBZeNK1VTsfTFUHCdrfp1bgwQ9xv5ski8PX9rL2dZXvgGDnw",
+                "xprv9uHRZZhk6KAJC1avXpDAp4MDc3sQKNxDiPvvkX8Br5ngLNv1TxvUxt4cV1rGL5hj6KCesnDYUhd7oWgT11eZG7XnxHrnYeSvkzY7d2bhkJ7",
+            ),
+            (
+                "m/0h/1",
+                "xpub6ASuArnXKPbfEwhqN6e3mwBcDTgzisQN1wXN9BJcM47sSikHjJf3UFHKkNAWbWMiGj7Wf5uMash7SyYq527Hqck2AxYysAA7xmALppuCkwQ",
+                "xprv9wTYmMFdV23N2TdNG573QoEsfRrWKQgWeibmLntzniatZvR9BmLnvSxqu53Kw1UmYPxLgboyZQaXwTCg8MSY3H2EU4pWcQDnRnrVA1xe8fs",
+            ),
+            (
+                "m/0h/1/2h",
+                "xpub6D4BDPcP2GT577Vvch3R8wDkScZWzQzMMUm3PWbmWvVJrZwQY4VUNgqFJPMM3No2dFDFGTsxxpG5uJh7n7epu4trkrX7x7DogT5Uv6fcLW5",
+                "xprv9z4pot5VBttmtdRTWfWQmoH1taj2axGVzFqSb8C9xaxKymcFzXBDptWmT7FwuEzG3ryjH4ktypQSAewRiNMjANTtpgP4mLTj34bhnZX7UiM",
+            ),
+            (
+                "m/0h/1/2h/2",
+                "xpub6FHa3pjLCk84BayeJxFW2SP4XRrFd1JYnxeLeU8EqN3vDfZmbqBqaGJAyiLjTAwm6ZLRQUMv1ZACTj37sR62cfN7fe5JnJ7dh8zL4fiyLHV",
+                "xprvA2JDeKCSNNZky6uBCviVfJSKyQ1mDYahRjijr5idH2WwLsEd4Hsb2Tyh8RfQMuPh7f7RtyzTtdrbdqqsunu5Mm3wDvUAKRHSC34sJ7in334",
+            ),
+            (
+                "m/0h/1/2h/2/1000000000",
+                "xpub6H1LXWLaKsWFhvm6RVpEL9P4KfRZSW7abD2ttkWP3SSQvnyA8FSVqNTEcYFgJS2UaFcxupHiYkro49S8yGasTvXEYBVPamhGW6cFJodrTHy",
+                "xprvA41z7zogVVwxVSgdKUHDy1SKmdb533PjDz7J6N6mV6uS3ze1ai8FHa8kmHScGpWmj4WggLyQjgPie1rFSruoUihUZREPSL39UNdE3BBDu76",
+            ),
+        ],
+    ),
+    (
+        "fffcf9f6f3f0edeae7e4e1dedbd8d5d2cfccc9c6c3c0bdbab7b4b1aeaba8a5a29f9c999693908d8a8784817e7b7875726f6c696663605d5a5754514e4b484542",
+        &[
+            (
+                "m",
+                "xpub661MyMwAqRbcFW31YEwpkMuc5THy2PSt5bDMsktWQcFF8syAmRUapSCGu8ED9W6oDMSgv6Zz8idoc4a6mr8BDzTJY47LJhkJ8UB7WEGuduB",
+                "xprv9s21ZrQH143K31xYSDQpPDxsXRTUcvj2iNHm5NUtrGiGG5e2DtALGdso3pGz6ssrdK4PFmM8NSpSBHNqPqm55Qn3LqFtT2emdEXVYsCzC2U",
+            ),
+            (
+                "m/0",
+                "xpub69H7F5d8KSRgmmdJg2KhpAK8SR3DjMwAdkxj3ZuxV27CprR9LgpeyGmXUbC6wb7ERfvrnKZjXoUmmDznezpbZb7ap6r1D3tgFxHmwMkQTPH",
+                "xprv9vHkqa6EV4sPZHYqZznhT2NPtPCjKuDKGY38FBWLvgaDx45zo9WQRUT3dKYnjwih2yJD9mkrocEZXo1ex8G81dwSM1fwqWpWkeS3v86pgKt",
+            ),
+            (
+                "m/0/2147483647h",
+                "xpub6ASAVgeehLbnwdqV6UKMHVzgqAG8Gr6riv3Fxxpj8ksbH9ebxaEyBLZ85ySDhKiLDBrQSARLq1uNRts8RuJiHjaDMBU4Zn9h8LZNnBC5y4a",
+                "xprv9wSp6B7kry3Vj9m1zSnLvN3xH8RdsPP1Mh7fAaR7aRLcQMKTR2vidYEeEg2mUCTAwCd6vnxVrcjfy2kRgVsFawNzmjuHc2YmYRmagcEPdU9",
+            ),
+            (
+                "m/0/2147483647h/1",
+                "xpub6DF8uhdarytz3FWdA8TvFSvvAh8dP3283MY7p2V4SeE2wyWmG5mg5EwVvmdMVCQcoNJxGoWaU9DCWh89LojfZ537wTfunKau47EL2dhHKon",
+                "xprv9zFnWC6h2cLgpmSA46vutJzBcfJ8yaJGg8cX1e5StJh45BBciYTRXSd25UEPVuesF9yog62tGAQtHjXajPPdbRCHuWS6T8XA2ECKADdw4Ef",
+            ),
+            (
+                "m/0/2147483647h/1/2147483646h",
+                "xpub6ERApfZwUNrhLCkDtcHTcxd75RbzS1ed54G1LkBUHQVHQKqhMkhgbmJbZRkrgZw4koxb5JaHWkY4ALHY2grBGRjaDMzQLcgJvLJuZZvRcEL",
+                "xprvA1RpRA33e1JQ7ifknakTFpgNXPmW2YvmhqLQYMmrj4xJXXWYpDPS3xz7iAxn8L39njGVyuoseXzU6rcxFLJ8HFsTjSyQbLYnMpCqE2VbFWc",
+            ),
+            (
+                "m/0/2147483647h/1/2147483646h/2",
+                "xpub6FnCn6nSzZAw5Tw7cgR9bi15UV96gLZhjDstkXXxvCLsUXBGXPdSnLFbdpq8p9HmGsApME5hQTZ3emM2rnY5agb9rXpVGyy3bdW6EEgAtqt",
+                "xprvA2nrNbFZABcdryreWet9Ea4LvTJcGsqrMzxHx98MMrotbir7yrKCEXw7nadnHM8Dq38EGfSh6dqA9QWTyefMLEcBYJUuekgW4BYPJcr9E7j",
+            ),
+        ],
+    ),
+    // Leading zeros of private keys are kept
+    (
+        "4b381541583be4423346c643850da4b320e46a87ae3d2a4e6da11eba819cd4acba45d239319ac14f863b8d5ab5a0d0c64d2e8a1e7d1457df2e5a3c51c73235be",
+        &[
+            (
+                "m",
+                "xpub661MyMwAqRbcEZVB4dScxMAdx6d4nFc9nvyvH3v4gJL378CSRZiYmhRoP7mBy6gSPSCYk6SzXPTf3ND1cZAceL7SfJ1Z3GC8vBgp2epUt13",
+                "xprv9s21ZrQH143K25QhxbucbDDuQ4naNntJRi4KUfWT7xo4EKsHt2QJDu7KXp1A3u7Bi1j8ph3EGsZ9Xvz9dGuVrtHHs7pXeTzjuxBrCmmhgC6",
+            ),
+            (
+                "m/0h",
+                "xpub68NZiKmJWnxxS6aaHmn81bvJeTESw724CRDs6HbuccFQN9Ku14VQrADWgqbhhTHBaohPX4CjNLf9fq9MYo6oDaPPLPxSb7gwQN3ih19Zm4Y",
+                "xprv9uPDJpEQgRQfDcW7BkF7eTya6RPxXeJCqCJGHuCJ4GiRVLzkTXBAJMu2qaMWPrS7AANYqdq6vcBcBUdJCVVFceUvJFjaPdGZ2y9WACViL4L",
+            ),
+        ],
+    ),
+    // Leading zeros of private keys are kept, in hardened derivation too
+    (
+        "3ddd5602285899a946114506157c7997e5444528f3003f6134712147db19b678",
+        &[
+            (
+                "m",
+                "xpub661MyMwAqRbcGczjuMoRm6dXaLDEhW1u34gKenbeYqAix21mdUKJyuyu5F1rzYGVxyL6tmgBUAEPrEz92mBXjByMRiJdba9wpnN37RLLAXa",
+                "xprv9s21ZrQH143K48vGoLGRPxgo2JNkJ3J3fqkirQC2zVdk5Dgd5w14S7fRDyHH4dWNHUgkvsvNDCkvAwcSHNAQwhwgNMgZhLtQC63zxwhQmRv",
+            ),
+            (
+                "m/0h",
+                "xpub69AUMk3qDBi3uW1sXgjCmVjJ2G6WQoYSnNHyzkmdCHEhSZ4tBok37xfFEqHd2AddP56Tqp4o56AePAgCjYdvpW2PU2jbUPFKsav5ut6Ch1m",
+                "xprv9vB7xEWwNp9kh1wQRfCCQMnZUEG21LpbR9NPCNN1dwhiZkjjeGRnaALmPXCX7SgjFTiCTT6bXes17boXtjq3xLpcDjzEuGLQBM5ohqkao9G",
+            ),
+            (
+                "m/0h/1h",
+                "xpub6BJA1jSqiukeaesWfxe6sNK9CCGaujFFSJLomWHprUL9DePQ4JDkM5d88n49sMGJxrhpjazuXYWdMf17C9T5XnxkopaeS7jGk1GyyVziaMt",
+                "xprv9xJocDuwtYCMNAo3Zw76WENQeAS6WGXQ55RCy7tDJ8oALr4FWkuVoHJeHVAcAqiZLE7Je3vZJHxspZdFHfnBEjHqU5hG1Jaj32dVoS6XLT1",
+            ),
+        ],
+    ),
+];
+
+/// Official BIP32 test vector 5: extended keys that must be rejected, and
+/// why.
+const BIP32_INVALID_KEYS: [(&str, &str); 16] = [
+    (
+        "xpub661MyMwAqRbcEYS8w7XLSVeEsBXy79zSzH1J8vCdxAZningWLdN3zgtU6LBpB85b3D2yc8sfvZU521AAwdZafEz7mnzBBsz4wKY5fTtTQBm",
+        "public version with a private key",
+    ),
+    (
+        "xprv9s21ZrQH143K24Mfq5zL5MhWK9hUhhGbd45hLXo2Pq2oqzMMo63oStZzFGTQQD3dC4H2D5GBj7vWvSQaaBv5cxi9gafk7NF3pnBju6dwKvH",
+        "private version with a public key",
+    ),
+    (
+        "xpub661MyMwAqRbcEYS8w7XLSVeEsBXy79zSzH1J8vCdxAZningWLdN3zgtU6Txnt3siSujt9RCVYsx4qHZGc62TG4McvMGcAUjeuwZdduYEvFn",
+        "public key prefix 04",
+    ),
+    (
+        "xprv9s21ZrQH143K24Mfq5zL5MhWK9hUhhGbd45hLXo2Pq2oqzMMo63oStZzFGpWnsj83BHtEy5Zt8CcDr1UiRXuWCmTQLxEK9vbz5gPstX92JQ",
+        "private key prefix 04",
+    ),
+    (
+        "xpub661MyMwAqRbcEYS8w7XLSVeEsBXy79zSzH1J8vCdxAZningWLdN3zgtU6N8ZMMXctdiCjxTNq964yKkwrkBJJwpzZS4HS2fxvyYUA4q2Xe4",
+        "public key prefix 01",
+    ),
+    (
+        "xprv9s21ZrQH143K24Mfq5zL5MhWK9hUhhGbd45hLXo2Pq2oqzMMo63oStZzFAzHGBP2UuGCqWLTAPLcMtD9y5gkZ6Eq3Rjuahrv17fEQ3Qen6J",
+        "private key prefix 01",
+    ),
+    (
+        "xprv9s2SPatNQ9Vc6GTbVMFPFo7jsaZySyzk7L8n2uqKXJen3KUmvQNTuLh3fhZMBoG3G4ZW1N2kZuHEPY53qmbZzCHshoQnNf4GvELZfqTUrcv",
+        "zero depth with a parent fingerprint",
+    ),
+    (
+        "xpub661no6RGEX3uJkY4bNnPcw4URcQTrSibUZ4NqJEw5eBkv7ovTwgiT91XX27VbEXGENhYRCf7hyEbWrR3FewATdCEebj6znwMfQkhRYHRLpJ",
+        "zero depth with a parent fingerprint",
+    ),
+    (
+        "xprv9s21ZrQH4r4TsiLvyLXqM9P7k1K3EYhA1kkD6xuquB5i39AU8KF42acDyL3qsDbU9NmZn6MsGSUYZEsuoePmjzsB3eFKSUEh3Gu1N3cqVUN",
+        "zero depth with a child number",
+    ),
+    (
+        "xpub661MyMwAuDcm6CRQ5N4qiHKrJ39Xe1R1NyfouMKTTWcguwVcfrZJaNvhpebzGerh7gucBvzEQWRugZDuDXjNDRmXzSZe4c7mnTK97pTvGS8",
+        "zero depth with a child number",
+    ),
+    (
+        "DMwo58pR1QLEFihHiXPVykYB6fJmsTeHvyTp7hRThAtCX8CvYzgPcn8XnmdfHGMQzT7ayAmfo4z3gY5KfbrZWZ6St24UVf2Qgo6oujFktLHdHY4",
+        "unknown version",
+    ),
+    (
+        "DMwo58pR1QLEFihHiXPVykYB6fJmsTeHvyTp7hRThAtCX8CvYzgPcn8XnmdfHPmHJiEDXkTiJTVV9rHEBUem2mwVbbNfvT2MTcAqj3nesx8uBf9",
+        "unknown version",
+    ),
+    (
+        "xprv9s21ZrQH143K24Mfq5zL5MhWK9hUhhGbd45hLXo2Pq2oqzMMo63oStZzF93Y5wvzdUayhgkkFoicQZcP3y52uPPxFnfoLZB21Teqt1VvEHx",
+        "private key 0",
+    ),
+    (
+        "xprv9s21ZrQH143K24Mfq5zL5MhWK9hUhhGbd45hLXo2Pq2oqzMMo63oStZzFAzHGBP2UuGCqWLTAPLcMtD5SDKr24z3aiUvKr9bJpdrcLg1y3G",
+        "private key n",
+    ),
+    (
+        "xpub661MyMwAqRbcEYS8w7XLSVeEsBXy79zSzH1J8vCdxAZningWLdN3zgtU6Q5JXayek4PRsn35jii4veMimro1xefsM58PgBMrvdYre8QyULY",
+        "public key not on the curve",
+    ),
+    (
+        "xprv9s21ZrQH143K3QTDL4LXw2F7HEK3wJUD2nW2nRk4stbPy6cq3jPPqjiChkVvvNKmPGJxWUtg6LnF5kejMRNNU3TGtRBeJgk33yuGBxrMPHL",
+        "bad checksum",
+    ),
+];
@@ -0,0 +1,172 @@
+//! Validation over a socket, for `--listen-tcp` and `--listen-unix`.
+//!
+//! Other processes connect and send candidate phrases, one per line; every
+//! line is answered, in order, with a line reading `valid` or `invalid`.
+//! Lines are validated as a run with the same `--language` and layout
+//! options would validate them, except that a phrase can't span lines.
+//! Phrases are never echoed back or logged.
+//!
+//! Connections are served by a fixed number of threads, `--threads` or one
+//! per CPU. A few more wait their turn; past that a connection is told the
+//! server is busy and closed, and one that sends nothing for a minute, or
+//! doesn't read its answers, is closed too.
+
+use crate::parse_in;
+use crossbeam_channel::{bounded, Receiver, TrySendError};
+use mnemonic_validator::{config::EngineConfig, languages::Choice, layout::Layout, lines};
+use std::{
+    io::{self, BufRead, BufReader, BufWriter, Read, Write},
+    net::{SocketAddr, TcpListener},
+    path::Path,
+    thread,
+    time::Duration,
+};
+use zeroize::Zeroizing;
+
+/// Longer lines can't be mnemonics; a connection sending one is closed
+/// rather than buffered without bound.
+const MAX_LINE: usize = 64 << 10;
+
+/// How long a connection may keep a thread waiting on it.
+const TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Accepted connections waiting for a thread, per thread.
+const WAITING_PER_THREAD: usize = 4;
+
+/// How lines are validated, and on how many threads.
+pub struct Settings {
+    pub threads: usize,
+    pub language: Choice,
+    pub layout: Layout,
+}
+
+impl Settings {
+    /// Settings from the engine's configuration and a run's `--language`
+    /// and layout options.
+    pub fn new(engine: &EngineConfig, language: Choice, layout: Layout) -> Self {
+        let threads = engine
+            .threads
+            .unwrap_or_else(|| thread::available_parallelism().map_or(1, |n| n.get()));
+        Self { threads, language, layout }
+    }
+
+    fn is_valid(&self, line: &str) -> bool {
+        self.layout
+            .find_map(line, |candidate| parse_in(candidate, self.language).into_iter().next())
+            .is_some()
+    }
+}
+
+type Connection = (Box<dyn Read + Send>, Box<dyn Write + Send>);
+
+/// Answers the lines read from `reader` until it ends.
+fn serve(reader: impl Read, writer: impl Write, settings: &Settings) -> io::Result<()> {
+    let mut reader = BufReader::new(reader);
+    let mut writer = BufWriter::new(writer);
+    let mut line = Zeroizing::new(Vec::new());
+    loop {
+        line.clear();
+        let n = (&mut reader).take(MAX_LINE as u64 + 1).read_until(b'\n', &mut line)?;
+        if n == 0 {
+            return writer.flush();
+        }
+        if n > MAX_LINE && !line.ends_with(b"\n") {
+            writeln!(writer, "error: line longer than {} bytes", MAX_LINE)?;
+            return writer.flush();
+        }
+        let valid = std::str::from_utf8(lines::normalize(&line)).is_ok_and(|text| settings.is_valid(text));
+        writeln!(writer, "{}", if valid { "valid" } else { "invalid" })?;
+        // Answer a batch in one write, but never leave a client waiting
+        if reader.buffer().is_empty() {
+            writer.flush()?;
+        }
+    }
+}
+
+/// Serves connections from `queue` until it's closed.
+fn work(queue: Receiver<Connection>, settings: &Settings) {
+    for (reader, writer) in queue {
+        if let Err(e) = serve(reader, writer, settings) {
+            eprintln!("Warning: connection closed: {}", e);
+        }
+    }
+}
+
+/// Hands each connection `accept` gives to the threads, until it returns
+/// `None`.
+fn serve_all(mut accept: impl FnMut() -> Option<io::Result<Connection>>, settings: Settings) {
+    let (sender, queue) = bounded(settings.threads * WAITING_PER_THREAD);
+    thread::scope(|scope| {
+        for _ in 0..settings.threads {
+            let queue = queue.clone();
+            let settings = &settings;
+            scope.spawn(move || work(queue, settings));
+        }
+        while let Some(connection) = accept() {
+            let connection = match connection {
+                Ok(connection) => connection,
+                Err(e) => {
+                    eprintln!("Warning: failed to accept a connection: {}", e);
+                    continue;
+                }
+            };
+            if let Err(TrySendError::Full((_, mut writer))) = sender.try_send(connection) {
+                let _ = writeln!(writer, "error: busy, try again later");
+            }
+        }
+        drop(sender);
+    });
+}
+
+pub fn listen_tcp(addr: SocketAddr, settings: Settings) -> Result<(), Box<dyn std::error::Error>> {
+    let listener = TcpListener::bind(addr)?;
+    if !addr.ip().is_loopback() {
+        println!("Warning: phrases sent to {} cross the network unencrypted.", addr);
+    }
+    println!("Listening on {} with {} threads (Ctrl+C to stop)", listener.local_addr()?, settings.threads);
+    let mut incoming = listener.incoming();
+    serve_all(
+        || {
+            let connection = incoming.next()?.and_then(|stream| {
+                stream.set_read_timeout(Some(TIMEOUT))?;
+                stream.set_write_timeout(Some(TIMEOUT))?;
+                let writer = stream.try_clone()?;
+                Ok((Box::new(stream) as Box<dyn Read + Send>, Box::new(writer) as Box<dyn Write + Send>))
+            });
+            Some(connection)
+        },
+        settings,
+    );
+    Ok(())
+}
+
+#[cfg(unix)]
+pub fn listen_unix(path: &Path, settings: Settings) -> Result<(), Box<dyn std::error::Error>> {
+    use std::os::unix::{fs::FileTypeExt, net::UnixListener};
+
+    // A socket left behind by an earlier run would make bind fail
+    if std::fs::symlink_metadata(path).is_ok_and(|meta| meta.file_type().is_socket()) {
+        std::fs::remove_file(path)?;
+    }
+    let listener = UnixListener::bind(path)?;
+    println!("Listening on {} with {} threads (Ctrl+C to stop)", path.display(), settings.threads);
+    let mut incoming = listener.incoming();
+    serve_all(
+        || {
+            let connection = incoming.next()?.and_then(|stream| {
+                stream.set_read_timeout(Some(TIMEOUT))?;
+                stream.set_write_timeout(Some(TIMEOUT))?;
+                let writer = stream.try_clone()?;
+                Ok((Box::new(stream) as Box<dyn Read + Send>, Box::new(writer) as Box<dyn Write + Send>))
+            });
+            Some(connection)
+        },
+        settings,
+    );
+    Ok(())
+}
+
+#[cfg(not(unix))]
+pub fn listen_unix(_path: &Path, _settings: Settings) -> Result<(), Box<dyn std::error::Error>> {
+    Err("--listen-unix needs a Unix system; use --listen-tcp 127.0.0.1:PORT instead".into())
+}
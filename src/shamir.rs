@@ -0,0 +1,78 @@
+//! `mnemonic_validator slip39`: combining SLIP-39 shares found in a dump
+//! into the master secret they were split from.
+//!
+//! Shares are read from a file or standard input, and the passphrase from
+//! the environment, to keep both out of the shell history and the process
+//! list.
+
+use clap::{Args, Subcommand};
+use mnemonic_validator::{digest::to_hex, lines, paths, secret::Zeroizing, slip39};
+use std::{
+    fs,
+    io::{self, Read},
+    path::PathBuf,
+};
+
+/// The passphrase the master secret was encrypted with; empty if unset.
+const PASSPHRASE_ENV: &str = "MV_SLIP39_PASSPHRASE";
+
+#[derive(Args, Debug)]
+pub struct Slip39Args {
+    #[clap(subcommand)]
+    command: Slip39Command,
+}
+
+#[derive(Subcommand, Debug)]
+enum Slip39Command {
+    /// Print the master secret, in hex, that enough shares combine into (passphrase from MV_SLIP39_PASSPHRASE).
+    Combine {
+        /// File with the shares, one per line, e.g. an output file; standard input if left out.
+        #[clap(value_parser = paths::parser())]
+        file: Option<PathBuf>,
+
+        /// Combine only these lines of the file, counting from 1, e.g. --lines 3,8,12.
+        #[clap(long, value_name = "N,N,...", value_delimiter = ',')]
+        lines: Vec<usize>,
+    },
+}
+
+/// The shares on the given lines (every non-blank one if `wanted` is empty)
+/// of `file`, or of standard input.
+fn read_shares(file: Option<&PathBuf>, wanted: &[usize]) -> Result<Vec<slip39::Share>, Box<dyn std::error::Error>> {
+    let mut text = Zeroizing::new(Vec::new());
+    match file {
+        Some(path) => fs::File::open(path)?.read_to_end(&mut text)?,
+        None => io::stdin().read_to_end(&mut text)?,
+    };
+
+    let mut shares = Vec::new();
+    let mut last = 0;
+    for (n, line) in (1..).zip(lines::lines(&text)) {
+        last = n;
+        if !wanted.is_empty() && !wanted.contains(&n) {
+            continue;
+        }
+        let line = std::str::from_utf8(line).unwrap_or_default().trim();
+        if line.is_empty() && wanted.is_empty() {
+            continue;
+        }
+        let share = slip39::parse(line).map_err(|e| format!("line {} isn't a valid SLIP-39 share: {}", n, e))?;
+        shares.push(share);
+    }
+    if let Some(missing) = wanted.iter().find(|&&n| n == 0 || n > last) {
+        return Err(format!("there is no line {}", missing).into());
+    }
+    Ok(shares)
+}
+
+pub fn run(args: &Slip39Args) -> Result<(), Box<dyn std::error::Error>> {
+    match &args.command {
+        Slip39Command::Combine { file, lines } => {
+            let shares = read_shares(file.as_ref(), lines)?;
+            let passphrase = Zeroizing::new(std::env::var(PASSPHRASE_ENV).unwrap_or_default());
+            let secret = slip39::combine(&shares, passphrase.as_bytes())?;
+            println!("{}", Zeroizing::new(to_hex(&secret)).as_str());
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,352 @@
+//! The coordinator's book: its inputs split into shards of lines, which
+//! worker has which, and how far each has got.
+//!
+//! A shard is a window of one file, run on a worker as `--skip`/`--limit`.
+//! A worker holds its shard for as long as it reports progress; one that
+//! goes quiet for longer than the lease loses it to the next worker asking.
+//! A run stopped before the end of its shard (by a signal or a time limit)
+//! gives it back from the line it stopped at, which it knows exactly, so
+//! the next worker carries on from there. A shard taken from a worker that
+//! went quiet starts over from where it was last given out: the progress
+//! reported on the way isn't a point every hit before has been written at.
+
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    fs::{self, File},
+    io::{self, Read},
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+/// Attempts at a shard before it's given up as failed.
+pub const MAX_ATTEMPTS: u32 = 3;
+
+/// Lines to give a worker: from line `skip` of `path`, `limit` of them or
+/// to the end of the file.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Shard {
+    pub id: usize,
+    pub path: PathBuf,
+    pub skip: usize,
+    pub limit: Option<usize>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Status {
+    Pending,
+    Running,
+    Done,
+    Failed,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ShardState {
+    pub shard: Shard,
+    /// Lines in the shard as counted when it was planned; given back
+    /// part-way, it's worked on from further on, but keeps its count.
+    pub lines: usize,
+    pub status: Status,
+    pub worker: Option<String>,
+    /// Lines of it validated so far, and valid ones found, by this and the
+    /// earlier attempts.
+    pub done: usize,
+    pub valid: usize,
+    /// The lines and hits of the attempts that gave it back part-way.
+    banked: (usize, usize),
+    pub attempts: u32,
+    pub error: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct WorkerState {
+    /// Unix times.
+    pub registered_at: i64,
+    pub last_seen: i64,
+    pub shard: Option<usize>,
+    /// Lines per second over the last two reports.
+    pub rate: f64,
+    pub lines: usize,
+    pub valid: usize,
+    /// Progress at the last report, for `rate`.
+    #[serde(skip)]
+    last_report: Option<(Instant, usize)>,
+}
+
+/// Totals over all shards, for the dashboard.
+#[derive(Serialize, Debug, Default)]
+pub struct Totals {
+    pub shards: usize,
+    pub pending: usize,
+    pub running: usize,
+    pub done: usize,
+    pub failed: usize,
+    pub lines: usize,
+    pub lines_done: usize,
+    pub valid: usize,
+    pub rate: f64,
+}
+
+/// What a worker asking for work gets.
+pub enum Next {
+    Shard(Shard),
+    /// Everything left is being run by other workers.
+    Wait,
+    Finished,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct Book {
+    pub shards: Vec<ShardState>,
+    pub workers: BTreeMap<String, WorkerState>,
+}
+
+fn now() -> i64 {
+    chrono::Utc::now().timestamp()
+}
+
+/// Counts the lines of `path`; a last line without a newline counts too.
+fn count_lines(path: &Path) -> io::Result<usize> {
+    let mut file = File::open(path)?;
+    let mut buf = vec![0; 1 << 20];
+    let (mut lines, mut last) = (0, b'\n');
+    loop {
+        let n = file.read(&mut buf)?;
+        if n == 0 {
+            return Ok(lines + usize::from(last != b'\n'));
+        }
+        lines += memchr::memchr_iter(b'\n', &buf[..n]).count();
+        last = buf[n - 1];
+    }
+}
+
+impl Book {
+    /// Splits each of `paths` into shards of `shard_lines` lines. The last
+    /// shard of a file runs to its end, whatever was counted.
+    pub fn plan(paths: &[PathBuf], shard_lines: usize) -> io::Result<Self> {
+        let mut book = Self::default();
+        for path in paths {
+            let lines = count_lines(path)
+                .map_err(|e| io::Error::new(e.kind(), format!("can't read {}: {}", path.display(), e)))?;
+            let mut skip = 0;
+            loop {
+                let last = lines <= skip + shard_lines;
+                book.shards.push(ShardState {
+                    shard: Shard {
+                        id: book.shards.len(),
+                        path: path.clone(),
+                        skip,
+                        limit: (!last).then_some(shard_lines),
+                    },
+                    lines: lines.saturating_sub(skip).min(shard_lines),
+                    status: Status::Pending,
+                    worker: None,
+                    done: 0,
+                    valid: 0,
+                    banked: (0, 0),
+                    attempts: 0,
+                    error: None,
+                });
+                if last {
+                    break;
+                }
+                skip += shard_lines;
+            }
+        }
+        Ok(book)
+    }
+
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let mut book: Self = serde_json::from_slice(&fs::read(path)?)?;
+        // Whoever was running a shard has to ask again
+        for state in &mut book.shards {
+            if state.status == Status::Running {
+                state.status = Status::Pending;
+                state.worker = None;
+                state.done = state.banked.0;
+                state.valid = state.banked.1;
+            }
+        }
+        for worker in book.workers.values_mut() {
+            worker.shard = None;
+            worker.rate = 0.0;
+        }
+        Ok(book)
+    }
+
+    /// Saves the book in one rename, so it's never seen half written.
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let mut temporary = path.as_os_str().to_owned();
+        temporary.push(".tmp");
+        fs::write(&temporary, serde_json::to_vec_pretty(self)?)?;
+        fs::rename(&temporary, path)
+    }
+
+    pub fn register(&mut self, worker: &str) {
+        let now = now();
+        let state = self.workers.entry(worker.to_string()).or_insert_with(|| WorkerState {
+            registered_at: now,
+            last_seen: now,
+            shard: None,
+            rate: 0.0,
+            lines: 0,
+            valid: 0,
+            last_report: None,
+        });
+        state.last_seen = now;
+        // A worker registering again has lost what it was running
+        if let Some(id) = state.shard.take() {
+            self.give_back(id, None);
+        }
+    }
+
+    /// Takes their shards from the workers that have gone quiet for longer
+    /// than `lease`.
+    fn expire(&mut self, lease: Duration) {
+        let cutoff = now().saturating_sub(lease.as_secs() as i64);
+        let mut expired = Vec::new();
+        for worker in self.workers.values_mut().filter(|worker| worker.last_seen < cutoff) {
+            worker.rate = 0.0;
+            expired.extend(worker.shard.take());
+        }
+        for id in expired {
+            self.give_back(id, None);
+        }
+    }
+
+    /// Puts shard `id` back to be given out again: from line `from` if its
+    /// run stopped there with so many hits, or from where it was last given
+    /// out.
+    fn give_back(&mut self, id: usize, from: Option<(usize, usize)>) {
+        let Some(state) = self.shards.get_mut(id).filter(|state| state.status == Status::Running) else {
+            return;
+        };
+        state.status = Status::Pending;
+        state.worker = None;
+        match from {
+            Some((line, valid)) if line > state.shard.skip => {
+                // The first `line - skip` lines of it are done for good
+                let moved = line - state.shard.skip;
+                state.shard.limit = state.shard.limit.map(|limit| limit.saturating_sub(moved));
+                state.shard.skip = line;
+                state.banked = (state.banked.0 + moved, state.banked.1 + valid);
+            }
+            _ => {}
+        }
+        state.done = state.banked.0;
+        state.valid = state.banked.1;
+    }
+
+    /// The next shard for `worker`, if there's one left.
+    pub fn next(&mut self, worker: &str, lease: Duration) -> Next {
+        self.expire(lease);
+        if !self.workers.contains_key(worker) {
+            self.register(worker);
+        }
+        let Some(state) = self.shards.iter_mut().find(|state| state.status == Status::Pending) else {
+            return match self.shards.iter().any(|state| state.status == Status::Running) {
+                true => Next::Wait,
+                false => Next::Finished,
+            };
+        };
+        state.status = Status::Running;
+        state.worker = Some(worker.to_string());
+        state.attempts += 1;
+        let shard = state.shard.clone();
+        let worker = self.workers.get_mut(worker).unwrap();
+        worker.shard = Some(shard.id);
+        worker.last_seen = now();
+        worker.last_report = None;
+        Next::Shard(shard)
+    }
+
+    /// Whether `worker` holds shard `id`; it's lost it if it went quiet.
+    fn holds(&self, worker: &str, id: usize) -> bool {
+        self.workers.get(worker).is_some_and(|state| state.shard == Some(id))
+    }
+
+    /// Records a worker's progress through shard `id`: it's at `line` of the
+    /// file, with `valid` hits of this attempt. False if the shard isn't
+    /// the worker's any more.
+    pub fn progress(&mut self, worker: &str, id: usize, line: usize, valid: usize) -> bool {
+        if !self.holds(worker, id) {
+            return false;
+        }
+        let state = &mut self.shards[id];
+        let done = state.banked.0 + line.saturating_sub(state.shard.skip);
+        state.done = done;
+        state.valid = state.banked.1 + valid;
+        let worker = self.workers.get_mut(worker).unwrap();
+        if let Some((at, before)) = worker.last_report {
+            let secs = at.elapsed().as_secs_f64();
+            if secs > 0.0 {
+                worker.rate = done.saturating_sub(before) as f64 / secs;
+            }
+        }
+        worker.last_report = Some((Instant::now(), done));
+        worker.last_seen = now();
+        true
+    }
+
+    /// Records the end of `worker`'s run of shard `id`: done, failed with
+    /// `error`, or stopped at `line` to be carried on by the next worker.
+    pub fn finish(&mut self, worker: &str, id: usize, status: &str, line: usize, valid: usize, error: Option<String>) -> bool {
+        if !self.holds(worker, id) {
+            return false;
+        }
+        let state = &mut self.shards[id];
+        let lines = match status {
+            "done" => state.lines.max(state.banked.0 + line.saturating_sub(state.shard.skip)) - state.banked.0,
+            _ => line.saturating_sub(state.shard.skip),
+        };
+        let worker_state = self.workers.get_mut(worker).unwrap();
+        worker_state.shard = None;
+        worker_state.last_seen = now();
+        worker_state.rate = 0.0;
+        match status {
+            "done" => {
+                worker_state.lines += lines;
+                worker_state.valid += valid;
+                state.status = Status::Done;
+                state.done = state.banked.0 + lines;
+                state.valid = state.banked.1 + valid;
+                state.error = None;
+            }
+            "stopped" => {
+                worker_state.lines += lines;
+                worker_state.valid += valid;
+                self.give_back(id, Some((line, valid)));
+            }
+            _ => {
+                state.error = error;
+                if state.attempts >= MAX_ATTEMPTS {
+                    state.status = Status::Failed;
+                } else {
+                    self.give_back(id, None);
+                }
+            }
+        }
+        true
+    }
+
+    pub fn totals(&self) -> Totals {
+        let mut totals = Totals {
+            shards: self.shards.len(),
+            ..Totals::default()
+        };
+        for state in &self.shards {
+            match state.status {
+                Status::Pending => totals.pending += 1,
+                Status::Running => totals.running += 1,
+                Status::Done => totals.done += 1,
+                Status::Failed => totals.failed += 1,
+            }
+            totals.lines += state.lines;
+            totals.lines_done += state.done;
+            totals.valid += state.valid;
+        }
+        totals.rate = self.workers.values().map(|worker| worker.rate).sum();
+        totals
+    }
+}
@@ -0,0 +1,293 @@
+//! SLIP-39 shares: reading them, and combining enough of them into the
+//! master secret they were split from.
+//!
+//! A share is 20 or more words from a list of 1024 of its own, whose last
+//! three are an RS1024 checksum. It carries the identifier and parameters
+//! of its set, its group and member index, and one value of a two-level
+//! Shamir sharing over GF(256): members' values give their group's, and
+//! groups' values give the encrypted master secret, which a passphrase
+//! (empty if none was set) decrypts.
+
+use crate::secret::Zeroizing;
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use std::collections::BTreeMap;
+
+/// The SLIP-39 wordlist, in alphabetical order.
+const WORDLIST: &str = include_str!("slip39_english.txt");
+/// Words of the checksum.
+const CHECKSUM_WORDS: usize = 3;
+/// Words before the share's value: identifier and parameters.
+const METADATA_WORDS: usize = 4;
+/// Words of the shortest share: a 128-bit secret.
+pub const MIN_WORDS: usize = 20;
+/// Base PBKDF2 iterations of each of the four encryption rounds.
+const BASE_ITERATIONS: u32 = 2500;
+/// The x of the shared secret, and of its digest, in each sharing.
+const SECRET_INDEX: u8 = 255;
+const DIGEST_INDEX: u8 = 254;
+const DIGEST_LEN: usize = 4;
+
+fn words() -> impl Iterator<Item = &'static str> {
+    WORDLIST.lines()
+}
+
+/// Index of `word` in the wordlist.
+pub fn index_of(word: &str) -> Option<u16> {
+    words().position(|known| known == word).map(|index| index as u16)
+}
+
+/// One share, checked on its own.
+#[derive(Clone)]
+pub struct Share {
+    pub identifier: u16,
+    /// Shares of extendable sets don't salt the encryption with the
+    /// identifier, so more sets can be made for the same secret.
+    pub extendable: bool,
+    pub iteration_exponent: u8,
+    pub group_index: u8,
+    pub group_threshold: u8,
+    pub group_count: u8,
+    pub member_index: u8,
+    pub member_threshold: u8,
+    value: Zeroizing<Vec<u8>>,
+}
+
+impl std::fmt::Debug for Share {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // The value stays out of logs
+        write!(
+            f,
+            "Share {{ set {}, group {} of {} ({} needed), member {} ({} needed) }}",
+            self.identifier,
+            self.group_index + 1,
+            self.group_count,
+            self.group_threshold,
+            self.member_index + 1,
+            self.member_threshold
+        )
+    }
+}
+
+/// RS1024 over the word indices, as SLIP-39 defines it; a share is intact
+/// when it comes to 1.
+fn polymod(customization: &[u8], values: &[u16]) -> u32 {
+    const GEN: [u32; 10] = [
+        0xE0E040, 0x1C1C080, 0x3838100, 0x7070200, 0xE0E0009, 0x1C0C2412, 0x38086C24, 0x3090FC48, 0x21B1F890, 0x3F3F120,
+    ];
+    let mut chk = 1u32;
+    for value in customization.iter().map(|&byte| u32::from(byte)).chain(values.iter().map(|&v| u32::from(v))) {
+        let b = chk >> 20;
+        chk = (chk & 0xFFFFF) << 10 ^ value;
+        for (i, gen) in GEN.iter().enumerate() {
+            if b >> i & 1 == 1 {
+                chk ^= gen;
+            }
+        }
+    }
+    chk
+}
+
+fn customization(extendable: bool) -> &'static [u8] {
+    match extendable {
+        true => b"shamir_extendable",
+        false => b"shamir",
+    }
+}
+
+/// Reads a share, checking its checksum, padding and length.
+pub fn parse(phrase: &str) -> Result<Share, String> {
+    let indices = Zeroizing::new(
+        phrase
+            .split_whitespace()
+            .enumerate()
+            .map(|(n, word)| index_of(&word.to_lowercase()).ok_or_else(|| format!("word {} isn't a SLIP-39 word", n + 1)))
+            .collect::<Result<Vec<u16>, String>>()?,
+    );
+    if indices.len() < MIN_WORDS {
+        return Err(format!("a share has at least {} words, not {}", MIN_WORDS, indices.len()));
+    }
+    let extendable = indices[1] >> 4 & 1 == 1;
+    if polymod(customization(extendable), &indices) != 1 {
+        return Err("the checksum is wrong".to_string());
+    }
+
+    // 10 bits per word: the value's are padded at the front to whole bytes
+    let value_words = &indices[METADATA_WORDS..indices.len() - CHECKSUM_WORDS];
+    let padding = value_words.len() * 10 % 16;
+    if padding > 8 {
+        return Err("the share has the wrong number of words".to_string());
+    }
+    let mut bits = Zeroizing::new(Vec::with_capacity(value_words.len() * 10));
+    for &index in value_words {
+        bits.extend((0..10).rev().map(|bit| index >> bit & 1 == 1));
+    }
+    if bits[..padding].iter().any(|&bit| bit) {
+        return Err("the padding isn't zero".to_string());
+    }
+    let value: Zeroizing<Vec<u8>> = Zeroizing::new(
+        bits[padding..]
+            .chunks(8)
+            .map(|byte| byte.iter().fold(0u8, |acc, &bit| acc << 1 | u8::from(bit)))
+            .collect(),
+    );
+    if value.len() < 16 {
+        return Err(format!("the secret is {} bits; SLIP-39 secrets are at least 128", value.len() * 8));
+    }
+
+    // Identifier 15 bits, extendable 1, iteration exponent 4, then 4 each
+    // for the group index, group threshold and count, member index and
+    // threshold, thresholds and count less one
+    let metadata = indices[..METADATA_WORDS].iter().fold(0u64, |acc, &index| acc << 10 | u64::from(index));
+    let field = |shift: u32| (metadata >> shift & 0xF) as u8;
+    let (group_threshold, group_count) = (field(12) + 1, field(8) + 1);
+    if group_threshold > group_count {
+        return Err(format!("the share needs {} groups of {}", group_threshold, group_count));
+    }
+    Ok(Share {
+        identifier: (metadata >> 25) as u16,
+        extendable,
+        iteration_exponent: field(20),
+        group_index: field(16),
+        group_threshold,
+        group_count,
+        member_index: field(4),
+        member_threshold: field(0) + 1,
+        value,
+    })
+}
+
+/// Logarithms and powers of 3 in GF(256), as AES defines it.
+struct Field {
+    exp: [u8; 255],
+    log: [u8; 256],
+}
+
+impl Field {
+    fn new() -> Self {
+        let mut field = Field { exp: [0; 255], log: [0; 256] };
+        let mut power = 1u16;
+        for i in 0..255 {
+            field.exp[i] = power as u8;
+            field.log[power as usize] = i as u8;
+            // Times 3: x + 1
+            power ^= power << 1;
+            if power & 0x100 != 0 {
+                power ^= 0x11B;
+            }
+        }
+        field
+    }
+
+    /// The values at `x` of the polynomials through the `points`, one per
+    /// byte of their values.
+    fn interpolate(&self, points: &[(u8, &[u8])], x: u8) -> Zeroizing<Vec<u8>> {
+        if let Some((_, y)) = points.iter().find(|(at, _)| *at == x) {
+            return Zeroizing::new(y.to_vec());
+        }
+        let log = |v: u8| u32::from(self.log[v as usize]);
+        let log_product: u32 = points.iter().map(|&(at, _)| log(at ^ x)).sum();
+        let mut result = Zeroizing::new(vec![0u8; points[0].1.len()]);
+        for &(at, y) in points {
+            let others: u32 = points.iter().filter(|(other, _)| *other != at).map(|&(other, _)| log(other ^ at)).sum();
+            let basis = (log_product + 255 * 255 - log(at ^ x) - others) % 255;
+            for (byte, &value) in result.iter_mut().zip(y) {
+                if value != 0 {
+                    *byte ^= self.exp[((log(value) + basis) % 255) as usize];
+                }
+            }
+        }
+        result
+    }
+}
+
+/// The secret `threshold` of the `points` share, checked against the digest
+/// shared along with it when more than one is needed.
+fn recover(field: &Field, threshold: u8, points: &[(u8, &[u8])]) -> Result<Zeroizing<Vec<u8>>, String> {
+    if threshold == 1 {
+        return Ok(Zeroizing::new(points[0].1.to_vec()));
+    }
+    let secret = field.interpolate(points, SECRET_INDEX);
+    let digest = field.interpolate(points, DIGEST_INDEX);
+    let mut mac = Hmac::<Sha256>::new_from_slice(&digest[DIGEST_LEN..]).expect("HMAC takes any key length");
+    mac.update(&secret);
+    mac.verify_truncated_left(&digest[..DIGEST_LEN])
+        .map_err(|_| "the shares don't fit together (wrong digest)".to_string())?;
+    Ok(secret)
+}
+
+/// Decrypts the encrypted master secret with `passphrase`: four rounds of
+/// a Feistel network keyed by PBKDF2.
+fn decrypt(encrypted: &[u8], passphrase: &[u8], share: &Share) -> Zeroizing<Vec<u8>> {
+    let half = encrypted.len() / 2;
+    let mut left = Zeroizing::new(encrypted[..half].to_vec());
+    let mut right = Zeroizing::new(encrypted[half..].to_vec());
+    let mut salt = Vec::new();
+    if !share.extendable {
+        salt.extend_from_slice(customization(false));
+        salt.extend_from_slice(&share.identifier.to_be_bytes());
+    }
+    let iterations = BASE_ITERATIONS << share.iteration_exponent;
+    for round in (0..4u8).rev() {
+        let mut password = Zeroizing::new(vec![round]);
+        password.extend_from_slice(passphrase);
+        let mut round_salt = Zeroizing::new(salt.clone());
+        round_salt.extend_from_slice(&right);
+        let mut key = Zeroizing::new(vec![0u8; half]);
+        pbkdf2::pbkdf2_hmac::<Sha256>(&password, &round_salt, iterations, &mut key);
+        for (byte, key) in left.iter_mut().zip(key.iter()) {
+            *byte ^= key;
+        }
+        std::mem::swap(&mut left, &mut right);
+    }
+    right.extend_from_slice(&left);
+    right
+}
+
+/// The master secret that `shares` combine into, decrypted with
+/// `passphrase`. Shares beyond a group's threshold, and groups beyond the
+/// group threshold, are left out.
+pub fn combine(shares: &[Share], passphrase: &[u8]) -> Result<Zeroizing<Vec<u8>>, String> {
+    let first = shares.first().ok_or("no shares to combine")?;
+    let same_set = |share: &&Share| {
+        (share.identifier, share.extendable, share.iteration_exponent, share.group_threshold, share.group_count)
+            == (first.identifier, first.extendable, first.iteration_exponent, first.group_threshold, first.group_count)
+            && share.value.len() == first.value.len()
+    };
+    if !shares.iter().all(|share| same_set(&share)) {
+        return Err("the shares aren't all of the same set".to_string());
+    }
+
+    let mut groups: BTreeMap<u8, Vec<&Share>> = BTreeMap::new();
+    for share in shares {
+        let members = groups.entry(share.group_index).or_default();
+        if members.iter().any(|member| member.member_threshold != share.member_threshold) {
+            return Err(format!("the shares of group {} need different numbers of members", share.group_index + 1));
+        }
+        if members.iter().any(|member| member.member_index == share.member_index) {
+            return Err(format!("member {} of group {} is given twice", share.member_index + 1, share.group_index + 1));
+        }
+        members.push(share);
+    }
+
+    let field = Field::new();
+    let mut group_values = Vec::new();
+    for (&index, members) in &groups {
+        let threshold = members[0].member_threshold;
+        if members.len() >= threshold as usize && group_values.len() < first.group_threshold as usize {
+            let points: Vec<(u8, &[u8])> =
+                members[..threshold as usize].iter().map(|member| (member.member_index, &member.value[..])).collect();
+            group_values.push((index, recover(&field, threshold, &points)?));
+        }
+    }
+    if group_values.len() < first.group_threshold as usize {
+        return Err(format!(
+            "{} of the {} groups needed have enough shares",
+            group_values.len(),
+            first.group_threshold
+        ));
+    }
+    let points: Vec<(u8, &[u8])> = group_values.iter().map(|(index, value)| (*index, &value[..])).collect();
+    let encrypted = recover(&field, first.group_threshold, &points)?;
+    Ok(decrypt(&encrypted, passphrase, first))
+}
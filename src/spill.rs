@@ -0,0 +1,189 @@
+//! Sets of line keys that may outgrow their share of `--max-memory`, for
+//! the `--incremental` index.
+//!
+//! Keys are held in a hash set up to a limit. Past it they're sorted and
+//! written out as a run, a file of 16-byte keys next to the output file
+//! they're of, and looked up there by binary search; once there are
+//! [`MAX_RUNS`] runs they're merged into one. A lookup on disk is slow next
+//! to one in memory, but lookups only happen for hits. The runs hold
+//! hashes, never lines, and are removed when the set is dropped.
+
+use std::{
+    cmp::{Ordering, Reverse},
+    collections::{BinaryHeap, HashSet},
+    fs::{self, File},
+    io::{self, BufReader, BufWriter, Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+};
+
+pub type Key = [u8; 16];
+
+/// Runs kept before they're merged.
+const MAX_RUNS: usize = 8;
+
+/// Sorted keys in a file.
+struct Run {
+    path: PathBuf,
+    file: File,
+    keys: u64,
+}
+
+impl Run {
+    /// Writes `keys`, which must be sorted, to a new file at `path`.
+    fn create(path: PathBuf, keys: impl Iterator<Item = io::Result<Key>>) -> io::Result<Self> {
+        let file = File::options().read(true).write(true).create(true).truncate(true).open(&path)?;
+        let mut run = Run { path, file, keys: 0 };
+        let mut out = BufWriter::new(&run.file);
+        for key in keys {
+            out.write_all(&key?)?;
+            run.keys += 1;
+        }
+        out.flush()?;
+        drop(out);
+        Ok(run)
+    }
+
+    fn contains(&mut self, key: &Key) -> io::Result<bool> {
+        let (mut low, mut high) = (0, self.keys);
+        let mut at = [0u8; 16];
+        while low < high {
+            let middle = low + (high - low) / 2;
+            self.file.seek(SeekFrom::Start(middle * 16))?;
+            self.file.read_exact(&mut at)?;
+            match at.cmp(key) {
+                Ordering::Less => low = middle + 1,
+                Ordering::Greater => high = middle,
+                Ordering::Equal => return Ok(true),
+            }
+        }
+        Ok(false)
+    }
+
+    /// The keys, in order.
+    fn read(&self) -> io::Result<impl Iterator<Item = io::Result<Key>>> {
+        let mut file = self.file.try_clone()?;
+        file.seek(SeekFrom::Start(0))?;
+        let mut reader = BufReader::new(file);
+        let mut left = self.keys;
+        Ok(std::iter::from_fn(move || {
+            if left == 0 {
+                return None;
+            }
+            left -= 1;
+            let mut key = [0u8; 16];
+            Some(reader.read_exact(&mut key).map(|()| key))
+        }))
+    }
+}
+
+impl Drop for Run {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+/// The keys of `runs` merged in order, each once.
+fn merge(runs: &[Run]) -> io::Result<impl Iterator<Item = io::Result<Key>>> {
+    let mut readers = runs.iter().map(Run::read).collect::<io::Result<Vec<_>>>()?;
+    let mut heap = BinaryHeap::new();
+    for (i, reader) in readers.iter_mut().enumerate() {
+        if let Some(key) = reader.next() {
+            heap.push(Reverse((key?, i)));
+        }
+    }
+    let mut last = None;
+    Ok(std::iter::from_fn(move || loop {
+        let Reverse((key, i)) = heap.pop()?;
+        match readers[i].next() {
+            Some(Ok(next)) => heap.push(Reverse((next, i))),
+            Some(Err(e)) => return Some(Err(e)),
+            None => {}
+        }
+        if last != Some(key) {
+            last = Some(key);
+            return Some(Ok(key));
+        }
+    }))
+}
+
+pub struct KeySet {
+    memory: HashSet<Key>,
+    /// Keys held in memory before they're spilled; `None` for no limit.
+    limit: Option<usize>,
+    /// Runs are this path with `.1`, `.2`, ... appended.
+    base: PathBuf,
+    runs: Vec<Run>,
+    next_run: usize,
+}
+
+impl KeySet {
+    /// An empty set spilling to `base.1`, `base.2`, ... once it holds more
+    /// than `limit` keys.
+    pub fn new(base: &Path, limit: Option<usize>) -> Self {
+        Self {
+            memory: HashSet::new(),
+            limit: limit.map(|limit| limit.max(1)),
+            base: base.to_path_buf(),
+            runs: Vec::new(),
+            next_run: 0,
+        }
+    }
+
+    pub fn contains(&mut self, key: &Key) -> io::Result<bool> {
+        if self.memory.contains(key) {
+            return Ok(true);
+        }
+        for run in &mut self.runs {
+            if run.contains(key)? {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    /// Adds `key` without looking for it first, e.g. while loading keys
+    /// known to be new.
+    pub fn add(&mut self, key: Key) -> io::Result<()> {
+        self.memory.insert(key);
+        if self.limit.is_some_and(|limit| self.memory.len() > limit) {
+            self.spill()?;
+        }
+        Ok(())
+    }
+
+    /// Adds `key`; returns false if it was there already.
+    pub fn insert(&mut self, key: Key) -> io::Result<bool> {
+        if self.contains(&key)? {
+            return Ok(false);
+        }
+        self.add(key)?;
+        Ok(true)
+    }
+
+    /// Whether some keys are on disk.
+    pub fn spilled(&self) -> bool {
+        !self.runs.is_empty()
+    }
+
+    fn run_path(&mut self) -> PathBuf {
+        self.next_run += 1;
+        let mut name = self.base.as_os_str().to_os_string();
+        name.push(format!(".{}", self.next_run));
+        PathBuf::from(name)
+    }
+
+    /// Moves the keys in memory to a new run, merging the runs if there are
+    /// too many.
+    fn spill(&mut self) -> io::Result<()> {
+        let mut keys: Vec<Key> = self.memory.drain().collect();
+        keys.sort_unstable();
+        let path = self.run_path();
+        self.runs.push(Run::create(path, keys.into_iter().map(Ok))?);
+        if self.runs.len() >= MAX_RUNS {
+            let path = self.run_path();
+            let merged = Run::create(path, merge(&self.runs)?)?;
+            self.runs = vec![merged];
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,202 @@
+//! Per-file and aggregate timing of a run.
+//!
+//! Files are validated in order, so a file's elapsed time is counted from
+//! when the previous one finished to when its own last selected line was
+//! validated. That makes slow storage or pathological files stand out in
+//! the end-of-run summary and the manifest.
+//!
+//! Progress over time is sampled too, for the charts of `report`: once a
+//! second at first, less often as the run goes on, so that a run of any
+//! length keeps a few hundred samples.
+
+use crate::inputs::Inputs;
+use std::{
+    ops::Range,
+    sync::{
+        atomic::{AtomicU64, AtomicUsize, Ordering},
+        Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+/// Time between samples at the start of a run.
+const SAMPLE_INTERVAL: Duration = Duration::from_secs(1);
+/// Samples kept before every other one is dropped and the interval doubled.
+const MAX_SAMPLES: usize = 512;
+
+#[derive(Default)]
+struct Counters {
+    /// Lines of this file within the run's range.
+    selected: usize,
+    seen: AtomicUsize,
+    processed: AtomicUsize,
+    valid: AtomicUsize,
+    /// Nanoseconds since the start of the run; 0 while unfinished.
+    finished: AtomicU64,
+}
+
+pub struct RunStats {
+    start: Instant,
+    files: Vec<Counters>,
+    timeline: Mutex<Timeline>,
+}
+
+/// Lines processed and valid so far, some time into the run.
+#[derive(Debug, Clone, Copy)]
+pub struct Sample {
+    pub elapsed: Duration,
+    pub processed: usize,
+    pub valid: usize,
+}
+
+struct Timeline {
+    samples: Vec<Sample>,
+    interval: Duration,
+}
+
+/// Timing and hit rate of one file, or of the whole run.
+#[derive(Debug, Clone)]
+pub struct Summary {
+    pub lines: usize,
+    pub processed: usize,
+    pub valid: usize,
+    pub elapsed: Duration,
+}
+
+impl Summary {
+    pub fn lines_per_sec(&self) -> f64 {
+        if self.elapsed.is_zero() {
+            0.0
+        } else {
+            self.processed as f64 / self.elapsed.as_secs_f64()
+        }
+    }
+
+    /// Valid mnemonics per processed line.
+    pub fn hit_rate(&self) -> f64 {
+        if self.processed == 0 {
+            0.0
+        } else {
+            self.valid as f64 / self.processed as f64
+        }
+    }
+}
+
+impl RunStats {
+    /// Tracks the lines `range` of the combined input.
+    pub fn new(inputs: &Inputs, range: Range<usize>, start: Instant) -> Self {
+        let files = inputs
+            .files
+            .iter()
+            .map(|file| {
+                let lines = file.first_line..file.first_line + file.scan.lines;
+                Counters {
+                    selected: range.end.min(lines.end).saturating_sub(range.start.max(lines.start)),
+                    ..Counters::default()
+                }
+            })
+            .collect();
+        Self {
+            start,
+            files,
+            timeline: Mutex::new(Timeline {
+                samples: Vec::new(),
+                interval: SAMPLE_INTERVAL,
+            }),
+        }
+    }
+
+    /// Adds a sample of the progress so far, unless the last one is too
+    /// recent. Cheap enough to call every few thousand lines.
+    pub fn sample(&self) {
+        let elapsed = self.start.elapsed();
+        let mut timeline = self.timeline.lock().unwrap();
+        let last = timeline.samples.last().map_or(Duration::ZERO, |sample| sample.elapsed);
+        if elapsed < last + timeline.interval {
+            return;
+        }
+        if timeline.samples.len() == MAX_SAMPLES {
+            let mut i = 0;
+            timeline.samples.retain(|_| {
+                i += 1;
+                i % 2 == 0
+            });
+            timeline.interval *= 2;
+        }
+        timeline.samples.push(Sample {
+            elapsed,
+            processed: self.processed(),
+            valid: self.valid(),
+        });
+    }
+
+    /// The samples taken, oldest first, and the state at the end.
+    pub fn timeline(&self) -> Vec<Sample> {
+        let mut samples = self.timeline.lock().unwrap().samples.clone();
+        samples.push(Sample {
+            elapsed: self.start.elapsed(),
+            processed: self.processed(),
+            valid: self.valid(),
+        });
+        samples
+    }
+
+    /// Counts a line of `file` that was looked at; `processed` is false for
+    /// lines left out by sampling.
+    pub fn line_done(&self, file: usize, processed: bool) {
+        let counters = &self.files[file];
+        if processed {
+            counters.processed.fetch_add(1, Ordering::Relaxed);
+        }
+        if counters.seen.fetch_add(1, Ordering::Relaxed) + 1 == counters.selected {
+            let nanos = self.start.elapsed().as_nanos().max(1) as u64;
+            counters.finished.store(nanos, Ordering::Relaxed);
+        }
+    }
+
+    pub fn hit(&self, file: usize) {
+        self.files[file].valid.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn processed(&self) -> usize {
+        self.files.iter().map(|c| c.processed.load(Ordering::Relaxed)).sum()
+    }
+
+    pub fn valid(&self) -> usize {
+        self.files.iter().map(|c| c.valid.load(Ordering::Relaxed)).sum()
+    }
+
+    /// One summary per file, in input order. Files that didn't finish
+    /// (interrupted runs) are timed up to now.
+    pub fn per_file(&self) -> Vec<Summary> {
+        let now = self.start.elapsed();
+        let mut previous = Duration::ZERO;
+        self.files
+            .iter()
+            .map(|counters| {
+                let finished = match counters.finished.load(Ordering::Relaxed) {
+                    0 if counters.selected > 0 => now,
+                    0 => previous,
+                    nanos => Duration::from_nanos(nanos),
+                };
+                let summary = Summary {
+                    lines: counters.selected,
+                    processed: counters.processed.load(Ordering::Relaxed),
+                    valid: counters.valid.load(Ordering::Relaxed),
+                    elapsed: finished.saturating_sub(previous),
+                };
+                previous = previous.max(finished);
+                summary
+            })
+            .collect()
+    }
+
+    pub fn total(&self) -> Summary {
+        Summary {
+            lines: self.files.iter().map(|c| c.selected).sum(),
+            processed: self.processed(),
+            valid: self.valid(),
+            elapsed: self.start.elapsed(),
+        }
+    }
+}
@@ -0,0 +1,86 @@
+//! CPU throttling for `--max-cpu` and the GUI slider.
+//!
+//! Each validator thread runs on a duty cycle: after working for a while it
+//! sleeps long enough that its busy share of the wall-clock time stays at
+//! the configured fraction. With every thread paced that way the process
+//! uses that fraction of the cores it runs on. The share can be changed
+//! while a run is going.
+
+use std::{
+    sync::atomic::{AtomicU64, Ordering},
+    thread,
+    time::Duration,
+};
+
+/// Busy time collected before a thread sleeps; short enough that the load
+/// looks smooth, long enough that the sleeps stay cheap.
+const SLICE: Duration = Duration::from_millis(20);
+/// Lowest share accepted, so a thread always makes some progress.
+pub const MIN_SHARE: f64 = 0.01;
+
+/// Parses a CPU share given as a fraction (`0.5`) or a percentage (`50%`).
+pub fn parse_share(s: &str) -> Result<f64, String> {
+    let share = match s.strip_suffix('%') {
+        Some(percent) => percent.trim().parse::<f64>().map(|p| p / 100.0),
+        None => s.trim().parse::<f64>(),
+    }
+    .map_err(|_| format!("invalid CPU share '{}'", s))?;
+
+    if (MIN_SHARE..=1.0).contains(&share) {
+        Ok(share)
+    } else {
+        Err(format!("CPU share must be between 1% and 100%, got '{}'", s))
+    }
+}
+
+/// Fraction of each validator thread's time it may spend working.
+pub struct Throttle {
+    /// `f64` bits.
+    share: AtomicU64,
+}
+
+impl Throttle {
+    pub fn new(share: f64) -> Self {
+        Self {
+            share: AtomicU64::new(share.clamp(MIN_SHARE, 1.0).to_bits()),
+        }
+    }
+
+    pub fn set(&self, share: f64) {
+        self.share
+            .store(share.clamp(MIN_SHARE, 1.0).to_bits(), Ordering::Relaxed);
+    }
+
+    pub fn share(&self) -> f64 {
+        f64::from_bits(self.share.load(Ordering::Relaxed))
+    }
+
+    /// A pacer for one thread.
+    pub fn pacer(&self) -> Pacer<'_> {
+        Pacer {
+            throttle: self,
+            busy: Duration::ZERO,
+        }
+    }
+}
+
+/// Tracks one thread's busy time and sleeps off the excess.
+pub struct Pacer<'a> {
+    throttle: &'a Throttle,
+    busy: Duration,
+}
+
+impl Pacer<'_> {
+    /// Records `busy` time of work and sleeps once a slice is full.
+    pub fn worked(&mut self, busy: Duration) {
+        self.busy += busy;
+        if self.busy < SLICE {
+            return;
+        }
+        let share = self.throttle.share();
+        if share < 1.0 {
+            thread::sleep(self.busy.mul_f64((1.0 - share) / share));
+        }
+        self.busy = Duration::ZERO;
+    }
+}
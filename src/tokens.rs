@@ -0,0 +1,54 @@
+//! Splitting lines into words as bytes.
+//!
+//! `str::split_whitespace` decodes every character to ask whether it's
+//! whitespace. Phrases are ASCII nearly always, and for an ASCII line the
+//! words can be split on bytes instead, finding each separating space with
+//! memchr. Lines with other characters are split as before, on any Unicode
+//! whitespace, so both give the same words.
+
+/// The words of `line`, as `line.split_whitespace()` gives them.
+#[inline]
+pub fn words(line: &str) -> Words<'_> {
+    if line.is_ascii() {
+        Words::Ascii(line)
+    } else {
+        Words::Unicode(line.split_whitespace())
+    }
+}
+
+pub enum Words<'a> {
+    /// The rest of an ASCII line.
+    Ascii(&'a str),
+    Unicode(std::str::SplitWhitespace<'a>),
+}
+
+/// The ASCII characters `char::is_whitespace` holds for; unlike
+/// `u8::is_ascii_whitespace` these include the vertical tab.
+#[inline]
+fn is_space(byte: u8) -> bool {
+    matches!(byte, b' ' | b'\t' | b'\n' | b'\x0b' | b'\x0c' | b'\r')
+}
+
+impl<'a> Iterator for Words<'a> {
+    type Item = &'a str;
+
+    #[inline]
+    fn next(&mut self) -> Option<&'a str> {
+        let rest = match self {
+            Words::Ascii(rest) => rest,
+            Words::Unicode(words) => return words.next(),
+        };
+        let bytes = rest.as_bytes();
+        let start = bytes.iter().position(|&byte| !is_space(byte))?;
+        // Words are nearly always separated by a space
+        let end = memchr::memchr2(b' ', b'\t', &bytes[start..]).map_or(bytes.len(), |len| start + len);
+        let end = bytes[start..end]
+            .iter()
+            .position(|&byte| is_space(byte))
+            .map_or(end, |len| start + len);
+        // Splitting ASCII on bytes keeps to character boundaries
+        let word = &rest[start..end];
+        *rest = &rest[end..];
+        Some(word)
+    }
+}
@@ -0,0 +1,158 @@
+//! Picking the thread count, read size, queue depth and chunk size for the
+//! machine and input at hand.
+//!
+//! Before a run, [`probe`] reads the start of the first input to see how
+//! fast the storage delivers it, and validates a sample of its lines on one
+//! thread to see how fast they're checked. [`Probe::profile`] turns that and
+//! the number of cores into sizes: no more validator threads than the
+//! storage can keep busy, reads that take a few tens of milliseconds, and
+//! chunks that take a validator a couple of milliseconds. Input on a slow
+//! network share then doesn't get a thread per core that only waits, and
+//! noise that's rejected in nanoseconds a line doesn't get chunks so small
+//! that handing them out costs more than checking them.
+
+use crate::{
+    lines,
+    memory::{self, MIN_BLOCK_SIZE},
+    pipeline::LINES_PER_CHUNK,
+    secret::Zeroizing,
+};
+use std::{
+    fmt,
+    fs::File,
+    io::{self, Read},
+    path::Path,
+    thread,
+    time::Instant,
+};
+
+/// Read from the start of the input to time the storage.
+const PROBE_BYTES: u64 = 32 << 20;
+/// Reads shorter than this are over too soon to be timed.
+const MIN_TIMED: usize = 8 << 20;
+/// Lines validated to time validation.
+const SAMPLE_LINES: usize = 20_000;
+/// About how long a read should take.
+const READ_SECS: f64 = 0.05;
+/// About how long a validator should take over a chunk.
+const CHUNK_SECS: f64 = 0.002;
+const MAX_BLOCK_SIZE: usize = 16 << 20;
+/// The read size without a timed read, as without tuning.
+const DEFAULT_BLOCK_SIZE: usize = 4 << 20;
+const MIN_CHUNK_LINES: usize = 1 << 10;
+const MAX_CHUNK_LINES: usize = 1 << 16;
+
+/// What [`probe`] found.
+#[derive(Clone, Copy, Debug)]
+pub struct Probe {
+    pub cores: usize,
+    /// Bytes per second read from the input; `None` if it's too short to
+    /// tell.
+    pub read_rate: Option<f64>,
+    /// Bytes of lines per second one thread validates; `None` without lines.
+    pub validate_rate: Option<f64>,
+    /// Mean length of the sampled lines, with their line breaks.
+    pub avg_line: Option<usize>,
+}
+
+/// Sizes for a run, from [`Probe::profile`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Profile {
+    pub threads: usize,
+    /// Bytes per read, when the input isn't memory-mapped.
+    pub block_size: usize,
+    pub queue_depth: usize,
+    /// Lines per chunk cut from a memory-mapped input.
+    pub chunk_lines: usize,
+}
+
+/// Times reading the start of `path` and validating its first lines with
+/// `validate` (which should do all a run does to a line).
+pub fn probe(path: &Path, validate: impl Fn(&str) -> bool) -> io::Result<Probe> {
+    let cores = thread::available_parallelism().map_or(1, |n| n.get());
+    let mut buf = Zeroizing::new(Vec::new());
+    let started = Instant::now();
+    File::open(path)?.take(PROBE_BYTES).read_to_end(&mut buf)?;
+    let read_secs = started.elapsed().as_secs_f64();
+    let read_rate = (buf.len() >= MIN_TIMED).then(|| buf.len() as f64 / read_secs.max(1e-6));
+
+    // The last line may be cut off by the end of the read
+    let complete = match lines::last_break(&buf) {
+        Some(end) if buf.len() as u64 == PROBE_BYTES => &buf[..end],
+        _ => &buf[..],
+    };
+    let sample: Vec<&[u8]> = lines::lines(complete).take(SAMPLE_LINES).collect();
+    let bytes: usize = sample.iter().map(|line| line.len() + 1).sum();
+    let started = Instant::now();
+    let valid = sample
+        .iter()
+        .filter(|line| std::str::from_utf8(lines::normalize(line)).is_ok_and(&validate))
+        .count();
+    let validate_secs = started.elapsed().as_secs_f64();
+    // Keeps the checks from being optimized away
+    std::hint::black_box(valid);
+
+    Ok(Probe {
+        cores,
+        read_rate,
+        validate_rate: (!sample.is_empty()).then(|| bytes as f64 / validate_secs.max(1e-6)),
+        avg_line: (!sample.is_empty()).then(|| bytes / sample.len()),
+    })
+}
+
+impl Probe {
+    /// The sizes that suit what was found.
+    pub fn profile(&self) -> Profile {
+        let threads = match (self.read_rate, self.validate_rate) {
+            // One more than the storage keeps busy, for reads that come
+            // faster once they're under way
+            (Some(read), Some(validate)) => ((read / validate).ceil() as usize + 1).clamp(1, self.cores),
+            _ => self.cores,
+        };
+        let block_size = self.read_rate.map_or(DEFAULT_BLOCK_SIZE, |read| {
+            ((read * READ_SECS) as usize)
+                .next_power_of_two()
+                .clamp(MIN_BLOCK_SIZE, MAX_BLOCK_SIZE)
+        });
+        let chunk_lines = match (self.validate_rate, self.avg_line) {
+            (Some(validate), Some(avg_line)) => ((validate * CHUNK_SECS) as usize / avg_line.max(1))
+                .next_power_of_two()
+                .clamp(MIN_CHUNK_LINES, MAX_CHUNK_LINES),
+            _ => LINES_PER_CHUNK,
+        };
+        Profile {
+            threads,
+            block_size,
+            queue_depth: 2 * threads,
+            chunk_lines,
+        }
+    }
+}
+
+impl fmt::Display for Probe {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let rate = |rate: Option<f64>| rate.map_or("-".to_string(), |rate| format!("{}/s", memory::format_size(rate as u64)));
+        write!(
+            f,
+            "{} CPU{}, reading at {}, validating at {} per thread",
+            self.cores,
+            if self.cores == 1 { "" } else { "s" },
+            rate(self.read_rate),
+            rate(self.validate_rate)
+        )
+    }
+}
+
+impl fmt::Display for Profile {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} thread{}, blocks of {}, queue depth {}, {} lines per chunk",
+            self.threads,
+            if self.threads == 1 { "" } else { "s" },
+            memory::format_size(self.block_size as u64),
+            self.queue_depth,
+            self.chunk_lines
+        )
+    }
+}
@@ -0,0 +1,52 @@
+//! Undoing what word processors and OCR do to a phrase.
+//!
+//! A phrase copied out of a Word document or a scan often comes with curly
+//! quotes around it, en or em dashes between the words (or spaces that
+//! aren't plain spaces), and invisible zero-width characters inside words.
+//! None of those can be part of a BIP39 word, so before a line is split into
+//! words quotes, dashes and unusual spaces become plain spaces and
+//! zero-width characters are dropped.
+
+use crate::secret::SecretString;
+
+/// Characters that separate words, or surround the phrase, in typeset text.
+pub(crate) fn is_separator(c: char) -> bool {
+    matches!(
+        c,
+        // Dashes and the minus sign
+        '\u{2010}'..='\u{2015}' | '\u{2212}'
+        // Curly, low and angle quotes
+        | '\u{2018}'..='\u{201F}' | '\u{00AB}' | '\u{00BB}' | '\u{2039}' | '\u{203A}'
+        // No-break, fixed-width and ideographic spaces
+        | '\u{00A0}' | '\u{2000}'..='\u{200A}' | '\u{202F}' | '\u{205F}' | '\u{3000}'
+    )
+}
+
+/// Characters that take no space at all: zero-width space, (non-)joiners,
+/// word joiner, byte order mark and soft hyphen.
+pub(crate) fn is_invisible(c: char) -> bool {
+    matches!(c, '\u{200B}'..='\u{200D}' | '\u{2060}' | '\u{FEFF}' | '\u{00AD}')
+}
+
+/// The line with typographic separators turned into spaces and invisible
+/// characters removed, or `None` if it has neither (as with any ASCII line).
+pub fn clean(line: &str) -> Option<SecretString> {
+    if line.is_ascii() || !line.chars().any(|c| is_separator(c) || is_invisible(c)) {
+        return None;
+    }
+    // Never longer than the line, so it's never reallocated
+    let mut cleaned = SecretString::new(String::with_capacity(line.len()));
+    for c in line.chars() {
+        if is_separator(c) {
+            cleaned.push(' ');
+        } else if !is_invisible(c) {
+            cleaned.push(c);
+        }
+    }
+    // Quotes around the phrase leave spaces at its ends
+    let end = cleaned.trim_end().len();
+    cleaned.truncate(end);
+    let start = cleaned.len() - cleaned.trim_start().len();
+    cleaned.drain(..start);
+    Some(cleaned)
+}
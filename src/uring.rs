@@ -0,0 +1,168 @@
+//! io_uring block reader (Linux, `io-uring` feature).
+//!
+//! Keeps several fixed-size reads in flight at once and hands out blocks
+//! that always end on a line boundary (see [`crate::blocks`]), so the
+//! pipeline can split them into lines independently.
+
+use crate::blocks::{Block, Joiner, CARRY_ROOM};
+use io_uring::{opcode, types, IoUring};
+use std::{
+    collections::BTreeMap,
+    fs::File,
+    io,
+    os::unix::io::AsRawFd,
+    path::Path,
+};
+
+/// Bytes per read, by default.
+pub const BLOCK_SIZE: usize = 4 << 20;
+/// Reads kept in flight, by default.
+pub const QUEUE_DEPTH: usize = 8;
+/// A read in flight: where it reads to and into which buffer.
+struct Pending {
+    offset: u64,
+    len: usize,
+    buf: Vec<u8>,
+}
+
+pub struct BlockReader {
+    ring: IoUring,
+    file: File,
+    file_len: u64,
+    block_size: usize,
+    /// Next byte not yet requested.
+    next_offset: u64,
+    /// Ranges that came back short and still need reading.
+    retry: Vec<(u64, usize)>,
+    slots: Vec<Option<Pending>>,
+    /// Finished reads by offset, waiting to be joined in order.
+    done: BTreeMap<u64, Vec<u8>>,
+    joiner: Joiner,
+}
+
+/// Checks that io_uring can be used at all.
+pub fn available() -> io::Result<()> {
+    IoUring::new(1).map(drop)
+}
+
+impl BlockReader {
+    /// Numbers lines from `first_line`. Fails if the kernel doesn't
+    /// support io_uring (or it's disabled).
+    pub fn open(path: &Path, first_line: usize) -> io::Result<Self> {
+        Self::with_sizes(path, first_line, BLOCK_SIZE, QUEUE_DEPTH)
+    }
+
+    /// Like [`BlockReader::open`], with `block_size` bytes per read and
+    /// `queue_depth` reads in flight.
+    pub fn with_sizes(
+        path: &Path,
+        first_line: usize,
+        block_size: usize,
+        queue_depth: usize,
+    ) -> io::Result<Self> {
+        let file = File::open(path)?;
+        let file_len = file.metadata()?.len();
+        Ok(Self {
+            ring: IoUring::new(queue_depth as u32)?,
+            file,
+            file_len,
+            block_size,
+            next_offset: 0,
+            retry: Vec::new(),
+            slots: (0..queue_depth).map(|_| None).collect(),
+            done: BTreeMap::new(),
+            joiner: Joiner::new(file_len, first_line),
+        })
+    }
+
+    /// Queues reads until every slot is busy or the whole file is requested.
+    fn fill(&mut self) -> io::Result<usize> {
+        let mut queued = 0;
+        while let Some(slot) = self.slots.iter().position(Option::is_none) {
+            let (offset, len) = match self.retry.pop() {
+                Some(range) => range,
+                None if self.next_offset < self.file_len => {
+                    let len = self.block_size.min((self.file_len - self.next_offset) as usize);
+                    let offset = self.next_offset;
+                    self.next_offset += len as u64;
+                    (offset, len)
+                }
+                None => break,
+            };
+
+            let mut buf = vec![0u8; CARRY_ROOM + len];
+            let entry = opcode::Read::new(
+                types::Fd(self.file.as_raw_fd()),
+                buf[CARRY_ROOM..].as_mut_ptr(),
+                len as u32,
+            )
+            .offset(offset)
+            .build()
+            .user_data(slot as u64);
+
+            // Safety: the buffer is owned by the slot until its completion
+            // is reaped, and a Vec's heap storage doesn't move with it.
+            unsafe {
+                self.ring
+                    .submission()
+                    .push(&entry)
+                    .map_err(|_| io::Error::other("io_uring submission queue is full"))?;
+            }
+            self.slots[slot] = Some(Pending { offset, len, buf });
+            queued += 1;
+        }
+        Ok(queued)
+    }
+
+    /// Waits for at least one read and files away whatever has completed.
+    fn reap(&mut self) -> io::Result<()> {
+        self.ring.submit_and_wait(1)?;
+        let completions: Vec<_> = self
+            .ring
+            .completion()
+            .map(|cqe| (cqe.user_data() as usize, cqe.result()))
+            .collect();
+
+        for (slot, result) in completions {
+            let Pending { offset, len, mut buf } =
+                self.slots[slot].take().expect("completion for an idle slot");
+            if result < 0 {
+                return Err(io::Error::from_raw_os_error(-result));
+            }
+            let n = result as usize;
+            if n == 0 {
+                return Err(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    "input file shrank while reading",
+                ));
+            }
+            if n < len {
+                self.retry.push((offset + n as u64, len - n));
+            }
+            buf.truncate(CARRY_ROOM + n);
+            self.done.insert(offset, buf);
+        }
+        Ok(())
+    }
+}
+
+impl Iterator for BlockReader {
+    type Item = io::Result<Block>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(buf) = self.done.remove(&self.joiner.offset()) {
+                match self.joiner.join(buf) {
+                    Some(block) => return Some(Ok(block)),
+                    None => continue,
+                }
+            }
+            if self.joiner.is_done() {
+                return None;
+            }
+            if let Err(e) = self.fill().and_then(|_| self.reap()) {
+                return Some(Err(e));
+            }
+        }
+    }
+}
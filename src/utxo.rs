@@ -0,0 +1,424 @@
+//! Telling which derived keys hold coins, offline (`--utxo-set`), for
+//! air-gapped recoveries where asking a block explorer is out of the
+//! question.
+//!
+//! The funded outputs come from a snapshot Bitcoin Core wrote with
+//! `bitcoin-cli dumptxoutset`, or from a list of addresses. Only outputs
+//! that can pay to a single key's address are kept: P2PKH, P2SH (which
+//! nested SegWit addresses are), P2WPKH, and P2PK to a compressed key,
+//! which counts as the key's legacy address. They're kept by the hash they
+//! commit to, with the amounts paid to the same script added up.
+//!
+//! Reading a mainnet snapshot takes a while, and some 30 bytes of memory
+//! per funded script; `utxo-index` does it once and writes the sorted
+//! records to an index file, which later runs map into memory as it is.
+
+use crate::{
+    address::{self, Kind},
+    digest,
+    network::Network,
+};
+use memmap2::Mmap;
+use std::{
+    fs::File,
+    io::{self, BufReader, BufWriter, Read, Write},
+    path::Path,
+};
+
+/// First bytes of an index file.
+pub const INDEX_MAGIC: &[u8; 8] = b"mvutxo1\n";
+/// First bytes of a snapshot in the format of Bitcoin Core 28 and later.
+const SNAPSHOT_MAGIC: &[u8; 5] = b"utxo\xff";
+/// Kind, hash and amount in satoshis (little-endian).
+const RECORD: usize = 1 + 20 + 8;
+/// Magic, network, base block hash and record count.
+const INDEX_HEADER: usize = 8 + 1 + 32 + 8;
+
+type Record = [u8; RECORD];
+
+fn kind_byte(kind: Kind) -> u8 {
+    match kind {
+        Kind::Legacy => 0,
+        Kind::NestedSegwit => 1,
+        Kind::NativeSegwit => 2,
+    }
+}
+
+fn record(kind: Kind, hash: &[u8; 20], amount: u64) -> Record {
+    let mut record = [0; RECORD];
+    record[0] = kind_byte(kind);
+    record[1..21].copy_from_slice(hash);
+    record[21..].copy_from_slice(&amount.to_le_bytes());
+    record
+}
+
+/// Sorts records by script and adds up the amounts of the same one.
+fn merge(mut records: Vec<Record>) -> Vec<Record> {
+    records.sort_unstable_by(|a, b| a[..21].cmp(&b[..21]));
+    let mut merged: Vec<Record> = Vec::with_capacity(records.len());
+    for record in records {
+        match merged.last_mut() {
+            Some(last) if last[..21] == record[..21] => {
+                let total = amount(last).saturating_add(amount(&record));
+                last[21..].copy_from_slice(&total.to_le_bytes());
+            }
+            _ => merged.push(record),
+        }
+    }
+    merged.shrink_to_fit();
+    merged
+}
+
+fn amount(record: &[u8]) -> u64 {
+    u64::from_le_bytes(record[21..RECORD].try_into().unwrap())
+}
+
+/// The network a snapshot's message start bytes are those of.
+fn network_of(magic: [u8; 4]) -> Option<Network> {
+    Network::all().find(|network| network.has_magic(magic))
+}
+
+/// What a snapshot held, besides its funded scripts.
+#[derive(Debug, Default)]
+pub struct SnapshotInfo {
+    /// The block it's the state at, as block explorers show it.
+    pub block: String,
+    pub coins: u64,
+    /// Coins kept, the others paying to scripts that aren't a single
+    /// key's (multisig, Taproot, ...).
+    pub kept: u64,
+}
+
+struct Reader<R> {
+    inner: R,
+}
+
+impl<R: Read> Reader<R> {
+    fn bytes<const N: usize>(&mut self) -> io::Result<[u8; N]> {
+        let mut bytes = [0; N];
+        self.inner.read_exact(&mut bytes)?;
+        Ok(bytes)
+    }
+
+    fn skip(&mut self, n: u64) -> io::Result<()> {
+        let skipped = io::copy(&mut (&mut self.inner).take(n), &mut io::sink())?;
+        if skipped < n {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        Ok(())
+    }
+
+    /// Bitcoin Core's `CompactSize`.
+    fn compact_size(&mut self) -> io::Result<u64> {
+        Ok(match self.bytes::<1>()?[0] {
+            0xfd => u16::from_le_bytes(self.bytes()?) as u64,
+            0xfe => u32::from_le_bytes(self.bytes()?) as u64,
+            0xff => u64::from_le_bytes(self.bytes()?),
+            n => n as u64,
+        })
+    }
+
+    /// Bitcoin Core's `VARINT`: base 128, most significant digit first,
+    /// each digit but the last one less than it says.
+    fn varint(&mut self) -> io::Result<u64> {
+        let mut n = 0u64;
+        loop {
+            let byte = self.bytes::<1>()?[0];
+            n = n
+                .checked_mul(128)
+                .map(|n| n | (byte & 0x7f) as u64)
+                .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "malformed snapshot"))?;
+            if byte & 0x80 == 0 {
+                return Ok(n);
+            }
+            n += 1;
+        }
+    }
+
+    /// A `Coin`: its height, then its output compressed. The record of the
+    /// script it pays to, if it's a single key's.
+    fn coin(&mut self) -> io::Result<Option<Record>> {
+        self.varint()?;
+        let value = decompress_amount(self.varint()?);
+        let kind = self.varint()?;
+        Ok(match kind {
+            0 => Some(record(Kind::Legacy, &self.bytes()?, value)),
+            1 => Some(record(Kind::NestedSegwit, &self.bytes()?, value)),
+            // P2PK to a compressed key: the key's hash is its legacy address
+            2 | 3 => {
+                let mut key = [0; 33];
+                key[0] = kind as u8;
+                key[1..].copy_from_slice(&self.bytes::<32>()?);
+                Some(record(Kind::Legacy, &digest::hash160(&key), value))
+            }
+            4 | 5 => {
+                self.skip(32)?;
+                None
+            }
+            size => {
+                let size = size - 6;
+                if size == 22 {
+                    let script: [u8; 22] = self.bytes()?;
+                    (script[..2] == [0x00, 0x14])
+                        .then(|| record(Kind::NativeSegwit, script[2..].try_into().unwrap(), value))
+                } else {
+                    self.skip(size)?;
+                    None
+                }
+            }
+        })
+    }
+}
+
+/// Bitcoin Core's `DecompressAmount`.
+fn decompress_amount(mut x: u64) -> u64 {
+    if x == 0 {
+        return 0;
+    }
+    x -= 1;
+    let mut e = x % 10;
+    x /= 10;
+    let mut n = if e < 9 {
+        let d = x % 9 + 1;
+        x /= 9;
+        x * 10 + d
+    } else {
+        x + 1
+    };
+    while e > 0 {
+        n = n.saturating_mul(10);
+        e -= 1;
+    }
+    n
+}
+
+/// Reads a `dumptxoutset` snapshot for `network`, in the format of Bitcoin
+/// Core 28 and later or the one before. `progress` is told the coins read
+/// so far and how many there are, every million.
+fn read_snapshot(
+    path: &Path,
+    network: Network,
+    mut progress: impl FnMut(u64, u64),
+) -> io::Result<(Vec<Record>, SnapshotInfo)> {
+    let invalid = |message: String| io::Error::new(io::ErrorKind::InvalidData, message);
+    let mut reader = Reader {
+        inner: BufReader::with_capacity(1 << 20, File::open(path)?),
+    };
+    let start: [u8; 5] = reader.bytes()?;
+    let grouped = start == *SNAPSHOT_MAGIC;
+    let mut block = [0; 32];
+    if grouped {
+        let version = u16::from_le_bytes(reader.bytes()?);
+        if version != 2 {
+            return Err(invalid(format!("snapshot format version {} isn't supported", version)));
+        }
+        let magic: [u8; 4] = reader.bytes()?;
+        match network_of(magic) {
+            Some(of) if of != network => {
+                return Err(invalid(format!("the snapshot is of {}, not {} (see --network)", of, network)))
+            }
+            // Custom signets have message starts of their own
+            None if network.name() != "signet" => return Err(invalid("the snapshot is of an unknown network".to_string())),
+            _ => {}
+        }
+        reader.inner.read_exact(&mut block)?;
+    } else {
+        block[..5].copy_from_slice(&start);
+        reader.inner.read_exact(&mut block[5..])?;
+    }
+    let coins = u64::from_le_bytes(reader.bytes()?);
+    block.reverse();
+    let mut info = SnapshotInfo {
+        block: crate::digest::to_hex(&block),
+        coins,
+        kept: 0,
+    };
+    let mut records = Vec::new();
+    let mut read = 0;
+    let truncated = |e: io::Error| match e.kind() {
+        io::ErrorKind::UnexpectedEof => invalid("the snapshot ends before its last coin".to_string()),
+        _ => e,
+    };
+    while read < coins {
+        // Coins grouped by transaction, or each with its outpoint
+        let outputs = if grouped {
+            reader.skip(32).map_err(truncated)?;
+            reader.compact_size().map_err(truncated)?
+        } else {
+            1
+        };
+        for _ in 0..outputs {
+            if grouped {
+                reader.compact_size().map_err(truncated)?;
+            } else {
+                reader.skip(36).map_err(truncated)?;
+            }
+            if let Some(record) = reader.coin().map_err(truncated)? {
+                records.push(record);
+            }
+            read += 1;
+            if read % 1_000_000 == 0 {
+                progress(read, coins);
+            }
+        }
+    }
+    info.kept = records.len() as u64;
+    Ok((merge(records), info))
+}
+
+/// Reads a list of addresses, one per line, each optionally followed by an
+/// amount in satoshis (after a space, tab or comma), with blank lines and
+/// `#` comments ignored. Addresses of other kinds, and header lines, are
+/// skipped; returns the records and how many lines were skipped.
+fn read_addresses(path: &Path, network: Network) -> io::Result<(Vec<Record>, usize)> {
+    let text = std::fs::read_to_string(path)?;
+    let (mut records, mut skipped) = (Vec::new(), 0);
+    for line in text.lines() {
+        let line = line.split('#').next().unwrap_or_default().trim();
+        let mut fields = line.split([',', '\t', ' ']).filter(|field| !field.is_empty());
+        let Some(address) = fields.next() else {
+            continue;
+        };
+        match address::decode(address, network) {
+            Some((kind, hash)) => {
+                let amount = fields.next().and_then(|amount| amount.parse().ok()).unwrap_or(0);
+                records.push(record(kind, &hash, amount));
+            }
+            None => skipped += 1,
+        }
+    }
+    Ok((merge(records), skipped))
+}
+
+enum Records {
+    Loaded(Vec<Record>),
+    Mapped(Mmap),
+}
+
+/// The funded scripts, sorted.
+pub struct UtxoSet {
+    records: Records,
+}
+
+/// How an [`UtxoSet`] was opened, to tell the user.
+pub enum Source {
+    /// With the block the set is the state at.
+    Index(String),
+    Snapshot(SnapshotInfo),
+    /// With the number of lines that weren't addresses of single keys.
+    Addresses(usize),
+}
+
+impl UtxoSet {
+    /// Opens an index file, a snapshot or a list of addresses, told apart
+    /// by their first bytes.
+    pub fn open(path: &Path, network: Network, progress: impl FnMut(u64, u64)) -> io::Result<(Self, Source)> {
+        let mut start = [0; 64];
+        let mut file = File::open(path)?;
+        let n = read_up_to(&mut file, &mut start)?;
+        let start = &start[..n];
+        if start.starts_with(INDEX_MAGIC) {
+            let set = Self::open_index(path, network)?;
+            let Records::Mapped(map) = &set.records else { unreachable!() };
+            let block = crate::digest::to_hex(&map[9..41]);
+            return Ok((set, Source::Index(block)));
+        }
+        // The older snapshots start with a block hash, which is binary
+        let text = start.iter().all(|&b| b.is_ascii_graphic() || b.is_ascii_whitespace());
+        if start.starts_with(SNAPSHOT_MAGIC) || !text {
+            let (records, info) = read_snapshot(path, network, progress)?;
+            return Ok((Self { records: Records::Loaded(records) }, Source::Snapshot(info)));
+        }
+        let (records, skipped) = read_addresses(path, network)?;
+        Ok((Self { records: Records::Loaded(records) }, Source::Addresses(skipped)))
+    }
+
+    fn open_index(path: &Path, network: Network) -> io::Result<Self> {
+        let invalid = |message: String| io::Error::new(io::ErrorKind::InvalidData, message);
+        let file = File::open(path)?;
+        // Safety: the index isn't written to while it's in use, as for the
+        // inputs
+        let map = unsafe { Mmap::map(&file)? };
+        if map.len() < INDEX_HEADER {
+            return Err(invalid("the index file is truncated".to_string()));
+        }
+        match Network::all().nth(map[8] as usize) {
+            Some(of) if of == network => {}
+            Some(of) => return Err(invalid(format!("the index is of {}, not {} (see --network)", of, network))),
+            None => return Err(invalid("not an index file".to_string())),
+        }
+        let count = u64::from_le_bytes(map[41..49].try_into().unwrap());
+        if (map.len() - INDEX_HEADER) as u64 != count.saturating_mul(RECORD as u64) {
+            return Err(invalid("the index file is truncated".to_string()));
+        }
+        Ok(Self {
+            records: Records::Mapped(map),
+        })
+    }
+
+    fn records(&self) -> &[u8] {
+        match &self.records {
+            Records::Loaded(records) => records.as_flattened(),
+            Records::Mapped(map) => &map[INDEX_HEADER..],
+        }
+    }
+
+    /// Funded scripts.
+    pub fn len(&self) -> usize {
+        self.records().len() / RECORD
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The amount paid to `public_key`'s address of `kind`, if any is.
+    pub fn lookup(&self, kind: Kind, public_key: &[u8; 33]) -> Option<u64> {
+        let mut key = [0; 21];
+        key[0] = kind_byte(kind);
+        key[1..].copy_from_slice(&kind.hash(public_key));
+        let records = self.records();
+        let (mut low, mut high) = (0, records.len() / RECORD);
+        while low < high {
+            let middle = (low + high) / 2;
+            let record = &records[middle * RECORD..(middle + 1) * RECORD];
+            match record[..21].cmp(&key) {
+                std::cmp::Ordering::Less => low = middle + 1,
+                std::cmp::Ordering::Greater => high = middle,
+                std::cmp::Ordering::Equal => return Some(amount(record)),
+            }
+        }
+        None
+    }
+
+    /// Writes the set to an index file for `network`, of the state at
+    /// `block` (hex, as shown by block explorers).
+    pub fn write_index(&self, path: &Path, network: Network, block: &str) -> io::Result<()> {
+        let mut hash = [0; 32];
+        for (i, byte) in hash.iter_mut().enumerate() {
+            *byte = block.get(2 * i..2 * i + 2).and_then(|hex| u8::from_str_radix(hex, 16).ok()).unwrap_or(0);
+        }
+        let network = network.index() as u8;
+        let mut temporary = path.as_os_str().to_owned();
+        temporary.push(".tmp");
+        let mut file = BufWriter::new(File::create(&temporary)?);
+        file.write_all(INDEX_MAGIC)?;
+        file.write_all(&[network])?;
+        file.write_all(&hash)?;
+        file.write_all(&(self.len() as u64).to_le_bytes())?;
+        file.write_all(self.records())?;
+        file.into_inner().map_err(|e| e.into_error())?.sync_all()?;
+        std::fs::rename(&temporary, path)
+    }
+}
+
+fn read_up_to(file: &mut File, buf: &mut [u8]) -> io::Result<usize> {
+    let mut n = 0;
+    while n < buf.len() {
+        match file.read(&mut buf[n..])? {
+            0 => break,
+            read => n += read,
+        }
+    }
+    Ok(n)
+}
@@ -0,0 +1,68 @@
+//! `mnemonic_validator utxo-index`: reading a `dumptxoutset` snapshot once
+//! into an index file that `--utxo-set` opens in an instant.
+
+use clap::Args;
+use mnemonic_validator::{
+    network::{self, Network},
+    paths,
+    utxo::{Source, UtxoSet},
+};
+use std::path::{Path, PathBuf};
+
+#[derive(Args, Debug)]
+pub struct UtxoIndexArgs {
+    /// Snapshot written by `bitcoin-cli dumptxoutset`, or a list of addresses.
+    #[clap(value_parser = paths::parser())]
+    snapshot: PathBuf,
+
+    /// Index file to write.
+    #[clap(short, long, value_name = "FILE", value_parser = paths::parser())]
+    output: PathBuf,
+
+    /// Network of the snapshot: mainnet, testnet, signet, regtest, or one of the coin registry with a `magic`.
+    #[clap(long, value_name = "NETWORK", default_value = "mainnet", value_parser = network::parse_network)]
+    network: Network,
+}
+
+/// Opens a `--utxo-set`, saying what it holds.
+pub fn open(path: &Path, network: Network) -> Result<(UtxoSet, Option<String>), String> {
+    if network.keccak() {
+        return Err(format!("a UTXO set holds Bitcoin-style outputs, and {} has none", network));
+    }
+    let (set, source) = UtxoSet::open(path, network, |read, coins| {
+        println!("Reading the snapshot: {} of {} coins", read, coins);
+    })
+    .map_err(|e| format!("can't read the UTXO set {}: {}", path.display(), e))?;
+    let block = match source {
+        Source::Index(block) => {
+            println!("UTXO set: {} funded scripts, as of block {}", set.len(), block);
+            Some(block)
+        }
+        Source::Snapshot(info) => {
+            println!(
+                "UTXO set: {} of {} coins pay to single keys, at {} scripts, as of block {}",
+                info.kept,
+                info.coins,
+                set.len(),
+                info.block
+            );
+            Some(info.block)
+        }
+        Source::Addresses(skipped) => {
+            println!("UTXO set: {} addresses", set.len());
+            if skipped > 0 {
+                println!("Warning: {} lines weren't addresses of single keys on {}, and were left out", skipped, network);
+            }
+            None
+        }
+    };
+    Ok((set, block))
+}
+
+pub fn run(args: &UtxoIndexArgs) -> Result<(), Box<dyn std::error::Error>> {
+    let (set, block) = open(&args.snapshot, args.network)?;
+    set.write_index(&args.output, args.network, block.as_deref().unwrap_or_default())
+        .map_err(|e| format!("can't write {}: {}", args.output.display(), e))?;
+    println!("Index written to {}", args.output.display());
+    Ok(())
+}
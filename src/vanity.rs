@@ -0,0 +1,125 @@
+//! Keeping only the valid phrases whose wallet has an address that looks
+//! like one remembered (`--address-prefix`, `--address-regex`), or one
+//! that holds coins (`--utxo-set`).
+//!
+//! The addresses checked are the first receive addresses of a phrase's
+//! legacy, nested SegWit and native SegWit accounts, without a passphrase.
+//! Accounts whose addresses can't start with any of the prefixes are left
+//! out, which matters: deriving addresses costs far more than validating.
+//! With `--blockchain solana` or `cardano`, they're those of the phrase's
+//! wallet on that chain instead (see [`derive`](crate::derive)).
+
+use crate::{
+    address::{self, Kind},
+    bip32::{ExtendedKey, HARDENED},
+    derive::{self, Blockchain},
+    network::Network,
+    secret::Zeroizing,
+    utxo::UtxoSet,
+};
+use bip39::Mnemonic;
+use regex::Regex;
+
+/// Receive addresses checked per account when none is asked for.
+pub const DEFAULT_ADDRESSES: u32 = 20;
+
+/// For clap.
+pub fn parse_regex(s: &str) -> Result<Regex, String> {
+    Regex::new(s).map_err(|e| e.to_string())
+}
+
+pub struct AddressFilter {
+    /// Any one will do; none means any address.
+    prefixes: Vec<String>,
+    regex: Option<Regex>,
+    /// Funded scripts an address must be among.
+    funded: Option<UtxoSet>,
+    /// Receive addresses per account.
+    addresses: u32,
+    blockchain: Blockchain,
+    network: Network,
+    /// Accounts whose addresses could match.
+    kinds: Vec<Kind>,
+}
+
+impl AddressFilter {
+    /// `None` when there is nothing to filter on.
+    pub fn new(
+        prefixes: Vec<String>,
+        regex: Option<Regex>,
+        funded: Option<UtxoSet>,
+        addresses: u32,
+        blockchain: Blockchain,
+        network: Network,
+    ) -> Option<Self> {
+        if prefixes.is_empty() && regex.is_none() && funded.is_none() {
+            return None;
+        }
+        let kinds = Kind::ALL
+            .into_iter()
+            .filter(|&kind| kind.exists_on(network))
+            .filter(|&kind| {
+                prefixes.is_empty()
+                    || address::starts(kind, network).iter().any(|start| {
+                        prefixes.iter().any(|prefix| prefix.starts_with(start.as_str()) || start.starts_with(prefix.as_str()))
+                    })
+            })
+            .collect();
+        Some(Self {
+            prefixes,
+            regex,
+            funded,
+            addresses,
+            blockchain,
+            network,
+            kinds,
+        })
+    }
+
+    fn matches_address(&self, address: &str) -> bool {
+        (self.prefixes.is_empty() || self.prefixes.iter().any(|prefix| address.starts_with(prefix.as_str())))
+            && self.regex.as_ref().is_none_or(|regex| regex.is_match(address))
+    }
+
+    /// The first address of `mnemonic`'s wallet that passes the filter.
+    pub fn find(&self, mnemonic: &Mnemonic) -> Option<String> {
+        let seed = Zeroizing::new(mnemonic.to_seed_normalized(""));
+        match self.blockchain {
+            Blockchain::Bitcoin => {}
+            Blockchain::Solana => {
+                return derive::solana_addresses(&*seed, self.addresses).find(|address| self.matches_address(address))
+            }
+            Blockchain::Cardano => {
+                return derive::cardano_addresses(mnemonic, self.addresses).find(|address| self.matches_address(address))
+            }
+        }
+        let master = ExtendedKey::master(&*seed)?;
+        let coin = self.network.coin_type();
+        for &kind in &self.kinds {
+            let Some(receive) = master.derive(&[kind.purpose() | HARDENED, coin | HARDENED, HARDENED, 0]) else {
+                continue;
+            };
+            for index in 0..self.addresses {
+                let Some(key) = receive.child(index) else {
+                    continue;
+                };
+                let public_key = key.public_key();
+                // Looking it up is far cheaper than encoding it
+                if self.funded.as_ref().is_some_and(|funded| funded.lookup(kind, &public_key).is_none()) {
+                    continue;
+                }
+                let Some(address) = address::encode(kind, &public_key, self.network) else {
+                    continue;
+                };
+                if self.matches_address(&address) {
+                    return Some(address);
+                }
+            }
+        }
+        None
+    }
+
+    pub fn matches(&self, mnemonic: &Mnemonic) -> bool {
+        self.find(mnemonic).is_some()
+    }
+}
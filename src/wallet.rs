@@ -0,0 +1,113 @@
+//! Finding known addresses in a wallet the way wallet software discovers
+//! them: each chain of an account is walked until the gap limit, a run of
+//! that many addresses none of which is known, is reached.
+//!
+//! An address counts as used when it's one of the targets, so every hit
+//! extends the scan by another gap after it. Only the first account of each
+//! kind of address among the targets is scanned.
+
+use crate::{
+    address::Targets,
+    bip32::{ExtendedKey, HARDENED},
+    network::Network,
+};
+
+/// Unused addresses in a row after which a chain is given up on, as in
+/// BIP44 account discovery.
+pub const DEFAULT_GAP_LIMIT: u32 = 20;
+
+/// The two chains of a BIP44 account.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Chain {
+    /// Receive addresses, `…/0/i`.
+    External,
+    /// Change addresses, `…/1/i`.
+    Internal,
+}
+
+impl Chain {
+    /// The chain's step in a derivation path: 0 external, 1 internal.
+    pub fn index(self) -> u32 {
+        match self {
+            Chain::External => 0,
+            Chain::Internal => 1,
+        }
+    }
+}
+
+/// `external` (or `receive`) and `internal` (or `change`), for clap.
+pub fn parse_chain(s: &str) -> Result<Chain, String> {
+    match s.trim().to_ascii_lowercase().as_str() {
+        "external" | "receive" => Ok(Chain::External),
+        "internal" | "change" => Ok(Chain::Internal),
+        _ => Err(format!("'{}' isn't a chain; expected external (receive) or internal (change)", s)),
+    }
+}
+
+/// What to walk and how far.
+#[derive(Clone, Debug)]
+pub struct Scan {
+    pub chains: Vec<Chain>,
+    pub gap_limit: u32,
+    pub network: Network,
+}
+
+impl Default for Scan {
+    fn default() -> Self {
+        Self {
+            chains: vec![Chain::External],
+            gap_limit: DEFAULT_GAP_LIMIT,
+            network: Network::default(),
+        }
+    }
+}
+
+/// A target found in the wallet.
+#[derive(Debug)]
+pub struct Hit {
+    /// Derivation path of the address, e.g. `m/84'/0'/0'/0/3`.
+    pub path: String,
+    pub address: String,
+}
+
+impl Scan {
+    /// The targets in the wallet of `master`, in the order they were found;
+    /// with `first_only`, the scan stops at the first.
+    pub fn run(&self, master: &ExtendedKey, targets: &Targets, first_only: bool) -> Vec<Hit> {
+        let mut hits = Vec::new();
+        for kind in targets.kinds() {
+            let (purpose, coin) = (kind.purpose(), self.network.coin_type());
+            let Some(account) = master.derive(&[purpose | HARDENED, coin | HARDENED, HARDENED]) else {
+                continue;
+            };
+            for &chain in &self.chains {
+                let Some(chain_key) = account.child(chain.index()) else {
+                    continue;
+                };
+                let mut unused = 0;
+                // Indices past 2^31 are hardened ones, outside the chain
+                for index in 0..HARDENED {
+                    if unused == self.gap_limit {
+                        break;
+                    }
+                    let found = chain_key
+                        .child(index)
+                        .and_then(|key| targets.find(kind, &key.public_key()));
+                    let Some(address) = found else {
+                        unused += 1;
+                        continue;
+                    };
+                    unused = 0;
+                    hits.push(Hit {
+                        path: format!("m/{}'/{}'/0'/{}/{}", purpose, coin, chain.index(), index),
+                        address: address.to_string(),
+                    });
+                    if first_only {
+                        return hits;
+                    }
+                }
+            }
+        }
+        hits
+    }
+}
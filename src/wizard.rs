@@ -0,0 +1,223 @@
+//! `mnemonic_validator wizard`: a few questions about what's remembered of
+//! a phrase, and the search they call for.
+//!
+//! The answers make up a `recover` search (see [`recover`](crate::recover)):
+//! the words typed, `?` for the missing ones, similar words or any word for
+//! those marked uncertain, and the addresses and passphrases to confirm the
+//! right phrase with. The equivalent `recover` command is shown before it
+//! starts, for running it again without the questions.
+
+use crate::recover::{self, Plan};
+use bip39::Language;
+use clap::Args;
+use mnemonic_validator::{
+    address::Targets,
+    diagnostics, eta,
+    network::{self, Network},
+    paths,
+    recovery::{self, Confirm, Template},
+    secret::SecretString,
+    wallet::{self, Chain, Scan},
+    wordlist,
+};
+use std::{
+    io::{self, BufRead, Write},
+    path::PathBuf,
+};
+
+#[derive(Args, Debug)]
+pub struct WizardArgs {
+    /// Network of the addresses asked for: mainnet, testnet, signet, regtest, ethereum, or one of the coin registry.
+    #[clap(long, value_name = "NETWORK", default_value = "mainnet", value_parser = network::parse_network)]
+    network: Network,
+
+    /// File to append the phrases found to.
+    #[clap(short, long, value_name = "FILE", default_value = "output/recovered.txt", value_parser = paths::parser())]
+    output: PathBuf,
+
+    /// Number of threads (default: one per CPU).
+    #[clap(long, value_name = "N", value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..))]
+    threads: Option<usize>,
+}
+
+/// Asks `question` and returns the answer, trimmed.
+fn ask(question: &str) -> Result<SecretString, Box<dyn std::error::Error>> {
+    print!("{} ", question);
+    io::stdout().flush()?;
+    let mut answer = SecretString::new(String::new());
+    if io::stdin().lock().read_line(&mut answer)? == 0 {
+        return Err("no answer; the wizard needs a terminal to ask its questions".into());
+    }
+    Ok(SecretString::new(answer.trim().to_string()))
+}
+
+fn yes(question: &str, default: bool) -> Result<bool, Box<dyn std::error::Error>> {
+    loop {
+        let answer = ask(&format!("{} [{}]", question, if default { "Y/n" } else { "y/N" }))?;
+        match answer.to_ascii_lowercase().as_str() {
+            "" => return Ok(default),
+            "y" | "yes" => return Ok(true),
+            "n" | "no" => return Ok(false),
+            _ => println!("  Answer y or n."),
+        }
+    }
+}
+
+/// Lines typed until an empty one.
+fn ask_lines(question: &str) -> Result<Vec<SecretString>, Box<dyn std::error::Error>> {
+    println!("{}", question);
+    let mut lines = Vec::new();
+    loop {
+        let line = ask(" ")?;
+        if line.is_empty() {
+            return Ok(lines);
+        }
+        lines.push(line);
+    }
+}
+
+/// The slot of a typed word; one that isn't on the list stands for the
+/// list words like it.
+fn slot(position: usize, word: &str) -> Result<Vec<u16>, Box<dyn std::error::Error>> {
+    if word == "?" {
+        return Ok((0..2048).collect());
+    }
+    if let Some(index) = wordlist::index_in(word, Language::English) {
+        return Ok(vec![index]);
+    }
+    let similar = recovery::similar(word);
+    if similar.is_empty() {
+        println!("  Word {}, '{}', isn't on the list and nothing on it is like it; it's taken as unknown.", position, word);
+        return Ok((0..2048).collect());
+    }
+    println!(
+        "  Word {}, '{}', isn't on the list; trying what it may have been: {}.",
+        position,
+        word,
+        diagnostics::suggest(word).join(", ")
+    );
+    Ok(similar)
+}
+
+pub fn run(args: &WizardArgs) -> Result<(), Box<dyn std::error::Error>> {
+    println!("Phrase recovery: a few questions about what you remember, then the search. Press Ctrl+C to quit.");
+    println!("What you type is shown on screen; make sure nobody is looking.\n");
+
+    let words = loop {
+        match ask("How many words does the phrase have (12, 15, 18, 21 or 24)?")?.parse::<usize>() {
+            Ok(n @ (12 | 15 | 18 | 21 | 24)) => break n,
+            _ => println!("  BIP39 phrases have 12, 15, 18, 21 or 24 words."),
+        }
+    };
+
+    let mut slots = loop {
+        println!("\nType the words you remember, in order, separated by spaces, with ? for each one you don't.");
+        let typed = ask(">")?;
+        let typed: Vec<String> = typed.split_whitespace().map(|word| word.to_lowercase()).collect();
+        if typed.len() != words {
+            println!("  That's {} words; the phrase has {}. Use ? for the ones you don't remember.", typed.len(), words);
+            continue;
+        }
+        let slots = typed
+            .iter()
+            .enumerate()
+            .map(|(i, word)| slot(i + 1, word))
+            .collect::<Result<Vec<_>, _>>()?;
+        break slots;
+    };
+
+    println!();
+    let uncertain = ask("Which of the words you typed are you unsure of? Give their positions (e.g. 3 7), or press Enter for none:")?;
+    for position in uncertain.split(|c: char| c.is_whitespace() || c == ',').filter(|s| !s.is_empty()) {
+        let Some(i) = position.parse::<usize>().ok().filter(|i| (1..=words).contains(i)) else {
+            println!("  '{}' isn't a position from 1 to {}; skipped.", position, words);
+            continue;
+        };
+        if slots[i - 1].len() == 2048 {
+            continue;
+        }
+        let list = Language::English.word_list();
+        let word = list[slots[i - 1][0] as usize];
+        if yes(&format!("  Word {} ('{}'): might it be any word at all, rather than one like it?", i, word), false)? {
+            slots[i - 1] = (0..2048).collect();
+        } else {
+            slots[i - 1] = recovery::similar(word);
+        }
+    }
+    let template = Template::new(slots)?;
+
+    println!();
+    let confirm = if yes(
+        "Do you know an address the wallet received coins at? It tells the right phrase apart from the others with a valid checksum.",
+        true,
+    )? {
+        let targets = loop {
+            let lines = ask_lines("Paste the addresses, one per line, and an empty line to finish:")?;
+            let text: Vec<&str> = lines.iter().map(|line| line.as_str()).collect();
+            match Targets::parse(&text.join("\n"), args.network) {
+                Ok(targets) if !targets.is_empty() => break targets,
+                Ok(_) => println!("  No addresses given."),
+                Err(e) => println!("  {}", e),
+            }
+        };
+        let mut passphrases = vec![SecretString::new(String::new())];
+        if yes("Might the wallet have a passphrase (a \"25th word\")?", false)? {
+            passphrases.extend(ask_lines("Type the passphrases it might be, one per line, and an empty line to finish (no passphrase is tried too):")?);
+        }
+        Some(Confirm {
+            targets,
+            passphrases,
+            scan: Scan {
+                chains: vec![Chain::External],
+                gap_limit: wallet::DEFAULT_GAP_LIMIT,
+                network: args.network,
+            },
+        })
+    } else {
+        None
+    };
+    let stop_at_first = confirm.is_none()
+        && yes(
+            "Stop at the first phrase with a valid checksum? Without an address, it's only a guess at which one is yours.",
+            false,
+        )?;
+
+    let plan = Plan {
+        template,
+        confirm,
+        output: args.output.clone(),
+        threads: args.threads.unwrap_or_else(recover::default_threads),
+        checkpoint: recover::default_checkpoint(&args.output),
+        restart: false,
+        stop_after: stop_at_first.then_some(1),
+    };
+    let mut estimate = recovery::estimate(&plan.template, plan.threads, plan.confirm.as_ref());
+    if let Some(limit) = plan.stop_after.filter(|_| plan.confirm.is_none()) {
+        estimate = estimate.until(limit);
+    }
+    println!("\nThat's {}.", estimate);
+    let files = match &plan.confirm {
+        Some(confirm) if confirm.passphrases.len() > 1 => " --targets addresses.txt --passphrase-file passphrases.txt",
+        Some(_) => " --targets addresses.txt",
+        None => "",
+    };
+    println!("The same search without the questions{}:", if files.is_empty() { "" } else { ", with your answers in those files" });
+    println!(
+        "  mnemonic_validator recover '{}'{} -o {}{}{}",
+        plan.template.to_text(),
+        files,
+        args.output.display(),
+        if plan.stop_after.is_some() { " --stop-at-first" } else { "" },
+        if estimate.is_long() { " --yes" } else { "" }
+    );
+    if estimate.is_long() {
+        println!(
+            "It would take {}; remembering more of the words, or more about them, cuts that down.",
+            eta::format_span(estimate.seconds)
+        );
+    }
+    if !yes("Start the search?", !estimate.is_long())? {
+        return Ok(());
+    }
+    recover::execute(&plan)
+}
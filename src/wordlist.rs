@@ -0,0 +1,34 @@
+//! Compile-time perfect-hash lookups over the BIP39 wordlists.
+//!
+//! The map is generated by `build.rs` from the wordlists shipped with the
+//! `bip39` crate for every supported language, so it always matches what
+//! the validator accepts. Words must already be NFKD-normalized, like the
+//! input to [`bip39::Mnemonic::parse_in_normalized`].
+
+use bip39::Language;
+
+include!(concat!(env!("OUT_DIR"), "/wordlists.rs"));
+
+/// Every `(language, index)` the word appears at; empty if it's on no list.
+///
+/// Most words belong to a single list, but some are shared (for instance
+/// between English and French, or the two Chinese lists).
+#[inline]
+pub fn lookup(word: &str) -> &'static [(Language, u16)] {
+    WORDS.get(word).copied().unwrap_or(&[])
+}
+
+/// Index of `word` in the wordlist of `language`.
+#[inline]
+pub fn index_in(word: &str, language: Language) -> Option<u16> {
+    lookup(word)
+        .iter()
+        .find(|(lang, _)| *lang == language)
+        .map(|&(_, index)| index)
+}
+
+/// True if `word` is in the English wordlist.
+#[inline]
+pub fn is_english_word(word: &str) -> bool {
+    index_in(word, Language::English).is_some()
+}
@@ -0,0 +1,465 @@
+//! `mnemonic_validator worker`: taking work from a queue shared by a fleet
+//! of workers, so that a backlog needn't be split up by hand.
+//!
+//! A message on the queue names a file to validate, or carries a batch of
+//! lines. A file is validated by a run of its own in a child process, as by
+//! the daemon, with the options given after `--`, a checkpoint in the work
+//! directory and its output wherever those options say. A batch is
+//! validated right here, and only the positions of its valid lines go back
+//! with the result: the producer has the lines, and phrases never travel
+//! over the queue. A run stopped before the end of its file (by a signal or
+//! a time limit) puts its message back for the next worker.
+//!
+//! With `--coordinator` instead, the work is shards of lines handed out by
+//! `mnemonic_validator coordinator`. Each is run the same way, with
+//! `--skip`/`--limit` and a heartbeat file the worker reads its progress
+//! from to report it; a run stopped part-way reports the line it stopped
+//! at, and the next worker carries on from there.
+
+use crate::{console, Cli};
+use clap::{Args, Parser};
+use mnemonic_validator::{
+    audit, batch, digest,
+    http::{self, Endpoint},
+    paths,
+    queue::{self, QueueUrl, Taken, WorkQueue},
+    shards::Shard,
+};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::{
+    fs::{self, File},
+    path::{Path, PathBuf},
+    process::{Child, Command, ExitStatus, Stdio},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+/// How long a worker waits on the queue before looking at its stop flag.
+const TAKE_TIMEOUT: Duration = Duration::from_secs(1);
+/// Time between attempts to reach a queue server that went away.
+const RECONNECT: Duration = Duration::from_secs(5);
+/// Time between progress reports to the coordinator.
+const REPORT_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Options of `mnemonic_validator worker`.
+#[derive(Args, Debug)]
+pub struct WorkerArgs {
+    /// Queue to take work from: redis://[user:password@]host[:port]/LIST (password also from REDIS_PASSWORD), or an SQS queue URL (credentials from AWS_ACCESS_KEY_ID and AWS_SECRET_ACCESS_KEY).
+    #[clap(long, value_parser = queue::parse_url, required_unless_present = "coordinator")]
+    queue: Option<QueueUrl>,
+
+    /// Take shards from a `mnemonic_validator coordinator` at http://host[:port] instead of a queue.
+    #[clap(long, value_name = "URL", value_parser = http::parse_endpoint, conflicts_with = "queue")]
+    coordinator: Option<Endpoint>,
+
+    /// Token the coordinator expects (default: MV_COORDINATOR_TOKEN).
+    #[clap(long, value_name = "TOKEN", requires = "coordinator")]
+    token: Option<String>,
+
+    /// Name of this worker on the queue; give it the same name when it starts again, so it puts back what it had taken (default: host name and process ID).
+    #[clap(long, value_name = "NAME")]
+    worker_id: Option<String>,
+
+    /// Directory for the checkpoints and logs of the files this worker validates.
+    #[clap(long, value_name = "DIR", value_parser = paths::parser(), default_value = "worker")]
+    work_dir: PathBuf,
+
+    /// Exit once the queue is empty, or the coordinator has nothing left to hand out, instead of waiting for more.
+    #[clap(long)]
+    once: bool,
+
+    /// Options for the run of each file, e.g. `-- -o /cases/valid.txt --mask`; the input and checkpoint come from the worker.
+    #[clap(last = true)]
+    run_args: Vec<String>,
+}
+
+/// A message: `{"path": "/cases/7/dump.txt"}`, `{"lines": ["...", ...]}`,
+/// or just a path. `id` names its checkpoint and log, and its result.
+#[derive(Deserialize, Default)]
+struct Message {
+    id: Option<String>,
+    path: Option<PathBuf>,
+    lines: Option<Vec<String>>,
+}
+
+impl Message {
+    fn parse(raw: &[u8]) -> Result<Message, String> {
+        if raw.first() == Some(&b'{') {
+            return serde_json::from_slice(raw).map_err(|e| format!("unreadable message: {}", e));
+        }
+        match std::str::from_utf8(raw) {
+            Ok(path) if !path.trim().is_empty() => Ok(Message {
+                path: Some(PathBuf::from(path.trim())),
+                ..Message::default()
+            }),
+            _ => Err("a message is a path or a JSON object".to_string()),
+        }
+    }
+}
+
+/// Pushed onto `<list>:results` for every message taken.
+#[derive(Serialize)]
+struct Outcome {
+    id: String,
+    worker: String,
+    /// `done`, `failed` or `invalid`.
+    status: &'static str,
+    path: Option<PathBuf>,
+    exit_code: Option<i32>,
+    log: Option<PathBuf>,
+    /// Lines in the batch, and the 0-based positions of the valid ones.
+    lines: Option<usize>,
+    hits: Option<Vec<usize>>,
+    error: Option<String>,
+    finished_at: String,
+}
+
+/// The message's own ID, or one made from its contents.
+fn message_id(raw: &[u8], message: Option<&Message>) -> String {
+    match message.and_then(|message| message.id.clone()) {
+        Some(id) => id.replace(['/', '\\'], "_"),
+        None => digest::to_hex(&Sha256::digest(raw)[..8]),
+    }
+}
+
+pub fn run(args: &WorkerArgs) -> Result<(), Box<dyn std::error::Error>> {
+    // Options that wouldn't make a run of one file fail here, not on every
+    // message
+    match Cli::try_parse_from(["mnemonic_validator".to_string()].into_iter().chain(args.run_args.clone())) {
+        Ok(cli) if cli.command.is_some() || cli.job.is_some() => {
+            return Err("the options after -- are those of a run, without a subcommand or --job".into())
+        }
+        Ok(cli) if cli.listen_tcp.is_some() || cli.listen_unix.is_some() || cli.follow => {
+            return Err("the runs of a worker must finish; drop --listen-tcp, --listen-unix and --follow".into())
+        }
+        Ok(cli) if args.coordinator.is_some() && (cli.skip > 0 || cli.limit.is_some() || cli.heartbeat.is_some()) => {
+            return Err("shards come with their own --skip, --limit and --heartbeat; drop those".into())
+        }
+        Ok(_) => {}
+        Err(e) => return Err(format!("options after --: {}", e.to_string().lines().next().unwrap_or_default()).into()),
+    }
+    for dir in ["checkpoints", "logs", "heartbeats"] {
+        fs::create_dir_all(args.work_dir.join(dir))?;
+    }
+    let worker = args
+        .worker_id
+        .clone()
+        .unwrap_or_else(|| format!("{}-{}", audit::host(), std::process::id()));
+    let exe = std::env::current_exe()?;
+
+    // A file's run gets the same signal and saves its checkpoint; the
+    // worker puts its message back, and stops
+    let stop = Arc::new(AtomicBool::new(false));
+    let stop_flag = stop.clone();
+    console::on_exit(move |_| stop_flag.store(true, Ordering::SeqCst))?;
+
+    if let Some(coordinator) = &args.coordinator {
+        let token = args.token.clone().or_else(|| std::env::var("MV_COORDINATOR_TOKEN").ok());
+        let coordinator = Coordinator {
+            endpoint: coordinator,
+            token: token.as_deref(),
+            worker: &worker,
+        };
+        println!("Worker {} taking shards from {}", worker, coordinator.endpoint);
+        while !stop.load(Ordering::SeqCst) {
+            match take_shards(args, &coordinator, &exe, &stop) {
+                Ok(()) => break,
+                Err(e) => {
+                    eprintln!("Warning: lost the coordinator ({}); trying again in {}s", e, RECONNECT.as_secs());
+                    pause(RECONNECT, &stop);
+                }
+            }
+        }
+        return Ok(());
+    }
+    let queue = args.queue.as_ref().expect("--queue or --coordinator");
+    println!("Worker {} taking work from {}", worker, queue);
+    while !stop.load(Ordering::SeqCst) {
+        match serve(args, queue, &worker, &exe, &stop) {
+            Ok(()) => break,
+            Err(e) => {
+                // What it had taken is put back once it's reconnected
+                eprintln!("Warning: lost the queue ({}); trying again in {}s", e, RECONNECT.as_secs());
+                pause(RECONNECT, &stop);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Sleeps for `duration`, or until stopped.
+fn pause(duration: Duration, stop: &AtomicBool) {
+    let start = Instant::now();
+    while start.elapsed() < duration && !stop.load(Ordering::SeqCst) {
+        thread::sleep(Duration::from_millis(100));
+    }
+}
+
+/// Takes messages until stopped, or with --once until the queue is empty.
+fn serve(args: &WorkerArgs, url: &QueueUrl, worker: &str, exe: &Path, stop: &AtomicBool) -> std::io::Result<()> {
+    let mut queue = WorkQueue::open(url, worker)?;
+    let recovered = queue.recover()?;
+    if recovered > 0 {
+        println!("Put back {} message(s) this worker had taken before", recovered);
+    }
+    while !stop.load(Ordering::SeqCst) {
+        let Some(taken) = queue.take(TAKE_TIMEOUT)? else {
+            if args.once {
+                break;
+            }
+            continue;
+        };
+        let message = Message::parse(&taken.body);
+        let id = message_id(&taken.body, message.as_ref().ok());
+        let mut outcome = Outcome {
+            id: id.clone(),
+            worker: worker.to_string(),
+            status: "failed",
+            path: None,
+            exit_code: None,
+            log: None,
+            lines: None,
+            hits: None,
+            error: None,
+            finished_at: String::new(),
+        };
+        match message {
+            Err(e) => {
+                outcome.status = "invalid";
+                outcome.error = Some(e);
+            }
+            Ok(Message { lines: Some(lines), .. }) => {
+                let outcomes = batch::validate_batch(&lines);
+                outcome.status = "done";
+                outcome.lines = Some(lines.len());
+                outcome.hits = Some((0..lines.len()).filter(|&i| outcomes[i].is_valid()).collect());
+            }
+            Ok(Message { path: Some(path), .. }) => {
+                println!("Message {}: validating {}", id, path.display());
+                let checkpoint = args.work_dir.join("checkpoints").join(format!("{}.txt", id));
+                let log = args.work_dir.join("logs").join(format!("{}.log", id));
+                outcome.path = Some(path.clone());
+                outcome.log = Some(log.clone());
+                let status = start_run(exe, args, &path, &checkpoint, &log, &[])
+                    .and_then(|mut run| wait_keeping(&mut run, &mut queue, &taken, &id));
+                match status {
+                    Ok(status) if status.code() == Some(crate::EXIT_RESUMABLE) || stop.load(Ordering::SeqCst) => {
+                        // To be resumed from its checkpoint if it comes back here
+                        println!("Message {}: stopped before the end, put back", id);
+                        queue.release(&taken)?;
+                        if stop.load(Ordering::SeqCst) {
+                            break;
+                        }
+                        continue;
+                    }
+                    Ok(status) => {
+                        outcome.exit_code = status.code();
+                        if status.success() {
+                            outcome.status = "done";
+                        } else {
+                            outcome.error = Some(format!("the run failed, see {}", log.display()));
+                        }
+                    }
+                    Err(e) => outcome.error = Some(format!("failed to start: {}", e)),
+                }
+            }
+            Ok(_) => {
+                outcome.status = "invalid";
+                outcome.error = Some("the message has neither a path nor lines".to_string());
+            }
+        }
+        println!("Message {}: {}", id, outcome.status);
+        outcome.finished_at = chrono::Utc::now().to_rfc3339();
+        queue.ack(&taken, &serde_json::to_vec(&outcome)?)?;
+    }
+    Ok(())
+}
+
+/// Waits for `run`, keeping `taken` from the other workers meanwhile. A
+/// queue that can't be told is only warned about: the run goes on.
+fn wait_keeping(run: &mut Child, queue: &mut WorkQueue, taken: &Taken, id: &str) -> std::io::Result<ExitStatus> {
+    let mut kept = Instant::now();
+    loop {
+        if let Some(status) = run.try_wait()? {
+            return Ok(status);
+        }
+        if kept.elapsed() >= queue::KEEP_INTERVAL {
+            if let Err(e) = queue.keep(taken) {
+                eprintln!("Warning: couldn't keep message {} ({}); another worker may take it", id, e);
+            }
+            kept = Instant::now();
+        }
+        thread::sleep(Duration::from_millis(200));
+    }
+}
+
+/// Starts a run of `path` with the options after `--` and `extra`, its
+/// output going to `log`.
+fn start_run(exe: &Path, args: &WorkerArgs, path: &Path, checkpoint: &Path, log: &Path, extra: &[String]) -> std::io::Result<Child> {
+    let log = File::options().create(true).append(true).open(log)?;
+    let stderr = log.try_clone()?;
+    Command::new(exe)
+        .args(&args.run_args)
+        .arg("-i")
+        .arg(path)
+        .arg("--checkpoint")
+        .arg(checkpoint)
+        .args(extra)
+        .stdin(Stdio::null())
+        .stdout(log)
+        .stderr(stderr)
+        .spawn()
+}
+
+/// The coordinator, as this worker talks to it.
+struct Coordinator<'a> {
+    endpoint: &'a Endpoint,
+    token: Option<&'a str>,
+    worker: &'a str,
+}
+
+impl Coordinator<'_> {
+    fn call(&self, path: &str, mut body: serde_json::Value) -> std::io::Result<serde_json::Value> {
+        body["worker"] = self.worker.into();
+        let (status, reply) = http::post(self.endpoint, path, self.token, &serde_json::to_vec(&body)?)?;
+        let reply: serde_json::Value = serde_json::from_slice(&reply).unwrap_or_default();
+        match status {
+            200 => Ok(reply),
+            _ => Err(std::io::Error::other(format!(
+                "the coordinator says {}: {}",
+                status,
+                reply["error"].as_str().unwrap_or("no reason given")
+            ))),
+        }
+    }
+
+    /// Reports the end of a run of `shard`, trying again until the
+    /// coordinator takes it or the worker is stopped: one reported lost
+    /// would be run all over again.
+    fn finish(&self, shard: usize, report: serde_json::Value, stop: &AtomicBool) {
+        let mut report = report;
+        report["shard"] = shard.into();
+        loop {
+            match self.call("/api/finish", report.clone()) {
+                Ok(_) => return,
+                Err(e) if stop.load(Ordering::SeqCst) => {
+                    eprintln!("Warning: couldn't report the end of shard {} ({})", shard, e);
+                    return;
+                }
+                Err(e) => {
+                    eprintln!("Warning: couldn't report the end of shard {} ({}); trying again", shard, e);
+                    pause(RECONNECT, stop);
+                }
+            }
+        }
+    }
+}
+
+/// The line, hits and status in a run's heartbeat file.
+fn read_heartbeat(path: &Path) -> Option<(usize, usize, String)> {
+    let status: serde_json::Value = serde_json::from_slice(&fs::read(path).ok()?).ok()?;
+    Some((
+        status["line"].as_u64()? as usize,
+        status["valid"].as_u64()? as usize,
+        status["status"].as_str()?.to_string(),
+    ))
+}
+
+/// Takes shards until stopped, or until the coordinator has none left.
+fn take_shards(args: &WorkerArgs, coordinator: &Coordinator, exe: &Path, stop: &AtomicBool) -> std::io::Result<()> {
+    coordinator.call("/api/register", serde_json::json!({}))?;
+    while !stop.load(Ordering::SeqCst) {
+        let reply = coordinator.call("/api/next", serde_json::json!({}))?;
+        if reply["finished"].as_bool() == Some(true) {
+            println!("The coordinator has no shards left");
+            break;
+        }
+        let Some(shard) = reply.get("shard").and_then(|shard| serde_json::from_value::<Shard>(shard.clone()).ok()) else {
+            if args.once {
+                break;
+            }
+            pause(Duration::from_secs(reply["wait"].as_u64().unwrap_or(10)), stop);
+            continue;
+        };
+        run_shard(args, coordinator, exe, &shard, stop)?;
+    }
+    Ok(())
+}
+
+/// Runs `shard`, reporting its progress on the way and how it ended.
+fn run_shard(args: &WorkerArgs, coordinator: &Coordinator, exe: &Path, shard: &Shard, stop: &AtomicBool) -> std::io::Result<()> {
+    // Named after where it starts, since a shard given back part-way
+    // starts further on
+    let name = format!("shard-{}-{}", shard.id, shard.skip);
+    let checkpoint = args.work_dir.join("checkpoints").join(format!("{}.txt", name));
+    let log = args.work_dir.join("logs").join(format!("{}.log", name));
+    let heartbeat = args.work_dir.join("heartbeats").join(format!("{}.json", name));
+    let _ = fs::remove_file(&heartbeat);
+    println!("Shard {}: validating {} from line {}", shard.id, shard.path.display(), shard.skip + 1);
+    let mut extra = vec!["--skip".to_string(), shard.skip.to_string()];
+    if let Some(limit) = shard.limit {
+        extra.extend(["--limit".to_string(), limit.to_string()]);
+    }
+    extra.extend([
+        "--heartbeat".to_string(),
+        heartbeat.display().to_string(),
+        "--heartbeat-interval".to_string(),
+        REPORT_INTERVAL.as_secs().to_string(),
+    ]);
+    let mut run = match start_run(exe, args, &shard.path, &checkpoint, &log, &extra) {
+        Ok(run) => run,
+        Err(e) => {
+            let report = serde_json::json!({ "status": "failed", "error": format!("failed to start: {}", e) });
+            coordinator.finish(shard.id, report, stop);
+            return Ok(());
+        }
+    };
+    let mut last_report = Instant::now();
+    let status = loop {
+        if let Some(status) = run.try_wait()? {
+            break status;
+        }
+        thread::sleep(Duration::from_millis(200));
+        if last_report.elapsed() < REPORT_INTERVAL {
+            continue;
+        }
+        last_report = Instant::now();
+        let Some((line, valid, _)) = read_heartbeat(&heartbeat) else {
+            continue;
+        };
+        let progress = serde_json::json!({ "shard": shard.id, "line": line, "valid": valid });
+        match coordinator.call("/api/progress", progress) {
+            Ok(reply) if reply["ok"].as_bool() == Some(false) => {
+                // Given to another worker while this one was out of touch
+                println!("Shard {}: taken back by the coordinator, stopping its run", shard.id);
+                run.kill()?;
+                run.wait()?;
+                return Ok(());
+            }
+            Ok(_) => {}
+            Err(e) => eprintln!("Warning: couldn't report progress on shard {} ({})", shard.id, e),
+        }
+    };
+    let (line, valid, ended) = read_heartbeat(&heartbeat).unwrap_or((shard.skip, 0, String::new()));
+    let report = match ended.as_str() {
+        "done" if status.success() => serde_json::json!({ "status": "done", "line": line, "valid": valid }),
+        "stopped" | "interrupted" if status.code() == Some(crate::EXIT_RESUMABLE) => {
+            serde_json::json!({ "status": "stopped", "line": line, "valid": valid })
+        }
+        // Killed before it could say where it stopped: it's carried on from
+        // its checkpoint if it comes back here, from its start elsewhere
+        _ if status.code() == Some(crate::EXIT_RESUMABLE) => serde_json::json!({ "status": "stopped", "line": shard.skip }),
+        _ => serde_json::json!({
+            "status": "failed",
+            "error": format!("the run failed (exit status {}), see {} on {}", status, log.display(), coordinator.worker),
+        }),
+    };
+    println!("Shard {}: {}", shard.id, report["status"].as_str().unwrap_or_default());
+    coordinator.finish(shard.id, report, stop);
+    Ok(())
+}
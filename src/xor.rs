@@ -0,0 +1,98 @@
+//! `mnemonic_validator xor`: combining Seed XOR shares found in a dump, and
+//! splitting a phrase into shares.
+//!
+//! Phrases are read from a file or standard input rather than taken as
+//! arguments, which would leave them in the shell history and the process
+//! list.
+
+use bip39::Mnemonic;
+use clap::{Args, Subcommand};
+use mnemonic_validator::{
+    lines, paths,
+    secret::Zeroizing,
+    seedxor, typography,
+};
+use std::{
+    fs,
+    io::{self, Read},
+    path::PathBuf,
+};
+
+#[derive(Args, Debug)]
+pub struct XorArgs {
+    #[clap(subcommand)]
+    command: XorCommand,
+}
+
+#[derive(Subcommand, Debug)]
+enum XorCommand {
+    /// Print the phrase that 2 to 4 shares combine into.
+    Combine {
+        /// File with the shares, one per line, e.g. an output file; standard input if left out.
+        #[clap(value_parser = paths::parser())]
+        file: Option<PathBuf>,
+
+        /// Combine only these lines of the file, counting from 1, e.g. --lines 3,8,12.
+        #[clap(long, value_name = "N,N,...", value_delimiter = ',')]
+        lines: Vec<usize>,
+    },
+    /// Split the phrase in a file (or read from standard input) into shares, printed one per line.
+    Split {
+        /// Number of shares, 2 to 4; all of them are needed to get the phrase back.
+        #[clap(long, value_name = "N", default_value_t = 3, value_parser = clap::value_parser!(u32).range(2..=4))]
+        parts: u32,
+
+        #[clap(value_parser = paths::parser())]
+        file: Option<PathBuf>,
+    },
+}
+
+/// The phrases on the given lines (every non-blank one if `wanted` is
+/// empty) of `file`, or of standard input.
+fn read_phrases(file: Option<&PathBuf>, wanted: &[usize]) -> Result<Vec<Mnemonic>, Box<dyn std::error::Error>> {
+    let mut text = Zeroizing::new(Vec::new());
+    match file {
+        Some(path) => fs::File::open(path)?.read_to_end(&mut text)?,
+        None => io::stdin().read_to_end(&mut text)?,
+    };
+
+    let mut phrases = Vec::new();
+    let mut last = 0;
+    for (n, line) in (1..).zip(lines::lines(&text)) {
+        last = n;
+        if !wanted.is_empty() && !wanted.contains(&n) {
+            continue;
+        }
+        let line = std::str::from_utf8(line).unwrap_or_default().trim();
+        if line.is_empty() && wanted.is_empty() {
+            continue;
+        }
+        let cleaned = typography::clean(line);
+        let line = cleaned.as_deref().map_or(line, String::as_str);
+        let phrase = Mnemonic::parse_normalized(line).map_err(|e| format!("line {} isn't a valid mnemonic: {}", n, e))?;
+        phrases.push(phrase);
+    }
+    if let Some(missing) = wanted.iter().find(|&&n| n == 0 || n > last) {
+        return Err(format!("there is no line {}", missing).into());
+    }
+    Ok(phrases)
+}
+
+pub fn run(args: &XorArgs) -> Result<(), Box<dyn std::error::Error>> {
+    match &args.command {
+        XorCommand::Combine { file, lines } => {
+            let shares = read_phrases(file.as_ref(), lines)?;
+            println!("{}", seedxor::combine(&shares)?);
+        }
+        XorCommand::Split { parts, file } => {
+            let phrases = read_phrases(file.as_ref(), &[])?;
+            let [phrase] = &phrases[..] else {
+                return Err(format!("expected one phrase to split, found {}", phrases.len()).into());
+            };
+            for share in seedxor::split(phrase, *parts as usize)? {
+                println!("{}", share);
+            }
+        }
+    }
+    Ok(())
+}
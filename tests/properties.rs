@@ -0,0 +1,122 @@
+//! Properties of parsing, repair and conversion on arbitrary entropy, with
+//! cases shrunk to the smallest failing one by `proptest`.
+
+use bip39::{Language, Mnemonic};
+use mnemonic_validator::{batch, diagnostics, prefilter, seedqr, seedxor};
+use proptest::prelude::*;
+use sha2::{Digest, Sha256};
+
+/// Entropy of a 12, 15, 18, 21 or 24-word phrase.
+fn entropy() -> impl Strategy<Value = Vec<u8>> {
+    prop::sample::select(vec![16usize, 20, 24, 28, 32]).prop_flat_map(|len| prop::collection::vec(any::<u8>(), len))
+}
+
+fn mnemonic() -> impl Strategy<Value = Mnemonic> {
+    entropy().prop_map(|entropy| Mnemonic::from_entropy(&entropy).unwrap())
+}
+
+/// Whether the checksum of the phrase made of English word `indices` is
+/// right, worked out from the BIP39 definition rather than by the parser.
+fn checksum_matches(indices: &[usize]) -> bool {
+    let bits: Vec<bool> = indices.iter().flat_map(|&index| (0..11).rev().map(move |bit| index >> bit & 1 == 1)).collect();
+    let checksum_bits = bits.len() / 33;
+    let (entropy_bits, checksum) = bits.split_at(bits.len() - checksum_bits);
+    let entropy: Vec<u8> = entropy_bits
+        .chunks(8)
+        .map(|byte| byte.iter().fold(0, |acc, &bit| acc << 1 | bit as u8))
+        .collect();
+    let hash = Sha256::digest(&entropy);
+    checksum.iter().enumerate().all(|(n, &bit)| (hash[n / 8] >> (7 - n % 8) & 1 == 1) == bit)
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(512))]
+
+    /// A phrase parses back to the mnemonic it was formatted from, and gives
+    /// back its entropy.
+    #[test]
+    fn parse_round_trips(entropy in entropy()) {
+        let mnemonic = Mnemonic::from_entropy(&entropy).unwrap();
+        let phrase = mnemonic.to_string();
+        let parsed = prefilter::parse(&phrase);
+        prop_assert_eq!(parsed.as_ref(), Some(&mnemonic));
+        prop_assert_eq!(parsed.unwrap().to_entropy(), entropy);
+    }
+
+    /// Spaces and tabs around and between the words don't change what a
+    /// phrase parses to.
+    #[test]
+    fn spacing_is_ignored(mnemonic in mnemonic(), separators in prop::collection::vec("[ \t]{1,3}", 25)) {
+        let mut phrase = String::new();
+        for (word, separator) in mnemonic.words().zip(&separators) {
+            phrase.push_str(separator);
+            phrase.push_str(word);
+        }
+        phrase.push_str(&separators[24]);
+        prop_assert!(batch::validate(&phrase).is_valid(), "{:?} is rejected", phrase);
+    }
+
+    /// Replacing one word with another wordlist word gives a phrase that's
+    /// valid exactly when its checksum happens to be right (1 in 16 for 12
+    /// words), and replacing it with anything else is always rejected.
+    #[test]
+    fn one_word_mutations(mnemonic in mnemonic(), at in any::<prop::sample::Index>(), index in 0usize..2048, suffix in "[a-z]") {
+        let words: Vec<&str> = mnemonic.words().collect();
+        let at = at.index(words.len());
+        let english = Language::English.word_list();
+        prop_assume!(english[index] != words[at]);
+
+        let mut indices: Vec<usize> = mnemonic.word_indices().collect();
+        prop_assert!(checksum_matches(&indices));
+        indices[at] = index;
+        let mut mutated = words.clone();
+        mutated[at] = english[index];
+        prop_assert_eq!(prefilter::parse(&mutated.join(" ")).is_some(), checksum_matches(&indices));
+
+        let unknown = format!("{}{}", words[at], suffix);
+        prop_assume!(english.binary_search(&unknown.as_str()).is_err());
+        mutated[at] = &unknown;
+        prop_assert!(!batch::validate(&mutated.join(" ")).is_valid(), "'{}' in place of '{}' is accepted", unknown, words[at]);
+    }
+
+    /// The same word indices in any wordlist keep the entropy.
+    #[test]
+    fn translation_keeps_entropy(mnemonic in mnemonic(), language in prop::sample::select(Language::ALL.to_vec())) {
+        let words = language.word_list();
+        let translated: Vec<&str> = mnemonic.word_indices().map(|index| words[index]).collect();
+        let translated = Mnemonic::parse_in(language, translated.join(" ")).unwrap();
+        // Not `to_entropy`, which guesses the language again and gives up on
+        // a phrase whose words are all in both Chinese lists
+        prop_assert!(translated.word_indices().eq(mnemonic.word_indices()));
+    }
+
+    /// Repair leaves a valid phrase as it is.
+    #[test]
+    fn repair_keeps_valid_phrases(mnemonic in mnemonic()) {
+        let phrase = mnemonic.to_string();
+        let repaired = diagnostics::repair(&phrase);
+        prop_assert_eq!(repaired.len(), 1);
+        prop_assert_eq!(repaired[0].as_str(), phrase.as_str());
+    }
+
+    /// A 12 or 24-word phrase comes back from its SeedQR and CompactSeedQR.
+    #[test]
+    fn seedqr_round_trips(entropy in prop_oneof![prop::collection::vec(any::<u8>(), 16), prop::collection::vec(any::<u8>(), 32)]) {
+        let mnemonic = Mnemonic::from_entropy(&entropy).unwrap();
+        let phrase = mnemonic.to_string();
+        let digits: String = mnemonic.word_indices().map(|index| format!("{:04}", index)).collect();
+        let decoded = seedqr::decode_standard(&digits);
+        prop_assert_eq!(decoded.as_deref().map(String::as_str), Some(phrase.as_str()));
+        let hex: String = entropy.iter().map(|byte| format!("{:02x}", byte)).collect();
+        let decoded = seedqr::decode_compact(&hex);
+        prop_assert_eq!(decoded.as_deref().map(String::as_str), Some(phrase.as_str()));
+    }
+
+    /// Seed XOR shares combine back to the phrase they were split from.
+    #[test]
+    fn seedxor_round_trips(mnemonic in mnemonic(), parts in 2usize..5) {
+        let shares = seedxor::split(&mnemonic, parts).unwrap();
+        prop_assert_eq!(shares.len(), parts);
+        prop_assert_eq!(seedxor::combine(&shares), Ok(mnemonic));
+    }
+}